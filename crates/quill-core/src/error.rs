@@ -0,0 +1,75 @@
+use thiserror::Error;
+
+/// Errors returned by quill-core, grouped by what a caller can do about them.
+#[derive(Debug, Error)]
+pub enum QuillError {
+    /// The backend can't be reached or opened right now; another backend or
+    /// a retry may work.
+    #[error("{0}")]
+    StorageUnavailable(String),
+    /// This build can't handle it: a cargo feature is off, or the data was
+    /// written by a newer quill.
+    #[error("{0}")]
+    Unsupported(String),
+    /// No git context could be determined for the directory.
+    #[error("{0}")]
+    ContextNotFound(String),
+    /// Someone else changed the data first; reload and try again.
+    #[error("{0}")]
+    Conflict(String),
+    /// Input or configuration the API rejects.
+    #[error("{0}")]
+    Validation(String),
+    /// The backend refuses changes, such as read-only storage or a wrong token.
+    #[error("{0}")]
+    PermissionDenied(String),
+    /// Stored data that can't be read back.
+    #[error("{0}")]
+    Corrupt(String),
+    #[error(transparent)]
+    Git(#[from] git2::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T, E = QuillError> = std::result::Result<T, E>;
+
+impl From<serde_json::Error> for QuillError {
+    fn from(error: serde_json::Error) -> Self {
+        QuillError::Corrupt(error.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for QuillError {
+    fn from(error: toml::ser::Error) -> Self {
+        QuillError::Validation(error.to_string())
+    }
+}
+
+#[cfg(feature = "mongo")]
+impl From<mongodb::error::Error> for QuillError {
+    fn from(error: mongodb::error::Error) -> Self {
+        QuillError::StorageUnavailable(error.to_string())
+    }
+}
+
+#[cfg(feature = "mongo")]
+impl From<bson::de::Error> for QuillError {
+    fn from(error: bson::de::Error) -> Self {
+        QuillError::Corrupt(error.to_string())
+    }
+}
+
+#[cfg(feature = "mongo")]
+impl From<bson::ser::Error> for QuillError {
+    fn from(error: bson::ser::Error) -> Self {
+        QuillError::Validation(error.to_string())
+    }
+}
+
+#[cfg(feature = "remote")]
+impl From<reqwest::Error> for QuillError {
+    fn from(error: reqwest::Error) -> Self {
+        QuillError::StorageUnavailable(error.to_string())
+    }
+}
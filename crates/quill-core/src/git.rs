@@ -0,0 +1,715 @@
+use crate::{QuillError, Result};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Code hosts whose remote URL layout quill knows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Forge {
+    #[default]
+    GitHub,
+    GitLab,
+    Bitbucket,
+    Codeberg,
+}
+
+impl Forge {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Forge::GitHub => "GitHub",
+            Forge::GitLab => "GitLab",
+            Forge::Bitbucket => "Bitbucket",
+            Forge::Codeberg => "Codeberg",
+        }
+    }
+
+    /// The forge behind a public hostname.
+    fn for_host(host: &str) -> Option<Forge> {
+        match host.to_lowercase().as_str() {
+            "github.com" | "ssh.github.com" => Some(Forge::GitHub),
+            "gitlab.com" => Some(Forge::GitLab),
+            "bitbucket.org" => Some(Forge::Bitbucket),
+            "codeberg.org" => Some(Forge::Codeberg),
+            _ => None,
+        }
+    }
+}
+
+/// A remote URL split into where it is hosted and whose repository it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteUrl {
+    pub host: String,
+    /// `None` for hosts that are neither well known nor configured.
+    pub forge: Option<Forge>,
+    /// The owner: a GitHub org or user, a Bitbucket workspace or project,
+    /// or a GitLab group path including subgroups (`group/sub`).
+    pub namespace: String,
+    pub repo: String,
+}
+
+impl RemoteUrl {
+    /// Reads scp-style (`git@host:org/repo.git`), `ssh://`, `git://` and
+    /// HTTP(S) remote URLs. `hosts` names self-hosted forges by hostname.
+    pub fn parse(url: &str, hosts: &BTreeMap<String, Forge>) -> Option<Self> {
+        let url = url.trim();
+        let (authority, path) = match url.split_once("://") {
+            Some((_, rest)) => rest.split_once('/')?,
+            None => url.split_once(':').filter(|(authority, _)| !authority.contains('/'))?,
+        };
+        // Drop the user (`git@`) and port (`:7999`)
+        let host = authority.rsplit('@').next()?.split(':').next()?.to_lowercase();
+        let path = path.trim_end_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+
+        let forge = hosts.get(&host).copied().or_else(|| Forge::for_host(&host));
+        let mut segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        // Bitbucket Server serves clones from `/scm/<project>/<repo>`
+        if forge == Some(Forge::Bitbucket) && segments.first() == Some(&"scm") {
+            segments.remove(0);
+        }
+        if segments.len() < 2 {
+            return None;
+        }
+
+        let repo = segments.pop()?.to_string();
+        let namespace = match forge {
+            Some(Forge::GitLab) => segments.join("/"),
+            _ => segments[0].to_string(),
+        };
+        Some(RemoteUrl { host, forge, namespace, repo })
+    }
+
+    /// `namespace/repo`, the project path forge APIs take.
+    pub fn path(&self) -> String {
+        format!("{}/{}", self.namespace, self.repo)
+    }
+}
+
+/// The current directory, or `.` when it can't be read (e.g. it was deleted),
+/// so detection still ends in a usable context.
+pub fn current_dir() -> PathBuf {
+    std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
+}
+
+/// The remote of the repository containing the current directory.
+pub fn origin_remote(hosts: &BTreeMap<String, Forge>) -> Option<RemoteUrl> {
+    let url = match Repository::discover(".") {
+        Ok(repo) => GitContext::remote_url(&repo)?,
+        Err(_) => fallback::remote_url(&fallback::find(&current_dir())?.1)?,
+    };
+    RemoteUrl::parse(&url, hosts)
+}
+
+/// The org earlier versions gave the repository containing `dir`, when it
+/// differs from what `RemoteUrl` reads now: they took the first path segment
+/// of URLs (so only the top GitLab group) and put scp-style URLs off
+/// github.com under `local`.
+pub fn legacy_org(dir: &Path, hosts: &BTreeMap<String, Forge>) -> Option<String> {
+    let url = match Repository::discover(dir) {
+        Ok(repo) => GitContext::remote_url(&repo),
+        Err(_) => fallback::remote_url(&fallback::find(dir)?.1),
+    }?;
+    let current = RemoteUrl::parse(&url, hosts).map(|remote| remote.namespace).unwrap_or_else(|| "local".to_string());
+    Some(legacy_org_in(&url)).filter(|legacy| *legacy != current)
+}
+
+fn legacy_org_in(url: &str) -> String {
+    let path = match url.strip_prefix("git@github.com:") {
+        Some(path) => Some(path),
+        None => url.split_once("://").and_then(|(_, rest)| rest.split_once('/')).map(|(_, path)| path),
+    };
+    path.and_then(|path| path.split('/').next()).map(str::to_string).unwrap_or_else(|| "local".to_string())
+}
+
+/// `user.email` from the git config of the repository containing `dir`, or
+/// the global one outside a repository.
+pub fn user_email(dir: &Path) -> Option<String> {
+    let config = match Repository::discover(dir) {
+        Ok(repo) => repo.config().ok()?,
+        Err(_) => git2::Config::open_default().ok()?,
+    };
+    config.get_string("user.email").ok().map(|email| email.trim().to_string()).filter(|email| !email.is_empty())
+}
+
+/// Reads a repository's `.git` files directly, for repositories git2 can't
+/// open. Only what a context needs: the working directory, `HEAD` and the
+/// remote URLs in `config`.
+mod fallback {
+    use super::{Forge, GitContext, RemoteUrl};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// The working directory and git directory of the repository containing
+    /// `dir`, following `gitdir:` files of worktrees and submodules.
+    pub fn find(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+        for workdir in dir.ancestors() {
+            let dot_git = workdir.join(".git");
+            if dot_git.is_dir() {
+                return Some((workdir.to_path_buf(), dot_git));
+            }
+            if let Ok(content) = fs::read_to_string(&dot_git) {
+                let git_dir = content.trim().strip_prefix("gitdir:")?.trim();
+                return Some((workdir.to_path_buf(), workdir.join(git_dir)));
+            }
+        }
+        None
+    }
+
+    /// The branch `HEAD` points at, or `detached-<sha>`.
+    fn branch(git_dir: &Path) -> Option<String> {
+        let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        match head.strip_prefix("ref: ") {
+            Some(reference) => Some(reference.strip_prefix("refs/heads/").unwrap_or(reference).to_string()),
+            None => head.get(..8).map(|sha| format!("detached-{}", sha)),
+        }
+    }
+
+    /// The URL of `origin`, or of the first remote, from the config file.
+    /// Worktrees keep their config in the main repository (`commondir`).
+    pub fn remote_url(git_dir: &Path) -> Option<String> {
+        let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common) => git_dir.join(common.trim()),
+            Err(_) => git_dir.to_path_buf(),
+        };
+        let config = fs::read_to_string(common_dir.join("config")).ok()?;
+        remote_url_in(&config)
+    }
+
+    fn remote_url_in(config: &str) -> Option<String> {
+        let mut remote = None;
+        let mut urls: Vec<(String, String)> = Vec::new();
+        for line in config.lines().map(str::trim) {
+            if line.starts_with('[') {
+                remote = line
+                    .strip_prefix("[remote \"")
+                    .and_then(|rest| rest.strip_suffix("\"]"))
+                    .map(str::to_string);
+            } else if let (Some(name), Some((key, value))) = (&remote, line.split_once('=')) {
+                if key.trim() == "url" {
+                    urls.push((name.clone(), value.trim().trim_matches('"').to_string()));
+                }
+            }
+        }
+        let origin = urls.iter().position(|(name, _)| name == "origin").unwrap_or(0);
+        urls.into_iter().nth(origin).map(|(_, url)| url)
+    }
+
+    pub fn context(dir: &Path, hosts: &BTreeMap<String, Forge>) -> Option<GitContext> {
+        let (workdir, git_dir) = find(dir)?;
+        let org = remote_url(&git_dir)
+            .and_then(|url| RemoteUrl::parse(&url, hosts))
+            .map(|remote| remote.namespace)
+            .unwrap_or_else(|| "local".to_string());
+        Some(GitContext {
+            org,
+            repo: workdir.file_name()?.to_str()?.to_string(),
+            branch: branch(&git_dir).unwrap_or_else(|| "main".to_string()),
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_remote_url_prefers_origin() {
+            let config = "[core]\n\tbare = false\n[remote \"upstream\"]\n\turl = git@github.com:up/app.git\n[remote \"origin\"]\n\turl = git@github.com:me/app.git\n";
+            assert_eq!(remote_url_in(config).as_deref(), Some("git@github.com:me/app.git"));
+            assert_eq!(remote_url_in("[remote \"fork\"]\nurl = https://x.org/a/b").as_deref(), Some("https://x.org/a/b"));
+            assert_eq!(remote_url_in("[core]\n"), None);
+        }
+    }
+}
+
+/// A detected context and, when git2 failed on the repository, why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub context: GitContext,
+    /// git2's error when the context was read from `.git` as plain files.
+    pub degraded: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GitContext {
+    pub org: String,
+    pub repo: String,
+    pub branch: String,
+}
+
+impl GitContext {
+    pub fn from_current_dir() -> Result<Self> {
+        Self::from_current_dir_with(&BTreeMap::new())
+    }
+
+    /// Like `from_current_dir`, with `hosts` naming self-hosted forges by
+    /// hostname so their remote URLs are read the right way.
+    pub fn from_current_dir_with(hosts: &BTreeMap<String, Forge>) -> Result<Self> {
+        Self::from_dir_with(&current_dir(), hosts)
+    }
+
+    /// The context of the repository containing `dir`, or a `local` one
+    /// named after `dir` outside a repository.
+    pub fn from_dir_with(dir: &Path, hosts: &BTreeMap<String, Forge>) -> Result<Self> {
+        Ok(Self::detect(dir, hosts).context)
+    }
+
+    /// Like `from_dir_with`, but also says when git2 couldn't open the
+    /// repository (unsupported extensions, partial clones, bare repos) and
+    /// the context was read from the files in `.git` instead.
+    pub fn detect(dir: &Path, hosts: &BTreeMap<String, Forge>) -> Detection {
+        let error = match Repository::discover(dir) {
+            Ok(repo) => match repo.workdir() {
+                Some(workdir) => {
+                    if let Ok(repo_name) = Self::extract_repo_name(workdir) {
+                        let org_name = Self::extract_org_name(&repo, hosts).unwrap_or_else(|_| "local".to_string());
+                        let branch_name = Self::get_current_branch(&repo).unwrap_or_else(|_| "main".to_string());
+                        return Detection { context: GitContext { org: org_name, repo: repo_name, branch: branch_name }, degraded: None };
+                    }
+                    "Could not extract repository name".to_string()
+                }
+                None => "Repository has no working directory".to_string(),
+            },
+            Err(e) => e.message().to_string(),
+        };
+
+        if let Some(context) = fallback::context(dir, hosts) {
+            tracing::warn!(%error, dir = %dir.display(), "git2 could not open the repository; read .git directly");
+            return Detection { context, degraded: Some(error) };
+        }
+
+        // Not in a git repository, create a default context
+        let dir_name = dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("quill-tasks")
+            .to_string();
+        Detection {
+            context: GitContext { org: "local".to_string(), repo: dir_name, branch: "default".to_string() },
+            degraded: None,
+        }
+    }
+
+    fn extract_repo_name(workdir: &Path) -> Result<String> {
+        workdir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| QuillError::ContextNotFound("Could not extract repository name".to_string()))
+    }
+
+    fn extract_org_name(repo: &Repository, hosts: &BTreeMap<String, Forge>) -> Result<String> {
+        let remote_url = Self::remote_url(repo).unwrap_or_else(|| "local".to_string());
+        Ok(RemoteUrl::parse(&remote_url, hosts)
+            .map(|remote| remote.namespace)
+            .unwrap_or_else(|| "local".to_string()))
+    }
+
+    /// The URL of `origin`, or of the first remote when there is no origin.
+    fn remote_url(repo: &Repository) -> Option<String> {
+        let config = repo.config().ok()?;
+        config
+            .get_string("remote.origin.url")
+            .or_else(|_| {
+                let remotes = repo.remotes()?;
+                if let Some(remote_name) = remotes.get(0) {
+                    config.get_string(&format!("remote.{}.url", remote_name))
+                } else {
+                    Err(git2::Error::from_str("No remotes found"))
+                }
+            })
+            .ok()
+    }
+
+    fn get_current_branch(repo: &Repository) -> Result<String> {
+        let head = repo.head()?;
+        if let Some(branch_name) = head.shorthand() {
+            Ok(branch_name.to_string())
+        } else {
+            // Handle detached HEAD state
+            let oid = head.target().ok_or_else(|| QuillError::ContextNotFound("HEAD has no target".to_string()))?;
+            Ok(format!("detached-{}", &oid.to_string()[..8]))
+        }
+    }
+
+    pub fn context_key(&self) -> String {
+        format!("{}:{}:{}", self.org, self.repo, self.branch)
+    }
+
+    /// The context for tasks captured outside any repository, the same
+    /// from every directory.
+    pub fn inbox() -> Self {
+        GitContext { org: "inbox".to_string(), repo: "inbox".to_string(), branch: "default".to_string() }
+    }
+
+    pub fn is_inbox(&self) -> bool {
+        *self == Self::inbox()
+    }
+}
+
+/// Re-detects the git context every `interval` on a background task and
+/// sends it whenever it differs from the last one seen, so slow repository
+/// discovery (network filesystems, huge repos) never blocks rendering.
+/// The watcher stops once the receiver is dropped.
+pub fn spawn_context_watcher(
+    initial: GitContext,
+    interval: Duration,
+    hosts: BTreeMap<String, Forge>,
+) -> mpsc::UnboundedReceiver<GitContext> {
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut last = initial;
+        loop {
+            tokio::time::sleep(interval).await;
+            if tx.is_closed() {
+                break;
+            }
+
+            let detected = {
+                let hosts = hosts.clone();
+                tokio::task::spawn_blocking(move || GitContext::from_current_dir_with(&hosts)).await
+            };
+            if let Ok(Ok(context)) = detected {
+                if context != last {
+                    tracing::info!(from = %last.context_key(), to = %context.context_key(), "git context changed");
+                    last = context.clone();
+                    if tx.send(context).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    rx
+}
+
+/// Returns the working directory root of the repository containing the
+/// current directory, if there is one.
+pub fn repo_root() -> Option<PathBuf> {
+    repo_root_of(Path::new("."))
+}
+
+/// Returns the working directory root of the repository containing `dir`.
+pub fn repo_root_of(dir: &Path) -> Option<PathBuf> {
+    let repo = Repository::discover(dir).ok()?;
+    repo.workdir().map(Path::to_path_buf)
+}
+
+/// Why a branch's task list may be left over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchState {
+    /// Neither a local nor a remote branch has the name: deleted, or a
+    /// branch tasks were deferred to that was never created.
+    Missing,
+    /// Every commit of the branch is in the default branch.
+    Merged,
+}
+
+impl BranchState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BranchState::Missing => "no such branch",
+            BranchState::Merged => "merged",
+        }
+    }
+}
+
+/// The branch others are merged into, with its tip: what `origin/HEAD`
+/// points to, or else a local `main` or `master`.
+fn default_branch(repo: &Repository) -> Option<(String, git2::Oid)> {
+    if let Ok(head) = repo.find_reference("refs/remotes/origin/HEAD") {
+        if let Some(target) = head.symbolic_target() {
+            let name = target.trim_start_matches("refs/remotes/origin/").to_string();
+            return repo.refname_to_id(target).ok().map(|oid| (name, oid));
+        }
+    }
+    ["main", "master"].iter().find_map(|name| {
+        let branch = repo.find_branch(name, git2::BranchType::Local).ok()?;
+        branch.get().target().map(|oid| (name.to_string(), oid))
+    })
+}
+
+/// Of `branches` of the repository containing `dir`, the ones that no
+/// longer exist and the ones merged into the default branch. The default
+/// branch itself, and branches still at its tip, are never stale.
+pub fn stale_branches(dir: &Path, branches: &[String]) -> Result<Vec<(String, BranchState)>> {
+    let repo = Repository::discover(dir).map_err(|_| QuillError::ContextNotFound("Not in a git repository".to_string()))?;
+    let default = default_branch(&repo);
+    let remotes = repo.remotes()?;
+    let mut stale = Vec::new();
+    for branch in branches {
+        if default.as_ref().is_some_and(|(name, _)| name == branch) {
+            continue;
+        }
+        let tip = repo
+            .find_branch(branch, git2::BranchType::Local)
+            .ok()
+            .and_then(|b| b.get().target())
+            .or_else(|| {
+                remotes.iter().flatten().find_map(|remote| {
+                    let b = repo.find_branch(&format!("{}/{}", remote, branch), git2::BranchType::Remote).ok()?;
+                    b.get().target()
+                })
+            });
+        match (tip, &default) {
+            (None, _) => stale.push((branch.clone(), BranchState::Missing)),
+            (Some(tip), Some((_, base))) if tip != *base && repo.graph_descendant_of(*base, tip)? => {
+                stale.push((branch.clone(), BranchState::Merged))
+            }
+            _ => {}
+        }
+    }
+    Ok(stale)
+}
+
+/// Whether local `branch` points at the same commit as local `parent`, as a
+/// branch just created from it does.
+pub fn branched_from(dir: &Path, branch: &str, parent: &str) -> bool {
+    let Ok(repo) = Repository::discover(dir) else {
+        return false;
+    };
+    let tip = |name: &str| repo.find_branch(name, git2::BranchType::Local).ok().and_then(|b| b.get().target());
+    tip(branch).is_some_and(|oid| tip(parent) == Some(oid))
+}
+
+/// A commit whose message mentions a task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkedCommit {
+    /// Abbreviated hash.
+    pub id: String,
+    pub summary: String,
+}
+
+/// Commits reachable from HEAD of the repository containing `dir`, newest
+/// first, whose message contains any of `needles`. Only the latest `limit`
+/// commits are looked at.
+pub fn commits_mentioning(dir: &Path, needles: &[String], limit: usize) -> Vec<LinkedCommit> {
+    let Ok(repo) = Repository::discover(dir) else {
+        return Vec::new();
+    };
+    let Ok(mut walk) = repo.revwalk() else {
+        return Vec::new();
+    };
+    if needles.is_empty() || walk.push_head().is_err() {
+        return Vec::new();
+    }
+    walk.take(limit)
+        .filter_map(|oid| repo.find_commit(oid.ok()?).ok())
+        .filter(|commit| commit.message().is_some_and(|message| needles.iter().any(|needle| message.contains(needle.as_str()))))
+        .map(|commit| LinkedCommit {
+            id: commit.id().to_string()[..7].to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+        })
+        .collect()
+}
+
+/// Returns the hooks directory of the repository containing the current
+/// directory, honouring `core.hooksPath` when it is set.
+pub fn hooks_dir() -> Result<PathBuf> {
+    let repo = Repository::discover(".").map_err(|_| QuillError::ContextNotFound("Not in a git repository".to_string()))?;
+
+    if let Ok(hooks_path) = repo.config()?.get_path("core.hooksPath") {
+        if hooks_path.is_absolute() {
+            return Ok(hooks_path);
+        }
+        let workdir = repo.workdir().ok_or_else(|| QuillError::ContextNotFound("Repository has no working directory".to_string()))?;
+        return Ok(workdir.join(hooks_path));
+    }
+
+    Ok(repo.path().join("hooks"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_git_context_creation() {
+        let context = GitContext {
+            org: "testorg".to_string(),
+            repo: "testrepo".to_string(),
+            branch: "main".to_string(),
+        };
+        
+        assert_eq!(context.org, "testorg");
+        assert_eq!(context.repo, "testrepo");
+        assert_eq!(context.branch, "main");
+    }
+
+    #[test]
+    fn test_context_key() {
+        let context = GitContext {
+            org: "myorg".to_string(),
+            repo: "myrepo".to_string(),
+            branch: "feature".to_string(),
+        };
+        
+        assert_eq!(context.context_key(), "myorg:myrepo:feature");
+    }
+
+    #[test]
+    fn test_parse_github_ssh_url() {
+        let url = "git@github.com:octocat/Hello-World.git";
+        let org = RemoteUrl::parse(url, &BTreeMap::new()).map(|remote| remote.namespace);
+        assert_eq!(org, Some("octocat".to_string()));
+    }
+
+    #[test]
+    fn test_parse_github_https_url() {
+        let url = "https://github.com/octocat/Hello-World.git";
+        let org = RemoteUrl::parse(url, &BTreeMap::new()).map(|remote| remote.namespace);
+        assert_eq!(org, Some("octocat".to_string()));
+    }
+
+    #[test]
+    fn test_parse_invalid_url() {
+        let url = "not-a-git-url";
+        let org = RemoteUrl::parse(url, &BTreeMap::new()).map(|remote| remote.namespace);
+        assert_eq!(org, None);
+    }
+
+    #[test]
+    fn test_legacy_org() {
+        assert_eq!(legacy_org_in("git@github.com:octocat/Hello-World.git"), "octocat");
+        assert_eq!(legacy_org_in("https://gitlab.com/acme/platform/api.git"), "acme");
+        assert_eq!(legacy_org_in("ssh://git@bitbucket.acme.com:7999/scm/proj/app.git"), "scm");
+        assert_eq!(legacy_org_in("git@gitlab.com:acme/api.git"), "local");
+    }
+
+    #[test]
+    fn test_parse_remote_urls_by_forge() {
+        let hosts = BTreeMap::from([("git.acme.internal".to_string(), Forge::GitLab)]);
+        let parse = |url: &str| RemoteUrl::parse(url, &hosts).unwrap();
+
+        let gitlab = parse("git@gitlab.com:acme/platform/api.git");
+        assert_eq!((gitlab.forge, gitlab.namespace.as_str(), gitlab.repo.as_str()), (Some(Forge::GitLab), "acme/platform", "api"));
+        assert_eq!(gitlab.path(), "acme/platform/api");
+
+        let bitbucket = parse("https://me@bitbucket.org/acme/widgets.git");
+        assert_eq!((bitbucket.forge, bitbucket.namespace.as_str()), (Some(Forge::Bitbucket), "acme"));
+
+        let codeberg = parse("ssh://git@codeberg.org/acme/widgets");
+        assert_eq!((codeberg.forge, codeberg.namespace.as_str()), (Some(Forge::Codeberg), "acme"));
+
+        let self_hosted = parse("ssh://git@git.acme.internal:2222/infra/tools/deploy.git");
+        assert_eq!((self_hosted.forge, self_hosted.namespace.as_str()), (Some(Forge::GitLab), "infra/tools"));
+
+        let unknown = parse("https://git.example.com/team/app/");
+        assert_eq!((unknown.forge, unknown.namespace.as_str(), unknown.repo.as_str()), (None, "team", "app"));
+    }
+
+    #[test]
+    fn test_git_context_serialization() {
+        let context = GitContext {
+            org: "testorg".to_string(),
+            repo: "testrepo".to_string(),
+            branch: "main".to_string(),
+        };
+        
+        let json = serde_json::to_string(&context).unwrap();
+        let deserialized: GitContext = serde_json::from_str(&json).unwrap();
+        
+        assert_eq!(context, deserialized);
+    }
+
+    #[test]
+    fn test_from_current_dir_fallback() {
+        let context = GitContext::from_current_dir().unwrap();
+        
+        assert!(!context.org.is_empty());
+        assert!(!context.repo.is_empty());
+        assert!(!context.branch.is_empty());
+    }
+
+    #[test]
+    fn test_detect_reads_git_files_when_git2_fails() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let workdir = temp_dir.path().join("exotic");
+        let git_dir = workdir.join(".git");
+        fs::create_dir_all(workdir.join("src")).unwrap();
+        fs::create_dir_all(&git_dir).unwrap();
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/feature/login\n").unwrap();
+        fs::write(
+            git_dir.join("config"),
+            "[core]\n\trepositoryformatversion = 1\n[extensions]\n\tsomethingNew = true\n[remote \"origin\"]\n\turl = git@gitlab.com:acme/web/exotic.git\n",
+        )
+        .unwrap();
+
+        let detection = GitContext::detect(&workdir.join("src"), &BTreeMap::new());
+        assert!(detection.degraded.is_some());
+        assert_eq!(detection.context.context_key(), "acme/web:exotic:feature/login");
+    }
+
+    #[test]
+    fn test_stale_branches_finds_missing_and_merged() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let first = repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+        let first = repo.find_commit(first).unwrap();
+        // `done` gets a commit of its own that main then merges, `open` one it doesn't
+        let done = repo.branch("done", &first, false).unwrap().get().target().unwrap();
+        let done = repo.commit(Some("refs/heads/done"), &signature, &signature, "work", &tree, &[&repo.find_commit(done).unwrap()]).unwrap();
+        let done = repo.find_commit(done).unwrap();
+        repo.branch("open", &first, false).unwrap();
+        repo.commit(Some("refs/heads/open"), &signature, &signature, "more", &tree, &[&first]).unwrap();
+        let merge = repo.commit(Some("HEAD"), &signature, &signature, "merge done", &tree, &[&first, &done]).unwrap();
+        // Just created from main, so nothing to clean up yet
+        repo.branch("fresh", &repo.find_commit(merge).unwrap(), false).unwrap();
+        let main = repo.head().unwrap().shorthand().unwrap().to_string();
+
+        let branches: Vec<String> = [main.as_str(), "fresh", "done", "open", "gone"].iter().map(|b| b.to_string()).collect();
+        let stale = stale_branches(dir.path(), &branches).unwrap();
+        assert_eq!(
+            stale,
+            vec![("done".to_string(), BranchState::Merged), ("gone".to_string(), BranchState::Missing)]
+        );
+    }
+
+    #[test]
+    fn test_commits_mentioning() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let mut parents = Vec::new();
+        for message in ["Add login form (WEB-42)", "Unrelated", "Fix session expiry\n\nq:4f9a0c1e"] {
+            let parent = parents.last().map(|id| repo.find_commit(*id).unwrap());
+            let id = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parent.iter().collect::<Vec<_>>()).unwrap();
+            parents.push(id);
+        }
+
+        let needles = ["WEB-42".to_string(), "q:4f9a0c1e".to_string()];
+        let linked = commits_mentioning(dir.path(), &needles, 100);
+        assert_eq!(linked.iter().map(|c| c.summary.as_str()).collect::<Vec<_>>(), vec!["Fix session expiry", "Add login form (WEB-42)"]);
+        assert_eq!(linked[0].id.len(), 7);
+        assert_eq!(commits_mentioning(dir.path(), &needles, 1).len(), 1);
+        assert!(commits_mentioning(dir.path(), &[], 100).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_context_watcher_reports_changes() {
+        let stale = GitContext {
+            org: "stale".to_string(),
+            repo: "stale".to_string(),
+            branch: "stale".to_string(),
+        };
+
+        let mut updates = spawn_context_watcher(stale, Duration::from_millis(10), BTreeMap::new());
+        let detected = tokio::time::timeout(Duration::from_secs(5), updates.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(detected, GitContext::from_current_dir().unwrap());
+    }
+}
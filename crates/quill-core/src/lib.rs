@@ -0,0 +1,36 @@
+//! The task engine behind quill, usable without the TUI.
+//!
+//! - [`storage`]: the [`storage::Task`] model and the [`storage::TaskStorage`]
+//!   trait, with local-file and MongoDB backends.
+//! - [`git`]: [`git::GitContext`], which turns a working directory into the
+//!   context key tasks are grouped under.
+//! - [`config`]: the `~/.config/quill` configuration and per-project overrides.
+//! - [`templates`], [`pattern`] and [`prompt`]: task templates, context
+//!   patterns and the shell-prompt summary.
+//!
+//! Fallible calls return [`QuillError`], whose variants tell failure classes
+//! apart (an unreachable backend, a conflict, invalid input, ...).
+//!
+//! ```no_run
+//! use quill_core::{config::AppConfig, git::GitContext, storage};
+//!
+//! # async fn run() -> quill_core::Result<()> {
+//! let config = AppConfig::load()?;
+//! let mut storage = storage::open(&config).await?;
+//! let context = GitContext::from_current_dir()?;
+//! storage.add_task(&context.context_key(), "Write docs".into()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod config;
+pub mod error;
+pub mod git;
+pub mod pattern;
+pub mod prompt;
+pub mod secret;
+pub mod shared;
+pub mod storage;
+pub mod templates;
+
+pub use error::{QuillError, Result};
@@ -0,0 +1,53 @@
+/// Matches `text` against a shell-style wildcard pattern where `*` matches
+/// any run of characters (including `/`) and `?` matches a single character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_match() {
+        assert!(glob_match("main", "main"));
+        assert!(!glob_match("main", "mainline"));
+    }
+
+    #[test]
+    fn test_star_matches_across_slashes() {
+        assert!(glob_match("hotfix/*", "hotfix/login"));
+        assert!(glob_match("dependabot/*", "dependabot/cargo/serde-1.0"));
+        assert!(!glob_match("hotfix/*", "feature/hotfix"));
+        assert!(glob_match("*-wip", "feature/x-wip"));
+    }
+
+    #[test]
+    fn test_question_mark_and_multiple_stars() {
+        assert!(glob_match("v?.*", "v1.2.3"));
+        assert!(glob_match("*infra*", "acme:infra-tools:main"));
+        assert!(!glob_match("v?", "v10"));
+    }
+}
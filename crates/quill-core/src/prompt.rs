@@ -0,0 +1,126 @@
+use crate::git::GitContext;
+pub use crate::storage::TaskCounts;
+use std::collections::BTreeMap;
+
+/// Format used by `quill prompt` when none is configured.
+pub const DEFAULT_FORMAT: &str = "{sym_todo} {todo} {sym_doing} {doing} {sym_done} {done}";
+
+/// Open tasks in a context, across its repository's branches and across
+/// its organization's repositories.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rollup {
+    pub here: usize,
+    pub repo: usize,
+    pub org: usize,
+}
+
+impl Rollup {
+    /// Sums the per-context counts of `TaskStorage::open_task_counts`.
+    pub fn from_open_counts(counts: &BTreeMap<String, usize>, context: &GitContext) -> Self {
+        let mut rollup = Self::default();
+        for (context_key, open) in counts {
+            let mut parts = context_key.splitn(3, ':');
+            if parts.next() != Some(context.org.as_str()) {
+                continue;
+            }
+            rollup.org += open;
+            if parts.next() == Some(context.repo.as_str()) {
+                rollup.repo += open;
+                if parts.next() == Some(context.branch.as_str()) {
+                    rollup.here += open;
+                }
+            }
+        }
+        rollup
+    }
+}
+
+/// Renders a mini progress bar such as `▓▓░` with `width` cells.
+pub fn progress_bar(counts: &TaskCounts, width: usize) -> String {
+    let filled = if counts.total() == 0 {
+        0
+    } else {
+        (counts.completed * width + counts.total() / 2) / counts.total()
+    };
+    format!("{}{}", "▓".repeat(filled), "░".repeat(width - filled))
+}
+
+/// Expands a prompt format string. Supported placeholders:
+/// `{context}`, `{org}`, `{repo}`, `{branch}`, `{todo}`, `{doing}`, `{done}`,
+/// `{open}`, `{total}`, `{percent}`, `{bar}`, `{sym_todo}`, `{sym_doing}`, `{sym_done}`.
+pub fn render(format: &str, context: &GitContext, counts: &TaskCounts, bar_width: usize) -> String {
+    format
+        .replace("{context}", &context.context_key())
+        .replace("{org}", &context.org)
+        .replace("{repo}", &context.repo)
+        .replace("{branch}", &context.branch)
+        .replace("{todo}", &counts.not_started.to_string())
+        .replace("{doing}", &counts.in_progress.to_string())
+        .replace("{done}", &counts.completed.to_string())
+        .replace("{open}", &counts.open().to_string())
+        .replace("{total}", &counts.total().to_string())
+        .replace("{percent}", &counts.percent().to_string())
+        .replace("{bar}", &progress_bar(counts, bar_width))
+        .replace("{sym_todo}", "○")
+        .replace("{sym_doing}", "◐")
+        .replace("{sym_done}", "✓")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Task, TaskStatus};
+
+    fn counts(not_started: usize, in_progress: usize, completed: usize) -> TaskCounts {
+        TaskCounts { not_started, in_progress, completed }
+    }
+
+    fn context() -> GitContext {
+        GitContext {
+            org: "acme".to_string(),
+            repo: "widgets".to_string(),
+            branch: "main".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_counts_from_tasks() {
+        let mut done = Task::new(2, "b".to_string());
+        done.status = TaskStatus::Completed;
+        let tasks = vec![Task::new(1, "a".to_string()), done];
+
+        let counts = TaskCounts::from_tasks(&tasks);
+        assert_eq!(counts, TaskCounts { not_started: 1, in_progress: 0, completed: 1 });
+        assert_eq!(counts.percent(), 50);
+    }
+
+    #[test]
+    fn test_rollup_sums_repo_and_org() {
+        let counts: BTreeMap<String, usize> = [
+            ("acme:widgets:main", 4),
+            ("acme:widgets:feature/x", 8),
+            ("acme:gadgets:main", 25),
+            ("other:widgets:main", 100),
+        ]
+        .into_iter()
+        .map(|(key, open)| (key.to_string(), open))
+        .collect();
+        assert_eq!(Rollup::from_open_counts(&counts, &context()), Rollup { here: 4, repo: 12, org: 37 });
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        assert_eq!(progress_bar(&counts(1, 0, 2), 3), "▓▓░");
+        assert_eq!(progress_bar(&counts(0, 0, 0), 3), "░░░");
+        assert_eq!(progress_bar(&counts(0, 0, 4), 5), "▓▓▓▓▓");
+    }
+
+    #[test]
+    fn test_render_placeholders() {
+        let out = render("{repo}@{branch} {bar} {percent}% ({done}/{total})", &context(), &counts(1, 0, 2), 3);
+        assert_eq!(out, "widgets@main ▓▓░ 66% (2/3)");
+
+        let out = render(DEFAULT_FORMAT, &context(), &counts(3, 1, 2), 3);
+        assert_eq!(out, "○ 3 ◐ 1 ✓ 2");
+    }
+}
@@ -0,0 +1,185 @@
+//! Team conventions kept in one shared place and layered under each
+//! machine's own config, so everyone gets the same tags, templates and rules.
+
+use crate::config::{AppConfig, BranchRule, StorageRule, TagRule};
+use crate::storage::TaskStatus;
+use crate::templates::TaskTemplate;
+use crate::{QuillError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Where the shared settings are loaded from; leaving both unset turns them off.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SharedSource {
+    /// URL of a TOML file holding the shared settings.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    /// Read them from the `shared_config` collection of the configured MongoDB database.
+    pub mongodb: bool,
+}
+
+impl SharedSource {
+    pub fn is_set(&self) -> bool {
+        !self.url.is_empty() || self.mongodb
+    }
+}
+
+/// The settings a team can share. Local settings win over them; see
+/// `AppConfig::with_shared` for how each one is merged.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SharedConfig {
+    pub default_tags: Vec<String>,
+    pub tag_rules: Vec<TagRule>,
+    pub branch_rules: Vec<BranchRule>,
+    /// Only pick a backend type; where it connects stays each user's setting.
+    pub storage_rules: Vec<StorageRule>,
+    pub templates: Vec<TaskTemplate>,
+    /// Jira status names and the task status each stands for.
+    pub status_map: BTreeMap<String, TaskStatus>,
+}
+
+impl SharedConfig {
+    pub fn parse(text: &str) -> Result<Self> {
+        let shared: Self = toml::from_str(text).map_err(|e| QuillError::Validation(format!("Invalid shared config: {}", e)))?;
+        if let Some(rule) = shared.storage_rules.iter().find(|rule| rule.has_connection_settings()) {
+            return Err(QuillError::Validation(format!(
+                "Invalid shared config: the storage rule for {} can only set storage_type, not connection settings",
+                rule.repo
+            )));
+        }
+        Ok(shared)
+    }
+
+    /// The copy saved by the last successful fetch, when `source` is set.
+    pub fn cached(source: &SharedSource) -> Option<Self> {
+        if !source.is_set() {
+            return None;
+        }
+        let text = fs::read_to_string(cache_path().ok()?).ok()?;
+        Self::parse(&text).ok()
+    }
+
+    /// Fetches the shared settings and refreshes the cached copy. `None`
+    /// when no source is configured.
+    pub async fn fetch(config: &AppConfig) -> Result<Option<Self>> {
+        let source = &config.shared;
+        if !source.is_set() {
+            return Ok(None);
+        }
+        let text = if source.mongodb {
+            fetch_mongodb(config).await?
+        } else {
+            fetch_url(&source.url).await?
+        };
+
+        let shared = Self::parse(&text)?;
+        let path = cache_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, text)?;
+        tracing::info!(mongodb = source.mongodb, "fetched shared config");
+        Ok(Some(shared))
+    }
+
+    /// Stores `text` as the team's shared settings in the configured MongoDB
+    /// database, after checking that it parses.
+    pub async fn publish(config: &AppConfig, text: &str) -> Result<()> {
+        Self::parse(text)?;
+        publish_mongodb(config, text).await
+    }
+}
+
+fn cache_path() -> Result<PathBuf> {
+    Ok(AppConfig::get_config_dir()?.join("shared_config.toml"))
+}
+
+#[cfg(feature = "remote")]
+async fn fetch_url(url: &str) -> Result<String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .user_agent(concat!("quill/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    Ok(client.get(url).send().await?.error_for_status()?.text().await?)
+}
+
+#[cfg(not(feature = "remote"))]
+async fn fetch_url(_url: &str) -> Result<String> {
+    Err(QuillError::Unsupported("this build of quill can't fetch shared config over HTTP (the `remote` feature is off)".to_string()))
+}
+
+#[cfg(feature = "mongo")]
+const SHARED_DOCUMENT_ID: &str = "shared";
+
+#[cfg(feature = "mongo")]
+async fn shared_collection(config: &AppConfig) -> Result<mongodb::Collection<bson::Document>> {
+    let mongo = &config.mongo_config;
+    let client = crate::storage::mongodb::client_options(mongo).await.map(|mut options| {
+        options.server_selection_timeout = Some(std::time::Duration::from_secs(5));
+        mongodb::Client::with_options(options)
+    })??;
+    Ok(client.database(&mongo.database).collection("shared_config"))
+}
+
+#[cfg(feature = "mongo")]
+async fn fetch_mongodb(config: &AppConfig) -> Result<String> {
+    let document = shared_collection(config)
+        .await?
+        .find_one(bson::doc! { "_id": SHARED_DOCUMENT_ID })
+        .await?
+        .ok_or_else(|| QuillError::StorageUnavailable("no shared config has been published to MongoDB".to_string()))?;
+    document
+        .get_str("toml")
+        .map(str::to_string)
+        .map_err(|e| QuillError::Corrupt(format!("shared config document: {}", e)))
+}
+
+#[cfg(feature = "mongo")]
+async fn publish_mongodb(config: &AppConfig, text: &str) -> Result<()> {
+    shared_collection(config)
+        .await?
+        .replace_one(bson::doc! { "_id": SHARED_DOCUMENT_ID }, bson::doc! { "_id": SHARED_DOCUMENT_ID, "toml": text })
+        .upsert(true)
+        .await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "mongo"))]
+async fn fetch_mongodb(_config: &AppConfig) -> Result<String> {
+    Err(QuillError::Unsupported("this build of quill has no MongoDB support (the `mongo` feature is off)".to_string()))
+}
+
+#[cfg(not(feature = "mongo"))]
+async fn publish_mongodb(config: &AppConfig, _text: &str) -> Result<()> {
+    fetch_mongodb(config).await.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rejects_unknown_settings() {
+        let shared = SharedConfig::parse("default_tags = [\"team\"]\n\n[status_map]\n\"In Review\" = \"InProgress\"\n").unwrap();
+        assert_eq!(shared.default_tags, vec!["team"]);
+        assert_eq!(shared.status_map["In Review"], TaskStatus::InProgress);
+
+        // The backend itself stays a local choice
+        assert!(SharedConfig::parse("storage_type = \"MongoDB\"").is_err());
+    }
+
+    #[test]
+    fn test_storage_rules_only_pick_the_backend() {
+        let rule = "[[storage_rules]]\nrepo = \"acme/*\"\nstorage_type = \"Remote\"\n";
+        assert_eq!(SharedConfig::parse(rule).unwrap().storage_rules[0].repo, "acme/*");
+
+        let connecting = format!("{}\n[storage_rules.remote_config]\nurl = \"https://attacker\"\ntoken = \"${{GITHUB_TOKEN}}\"\n", rule);
+        assert!(matches!(SharedConfig::parse(&connecting), Err(QuillError::Validation(e)) if e.contains("acme/*")));
+        let connecting = format!("{}\n[storage_rules.mongo_config]\nconnection_string = \"mongodb://attacker/\"\n", rule);
+        assert!(SharedConfig::parse(&connecting).is_err());
+    }
+}
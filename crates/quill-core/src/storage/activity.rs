@@ -0,0 +1,507 @@
+//! Who changed which task and how, recorded for backends a team shares.
+//!
+//! [`ActivityStorage`] wraps a backend and, after each write, hands the
+//! backend an [`ActivityEntry`] per change through
+//! [`TaskStorage::record_activity`]. Backends that keep a log store them
+//! (MongoDB in an `activity` collection, local storage in `activity.jsonl`
+//! next to its index); the others ignore them.
+
+use super::tracked::SaveTracker;
+use super::{Attachment, BackendHealth, DeletedTask, ExternalChange, Task, TaskCounts, TaskStatus, TaskStorage, Tombstone};
+use crate::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Entries `list_activity` returns at most.
+pub const ACTIVITY_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityAction {
+    Added,
+    /// One field of the task changed; see `field`, `old` and `new`.
+    Changed,
+    Deleted,
+    Restored,
+    /// Removed from the trash for good.
+    Purged,
+    /// Moved up or down the list.
+    Reordered,
+    /// Every task of the context was deleted for good.
+    ContextDeleted,
+    /// Every task of the context moved to the context in `new`, or arrived
+    /// from the one in `old`.
+    ContextMoved,
+    /// The task moved to the context in `new`, or arrived from the one in `old`.
+    Moved,
+}
+
+/// One change to a context.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    pub context_key: String,
+    /// RFC 3339 time of the change.
+    pub at: String,
+    /// Who made it: their git `user.email`, or their login name.
+    pub actor: String,
+    pub action: ActivityAction,
+    /// `None` for changes to the whole context.
+    #[serde(default)]
+    pub task_uid: Option<Uuid>,
+    /// The task's text at the time, so deleted tasks can still be named.
+    #[serde(default)]
+    pub task_text: String,
+    #[serde(default)]
+    pub field: Option<String>,
+    #[serde(default)]
+    pub old: Option<String>,
+    #[serde(default)]
+    pub new: Option<String>,
+}
+
+impl ActivityEntry {
+    pub fn new(context_key: &str, actor: &str, action: ActivityAction, task: Option<&Task>) -> Self {
+        Self {
+            context_key: context_key.to_string(),
+            at: chrono::Utc::now().to_rfc3339(),
+            actor: actor.to_string(),
+            action,
+            task_uid: task.map(|t| t.uid),
+            task_text: task.map(|t| t.text.clone()).unwrap_or_default(),
+            field: None,
+            old: None,
+            new: None,
+        }
+    }
+
+    fn change(mut self, field: Option<&str>, old: Option<String>, new: Option<String>) -> Self {
+        self.field = field.map(str::to_string);
+        self.old = old;
+        self.new = new;
+        self
+    }
+
+    /// What happened, for the activity feed, e.g.
+    /// `changed status of "Ship": NotStarted → Completed`.
+    pub fn describe(&self) -> String {
+        let value = |v: &Option<String>| match v.as_deref() {
+            None | Some("") => "(none)".to_string(),
+            Some(v) => shorten(v),
+        };
+        match self.action {
+            ActivityAction::Added => format!("added \"{}\"", self.task_text),
+            ActivityAction::Changed => format!(
+                "changed {} of \"{}\": {} → {}",
+                self.field.as_deref().unwrap_or("task"),
+                self.task_text,
+                value(&self.old),
+                value(&self.new)
+            ),
+            ActivityAction::Deleted => format!("deleted \"{}\"", self.task_text),
+            ActivityAction::Restored => format!("restored \"{}\"", self.task_text),
+            ActivityAction::Purged => format!("purged \"{}\" from the trash", self.task_text),
+            ActivityAction::Reordered => format!("moved \"{}\" {}", self.task_text, value(&self.new)),
+            ActivityAction::ContextDeleted => format!("deleted all {} task(s)", value(&self.old)),
+            ActivityAction::ContextMoved => match (&self.old, &self.new) {
+                (_, Some(to)) => format!("moved every task to {}", to),
+                (Some(from), None) => format!("moved every task here from {}", from),
+                (None, None) => "moved every task".to_string(),
+            },
+            ActivityAction::Moved => match (&self.old, &self.new) {
+                (_, Some(to)) => format!("moved \"{}\" to {}", self.task_text, to),
+                (Some(from), None) => format!("moved \"{}\" here from {}", self.task_text, from),
+                (None, None) => format!("moved \"{}\"", self.task_text),
+            },
+        }
+    }
+}
+
+/// Long values such as notes, cut to their first line.
+fn shorten(value: &str) -> String {
+    let first = value.lines().next().unwrap_or_default();
+    if first.chars().count() > 60 || first.len() < value.trim_end().len() {
+        format!("{}…", first.chars().take(60).collect::<String>())
+    } else {
+        first.to_string()
+    }
+}
+
+/// Who changes made from here are recorded as.
+pub fn actor() -> String {
+    crate::git::user_email(&crate::git::current_dir())
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// The fields that differ between two versions of a task, as
+/// (field, old, new). Position in the list isn't one of them.
+pub fn task_changes(old: &Task, new: &Task) -> Vec<(&'static str, Option<String>, Option<String>)> {
+    fn opt<T: Display>(value: &Option<T>) -> Option<String> {
+        value.as_ref().map(T::to_string)
+    }
+    let mut changes = Vec::new();
+    let mut compare = |field, old: Option<String>, new: Option<String>| {
+        if old != new {
+            changes.push((field, old, new));
+        }
+    };
+    compare("text", Some(old.text.clone()), Some(new.text.clone()));
+    compare("status", Some(format!("{:?}", old.status)), Some(format!("{:?}", new.status)));
+    compare("must finish", Some(old.must_finish.to_string()), Some(new.must_finish.to_string()));
+    compare("pinned", Some(old.pinned.to_string()), Some(new.pinned.to_string()));
+    compare("tags", Some(old.tags.join(", ")), Some(new.tags.join(", ")));
+    compare("notes", Some(old.notes.clone()), Some(new.notes.clone()));
+    compare("due", opt(&old.due), opt(&new.due));
+    compare("priority", Some(format!("{:?}", old.priority)), Some(format!("{:?}", new.priority)));
+    compare("anchor", opt(&old.anchor), opt(&new.anchor));
+    let attachments = |task: &Task| task.attachments.iter().map(Attachment::to_string).collect::<Vec<_>>().join(", ");
+    compare("attachments", Some(attachments(old)), Some(attachments(new)));
+    compare("parent", opt(&old.parent), opt(&new.parent));
+    compare("issue", old.issue.clone(), new.issue.clone());
+    compare("assignee", old.assignee.clone(), new.assignee.clone());
+    compare("snoozed_until", old.snoozed_until.clone(), new.snoozed_until.clone());
+    changes
+}
+
+/// Wraps a backend and records every change made through it as `actor`.
+///
+/// Each write reads the task once beforehand; what it looks like afterwards
+/// is worked out from the change rather than read back.
+pub struct ActivityStorage {
+    inner: Box<dyn TaskStorage>,
+    actor: String,
+}
+
+impl ActivityStorage {
+    pub fn new(inner: Box<dyn TaskStorage>, actor: String) -> Self {
+        Self { inner, actor }
+    }
+
+    /// Records the following changes as `actor`, e.g. per request on a
+    /// server shared by several people.
+    pub fn set_actor(&mut self, actor: String) {
+        self.actor = actor;
+    }
+
+    fn entry(&self, context_key: &str, action: ActivityAction, task: Option<&Task>) -> ActivityEntry {
+        ActivityEntry::new(context_key, &self.actor, action, task)
+    }
+
+    async fn find(&self, context_key: &str, id: usize) -> Result<Option<Task>> {
+        Ok(self.inner.get_tasks(context_key).await?.into_iter().find(|t| t.id == id))
+    }
+
+    /// The stored version of `task`, found by uid as `update_task` finds it,
+    /// since its id may belong to another task by now.
+    async fn find_uid(&self, context_key: &str, task: &Task) -> Result<Option<Task>> {
+        Ok(self.inner.get_tasks(context_key).await?.into_iter().find(|t| t.uid == task.uid))
+    }
+
+    /// The change itself already succeeded, so failing to record it is only logged.
+    async fn record(&mut self, entries: Vec<ActivityEntry>) {
+        if entries.is_empty() {
+            return;
+        }
+        if let Err(e) = self.inner.record_activity(entries).await {
+            tracing::warn!(error = %e, "couldn't record activity");
+        }
+    }
+
+    /// Records the differences between `before` and `after`.
+    async fn record_update(&mut self, context_key: &str, before: Option<Task>, after: impl FnOnce(&mut Task)) {
+        let Some(before) = before else {
+            return;
+        };
+        let mut changed = before.clone();
+        after(&mut changed);
+        let entries = task_changes(&before, &changed)
+            .into_iter()
+            .map(|(field, old, new)| self.entry(context_key, ActivityAction::Changed, Some(&before)).change(Some(field), old, new))
+            .collect();
+        self.record(entries).await;
+    }
+
+    async fn record_reorder(&mut self, context_key: &str, task: Option<Task>, direction: &str) {
+        if let Some(task) = task {
+            let entry = self.entry(context_key, ActivityAction::Reordered, Some(&task)).change(None, None, Some(direction.to_string()));
+            self.record(vec![entry]).await;
+        }
+    }
+}
+
+#[async_trait]
+impl TaskStorage for ActivityStorage {
+    async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>> {
+        self.inner.get_tasks(context_key).await
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<String>> {
+        self.inner.list_contexts().await
+    }
+
+    async fn get_all_tasks(&self) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.inner.get_all_tasks().await
+    }
+
+    async fn get_task_counts(&self, context_key: &str) -> Result<TaskCounts> {
+        self.inner.get_task_counts(context_key).await
+    }
+
+    async fn all_task_counts(&self) -> Result<BTreeMap<String, TaskCounts>> {
+        self.inner.all_task_counts().await
+    }
+
+    async fn open_task_counts(&self) -> Result<BTreeMap<String, usize>> {
+        self.inner.open_task_counts().await
+    }
+
+    async fn search_tasks(&self, query: &str) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.inner.search_tasks(query).await
+    }
+
+    async fn add_task(&mut self, context_key: &str, text: String) -> Result<usize> {
+        let id = self.inner.add_task(context_key, text).await?;
+        // The new task's uid is only known once it's stored
+        match self.find(context_key, id).await {
+            Ok(Some(task)) => {
+                let entry = self.entry(context_key, ActivityAction::Added, Some(&task));
+                self.record(vec![entry]).await;
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(error = %e, "couldn't read the added task to record it"),
+        }
+        Ok(id)
+    }
+
+    async fn toggle_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let before = self.find(context_key, id).await?;
+        let toggled = self.inner.toggle_task(context_key, id).await?;
+        if toggled {
+            self.record_update(context_key, before, |task| task.set_status(task.status.next())).await;
+        }
+        Ok(toggled)
+    }
+
+    async fn set_task_status(&mut self, context_key: &str, id: usize, status: TaskStatus) -> Result<bool> {
+        let before = self.find(context_key, id).await?;
+        let changed = self.inner.set_task_status(context_key, id, status.clone()).await?;
+        if changed {
+            self.record_update(context_key, before, |task| task.set_status(status)).await;
+        }
+        Ok(changed)
+    }
+
+    async fn remove_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let before = self.find(context_key, id).await?;
+        let removed = self.inner.remove_task(context_key, id).await?;
+        if let Some(task) = before.filter(|_| removed) {
+            let entry = self.entry(context_key, ActivityAction::Deleted, Some(&task));
+            self.record(vec![entry]).await;
+        }
+        Ok(removed)
+    }
+
+    async fn edit_task(&mut self, context_key: &str, id: usize, new_text: String) -> Result<bool> {
+        let before = self.find(context_key, id).await?;
+        let edited = self.inner.edit_task(context_key, id, new_text.clone()).await?;
+        if edited {
+            self.record_update(context_key, before, |task| task.text = new_text).await;
+        }
+        Ok(edited)
+    }
+
+    async fn update_task(&mut self, context_key: &str, task: Task) -> Result<bool> {
+        let before = self.find_uid(context_key, &task).await?;
+        let updated = self.inner.update_task(context_key, task.clone()).await?;
+        if updated {
+            self.record_update(context_key, before, |stored| *stored = task).await;
+        }
+        Ok(updated)
+    }
+
+    async fn undo_delete(&mut self, context_key: &str) -> Result<Option<Task>> {
+        let restored = self.inner.undo_delete(context_key).await?;
+        if let Some(task) = &restored {
+            let entry = self.entry(context_key, ActivityAction::Restored, Some(task));
+            self.record(vec![entry]).await;
+        }
+        Ok(restored)
+    }
+
+    async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>> {
+        self.inner.list_deleted(context_key).await
+    }
+
+    async fn restore_deleted(&mut self, context_key: &str, id: usize) -> Result<Option<Task>> {
+        let restored = self.inner.restore_deleted(context_key, id).await?;
+        if let Some(task) = &restored {
+            let entry = self.entry(context_key, ActivityAction::Restored, Some(task));
+            self.record(vec![entry]).await;
+        }
+        Ok(restored)
+    }
+
+    async fn purge_deleted(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let before = self.inner.list_deleted(context_key).await?.into_iter().find(|d| d.task.id == id);
+        let purged = self.inner.purge_deleted(context_key, id).await?;
+        if let Some(deleted) = before.filter(|_| purged) {
+            let entry = self.entry(context_key, ActivityAction::Purged, Some(&deleted.task));
+            self.record(vec![entry]).await;
+        }
+        Ok(purged)
+    }
+
+    async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>> {
+        self.inner.list_tombstones(context_key).await
+    }
+
+    async fn move_task_up(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let task = self.find(context_key, id).await?;
+        let moved = self.inner.move_task_up(context_key, id).await?;
+        if moved {
+            self.record_reorder(context_key, task, "up").await;
+        }
+        Ok(moved)
+    }
+
+    async fn move_task_down(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let task = self.find(context_key, id).await?;
+        let moved = self.inner.move_task_down(context_key, id).await?;
+        if moved {
+            self.record_reorder(context_key, task, "down").await;
+        }
+        Ok(moved)
+    }
+
+    async fn delete_context(&mut self, context_key: &str) -> Result<usize> {
+        let deleted = self.inner.delete_context(context_key).await?;
+        if deleted > 0 {
+            let entry = self.entry(context_key, ActivityAction::ContextDeleted, None).change(None, Some(deleted.to_string()), None);
+            self.record(vec![entry]).await;
+        }
+        Ok(deleted)
+    }
+
+    async fn move_context_tasks(&mut self, from: &str, to: &str) -> Result<usize> {
+        let moved = self.inner.move_context_tasks(from, to).await?;
+        if moved > 0 {
+            let left = self.entry(from, ActivityAction::ContextMoved, None).change(None, None, Some(to.to_string()));
+            let arrived = self.entry(to, ActivityAction::ContextMoved, None).change(None, Some(from.to_string()), None);
+            self.record(vec![left, arrived]).await;
+        }
+        Ok(moved)
+    }
+
+    async fn move_tasks(&mut self, from: &str, ids: &[usize], to: &str) -> Result<usize> {
+        let moving: Vec<Task> = self.inner.get_tasks(from).await?.into_iter().filter(|t| ids.contains(&t.id)).collect();
+        let moved = self.inner.move_tasks(from, ids, to).await?;
+        let mut entries = Vec::new();
+        for task in &moving {
+            entries.push(self.entry(from, ActivityAction::Moved, Some(task)).change(None, None, Some(to.to_string())));
+            entries.push(self.entry(to, ActivityAction::Moved, Some(task)).change(None, Some(from.to_string()), None));
+        }
+        self.record(entries).await;
+        Ok(moved)
+    }
+
+    async fn record_activity(&mut self, entries: Vec<ActivityEntry>) -> Result<()> {
+        self.inner.record_activity(entries).await
+    }
+
+    async fn list_activity(&self, context_key: &str) -> Result<Vec<ActivityEntry>> {
+        self.inner.list_activity(context_key).await
+    }
+
+    async fn export_activity(&self, since: Option<&str>) -> Result<Vec<ActivityEntry>> {
+        self.inner.export_activity(since).await
+    }
+
+    async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String> {
+        self.inner.inspect_task(context_key, id).await
+    }
+
+    async fn health(&self) -> Result<BackendHealth> {
+        self.inner.health().await
+    }
+
+    fn watch_path(&self) -> Option<PathBuf> {
+        self.inner.watch_path()
+    }
+
+    async fn check_external_changes(&mut self) -> Result<ExternalChange> {
+        self.inner.check_external_changes().await
+    }
+
+    async fn resolve_external_change(&mut self, keep_mine: bool) -> Result<()> {
+        self.inner.resolve_external_change(keep_mine).await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.inner.flush().await
+    }
+
+    fn take_failed_writes(&mut self) -> Vec<String> {
+        self.inner.take_failed_writes()
+    }
+
+    fn track_saves(&mut self, tracker: SaveTracker) {
+        self.inner.track_saves(tracker);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::local::LocalTaskStorage;
+
+    #[tokio::test]
+    async fn test_changes_are_recorded_with_old_and_new_values() {
+        let mut storage = ActivityStorage::new(Box::new(LocalTaskStorage::in_memory()), "ann@acme.dev".to_string());
+        let context = "acme:web:main";
+        let id = storage.add_task(context, "Ship".to_string()).await.unwrap();
+        storage.set_task_status(context, id, TaskStatus::Completed).await.unwrap();
+        let mut task = storage.get_tasks(context).await.unwrap().remove(0);
+        task.tags = vec!["release".to_string()];
+        task.order += 10.0;
+        storage.update_task(context, task).await.unwrap();
+        storage.remove_task(context, id).await.unwrap();
+        storage.add_task("acme:web:other", "Elsewhere".to_string()).await.unwrap();
+
+        let activity = storage.list_activity(context).await.unwrap();
+        let described: Vec<String> = activity.iter().map(ActivityEntry::describe).collect();
+        // Newest first; the order change alone isn't recorded
+        assert_eq!(
+            described,
+            vec![
+                "deleted \"Ship\"",
+                "changed tags of \"Ship\": (none) → release",
+                "changed status of \"Ship\": NotStarted → Completed",
+                "added \"Ship\"",
+            ]
+        );
+        assert!(activity.iter().all(|entry| entry.actor == "ann@acme.dev" && entry.context_key == context));
+    }
+
+    #[tokio::test]
+    async fn test_updates_are_compared_with_the_same_task() {
+        let mut storage = ActivityStorage::new(Box::new(LocalTaskStorage::in_memory()), "ann@acme.dev".to_string());
+        let context = "acme:web:main";
+        storage.add_task(context, "First".to_string()).await.unwrap();
+        let second = storage.add_task(context, "Second".to_string()).await.unwrap();
+
+        // A copy of the first task whose id another task has by now, as after a renumbering
+        let mut stale = storage.get_tasks(context).await.unwrap().remove(0);
+        stale.id = second;
+        stale.notes = "Details".to_string();
+        assert!(storage.update_task(context, stale).await.unwrap());
+
+        let activity = storage.list_activity(context).await.unwrap();
+        assert_eq!(activity[0].describe(), "changed notes of \"First\": (none) → Details");
+        assert_eq!(activity.iter().filter(|entry| entry.action == ActivityAction::Changed).count(), 1);
+    }
+}
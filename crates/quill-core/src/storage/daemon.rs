@@ -0,0 +1,485 @@
+//! The protocol between `quill daemon` and the TUI instances using it.
+//!
+//! Both sides write one JSON value per line over a unix socket. A client
+//! sends numbered [`Call`]s and matches each [`Message::Reply`] to its call
+//! by number; the daemon greets every client with [`Message::Hello`] and
+//! sends [`Message::Changed`] to all of them after each write, so other
+//! instances refresh without polling.
+
+use super::remote::{self, Request, Response};
+use super::activity::ActivityEntry;
+use super::{BackendHealth, DeletedTask, ExternalChange, Task, TaskCounts, TaskStatus, TaskStorage, Tombstone};
+use crate::config::{AppConfig, StorageType};
+use crate::{QuillError, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+use tokio::sync::{mpsc, oneshot, Mutex as AsyncMutex};
+
+/// How long a context read through the daemon is kept without a change
+/// notification, for backends other machines write to as well.
+const CACHE_FOR: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Message {
+    /// Sent once to each client, naming the storage served (see [`storage_id`]).
+    Hello { storage: String },
+    /// The answer to the client's call numbered `id`, with the status
+    /// [`remote::status_for`] gives its error.
+    Reply { id: u64, status: u16, response: Response },
+    /// A context was changed; `None` when any may have been.
+    Changed { context_key: Option<String> },
+}
+
+impl Message {
+    /// The message as one line of the protocol.
+    pub fn encode(&self) -> Result<String> {
+        Ok(format!("{}\n", serde_json::to_string(self)?))
+    }
+}
+
+/// A request as a client sends it, numbered so the reply finds its way back
+/// even after an earlier call gave up waiting. The request is nested rather
+/// than flattened, as several carry an `id` of their own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Call {
+    pub id: u64,
+    pub request: Request,
+}
+
+/// `~/.quill/daemon.sock`
+pub fn socket_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".quill").join("daemon.sock"))
+}
+
+/// Names the storage `config` selects, so clients only use a daemon serving
+/// the same one. Leaves out credentials.
+pub fn storage_id(config: &AppConfig) -> String {
+    let location = match config.storage_type {
+        StorageType::Local => config.expand_local_path(),
+        StorageType::MongoDB => format!("{}/{}", config.mongo_config.database, config.mongo_config.collection),
+        StorageType::Git => config.git_config.branch.clone(),
+        StorageType::Remote => config.remote_config.url.clone(),
+    };
+    format!("{} {}", config.storage_type.label(), location)
+}
+
+/// Calls waiting for their reply, by number.
+#[derive(Default)]
+struct Waiting {
+    replies: HashMap<u64, oneshot::Sender<(u16, Response)>>,
+    /// Set once the daemon's side of the socket closes.
+    closed: bool,
+}
+
+struct Connection {
+    /// Lines for the task writing to the socket, so a cancelled call never
+    /// leaves half a line behind.
+    lines: mpsc::UnboundedSender<String>,
+    waiting: Arc<Mutex<Waiting>>,
+    next_id: u64,
+}
+
+impl Connection {
+    fn is_closed(&self) -> bool {
+        lock(&self.waiting).closed || self.lines.is_closed()
+    }
+
+    async fn call(&mut self, request: &Request) -> Result<(u16, Response)> {
+        self.next_id += 1;
+        let call = Call { id: self.next_id, request: request.clone() };
+        let (reply_tx, reply) = oneshot::channel();
+        {
+            let mut waiting = lock(&self.waiting);
+            if waiting.closed {
+                return Err(closed());
+            }
+            waiting.replies.insert(call.id, reply_tx);
+        }
+        self.lines.send(format!("{}\n", serde_json::to_string(&call)?)).map_err(|_| closed())?;
+        reply.await.map_err(|_| closed())
+    }
+}
+
+/// Where calls go: the daemon, or the storage itself once the daemon is
+/// gone and no other has taken its place.
+enum Backend {
+    Daemon(Connection),
+    Direct(Box<dyn TaskStorage>),
+}
+
+#[derive(Default)]
+struct Cache {
+    contexts: HashMap<String, (Instant, Vec<Task>)>,
+    /// Bumped by every change notification, so a read that raced one isn't cached.
+    generation: u64,
+}
+
+impl Cache {
+    fn clear(&mut self) {
+        self.generation += 1;
+        self.contexts.clear();
+    }
+}
+
+/// Storage owned by a `quill daemon`, reached over its socket. Contexts are
+/// cached until the daemon reports a change to them. If the daemon stops,
+/// the next call reconnects to a new one or else opens the storage directly.
+pub struct DaemonStorage {
+    backend: AsyncMutex<Backend>,
+    path: PathBuf,
+    config: AppConfig,
+    cache: Arc<Mutex<Cache>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn closed() -> QuillError {
+    QuillError::StorageUnavailable("the quill daemon closed the connection".to_string())
+}
+
+/// Hands replies to the calls waiting for them and applies change notifications.
+async fn read_messages(mut reader: BufReader<OwnedReadHalf>, waiting: Arc<Mutex<Waiting>>, cache: Arc<Mutex<Cache>>) {
+    let mut line = String::new();
+    while reader.read_line(&mut line).await.is_ok_and(|read| read > 0) {
+        match serde_json::from_str::<Message>(&line) {
+            Ok(Message::Reply { id, status, response }) => {
+                // Nobody is waiting when the call was cancelled
+                if let Some(reply) = lock(&waiting).replies.remove(&id) {
+                    let _ = reply.send((status, response));
+                }
+            }
+            Ok(Message::Changed { context_key }) => {
+                let mut cache = lock(&cache);
+                match context_key {
+                    Some(key) => {
+                        cache.generation += 1;
+                        cache.contexts.remove(&key);
+                    }
+                    None => cache.clear(),
+                }
+            }
+            Ok(Message::Hello { .. }) => {}
+            Err(e) => tracing::warn!(error = %e, "unreadable message from the quill daemon"),
+        }
+        line.clear();
+    }
+    tracing::debug!("quill daemon connection closed");
+    // Dropping the senders fails the calls still waiting
+    let mut waiting = lock(&waiting);
+    waiting.closed = true;
+    waiting.replies.clear();
+}
+
+async fn write_lines(mut writer: OwnedWriteHalf, mut lines: mpsc::UnboundedReceiver<String>) {
+    while let Some(line) = lines.recv().await {
+        if writer.write_all(line.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Connects to the daemon at `path` and checks it serves the storage
+/// `config` selects.
+async fn open_connection(path: &Path, config: &AppConfig, cache: Arc<Mutex<Cache>>) -> Result<Connection> {
+    let stream = UnixStream::connect(path)
+        .await
+        .map_err(|e| QuillError::StorageUnavailable(format!("no quill daemon at {}: {}", path.display(), e)))?;
+    let (reader, writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let expected = storage_id(config);
+    match serde_json::from_str::<Message>(&line) {
+        Ok(Message::Hello { storage }) if storage == expected => {}
+        Ok(Message::Hello { storage }) => {
+            return Err(QuillError::Conflict(format!("the quill daemon serves {}, not {}", storage, expected)))
+        }
+        _ => return Err(QuillError::StorageUnavailable("the quill daemon didn't say hello".to_string())),
+    }
+
+    let waiting = Arc::new(Mutex::new(Waiting::default()));
+    let (lines, lines_rx) = mpsc::unbounded_channel();
+    tokio::spawn(read_messages(reader, waiting.clone(), cache));
+    tokio::spawn(write_lines(writer, lines_rx));
+    tracing::info!(socket = %path.display(), storage = %expected, "using the quill daemon");
+    Ok(Connection { lines, waiting, next_id: 0 })
+}
+
+impl DaemonStorage {
+    /// Connects to the daemon listening at `path`, if it serves the storage
+    /// `config` selects. Must be called within a tokio runtime.
+    pub async fn connect(path: &Path, config: &AppConfig) -> Result<Self> {
+        let cache = Arc::new(Mutex::new(Cache::default()));
+        let connection = open_connection(path, config, cache.clone()).await?;
+        Ok(Self { backend: AsyncMutex::new(Backend::Daemon(connection)), path: path.to_path_buf(), config: config.clone(), cache })
+    }
+
+    /// A new daemon's connection, or else the storage itself. Either way
+    /// change notifications were missed, so the cache starts over.
+    async fn replace_connection(&self) -> Result<Backend> {
+        lock(&self.cache).clear();
+        match open_connection(&self.path, &self.config, self.cache.clone()).await {
+            Ok(connection) => Ok(Backend::Daemon(connection)),
+            Err(e) => {
+                tracing::warn!(error = %e, "the quill daemon went away; using the storage directly");
+                Ok(Backend::Direct(super::open(&self.config).await?))
+            }
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(&self, request: &Request) -> Result<T> {
+        let mut backend = self.backend.lock().await;
+        if matches!(&*backend, Backend::Daemon(connection) if connection.is_closed()) {
+            *backend = self.replace_connection().await?;
+        }
+        let value = match &mut *backend {
+            Backend::Daemon(connection) => match connection.call(request).await? {
+                (_, Response::Result(value)) => value,
+                (status, Response::Error(message)) => {
+                    return Err(remote::error_for(status, format!("quill daemon: {}", message)))
+                }
+            },
+            Backend::Direct(storage) => remote::dispatch(storage.as_mut(), request.clone()).await?,
+        };
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Sends a write and drops the cached copy of its context.
+    async fn write<T: DeserializeOwned>(&self, request: Request) -> Result<T> {
+        let result = self.call(&request).await;
+        let mut cache = lock(&self.cache);
+        for context_key in request.written_contexts() {
+            cache.contexts.remove(context_key);
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl TaskStorage for DaemonStorage {
+    async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>> {
+        let generation = {
+            let cache = lock(&self.cache);
+            if let Some((read_at, tasks)) = cache.contexts.get(context_key) {
+                if read_at.elapsed() < CACHE_FOR {
+                    return Ok(tasks.clone());
+                }
+            }
+            cache.generation
+        };
+        let tasks: Vec<Task> = self.call(&Request::GetTasks { context_key: context_key.to_string() }).await?;
+        let mut cache = lock(&self.cache);
+        if cache.generation == generation {
+            cache.contexts.insert(context_key.to_string(), (Instant::now(), tasks.clone()));
+        }
+        Ok(tasks)
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<String>> {
+        self.call(&Request::ListContexts).await
+    }
+
+    async fn get_all_tasks(&self) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.call(&Request::GetAllTasks).await
+    }
+
+    async fn get_task_counts(&self, context_key: &str) -> Result<TaskCounts> {
+        self.call(&Request::GetTaskCounts { context_key: context_key.to_string() }).await
+    }
+
+    async fn all_task_counts(&self) -> Result<BTreeMap<String, TaskCounts>> {
+        self.call(&Request::AllTaskCounts).await
+    }
+
+    async fn open_task_counts(&self) -> Result<BTreeMap<String, usize>> {
+        self.call(&Request::OpenTaskCounts).await
+    }
+
+    async fn search_tasks(&self, query: &str) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.call(&Request::SearchTasks { query: query.to_string() }).await
+    }
+
+    async fn add_task(&mut self, context_key: &str, text: String) -> Result<usize> {
+        self.write(Request::AddTask { context_key: context_key.to_string(), text }).await
+    }
+
+    async fn toggle_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.write(Request::ToggleTask { context_key: context_key.to_string(), id }).await
+    }
+
+    async fn set_task_status(&mut self, context_key: &str, id: usize, status: TaskStatus) -> Result<bool> {
+        self.write(Request::SetTaskStatus { context_key: context_key.to_string(), id, status }).await
+    }
+
+    async fn remove_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.write(Request::RemoveTask { context_key: context_key.to_string(), id }).await
+    }
+
+    async fn edit_task(&mut self, context_key: &str, id: usize, new_text: String) -> Result<bool> {
+        self.write(Request::EditTask { context_key: context_key.to_string(), id, new_text }).await
+    }
+
+    async fn update_task(&mut self, context_key: &str, task: Task) -> Result<bool> {
+        self.write(Request::UpdateTask { context_key: context_key.to_string(), task: Box::new(task) }).await
+    }
+
+    async fn undo_delete(&mut self, context_key: &str) -> Result<Option<Task>> {
+        self.write(Request::UndoDelete { context_key: context_key.to_string() }).await
+    }
+
+    async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>> {
+        self.call(&Request::ListDeleted { context_key: context_key.to_string() }).await
+    }
+
+    async fn restore_deleted(&mut self, context_key: &str, id: usize) -> Result<Option<Task>> {
+        self.write(Request::RestoreDeleted { context_key: context_key.to_string(), id }).await
+    }
+
+    async fn purge_deleted(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.write(Request::PurgeDeleted { context_key: context_key.to_string(), id }).await
+    }
+
+    async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>> {
+        self.call(&Request::ListTombstones { context_key: context_key.to_string() }).await
+    }
+
+    async fn move_task_up(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.write(Request::MoveTaskUp { context_key: context_key.to_string(), id }).await
+    }
+
+    async fn move_task_down(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.write(Request::MoveTaskDown { context_key: context_key.to_string(), id }).await
+    }
+
+    async fn delete_context(&mut self, context_key: &str) -> Result<usize> {
+        self.write(Request::DeleteContext { context_key: context_key.to_string() }).await
+    }
+
+    async fn move_context_tasks(&mut self, from: &str, to: &str) -> Result<usize> {
+        self.write(Request::MoveContextTasks { from: from.to_string(), to: to.to_string() }).await
+    }
+
+    async fn move_tasks(&mut self, from: &str, ids: &[usize], to: &str) -> Result<usize> {
+        self.write(Request::MoveTasks { from: from.to_string(), ids: ids.to_vec(), to: to.to_string() }).await
+    }
+
+    async fn record_activity(&mut self, entries: Vec<ActivityEntry>) -> Result<()> {
+        self.call(&Request::RecordActivity { entries }).await
+    }
+
+    async fn list_activity(&self, context_key: &str) -> Result<Vec<ActivityEntry>> {
+        self.call(&Request::ListActivity { context_key: context_key.to_string() }).await
+    }
+
+    async fn export_activity(&self, since: Option<&str>) -> Result<Vec<ActivityEntry>> {
+        self.call(&Request::ExportActivity { since: since.map(str::to_string) }).await
+    }
+
+    async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String> {
+        self.call(&Request::InspectTask { context_key: context_key.to_string(), id }).await
+    }
+
+    async fn health(&self) -> Result<BackendHealth> {
+        let mut health: BackendHealth = self.call(&Request::Health).await?;
+        if matches!(*self.backend.lock().await, Backend::Daemon(_)) {
+            health.backend = format!("quill daemon ({})", health.backend);
+        }
+        Ok(health)
+    }
+
+    async fn check_external_changes(&mut self) -> Result<ExternalChange> {
+        // The daemon reports changes itself; storage opened in its place is asked
+        let Backend::Direct(storage) = self.backend.get_mut() else {
+            return Ok(ExternalChange::None);
+        };
+        let change = storage.check_external_changes().await?;
+        if change != ExternalChange::None {
+            lock(&self.cache).clear();
+        }
+        Ok(change)
+    }
+
+    async fn resolve_external_change(&mut self, keep_mine: bool) -> Result<()> {
+        lock(&self.cache).clear();
+        match self.backend.get_mut() {
+            Backend::Direct(storage) => storage.resolve_external_change(keep_mine).await,
+            Backend::Daemon(_) => Ok(()),
+        }
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        match self.backend.get_mut() {
+            Backend::Direct(storage) => storage.flush().await,
+            Backend::Daemon(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::local::LocalTaskStorage;
+    use tempfile::TempDir;
+    use tokio::net::UnixListener;
+
+    #[tokio::test]
+    async fn test_replies_find_their_call_and_a_gone_daemon_is_replaced() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("daemon.sock");
+        let mut config = AppConfig::default();
+        config.local_config.path = dir.path().join("todos.json").to_string_lossy().to_string();
+
+        // Answers the first two calls together, oldest first, then goes away
+        let listener = UnixListener::bind(&path).unwrap();
+        let hello = Message::Hello { storage: storage_id(&config) }.encode().unwrap();
+        let daemon = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            writer.write_all(hello.as_bytes()).await.unwrap();
+            let mut lines = BufReader::new(reader).lines();
+            let mut calls = Vec::new();
+            for _ in 0..2 {
+                let call: Call = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+                assert_eq!(call.request, Request::ListContexts);
+                calls.push(call.id);
+            }
+            // The first caller has given up by now
+            for (id, contexts) in calls.into_iter().zip(["stale", "fresh"]) {
+                let reply = Message::Reply { id, status: 200, response: Response::Result(serde_json::json!([contexts])) };
+                writer.write_all(reply.encode().unwrap().as_bytes()).await.unwrap();
+            }
+        });
+
+        let mut storage = DaemonStorage::connect(&path, &config).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(50), storage.list_contexts()).await.is_err());
+        assert_eq!(storage.list_contexts().await.unwrap(), vec!["fresh"]);
+        daemon.await.unwrap();
+
+        // Nothing listens any more, so the storage is opened directly
+        let context = "acme:web:main";
+        let mut added = Err(closed());
+        for _ in 0..50 {
+            added = storage.add_task(context, "Direct".to_string()).await;
+            if added.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        added.unwrap();
+        assert!(!storage.health().await.unwrap().backend.starts_with("quill daemon"));
+        let on_disk = LocalTaskStorage::new(config.local_config.path.clone()).unwrap();
+        assert_eq!(on_disk.get_tasks(context).await.unwrap()[0].text, "Direct");
+    }
+}
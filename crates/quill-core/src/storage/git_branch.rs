@@ -0,0 +1,504 @@
+use super::schema::{self, SCHEMA_VERSION};
+use super::{compact_tombstones, local::LocalTaskStorage, BackendHealth, DeletedTask, Task, TaskStatus, TaskStorage, Tombstone};
+use crate::config::GitStorageConfig;
+use crate::{QuillError, Result};
+use async_trait::async_trait;
+use git2::{Commit, Oid, Repository, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Stores tasks as files on an orphan branch of a repository, committing
+/// after every change. The branch is never checked out, so it doesn't touch
+/// the working tree; it is shared through the repository's remotes.
+///
+/// Branch layout:
+/// - `contexts/<context>.json`: tasks and trash of one context
+/// - `meta.json`: the next task id
+/// - `README.md`: every context as a Markdown checklist, for browsing on a forge
+pub struct GitBranchStorage {
+    /// Tasks as of `head`; its own persistence is disabled.
+    inner: LocalTaskStorage,
+    repo_path: PathBuf,
+    branch: String,
+    remote: String,
+    /// Commit the in-memory tasks were read from or written as.
+    head: Option<Oid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ContextFile {
+    #[serde(default)]
+    schema_version: u32,
+    context: String,
+    tasks: Vec<Task>,
+    #[serde(default)]
+    deleted: VecDeque<DeletedTask>,
+    #[serde(default)]
+    tombstones: Vec<Tombstone>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Meta {
+    next_id: usize,
+}
+
+impl GitBranchStorage {
+    /// Opens the tasks branch of the repository containing `path`; the branch
+    /// is created with the first change.
+    pub fn open(path: &Path, config: &GitStorageConfig) -> Result<Self> {
+        check_names(config)?;
+        let repo = Repository::discover(path)
+            .map_err(|e| QuillError::StorageUnavailable(format!("git storage needs a repository: {}", e.message())))?;
+        let mut storage = Self {
+            inner: LocalTaskStorage::in_memory(),
+            repo_path: repo.path().to_path_buf(),
+            branch: config.branch.clone(),
+            remote: config.remote.clone(),
+            head: None,
+        };
+        storage.load(&repo)?;
+        Ok(storage)
+    }
+
+    /// Sets how many deleted tasks are kept per context.
+    pub fn with_trash_retention(mut self, retention: usize) -> Self {
+        self.inner = self.inner.with_trash_retention(retention);
+        self
+    }
+
+    fn refname(&self) -> String {
+        format!("refs/heads/{}", self.branch)
+    }
+
+    fn repo(&self) -> Result<Repository> {
+        Ok(Repository::open(&self.repo_path)?)
+    }
+
+    fn tip<'r>(&self, repo: &'r Repository) -> Result<Option<Commit<'r>>> {
+        match repo.find_reference(&self.refname()) {
+            Ok(reference) => Ok(Some(reference.peel_to_commit()?)),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replaces the in-memory tasks with those at the branch tip.
+    fn load(&mut self, repo: &Repository) -> Result<()> {
+        self.inner.contexts.clear();
+        self.inner.deleted_tasks.clear();
+        self.inner.tombstones.clear();
+        self.inner.next_id = 1;
+
+        let Some(tip) = self.tip(repo)? else {
+            self.head = None;
+            return Ok(());
+        };
+        let tree = tip.tree()?;
+        if let Some(entry) = tree.get_name("contexts") {
+            let contexts = entry.to_object(repo)?.peel_to_tree()?;
+            for entry in contexts.iter() {
+                let blob = entry.to_object(repo)?.peel_to_blob()?;
+                let what = format!("contexts/{} on {}", entry.name().unwrap_or("?"), self.branch);
+                let mut value = serde_json::from_slice(blob.content()).map_err(|e| QuillError::Corrupt(format!("{}: {}", what, e)))?;
+                schema::upgrade_file(&mut value, &what)?;
+                let file: ContextFile = serde_json::from_value(value).map_err(|e| QuillError::Corrupt(format!("{}: {}", what, e)))?;
+                self.inner.contexts.insert(file.context.clone(), file.tasks);
+                self.inner.deleted_tasks.insert(file.context.clone(), file.deleted);
+                self.inner.tombstones.insert(file.context, file.tombstones);
+            }
+        }
+        let stored_next_id = match tree.get_name("meta.json") {
+            Some(entry) => serde_json::from_slice::<Meta>(entry.to_object(repo)?.peel_to_blob()?.content())?.next_id,
+            None => 1,
+        };
+        let max_id = self
+            .inner
+            .contexts
+            .values()
+            .flatten()
+            .chain(self.inner.deleted_tasks.values().flatten().map(|d| &d.task))
+            .map(|t| t.id)
+            .max()
+            .unwrap_or(0);
+        self.inner.next_id = stored_next_id.max(max_id + 1);
+        self.head = Some(tip.id());
+        Ok(())
+    }
+
+    /// Picks up commits made since the last read, e.g. by `quill git pull`.
+    fn refresh(&mut self) -> Result<()> {
+        let repo = self.repo()?;
+        if self.tip(&repo)?.map(|c| c.id()) != self.head {
+            self.load(&repo)?;
+        }
+        Ok(())
+    }
+
+    /// Commits the in-memory tasks to the branch unless nothing changed.
+    fn commit(&mut self, message: &str) -> Result<()> {
+        let repo = self.repo()?;
+
+        let mut contexts = repo.treebuilder(None)?;
+        for tombstones in self.inner.tombstones.values_mut() {
+            compact_tombstones(tombstones);
+        }
+        let keys: BTreeSet<&String> = self
+            .inner
+            .contexts
+            .keys()
+            .chain(self.inner.deleted_tasks.keys())
+            .chain(self.inner.tombstones.keys())
+            .collect();
+        for key in keys {
+            let file = ContextFile {
+                schema_version: SCHEMA_VERSION,
+                context: key.clone(),
+                tasks: self.inner.contexts.get(key).cloned().unwrap_or_default(),
+                deleted: self.inner.deleted_tasks.get(key).cloned().unwrap_or_default(),
+                tombstones: self.inner.tombstones.get(key).cloned().unwrap_or_default(),
+            };
+            if file.tasks.is_empty() && file.deleted.is_empty() && file.tombstones.is_empty() {
+                continue;
+            }
+            let blob = repo.blob(serde_json::to_string_pretty(&file)?.as_bytes())?;
+            contexts.insert(format!("{}.json", file_name(key)), blob, 0o100644)?;
+        }
+
+        let mut root = repo.treebuilder(None)?;
+        if !contexts.is_empty() {
+            root.insert("contexts", contexts.write()?, 0o040000)?;
+        }
+        let meta = serde_json::to_string_pretty(&Meta { next_id: self.inner.next_id })?;
+        root.insert("meta.json", repo.blob(meta.as_bytes())?, 0o100644)?;
+        root.insert("README.md", repo.blob(self.markdown().as_bytes())?, 0o100644)?;
+        let tree = repo.find_tree(root.write()?)?;
+
+        let parent = self.tip(&repo)?;
+        if parent.as_ref().is_some_and(|p| p.tree_id() == tree.id()) {
+            return Ok(());
+        }
+        let signature = repo.signature().or_else(|_| Signature::now("quill", "quill@localhost"))?;
+        let parents: Vec<&Commit> = parent.iter().collect();
+        let oid = repo.commit(Some(&self.refname()), &signature, &signature, message, &tree, &parents)?;
+        tracing::debug!(%oid, message, "committed tasks");
+        self.head = Some(oid);
+        Ok(())
+    }
+
+    fn markdown(&self) -> String {
+        let mut keys: Vec<&String> = self.inner.contexts.keys().filter(|k| !self.inner.contexts[*k].is_empty()).collect();
+        keys.sort();
+        let mut out = String::from("# Tasks\n\nManaged by quill; edit with `quill` rather than by hand.\n");
+        for key in keys {
+            out.push_str(&format!("\n## {}\n\n", key));
+            for task in &self.inner.contexts[key] {
+                let mark = match task.status {
+                    TaskStatus::NotStarted => " ",
+                    TaskStatus::InProgress => "~",
+                    TaskStatus::Completed => "x",
+                };
+                out.push_str(&format!("- [{}] {}\n", mark, task.text));
+            }
+        }
+        out
+    }
+
+    /// Commits after a change that `changed` reports actually happened.
+    fn committed<T>(&mut self, changed: bool, result: T, message: String) -> Result<T> {
+        if changed {
+            self.commit(&message)?;
+        }
+        Ok(result)
+    }
+}
+
+/// File name for a context key, with characters that are unsafe in paths
+/// (`:`, `/`, `*`, ...) percent-encoded.
+fn file_name(context_key: &str) -> String {
+    let mut name = String::new();
+    for byte in context_key.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.@+".contains(&byte) {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    name
+}
+
+/// Refuses a branch or remote git would take for an option, or a branch
+/// name git doesn't accept.
+fn check_names(config: &GitStorageConfig) -> Result<()> {
+    if config.remote.is_empty() || config.remote.starts_with('-') {
+        return Err(QuillError::Validation(format!("invalid git storage remote `{}`", config.remote)));
+    }
+    if config.branch.starts_with('-') || !git2::Reference::is_valid_name(&format!("refs/heads/{}", config.branch)) {
+        return Err(QuillError::Validation(format!("invalid git storage branch `{}`", config.branch)));
+    }
+    Ok(())
+}
+
+/// Fast-forwards the local tasks branch from the remote.
+pub fn pull(repo_root: &Path, config: &GitStorageConfig) -> Result<String> {
+    check_names(config)?;
+    let refspec = format!("{}:{}", config.branch, config.branch);
+    run_git(repo_root, &["fetch", "--end-of-options", &config.remote, &refspec]).map_err(|e| {
+        QuillError::Conflict(format!("{}\nIf both sides changed, push is rejected and pull cannot fast-forward; resolve on the `{}` branch with git", e, config.branch))
+    })
+}
+
+/// Pushes the local tasks branch to the remote.
+pub fn push(repo_root: &Path, config: &GitStorageConfig) -> Result<String> {
+    check_names(config)?;
+    let repo = Repository::discover(repo_root)?;
+    if repo.find_branch(&config.branch, git2::BranchType::Local).is_err() {
+        return Err(QuillError::Validation(format!("nothing to push: no tasks have been committed to `{}` yet", config.branch)));
+    }
+    run_git(repo_root, &["push", "--end-of-options", &config.remote, &config.branch])
+}
+
+fn run_git(repo_root: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).current_dir(repo_root).output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if output.status.success() {
+        Ok(stderr)
+    } else {
+        Err(QuillError::StorageUnavailable(format!("git {} failed: {}", args.join(" "), stderr)))
+    }
+}
+
+#[async_trait]
+impl TaskStorage for GitBranchStorage {
+    async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>> {
+        self.inner.get_tasks(context_key).await
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<String>> {
+        self.inner.list_contexts().await
+    }
+
+    async fn add_task(&mut self, context_key: &str, text: String) -> Result<usize> {
+        self.refresh()?;
+        let message = format!("Add task in {}: {}", context_key, text);
+        let id = self.inner.add_task(context_key, text).await?;
+        self.committed(true, id, message)
+    }
+
+    async fn toggle_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.refresh()?;
+        let changed = self.inner.toggle_task(context_key, id).await?;
+        self.committed(changed, changed, format!("Cycle status of task {} in {}", id, context_key))
+    }
+
+    async fn set_task_status(&mut self, context_key: &str, id: usize, status: TaskStatus) -> Result<bool> {
+        self.refresh()?;
+        let message = format!("Set task {} in {} to {:?}", id, context_key, status);
+        let changed = self.inner.set_task_status(context_key, id, status).await?;
+        self.committed(changed, changed, message)
+    }
+
+    async fn remove_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.refresh()?;
+        let changed = self.inner.remove_task(context_key, id).await?;
+        self.committed(changed, changed, format!("Delete task {} in {}", id, context_key))
+    }
+
+    async fn edit_task(&mut self, context_key: &str, id: usize, new_text: String) -> Result<bool> {
+        self.refresh()?;
+        let changed = self.inner.edit_task(context_key, id, new_text).await?;
+        self.committed(changed, changed, format!("Edit task {} in {}", id, context_key))
+    }
+
+    async fn update_task(&mut self, context_key: &str, task: Task) -> Result<bool> {
+        self.refresh()?;
+        let message = format!("Update task {} in {}", task.id, context_key);
+        let changed = self.inner.update_task(context_key, task).await?;
+        self.committed(changed, changed, message)
+    }
+
+    async fn undo_delete(&mut self, context_key: &str) -> Result<Option<Task>> {
+        self.refresh()?;
+        let restored = self.inner.undo_delete(context_key).await?;
+        self.committed(restored.is_some(), restored, format!("Undo delete in {}", context_key))
+    }
+
+    async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>> {
+        self.inner.list_deleted(context_key).await
+    }
+
+    async fn restore_deleted(&mut self, context_key: &str, id: usize) -> Result<Option<Task>> {
+        self.refresh()?;
+        let restored = self.inner.restore_deleted(context_key, id).await?;
+        self.committed(restored.is_some(), restored, format!("Restore task {} in {}", id, context_key))
+    }
+
+    async fn purge_deleted(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.refresh()?;
+        let changed = self.inner.purge_deleted(context_key, id).await?;
+        self.committed(changed, changed, format!("Purge task {} in {}", id, context_key))
+    }
+
+    async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>> {
+        self.inner.list_tombstones(context_key).await
+    }
+
+    async fn move_task_up(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.refresh()?;
+        let changed = self.inner.move_task_up(context_key, id).await?;
+        self.committed(changed, changed, format!("Move task {} up in {}", id, context_key))
+    }
+
+    async fn move_task_down(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.refresh()?;
+        let changed = self.inner.move_task_down(context_key, id).await?;
+        self.committed(changed, changed, format!("Move task {} down in {}", id, context_key))
+    }
+
+    async fn delete_context(&mut self, context_key: &str) -> Result<usize> {
+        self.refresh()?;
+        let deleted = self.inner.delete_context(context_key).await?;
+        self.committed(deleted > 0, deleted, format!("Delete {} task(s) in {}", deleted, context_key))
+    }
+
+    async fn move_context_tasks(&mut self, from: &str, to: &str) -> Result<usize> {
+        self.refresh()?;
+        let moved = self.inner.move_context_tasks(from, to).await?;
+        self.committed(moved > 0, moved, format!("Move {} task(s) from {} to {}", moved, from, to))
+    }
+
+    async fn move_tasks(&mut self, from: &str, ids: &[usize], to: &str) -> Result<usize> {
+        self.refresh()?;
+        let moved = self.inner.move_tasks(from, ids, to).await?;
+        self.committed(moved > 0, moved, format!("Move {} task(s) from {} to {}", moved, from, to))
+    }
+
+    async fn health(&self) -> Result<BackendHealth> {
+        let repo = self.repo()?;
+        let mut health = BackendHealth::new("Git branch");
+        health.detail("Repository", repo.workdir().unwrap_or(repo.path()).display().to_string());
+        health.detail("Branch", self.branch.clone());
+        match self.tip(&repo)? {
+            Some(tip) => {
+                let time = chrono::DateTime::from_timestamp(tip.time().seconds(), 0).unwrap_or_default();
+                let time: chrono::DateTime<chrono::Local> = time.into();
+                health.detail("Last commit", format!("{} at {}", &tip.id().to_string()[..7], time.format("%Y-%m-%d %H:%M:%S")));
+                let remote_ref = format!("refs/remotes/{}/{}", self.remote, self.branch);
+                match repo.refname_to_id(&remote_ref) {
+                    Ok(remote) => {
+                        let (ahead, behind) = repo.graph_ahead_behind(tip.id(), remote)?;
+                        health.detail("Remote", format!("{}/{}: {} ahead, {} behind", self.remote, self.branch, ahead, behind));
+                    }
+                    Err(_) => health.warnings.push(format!(
+                        "The branch was never pushed to {}; run `quill git push` to share it",
+                        self.remote
+                    )),
+                }
+            }
+            None => health.warnings.push("The branch doesn't exist yet; it is created with the first change".to_string()),
+        }
+        health.set_contexts(self.inner.context_counts());
+        Ok(health)
+    }
+
+    async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String> {
+        let tasks = self.inner.get_tasks(context_key).await?;
+        let file = ContextFile {
+            schema_version: SCHEMA_VERSION,
+            context: context_key.to_string(),
+            tasks: tasks.clone(),
+            deleted: self.inner.list_deleted(context_key).await?.into(),
+            tombstones: self.inner.list_tombstones(context_key).await?,
+        };
+        Ok(format!(
+            "// task {} in {}\n{}\n\n// contexts/{}.json on branch {} ({})\n{}",
+            id,
+            context_key,
+            serde_json::to_string_pretty(&tasks.iter().find(|t| t.id == id))?,
+            file_name(context_key),
+            self.branch,
+            self.head.map_or("no commits yet".to_string(), |oid| oid.to_string()),
+            serde_json::to_string_pretty(&file)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn repo_with_commit() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_committed_to_the_branch() {
+        let dir = repo_with_commit();
+        let config = GitStorageConfig::default();
+        let mut storage = GitBranchStorage::open(dir.path(), &config).unwrap();
+        let context = "acme:web:feature/login";
+
+        let id = storage.add_task(context, "Write tests".to_string()).await.unwrap();
+        storage.set_task_status(context, id, TaskStatus::Completed).await.unwrap();
+        storage.add_task(context, "Ship it".to_string()).await.unwrap();
+        // No-ops don't create commits
+        assert!(!storage.move_task_up(context, id).await.unwrap());
+
+        let repo = Repository::open(dir.path()).unwrap();
+        let tip = repo.find_reference("refs/heads/quill-tasks").unwrap().peel_to_commit().unwrap();
+        assert_eq!(tip.parent_count(), 1);
+        assert_eq!(tip.message(), Some("Add task in acme:web:feature/login: Ship it"));
+        assert!(tip.tree().unwrap().get_path(Path::new("contexts/acme%3Aweb%3Afeature%2Flogin.json")).is_ok());
+        // The checked-out branch is untouched
+        assert_eq!(repo.head().unwrap().peel_to_commit().unwrap().message(), Some("init"));
+
+        // A fresh open reads everything back from the branch
+        let reopened = GitBranchStorage::open(dir.path(), &config).unwrap();
+        let tasks = reopened.get_tasks(context).await.unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].status, TaskStatus::Completed);
+        assert_eq!(reopened.inner.next_id, 3);
+
+        let health = reopened.health().await.unwrap();
+        assert_eq!(health.contexts, vec![(context.to_string(), 2)]);
+        assert!(health.warnings[0].contains("never pushed"));
+    }
+
+    #[tokio::test]
+    async fn test_picks_up_commits_made_elsewhere_before_writing() {
+        let dir = repo_with_commit();
+        let config = GitStorageConfig::default();
+        let mut first = GitBranchStorage::open(dir.path(), &config).unwrap();
+        let mut second = GitBranchStorage::open(dir.path(), &config).unwrap();
+
+        first.add_task("a:b:c", "From first".to_string()).await.unwrap();
+        second.add_task("a:b:c", "From second".to_string()).await.unwrap();
+
+        let texts: Vec<String> = second.get_tasks("a:b:c").await.unwrap().into_iter().map(|t| t.text).collect();
+        assert_eq!(texts, vec!["From first", "From second"]);
+    }
+
+    #[test]
+    fn test_option_like_names_are_refused() {
+        let dir = repo_with_commit();
+        for (branch, remote) in [("x/y", "--upload-pack=touch pwned"), ("--force", "origin"), ("a..b", "origin"), ("quill-tasks", "")] {
+            let config = GitStorageConfig { branch: branch.to_string(), remote: remote.to_string() };
+            assert!(matches!(pull(dir.path(), &config), Err(QuillError::Validation(_))), "{} {}", branch, remote);
+            assert!(matches!(push(dir.path(), &config), Err(QuillError::Validation(_))));
+        }
+        assert!(!dir.path().join("pwned").exists());
+    }
+
+    #[test]
+    fn test_file_names_are_path_safe() {
+        assert_eq!(file_name("acme:web:*"), "acme%3Aweb%3A%2A");
+        assert_eq!(file_name("a:b:fix/ü"), "a%3Ab%3Afix%2F%C3%BC");
+    }
+}
@@ -0,0 +1,144 @@
+use super::{TaskStatus, TaskStorage};
+use crate::{QuillError, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file written by the `TodoStorage` of releases before `LocalTaskStorage`:
+/// todos by context key, each with either a `completed` flag or a `status`.
+#[derive(Debug, Deserialize)]
+struct LegacyStorage {
+    #[serde(alias = "contexts")]
+    todos: HashMap<String, Vec<LegacyTodo>>,
+    /// Only present in the current format, which must not be imported twice.
+    storage_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LegacyTodo {
+    text: String,
+    #[serde(default)]
+    completed: bool,
+    #[serde(default)]
+    status: Option<TaskStatus>,
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+/// Result of importing a legacy file.
+#[derive(Debug, PartialEq)]
+pub struct Migration {
+    pub tasks: usize,
+    pub contexts: usize,
+    pub archived_to: PathBuf,
+}
+
+/// `~/.quill/todos.json`, where the legacy storage kept its todos.
+pub fn legacy_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".quill").join("todos.json"))
+}
+
+/// Imports the todos of a legacy file into `storage`, keeping contexts,
+/// statuses and creation times, then renames the file to `todos.json.migrated`.
+/// Returns `None` when there is nothing to migrate.
+pub async fn migrate(path: &Path, storage: &mut dyn TaskStorage) -> Result<Option<Migration>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    let legacy: LegacyStorage = serde_json::from_str(&content)
+        .map_err(|e| QuillError::Corrupt(format!("{} is not in a format quill can migrate: {}", path.display(), e)))?;
+    if legacy.storage_path.is_some() {
+        return Ok(None);
+    }
+
+    let mut context_keys: Vec<&String> = legacy.todos.keys().collect();
+    context_keys.sort();
+    let mut migrated = 0;
+    for context_key in &context_keys {
+        for todo in &legacy.todos[*context_key] {
+            let id = storage.add_task(context_key, todo.text.clone()).await?;
+            let Some(mut task) = storage.get_tasks(context_key).await?.into_iter().find(|t| t.id == id) else {
+                continue;
+            };
+            let status = match (&todo.status, todo.completed) {
+                (Some(status), _) => status.clone(),
+                (None, true) => TaskStatus::Completed,
+                (None, false) => TaskStatus::NotStarted,
+            };
+            task.set_status(status);
+            if let Some(created_at) = &todo.created_at {
+                task.created_at = created_at.clone();
+            }
+            storage.update_task(context_key, task).await?;
+            migrated += 1;
+        }
+    }
+
+    let archived_to = path.with_extension("json.migrated");
+    fs::rename(path, &archived_to)?;
+    Ok(Some(Migration { tasks: migrated, contexts: context_keys.len(), archived_to }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::local::LocalTaskStorage;
+    use tempfile::TempDir;
+
+    fn storage_in(dir: &TempDir) -> LocalTaskStorage {
+        LocalTaskStorage::new(dir.path().join("storage").join("todos.json").to_string_lossy().to_string()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_migrates_contexts_and_statuses_then_archives() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todos.json");
+        fs::write(&path, r#"{
+            "todos": {
+                "acme:web:main": [
+                    { "id": 1, "text": "Open", "completed": false, "created_at": "2023-05-01T10:00:00Z" },
+                    { "id": 2, "text": "Done", "completed": true }
+                ],
+                "acme:api:dev": [
+                    { "id": 3, "text": "Started", "status": "InProgress" }
+                ]
+            },
+            "next_id": 4
+        }"#).unwrap();
+
+        let mut storage = storage_in(&dir);
+        let migration = migrate(&path, &mut storage).await.unwrap().unwrap();
+        assert_eq!(migration.tasks, 3);
+        assert_eq!(migration.contexts, 2);
+        assert!(!path.exists());
+        assert!(migration.archived_to.exists());
+
+        let web = storage.get_tasks("acme:web:main").await.unwrap();
+        assert_eq!(web[0].text, "Open");
+        assert_eq!(web[0].created_at, "2023-05-01T10:00:00Z");
+        assert_eq!(web[1].status, TaskStatus::Completed);
+        assert!(web[1].completed_at.is_some());
+        let api = storage.get_tasks("acme:api:dev").await.unwrap();
+        assert_eq!(api[0].status, TaskStatus::InProgress);
+
+        // Nothing left to migrate on the next start
+        assert!(migrate(&path, &mut storage).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_skips_current_format_and_keeps_unreadable_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todos.json");
+        let mut storage = storage_in(&dir);
+
+        fs::write(&path, r#"{ "contexts": {}, "next_id": 1, "storage_path": "todos.json" }"#).unwrap();
+        assert!(migrate(&path, &mut storage).await.unwrap().is_none());
+        assert!(path.exists());
+
+        fs::write(&path, "not json").unwrap();
+        assert!(migrate(&path, &mut storage).await.is_err());
+        assert!(path.exists());
+    }
+}
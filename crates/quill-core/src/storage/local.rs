@@ -0,0 +1,1802 @@
+use super::activity::{ActivityEntry, ACTIVITY_LIMIT};
+use super::schema::{self, SCHEMA_VERSION};
+use super::search::SearchIndex;
+use super::tracked::SaveTracker;
+use super::{
+    compact_tombstones, next_order, BackendHealth, DeletedTask, ExternalChange, Task, TaskStatus, TaskStorage, Tombstone,
+    DEFAULT_TRASH_RETENTION,
+};
+use crate::{QuillError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// Size at which `activity.jsonl` is moved aside to `activity.1.jsonl`,
+/// replacing the previous one, so the log stays under twice this.
+const ACTIVITY_ROTATE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Tasks kept in JSON files: an index at the configured path and one file
+/// per context next to it, read the first time the context is used.
+#[derive(Debug)]
+pub struct LocalTaskStorage {
+    /// The contexts read so far.
+    pub contexts: HashMap<String, Vec<Task>>,
+    pub next_id: usize,
+    pub deleted_tasks: HashMap<String, VecDeque<DeletedTask>>,
+    /// Tasks that left the trash, per context.
+    pub tombstones: HashMap<String, Vec<Tombstone>>,
+    storage_path: PathBuf,
+    trash_retention: usize,
+    /// Never written to disk; for backends that persist the data themselves.
+    in_memory: bool,
+    /// The activity log of an in-memory storage; files append to `activity.jsonl`.
+    activity: Vec<ActivityEntry>,
+    /// Every context on disk, from the index.
+    files: BTreeMap<String, IndexEntry>,
+    revision: u64,
+    /// Contexts read for `get_tasks` and friends before any change to them.
+    read_cache: Mutex<HashMap<String, ContextFile>>,
+    /// Contexts indexed for `search_tasks`, kept up to date as they change.
+    search_index: Mutex<SearchIndex>,
+    file: Arc<Mutex<FileState>>,
+    /// Set by `with_debounced_saves`.
+    saver: Option<BackgroundSaver>,
+}
+
+/// The file at the configured path: where each context's tasks are.
+#[derive(Debug, Serialize, Deserialize)]
+struct Index {
+    #[serde(default)]
+    schema_version: u32,
+    next_id: usize,
+    /// Bumped on every save, so other instances notice any change.
+    #[serde(default)]
+    revision: u64,
+    files: BTreeMap<String, IndexEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    file: String,
+    /// Open and completed tasks, for listing contexts without reading them.
+    tasks: usize,
+    #[serde(default)]
+    deleted: usize,
+}
+
+/// One context's tasks and trash.
+#[derive(Debug, Serialize, Deserialize)]
+struct ContextFile {
+    #[serde(default)]
+    schema_version: u32,
+    context: String,
+    tasks: Vec<Task>,
+    #[serde(default)]
+    deleted: VecDeque<DeletedTask>,
+    #[serde(default)]
+    tombstones: Vec<Tombstone>,
+}
+
+/// Releases before the split kept every context in the configured file.
+#[derive(Debug, Deserialize)]
+struct SingleFile {
+    contexts: HashMap<String, Vec<Task>>,
+    next_id: usize,
+    #[serde(default)]
+    deleted_tasks: HashMap<String, VecDeque<DeletedTask>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum StoredFile {
+    Index(Index),
+    SingleFile(SingleFile),
+}
+
+/// Checks that local storage can be kept at `path`: it isn't a directory,
+/// it can be written if it exists, and otherwise its nearest existing
+/// ancestor is a writable directory (missing ones are created on save).
+pub fn check_path(path: &Path) -> Result<()> {
+    if path.as_os_str().is_empty() {
+        return Err(QuillError::Validation("the path is empty".to_string()));
+    }
+    if path.is_dir() {
+        return Err(QuillError::Validation(format!("{} is a directory; give a file such as todos.json", path.display())));
+    }
+    if path.exists() {
+        return fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map(|_| ())
+            .map_err(|e| QuillError::PermissionDenied(format!("{} can't be written: {}", path.display(), e)));
+    }
+    let Some(dir) = path.ancestors().skip(1).find(|dir| dir.exists() || dir.as_os_str().is_empty()) else {
+        return Err(QuillError::Validation(format!("{} has no existing parent directory", path.display())));
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    if !dir.is_dir() {
+        return Err(QuillError::Validation(format!("{} is not a directory", dir.display())));
+    }
+    // Creating a file is the only reliable test, whatever the platform's permission model
+    let probe = dir.join(format!(".quill-write-test-{}", std::process::id()));
+    fs::write(&probe, b"").map_err(|e| QuillError::PermissionDenied(format!("{} is not writable: {}", dir.display(), e)))?;
+    let _ = fs::remove_file(probe);
+    Ok(())
+}
+
+/// The file a context's tasks are kept in, named after a hash of its key
+/// that stays the same across builds.
+fn context_file_name(context_key: &str) -> String {
+    let hash = context_key
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}.json", hash)
+}
+
+/// Files to write, with the index last so it never names a missing file.
+type Snapshot = Vec<(PathBuf, String)>;
+
+/// What is known about the files on disk, shared with the background saver.
+#[derive(Debug, Default)]
+struct FileState {
+    /// Hash of the index as last read or written here, to tell other
+    /// writers' changes from our own.
+    synced: Option<u64>,
+    /// The last snapshot handed to the background saver.
+    queued: u64,
+    /// The last snapshot the background saver wrote.
+    written: u64,
+    /// Snapshots up to this one were taken before a reload and are dropped.
+    discarded: u64,
+    /// Set when a change couldn't be written, so the files lag behind.
+    failed: bool,
+    /// Why a background write failed, until a save or flush reports it.
+    error: Option<String>,
+    /// Told when background writes start and finish, from `track_saves`.
+    tracker: SaveTracker,
+    /// Whether `tracker` was told of queued writes that aren't written yet.
+    tracking: bool,
+}
+
+impl FileState {
+    fn dirty(&self) -> bool {
+        self.failed || self.written < self.queued
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn content_hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Writes `snapshot` in order, returning the hash of the index. Each file
+/// is written to a temporary file beside it and renamed into place, so a
+/// crash part-way leaves either the old or the new content.
+fn write_files(snapshot: &Snapshot) -> io::Result<Option<u64>> {
+    for (path, content) in snapshot {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, content.as_bytes())?;
+    }
+    Ok(snapshot.last().map(|(_, index)| content_hash(index.as_bytes())))
+}
+
+fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Writes snapshots of the storage on a background task, coalescing the
+/// changes made within a short delay into one write.
+#[derive(Debug)]
+struct BackgroundSaver {
+    requests: mpsc::UnboundedSender<SaveRequest>,
+}
+
+#[derive(Debug)]
+enum SaveRequest {
+    Write(u64, Snapshot),
+    Flush(oneshot::Sender<()>),
+}
+
+impl BackgroundSaver {
+    fn spawn(path: PathBuf, delay: Duration, file: Arc<Mutex<FileState>>) -> Self {
+        let (requests, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run_saver(path, delay, file, receiver));
+        Self { requests }
+    }
+}
+
+/// Writes the latest snapshots `delay` after the first unwritten change,
+/// when flushed, and once more when the storage is dropped.
+async fn run_saver(path: PathBuf, delay: Duration, file: Arc<Mutex<FileState>>, mut requests: mpsc::UnboundedReceiver<SaveRequest>) {
+    let mut pending: Option<(u64, Snapshot)> = None;
+    let mut deadline = Instant::now();
+    loop {
+        let request = if pending.is_some() {
+            match tokio::time::timeout_at(deadline, requests.recv()).await {
+                Ok(request) => request,
+                Err(_) => {
+                    write_snapshot(&path, pending.take(), &file).await;
+                    continue;
+                }
+            }
+        } else {
+            requests.recv().await
+        };
+        match request {
+            Some(SaveRequest::Write(generation, snapshot)) => {
+                let mut files = match pending.take() {
+                    Some((_, files)) => files,
+                    None => {
+                        deadline = Instant::now() + delay;
+                        Vec::new()
+                    }
+                };
+                // Newer contents replace older ones, and the index stays last
+                files.retain(|(path, _)| !snapshot.iter().any(|(newer, _)| newer == path));
+                files.extend(snapshot);
+                pending = Some((generation, files));
+            }
+            Some(SaveRequest::Flush(done)) => {
+                write_snapshot(&path, pending.take(), &file).await;
+                let _ = done.send(());
+            }
+            None => {
+                write_snapshot(&path, pending.take(), &file).await;
+                return;
+            }
+        }
+    }
+}
+
+async fn write_snapshot(path: &Path, pending: Option<(u64, Snapshot)>, file: &Mutex<FileState>) {
+    let Some((generation, snapshot)) = pending else {
+        return;
+    };
+    if generation <= lock(file).discarded {
+        settle(&mut lock(file), generation, None);
+        return;
+    }
+    let result = tokio::task::spawn_blocking(move || write_files(&snapshot))
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)));
+
+    let mut file = lock(file);
+    match result {
+        Ok(hash) => {
+            file.synced = hash;
+            file.written = file.written.max(generation);
+            file.failed = false;
+            tracing::debug!(path = %path.display(), "saved local storage");
+            settle(&mut file, generation, None);
+        }
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "couldn't save local storage");
+            file.failed = true;
+            file.error = Some(e.to_string());
+            settle(&mut file, generation, Some(e.to_string()));
+        }
+    }
+}
+
+/// Tells the tracker the background writes are done once the last one
+/// queued is, or shows a failure right away while more are queued.
+fn settle(file: &mut FileState, generation: u64, error: Option<String>) {
+    if !file.tracking {
+        return;
+    }
+    if generation >= file.queued {
+        file.tracking = false;
+        file.tracker.finish_with(error);
+    } else if let Some(error) = error {
+        file.tracker.fail(error);
+    }
+}
+
+impl LocalTaskStorage {
+    pub fn new(path: String) -> Result<Self> {
+        let storage_path = if path.starts_with("~/") {
+            let home = dirs::home_dir()
+                .ok_or_else(|| QuillError::StorageUnavailable("Could not find home directory".to_string()))?;
+            PathBuf::from(path.replacen("~", &home.to_string_lossy(), 1))
+        } else {
+            PathBuf::from(path)
+        };
+
+        let mut storage = Self {
+            storage_path,
+            ..Self::in_memory()
+        };
+        storage.in_memory = false;
+
+        storage.load()?;
+        Ok(storage)
+    }
+
+    /// An empty storage that keeps its tasks in memory only.
+    pub fn in_memory() -> Self {
+        Self {
+            contexts: HashMap::new(),
+            next_id: 1,
+            deleted_tasks: HashMap::new(),
+            tombstones: HashMap::new(),
+            storage_path: PathBuf::new(),
+            trash_retention: DEFAULT_TRASH_RETENTION,
+            in_memory: true,
+            activity: Vec::new(),
+            files: BTreeMap::new(),
+            revision: 0,
+            read_cache: Mutex::default(),
+            search_index: Mutex::default(),
+            file: Arc::default(),
+            saver: None,
+        }
+    }
+
+    /// Sets how many deleted tasks are kept per context.
+    pub fn with_trash_retention(mut self, retention: usize) -> Self {
+        self.trash_retention = retention;
+        self
+    }
+
+    /// Writes changes on a background task, `delay` after the first unsaved
+    /// one, instead of on every change. `flush` writes them right away.
+    /// Must be called within a tokio runtime.
+    pub fn with_debounced_saves(mut self, delay: Duration) -> Self {
+        if !self.in_memory {
+            self.saver = Some(BackgroundSaver::spawn(self.storage_path.clone(), delay, self.file.clone()));
+        }
+        self
+    }
+
+    /// `activity.jsonl` next to the index.
+    fn activity_path(&self) -> PathBuf {
+        self.storage_path.with_file_name("activity.jsonl")
+    }
+
+    /// The older half of the activity log.
+    fn rotated_activity_path(&self) -> PathBuf {
+        self.storage_path.with_file_name("activity.1.jsonl")
+    }
+
+    /// Copies of the index next to it, such as `todos.json.bak` or
+    /// `todos.json.migrated`.
+    fn backups(&self) -> Vec<PathBuf> {
+        let (Some(dir), Some(name)) = (self.storage_path.parent(), self.storage_path.file_name()) else {
+            return Vec::new();
+        };
+        let prefix = format!("{}.", name.to_string_lossy());
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name().is_some_and(|n| n.to_string_lossy().starts_with(&prefix)))
+            .collect()
+    }
+
+    fn context_path(&self, entry: &IndexEntry) -> PathBuf {
+        self.storage_path.with_file_name(&entry.file)
+    }
+
+    /// Task counts per context, for `health` reports.
+    pub(crate) fn context_counts(&self) -> Vec<(String, usize)> {
+        let unread = self.files.iter().filter(|(key, _)| !self.contexts.contains_key(*key));
+        unread
+            .map(|(key, entry)| (key.clone(), entry.tasks))
+            .chain(self.contexts.iter().map(|(key, tasks)| (key.clone(), tasks.len())))
+            .collect()
+    }
+
+    fn trash_count(&self) -> usize {
+        let unread = self.files.iter().filter(|(key, _)| !self.contexts.contains_key(*key));
+        unread.map(|(_, entry)| entry.deleted).sum::<usize>() + self.deleted_tasks.values().map(|d| d.len()).sum::<usize>()
+    }
+
+    fn read_context(&self, entry: &IndexEntry) -> Result<ContextFile> {
+        let path = self.context_path(entry);
+        let mut value = serde_json::from_slice(&fs::read(&path)?)?;
+        schema::upgrade_file(&mut value, &path.display().to_string())?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Reads a context's file into `contexts` before it is changed.
+    fn load_context(&mut self, context_key: &str) -> Result<()> {
+        if self.contexts.contains_key(context_key) {
+            return Ok(());
+        }
+        let Some(entry) = self.files.get(context_key) else {
+            return Ok(());
+        };
+        let data = match self.read_cache.get_mut().unwrap_or_else(|e| e.into_inner()).remove(context_key) {
+            Some(data) => data,
+            None => self.read_context(entry)?,
+        };
+        self.contexts.insert(context_key.to_string(), data.tasks);
+        self.deleted_tasks.insert(context_key.to_string(), data.deleted);
+        self.tombstones.insert(context_key.to_string(), data.tombstones);
+        Ok(())
+    }
+
+    /// Runs `f` on a context's tasks, trash and tombstones, reading its file
+    /// if needed.
+    fn with_context<T>(
+        &self,
+        context_key: &str,
+        f: impl FnOnce(&[Task], Option<&VecDeque<DeletedTask>>, &[Tombstone]) -> T,
+    ) -> Result<T> {
+        match self.files.get(context_key) {
+            Some(entry) if !self.contexts.contains_key(context_key) => {
+                let mut cache = lock(&self.read_cache);
+                if !cache.contains_key(context_key) {
+                    cache.insert(context_key.to_string(), self.read_context(entry)?);
+                }
+                let data = &cache[context_key];
+                Ok(f(&data.tasks, Some(&data.deleted), &data.tombstones))
+            }
+            _ => Ok(f(
+                self.contexts.get(context_key).map(Vec::as_slice).unwrap_or_default(),
+                self.deleted_tasks.get(context_key),
+                self.tombstones.get(context_key).map(Vec::as_slice).unwrap_or_default(),
+            )),
+        }
+    }
+
+    fn load(&mut self) -> Result<()> {
+        if self.storage_path.exists() {
+            let content = fs::read(&self.storage_path)?;
+            let value: Value = serde_json::from_slice(&content)?;
+            let outdated = schema::version_of(&value) < SCHEMA_VERSION;
+            let stored = self.parse_index(value)?;
+            // A single file is split, which writes everything anew
+            let split = matches!(stored, StoredFile::SingleFile(_));
+            self.apply(stored, &content)?;
+            if outdated && !split {
+                self.persist_upgrade()?;
+            }
+        }
+        tracing::debug!(path = %self.storage_path.display(), contexts = self.files.len(), "loaded local storage");
+        Ok(())
+    }
+
+    /// Rewrites every context at the current version, so what migrations
+    /// fill in, like uids, is stored instead of derived again on each read.
+    fn persist_upgrade(&mut self) -> Result<()> {
+        let keys: Vec<String> = self.files.keys().cloned().collect();
+        for key in &keys {
+            self.load_context(key)?;
+        }
+        self.save_contexts(&keys)?;
+        tracing::info!(path = %self.storage_path.display(), contexts = keys.len(), version = SCHEMA_VERSION, "upgraded local storage");
+        Ok(())
+    }
+
+    /// Adds `tombstones` to the context's and drops the tasks they name,
+    /// from the list and the trash.
+    fn bury(&mut self, context_key: &str, tombstones: Vec<Tombstone>) {
+        let known = self.tombstones.entry(context_key.to_string()).or_default();
+        for tombstone in tombstones {
+            if !known.iter().any(|t| t.uid == tombstone.uid) {
+                known.push(tombstone);
+            }
+        }
+        let buried: Vec<Uuid> = known.iter().map(|t| t.uid).collect();
+        if let Some(tasks) = self.contexts.get_mut(context_key) {
+            tasks.retain(|t| !buried.contains(&t.uid));
+        }
+        if let Some(deleted) = self.deleted_tasks.get_mut(context_key) {
+            deleted.retain(|d| !buried.contains(&d.task.uid));
+        }
+    }
+
+    fn parse_index(&self, mut value: Value) -> Result<StoredFile> {
+        schema::upgrade_file(&mut value, &self.storage_path.display().to_string())?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Takes the tasks of `stored`, read from the index as `content`.
+    fn apply(&mut self, stored: StoredFile, content: &[u8]) -> Result<()> {
+        self.contexts.clear();
+        self.deleted_tasks.clear();
+        self.tombstones.clear();
+        self.read_cache.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+        self.search_index.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+        {
+            let mut file = lock(&self.file);
+            file.synced = Some(content_hash(content));
+            // Snapshots still waiting to be written would undo the reload
+            file.discarded = file.queued;
+            file.written = file.queued;
+            file.failed = false;
+            file.error = None;
+        }
+        match stored {
+            StoredFile::Index(index) => {
+                self.next_id = index.next_id;
+                self.revision = index.revision;
+                self.files = index.files;
+                Ok(())
+            }
+            StoredFile::SingleFile(data) => self.split(data),
+        }
+    }
+
+    /// Moves the tasks of a single-file list into a file per context,
+    /// keeping the original as `todos.json.bak`.
+    fn split(&mut self, data: SingleFile) -> Result<()> {
+        let backup = self.backup_path();
+        fs::copy(&self.storage_path, &backup)?;
+        self.next_id = data.next_id;
+        self.files.clear();
+        self.contexts = data.contexts;
+        self.deleted_tasks = data.deleted_tasks;
+        let keys: Vec<String> = self.contexts.keys().chain(self.deleted_tasks.keys()).cloned().collect();
+        for key in &keys {
+            self.contexts.entry(key.clone()).or_default();
+            self.deleted_tasks.entry(key.clone()).or_default();
+        }
+        let snapshot = self.snapshot(&keys)?;
+        lock(&self.file).synced = write_files(&snapshot)?;
+        tracing::info!(path = %self.storage_path.display(), contexts = keys.len(), backup = %backup.display(), "split local storage into a file per context");
+        Ok(())
+    }
+
+    fn backup_path(&self) -> PathBuf {
+        let mut name = self.storage_path.as_os_str().to_owned();
+        name.push(".bak");
+        PathBuf::from(name)
+    }
+
+    /// The files for `context_keys` and the index, updating the index.
+    fn snapshot(&mut self, context_keys: &[String]) -> Result<Snapshot> {
+        let mut snapshot = Vec::new();
+        for key in context_keys {
+            if let Some(tombstones) = self.tombstones.get_mut(key) {
+                compact_tombstones(tombstones);
+            }
+            let data = ContextFile {
+                schema_version: SCHEMA_VERSION,
+                context: key.clone(),
+                tasks: self.contexts.get(key).cloned().unwrap_or_default(),
+                deleted: self.deleted_tasks.get(key).cloned().unwrap_or_default(),
+                tombstones: self.tombstones.get(key).cloned().unwrap_or_default(),
+            };
+            if !self.files.contains_key(key) {
+                let mut file = context_file_name(key);
+                // Two keys with the same hash get numbered files
+                for n in 2.. {
+                    if !self.files.values().any(|entry| entry.file == file) {
+                        break;
+                    }
+                    file = format!("{}-{}.json", context_file_name(key).trim_end_matches(".json"), n);
+                }
+                self.files.insert(key.clone(), IndexEntry { file, tasks: 0, deleted: 0 });
+            }
+            let entry = self.files.get_mut(key).expect("inserted above");
+            entry.tasks = data.tasks.len();
+            entry.deleted = data.deleted.len();
+            let path = self.storage_path.with_file_name(&entry.file);
+            snapshot.push((path, serde_json::to_string_pretty(&data)?));
+        }
+
+        self.revision += 1;
+        let index = Index { schema_version: SCHEMA_VERSION, next_id: self.next_id, revision: self.revision, files: self.files.clone() };
+        snapshot.push((self.storage_path.clone(), serde_json::to_string_pretty(&index)?));
+        Ok(snapshot)
+    }
+
+    fn save(&mut self, context_key: &str) -> Result<()> {
+        self.save_contexts(&[context_key.to_string()])
+    }
+
+    fn save_contexts(&mut self, context_keys: &[String]) -> Result<()> {
+        let index = self.search_index.get_mut().unwrap_or_else(|e| e.into_inner());
+        for key in context_keys {
+            index.update(key, self.contexts.get(key).map(Vec::as_slice).unwrap_or_default());
+        }
+        if self.in_memory {
+            return Ok(());
+        }
+        let snapshot = self.snapshot(context_keys)?;
+        if let Some(saver) = &self.saver {
+            let mut file = lock(&self.file);
+            file.queued += 1;
+            if !file.tracking {
+                file.tracking = true;
+                file.tracker.begin(std::time::Instant::now());
+            }
+            let _ = saver.requests.send(SaveRequest::Write(file.queued, snapshot));
+            // A failed background write is reported once; the next one retries
+            return match file.error.take() {
+                Some(e) => Err(io::Error::other(e).into()),
+                None => Ok(()),
+            };
+        }
+        let result = write_files(&snapshot);
+        let mut file = lock(&self.file);
+        file.failed = result.is_err();
+        file.synced = result?;
+        tracing::debug!(path = %self.storage_path.display(), "saved local storage");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TaskStorage for LocalTaskStorage {
+    async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>> {
+        self.with_context(context_key, |tasks, _, _| tasks.to_vec())
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<String>> {
+        let mut keys: Vec<String> = self
+            .context_counts()
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn search_tasks(&self, query: &str) -> Result<BTreeMap<String, Vec<Task>>> {
+        let contexts = self.list_contexts().await?;
+        let missing: Vec<String> = {
+            let index = lock(&self.search_index);
+            contexts.into_iter().filter(|key| !index.contains(key)).collect()
+        };
+        for key in missing {
+            let tasks = self.with_context(&key, |tasks, _, _| tasks.to_vec())?;
+            lock(&self.search_index).update(&key, &tasks);
+        }
+        Ok(lock(&self.search_index).search(query))
+    }
+
+    async fn add_task(&mut self, context_key: &str, text: String) -> Result<usize> {
+        self.load_context(context_key)?;
+        let mut task = Task::new(self.next_id, text);
+        let id = task.id;
+        
+        let tasks = self.contexts.entry(context_key.to_string()).or_default();
+        task.order = next_order(tasks);
+        tasks.push(task);
+        
+        self.next_id += 1;
+        self.save(context_key)?;
+        Ok(id)
+    }
+
+    async fn toggle_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.load_context(context_key)?;
+        if let Some(tasks) = self.contexts.get_mut(context_key) {
+            if let Some(task) = tasks.iter_mut().find(|t| t.id == id) {
+                task.set_status(task.status.next());
+                self.save(context_key)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn set_task_status(&mut self, context_key: &str, id: usize, status: TaskStatus) -> Result<bool> {
+        self.load_context(context_key)?;
+        if let Some(tasks) = self.contexts.get_mut(context_key) {
+            if let Some(task) = tasks.iter_mut().find(|t| t.id == id) {
+                task.set_status(status);
+                self.save(context_key)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn remove_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.load_context(context_key)?;
+        if let Some(tasks) = self.contexts.get_mut(context_key) {
+            if let Some(pos) = tasks.iter().position(|t| t.id == id) {
+                let removed_task = tasks.remove(pos);
+                
+                // Store the deleted task for undo and the trash view
+                let deleted_deque = self.deleted_tasks
+                    .entry(context_key.to_string())
+                    .or_default();
+                
+                deleted_deque.push_front(DeletedTask::new(removed_task));
+                
+                // Keep only the configured number of deleted tasks
+                while deleted_deque.len() > self.trash_retention {
+                    if let Some(evicted) = deleted_deque.pop_back() {
+                        self.tombstones.entry(context_key.to_string()).or_default().push(Tombstone::new(evicted.task.uid));
+                    }
+                }
+                
+                self.save(context_key)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn edit_task(&mut self, context_key: &str, id: usize, new_text: String) -> Result<bool> {
+        self.load_context(context_key)?;
+        if let Some(tasks) = self.contexts.get_mut(context_key) {
+            if let Some(task) = tasks.iter_mut().find(|t| t.id == id) {
+                task.text = new_text;
+                self.save(context_key)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn update_task(&mut self, context_key: &str, task: Task) -> Result<bool> {
+        self.load_context(context_key)?;
+        if let Some(tasks) = self.contexts.get_mut(context_key) {
+            if let Some(existing) = tasks.iter_mut().find(|t| t.uid == task.uid) {
+                *existing = task;
+                tasks.sort_by(|a, b| a.order.total_cmp(&b.order));
+                self.save(context_key)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn undo_delete(&mut self, context_key: &str) -> Result<Option<Task>> {
+        self.load_context(context_key)?;
+        if let Some(deleted_deque) = self.deleted_tasks.get_mut(context_key) {
+            if let Some(DeletedTask { mut task, .. }) = deleted_deque.pop_front() {
+                // Restore the task to the end of the context
+                let tasks = self.contexts.entry(context_key.to_string()).or_default();
+                task.order = next_order(tasks);
+                tasks.push(task.clone());
+                
+                self.save(context_key)?;
+                return Ok(Some(task));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>> {
+        self.with_context(context_key, |_, deleted, _| deleted.map(|d| d.iter().cloned().collect()).unwrap_or_default())
+    }
+
+    async fn restore_deleted(&mut self, context_key: &str, id: usize) -> Result<Option<Task>> {
+        self.load_context(context_key)?;
+        if let Some(deleted_deque) = self.deleted_tasks.get_mut(context_key) {
+            if let Some(pos) = deleted_deque.iter().position(|d| d.task.id == id) {
+                let mut task = deleted_deque.remove(pos).map(|d| d.task);
+                if let Some(task) = &mut task {
+                    let tasks = self.contexts.entry(context_key.to_string()).or_default();
+                    task.order = next_order(tasks);
+                    tasks.push(task.clone());
+                }
+                self.save(context_key)?;
+                return Ok(task);
+            }
+        }
+        Ok(None)
+    }
+
+    async fn purge_deleted(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.load_context(context_key)?;
+        if let Some(deleted_deque) = self.deleted_tasks.get_mut(context_key) {
+            if let Some(pos) = deleted_deque.iter().position(|d| d.task.id == id) {
+                if let Some(purged) = deleted_deque.remove(pos) {
+                    self.tombstones.entry(context_key.to_string()).or_default().push(Tombstone::new(purged.task.uid));
+                }
+                self.save(context_key)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>> {
+        self.with_context(context_key, |_, _, tombstones| tombstones.to_vec())
+    }
+
+    async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String> {
+        let (tasks, deleted) = self.with_context(context_key, |tasks, deleted, _| (tasks.to_vec(), deleted.cloned().unwrap_or_default()))?;
+        let task = tasks.iter().find(|t| t.id == id);
+        let context = serde_json::json!({
+            "context": context_key,
+            "tasks": tasks,
+            "deleted": deleted,
+            "next_id": self.next_id,
+        });
+        let file = self.files.get(context_key).map_or_else(|| self.storage_path.clone(), |entry| self.context_path(entry));
+
+        Ok(format!(
+            "// task {} in {}\n{}\n\n// context fragment of {}\n{}",
+            id,
+            context_key,
+            serde_json::to_string_pretty(&task)?,
+            file.display(),
+            serde_json::to_string_pretty(&context)?,
+        ))
+    }
+
+    async fn health(&self) -> Result<BackendHealth> {
+        let mut health = BackendHealth::new("Local file");
+        health.detail("Path", self.storage_path.display().to_string());
+        match fs::metadata(&self.storage_path) {
+            Ok(metadata) => {
+                let contexts = self.files.values().filter_map(|entry| fs::metadata(self.context_path(entry)).ok());
+                health.detail("Size", format_size(metadata.len() + contexts.map(|m| m.len()).sum::<u64>()));
+                if let Ok(modified) = metadata.modified() {
+                    let modified: chrono::DateTime<chrono::Local> = modified.into();
+                    health.detail("Last write", modified.format("%Y-%m-%d %H:%M:%S").to_string());
+                }
+                if metadata.permissions().readonly() {
+                    health.warnings.push("The file is read-only; changes can't be saved".to_string());
+                }
+            }
+            Err(_) => health.warnings.push("The file doesn't exist yet; it is created with the first task".to_string()),
+        }
+        health.detail("Backups", self.backups().len().to_string());
+        health.detail("Context files", self.files.len().to_string());
+        health.detail("Trash", self.trash_count().to_string());
+        health.set_contexts(self.context_counts());
+        Ok(health)
+    }
+
+    async fn record_activity(&mut self, entries: Vec<ActivityEntry>) -> Result<()> {
+        if self.in_memory {
+            self.activity.extend(entries);
+            return Ok(());
+        }
+        let mut lines = String::new();
+        for entry in &entries {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        let path = self.activity_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::metadata(&path).is_ok_and(|meta| meta.len() >= ACTIVITY_ROTATE_BYTES) {
+            fs::rename(&path, self.rotated_activity_path())?;
+        }
+        fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(lines.as_bytes())?;
+        Ok(())
+    }
+
+    async fn list_activity(&self, context_key: &str) -> Result<Vec<ActivityEntry>> {
+        if self.in_memory {
+            return Ok(self.activity.iter().rev().filter(|entry| entry.context_key == context_key).take(ACTIVITY_LIMIT).cloned().collect());
+        }
+        let mut entries = Vec::new();
+        for path in [self.activity_path(), self.rotated_activity_path()] {
+            // Newest last, so the log is read from its end until there are enough
+            read_lines_backwards(&path, |line| {
+                // A line cut short by a crash is skipped rather than failing the whole log
+                if let Ok(entry) = serde_json::from_str::<ActivityEntry>(line) {
+                    if entry.context_key == context_key {
+                        entries.push(entry);
+                    }
+                }
+                entries.len() < ACTIVITY_LIMIT
+            })?;
+            if entries.len() >= ACTIVITY_LIMIT {
+                break;
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn export_activity(&self, since: Option<&str>) -> Result<Vec<ActivityEntry>> {
+        let since = since.map(parse_time).transpose()?;
+        let after_since = |entry: &ActivityEntry| since.is_none_or(|since| parse_time(&entry.at).is_ok_and(|at| at >= since));
+        if self.in_memory {
+            return Ok(self.activity.iter().filter(|entry| after_since(entry)).cloned().collect());
+        }
+        let mut entries = Vec::new();
+        for path in [self.rotated_activity_path(), self.activity_path()] {
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            // As in `list_activity`, a line cut short by a crash is skipped
+            entries.extend(content.lines().filter_map(|line| serde_json::from_str::<ActivityEntry>(line).ok()).filter(after_since));
+        }
+        Ok(entries)
+    }
+
+    fn watch_path(&self) -> Option<PathBuf> {
+        (!self.in_memory).then(|| self.storage_path.clone())
+    }
+
+    async fn check_external_changes(&mut self) -> Result<ExternalChange> {
+        if self.in_memory {
+            return Ok(ExternalChange::None);
+        }
+        let content = match fs::read(&self.storage_path) {
+            Ok(content) => content,
+            // Editors that save by renaming leave a brief gap
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(ExternalChange::None),
+            Err(e) => return Err(e.into()),
+        };
+        if lock(&self.file).synced == Some(content_hash(&content)) {
+            return Ok(ExternalChange::None);
+        }
+        // A half-written file is picked up once its writer finishes
+        let Ok(value) = serde_json::from_slice::<Value>(&content) else {
+            return Ok(ExternalChange::None);
+        };
+        let data = self.parse_index(value)?;
+        if lock(&self.file).dirty() {
+            return Ok(ExternalChange::Conflict);
+        }
+        self.apply(data, &content)?;
+        tracing::info!(path = %self.storage_path.display(), "reloaded local storage after an external change");
+        Ok(ExternalChange::Reloaded)
+    }
+
+    async fn resolve_external_change(&mut self, keep_mine: bool) -> Result<()> {
+        if keep_mine {
+            // Contexts only the other writer changed keep its changes
+            let theirs = self.parse_index(serde_json::from_slice(&fs::read(&self.storage_path)?)?)?;
+            if let StoredFile::Index(theirs) = theirs {
+                for (key, entry) in theirs.files {
+                    if self.contexts.contains_key(&key) {
+                        // Tasks they removed for good stay removed
+                        let tombstones = self.read_context(&entry)?.tombstones;
+                        self.bury(&key, tombstones);
+                    } else {
+                        self.files.insert(key, entry);
+                    }
+                }
+                self.next_id = self.next_id.max(theirs.next_id);
+                self.read_cache.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+                self.search_index.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+            }
+            let keys: Vec<String> = self.contexts.keys().cloned().collect();
+            self.save_contexts(&keys)?;
+            self.flush().await
+        } else {
+            self.load()
+        }
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if let Some(saver) = &self.saver {
+            let (done, flushed) = oneshot::channel();
+            if saver.requests.send(SaveRequest::Flush(done)).is_ok() {
+                let _ = flushed.await;
+            }
+        }
+        match lock(&self.file).error.take() {
+            Some(e) => Err(io::Error::other(e).into()),
+            None => Ok(()),
+        }
+    }
+
+    fn track_saves(&mut self, tracker: SaveTracker) {
+        lock(&self.file).tracker = tracker;
+    }
+
+    async fn move_task_up(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.load_context(context_key)?;
+        if let Some(tasks) = self.contexts.get_mut(context_key) {
+            if let Some(pos) = tasks.iter().position(|t| t.id == id) {
+                if pos > 0 {
+                    swap_places(tasks, pos, pos - 1);
+                    self.save(context_key)?;
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    async fn move_task_down(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.load_context(context_key)?;
+        if let Some(tasks) = self.contexts.get_mut(context_key) {
+            if let Some(pos) = tasks.iter().position(|t| t.id == id) {
+                if pos < tasks.len() - 1 {
+                    swap_places(tasks, pos, pos + 1);
+                    self.save(context_key)?;
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    async fn move_context_tasks(&mut self, from: &str, to: &str) -> Result<usize> {
+        let ids: Vec<usize> = self.get_tasks(from).await?.iter().map(|t| t.id).collect();
+        self.move_tasks(from, &ids, to).await
+    }
+
+    async fn move_tasks(&mut self, from: &str, ids: &[usize], to: &str) -> Result<usize> {
+        // Moving within one context would only renumber and reorder its tasks
+        if from == to {
+            return Ok(0);
+        }
+        self.load_context(from)?;
+        self.load_context(to)?;
+        let left = self.contexts.entry(from.to_string()).or_default();
+        let (tasks, staying): (Vec<Task>, Vec<Task>) = std::mem::take(left).into_iter().partition(|t| ids.contains(&t.id));
+        *left = staying;
+        let new_ids: HashMap<usize, usize> = tasks.iter().zip(self.next_id..).map(|(task, id)| (task.id, id)).collect();
+        self.next_id += tasks.len();
+        let moved = tasks.len();
+        let target = self.contexts.entry(to.to_string()).or_default();
+        for mut task in tasks {
+            task.id = new_ids[&task.id];
+            task.parent = task.parent.and_then(|parent| new_ids.get(&parent).copied());
+            task.order = next_order(target);
+            target.push(task);
+        }
+        self.save_contexts(&[from.to_string(), to.to_string()])?;
+        Ok(moved)
+    }
+}
+
+/// Swaps two tasks along with their `order`, which stays with the place.
+fn swap_places(tasks: &mut [Task], a: usize, b: usize) {
+    let order = tasks[a].order;
+    tasks[a].order = tasks[b].order;
+    tasks[b].order = order;
+    tasks.swap(a, b);
+}
+
+fn parse_time(at: &str) -> Result<chrono::DateTime<chrono::FixedOffset>> {
+    chrono::DateTime::parse_from_rfc3339(at).map_err(|e| QuillError::Validation(format!("invalid time {}: {}", at, e)))
+}
+
+/// Hands `f` the lines of the file at `path` last to first, until it returns
+/// false. A missing file has no lines.
+fn read_lines_backwards(path: &Path, mut f: impl FnMut(&str) -> bool) -> io::Result<()> {
+    const CHUNK: u64 = 64 * 1024;
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let mut end = file.metadata()?.len();
+    // The start of a line whose beginning is in a chunk not read yet
+    let mut partial = Vec::new();
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let mut chunk = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&partial);
+        end = start;
+        let mut lines = chunk.split(|&b| b == b'\n');
+        let first = lines.next().unwrap_or_default();
+        for line in lines.rev().filter(|line| !line.is_empty()) {
+            if !f(&String::from_utf8_lossy(line)) {
+                return Ok(());
+            }
+        }
+        partial = first.to_vec();
+    }
+    if !partial.is_empty() {
+        f(&String::from_utf8_lossy(&partial));
+    }
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tracked::SaveIndicator;
+    use crate::storage::strategies::{arb_status, arb_text};
+    use proptest::prelude::*;
+    use tempfile::TempDir;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Add(String),
+        Remove(usize),
+        SetStatus(usize, TaskStatus),
+        MoveUp(usize),
+        MoveDown(usize),
+        Undo,
+    }
+
+    fn arb_op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            3 => arb_text().prop_map(Op::Add),
+            1 => any::<usize>().prop_map(Op::Remove),
+            1 => (any::<usize>(), arb_status()).prop_map(|(i, s)| Op::SetStatus(i, s)),
+            1 => any::<usize>().prop_map(Op::MoveUp),
+            1 => any::<usize>().prop_map(Op::MoveDown),
+            1 => Just(Op::Undo),
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        /// Applies random operations to the storage and to a plain Vec model,
+        /// checking ids stay unique, order matches, and a reload sees the same state.
+        #[test]
+        fn prop_operation_sequences_preserve_invariants(ops in proptest::collection::vec(arb_op(), 1..30)) {
+            let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+            let temp_dir = TempDir::new().unwrap();
+            let path = temp_dir.path().join("todos.json").to_string_lossy().to_string();
+            let context = "prop:repo:main";
+
+            runtime.block_on(async {
+                let mut storage = LocalTaskStorage::new(path.clone()).unwrap();
+                let mut model: Vec<Task> = Vec::new();
+                let mut trash: Vec<Task> = Vec::new();
+
+                for op in ops {
+                    match op {
+                        Op::Add(text) => {
+                            let id = storage.add_task(context, text.clone()).await.unwrap();
+                            prop_assert!(model.iter().all(|t| t.id != id));
+                            model.push(Task::new(id, text));
+                        }
+                        Op::Remove(i) if !model.is_empty() => {
+                            let task = model.remove(i % model.len());
+                            prop_assert!(storage.remove_task(context, task.id).await.unwrap());
+                            trash.insert(0, task);
+                            trash.truncate(DEFAULT_TRASH_RETENTION);
+                        }
+                        Op::SetStatus(i, status) if !model.is_empty() => {
+                            let i = i % model.len();
+                            prop_assert!(storage.set_task_status(context, model[i].id, status.clone()).await.unwrap());
+                            model[i].set_status(status);
+                        }
+                        Op::MoveUp(i) if !model.is_empty() => {
+                            let i = i % model.len();
+                            prop_assert_eq!(storage.move_task_up(context, model[i].id).await.unwrap(), i > 0);
+                            if i > 0 {
+                                model.swap(i, i - 1);
+                            }
+                        }
+                        Op::MoveDown(i) if !model.is_empty() => {
+                            let i = i % model.len();
+                            let movable = i + 1 < model.len();
+                            prop_assert_eq!(storage.move_task_down(context, model[i].id).await.unwrap(), movable);
+                            if movable {
+                                model.swap(i, i + 1);
+                            }
+                        }
+                        Op::Undo => {
+                            let restored = storage.undo_delete(context).await.unwrap();
+                            prop_assert_eq!(restored.as_ref().map(|t| t.id), trash.first().map(|t| t.id));
+                            if !trash.is_empty() {
+                                model.push(trash.remove(0));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                let tasks = storage.get_tasks(context).await.unwrap();
+                let ids: Vec<usize> = tasks.iter().map(|t| t.id).collect();
+                prop_assert_eq!(&ids, &model.iter().map(|t| t.id).collect::<Vec<_>>());
+                prop_assert!(ids.iter().all(|id| *id < storage.next_id));
+                for (stored, expected) in tasks.iter().zip(&model) {
+                    prop_assert_eq!(&stored.text, &expected.text);
+                    prop_assert_eq!(&stored.status, &expected.status);
+                }
+
+                let reloaded = LocalTaskStorage::new(path.clone()).unwrap();
+                prop_assert_eq!(reloaded.get_tasks(context).await.unwrap(), tasks);
+                prop_assert_eq!(reloaded.next_id, storage.next_id);
+                Ok(())
+            })?;
+        }
+    }
+
+    fn create_test_storage() -> LocalTaskStorage {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_todos.json");
+        LocalTaskStorage::new(path.to_string_lossy().to_string()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_tasks() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+        
+        let id = storage.add_task(context, "Test task".to_string()).await.unwrap();
+        assert_eq!(id, 1);
+        
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].text, "Test task");
+        assert_eq!(tasks[0].id, 1);
+    }
+
+    #[test]
+    fn test_write_files_replaces_files_without_leaving_temporaries() {
+        let dir = TempDir::new().unwrap();
+        let context = dir.path().join("todos").join("main.json");
+        let index = dir.path().join("todos.json");
+        fs::create_dir_all(context.parent().unwrap()).unwrap();
+        fs::write(&context, "old").unwrap();
+
+        let snapshot = vec![(context.clone(), "new".to_string()), (index.clone(), "{}".to_string())];
+        assert_eq!(write_files(&snapshot).unwrap(), Some(content_hash(b"{}")));
+        assert_eq!(fs::read_to_string(&context).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&index).unwrap(), "{}");
+        assert!(!dir.path().join("todos.json.tmp").exists());
+        assert!(!context.with_extension("json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_check_external_changes_reloads_other_writers() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todos.json").to_string_lossy().to_string();
+        let mut mine = LocalTaskStorage::new(path.clone()).unwrap();
+        let context = "test:repo:main";
+        mine.add_task(context, "Mine".to_string()).await.unwrap();
+        // Our own writes aren't external changes
+        assert_eq!(mine.check_external_changes().await.unwrap(), ExternalChange::None);
+
+        let mut theirs = LocalTaskStorage::new(path).unwrap();
+        theirs.add_task(context, "Theirs".to_string()).await.unwrap();
+        assert_eq!(mine.check_external_changes().await.unwrap(), ExternalChange::Reloaded);
+        let texts: Vec<String> = mine.get_tasks(context).await.unwrap().into_iter().map(|t| t.text).collect();
+        assert_eq!(texts, vec!["Mine", "Theirs"]);
+
+        theirs.add_task(context, "More".to_string()).await.unwrap();
+        mine.load_context(context).unwrap();
+        lock(&mine.file).failed = true;
+        assert_eq!(mine.check_external_changes().await.unwrap(), ExternalChange::Conflict);
+        mine.resolve_external_change(true).await.unwrap();
+        assert_eq!(mine.check_external_changes().await.unwrap(), ExternalChange::None);
+        assert_eq!(LocalTaskStorage::new(dir.path().join("todos.json").to_string_lossy().to_string()).unwrap().get_tasks(context).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_keeping_mine_leaves_their_purged_tasks_out() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todos.json").to_string_lossy().to_string();
+        let context = "test:repo:main";
+        let mut mine = LocalTaskStorage::new(path.clone()).unwrap();
+        let purged = mine.add_task(context, "Purged".to_string()).await.unwrap();
+        mine.add_task(context, "Kept".to_string()).await.unwrap();
+
+        let mut theirs = LocalTaskStorage::new(path).unwrap();
+        theirs.remove_task(context, purged).await.unwrap();
+        theirs.purge_deleted(context, purged).await.unwrap();
+
+        // An edit of ours that failed to save
+        mine.contexts.get_mut(context).unwrap()[1].text = "Kept, edited".to_string();
+        lock(&mine.file).failed = true;
+        assert_eq!(mine.check_external_changes().await.unwrap(), ExternalChange::Conflict);
+        mine.resolve_external_change(true).await.unwrap();
+        let texts: Vec<String> = mine.get_tasks(context).await.unwrap().into_iter().map(|t| t.text).collect();
+        assert_eq!(texts, vec!["Kept, edited"]);
+        assert_eq!(mine.list_tombstones(context).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_activity_log_is_read_from_the_end_and_rotated() {
+        use crate::storage::activity::ActivityAction;
+        let dir = TempDir::new().unwrap();
+        let mut storage = LocalTaskStorage::new(dir.path().join("todos.json").to_string_lossy().to_string()).unwrap();
+        let entry = |context: &str, n: usize| {
+            let mut entry = ActivityEntry::new(context, "ann@acme.dev", ActivityAction::Added, None);
+            entry.task_text = format!("task {} {}", n, "x".repeat(200));
+            entry
+        };
+        // Enough to span several chunks of the backwards read
+        for n in 0..600 {
+            storage.record_activity(vec![entry("a:b:c", n), entry("a:b:d", n)]).await.unwrap();
+        }
+        let activity = storage.list_activity("a:b:c").await.unwrap();
+        assert_eq!(activity.len(), ACTIVITY_LIMIT);
+        assert!(activity[0].task_text.starts_with("task 599 "));
+        assert!(activity[ACTIVITY_LIMIT - 1].task_text.starts_with("task 400 "));
+
+        // A full log moves aside, and is still read once the new one runs out
+        let mut log = fs::OpenOptions::new().append(true).open(storage.activity_path()).unwrap();
+        log.write_all(&vec![b'x'; ACTIVITY_ROTATE_BYTES as usize]).unwrap();
+        log.write_all(b"\n").unwrap();
+        storage.record_activity(vec![entry("a:b:c", 600)]).await.unwrap();
+        assert!(storage.rotated_activity_path().exists());
+        let activity = storage.list_activity("a:b:c").await.unwrap();
+        assert!(activity[0].task_text.starts_with("task 600 "));
+        assert!(activity[1].task_text.starts_with("task 599 "));
+        assert_eq!(activity.len(), ACTIVITY_LIMIT);
+
+        // Exports read both halves, oldest first
+        let exported = storage.export_activity(None).await.unwrap();
+        assert_eq!(exported.len(), 1201);
+        assert!(exported[0].task_text.starts_with("task 0 "));
+        assert!(exported[1200].task_text.starts_with("task 600 "));
+        assert!(storage.export_activity(Some("2999-01-01T00:00:00+00:00")).await.unwrap().is_empty());
+        assert!(storage.export_activity(Some("yesterday")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_debounced_saves_coalesce_until_flushed() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todos.json").to_string_lossy().to_string();
+        let mut storage = LocalTaskStorage::new(path.clone()).unwrap().with_debounced_saves(Duration::from_secs(60));
+        let tracker = SaveTracker::default();
+        storage.track_saves(tracker.clone());
+        let later = || std::time::Instant::now() + Duration::from_secs(1);
+        let context = "test:repo:main";
+        storage.add_task(context, "First".to_string()).await.unwrap();
+        storage.add_task(context, "Second".to_string()).await.unwrap();
+        assert!(LocalTaskStorage::new(path.clone()).unwrap().get_tasks(context).await.unwrap().is_empty());
+        // Unwritten changes make another writer's change a conflict
+        assert!(lock(&storage.file).dirty());
+        assert_eq!(tracker.indicator(later()), Some(SaveIndicator::Saving));
+
+        storage.flush().await.unwrap();
+        assert!(!lock(&storage.file).dirty());
+        assert_eq!(tracker.indicator(later()), Some(SaveIndicator::Saved));
+        assert_eq!(LocalTaskStorage::new(path).unwrap().get_tasks(context).await.unwrap().len(), 2);
+        assert_eq!(storage.check_external_changes().await.unwrap(), ExternalChange::None);
+    }
+
+    #[tokio::test]
+    async fn test_toggle_task_status() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+        
+        let id = storage.add_task(context, "Test task".to_string()).await.unwrap();
+        
+        let success = storage.toggle_task(context, id).await.unwrap();
+        assert!(success);
+        
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert_eq!(tasks[0].status, TaskStatus::InProgress);
+        
+        storage.toggle_task(context, id).await.unwrap();
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert_eq!(tasks[0].status, TaskStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_set_task_status() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+        
+        let id = storage.add_task(context, "Test task".to_string()).await.unwrap();
+        
+        let success = storage.set_task_status(context, id, TaskStatus::Completed).await.unwrap();
+        assert!(success);
+        
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert_eq!(tasks[0].status, TaskStatus::Completed);
+        assert!(tasks[0].completed_at.is_some());
+
+        storage.set_task_status(context, id, TaskStatus::InProgress).await.unwrap();
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert!(tasks[0].completed_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_remove_and_undo_task() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+        
+        let id = storage.add_task(context, "Test task".to_string()).await.unwrap();
+        
+        let success = storage.remove_task(context, id).await.unwrap();
+        assert!(success);
+        
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert_eq!(tasks.len(), 0);
+        
+        let restored = storage.undo_delete(context).await.unwrap();
+        assert!(restored.is_some());
+        assert_eq!(restored.unwrap().text, "Test task");
+        
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_edit_task() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+        
+        let id = storage.add_task(context, "Original task".to_string()).await.unwrap();
+        
+        let success = storage.edit_task(context, id, "Edited task".to_string()).await.unwrap();
+        assert!(success);
+        
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert_eq!(tasks[0].text, "Edited task");
+    }
+
+    #[tokio::test]
+    async fn test_update_task() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+
+        let id = storage.add_task(context, "Test task".to_string()).await.unwrap();
+
+        let mut task = storage.get_tasks(context).await.unwrap().remove(0);
+        task.must_finish = true;
+        task.notes = "Line one\nLine two".to_string();
+        assert!(storage.update_task(context, task).await.unwrap());
+
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert!(tasks[0].must_finish);
+        assert_eq!(tasks[0].notes, "Line one\nLine two");
+        assert_eq!(tasks[0].id, id);
+
+        let missing = Task::new(999, "Missing".to_string());
+        assert!(!storage.update_task(context, missing).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_move_context_tasks_keeps_uids() {
+        let mut storage = create_test_storage();
+        storage.add_task("a:web:main", "Kept".to_string()).await.unwrap();
+        let parent = storage.add_task("b:web:main", "Parent".to_string()).await.unwrap();
+        let child = storage.add_task("b:web:main", "Child".to_string()).await.unwrap();
+        let mut task = storage.get_tasks("b:web:main").await.unwrap().remove(1);
+        task.parent = Some(parent);
+        storage.update_task("b:web:main", task).await.unwrap();
+        let uids: Vec<Uuid> = storage.get_tasks("b:web:main").await.unwrap().iter().map(|t| t.uid).collect();
+
+        assert_eq!(storage.move_context_tasks("b:web:main", "a:web:main").await.unwrap(), 2);
+        assert!(storage.get_tasks("b:web:main").await.unwrap().is_empty());
+        let tasks = storage.get_tasks("a:web:main").await.unwrap();
+        let texts: Vec<&str> = tasks.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, ["Kept", "Parent", "Child"]);
+        assert_eq!(tasks[1..].iter().map(|t| t.uid).collect::<Vec<_>>(), uids);
+        assert!(tasks[1].id != parent && tasks[2].id != child);
+        assert_eq!(tasks[2].parent, Some(tasks[1].id));
+    }
+
+    #[tokio::test]
+    async fn test_move_tasks_to_the_same_context_changes_nothing() {
+        let mut storage = create_test_storage();
+        let first = storage.add_task("a:web:main", "First".to_string()).await.unwrap();
+        storage.add_task("a:web:main", "Second".to_string()).await.unwrap();
+        let before = storage.get_tasks("a:web:main").await.unwrap();
+
+        assert_eq!(storage.move_tasks("a:web:main", &[first], "a:web:main").await.unwrap(), 0);
+        assert_eq!(storage.get_tasks("a:web:main").await.unwrap(), before);
+    }
+
+    #[tokio::test]
+    async fn test_inspect_task() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+
+        let id = storage.add_task(context, "Inspect me".to_string()).await.unwrap();
+        let dump = storage.inspect_task(context, id).await.unwrap();
+
+        assert!(dump.contains("\"text\": \"Inspect me\""));
+        assert!(dump.contains("\"next_id\": 2"));
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_file_backups_and_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("todos.json");
+        let mut storage = LocalTaskStorage::new(path.to_string_lossy().to_string()).unwrap();
+        assert!(!storage.health().await.unwrap().warnings.is_empty());
+
+        storage.add_task("a", "One".to_string()).await.unwrap();
+        storage.add_task("b", "Two".to_string()).await.unwrap();
+        storage.add_task("b", "Three".to_string()).await.unwrap();
+        fs::write(temp_dir.path().join("todos.json.migrated"), "{}").unwrap();
+        fs::write(temp_dir.path().join("other.json"), "{}").unwrap();
+
+        let health = storage.health().await.unwrap();
+        assert!(health.warnings.is_empty());
+        assert!(health.details.contains(&("Backups".to_string(), "1".to_string())));
+        assert!(health.details.iter().any(|(label, _)| label == "Last write"));
+        assert_eq!(health.contexts, vec![("b".to_string(), 2), ("a".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_check_path() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(check_path(&temp_dir.path().join("todos.json")).is_ok());
+        assert!(check_path(&temp_dir.path().join("new/dirs/todos.json")).is_ok());
+        assert!(matches!(check_path(temp_dir.path()), Err(QuillError::Validation(_))));
+        assert!(matches!(check_path(Path::new("")), Err(QuillError::Validation(_))));
+
+        let file = temp_dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert!(check_path(&file).is_ok());
+        assert!(matches!(check_path(&file.join("todos.json")), Err(QuillError::Validation(_))));
+        // The probe file is cleaned up
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_multiple_contexts() {
+        let mut storage = create_test_storage();
+        let context1 = "test:repo1:main";
+        let context2 = "test:repo2:main";
+        
+        storage.add_task(context1, "Task 1".to_string()).await.unwrap();
+        storage.add_task(context2, "Task 2".to_string()).await.unwrap();
+        
+        let tasks1 = storage.get_tasks(context1).await.unwrap();
+        let tasks2 = storage.get_tasks(context2).await.unwrap();
+        
+        assert_eq!(tasks1.len(), 1);
+        assert_eq!(tasks2.len(), 1);
+        assert_eq!(tasks1[0].text, "Task 1");
+        assert_eq!(tasks2[0].text, "Task 2");
+    }
+
+    #[tokio::test]
+    async fn test_get_all_tasks_covers_every_context() {
+        let mut storage = create_test_storage();
+        let context1 = "test:repo1:main";
+        let context2 = "test:repo2:main";
+        storage.add_task(context1, "Task 1".to_string()).await.unwrap();
+        storage.add_task(context2, "Task 2".to_string()).await.unwrap();
+
+        let contexts = storage.list_contexts().await.unwrap();
+        assert_eq!(contexts, vec![context1.to_string(), context2.to_string()]);
+
+        let all = storage.get_all_tasks().await.unwrap();
+        assert_eq!(all.keys().cloned().collect::<Vec<_>>(), contexts);
+        assert_eq!(all[context2][0].text, "Task 2");
+    }
+
+    #[tokio::test]
+    async fn test_search_tasks_follows_changes() {
+        let mut storage = create_test_storage();
+        let id = storage.add_task("test:repo1:main", "Fix login".to_string()).await.unwrap();
+        storage.add_task("test:repo2:main", "Login docs".to_string()).await.unwrap();
+
+        let found = storage.search_tasks("login").await.unwrap();
+        assert_eq!(found.keys().cloned().collect::<Vec<_>>(), vec!["test:repo1:main", "test:repo2:main"]);
+
+        storage.edit_task("test:repo1:main", id, "Fix signup".to_string()).await.unwrap();
+        let found = storage.search_tasks("login").await.unwrap();
+        assert_eq!(found.keys().cloned().collect::<Vec<_>>(), vec!["test:repo2:main"]);
+        assert_eq!(storage.search_tasks("sign").await.unwrap()["test:repo1:main"][0].id, id);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_tasks_limit() {
+        let mut storage = create_test_storage().with_trash_retention(3);
+        let context = "test:repo:main";
+        
+        for i in 1..=5 {
+            let id = storage.add_task(context, format!("Task {}", i)).await.unwrap();
+            storage.remove_task(context, id).await.unwrap();
+        }
+        
+        let deleted_count = storage.deleted_tasks.get(context).map(|d| d.len()).unwrap_or(0);
+        assert_eq!(deleted_count, 3); // Should be limited to the retention
+    }
+
+    #[tokio::test]
+    async fn test_trash_retention_is_configurable() {
+        let mut storage = create_test_storage().with_trash_retention(10);
+        let context = "test:repo:main";
+
+        for i in 1..=5 {
+            let id = storage.add_task(context, format!("Task {}", i)).await.unwrap();
+            storage.remove_task(context, id).await.unwrap();
+        }
+
+        let deleted = storage.list_deleted(context).await.unwrap();
+        assert_eq!(deleted.len(), 5);
+        assert_eq!(deleted[0].task.text, "Task 5");
+        assert!(!deleted[0].deleted_at.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restore_and_purge_deleted() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+
+        let id1 = storage.add_task(context, "Task 1".to_string()).await.unwrap();
+        let id2 = storage.add_task(context, "Task 2".to_string()).await.unwrap();
+        storage.remove_task(context, id1).await.unwrap();
+        storage.remove_task(context, id2).await.unwrap();
+
+        // Restore the older one, skipping past the most recent deletion
+        let restored = storage.restore_deleted(context, id1).await.unwrap();
+        assert_eq!(restored.unwrap().text, "Task 1");
+        assert_eq!(storage.get_tasks(context).await.unwrap().len(), 1);
+
+        assert!(storage.purge_deleted(context, id2).await.unwrap());
+        assert!(!storage.purge_deleted(context, id2).await.unwrap());
+        assert!(storage.list_deleted(context).await.unwrap().is_empty());
+        assert!(storage.undo_delete(context).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tasks_leaving_the_trash_leave_tombstones() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("todos.json").to_string_lossy().to_string();
+        let mut storage = LocalTaskStorage::new(path.clone()).unwrap().with_trash_retention(1);
+        let context = "test:repo:main";
+
+        let purged = storage.add_task(context, "Purged".to_string()).await.unwrap();
+        let purged_uid = storage.get_tasks(context).await.unwrap()[0].uid;
+        storage.remove_task(context, purged).await.unwrap();
+        storage.purge_deleted(context, purged).await.unwrap();
+        // The second deletion pushes the first out of a one-task trash
+        for text in ["Evicted", "Kept"] {
+            let id = storage.add_task(context, text.to_string()).await.unwrap();
+            storage.remove_task(context, id).await.unwrap();
+        }
+        storage.tombstones.get_mut(context).unwrap()[0].deleted_at = "2020-01-01T00:00:00Z".to_string();
+        storage.add_task(context, "Saves again".to_string()).await.unwrap();
+
+        // The old tombstone is compacted away; the eviction's survives reopening
+        let reopened = LocalTaskStorage::new(path).unwrap();
+        let tombstones = reopened.list_tombstones(context).await.unwrap();
+        assert_eq!(tombstones.len(), 1);
+        assert_ne!(tombstones[0].uid, purged_uid);
+        assert_eq!(reopened.list_deleted(context).await.unwrap()[0].task.text, "Kept");
+    }
+
+    #[tokio::test]
+    async fn test_loads_deleted_tasks_without_timestamps() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("todos.json");
+        fs::write(&path, r#"{
+            "contexts": {},
+            "next_id": 2,
+            "deleted_tasks": {
+                "test:repo:main": [
+                    { "id": 1, "text": "Old", "status": "NotStarted", "created_at": "2024-01-01T00:00:00Z" }
+                ]
+            },
+            "storage_path": "todos.json"
+        }"#).unwrap();
+
+        let storage = LocalTaskStorage::new(path.to_string_lossy().to_string()).unwrap();
+        let deleted = storage.list_deleted("test:repo:main").await.unwrap();
+        assert_eq!(deleted[0].task.text, "Old");
+        assert_eq!(deleted[0].deleted_at, "");
+    }
+
+    #[tokio::test]
+    async fn test_splits_single_file_into_context_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("todos.json");
+        fs::write(&path, r#"{
+            "contexts": {
+                "a:web:main": [{ "id": 1, "text": "Web", "status": "NotStarted", "created_at": "2024-01-01T00:00:00Z" }],
+                "a:api:main": [{ "id": 2, "text": "Api", "status": "Completed", "created_at": "2024-01-01T00:00:00Z" }]
+            },
+            "next_id": 3
+        }"#).unwrap();
+
+        let mut storage = LocalTaskStorage::new(path.to_string_lossy().to_string()).unwrap();
+        assert!(temp_dir.path().join("todos.json.bak").exists());
+        let api_file = temp_dir.path().join(context_file_name("a:api:main"));
+        assert!(fs::read_to_string(&api_file).unwrap().contains("\"Api\""));
+        storage.add_task("a:web:main", "More".to_string()).await.unwrap();
+
+        // Only the contexts in use are read
+        fs::write(&api_file, "not json").unwrap();
+        let reopened = LocalTaskStorage::new(path.to_string_lossy().to_string()).unwrap();
+        assert_eq!(reopened.list_contexts().await.unwrap(), vec!["a:api:main", "a:web:main"]);
+        let web = reopened.get_tasks("a:web:main").await.unwrap();
+        assert_eq!(web.iter().map(|t| (t.id, t.text.as_str())).collect::<Vec<_>>(), vec![(1, "Web"), (3, "More")]);
+        assert!(reopened.get_tasks("a:api:main").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upgraded_files_are_written_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("todos.json");
+        let file = context_file_name("a:web:main");
+        fs::write(&path, format!(r#"{{ "schema_version": 2, "next_id": 2, "files": {{ "a:web:main": {{ "file": "{}", "tasks": 1 }} }} }}"#, file)).unwrap();
+        fs::write(
+            temp_dir.path().join(&file),
+            r#"{ "schema_version": 2, "context": "a:web:main", "tasks": [{ "id": 1, "text": "Old", "created_at": "2024-01-01T00:00:00Z" }] }"#,
+        )
+        .unwrap();
+
+        let storage = LocalTaskStorage::new(path.to_string_lossy().to_string()).unwrap();
+        let uid = storage.get_tasks("a:web:main").await.unwrap()[0].uid;
+        let stored: Value = serde_json::from_str(&fs::read_to_string(temp_dir.path().join(&file)).unwrap()).unwrap();
+        assert_eq!(schema::version_of(&stored), SCHEMA_VERSION);
+        assert_eq!(stored["tasks"][0]["uid"], uid.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_refuses_files_from_a_newer_quill() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("todos.json");
+        fs::write(&path, format!(r#"{{ "schema_version": {}, "next_id": 1, "files": {{}} }}"#, SCHEMA_VERSION + 1)).unwrap();
+
+        let error = LocalTaskStorage::new(path.to_string_lossy().to_string()).unwrap_err();
+        assert!(matches!(error, QuillError::Unsupported(_)), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_move_task_up() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+        
+        let id1 = storage.add_task(context, "Task 1".to_string()).await.unwrap();
+        let id2 = storage.add_task(context, "Task 2".to_string()).await.unwrap();
+        let id3 = storage.add_task(context, "Task 3".to_string()).await.unwrap();
+        
+        // Move task 2 up (should swap with task 1)
+        let success = storage.move_task_up(context, id2).await.unwrap();
+        assert!(success);
+        
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert_eq!(tasks[0].text, "Task 2");
+        assert_eq!(tasks[1].text, "Task 1");
+        assert_eq!(tasks[2].text, "Task 3");
+        // Ids stay with their tasks; only the order changes
+        assert_eq!(tasks[0].id, id2);
+        assert_eq!((tasks[1].id, tasks[2].id), (id1, id3));
+        assert!(tasks[0].order < tasks[1].order && tasks[1].order < tasks[2].order);
+        
+        // Try to move first task up (should fail)
+        let success = storage.move_task_up(context, id2).await.unwrap();
+        assert!(!success);
+    }
+
+    #[tokio::test]
+    async fn test_move_task_down() {
+        let mut storage = create_test_storage();
+        let context = "test:repo:main";
+        
+        let id1 = storage.add_task(context, "Task 1".to_string()).await.unwrap();
+        let id2 = storage.add_task(context, "Task 2".to_string()).await.unwrap();
+        let id3 = storage.add_task(context, "Task 3".to_string()).await.unwrap();
+        
+        // Move task 2 down (should swap with task 3)
+        let success = storage.move_task_down(context, id2).await.unwrap();
+        assert!(success);
+        
+        let tasks = storage.get_tasks(context).await.unwrap();
+        assert_eq!(tasks[0].text, "Task 1");
+        assert_eq!(tasks[1].text, "Task 3");
+        assert_eq!(tasks[2].text, "Task 2");
+        assert_eq!((tasks[0].id, tasks[1].id), (id1, id3));
+        
+        // Try to move last task down (should fail)
+        let success = storage.move_task_down(context, id2).await.unwrap();
+        assert!(!success);
+    }
+}
\ No newline at end of file
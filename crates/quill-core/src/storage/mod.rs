@@ -0,0 +1,832 @@
+use crate::config::{AppConfig, StorageType};
+use crate::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+pub mod activity;
+#[cfg(unix)]
+pub mod daemon;
+pub mod git_branch;
+pub mod legacy;
+pub mod local;
+#[cfg(feature = "mongo")]
+pub mod mongodb;
+pub mod optimistic;
+pub mod read_only;
+pub mod remote;
+pub mod schema;
+pub mod search;
+pub mod tracked;
+
+/// Whether this build includes the MongoDB backend (the `mongo` cargo feature).
+pub const MONGO_SUPPORTED: bool = cfg!(feature = "mongo");
+/// Whether this build includes the remote storage client (the `remote` cargo feature).
+pub const REMOTE_SUPPORTED: bool = cfg!(feature = "remote");
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum TaskStatus {
+    #[default]
+    NotStarted,
+    InProgress,
+    Completed,
+}
+
+impl TaskStatus {
+    /// The status toggling moves to: NotStarted → InProgress → Completed → NotStarted.
+    pub fn next(&self) -> Self {
+        match self {
+            TaskStatus::NotStarted => TaskStatus::InProgress,
+            TaskStatus::InProgress => TaskStatus::Completed,
+            TaskStatus::Completed => TaskStatus::NotStarted,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    /// The next priority in the None → Low → Medium → High cycle.
+    pub fn next(self) -> Self {
+        match self {
+            Priority::None => Priority::Low,
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High => Priority::None,
+        }
+    }
+}
+
+/// A file (relative to the repository root) and optional line a task refers to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Anchor {
+    pub file: String,
+    #[serde(default)]
+    pub line: Option<u32>,
+}
+
+impl Anchor {
+    /// Parses `path` or `path:line`.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        match input.rsplit_once(':') {
+            Some((file, line)) if !file.is_empty() && line.parse::<u32>().is_ok() => {
+                Some(Self { file: file.to_string(), line: line.parse().ok() })
+            }
+            _ => Some(Self { file: input.to_string(), line: None }),
+        }
+    }
+}
+
+impl std::fmt::Display for Anchor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.file, line),
+            None => write!(f, "{}", self.file),
+        }
+    }
+}
+
+/// A file or link attached to a task, listed and opened with `F`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Attachment {
+    /// Relative to the repository root, like an anchor.
+    File(Anchor),
+    Url { url: String },
+}
+
+impl Attachment {
+    /// Parses an `http(s)://` URL, or `path` / `path:line`.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if input.starts_with("http://") || input.starts_with("https://") {
+            return Some(Attachment::Url { url: input.to_string() });
+        }
+        Anchor::parse(input).map(Attachment::File)
+    }
+}
+
+impl std::fmt::Display for Attachment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Attachment::File(anchor) => write!(f, "{}", anchor),
+            Attachment::Url { url } => write!(f, "{}", url),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    /// Short number the UI and CLI refer to the task by within its storage.
+    pub id: usize,
+    /// Stable identity, kept through reorders, moves between contexts and
+    /// copies to other backends. Tasks stored before uids get theirs from
+    /// the schema migration when they're read.
+    pub uid: Uuid,
+    pub text: String,
+    #[serde(default)]
+    pub status: TaskStatus,
+    pub created_at: String,
+    /// Blocks `git push` (via `quill check`) until the task is completed.
+    #[serde(default)]
+    pub must_finish: bool,
+    /// Kept at the top of the list whatever the sort order.
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Free-form, possibly multi-line notes edited in `$EDITOR`.
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub due: Option<chrono::NaiveDate>,
+    /// RFC 3339 time the task last became Completed.
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// File location the task is about, opened with `o`.
+    #[serde(default)]
+    pub anchor: Option<Anchor>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Id of the task this is a subtask of.
+    #[serde(default)]
+    pub parent: Option<usize>,
+    /// Place in the manual order, lowest first. Values leave gaps, so a task
+    /// moves by changing only its own `order`.
+    #[serde(default)]
+    pub order: f64,
+    /// Key of the linked tracker issue, e.g. `PROJ-123`.
+    #[serde(default)]
+    pub issue: Option<String>,
+    /// Email of the teammate the task is assigned to, on storage a team shares.
+    #[serde(default)]
+    pub assignee: Option<String>,
+    /// RFC 3339 time until which the task is hidden from the list.
+    #[serde(default)]
+    pub snoozed_until: Option<String>,
+}
+
+impl Task {
+    pub fn new(id: usize, text: String) -> Self {
+        Self {
+            id,
+            uid: Uuid::now_v7(),
+            text,
+            status: TaskStatus::NotStarted,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            must_finish: false,
+            pinned: false,
+            tags: Vec::new(),
+            notes: String::new(),
+            due: None,
+            completed_at: None,
+            priority: Priority::None,
+            anchor: None,
+            attachments: Vec::new(),
+            parent: None,
+            order: 0.0,
+            issue: None,
+            assignee: None,
+            snoozed_until: None,
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        matches!(self.status, TaskStatus::Completed)
+    }
+
+    /// Whether the task is snoozed at `now`.
+    pub fn is_snoozed(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.snoozed_until
+            .as_deref()
+            .and_then(|until| chrono::DateTime::parse_from_rfc3339(until).ok())
+            .is_some_and(|until| until > now)
+    }
+
+    /// Short id for mentioning the task in commit messages, e.g. `q:4f9a0c1e`.
+    pub fn short_uid(&self) -> String {
+        let uid = self.uid.simple().to_string();
+        // The leading digits of a v7 uid are a timestamp, shared by tasks added together
+        format!("q:{}", &uid[uid.len() - 8..])
+    }
+
+    /// The file `o` opens: the anchor, else the first attached file.
+    pub fn linked_file(&self) -> Option<&Anchor> {
+        self.anchor.as_ref().or_else(|| {
+            self.attachments.iter().find_map(|attachment| match attachment {
+                Attachment::File(anchor) => Some(anchor),
+                Attachment::Url { .. } => None,
+            })
+        })
+    }
+
+    /// Changes the status, stamping `completed_at` on the transition to
+    /// Completed and clearing it when the task is reopened.
+    pub fn set_status(&mut self, status: TaskStatus) {
+        match (&self.status, &status) {
+            (TaskStatus::Completed, TaskStatus::Completed) => {}
+            (_, TaskStatus::Completed) => self.completed_at = Some(chrono::Utc::now().to_rfc3339()),
+            _ => self.completed_at = None,
+        }
+        self.status = status;
+    }
+
+    /// Whether the task is open and its due date is before `today`.
+    pub fn is_overdue(&self, today: chrono::NaiveDate) -> bool {
+        !self.is_completed() && self.due.is_some_and(|due| due < today)
+    }
+}
+
+/// Deleted tasks kept per context unless `trash_retention` is configured.
+pub const DEFAULT_TRASH_RETENTION: usize = 20;
+
+/// A task in the trash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeletedTask {
+    #[serde(flatten)]
+    pub task: Task,
+    /// RFC 3339 deletion time; empty for tasks deleted before it was recorded.
+    #[serde(default)]
+    pub deleted_at: String,
+}
+
+impl DeletedTask {
+    pub fn new(task: Task) -> Self {
+        Self { task, deleted_at: chrono::Utc::now().to_rfc3339() }
+    }
+}
+
+/// How long a tombstone is kept after its task left the trash, giving other
+/// copies of the data time to learn about the deletion.
+pub const TOMBSTONE_RETENTION_DAYS: i64 = 90;
+
+/// What's left of a task removed for good, so a copy of the data that still
+/// has the task can tell it was deleted rather than never seen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub uid: Uuid,
+    /// RFC 3339 time the task left the trash.
+    pub deleted_at: String,
+}
+
+impl Tombstone {
+    pub fn new(uid: Uuid) -> Self {
+        Self { uid, deleted_at: chrono::Utc::now().to_rfc3339() }
+    }
+
+    /// Whether the tombstone is older than `TOMBSTONE_RETENTION_DAYS` at `now`.
+    pub fn expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        chrono::DateTime::parse_from_rfc3339(&self.deleted_at)
+            .map_or(true, |at| now - at.with_timezone(&chrono::Utc) > chrono::Duration::days(TOMBSTONE_RETENTION_DAYS))
+    }
+}
+
+/// Drops tombstones past the retention period.
+pub fn compact_tombstones(tombstones: &mut Vec<Tombstone>) {
+    let now = chrono::Utc::now();
+    tombstones.retain(|tombstone| !tombstone.expired(now));
+}
+
+/// Status counts for one context.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskCounts {
+    pub not_started: usize,
+    pub in_progress: usize,
+    pub completed: usize,
+}
+
+impl TaskCounts {
+    pub fn from_tasks(tasks: &[Task]) -> Self {
+        let mut counts = Self::default();
+        for task in tasks {
+            match task.status {
+                TaskStatus::NotStarted => counts.not_started += 1,
+                TaskStatus::InProgress => counts.in_progress += 1,
+                TaskStatus::Completed => counts.completed += 1,
+            }
+        }
+        counts
+    }
+
+    pub fn total(&self) -> usize {
+        self.not_started + self.in_progress + self.completed
+    }
+
+    pub fn open(&self) -> usize {
+        self.not_started + self.in_progress
+    }
+
+    /// Completed share of all tasks, rounded down; 0 for an empty context.
+    pub fn percent(&self) -> usize {
+        if self.total() == 0 {
+            0
+        } else {
+            self.completed * 100 / self.total()
+        }
+    }
+}
+
+/// Where a backend keeps its data and whether it looks healthy.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackendHealth {
+    pub backend: String,
+    /// Labelled facts such as path, size or server version, in display order.
+    pub details: Vec<(String, String)>,
+    /// Task counts per context, largest first.
+    pub contexts: Vec<(String, usize)>,
+    pub warnings: Vec<String>,
+}
+
+impl BackendHealth {
+    pub fn new(backend: &str) -> Self {
+        Self { backend: backend.to_string(), ..Self::default() }
+    }
+
+    pub fn detail(&mut self, label: &str, value: impl Into<String>) {
+        self.details.push((label.to_string(), value.into()));
+    }
+
+    pub fn set_contexts(&mut self, mut contexts: Vec<(String, usize)>) {
+        contexts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        self.contexts = contexts;
+    }
+}
+
+/// What `TaskStorage::check_external_changes` found.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExternalChange {
+    /// Nothing changed, or the backend can't tell.
+    None,
+    /// Another writer changed the data and it was loaded.
+    Reloaded,
+    /// Another writer changed the data while changes made here aren't saved
+    /// yet; `resolve_external_change` decides which side wins.
+    Conflict,
+}
+
+#[async_trait]
+pub trait TaskStorage: Send + Sync {
+    async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>>;
+    /// Lists every context key that currently has tasks.
+    async fn list_contexts(&self) -> Result<Vec<String>>;
+    /// Every context's tasks, in the order `get_tasks` returns them, for
+    /// views across contexts. Backends that can read them all at once override this.
+    async fn get_all_tasks(&self) -> Result<BTreeMap<String, Vec<Task>>> {
+        let mut all = BTreeMap::new();
+        for context_key in self.list_contexts().await? {
+            let tasks = self.get_tasks(&context_key).await?;
+            all.insert(context_key, tasks);
+        }
+        Ok(all)
+    }
+    /// How many of the context's tasks are in each status. Backends that can
+    /// count without loading tasks override this and `all_task_counts`.
+    async fn get_task_counts(&self, context_key: &str) -> Result<TaskCounts> {
+        Ok(TaskCounts::from_tasks(&self.get_tasks(context_key).await?))
+    }
+    /// `get_task_counts` of every context that has tasks.
+    async fn all_task_counts(&self) -> Result<BTreeMap<String, TaskCounts>> {
+        Ok(self
+            .get_all_tasks()
+            .await?
+            .into_iter()
+            .map(|(context_key, tasks)| (context_key, TaskCounts::from_tasks(&tasks)))
+            .collect())
+    }
+    /// How many unfinished tasks each context has, leaving out contexts
+    /// without any.
+    async fn open_task_counts(&self) -> Result<BTreeMap<String, usize>> {
+        Ok(self
+            .all_task_counts()
+            .await?
+            .into_iter()
+            .map(|(context_key, counts)| (context_key, counts.open()))
+            .filter(|(_, open)| *open > 0)
+            .collect())
+    }
+    /// Tasks of every context whose text, notes or tags match `query` (see
+    /// `search`), grouped by context. Backends with a search index override this.
+    async fn search_tasks(&self, query: &str) -> Result<BTreeMap<String, Vec<Task>>> {
+        Ok(search::filter(self.get_all_tasks().await?, query))
+    }
+    async fn add_task(&mut self, context_key: &str, text: String) -> Result<usize>;
+    async fn toggle_task(&mut self, context_key: &str, id: usize) -> Result<bool>;
+    async fn set_task_status(&mut self, context_key: &str, id: usize, status: TaskStatus) -> Result<bool>;
+    async fn remove_task(&mut self, context_key: &str, id: usize) -> Result<bool>;
+    async fn edit_task(&mut self, context_key: &str, id: usize, new_text: String) -> Result<bool>;
+    /// Replaces the stored task that has the same uid with `task`.
+    async fn update_task(&mut self, context_key: &str, task: Task) -> Result<bool>;
+    async fn undo_delete(&mut self, context_key: &str) -> Result<Option<Task>>;
+    /// Lists the context's trash, most recently deleted first.
+    async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>>;
+    /// Moves a specific task from the trash back into the context.
+    async fn restore_deleted(&mut self, context_key: &str, id: usize) -> Result<Option<Task>>;
+    /// Permanently removes a task from the trash, leaving a tombstone.
+    async fn purge_deleted(&mut self, context_key: &str, id: usize) -> Result<bool>;
+    /// Tasks of the context removed for good within the retention period.
+    async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>>;
+    async fn move_task_up(&mut self, context_key: &str, id: usize) -> Result<bool>;
+    async fn move_task_down(&mut self, context_key: &str, id: usize) -> Result<bool>;
+    /// Removes every task of the context for good, leaving tombstones, and
+    /// returns how many there were.
+    async fn delete_context(&mut self, context_key: &str) -> Result<usize> {
+        let tasks = self.get_tasks(context_key).await?;
+        for task in &tasks {
+            self.remove_task(context_key, task.id).await?;
+            self.purge_deleted(context_key, task.id).await?;
+        }
+        Ok(tasks.len())
+    }
+    /// Moves every task of `from` to the end of `to`, keeping everything but
+    /// their ids, and returns how many moved.
+    async fn move_context_tasks(&mut self, from: &str, to: &str) -> Result<usize>;
+    /// Moves the tasks `ids` of `from` to the end of `to` the same way, and
+    /// returns how many moved. A subtask whose parent stays behind becomes
+    /// a top-level task.
+    async fn move_tasks(&mut self, from: &str, ids: &[usize], to: &str) -> Result<usize>;
+    /// Stores records of changes made through an `activity::ActivityStorage`;
+    /// backends without an activity log drop them.
+    async fn record_activity(&mut self, _entries: Vec<activity::ActivityEntry>) -> Result<()> {
+        Ok(())
+    }
+    /// The context's recorded changes, newest first, at most `activity::ACTIVITY_LIMIT`.
+    async fn list_activity(&self, _context_key: &str) -> Result<Vec<activity::ActivityEntry>> {
+        Ok(Vec::new())
+    }
+    /// Every recorded change of every context at or after `since` (an RFC
+    /// 3339 time), oldest first, for exports.
+    async fn export_activity(&self, _since: Option<&str>) -> Result<Vec<activity::ActivityEntry>> {
+        Ok(Vec::new())
+    }
+    /// Returns the raw stored form of a task and of its context, for debugging.
+    async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String>;
+    /// Describes where the data lives and the state of the backend.
+    async fn health(&self) -> Result<BackendHealth>;
+    /// The file other processes may change under this backend, if any.
+    fn watch_path(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+    /// Loads changes another process made to the backing data.
+    async fn check_external_changes(&mut self) -> Result<ExternalChange> {
+        Ok(ExternalChange::None)
+    }
+    /// Settles a `Conflict`: writes this side's data over the other writer's
+    /// when `keep_mine`, otherwise loads theirs and drops the unsaved changes.
+    async fn resolve_external_change(&mut self, _keep_mine: bool) -> Result<()> {
+        Ok(())
+    }
+    /// Writes changes the backend is still holding back; called before exiting.
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Errors of writes that failed in the background since the last call;
+    /// the changes they were for have been undone.
+    fn take_failed_writes(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Reports writes the backend finishes in the background to `tracker`,
+    /// as `tracked::TrackedStorage` does for the calls themselves.
+    fn track_saves(&mut self, _tracker: tracked::SaveTracker) {}
+}
+
+/// Opens the backend selected in `config`, without any fallback. Changes
+/// made through it are recorded as the user's activity.
+pub async fn open(config: &AppConfig) -> Result<Box<dyn TaskStorage>> {
+    let backend = open_backend(config).await?;
+    // A sync server records changes itself, as whoever the token belongs to
+    if config.storage_type == StorageType::Remote {
+        return Ok(backend);
+    }
+    Ok(Box::new(activity::ActivityStorage::new(backend, activity::actor())))
+}
+
+/// Checks that the storage `config` describes can be used, without
+/// changing its data, and returns how long that took. MongoDB is pinged and
+/// a local path checked for writing; other backends are opened and listed.
+pub async fn test_connection(config: &AppConfig) -> Result<std::time::Duration> {
+    let started = std::time::Instant::now();
+    match config.storage_type {
+        StorageType::Local => local::check_path(std::path::Path::new(&config.expand_local_path()))?,
+        #[cfg(feature = "mongo")]
+        StorageType::MongoDB => return mongodb::ping(&config.mongo_config).await,
+        _ => {
+            let round_trip = async { open_backend(config).await?.list_contexts().await };
+            tokio::time::timeout(std::time::Duration::from_secs(10), round_trip)
+                .await
+                .map_err(|_| crate::QuillError::StorageUnavailable("no answer within 10 seconds".to_string()))??;
+        }
+    }
+    Ok(started.elapsed())
+}
+
+/// Like `open`, without recording activity: for serving the storage to
+/// others, which records it per client.
+pub async fn open_backend(config: &AppConfig) -> Result<Box<dyn TaskStorage>> {
+    tracing::info!(backend = config.storage_type.label(), "opening storage");
+    match config.storage_type {
+        StorageType::Local => Ok(Box::new(
+            local::LocalTaskStorage::new(config.expand_local_path())?.with_trash_retention(config.trash_retention),
+        )),
+        #[cfg(feature = "mongo")]
+        StorageType::MongoDB => Ok(Box::new(
+            mongodb::MongoTaskStorage::new(&config.mongo_config)
+                .await?
+                .with_trash_retention(config.trash_retention),
+        )),
+        #[cfg(not(feature = "mongo"))]
+        StorageType::MongoDB => Err(crate::QuillError::Unsupported(
+            "this build of quill has no MongoDB support (rebuild with the `mongo` feature)".to_string(),
+        )),
+        StorageType::Git => Ok(Box::new(
+            git_branch::GitBranchStorage::open(&std::env::current_dir()?, &config.git_config)?
+                .with_trash_retention(config.trash_retention),
+        )),
+        // The server applies its own trash retention
+        #[cfg(feature = "remote")]
+        StorageType::Remote => Ok(Box::new(remote::RemoteTaskStorage::connect(&config.remote_config).await?)),
+        #[cfg(not(feature = "remote"))]
+        StorageType::Remote => Err(crate::QuillError::Unsupported(
+            "this build of quill has no remote storage support (rebuild with the `remote` feature)".to_string(),
+        )),
+    }
+}
+
+/// How long the local file waits for more changes before it is written,
+/// in `open_interactive`.
+const SAVE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Like `open`, for a long-running session: a local file is written in the
+/// background shortly after changes rather than on each one, and changes to
+/// MongoDB or a remote server show before the backend confirms them. Call
+/// `flush` before exiting.
+///
+/// When a `quill daemon` serving the same storage is running, its
+/// connection is used instead.
+///
+/// Must be called within a tokio runtime.
+pub async fn open_interactive(config: &AppConfig) -> Result<Box<dyn TaskStorage>> {
+    #[cfg(unix)]
+    if let Some(path) = daemon::socket_path().filter(|path| path.exists()) {
+        match daemon::DaemonStorage::connect(&path, config).await {
+            // The daemon records changes itself, as the user running it
+            Ok(storage) => return Ok(Box::new(storage)),
+            Err(e) => tracing::debug!(error = %e, "not using the quill daemon"),
+        }
+    }
+    match config.storage_type {
+        StorageType::Local => {
+            tracing::info!(backend = config.storage_type.label(), "opening storage");
+            let local = local::LocalTaskStorage::new(config.expand_local_path())?
+                .with_trash_retention(config.trash_retention)
+                .with_debounced_saves(SAVE_DELAY);
+            Ok(Box::new(activity::ActivityStorage::new(Box::new(local), activity::actor())))
+        }
+        StorageType::MongoDB | StorageType::Remote => Ok(Box::new(optimistic::OptimisticStorage::new(open(config).await?))),
+        _ => open(config).await,
+    }
+}
+
+/// The `order` for a task placed between neighbours ordered `before` and
+/// `after`, either missing at the ends of the list. `None` when they are too
+/// close to fit another value and the list needs renumbering.
+pub fn order_between(before: Option<f64>, after: Option<f64>) -> Option<f64> {
+    let order = match (before, after) {
+        (Some(before), Some(after)) => (before + after) / 2.0,
+        (Some(before), None) => before + 1.0,
+        (None, Some(after)) => after - 1.0,
+        (None, None) => 1.0,
+    };
+    let clear = |bound: Option<f64>| bound.is_none_or(|bound| bound != order);
+    (clear(before) && clear(after)).then_some(order)
+}
+
+/// The `order` of a task added after `tasks`.
+pub fn next_order(tasks: &[Task]) -> f64 {
+    tasks.iter().map(|t| t.order).reduce(f64::max).map_or(1.0, |last| last + 1.0)
+}
+
+/// Proptest strategies shared by the storage backends' tests.
+#[cfg(test)]
+pub(crate) mod strategies {
+    use super::{Anchor, Attachment, Priority, Task, TaskStatus};
+    use proptest::prelude::*;
+
+    /// Task texts: arbitrary unicode including control characters, plus very long strings.
+    pub fn arb_text() -> impl Strategy<Value = String> {
+        prop_oneof![
+            4 => any::<String>(),
+            1 => proptest::collection::vec(any::<char>(), 5_000..20_000).prop_map(String::from_iter),
+        ]
+    }
+
+    pub fn arb_status() -> impl Strategy<Value = TaskStatus> {
+        prop_oneof![
+            Just(TaskStatus::NotStarted),
+            Just(TaskStatus::InProgress),
+            Just(TaskStatus::Completed),
+        ]
+    }
+
+    pub fn arb_task() -> impl Strategy<Value = Task> {
+        (
+            // MongoDB stores ids as i64
+            0..i64::MAX as usize,
+            arb_text(),
+            arb_status(),
+            any::<bool>(),
+            proptest::collection::vec(any::<String>(), 0..4),
+            any::<String>(),
+            proptest::option::of((-100_000i32..100_000).prop_map(|days| {
+                chrono::NaiveDate::from_num_days_from_ce_opt(730_000 + days).unwrap()
+            })),
+            proptest::option::of(any::<String>()),
+            prop_oneof![Just(Priority::None), Just(Priority::Low), Just(Priority::Medium), Just(Priority::High)],
+            proptest::option::of((any::<String>(), proptest::option::of(any::<u32>())).prop_map(|(file, line)| Anchor { file, line })),
+            proptest::option::of(0..i64::MAX as usize),
+            (
+                proptest::option::of("[A-Z]{2,5}-[0-9]{1,4}"),
+                proptest::option::of("[a-z]{1,8}@[a-z]{1,8}\\.dev"),
+                proptest::option::of((0i64..4_000_000_000).prop_map(|secs| chrono::DateTime::from_timestamp(secs, 0).unwrap().to_rfc3339())),
+                proptest::collection::vec(
+                    prop_oneof![
+                        (any::<String>(), proptest::option::of(any::<u32>())).prop_map(|(file, line)| Attachment::File(Anchor { file, line })),
+                        "https://[a-z]{1,12}\\.dev/[a-z0-9/]{0,20}".prop_map(|url| Attachment::Url { url }),
+                    ],
+                    0..3,
+                ),
+                any::<bool>(),
+            ),
+        )
+            .prop_map(|(id, text, status, must_finish, tags, notes, due, completed_at, priority, anchor, parent, (issue, assignee, snoozed_until, attachments, pinned))| Task {
+                id,
+                uid: uuid::Uuid::now_v7(),
+                text,
+                status,
+                created_at: chrono::Utc::now().to_rfc3339(),
+                must_finish,
+                pinned,
+                tags,
+                notes,
+                due,
+                completed_at,
+                priority,
+                anchor,
+                attachments,
+                parent,
+                // Realistic orders: whole numbers and the midpoints moves create
+                order: (id % 4096) as f64 / 2.0,
+                issue,
+                assignee,
+                snoozed_until,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn prop_task_json_round_trip(task in strategies::arb_task()) {
+            let json = serde_json::to_string(&task).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Task>(&json).unwrap(), task);
+        }
+
+        #[test]
+        fn prop_deleted_task_json_round_trip(task in strategies::arb_task()) {
+            let deleted = DeletedTask::new(task);
+            let json = serde_json::to_string(&deleted).unwrap();
+            prop_assert_eq!(serde_json::from_str::<DeletedTask>(&json).unwrap(), deleted);
+        }
+    }
+
+    #[test]
+    fn test_is_snoozed() {
+        let mut task = Task::new(1, "Follow up".to_string());
+        let now = chrono::Utc::now();
+        assert!(!task.is_snoozed(now));
+        task.snoozed_until = Some((now + chrono::Duration::hours(1)).to_rfc3339());
+        assert!(task.is_snoozed(now));
+        assert!(!task.is_snoozed(now + chrono::Duration::hours(2)));
+    }
+
+    #[test]
+    fn test_anchor_parse() {
+        let anchor = Anchor::parse(" src/app.rs:42 ").unwrap();
+        assert_eq!(anchor, Anchor { file: "src/app.rs".to_string(), line: Some(42) });
+        assert_eq!(anchor.to_string(), "src/app.rs:42");
+        assert_eq!(Anchor::parse("README.md").unwrap().line, None);
+        assert_eq!(Anchor::parse("c:notes").unwrap().file, "c:notes");
+        assert_eq!(Anchor::parse("  "), None);
+    }
+
+    #[test]
+    fn test_attachment_parse() {
+        assert_eq!(
+            Attachment::parse(" https://github.com/acme/web/pull/7 "),
+            Some(Attachment::Url { url: "https://github.com/acme/web/pull/7".to_string() })
+        );
+        let file = Attachment::parse("docs/design.md:12").unwrap();
+        assert_eq!(file, Attachment::File(Anchor { file: "docs/design.md".to_string(), line: Some(12) }));
+        assert_eq!(serde_json::to_string(&file).unwrap(), r#"{"kind":"file","file":"docs/design.md","line":12}"#);
+        assert_eq!(Attachment::parse(""), None);
+    }
+
+    #[test]
+    fn test_task_creation() {
+        let task = Task::new(1, "Test task".to_string());
+        assert_eq!(task.id, 1);
+        assert_eq!(task.text, "Test task");
+        assert_eq!(task.status, TaskStatus::NotStarted);
+        assert!(!task.created_at.is_empty());
+    }
+
+    #[test]
+    fn test_order_between_and_next_order() {
+        assert_eq!(order_between(Some(1.0), Some(2.0)), Some(1.5));
+        assert_eq!(order_between(None, Some(2.0)), Some(1.0));
+        assert_eq!(order_between(Some(2.0), None), Some(3.0));
+        assert_eq!(order_between(None, None), Some(1.0));
+        // No room left between neighbours that are next to each other
+        assert_eq!(order_between(Some(1.0), Some(1.0 + f64::EPSILON)), None);
+
+        assert_eq!(next_order(&[]), 1.0);
+        let mut task = Task::new(1, "Test task".to_string());
+        task.order = 4.5;
+        assert_eq!(next_order(&[task]), 5.5);
+    }
+
+    #[test]
+    fn test_tombstones_expire_after_retention() {
+        let now = chrono::Utc::now();
+        let mut tombstone = Tombstone::new(Uuid::now_v7());
+        assert!(!tombstone.expired(now));
+        tombstone.deleted_at = (now - chrono::Duration::days(TOMBSTONE_RETENTION_DAYS + 1)).to_rfc3339();
+        assert!(tombstone.expired(now));
+
+        let mut tombstones = vec![tombstone, Tombstone::new(Uuid::now_v7())];
+        compact_tombstones(&mut tombstones);
+        assert_eq!(tombstones.len(), 1);
+    }
+
+    #[test]
+    fn test_task_status_default() {
+        let status = TaskStatus::default();
+        assert_eq!(status, TaskStatus::NotStarted);
+    }
+
+    #[test]
+    fn test_task_is_completed() {
+        let mut task = Task::new(1, "Test task".to_string());
+        assert!(!task.is_completed());
+        
+        task.status = TaskStatus::Completed;
+        assert!(task.is_completed());
+    }
+
+    #[test]
+    fn test_task_serialization() {
+        let task = Task::new(1, "Test task".to_string());
+        let json = serde_json::to_string(&task).unwrap();
+        let deserialized: Task = serde_json::from_str(&json).unwrap();
+        
+        assert_eq!(task.id, deserialized.id);
+        assert_eq!(task.text, deserialized.text);
+        assert_eq!(task.status, deserialized.status);
+    }
+
+    #[test]
+    fn test_task_without_must_finish_deserializes() {
+        let json = r#"{"id":7,"uid":"0190a2b4-5c6d-7e8f-9a0b-1c2d3e4f5a6b","text":"Old task","status":"InProgress","created_at":"2024-01-01T00:00:00Z"}"#;
+        let task: Task = serde_json::from_str(json).unwrap();
+        assert_eq!(task.id, 7);
+        assert!(!task.must_finish);
+    }
+
+    #[test]
+    fn test_task_status_variants() {
+        let not_started = TaskStatus::NotStarted;
+        let in_progress = TaskStatus::InProgress;
+        let completed = TaskStatus::Completed;
+        
+        assert_ne!(not_started, in_progress);
+        assert_ne!(in_progress, completed);
+        assert_ne!(not_started, completed);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,1264 @@
+use super::activity::{ActivityAction, ActivityEntry, ACTIVITY_LIMIT};
+use super::schema::{self, SCHEMA_VERSION};
+use super::search;
+use super::{
+    order_between, Anchor, Attachment, BackendHealth, DeletedTask, Priority, Task, TaskCounts, TaskStatus, TaskStorage, Tombstone, DEFAULT_TRASH_RETENTION,
+    TOMBSTONE_RETENTION_DAYS,
+};
+use crate::config::MongoConfig;
+use crate::{QuillError, Result};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use bson::doc;
+use mongodb::options::{ClientOptions, Credential, IndexOptions, Tls, TlsOptions};
+use mongodb::error::TRANSIENT_TRANSACTION_ERROR;
+use mongodb::{Client, ClientSession, Collection, Database, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use uuid::Uuid;
+
+/// Name of the text index on task text, notes and tags.
+const TEXT_INDEX: &str = "task_text";
+
+/// Documents from before versioning have no `schema_version`.
+fn first_version() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TaskDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<bson::oid::ObjectId>,
+    pub task_id: i64,
+    #[serde(default)]
+    pub uid: String,
+    pub context_key: String,
+    pub text: String,
+    pub status: TaskStatus,
+    pub created_at: String,
+    #[serde(default)]
+    pub must_finish: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub due: Option<chrono::NaiveDate>,
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub anchor: Option<Anchor>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub parent: Option<i64>,
+    #[serde(default)]
+    pub order: f64,
+    #[serde(default)]
+    pub issue: Option<String>,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[serde(default)]
+    pub snoozed_until: Option<String>,
+    #[serde(default = "first_version")]
+    pub schema_version: u32,
+}
+
+impl From<(&str, &Task)> for TaskDocument {
+    fn from((context_key, task): (&str, &Task)) -> Self {
+        Self {
+            id: None,
+            task_id: task.id as i64,
+            uid: task.uid.to_string(),
+            context_key: context_key.to_string(),
+            text: task.text.clone(),
+            status: task.status.clone(),
+            created_at: task.created_at.clone(),
+            must_finish: task.must_finish,
+            pinned: task.pinned,
+            tags: task.tags.clone(),
+            notes: task.notes.clone(),
+            due: task.due,
+            completed_at: task.completed_at.clone(),
+            priority: task.priority,
+            anchor: task.anchor.clone(),
+            attachments: task.attachments.clone(),
+            parent: task.parent.map(|id| id as i64),
+            order: task.order,
+            issue: task.issue.clone(),
+            assignee: task.assignee.clone(),
+            snoozed_until: task.snoozed_until.clone(),
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+}
+
+/// A uid read back from MongoDB. One that doesn't parse is corrupt: making
+/// one up would give every such task the same identity.
+fn parse_uid(uid: &str, collection: &str) -> Result<Uuid> {
+    Uuid::parse_str(uid).map_err(|e| QuillError::Corrupt(format!("uid \"{}\" in the MongoDB collection \"{}\": {}", uid, collection, e)))
+}
+
+impl TryFrom<TaskDocument> for Task {
+    type Error = QuillError;
+
+    fn try_from(doc: TaskDocument) -> Result<Self> {
+        Ok(Self {
+            id: doc.task_id as usize,
+            uid: parse_uid(&doc.uid, "tasks")?,
+            text: doc.text,
+            status: doc.status,
+            created_at: doc.created_at,
+            must_finish: doc.must_finish,
+            pinned: doc.pinned,
+            tags: doc.tags,
+            notes: doc.notes,
+            due: doc.due,
+            completed_at: doc.completed_at,
+            priority: doc.priority,
+            anchor: doc.anchor,
+            attachments: doc.attachments,
+            parent: doc.parent.map(|id| id as usize),
+            order: doc.order,
+            issue: doc.issue,
+            assignee: doc.assignee,
+            snoozed_until: doc.snoozed_until,
+        })
+    }
+}
+
+/// `_id` of the counter handing out task ids in `context_key`.
+fn counter_id(context_key: &str) -> String {
+    format!("task_id:{}", context_key)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CounterDocument {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub value: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DeletedTaskDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<bson::oid::ObjectId>,
+    pub context_key: String,
+    pub task_id: i64,
+    #[serde(default)]
+    pub uid: String,
+    pub text: String,
+    pub status: TaskStatus,
+    pub created_at: String,
+    pub deleted_at: String,
+    #[serde(default)]
+    pub must_finish: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub due: Option<chrono::NaiveDate>,
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub anchor: Option<Anchor>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub parent: Option<i64>,
+    #[serde(default)]
+    pub order: f64,
+    #[serde(default)]
+    pub issue: Option<String>,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[serde(default)]
+    pub snoozed_until: Option<String>,
+    #[serde(default = "first_version")]
+    pub schema_version: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TombstoneDocument {
+    pub context_key: String,
+    pub uid: String,
+    pub deleted_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ActivityDocument {
+    pub context_key: String,
+    pub at: String,
+    pub actor: String,
+    pub action: ActivityAction,
+    #[serde(default)]
+    pub task_uid: Option<String>,
+    #[serde(default)]
+    pub task_text: String,
+    #[serde(default)]
+    pub field: Option<String>,
+    #[serde(default)]
+    pub old: Option<String>,
+    #[serde(default)]
+    pub new: Option<String>,
+}
+
+/// One `$group` of `count_tasks`.
+#[derive(Debug, Deserialize)]
+struct CountGroup {
+    #[serde(rename = "_id")]
+    key: CountKey,
+    count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CountKey {
+    context_key: String,
+    status: TaskStatus,
+}
+
+impl From<ActivityEntry> for ActivityDocument {
+    fn from(entry: ActivityEntry) -> Self {
+        Self {
+            context_key: entry.context_key,
+            at: entry.at,
+            actor: entry.actor,
+            action: entry.action,
+            task_uid: entry.task_uid.map(|uid| uid.to_string()),
+            task_text: entry.task_text,
+            field: entry.field,
+            old: entry.old,
+            new: entry.new,
+        }
+    }
+}
+
+impl TryFrom<ActivityDocument> for ActivityEntry {
+    type Error = QuillError;
+
+    fn try_from(doc: ActivityDocument) -> Result<Self> {
+        Ok(Self {
+            context_key: doc.context_key,
+            at: doc.at,
+            actor: doc.actor,
+            action: doc.action,
+            task_uid: doc.task_uid.map(|uid| parse_uid(&uid, "activity")).transpose()?,
+            task_text: doc.task_text,
+            field: doc.field,
+            old: doc.old,
+            new: doc.new,
+        })
+    }
+}
+
+impl TryFrom<TombstoneDocument> for Tombstone {
+    type Error = QuillError;
+
+    fn try_from(doc: TombstoneDocument) -> Result<Self> {
+        Ok(Self { uid: parse_uid(&doc.uid, "tombstones")?, deleted_at: doc.deleted_at })
+    }
+}
+
+impl From<(&str, &Task)> for DeletedTaskDocument {
+    fn from((context_key, task): (&str, &Task)) -> Self {
+        Self {
+            id: None,
+            context_key: context_key.to_string(),
+            task_id: task.id as i64,
+            uid: task.uid.to_string(),
+            text: task.text.clone(),
+            status: task.status.clone(),
+            created_at: task.created_at.clone(),
+            deleted_at: chrono::Utc::now().to_rfc3339(),
+            must_finish: task.must_finish,
+            pinned: task.pinned,
+            tags: task.tags.clone(),
+            notes: task.notes.clone(),
+            due: task.due,
+            completed_at: task.completed_at.clone(),
+            priority: task.priority,
+            anchor: task.anchor.clone(),
+            attachments: task.attachments.clone(),
+            parent: task.parent.map(|id| id as i64),
+            order: task.order,
+            issue: task.issue.clone(),
+            assignee: task.assignee.clone(),
+            snoozed_until: task.snoozed_until.clone(),
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+}
+
+impl TryFrom<DeletedTaskDocument> for DeletedTask {
+    type Error = QuillError;
+
+    fn try_from(doc: DeletedTaskDocument) -> Result<Self> {
+        Ok(Self {
+            deleted_at: doc.deleted_at.clone(),
+            task: Task::try_from(doc)?,
+        })
+    }
+}
+
+impl TryFrom<DeletedTaskDocument> for Task {
+    type Error = QuillError;
+
+    fn try_from(doc: DeletedTaskDocument) -> Result<Self> {
+        Ok(Self {
+            id: doc.task_id as usize,
+            uid: parse_uid(&doc.uid, "deleted_tasks")?,
+            text: doc.text,
+            status: doc.status,
+            created_at: doc.created_at,
+            must_finish: doc.must_finish,
+            pinned: doc.pinned,
+            tags: doc.tags,
+            notes: doc.notes,
+            due: doc.due,
+            completed_at: doc.completed_at,
+            priority: doc.priority,
+            anchor: doc.anchor,
+            attachments: doc.attachments,
+            parent: doc.parent.map(|id| id as usize),
+            order: doc.order,
+            issue: doc.issue,
+            assignee: doc.assignee,
+            snoozed_until: doc.snoozed_until,
+        })
+    }
+}
+
+/// How long connecting may take before the server counts as unreachable,
+/// unless a longer server selection timeout is configured.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn connect_timeout(config: &MongoConfig) -> Duration {
+    config.server_selection_timeout_secs.map_or(CONNECT_TIMEOUT, |secs| CONNECT_TIMEOUT.max(Duration::from_secs(secs)))
+}
+
+fn timed_out(config: &MongoConfig) -> QuillError {
+    QuillError::StorageUnavailable(format!("MongoDB connection timeout after {} seconds", connect_timeout(config).as_secs()))
+}
+
+/// Driver options for `config`: the connection string's, with the TLS,
+/// auth, timeout and pool settings of `config` on top.
+pub async fn client_options(config: &MongoConfig) -> Result<ClientOptions> {
+    let mut options = ClientOptions::parse(config.uri()?).await?;
+    if config.tls || !config.tls_ca_file.is_empty() || !config.tls_cert_key_file.is_empty() || config.tls_allow_invalid_certificates {
+        // Keeps TLS settings from the connection string
+        let mut tls = match options.tls.take() {
+            Some(Tls::Enabled(tls)) => tls,
+            _ => TlsOptions::default(),
+        };
+        if !config.tls_ca_file.is_empty() {
+            tls.ca_file_path = Some(pem_file(&config.tls_ca_file, "TLS CA file")?);
+        }
+        if !config.tls_cert_key_file.is_empty() {
+            tls.cert_key_file_path = Some(pem_file(&config.tls_cert_key_file, "TLS certificate file")?);
+        }
+        if config.tls_allow_invalid_certificates {
+            tls.allow_invalid_certificates = Some(true);
+        }
+        options.tls = Some(Tls::Enabled(tls));
+    }
+    if !config.auth_mechanism.is_empty() || !config.auth_source.is_empty() {
+        let credential = options.credential.get_or_insert_with(Credential::default);
+        if !config.auth_mechanism.is_empty() {
+            let mechanism = config.auth_mechanism.to_uppercase().parse().map_err(|_| {
+                QuillError::Validation(format!(
+                    "unknown MongoDB auth mechanism \"{}\", expected e.g. SCRAM-SHA-256, MONGODB-X509 or PLAIN",
+                    config.auth_mechanism
+                ))
+            })?;
+            credential.mechanism = Some(mechanism);
+        }
+        if !config.auth_source.is_empty() {
+            credential.source = Some(config.auth_source.clone());
+        }
+    }
+    if let Some(secs) = config.server_selection_timeout_secs {
+        options.server_selection_timeout = Some(Duration::from_secs(secs));
+    }
+    if config.max_pool_size.is_some() {
+        options.max_pool_size = config.max_pool_size;
+    }
+    if config.min_pool_size.is_some() {
+        options.min_pool_size = config.min_pool_size;
+    }
+    Ok(options)
+}
+
+/// `path` with `~/` expanded, which must be an existing file.
+fn pem_file(path: &str, what: &str) -> Result<PathBuf> {
+    let path = match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    };
+    if !path.is_file() {
+        return Err(QuillError::Validation(format!("{} {} doesn't exist", what, path.display())));
+    }
+    Ok(path)
+}
+
+/// Connects and pings the server without reading or changing any data;
+/// how long it took to answer.
+pub async fn ping(config: &MongoConfig) -> Result<Duration> {
+    let ping = async {
+        let started = Instant::now();
+        let client = Client::with_options(client_options(config).await?)?;
+        client.database(&config.database).run_command(doc! { "ping": 1 }).await?;
+        Ok::<_, QuillError>(started.elapsed())
+    };
+    timeout(connect_timeout(config), ping).await.map_err(|_| timed_out(config))?
+}
+
+/// One write of a change that takes several, see `MongoTaskStorage::apply`.
+enum Write {
+    Insert(Collection<bson::Document>, bson::Document),
+    Update(Collection<bson::Document>, bson::Document, bson::Document),
+    Delete(Collection<bson::Document>, bson::Document),
+    /// Deletes one document and fails the change when there was none to
+    /// delete, so of two clients racing for it only one goes on.
+    Claim(Collection<bson::Document>, bson::Document),
+}
+
+/// The error a `Write::Claim` fails with when another client got there first.
+#[derive(Debug)]
+struct AlreadyClaimed;
+
+impl Write {
+    fn insert<T: Serialize + Send + Sync>(collection: &Collection<T>, value: &T) -> Result<Self> {
+        Ok(Write::Insert(collection.clone_with_type(), bson::to_document(value)?))
+    }
+
+    fn update<T: Send + Sync>(collection: &Collection<T>, filter: bson::Document, update: bson::Document) -> Self {
+        Write::Update(collection.clone_with_type(), filter, update)
+    }
+
+    fn delete<T: Send + Sync>(collection: &Collection<T>, filter: bson::Document) -> Self {
+        Write::Delete(collection.clone_with_type(), filter)
+    }
+
+    fn claim<T: Send + Sync>(collection: &Collection<T>, filter: bson::Document) -> Self {
+        Write::Claim(collection.clone_with_type(), filter)
+    }
+
+    async fn run(&self, session: Option<&mut ClientSession>) -> mongodb::error::Result<()> {
+        match self {
+            Write::Insert(collection, document) => {
+                let insert = collection.insert_one(document);
+                match session {
+                    Some(session) => insert.session(session).await?,
+                    None => insert.await?,
+                };
+            }
+            Write::Update(collection, filter, update) => {
+                let update = collection.update_one(filter.clone(), update.clone());
+                match session {
+                    Some(session) => update.session(session).await?,
+                    None => update.await?,
+                };
+            }
+            Write::Delete(collection, filter) => {
+                let delete = collection.delete_one(filter.clone());
+                match session {
+                    Some(session) => delete.session(session).await?,
+                    None => delete.await?,
+                };
+            }
+            Write::Claim(collection, filter) => {
+                let delete = collection.delete_one(filter.clone());
+                let result = match session {
+                    Some(session) => delete.session(session).await?,
+                    None => delete.await?,
+                };
+                if result.deleted_count != 1 {
+                    return Err(mongodb::error::Error::custom(AlreadyClaimed));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the server runs multi-document transactions: replica set
+/// members and mongos routers do, standalone servers don't.
+async fn supports_transactions(db: &Database) -> bool {
+    match db.run_command(doc! { "hello": 1 }).await {
+        Ok(hello) => hello.contains_key("setName") || hello.get_str("msg") == Ok("isdbgrid"),
+        Err(_) => false,
+    }
+}
+
+pub struct MongoTaskStorage {
+    collection: Collection<TaskDocument>,
+    counter_collection: Collection<CounterDocument>,
+    deleted_collection: Collection<DeletedTaskDocument>,
+    tombstone_collection: Collection<TombstoneDocument>,
+    activity_collection: Collection<ActivityDocument>,
+    trash_retention: usize,
+    /// Whether changes of several writes run in a transaction.
+    transactions: bool,
+    db: Database,
+    client: Client,
+}
+
+impl MongoTaskStorage {
+    pub async fn new(config: &MongoConfig) -> Result<Self> {
+        let (database, collection) = (config.database.as_str(), config.collection.as_str());
+        // The connection string may carry credentials, so it isn't logged
+        tracing::info!(database, collection, "connecting to MongoDB");
+        let connect_future = async {
+            let client = Client::with_options(client_options(config).await?)?;
+            
+            // Test the connection by running a simple command
+            let db = client.database(database);
+            db.run_command(doc! { "ping": 1 }).await?;
+            tracing::debug!(database, "MongoDB answered ping");
+            
+            let task_collection = db.collection::<TaskDocument>(collection);
+            let counter_collection = db.collection::<CounterDocument>("counters");
+            let deleted_collection = db.collection::<DeletedTaskDocument>("deleted_tasks");
+            let tombstone_collection = db.collection::<TombstoneDocument>("tombstones");
+            let activity_collection = db.collection::<ActivityDocument>("activity");
+
+            let storage = Self {
+                collection: task_collection,
+                counter_collection,
+                deleted_collection,
+                tombstone_collection,
+                activity_collection,
+                trash_retention: DEFAULT_TRASH_RETENTION,
+                transactions: supports_transactions(&db).await,
+                db,
+                client,
+            };
+            storage.upgrade_documents().await?;
+            storage.compact_tombstones().await?;
+            storage.ensure_indexes().await;
+            Ok::<Self, QuillError>(storage)
+        };
+        
+        timeout(connect_timeout(config), connect_future).await.map_err(|_| timed_out(config))?
+    }
+
+    /// Sets how many deleted tasks are kept per context.
+    pub fn with_trash_retention(mut self, retention: usize) -> Self {
+        self.trash_retention = retention;
+        self
+    }
+
+    /// Creates the text index `search_tasks` queries, the index tasks are
+    /// looked up by and the one the activity feed is read by, if they're
+    /// missing. Users allowed only to read and write tasks can't, so that is
+    /// just logged.
+    async fn ensure_indexes(&self) {
+        let index = IndexModel::builder()
+            .keys(doc! { "text": "text", "notes": "text", "tags": "text" })
+            .options(IndexOptions::builder().name(TEXT_INDEX.to_string()).build())
+            .build();
+        if let Err(e) = self.collection.create_index(index).await {
+            tracing::warn!(error = %e, "couldn't create the MongoDB text index; search will fail");
+        }
+        let index = IndexModel::builder().keys(doc! { "context_key": 1, "uid": 1 }).build();
+        if let Err(e) = self.collection.create_index(index).await {
+            tracing::warn!(error = %e, "couldn't create the MongoDB uid index; updates will scan the context");
+        }
+        let index = IndexModel::builder().keys(doc! { "context_key": 1, "at": -1 }).build();
+        if let Err(e) = self.activity_collection.create_index(index).await {
+            tracing::warn!(error = %e, "couldn't create the MongoDB activity index; the activity feed will scan the log");
+        }
+    }
+
+    /// Refuses collections a newer quill wrote to, and upgrades the
+    /// documents older releases wrote.
+    async fn upgrade_documents(&self) -> Result<()> {
+        for name in [self.collection.name(), self.deleted_collection.name()] {
+            let raw = self.db.collection::<bson::Document>(name);
+            let newer = doc! { "schema_version": { "$gt": SCHEMA_VERSION as i64 } };
+            if let Some(document) = raw.find_one(newer).await? {
+                let version = document.get_i64("schema_version").or_else(|_| document.get_i32("schema_version").map(i64::from));
+                schema::check_version(version.unwrap_or(i64::MAX) as u32, &format!("The MongoDB collection \"{}\"", name))?;
+            }
+
+            let older = doc! { "$or": [
+                { "schema_version": { "$exists": false } },
+                { "schema_version": { "$lt": SCHEMA_VERSION as i64 } },
+            ] };
+            let mut cursor = raw.find(older).await?;
+            let mut upgraded = 0;
+            while cursor.advance().await? {
+                let mut document = cursor.deserialize_current()?;
+                let Some(id) = document.remove("_id") else {
+                    continue;
+                };
+                let mut task = serde_json::to_value(&document)?;
+                let version = schema::version_of(&task);
+                schema::upgrade_task(&mut task, version);
+                task["schema_version"] = SCHEMA_VERSION.into();
+                raw.replace_one(doc! { "_id": id }, bson::to_document(&task)?).await?;
+                upgraded += 1;
+            }
+            if upgraded > 0 {
+                tracing::info!(collection = name, upgraded, version = SCHEMA_VERSION, "upgraded MongoDB documents");
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes tombstones past the retention period.
+    async fn compact_tombstones(&self) -> Result<()> {
+        let cutoff = (chrono::Utc::now() - chrono::Duration::days(TOMBSTONE_RETENTION_DAYS)).to_rfc3339();
+        let result = self.tombstone_collection.delete_many(doc! { "deleted_at": { "$lt": cutoff } }).await?;
+        if result.deleted_count > 0 {
+            tracing::debug!(compacted = result.deleted_count, "compacted MongoDB tombstones");
+        }
+        Ok(())
+    }
+
+    /// Records that a task left the trash for good.
+    fn tombstone(&self, context_key: &str, uid: Uuid) -> Result<Write> {
+        let tombstone = Tombstone::new(uid);
+        let doc = TombstoneDocument { context_key: context_key.to_string(), uid: tombstone.uid.to_string(), deleted_at: tombstone.deleted_at };
+        Write::insert(&self.tombstone_collection, &doc)
+    }
+
+    /// Applies `writes` in order: in one transaction when the server has
+    /// them, so an interrupted change leaves nothing half done, and one
+    /// after another on a standalone server. A failed `Write::Claim` stops
+    /// the change with `QuillError::Conflict`.
+    async fn apply(&self, writes: Vec<Write>) -> Result<()> {
+        let claimed = |e: mongodb::error::Error| match e.get_custom::<AlreadyClaimed>() {
+            Some(_) => QuillError::Conflict("another client changed the task first".to_string()),
+            None => QuillError::from(e),
+        };
+        if !self.transactions {
+            for write in &writes {
+                write.run(None).await.map_err(claimed)?;
+            }
+            return Ok(());
+        }
+        let mut session = self.client.start_session().await?;
+        let mut attempts = 1;
+        loop {
+            session.start_transaction().await?;
+            let mut outcome = Ok(());
+            for write in &writes {
+                outcome = write.run(Some(&mut session)).await;
+                if outcome.is_err() {
+                    break;
+                }
+            }
+            let outcome = match outcome {
+                Ok(()) => session.commit_transaction().await,
+                Err(e) => {
+                    let _ = session.abort_transaction().await;
+                    Err(e)
+                }
+            };
+            match outcome {
+                // e.g. a write conflict with another client; the server
+                // says trying again may work
+                Err(e) if attempts < 3 && e.contains_label(TRANSIENT_TRANSACTION_ERROR) => attempts += 1,
+                outcome => return outcome.map_err(claimed),
+            }
+        }
+    }
+
+    /// Moves a task from the trash back to the end of its list; `None` when
+    /// another client restored or purged it first.
+    async fn restore(&self, context_key: &str, deleted_doc: DeletedTaskDocument) -> Result<Option<Task>> {
+        let Some(object_id) = deleted_doc.id else {
+            return Ok(None);
+        };
+        let mut task = Task::try_from(deleted_doc)?;
+        task.order = self.next_order(context_key).await?;
+        // Claimed first, so on a standalone server the losing client stops
+        // before inserting a second copy
+        let writes = vec![
+            Write::claim(&self.deleted_collection, doc! { "_id": object_id }),
+            Write::insert(&self.collection, &TaskDocument::from((context_key, &task)))?,
+        ];
+        match self.apply(writes).await {
+            Ok(()) => Ok(Some(task)),
+            Err(QuillError::Conflict(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The `order` of a task added at the end of the context.
+    async fn next_order(&self, context_key: &str) -> Result<f64> {
+        let last = self.collection.find_one(doc! { "context_key": context_key }).sort(doc! { "order": -1 }).await?;
+        Ok(last.map_or(1.0, |doc| doc.order + 1.0))
+    }
+
+    /// Moves a task one place up or down by giving it an `order` between its
+    /// new neighbours, renumbering the context when they leave no room.
+    async fn move_task(&mut self, context_key: &str, id: usize, up: bool) -> Result<bool> {
+        let tasks = self.get_tasks(context_key).await?;
+        let Some(pos) = tasks.iter().position(|t| t.id == id) else {
+            return Ok(false);
+        };
+        if (up && pos == 0) || (!up && pos + 1 == tasks.len()) {
+            return Ok(false);
+        }
+        let neighbours = |orders: &[f64]| match up {
+            true => (pos.checked_sub(2).map(|i| orders[i]), Some(orders[pos - 1])),
+            false => (Some(orders[pos + 1]), orders.get(pos + 2).copied()),
+        };
+
+        let mut orders: Vec<f64> = tasks.iter().map(|t| t.order).collect();
+        let (before, after) = neighbours(&orders);
+        let mut writes = Vec::new();
+        let order = match order_between(before, after) {
+            Some(order) => order,
+            None => {
+                for (i, task) in tasks.iter().enumerate() {
+                    let filter = doc! { "context_key": context_key, "uid": task.uid.to_string() };
+                    writes.push(Write::update(&self.collection, filter, doc! { "$set": { "order": i as f64 + 1.0 } }));
+                }
+                orders = (1..=tasks.len()).map(|i| i as f64).collect();
+                let (before, after) = neighbours(&orders);
+                order_between(before, after).unwrap_or_default()
+            }
+        };
+        let filter = doc! { "context_key": context_key, "task_id": id as i64 };
+        writes.push(Write::update(&self.collection, filter, doc! { "$set": { "order": order } }));
+        self.apply(writes).await?;
+        Ok(true)
+    }
+
+    /// The next task id of `context_key`; every context counts on its own.
+    async fn get_next_counter_value(&self, context_key: &str) -> Result<i64> {
+        let filter = doc! { "_id": counter_id(context_key) };
+        loop {
+            let counter = self.counter_collection
+                .find_one_and_update(filter.clone(), doc! { "$inc": { "value": 1 } })
+                .return_document(mongodb::options::ReturnDocument::After)
+                .await?;
+            if let Some(counter) = counter {
+                return Ok(counter.value);
+            }
+            // A new counter starts above the ids the context already has,
+            // which came from the one counter all contexts used to share.
+            // `$max` keeps a counter another client created meanwhile.
+            let highest = self.highest_task_id(context_key).await?;
+            self.counter_collection
+                .update_one(filter.clone(), doc! { "$max": { "value": highest } })
+                .upsert(true)
+                .await?;
+        }
+    }
+
+    /// The highest id among the context's tasks, deleted ones included.
+    async fn highest_task_id(&self, context_key: &str) -> Result<i64> {
+        let filter = doc! { "context_key": context_key };
+        let newest = doc! { "task_id": -1 };
+        let task = self.collection.find_one(filter.clone()).sort(newest.clone()).await?;
+        let deleted = self.deleted_collection.find_one(filter).sort(newest).await?;
+        Ok(task.map_or(0, |doc| doc.task_id).max(deleted.map_or(0, |doc| doc.task_id)))
+    }
+
+    /// Task counts per context of the tasks matching `filter`, counted by the
+    /// server in one aggregation.
+    async fn count_tasks(&self, filter: bson::Document) -> Result<BTreeMap<String, TaskCounts>> {
+        let mut cursor = self
+            .collection
+            .aggregate([
+                doc! { "$match": filter },
+                doc! { "$group": { "_id": { "context_key": "$context_key", "status": "$status" }, "count": { "$sum": 1 } } },
+            ])
+            .with_type::<CountGroup>()
+            .await?;
+        let mut counts: BTreeMap<String, TaskCounts> = BTreeMap::new();
+        while cursor.advance().await? {
+            // `$sum` gives an Int32 or, past its range, an Int64; both read as i64
+            let group = cursor.deserialize_current()?;
+            let count = usize::try_from(group.count).map_err(|_| QuillError::Corrupt(format!("negative task count {}", group.count)))?;
+            let counts = counts.entry(group.key.context_key).or_default();
+            match group.key.status {
+                TaskStatus::NotStarted => counts.not_started += count,
+                TaskStatus::InProgress => counts.in_progress += count,
+                TaskStatus::Completed => counts.completed += count,
+            }
+        }
+        Ok(counts)
+    }
+}
+
+#[async_trait]
+impl TaskStorage for MongoTaskStorage {
+    async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>> {
+        let filter = doc! { "context_key": context_key };
+        let mut cursor = self.collection.find(filter).await?;
+        let mut tasks = Vec::new();
+
+        while cursor.advance().await? {
+            let doc = cursor.deserialize_current()?;
+            tasks.push(Task::try_from(doc)?);
+        }
+
+        tasks.sort_by(|a, b| a.order.total_cmp(&b.order).then(a.id.cmp(&b.id)));
+        Ok(tasks)
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<String>> {
+        let values = self.collection.distinct("context_key", doc! {}).await?;
+        let mut keys: Vec<String> = values
+            .into_iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn get_all_tasks(&self) -> Result<BTreeMap<String, Vec<Task>>> {
+        // One query for every context instead of one per context
+        let mut cursor = self.collection.find(doc! {}).await?;
+        let mut all: BTreeMap<String, Vec<Task>> = BTreeMap::new();
+        while cursor.advance().await? {
+            let doc = cursor.deserialize_current()?;
+            all.entry(doc.context_key.clone()).or_default().push(Task::try_from(doc)?);
+        }
+        for tasks in all.values_mut() {
+            tasks.sort_by(|a, b| a.order.total_cmp(&b.order).then(a.id.cmp(&b.id)));
+        }
+        Ok(all)
+    }
+
+    async fn get_task_counts(&self, context_key: &str) -> Result<TaskCounts> {
+        Ok(self.count_tasks(doc! { "context_key": context_key }).await?.remove(context_key).unwrap_or_default())
+    }
+
+    async fn all_task_counts(&self) -> Result<BTreeMap<String, TaskCounts>> {
+        self.count_tasks(doc! {}).await
+    }
+
+    async fn search_tasks(&self, query: &str) -> Result<BTreeMap<String, Vec<Task>>> {
+        let words: Vec<String> = search::words(query).collect();
+        if words.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+        // Quoted, every word must occur; the text index matches whole words
+        // (stemmed) rather than their starts
+        let phrases = words.iter().map(|word| format!("\"{}\"", word)).collect::<Vec<_>>().join(" ");
+        let mut cursor = self.collection.find(doc! { "$text": { "$search": phrases } }).await?;
+        let mut found: BTreeMap<String, Vec<Task>> = BTreeMap::new();
+        while cursor.advance().await? {
+            let doc = cursor.deserialize_current()?;
+            found.entry(doc.context_key.clone()).or_default().push(Task::try_from(doc)?);
+        }
+        for tasks in found.values_mut() {
+            tasks.sort_by(|a, b| a.order.total_cmp(&b.order).then(a.id.cmp(&b.id)));
+        }
+        Ok(found)
+    }
+
+    async fn add_task(&mut self, context_key: &str, text: String) -> Result<usize> {
+        let task_id = self.get_next_counter_value(context_key).await?;
+        let mut task = Task::new(task_id as usize, text);
+        task.order = self.next_order(context_key).await?;
+        let doc = TaskDocument::from((context_key, &task));
+        
+        self.collection.insert_one(&doc).await?;
+        Ok(task_id as usize)
+    }
+
+    async fn toggle_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let filter = doc! { "context_key": context_key, "task_id": id as i64 };
+        
+        // First, get the current task to determine next status
+        if let Some(doc) = self.collection.find_one(filter.clone()).await? {
+            let mut task = Task::try_from(doc)?;
+            task.set_status(task.status.next());
+            let update = doc! { "$set": {
+                "status": bson::to_bson(&task.status)?,
+                "completed_at": bson::to_bson(&task.completed_at)?,
+            } };
+            let result = self.collection.update_one(filter, update).await?;
+            Ok(result.modified_count > 0)
+        } else {
+            Ok(false)
+        }
+    }
+
+    async fn set_task_status(&mut self, context_key: &str, id: usize, status: TaskStatus) -> Result<bool> {
+        let filter = doc! { "context_key": context_key, "task_id": id as i64 };
+
+        let Some(doc) = self.collection.find_one(filter.clone()).await? else {
+            return Ok(false);
+        };
+        let mut task = Task::try_from(doc)?;
+        task.set_status(status);
+        let update = doc! { "$set": {
+            "status": bson::to_bson(&task.status)?,
+            "completed_at": bson::to_bson(&task.completed_at)?,
+        } };
+        
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn remove_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let filter = doc! { "context_key": context_key, "task_id": id as i64 };
+        let Some(task_doc) = self.collection.find_one(filter.clone()).await? else {
+            return Ok(false);
+        };
+        let task = Task::try_from(task_doc)?;
+        // Without a transaction an interrupted removal leaves the task in
+        // both places rather than in neither
+        let mut writes = vec![if self.trash_retention > 0 {
+            Write::insert(&self.deleted_collection, &DeletedTaskDocument::from((context_key, &task)))?
+        } else {
+            self.tombstone(context_key, task.uid)?
+        }];
+        writes.push(Write::delete(&self.collection, filter));
+
+        // The trash keeps the configured number per context, newest first
+        let mut cursor = self.deleted_collection
+            .find(doc! { "context_key": context_key })
+            .sort(doc! { "deleted_at": -1 })
+            .await?;
+        let mut trash = Vec::new();
+        while cursor.advance().await? {
+            trash.push(cursor.deserialize_current()?);
+        }
+        for deleted in trash.into_iter().skip(self.trash_retention.saturating_sub(1)) {
+            if let Some(object_id) = deleted.id {
+                writes.push(Write::delete(&self.deleted_collection, doc! { "_id": object_id }));
+                writes.push(self.tombstone(context_key, Task::try_from(deleted)?.uid)?);
+            }
+        }
+        self.apply(writes).await?;
+        Ok(true)
+    }
+
+    async fn edit_task(&mut self, context_key: &str, id: usize, new_text: String) -> Result<bool> {
+        let filter = doc! { "context_key": context_key, "task_id": id as i64 };
+        let update = doc! { "$set": { "text": new_text } };
+        
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn update_task(&mut self, context_key: &str, task: Task) -> Result<bool> {
+        let filter = doc! { "context_key": context_key, "uid": task.uid.to_string() };
+        let replacement = TaskDocument::from((context_key, &task));
+
+        let result = self.collection.replace_one(filter, &replacement).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn undo_delete(&mut self, context_key: &str) -> Result<Option<Task>> {
+        let newest = self.deleted_collection
+            .find_one(doc! { "context_key": context_key })
+            .sort(doc! { "deleted_at": -1 })
+            .await?;
+        match newest {
+            Some(deleted_doc) => self.restore(context_key, deleted_doc).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>> {
+        let mut cursor = self.deleted_collection
+            .find(doc! { "context_key": context_key })
+            .sort(doc! { "deleted_at": -1 })
+            .await?;
+
+        let mut deleted = Vec::new();
+        while cursor.advance().await? {
+            deleted.push(DeletedTask::try_from(cursor.deserialize_current()?)?);
+        }
+        Ok(deleted)
+    }
+
+    async fn restore_deleted(&mut self, context_key: &str, id: usize) -> Result<Option<Task>> {
+        let filter = doc! { "context_key": context_key, "task_id": id as i64 };
+        match self.deleted_collection.find_one(filter).await? {
+            Some(deleted_doc) => self.restore(context_key, deleted_doc).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn purge_deleted(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let filter = doc! { "context_key": context_key, "task_id": id as i64 };
+        let Some(deleted) = self.deleted_collection.find_one(filter).await? else {
+            return Ok(false);
+        };
+        let Some(object_id) = deleted.id else {
+            return Ok(false);
+        };
+        let uid = Task::try_from(deleted)?.uid;
+        let writes = vec![Write::claim(&self.deleted_collection, doc! { "_id": object_id }), self.tombstone(context_key, uid)?];
+        match self.apply(writes).await {
+            Ok(()) => Ok(true),
+            Err(QuillError::Conflict(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>> {
+        let mut cursor = self.tombstone_collection.find(doc! { "context_key": context_key }).await?;
+        let mut tombstones = Vec::new();
+        while cursor.advance().await? {
+            tombstones.push(Tombstone::try_from(cursor.deserialize_current()?)?);
+        }
+        Ok(tombstones)
+    }
+
+    async fn record_activity(&mut self, entries: Vec<ActivityEntry>) -> Result<()> {
+        if !entries.is_empty() {
+            self.activity_collection.insert_many(entries.into_iter().map(ActivityDocument::from)).await?;
+        }
+        Ok(())
+    }
+
+    async fn list_activity(&self, context_key: &str) -> Result<Vec<ActivityEntry>> {
+        let mut cursor = self
+            .activity_collection
+            .find(doc! { "context_key": context_key })
+            .sort(doc! { "at": -1 })
+            .limit(ACTIVITY_LIMIT as i64)
+            .await?;
+        let mut entries = Vec::new();
+        while cursor.advance().await? {
+            entries.push(ActivityEntry::try_from(cursor.deserialize_current()?)?);
+        }
+        Ok(entries)
+    }
+
+    async fn export_activity(&self, since: Option<&str>) -> Result<Vec<ActivityEntry>> {
+        // Times are stored as UTC RFC 3339 strings, which sort by time
+        let since = since
+            .map(|since| chrono::DateTime::parse_from_rfc3339(since).map(|at| at.with_timezone(&chrono::Utc).to_rfc3339()))
+            .transpose()
+            .map_err(|e| QuillError::Validation(format!("invalid time: {}", e)))?;
+        let filter = match since {
+            Some(since) => doc! { "at": { "$gte": since } },
+            None => doc! {},
+        };
+        let mut cursor = self.activity_collection.find(filter).sort(doc! { "at": 1 }).await?;
+        let mut entries = Vec::new();
+        while cursor.advance().await? {
+            entries.push(ActivityEntry::try_from(cursor.deserialize_current()?)?);
+        }
+        Ok(entries)
+    }
+
+    async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String> {
+        let raw = self.collection.clone_with_type::<bson::Document>();
+        let task_doc = raw
+            .find_one(doc! { "context_key": context_key, "task_id": id as i64 })
+            .await?;
+
+        let mut context_docs = Vec::new();
+        let mut cursor = raw.find(doc! { "context_key": context_key }).await?;
+        while cursor.advance().await? {
+            context_docs.push(cursor.deserialize_current()?);
+        }
+
+        let counter = self.counter_collection
+            .clone_with_type::<bson::Document>()
+            .find_one(doc! { "_id": counter_id(context_key) })
+            .await?;
+
+        Ok(format!(
+            "// document for task {} in {}\n{}\n\n// {} documents in {} for {}\n{}\n\n// counter\n{}",
+            id,
+            self.collection.name(),
+            serde_json::to_string_pretty(&task_doc)?,
+            context_docs.len(),
+            self.collection.name(),
+            context_key,
+            serde_json::to_string_pretty(&context_docs)?,
+            serde_json::to_string_pretty(&counter)?,
+        ))
+    }
+
+    async fn health(&self) -> Result<BackendHealth> {
+        let mut health = BackendHealth::new("MongoDB");
+        let started = Instant::now();
+        self.db.run_command(doc! { "ping": 1 }).await?;
+        health.detail("Latency", format!("{} ms", started.elapsed().as_millis()));
+        let build_info = self.db.run_command(doc! { "buildInfo": 1 }).await?;
+        health.detail("Server version", build_info.get_str("version").unwrap_or("unknown"));
+        health.detail("Database", self.db.name());
+        health.detail("Collection", self.collection.name());
+        health.detail("Transactions", if self.transactions {
+            "yes"
+        } else {
+            "no (standalone server): changes of several writes are applied one at a time"
+        });
+
+        let indexes = self.collection.list_index_names().await?;
+        health.detail("Indexes", indexes.join(", "));
+        if !indexes.iter().any(|name| name.starts_with("context_key")) {
+            health.warnings.push(format!(
+                "No index on context_key; every context lookup scans {}",
+                self.collection.name()
+            ));
+        }
+        if !indexes.iter().any(|name| name == TEXT_INDEX) {
+            health.warnings.push(format!("No text index {}; search fails until a user allowed to create indexes connects", TEXT_INDEX));
+        }
+        health.detail("Trash", self.deleted_collection.count_documents(doc! {}).await?.to_string());
+
+        let mut contexts = Vec::new();
+        let mut cursor = self.collection
+            .aggregate([doc! { "$group": { "_id": "$context_key", "count": { "$sum": 1 } } }])
+            .await?;
+        while cursor.advance().await? {
+            let group = cursor.deserialize_current()?;
+            if let (Ok(context_key), Ok(count)) = (group.get_str("_id"), group.get_i32("count")) {
+                contexts.push((context_key.to_string(), count as usize));
+            }
+        }
+        health.set_contexts(contexts);
+        Ok(health)
+    }
+
+    async fn move_task_up(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.move_task(context_key, id, true).await
+    }
+
+    async fn move_task_down(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.move_task(context_key, id, false).await
+    }
+
+    async fn delete_context(&mut self, context_key: &str) -> Result<usize> {
+        let tasks = self.get_tasks(context_key).await?;
+        self.collection.delete_many(doc! { "context_key": context_key }).await?;
+        let tombstones: Vec<TombstoneDocument> = tasks
+            .iter()
+            .map(|task| {
+                let tombstone = Tombstone::new(task.uid);
+                TombstoneDocument { context_key: context_key.to_string(), uid: tombstone.uid.to_string(), deleted_at: tombstone.deleted_at }
+            })
+            .collect();
+        if !tombstones.is_empty() {
+            self.tombstone_collection.insert_many(tombstones).await?;
+        }
+        Ok(tasks.len())
+    }
+
+    async fn move_context_tasks(&mut self, from: &str, to: &str) -> Result<usize> {
+        let ids: Vec<usize> = self.get_tasks(from).await?.iter().map(|t| t.id).collect();
+        self.move_tasks(from, &ids, to).await
+    }
+
+    async fn move_tasks(&mut self, from: &str, ids: &[usize], to: &str) -> Result<usize> {
+        if from == to {
+            return Ok(0);
+        }
+        // Ids are per context, so the moved tasks get new ones from `to`
+        let tasks: Vec<Task> = self.get_tasks(from).await?.into_iter().filter(|t| ids.contains(&t.id)).collect();
+        let mut new_ids = BTreeMap::new();
+        for task in &tasks {
+            new_ids.insert(task.id, self.get_next_counter_value(to).await?);
+        }
+        let start = self.next_order(to).await?;
+        let mut writes = Vec::new();
+        for (i, task) in tasks.iter().enumerate() {
+            let parent = task.parent.and_then(|parent| new_ids.get(&parent).copied());
+            writes.push(Write::update(
+                &self.collection,
+                doc! { "context_key": from, "uid": task.uid.to_string() },
+                doc! { "$set": {
+                    "context_key": to,
+                    "task_id": new_ids[&task.id],
+                    "order": start + i as f64,
+                    "parent": parent,
+                } },
+            ));
+        }
+        self.apply(writes).await?;
+        Ok(tasks.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::strategies::{arb_task, arb_text};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn prop_task_document_round_trip(context_key in arb_text(), task in arb_task()) {
+            let doc = bson::to_document(&TaskDocument::from((context_key.as_str(), &task))).unwrap();
+            let decoded: TaskDocument = bson::from_document(doc).unwrap();
+
+            prop_assert_eq!(&decoded.context_key, &context_key);
+            prop_assert_eq!(Task::try_from(decoded).unwrap(), task);
+        }
+
+        #[test]
+        fn prop_deleted_task_document_round_trip(context_key in arb_text(), task in arb_task()) {
+            let doc = bson::to_document(&DeletedTaskDocument::from((context_key.as_str(), &task))).unwrap();
+            let decoded: DeletedTaskDocument = bson::from_document(doc).unwrap();
+
+            let deleted = DeletedTask::try_from(decoded)?;
+            prop_assert!(!deleted.deleted_at.is_empty());
+            prop_assert_eq!(deleted.task, task);
+        }
+    }
+
+    #[test]
+    fn test_invalid_uids_are_corrupt() {
+        let mut doc = TaskDocument::from(("acme:web:main", &Task::new(1, "Ship".to_string())));
+        doc.uid = "not-a-uid".to_string();
+        assert!(matches!(Task::try_from(doc), Err(QuillError::Corrupt(_))));
+    }
+
+    #[tokio::test]
+    async fn test_client_options_apply_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca_file = dir.path().join("acme-ca.pem");
+        std::fs::write(&ca_file, "").unwrap();
+        let config = MongoConfig {
+            connection_string: "mongodb://app:pw@db.acme.internal:27017/?maxPoolSize=50".to_string(),
+            tls_ca_file: ca_file.to_string_lossy().into_owned(),
+            auth_mechanism: "scram-sha-256".to_string(),
+            auth_source: "admin".to_string(),
+            server_selection_timeout_secs: Some(3),
+            min_pool_size: Some(2),
+            ..MongoConfig::default()
+        };
+
+        let options = client_options(&config).await.unwrap();
+        assert!(matches!(&options.tls, Some(Tls::Enabled(tls)) if tls.ca_file_path.as_deref() == Some(ca_file.as_path())));
+        let credential = options.credential.unwrap();
+        assert_eq!(credential.username.as_deref(), Some("app"));
+        assert_eq!(credential.mechanism, Some(mongodb::options::AuthMechanism::ScramSha256));
+        assert_eq!(credential.source.as_deref(), Some("admin"));
+        assert_eq!(options.server_selection_timeout, Some(Duration::from_secs(3)));
+        // Options left unset keep the connection string's
+        assert_eq!((options.min_pool_size, options.max_pool_size), (Some(2), Some(50)));
+
+        let missing = MongoConfig { tls_ca_file: "/nonexistent/ca.pem".to_string(), ..config.clone() };
+        assert!(matches!(client_options(&missing).await, Err(QuillError::Validation(_))));
+        let unknown = MongoConfig { auth_mechanism: "KERBEROS5".to_string(), ..config };
+        assert!(matches!(client_options(&unknown).await, Err(QuillError::Validation(_))));
+    }
+
+    #[test]
+    fn test_count_groups_read_either_int_width() {
+        let status = bson::to_bson(&TaskStatus::InProgress).unwrap();
+        let group = |count: bson::Bson| doc! { "_id": { "context_key": "acme:web:main", "status": status.clone() }, "count": count };
+        assert_eq!(bson::from_document::<CountGroup>(group(bson::Bson::Int32(3))).unwrap().count, 3);
+        assert_eq!(bson::from_document::<CountGroup>(group(bson::Bson::Int64(5_000_000_000))).unwrap().count, 5_000_000_000);
+        assert!(bson::from_document::<CountGroup>(group(bson::Bson::Double(1.5))).is_err());
+    }
+}
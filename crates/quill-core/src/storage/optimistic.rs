@@ -0,0 +1,524 @@
+use super::activity::ActivityEntry;
+use super::tracked::SaveTracker;
+use super::{local::LocalTaskStorage, BackendHealth, DeletedTask, ExternalChange, Task, TaskCounts, TaskStatus, TaskStorage, Tombstone};
+use crate::{QuillError, Result};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Mutex as AsyncMutex, MutexGuard};
+
+/// How long a cached context is shown before it is read again, so changes
+/// other writers make still turn up.
+const REFRESH_AFTER: Duration = Duration::from_secs(5);
+
+/// Wraps a slow backend so changes show at once: they are applied to a cached
+/// copy of the context and written by a background task in order, as the
+/// values they result in rather than the steps taken, so a write lands the
+/// same whatever else changed meanwhile. A write that fails drops the cached
+/// copy, undoing the change on the next read, and is reported by
+/// `take_failed_writes`; the writes queued behind it are dropped too, since
+/// they were made on top of it.
+///
+/// Adding a task waits for the backend, which picks the new task's id.
+pub struct OptimisticStorage {
+    inner: Arc<AsyncMutex<Box<dyn TaskStorage>>>,
+    cache: Arc<AsyncMutex<Cache>>,
+    jobs: mpsc::UnboundedSender<Job>,
+    /// Writes queued and not finished yet.
+    pending: Arc<AtomicUsize>,
+    /// Bumped when a write fails; writes queued before that are dropped.
+    failed_writes: Arc<AtomicU64>,
+    failures: Arc<Mutex<Vec<String>>>,
+    tracker: SaveTracker,
+    watch_path: Option<PathBuf>,
+}
+
+/// The contexts read so far, with the changes not yet written applied.
+struct Cache {
+    tasks: LocalTaskStorage,
+    read_at: HashMap<String, Instant>,
+}
+
+impl Cache {
+    fn store(&mut self, context_key: &str, tasks: Vec<Task>, deleted: Vec<DeletedTask>) {
+        self.tasks.contexts.insert(context_key.to_string(), tasks);
+        self.tasks.deleted_tasks.insert(context_key.to_string(), deleted.into());
+        self.read_at.insert(context_key.to_string(), Instant::now());
+    }
+
+    fn forget(&mut self, context_key: &str) {
+        self.tasks.contexts.remove(context_key);
+        self.tasks.deleted_tasks.remove(context_key);
+        self.read_at.remove(context_key);
+    }
+
+    fn clear(&mut self) {
+        self.tasks = LocalTaskStorage::in_memory();
+        self.read_at.clear();
+    }
+}
+
+/// A change already applied to the cache, waiting to be written: the state
+/// it left the task in, so it doesn't depend on what the backend holds by
+/// the time it is written.
+enum Write {
+    SetTaskStatus(usize, TaskStatus),
+    RemoveTask(usize),
+    EditTask(usize, String),
+    UpdateTask(Box<Task>),
+    RestoreDeleted(usize),
+    PurgeDeleted(usize),
+    /// Puts the task at `order` in the list, leaving the rest of it as the
+    /// backend has it.
+    SetOrder(usize, f64),
+}
+
+impl Write {
+    async fn apply(self, storage: &mut dyn TaskStorage, context_key: &str) -> Result<()> {
+        match self {
+            Write::SetTaskStatus(id, status) => storage.set_task_status(context_key, id, status).await.map(drop),
+            Write::RemoveTask(id) => storage.remove_task(context_key, id).await.map(drop),
+            Write::EditTask(id, text) => storage.edit_task(context_key, id, text).await.map(drop),
+            Write::UpdateTask(task) => storage.update_task(context_key, *task).await.map(drop),
+            Write::RestoreDeleted(id) => storage.restore_deleted(context_key, id).await.map(drop),
+            Write::PurgeDeleted(id) => storage.purge_deleted(context_key, id).await.map(drop),
+            Write::SetOrder(id, order) => {
+                let Some(mut task) = storage.get_tasks(context_key).await?.into_iter().find(|t| t.id == id) else {
+                    return Err(QuillError::Conflict(format!("task {} was deleted elsewhere before it could be moved", id)));
+                };
+                task.order = order;
+                storage.update_task(context_key, task).await.map(drop)
+            }
+        }
+    }
+}
+
+enum Job {
+    Write {
+        context_key: String,
+        write: Write,
+        /// `failed_writes` when it was queued.
+        failures_seen: u64,
+        tracker: SaveTracker,
+    },
+    /// Answered once every write queued before it is done.
+    Settle(oneshot::Sender<()>),
+}
+
+/// What the writer shares with `OptimisticStorage`.
+struct Writer {
+    inner: Arc<AsyncMutex<Box<dyn TaskStorage>>>,
+    cache: Arc<AsyncMutex<Cache>>,
+    pending: Arc<AtomicUsize>,
+    failed_writes: Arc<AtomicU64>,
+    failures: Arc<Mutex<Vec<String>>>,
+}
+
+/// Writes queued changes one at a time until the storage is dropped.
+async fn run_writer(mut jobs: mpsc::UnboundedReceiver<Job>, writer: Writer) {
+    while let Some(job) = jobs.recv().await {
+        match job {
+            Job::Write { context_key, write, failures_seen, tracker } => {
+                let result = if writer.failed_writes.load(Ordering::SeqCst) != failures_seen {
+                    // Made on top of a change that never reached the backend
+                    tracing::debug!(context = %context_key, "dropping a write queued behind a failed one");
+                    writer.cache.lock().await.forget(&context_key);
+                    Err(QuillError::StorageUnavailable("dropped after an earlier change couldn't be saved".to_string()))
+                } else {
+                    let result = write.apply(writer.inner.lock().await.as_mut(), &context_key).await;
+                    if let Err(e) = &result {
+                        tracing::warn!(context = %context_key, error = %e, "background write failed; undoing it and the writes after it");
+                        let mut cache = writer.cache.lock().await;
+                        writer.failed_writes.fetch_add(1, Ordering::SeqCst);
+                        cache.forget(&context_key);
+                        writer.failures.lock().unwrap_or_else(|e| e.into_inner()).push(e.to_string());
+                    }
+                    result
+                };
+                tracker.finish(&result);
+                writer.pending.fetch_sub(1, Ordering::SeqCst);
+            }
+            Job::Settle(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+impl OptimisticStorage {
+    /// Must be called within a tokio runtime.
+    pub fn new(inner: Box<dyn TaskStorage>) -> Self {
+        let watch_path = inner.watch_path();
+        let inner = Arc::new(AsyncMutex::new(inner));
+        let cache = Arc::new(AsyncMutex::new(Cache { tasks: LocalTaskStorage::in_memory(), read_at: HashMap::new() }));
+        let pending = Arc::new(AtomicUsize::new(0));
+        let failed_writes = Arc::new(AtomicU64::new(0));
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        let writer = Writer {
+            inner: inner.clone(),
+            cache: cache.clone(),
+            pending: pending.clone(),
+            failed_writes: failed_writes.clone(),
+            failures: failures.clone(),
+        };
+        tokio::spawn(run_writer(receiver, writer));
+        Self { inner, cache, jobs, pending, failed_writes, failures, tracker: SaveTracker::default(), watch_path }
+    }
+
+    /// Waits for the writes queued so far.
+    async fn settle(&self) {
+        let (done, settled) = oneshot::channel();
+        if self.jobs.send(Job::Settle(done)).is_ok() {
+            let _ = settled.await;
+        }
+    }
+
+    fn queue(&self, context_key: &str, write: Write) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.tracker.begin(Instant::now());
+        let job = Job::Write {
+            context_key: context_key.to_string(),
+            write,
+            failures_seen: self.failed_writes.load(Ordering::SeqCst),
+            tracker: self.tracker.clone(),
+        };
+        if self.jobs.send(job).is_err() {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            self.tracker.finish(&Ok(()));
+        }
+    }
+
+    /// Queues the new `order` of the tasks a move swapped.
+    async fn queue_move(&self, context_key: &str, id: usize, up: bool) -> Result<bool> {
+        let mut cache = self.cached(context_key).await?;
+        let moved = if up {
+            cache.tasks.move_task_up(context_key, id).await?
+        } else {
+            cache.tasks.move_task_down(context_key, id).await?
+        };
+        if moved {
+            let tasks = cache.tasks.get_tasks(context_key).await?;
+            let pos = tasks.iter().position(|t| t.id == id).unwrap_or_default();
+            let other = if up { pos + 1 } else { pos.wrapping_sub(1) };
+            for task in [tasks.get(pos), tasks.get(other)].into_iter().flatten() {
+                self.queue(context_key, Write::SetOrder(task.id, task.order));
+            }
+        }
+        Ok(moved)
+    }
+
+    /// The cache, holding a copy of the context read recently or changed
+    /// since; reading it again waits for queued writes first.
+    async fn cached(&self, context_key: &str) -> Result<MutexGuard<'_, Cache>> {
+        let cache = self.cache.lock().await;
+        if let Some(read_at) = cache.read_at.get(context_key) {
+            if read_at.elapsed() < REFRESH_AFTER || self.pending.load(Ordering::SeqCst) > 0 {
+                return Ok(cache);
+            }
+        }
+        // The writer takes the cache lock when a write fails
+        drop(cache);
+        self.settle().await;
+
+        let (tasks, deleted) = {
+            let inner = self.inner.lock().await;
+            (inner.get_tasks(context_key).await?, inner.list_deleted(context_key).await?)
+        };
+        let mut cache = self.cache.lock().await;
+        cache.store(context_key, tasks, deleted);
+        Ok(cache)
+    }
+
+    /// Runs a call the cache can't answer on the backend, once queued writes
+    /// are done.
+    async fn direct(&self) -> MutexGuard<'_, Box<dyn TaskStorage>> {
+        self.settle().await;
+        self.inner.lock().await
+    }
+}
+
+#[async_trait]
+impl TaskStorage for OptimisticStorage {
+    async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>> {
+        self.cached(context_key).await?.tasks.get_tasks(context_key).await
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<String>> {
+        self.direct().await.list_contexts().await
+    }
+
+    async fn get_all_tasks(&self) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.direct().await.get_all_tasks().await
+    }
+
+    async fn get_task_counts(&self, context_key: &str) -> Result<TaskCounts> {
+        Ok(TaskCounts::from_tasks(&self.get_tasks(context_key).await?))
+    }
+
+    async fn all_task_counts(&self) -> Result<BTreeMap<String, TaskCounts>> {
+        self.direct().await.all_task_counts().await
+    }
+
+    async fn open_task_counts(&self) -> Result<BTreeMap<String, usize>> {
+        self.direct().await.open_task_counts().await
+    }
+
+    async fn search_tasks(&self, query: &str) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.direct().await.search_tasks(query).await
+    }
+
+    async fn add_task(&mut self, context_key: &str, text: String) -> Result<usize> {
+        let id = self.direct().await.add_task(context_key, text).await?;
+        self.cache.lock().await.forget(context_key);
+        Ok(id)
+    }
+
+    async fn toggle_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let mut cache = self.cached(context_key).await?;
+        let toggled = cache.tasks.toggle_task(context_key, id).await?;
+        let status = cache.tasks.get_tasks(context_key).await?.into_iter().find(|t| t.id == id).map(|t| t.status);
+        drop(cache);
+        if let Some(status) = status.filter(|_| toggled) {
+            self.queue(context_key, Write::SetTaskStatus(id, status));
+        }
+        Ok(toggled)
+    }
+
+    async fn set_task_status(&mut self, context_key: &str, id: usize, status: TaskStatus) -> Result<bool> {
+        let set = self.cached(context_key).await?.tasks.set_task_status(context_key, id, status.clone()).await?;
+        if set {
+            self.queue(context_key, Write::SetTaskStatus(id, status));
+        }
+        Ok(set)
+    }
+
+    async fn remove_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let removed = self.cached(context_key).await?.tasks.remove_task(context_key, id).await?;
+        if removed {
+            self.queue(context_key, Write::RemoveTask(id));
+        }
+        Ok(removed)
+    }
+
+    async fn edit_task(&mut self, context_key: &str, id: usize, new_text: String) -> Result<bool> {
+        let edited = self.cached(context_key).await?.tasks.edit_task(context_key, id, new_text.clone()).await?;
+        if edited {
+            self.queue(context_key, Write::EditTask(id, new_text));
+        }
+        Ok(edited)
+    }
+
+    async fn update_task(&mut self, context_key: &str, task: Task) -> Result<bool> {
+        let updated = self.cached(context_key).await?.tasks.update_task(context_key, task.clone()).await?;
+        if updated {
+            self.queue(context_key, Write::UpdateTask(Box::new(task)));
+        }
+        Ok(updated)
+    }
+
+    async fn undo_delete(&mut self, context_key: &str) -> Result<Option<Task>> {
+        let restored = self.cached(context_key).await?.tasks.undo_delete(context_key).await?;
+        if let Some(task) = &restored {
+            self.queue(context_key, Write::RestoreDeleted(task.id));
+        }
+        Ok(restored)
+    }
+
+    async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>> {
+        self.cached(context_key).await?.tasks.list_deleted(context_key).await
+    }
+
+    async fn restore_deleted(&mut self, context_key: &str, id: usize) -> Result<Option<Task>> {
+        let restored = self.cached(context_key).await?.tasks.restore_deleted(context_key, id).await?;
+        if restored.is_some() {
+            self.queue(context_key, Write::RestoreDeleted(id));
+        }
+        Ok(restored)
+    }
+
+    async fn purge_deleted(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        let purged = self.cached(context_key).await?.tasks.purge_deleted(context_key, id).await?;
+        if purged {
+            self.queue(context_key, Write::PurgeDeleted(id));
+        }
+        Ok(purged)
+    }
+
+    async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>> {
+        self.direct().await.list_tombstones(context_key).await
+    }
+
+    async fn move_task_up(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.queue_move(context_key, id, true).await
+    }
+
+    async fn move_task_down(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.queue_move(context_key, id, false).await
+    }
+
+    async fn delete_context(&mut self, context_key: &str) -> Result<usize> {
+        let deleted = self.direct().await.delete_context(context_key).await?;
+        self.cache.lock().await.forget(context_key);
+        Ok(deleted)
+    }
+
+    async fn move_context_tasks(&mut self, from: &str, to: &str) -> Result<usize> {
+        let moved = self.direct().await.move_context_tasks(from, to).await?;
+        let mut cache = self.cache.lock().await;
+        cache.forget(from);
+        cache.forget(to);
+        Ok(moved)
+    }
+
+    async fn move_tasks(&mut self, from: &str, ids: &[usize], to: &str) -> Result<usize> {
+        let moved = self.direct().await.move_tasks(from, ids, to).await?;
+        let mut cache = self.cache.lock().await;
+        cache.forget(from);
+        cache.forget(to);
+        Ok(moved)
+    }
+
+    async fn record_activity(&mut self, entries: Vec<ActivityEntry>) -> Result<()> {
+        self.direct().await.record_activity(entries).await
+    }
+
+    async fn list_activity(&self, context_key: &str) -> Result<Vec<ActivityEntry>> {
+        self.direct().await.list_activity(context_key).await
+    }
+
+    async fn export_activity(&self, since: Option<&str>) -> Result<Vec<ActivityEntry>> {
+        self.direct().await.export_activity(since).await
+    }
+
+    async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String> {
+        self.direct().await.inspect_task(context_key, id).await
+    }
+
+    async fn health(&self) -> Result<BackendHealth> {
+        self.direct().await.health().await
+    }
+
+    fn watch_path(&self) -> Option<PathBuf> {
+        self.watch_path.clone()
+    }
+
+    async fn check_external_changes(&mut self) -> Result<ExternalChange> {
+        let change = self.direct().await.check_external_changes().await?;
+        if change == ExternalChange::Reloaded {
+            self.cache.lock().await.clear();
+        }
+        Ok(change)
+    }
+
+    async fn resolve_external_change(&mut self, keep_mine: bool) -> Result<()> {
+        let result = self.direct().await.resolve_external_change(keep_mine).await;
+        self.cache.lock().await.clear();
+        result
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.direct().await.flush().await?;
+        match self.take_failed_writes().as_slice() {
+            [] => Ok(()),
+            failures => Err(QuillError::StorageUnavailable(failures.join("; "))),
+        }
+    }
+
+    fn take_failed_writes(&mut self) -> Vec<String> {
+        std::mem::take(&mut *self.failures.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn track_saves(&mut self, tracker: SaveTracker) {
+        self.tracker = tracker;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::read_only::ReadOnlyStorage;
+
+    #[tokio::test]
+    async fn test_changes_show_at_once_and_are_written_in_order() {
+        let mut backend = LocalTaskStorage::in_memory();
+        let context = "test:repo:main";
+        let first = backend.add_task(context, "First".to_string()).await.unwrap();
+        backend.add_task(context, "Second".to_string()).await.unwrap();
+        let mut storage = OptimisticStorage::new(Box::new(backend));
+
+        assert!(storage.toggle_task(context, first).await.unwrap());
+        assert!(storage.move_task_down(context, first).await.unwrap());
+        let shown = storage.get_tasks(context).await.unwrap();
+        assert_eq!(shown[1].status, TaskStatus::InProgress);
+
+        storage.settle().await;
+        let written = storage.inner.lock().await.get_tasks(context).await.unwrap();
+        let summary = |tasks: &[Task]| tasks.iter().map(|t| (t.id, t.status.clone())).collect::<Vec<_>>();
+        assert_eq!(summary(&written), summary(&shown));
+        assert!(storage.take_failed_writes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_is_undone_and_reported() {
+        let mut backend = LocalTaskStorage::in_memory();
+        let context = "test:repo:main";
+        let id = backend.add_task(context, "Task".to_string()).await.unwrap();
+        let mut storage = OptimisticStorage::new(Box::new(ReadOnlyStorage::new(Box::new(backend), "The backend")));
+
+        assert!(storage.remove_task(context, id).await.unwrap());
+        assert!(storage.get_tasks(context).await.unwrap().is_empty());
+
+        storage.settle().await;
+        let failures = storage.take_failed_writes();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].contains("read-only"), "{}", failures[0]);
+        assert_eq!(storage.get_tasks(context).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_writes_land_as_values_and_stop_after_a_failure() {
+        use crate::storage::tracked::SaveIndicator;
+        let mut backend = LocalTaskStorage::in_memory();
+        let context = "test:repo:main";
+        let first = backend.add_task(context, "First".to_string()).await.unwrap();
+        let second = backend.add_task(context, "Second".to_string()).await.unwrap();
+        let mut storage = OptimisticStorage::new(Box::new(backend));
+        let tracker = SaveTracker::default();
+        storage.track_saves(tracker.clone());
+        storage.get_tasks(context).await.unwrap();
+
+        // Someone else finishes the task before the toggle is written; the
+        // toggle still lands as the status it showed
+        let inner = storage.inner.clone();
+        let mut backend = inner.lock().await;
+        assert!(storage.toggle_task(context, first).await.unwrap());
+        backend.set_task_status(context, first, TaskStatus::Completed).await.unwrap();
+        drop(backend);
+        storage.settle().await;
+        let written = inner.lock().await.get_tasks(context).await.unwrap();
+        assert_eq!(written[0].status, TaskStatus::InProgress);
+
+        // A move whose task was deleted elsewhere fails, and the writes
+        // queued behind it are dropped rather than written out of order
+        let mut backend = inner.lock().await;
+        assert!(storage.move_task_down(context, first).await.unwrap());
+        assert!(storage.edit_task(context, second, "Renamed".to_string()).await.unwrap());
+        backend.remove_task(context, first).await.unwrap();
+        drop(backend);
+        storage.settle().await;
+        assert_eq!(storage.take_failed_writes().len(), 1);
+        let written = inner.lock().await.get_tasks(context).await.unwrap();
+        assert_eq!(written.iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["Second"]);
+        assert!(matches!(tracker.indicator(Instant::now() + Duration::from_secs(1)), Some(SaveIndicator::Failed(_))));
+
+        // Later writes go through again
+        assert!(storage.edit_task(context, second, "Renamed".to_string()).await.unwrap());
+        storage.settle().await;
+        assert_eq!(inner.lock().await.get_tasks(context).await.unwrap()[0].text, "Renamed");
+        assert_eq!(tracker.indicator(Instant::now() + Duration::from_secs(1)), Some(SaveIndicator::Saved));
+    }
+}
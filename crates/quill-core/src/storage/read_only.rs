@@ -0,0 +1,169 @@
+use super::activity::ActivityEntry;
+use super::{BackendHealth, DeletedTask, ExternalChange, Task, TaskCounts, TaskStatus, TaskStorage, Tombstone};
+use crate::{QuillError, Result};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+
+/// Wraps a backend so it can be browsed without being changed: reads go
+/// through, every write fails.
+pub struct ReadOnlyStorage {
+    inner: Box<dyn TaskStorage>,
+    label: String,
+}
+
+impl ReadOnlyStorage {
+    /// `label` names the backend in the errors returned for writes.
+    pub fn new(inner: Box<dyn TaskStorage>, label: &str) -> Self {
+        Self { inner, label: label.to_string() }
+    }
+
+    fn refuse<T>(&self) -> Result<T> {
+        Err(QuillError::PermissionDenied(format!("{} is open read-only", self.label)))
+    }
+}
+
+#[async_trait]
+impl TaskStorage for ReadOnlyStorage {
+    async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>> {
+        self.inner.get_tasks(context_key).await
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<String>> {
+        self.inner.list_contexts().await
+    }
+
+    async fn get_all_tasks(&self) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.inner.get_all_tasks().await
+    }
+
+    async fn get_task_counts(&self, context_key: &str) -> Result<TaskCounts> {
+        self.inner.get_task_counts(context_key).await
+    }
+
+    async fn all_task_counts(&self) -> Result<BTreeMap<String, TaskCounts>> {
+        self.inner.all_task_counts().await
+    }
+
+    async fn open_task_counts(&self) -> Result<BTreeMap<String, usize>> {
+        self.inner.open_task_counts().await
+    }
+
+    async fn search_tasks(&self, query: &str) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.inner.search_tasks(query).await
+    }
+
+    async fn add_task(&mut self, _context_key: &str, _text: String) -> Result<usize> {
+        self.refuse()
+    }
+
+    async fn toggle_task(&mut self, _context_key: &str, _id: usize) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn set_task_status(&mut self, _context_key: &str, _id: usize, _status: TaskStatus) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn remove_task(&mut self, _context_key: &str, _id: usize) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn edit_task(&mut self, _context_key: &str, _id: usize, _new_text: String) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn update_task(&mut self, _context_key: &str, _task: Task) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn undo_delete(&mut self, _context_key: &str) -> Result<Option<Task>> {
+        self.refuse()
+    }
+
+    async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>> {
+        self.inner.list_deleted(context_key).await
+    }
+
+    async fn restore_deleted(&mut self, _context_key: &str, _id: usize) -> Result<Option<Task>> {
+        self.refuse()
+    }
+
+    async fn purge_deleted(&mut self, _context_key: &str, _id: usize) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>> {
+        self.inner.list_tombstones(context_key).await
+    }
+
+    async fn move_task_up(&mut self, _context_key: &str, _id: usize) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn move_task_down(&mut self, _context_key: &str, _id: usize) -> Result<bool> {
+        self.refuse()
+    }
+
+    async fn delete_context(&mut self, _context_key: &str) -> Result<usize> {
+        self.refuse()
+    }
+
+    async fn move_context_tasks(&mut self, _from: &str, _to: &str) -> Result<usize> {
+        self.refuse()
+    }
+
+    async fn move_tasks(&mut self, _from: &str, _ids: &[usize], _to: &str) -> Result<usize> {
+        self.refuse()
+    }
+
+    async fn record_activity(&mut self, _entries: Vec<ActivityEntry>) -> Result<()> {
+        self.refuse()
+    }
+
+    async fn list_activity(&self, context_key: &str) -> Result<Vec<ActivityEntry>> {
+        self.inner.list_activity(context_key).await
+    }
+
+    async fn export_activity(&self, since: Option<&str>) -> Result<Vec<ActivityEntry>> {
+        self.inner.export_activity(since).await
+    }
+
+    async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String> {
+        self.inner.inspect_task(context_key, id).await
+    }
+
+    async fn health(&self) -> Result<BackendHealth> {
+        self.inner.health().await
+    }
+
+    fn watch_path(&self) -> Option<std::path::PathBuf> {
+        self.inner.watch_path()
+    }
+
+    // Nothing is written here, so there is never a conflict to resolve
+    async fn check_external_changes(&mut self) -> Result<ExternalChange> {
+        self.inner.check_external_changes().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::local::LocalTaskStorage;
+
+    #[tokio::test]
+    async fn test_reads_pass_through_and_writes_fail() {
+        let mut inner = LocalTaskStorage::in_memory();
+        let id = inner.add_task("ctx", "Existing".to_string()).await.unwrap();
+        let mut storage = ReadOnlyStorage::new(Box::new(inner), "MongoDB");
+
+        assert_eq!(storage.get_tasks("ctx").await.unwrap()[0].text, "Existing");
+        assert_eq!(storage.list_contexts().await.unwrap(), vec!["ctx".to_string()]);
+
+        let error = storage.toggle_task("ctx", id).await.unwrap_err();
+        assert_eq!(error.to_string(), "MongoDB is open read-only");
+        assert!(matches!(error, QuillError::PermissionDenied(_)));
+        assert!(storage.add_task("ctx", "New".to_string()).await.is_err());
+        assert_eq!(storage.get_tasks("ctx").await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,447 @@
+//! The sync protocol spoken between remote storage and `quill serve-sync`.
+//!
+//! Every storage call is one `POST /v1/rpc` carrying a JSON [`Request`] and
+//! an `Authorization: Bearer <token>` header. The server answers with a
+//! [`Response`]: `{"result": ...}` holding what the call returns, or
+//! `{"error": "..."}` with a status naming the failure class (see
+//! [`status_for`]), or 401 for a wrong token.
+
+use super::activity::{ActivityEntry, ActivityStorage};
+use super::{Task, TaskStatus, TaskStorage};
+use crate::{QuillError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Path of the single endpoint.
+pub const RPC_PATH: &str = "/v1/rpc";
+
+/// One `TaskStorage` call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    GetTasks { context_key: String },
+    ListContexts,
+    GetAllTasks,
+    GetTaskCounts { context_key: String },
+    AllTaskCounts,
+    OpenTaskCounts,
+    SearchTasks { query: String },
+    AddTask { context_key: String, text: String },
+    ToggleTask { context_key: String, id: usize },
+    SetTaskStatus { context_key: String, id: usize, status: TaskStatus },
+    RemoveTask { context_key: String, id: usize },
+    EditTask { context_key: String, id: usize, new_text: String },
+    UpdateTask { context_key: String, task: Box<Task> },
+    UndoDelete { context_key: String },
+    ListDeleted { context_key: String },
+    RestoreDeleted { context_key: String, id: usize },
+    PurgeDeleted { context_key: String, id: usize },
+    ListTombstones { context_key: String },
+    MoveTaskUp { context_key: String, id: usize },
+    MoveTaskDown { context_key: String, id: usize },
+    DeleteContext { context_key: String },
+    MoveContextTasks { from: String, to: String },
+    MoveTasks { from: String, ids: Vec<usize>, to: String },
+    RecordActivity { entries: Vec<ActivityEntry> },
+    ListActivity { context_key: String },
+    ExportActivity { since: Option<String> },
+    InspectTask { context_key: String, id: usize },
+    Health,
+}
+
+impl Request {
+    /// The contexts a write changes; none for reads.
+    pub fn written_contexts(&self) -> Vec<&str> {
+        match self {
+            Request::AddTask { context_key, .. }
+            | Request::ToggleTask { context_key, .. }
+            | Request::SetTaskStatus { context_key, .. }
+            | Request::RemoveTask { context_key, .. }
+            | Request::EditTask { context_key, .. }
+            | Request::UpdateTask { context_key, .. }
+            | Request::UndoDelete { context_key }
+            | Request::RestoreDeleted { context_key, .. }
+            | Request::PurgeDeleted { context_key, .. }
+            | Request::MoveTaskUp { context_key, .. }
+            | Request::MoveTaskDown { context_key, .. }
+            | Request::DeleteContext { context_key } => vec![context_key],
+            Request::MoveContextTasks { from, to } | Request::MoveTasks { from, to, .. } => vec![from, to],
+            // The log isn't part of any context's tasks
+            Request::RecordActivity { .. } => Vec::new(),
+            Request::GetTasks { .. }
+            | Request::ListContexts
+            | Request::GetAllTasks
+            | Request::GetTaskCounts { .. }
+            | Request::AllTaskCounts
+            | Request::OpenTaskCounts
+            | Request::SearchTasks { .. }
+            | Request::ListDeleted { .. }
+            | Request::ListTombstones { .. }
+            | Request::ListActivity { .. }
+            | Request::ExportActivity { .. }
+            | Request::InspectTask { .. }
+            | Request::Health => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Result(Value),
+    Error(String),
+}
+
+/// The HTTP status the server answers `error` with.
+pub fn status_for(error: &QuillError) -> u16 {
+    match error {
+        QuillError::PermissionDenied(_) => 403,
+        QuillError::Conflict(_) => 409,
+        QuillError::StorageUnavailable(_) | QuillError::Git(_) | QuillError::Io(_) => 503,
+        QuillError::Corrupt(_) => 500,
+        QuillError::Unsupported(_) => 501,
+        QuillError::ContextNotFound(_) => 404,
+        QuillError::Validation(_) => 400,
+    }
+}
+
+/// Rebuilds the error class from a server's status, for the client side.
+pub fn error_for(status: u16, message: String) -> QuillError {
+    match status {
+        401 | 403 => QuillError::PermissionDenied(message),
+        409 => QuillError::Conflict(message),
+        503 => QuillError::StorageUnavailable(message),
+        500 => QuillError::Corrupt(message),
+        501 => QuillError::Unsupported(message),
+        404 => QuillError::ContextNotFound(message),
+        _ => QuillError::Validation(message),
+    }
+}
+
+/// Runs `request` against `storage`, for the server side.
+pub async fn dispatch(storage: &mut dyn TaskStorage, request: Request) -> Result<Value> {
+    Ok(match request {
+        Request::GetTasks { context_key } => serde_json::to_value(storage.get_tasks(&context_key).await?)?,
+        Request::ListContexts => serde_json::to_value(storage.list_contexts().await?)?,
+        Request::GetAllTasks => serde_json::to_value(storage.get_all_tasks().await?)?,
+        Request::GetTaskCounts { context_key } => serde_json::to_value(storage.get_task_counts(&context_key).await?)?,
+        Request::AllTaskCounts => serde_json::to_value(storage.all_task_counts().await?)?,
+        Request::OpenTaskCounts => serde_json::to_value(storage.open_task_counts().await?)?,
+        Request::SearchTasks { query } => serde_json::to_value(storage.search_tasks(&query).await?)?,
+        Request::AddTask { context_key, text } => serde_json::to_value(storage.add_task(&context_key, text).await?)?,
+        Request::ToggleTask { context_key, id } => serde_json::to_value(storage.toggle_task(&context_key, id).await?)?,
+        Request::SetTaskStatus { context_key, id, status } => {
+            serde_json::to_value(storage.set_task_status(&context_key, id, status).await?)?
+        }
+        Request::RemoveTask { context_key, id } => serde_json::to_value(storage.remove_task(&context_key, id).await?)?,
+        Request::EditTask { context_key, id, new_text } => {
+            serde_json::to_value(storage.edit_task(&context_key, id, new_text).await?)?
+        }
+        Request::UpdateTask { context_key, task } => serde_json::to_value(storage.update_task(&context_key, *task).await?)?,
+        Request::UndoDelete { context_key } => serde_json::to_value(storage.undo_delete(&context_key).await?)?,
+        Request::ListDeleted { context_key } => serde_json::to_value(storage.list_deleted(&context_key).await?)?,
+        Request::RestoreDeleted { context_key, id } => {
+            serde_json::to_value(storage.restore_deleted(&context_key, id).await?)?
+        }
+        Request::PurgeDeleted { context_key, id } => serde_json::to_value(storage.purge_deleted(&context_key, id).await?)?,
+        Request::ListTombstones { context_key } => serde_json::to_value(storage.list_tombstones(&context_key).await?)?,
+        Request::MoveTaskUp { context_key, id } => serde_json::to_value(storage.move_task_up(&context_key, id).await?)?,
+        Request::MoveTaskDown { context_key, id } => serde_json::to_value(storage.move_task_down(&context_key, id).await?)?,
+        Request::DeleteContext { context_key } => serde_json::to_value(storage.delete_context(&context_key).await?)?,
+        Request::MoveContextTasks { from, to } => serde_json::to_value(storage.move_context_tasks(&from, &to).await?)?,
+        Request::MoveTasks { from, ids, to } => serde_json::to_value(storage.move_tasks(&from, &ids, &to).await?)?,
+        Request::RecordActivity { entries } => serde_json::to_value(storage.record_activity(entries).await?)?,
+        Request::ListActivity { context_key } => serde_json::to_value(storage.list_activity(&context_key).await?)?,
+        Request::ExportActivity { since } => serde_json::to_value(storage.export_activity(since.as_deref()).await?)?,
+        Request::InspectTask { context_key, id } => serde_json::to_value(storage.inspect_task(&context_key, id).await?)?,
+        Request::Health => serde_json::to_value(storage.health().await?)?,
+    })
+}
+
+/// Runs `request` for a server whose storage records activity itself, as
+/// whoever the server knows the client to be. Entries a client sends are
+/// dropped, since they could name anyone.
+pub async fn dispatch_recorded(storage: &mut ActivityStorage, request: Request) -> Result<Value> {
+    if let Request::RecordActivity { .. } = request {
+        return Ok(Value::Null);
+    }
+    dispatch(storage, request).await
+}
+
+#[cfg(feature = "remote")]
+pub use client::RemoteTaskStorage;
+
+#[cfg(feature = "remote")]
+mod client {
+    use super::*;
+    use crate::config::RemoteConfig;
+    use crate::storage::{BackendHealth, DeletedTask, TaskCounts, Tombstone};
+    use async_trait::async_trait;
+    use serde::de::DeserializeOwned;
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    /// Storage kept by a `quill serve-sync` server.
+    pub struct RemoteTaskStorage {
+        client: reqwest::Client,
+        url: String,
+        token: String,
+        /// Set once the server turned down a count request, as servers older
+        /// than the count calls do; counts are then made from loaded tasks.
+        counts_unsupported: AtomicBool,
+    }
+
+    impl RemoteTaskStorage {
+        /// Connects to the server in `config` and checks the token.
+        pub async fn connect(config: &RemoteConfig) -> Result<Self> {
+            let token = config
+                .resolve_token()?
+                .ok_or_else(|| {
+                    QuillError::Validation(
+                        "remote storage needs a token: set remote_config.token or QUILL_REMOTE_TOKEN".to_string(),
+                    )
+                })?;
+            let storage = Self {
+                client: reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?,
+                url: format!("{}{}", config.url.trim_end_matches('/'), RPC_PATH),
+                token,
+                counts_unsupported: AtomicBool::new(false),
+            };
+            storage.list_contexts().await?;
+            Ok(storage)
+        }
+
+        async fn call<T: DeserializeOwned>(&self, request: Request) -> Result<T> {
+            let response = self
+                .client
+                .post(&self.url)
+                .bearer_auth(&self.token)
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| {
+                    tracing::warn!(url = %self.url, error = %e, "could not reach the sync server");
+                    QuillError::StorageUnavailable(format!("could not reach the sync server: {}", e))
+                })?;
+            let status = response.status();
+            tracing::debug!(url = %self.url, %status, "sync server answered");
+            match response.json::<Response>().await {
+                Ok(Response::Result(value)) => Ok(serde_json::from_value(value)?),
+                Ok(Response::Error(message)) => Err(error_for(status.as_u16(), format!("sync server: {}", message))),
+                // The request didn't parse, as with an op an older server doesn't know
+                Err(_) if status == reqwest::StatusCode::UNPROCESSABLE_ENTITY => {
+                    Err(QuillError::Unsupported("the sync server doesn't know this request; it may need upgrading".to_string()))
+                }
+                Err(_) => Err(QuillError::StorageUnavailable(format!(
+                    "sync server answered {} without a quill response",
+                    status
+                ))),
+            }
+        }
+
+        /// Like `call` for a count request; `None` when the server can't
+        /// answer it, and from then on without asking again.
+        async fn call_counts<T: DeserializeOwned>(&self, request: Request) -> Result<Option<T>> {
+            if self.counts_unsupported.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            match self.call(request).await {
+                Ok(counts) => Ok(Some(counts)),
+                Err(QuillError::Unsupported(message)) => {
+                    tracing::info!(%message, "sync server can't count tasks; counting loaded tasks instead");
+                    self.counts_unsupported.store(true, Ordering::Relaxed);
+                    Ok(None)
+                }
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl TaskStorage for RemoteTaskStorage {
+        async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>> {
+            self.call(Request::GetTasks { context_key: context_key.to_string() }).await
+        }
+
+        async fn list_contexts(&self) -> Result<Vec<String>> {
+            self.call(Request::ListContexts).await
+        }
+
+        async fn get_all_tasks(&self) -> Result<BTreeMap<String, Vec<Task>>> {
+            self.call(Request::GetAllTasks).await
+        }
+
+        async fn get_task_counts(&self, context_key: &str) -> Result<TaskCounts> {
+            match self.call_counts(Request::GetTaskCounts { context_key: context_key.to_string() }).await? {
+                Some(counts) => Ok(counts),
+                None => Ok(TaskCounts::from_tasks(&self.get_tasks(context_key).await?)),
+            }
+        }
+
+        async fn all_task_counts(&self) -> Result<BTreeMap<String, TaskCounts>> {
+            match self.call_counts(Request::AllTaskCounts).await? {
+                Some(counts) => Ok(counts),
+                None => Ok(self
+                    .get_all_tasks()
+                    .await?
+                    .into_iter()
+                    .map(|(context_key, tasks)| (context_key, TaskCounts::from_tasks(&tasks)))
+                    .collect()),
+            }
+        }
+
+        async fn open_task_counts(&self) -> Result<BTreeMap<String, usize>> {
+            match self.call_counts(Request::OpenTaskCounts).await? {
+                Some(counts) => Ok(counts),
+                None => Ok(self
+                    .all_task_counts()
+                    .await?
+                    .into_iter()
+                    .map(|(context_key, counts)| (context_key, counts.open()))
+                    .filter(|(_, open)| *open > 0)
+                    .collect()),
+            }
+        }
+
+        async fn search_tasks(&self, query: &str) -> Result<BTreeMap<String, Vec<Task>>> {
+            self.call(Request::SearchTasks { query: query.to_string() }).await
+        }
+
+        async fn add_task(&mut self, context_key: &str, text: String) -> Result<usize> {
+            self.call(Request::AddTask { context_key: context_key.to_string(), text }).await
+        }
+
+        async fn toggle_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+            self.call(Request::ToggleTask { context_key: context_key.to_string(), id }).await
+        }
+
+        async fn set_task_status(&mut self, context_key: &str, id: usize, status: TaskStatus) -> Result<bool> {
+            self.call(Request::SetTaskStatus { context_key: context_key.to_string(), id, status }).await
+        }
+
+        async fn remove_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+            self.call(Request::RemoveTask { context_key: context_key.to_string(), id }).await
+        }
+
+        async fn edit_task(&mut self, context_key: &str, id: usize, new_text: String) -> Result<bool> {
+            self.call(Request::EditTask { context_key: context_key.to_string(), id, new_text }).await
+        }
+
+        async fn update_task(&mut self, context_key: &str, task: Task) -> Result<bool> {
+            self.call(Request::UpdateTask { context_key: context_key.to_string(), task: Box::new(task) }).await
+        }
+
+        async fn undo_delete(&mut self, context_key: &str) -> Result<Option<Task>> {
+            self.call(Request::UndoDelete { context_key: context_key.to_string() }).await
+        }
+
+        async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>> {
+            self.call(Request::ListDeleted { context_key: context_key.to_string() }).await
+        }
+
+        async fn restore_deleted(&mut self, context_key: &str, id: usize) -> Result<Option<Task>> {
+            self.call(Request::RestoreDeleted { context_key: context_key.to_string(), id }).await
+        }
+
+        async fn purge_deleted(&mut self, context_key: &str, id: usize) -> Result<bool> {
+            self.call(Request::PurgeDeleted { context_key: context_key.to_string(), id }).await
+        }
+
+        async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>> {
+            self.call(Request::ListTombstones { context_key: context_key.to_string() }).await
+        }
+
+        async fn move_task_up(&mut self, context_key: &str, id: usize) -> Result<bool> {
+            self.call(Request::MoveTaskUp { context_key: context_key.to_string(), id }).await
+        }
+
+        async fn move_task_down(&mut self, context_key: &str, id: usize) -> Result<bool> {
+            self.call(Request::MoveTaskDown { context_key: context_key.to_string(), id }).await
+        }
+
+        async fn delete_context(&mut self, context_key: &str) -> Result<usize> {
+            self.call(Request::DeleteContext { context_key: context_key.to_string() }).await
+        }
+
+        async fn move_context_tasks(&mut self, from: &str, to: &str) -> Result<usize> {
+            self.call(Request::MoveContextTasks { from: from.to_string(), to: to.to_string() }).await
+        }
+
+        async fn move_tasks(&mut self, from: &str, ids: &[usize], to: &str) -> Result<usize> {
+            self.call(Request::MoveTasks { from: from.to_string(), ids: ids.to_vec(), to: to.to_string() }).await
+        }
+
+        async fn record_activity(&mut self, entries: Vec<ActivityEntry>) -> Result<()> {
+            self.call(Request::RecordActivity { entries }).await
+        }
+
+        async fn list_activity(&self, context_key: &str) -> Result<Vec<ActivityEntry>> {
+            self.call(Request::ListActivity { context_key: context_key.to_string() }).await
+        }
+
+        async fn export_activity(&self, since: Option<&str>) -> Result<Vec<ActivityEntry>> {
+            self.call(Request::ExportActivity { since: since.map(str::to_string) }).await
+        }
+
+        async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String> {
+            self.call(Request::InspectTask { context_key: context_key.to_string(), id }).await
+        }
+
+        async fn health(&self) -> Result<BackendHealth> {
+            let mut health: BackendHealth = self.call(Request::Health).await?;
+            health.backend = format!("Remote server ({})", health.backend);
+            health.details.insert(0, ("Server".to_string(), self.url.trim_end_matches(RPC_PATH).to_string()));
+            Ok(health)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::local::LocalTaskStorage;
+
+    #[test]
+    fn test_wire_format() {
+        let request = Request::SetTaskStatus { context_key: "acme:web:main".to_string(), id: 3, status: TaskStatus::Completed };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({ "op": "set_task_status", "context_key": "acme:web:main", "id": 3, "status": "Completed" })
+        );
+        assert_eq!(serde_json::to_string(&Response::Error("nope".to_string())).unwrap(), r#"{"error":"nope"}"#);
+    }
+
+    #[test]
+    fn test_error_class_survives_the_wire() {
+        for error in [
+            QuillError::Conflict("moved".to_string()),
+            QuillError::StorageUnavailable("down".to_string()),
+            QuillError::PermissionDenied("read-only".to_string()),
+            QuillError::Validation("bad".to_string()),
+        ] {
+            let back = error_for(status_for(&error), error.to_string());
+            assert_eq!(std::mem::discriminant(&back), std::mem::discriminant(&error));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_runs_calls_against_the_backend() {
+        let mut storage = LocalTaskStorage::in_memory();
+        let id = dispatch(&mut storage, Request::AddTask { context_key: "ctx".to_string(), text: "Sync".to_string() })
+            .await
+            .unwrap();
+        assert_eq!(id, serde_json::json!(1));
+
+        dispatch(&mut storage, Request::ToggleTask { context_key: "ctx".to_string(), id: 1 }).await.unwrap();
+        let tasks: Vec<Task> =
+            serde_json::from_value(dispatch(&mut storage, Request::GetTasks { context_key: "ctx".to_string() }).await.unwrap())
+                .unwrap();
+        assert_eq!(tasks[0].status, TaskStatus::InProgress);
+
+        let counts: crate::storage::TaskCounts =
+            serde_json::from_value(dispatch(&mut storage, Request::GetTaskCounts { context_key: "ctx".to_string() }).await.unwrap())
+                .unwrap();
+        assert_eq!((counts.in_progress, counts.open(), counts.total()), (1, 1, 1));
+    }
+}
@@ -0,0 +1,168 @@
+//! Versions of the stored task data, and the steps that bring data written
+//! by older releases up to date when it is loaded.
+
+use crate::{QuillError, Result};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// The version of the data this build writes. Bump it together with a new
+/// entry in `MIGRATIONS` whenever the stored shape of a task changes.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Rewrites a task stored at version `to - 1` into version `to`.
+struct Migration {
+    to: u32,
+    upgrade: fn(&mut Map<String, Value>),
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { to: 2, upgrade: completed_flag_to_status },
+    Migration { to: 3, upgrade: assign_uid },
+    Migration { to: 4, upgrade: order_by_id },
+];
+
+/// Tasks from before statuses only have a `completed` flag.
+fn completed_flag_to_status(task: &mut Map<String, Value>) {
+    if let Some(completed) = task.remove("completed") {
+        if !task.contains_key("status") {
+            let status = if completed.as_bool() == Some(true) { "Completed" } else { "NotStarted" };
+            task.insert("status".to_string(), Value::from(status));
+        }
+    }
+}
+
+/// Tasks from before uids get one derived from their id and creation time,
+/// so they keep it even if they're read again before being saved.
+fn assign_uid(task: &mut Map<String, Value>) {
+    if task.contains_key("uid") {
+        return;
+    }
+    // MongoDB documents call the id `task_id`
+    let id = task.get("id").or_else(|| task.get("task_id")).and_then(Value::as_u64).unwrap_or_default();
+    let created_at = task.get("created_at").and_then(Value::as_str).unwrap_or_default();
+    task.insert("uid".to_string(), Value::from(legacy_uid(id, created_at).to_string()));
+}
+
+/// MongoDB listed tasks by id before they had an `order`. Files kept them in
+/// list order instead, which `upgrade_file` applies on top.
+fn order_by_id(task: &mut Map<String, Value>) {
+    if !task.contains_key("order") {
+        let id = task.get("id").or_else(|| task.get("task_id")).and_then(Value::as_f64).unwrap_or_default();
+        task.insert("order".to_string(), Value::from(id));
+    }
+}
+
+/// The uid of a task stored before uids existed.
+pub fn legacy_uid(id: u64, created_at: &str) -> Uuid {
+    let key = format!("{}/{}", id, created_at);
+    let hash = |seed: u64| key.bytes().fold(seed, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    uuid::Builder::from_custom_bytes(((hash(0xcbf29ce484222325) as u128) << 64 | hash(0x84222325cbf29ce4) as u128).to_be_bytes()).into_uuid()
+}
+
+/// The version recorded in a document; data from before versioning is 1.
+pub fn version_of(document: &Value) -> u32 {
+    document.get("schema_version").and_then(Value::as_u64).map_or(1, |version| version as u32)
+}
+
+/// Refuses data written by a newer quill, which this build would read
+/// without what the newer one added and then write back without it.
+pub fn check_version(version: u32, what: &str) -> Result<()> {
+    if version > SCHEMA_VERSION {
+        return Err(QuillError::Unsupported(format!(
+            "{} was written by a newer quill (data version {}, this build reads up to {}); upgrade quill to open it",
+            what, version, SCHEMA_VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// Checks the version of a stored file and upgrades its tasks, whether it
+/// holds one context (`tasks`, `deleted`) or several (`contexts`,
+/// `deleted_tasks`). `what` names the file in errors.
+pub fn upgrade_file(file: &mut Value, what: &str) -> Result<()> {
+    let version = version_of(file);
+    check_version(version, what)?;
+    for key in ["tasks", "contexts"] {
+        for task in entries(file.get_mut(key)) {
+            upgrade_task(task, version);
+        }
+    }
+    if version < 4 {
+        for key in ["tasks", "contexts"] {
+            for list in lists(file.get_mut(key)) {
+                for (position, task) in list.iter_mut().enumerate() {
+                    task["order"] = Value::from(position as f64 + 1.0);
+                }
+            }
+        }
+    }
+    for key in ["deleted", "deleted_tasks"] {
+        for deleted in entries(file.get_mut(key)) {
+            // The trash used to hold bare tasks, without `deleted_at`
+            if deleted.get("task").is_some() {
+                upgrade_task(&mut deleted["task"], version);
+            } else {
+                upgrade_task(deleted, version);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A list, or every list in a map by context.
+fn lists(value: Option<&mut Value>) -> Vec<&mut Vec<Value>> {
+    match value {
+        Some(Value::Array(items)) => vec![items],
+        Some(Value::Object(lists)) => lists.values_mut().filter_map(Value::as_array_mut).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The items of `lists`.
+fn entries(value: Option<&mut Value>) -> Vec<&mut Value> {
+    lists(value).into_iter().flatten().collect()
+}
+
+/// Brings a task stored at `version` up to `SCHEMA_VERSION`.
+pub fn upgrade_task(task: &mut Value, version: u32) {
+    let Some(task) = task.as_object_mut() else {
+        return;
+    };
+    for migration in MIGRATIONS.iter().filter(|m| m.to > version) {
+        (migration.upgrade)(task);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_upgrades_old_tasks_and_refuses_newer_data() {
+        let mut file = json!({ "tasks": [
+            { "id": 5, "text": "Moved up", "created_at": "2024-01-01T00:00:00Z" },
+            { "id": 1, "text": "Old", "completed": true, "created_at": "2024-01-01T00:00:00Z" },
+        ] });
+        upgrade_file(&mut file, "todos.json").unwrap();
+        let uid = legacy_uid(1, "2024-01-01T00:00:00Z").to_string();
+        assert_eq!(
+            file["tasks"][1],
+            json!({ "id": 1, "text": "Old", "status": "Completed", "created_at": "2024-01-01T00:00:00Z", "uid": uid, "order": 2.0 })
+        );
+        assert_eq!(file["tasks"][0]["order"], json!(1.0));
+
+        let mut document = json!({ "task_id": 7, "text": "Mongo" });
+        upgrade_task(&mut document, 1);
+        assert_eq!(document["order"], json!(7.0));
+        assert_ne!(legacy_uid(2, "2024-01-01T00:00:00Z").to_string(), uid);
+
+        let mut current = json!({ "id": 2, "text": "New", "completed": true });
+        upgrade_task(&mut current, SCHEMA_VERSION);
+        assert_eq!(current["completed"], json!(true));
+
+        assert!(check_version(SCHEMA_VERSION, "todos.json").is_ok());
+        let error = check_version(SCHEMA_VERSION + 1, "todos.json").unwrap_err().to_string();
+        assert!(error.contains("newer quill"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Full-text search over task text, notes and tags.
+//!
+//! A query matches a task when each of its words starts a word of the task's
+//! text, notes or tags, ignoring case and punctuation: `log fix` finds
+//! "Fix the login form".
+
+use super::Task;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Lowercase words of letters and digits.
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+}
+
+fn task_words(task: &Task) -> BTreeSet<String> {
+    words(&task.text)
+        .chain(words(&task.notes))
+        .chain(task.tags.iter().flat_map(|tag| words(tag)))
+        .collect()
+}
+
+/// Whether `task` matches `query`; an empty query matches nothing.
+pub fn matches(task: &Task, query: &str) -> bool {
+    let query: Vec<String> = words(query).collect();
+    let task_words = task_words(task);
+    !query.is_empty() && query.iter().all(|q| task_words.iter().any(|word| word.starts_with(q.as_str())))
+}
+
+/// The tasks of `all` matching `query`, leaving out contexts without any.
+pub fn filter(all: BTreeMap<String, Vec<Task>>, query: &str) -> BTreeMap<String, Vec<Task>> {
+    all.into_iter()
+        .map(|(context_key, tasks)| (context_key, tasks.into_iter().filter(|task| matches(task, query)).collect::<Vec<_>>()))
+        .filter(|(_, tasks)| !tasks.is_empty())
+        .collect()
+}
+
+/// A context's tasks and where each word occurs in them.
+#[derive(Debug, Default)]
+struct ContextIndex {
+    tasks: Vec<Task>,
+    /// Word to the positions in `tasks` of the tasks that have it.
+    words: BTreeMap<String, BTreeSet<usize>>,
+}
+
+impl ContextIndex {
+    fn new(tasks: &[Task]) -> Self {
+        let mut words: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+        for (position, task) in tasks.iter().enumerate() {
+            for word in task_words(task) {
+                words.entry(word).or_default().insert(position);
+            }
+        }
+        Self { tasks: tasks.to_vec(), words }
+    }
+
+    /// Positions of the tasks having a word that starts with `prefix`.
+    fn starting_with(&self, prefix: &str) -> BTreeSet<usize> {
+        self.words
+            .range(prefix.to_string()..)
+            .take_while(|(word, _)| word.starts_with(prefix))
+            .flat_map(|(_, positions)| positions.iter().copied())
+            .collect()
+    }
+}
+
+/// An in-memory word index per context, for backends without one of their
+/// own. A change re-indexes only the context it touched.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    contexts: HashMap<String, ContextIndex>,
+}
+
+impl SearchIndex {
+    pub fn contains(&self, context_key: &str) -> bool {
+        self.contexts.contains_key(context_key)
+    }
+
+    /// Indexes `tasks` as the context's tasks, replacing what it had.
+    pub fn update(&mut self, context_key: &str, tasks: &[Task]) {
+        self.contexts.insert(context_key.to_string(), ContextIndex::new(tasks));
+    }
+
+    /// Forgets every context, e.g. after the data was reloaded.
+    pub fn clear(&mut self) {
+        self.contexts.clear();
+    }
+
+    /// The indexed tasks matching `query`, in list order, grouped by context.
+    pub fn search(&self, query: &str) -> BTreeMap<String, Vec<Task>> {
+        let query: Vec<String> = words(query).collect();
+        if query.is_empty() {
+            return BTreeMap::new();
+        }
+        let mut found = BTreeMap::new();
+        for (context_key, index) in &self.contexts {
+            let mut positions = index.starting_with(&query[0]);
+            for word in &query[1..] {
+                let with_word = index.starting_with(word);
+                positions.retain(|position| with_word.contains(position));
+            }
+            if !positions.is_empty() {
+                found.insert(context_key.clone(), positions.into_iter().map(|position| index.tasks[position].clone()).collect());
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_index_matches_filter() {
+        let mut login = Task::new(1, "Fix the login form".to_string());
+        login.tags = vec!["web-ui".to_string()];
+        let mut notes = Task::new(2, "Write release notes".to_string());
+        notes.notes = "Mention the LOGIN changes".to_string();
+        let docs = Task::new(3, "Update docs".to_string());
+        let all = BTreeMap::from([
+            ("acme:web:main".to_string(), vec![login, notes]),
+            ("acme:docs:main".to_string(), vec![docs.clone()]),
+        ]);
+
+        let mut index = SearchIndex::default();
+        for (context_key, tasks) in &all {
+            index.update(context_key, tasks);
+        }
+        let ids = |found: BTreeMap<String, Vec<Task>>| {
+            found.into_iter().map(|(key, tasks)| (key, tasks.iter().map(|t| t.id).collect::<Vec<_>>())).collect::<Vec<_>>()
+        };
+        for (query, expected) in [
+            ("log", vec![("acme:web:main".to_string(), vec![1, 2])]),
+            ("fix, LOG", vec![("acme:web:main".to_string(), vec![1])]),
+            ("ui", vec![("acme:web:main".to_string(), vec![1])]),
+            ("nothing", vec![]),
+            ("  ", vec![]),
+        ] {
+            assert_eq!(ids(index.search(query)), expected, "{}", query);
+            assert_eq!(ids(filter(all.clone(), query)), expected, "{}", query);
+        }
+
+        // Re-indexing one context leaves the others as they were
+        index.update("acme:web:main", &[]);
+        assert!(index.search("log").is_empty());
+        assert_eq!(ids(index.search("docs")), vec![("acme:docs:main".to_string(), vec![3])]);
+    }
+}
@@ -0,0 +1,313 @@
+use super::activity::ActivityEntry;
+use super::{BackendHealth, DeletedTask, ExternalChange, Task, TaskCounts, TaskStatus, TaskStorage, Tombstone};
+use crate::Result;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// "saving…" stays up at least this long so fast local writes don't flicker.
+const MIN_SAVING_DISPLAY: Duration = Duration::from_millis(400);
+
+/// What the status bar shows about the most recent write.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveIndicator {
+    Saving,
+    Saved,
+    Failed(String),
+}
+
+impl SaveIndicator {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SaveIndicator::Saving => "saving…",
+            SaveIndicator::Saved => "saved ✓",
+            SaveIndicator::Failed(_) => "save failed ✗",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SaveState {
+    started: Option<Instant>,
+    in_flight: usize,
+    error: Option<String>,
+}
+
+/// Shared record of writes going through a `TrackedStorage`.
+#[derive(Debug, Clone, Default)]
+pub struct SaveTracker {
+    state: Arc<Mutex<SaveState>>,
+}
+
+impl SaveTracker {
+    pub(crate) fn begin(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.started = Some(now);
+        state.in_flight += 1;
+    }
+
+    pub(crate) fn fail(&self, error: String) {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).error = Some(error);
+    }
+
+    pub(crate) fn finish<T>(&self, result: &Result<T>) {
+        self.finish_with(result.as_ref().err().map(|e| e.to_string()));
+    }
+
+    /// Like `finish`, for writes whose error isn't a `QuillError`.
+    pub(crate) fn finish_with(&self, error: Option<String>) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.in_flight = state.in_flight.saturating_sub(1);
+        state.error = error;
+    }
+
+    /// The indicator to show at `now`, or None before the first write.
+    pub fn indicator(&self, now: Instant) -> Option<SaveIndicator> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let started = state.started?;
+        if state.in_flight > 0 || now.saturating_duration_since(started) < MIN_SAVING_DISPLAY {
+            Some(SaveIndicator::Saving)
+        } else if let Some(error) = &state.error {
+            Some(SaveIndicator::Failed(error.clone()))
+        } else {
+            Some(SaveIndicator::Saved)
+        }
+    }
+}
+
+/// Wraps a backend and records the outcome of every write in a `SaveTracker`.
+pub struct TrackedStorage {
+    inner: Box<dyn TaskStorage>,
+    tracker: SaveTracker,
+}
+
+impl TrackedStorage {
+    pub fn new(mut inner: Box<dyn TaskStorage>, tracker: SaveTracker) -> Self {
+        inner.track_saves(tracker.clone());
+        Self { inner, tracker }
+    }
+
+    fn track<T>(&self, result: Result<T>) -> Result<T> {
+        self.tracker.finish(&result);
+        result
+    }
+}
+
+#[async_trait]
+impl TaskStorage for TrackedStorage {
+    async fn get_tasks(&self, context_key: &str) -> Result<Vec<Task>> {
+        self.inner.get_tasks(context_key).await
+    }
+
+    async fn list_contexts(&self) -> Result<Vec<String>> {
+        self.inner.list_contexts().await
+    }
+
+    async fn get_all_tasks(&self) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.inner.get_all_tasks().await
+    }
+
+    async fn get_task_counts(&self, context_key: &str) -> Result<TaskCounts> {
+        self.inner.get_task_counts(context_key).await
+    }
+
+    async fn all_task_counts(&self) -> Result<BTreeMap<String, TaskCounts>> {
+        self.inner.all_task_counts().await
+    }
+
+    async fn open_task_counts(&self) -> Result<BTreeMap<String, usize>> {
+        self.inner.open_task_counts().await
+    }
+
+    async fn search_tasks(&self, query: &str) -> Result<BTreeMap<String, Vec<Task>>> {
+        self.inner.search_tasks(query).await
+    }
+
+    async fn add_task(&mut self, context_key: &str, text: String) -> Result<usize> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.add_task(context_key, text).await;
+        self.track(result)
+    }
+
+    async fn toggle_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.toggle_task(context_key, id).await;
+        self.track(result)
+    }
+
+    async fn set_task_status(&mut self, context_key: &str, id: usize, status: TaskStatus) -> Result<bool> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.set_task_status(context_key, id, status).await;
+        self.track(result)
+    }
+
+    async fn remove_task(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.remove_task(context_key, id).await;
+        self.track(result)
+    }
+
+    async fn edit_task(&mut self, context_key: &str, id: usize, new_text: String) -> Result<bool> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.edit_task(context_key, id, new_text).await;
+        self.track(result)
+    }
+
+    async fn update_task(&mut self, context_key: &str, task: Task) -> Result<bool> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.update_task(context_key, task).await;
+        self.track(result)
+    }
+
+    async fn undo_delete(&mut self, context_key: &str) -> Result<Option<Task>> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.undo_delete(context_key).await;
+        self.track(result)
+    }
+
+    async fn list_deleted(&self, context_key: &str) -> Result<Vec<DeletedTask>> {
+        self.inner.list_deleted(context_key).await
+    }
+
+    async fn restore_deleted(&mut self, context_key: &str, id: usize) -> Result<Option<Task>> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.restore_deleted(context_key, id).await;
+        self.track(result)
+    }
+
+    async fn purge_deleted(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.purge_deleted(context_key, id).await;
+        self.track(result)
+    }
+
+    async fn list_tombstones(&self, context_key: &str) -> Result<Vec<Tombstone>> {
+        self.inner.list_tombstones(context_key).await
+    }
+
+    async fn move_task_up(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.move_task_up(context_key, id).await;
+        self.track(result)
+    }
+
+    async fn move_task_down(&mut self, context_key: &str, id: usize) -> Result<bool> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.move_task_down(context_key, id).await;
+        self.track(result)
+    }
+
+    async fn delete_context(&mut self, context_key: &str) -> Result<usize> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.delete_context(context_key).await;
+        self.track(result)
+    }
+
+    async fn move_context_tasks(&mut self, from: &str, to: &str) -> Result<usize> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.move_context_tasks(from, to).await;
+        self.track(result)
+    }
+
+    async fn move_tasks(&mut self, from: &str, ids: &[usize], to: &str) -> Result<usize> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.move_tasks(from, ids, to).await;
+        self.track(result)
+    }
+
+    async fn record_activity(&mut self, entries: Vec<ActivityEntry>) -> Result<()> {
+        self.inner.record_activity(entries).await
+    }
+
+    async fn list_activity(&self, context_key: &str) -> Result<Vec<ActivityEntry>> {
+        self.inner.list_activity(context_key).await
+    }
+
+    async fn export_activity(&self, since: Option<&str>) -> Result<Vec<ActivityEntry>> {
+        self.inner.export_activity(since).await
+    }
+
+    async fn inspect_task(&self, context_key: &str, id: usize) -> Result<String> {
+        self.inner.inspect_task(context_key, id).await
+    }
+
+    async fn health(&self) -> Result<BackendHealth> {
+        self.inner.health().await
+    }
+
+    fn watch_path(&self) -> Option<std::path::PathBuf> {
+        self.inner.watch_path()
+    }
+
+    async fn check_external_changes(&mut self) -> Result<ExternalChange> {
+        self.inner.check_external_changes().await
+    }
+
+    async fn resolve_external_change(&mut self, keep_mine: bool) -> Result<()> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.resolve_external_change(keep_mine).await;
+        self.track(result)
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.tracker.begin(Instant::now());
+        let result = self.inner.flush().await;
+        self.track(result)
+    }
+
+    fn track_saves(&mut self, tracker: SaveTracker) {
+        self.inner.track_saves(tracker.clone());
+        self.tracker = tracker;
+    }
+
+    fn take_failed_writes(&mut self) -> Vec<String> {
+        let failures = self.inner.take_failed_writes();
+        if let Some(error) = failures.last() {
+            self.tracker.fail(error.clone());
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::local::LocalTaskStorage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_indicator_holds_saving_then_reports_result() {
+        let tracker = SaveTracker::default();
+        let start = Instant::now();
+        assert_eq!(tracker.indicator(start), None);
+
+        tracker.begin(start);
+        assert_eq!(tracker.indicator(start + MIN_SAVING_DISPLAY * 2), Some(SaveIndicator::Saving));
+
+        tracker.finish(&Ok(()));
+        assert_eq!(tracker.indicator(start), Some(SaveIndicator::Saving));
+        assert_eq!(tracker.indicator(start + MIN_SAVING_DISPLAY), Some(SaveIndicator::Saved));
+
+        tracker.begin(start);
+        tracker.finish::<()>(&Err(crate::QuillError::StorageUnavailable("disk full".to_string())));
+        assert_eq!(
+            tracker.indicator(start + MIN_SAVING_DISPLAY),
+            Some(SaveIndicator::Failed("disk full".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_only_writes_are_tracked() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test.json").to_string_lossy().to_string();
+        let tracker = SaveTracker::default();
+        let mut storage = TrackedStorage::new(Box::new(LocalTaskStorage::new(path).unwrap()), tracker.clone());
+
+        storage.get_tasks("ctx").await.unwrap();
+        assert_eq!(tracker.indicator(Instant::now()), None);
+
+        storage.add_task("ctx", "Task".to_string()).await.unwrap();
+        assert_eq!(tracker.indicator(Instant::now() + MIN_SAVING_DISPLAY), Some(SaveIndicator::Saved));
+    }
+}
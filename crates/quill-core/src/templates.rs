@@ -0,0 +1,134 @@
+use crate::git::GitContext;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A named list of task texts that can be applied to a context in one go.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskTemplate {
+    pub name: String,
+    pub tasks: Vec<String>,
+}
+
+impl TaskTemplate {
+    /// Returns the template's tasks with `{branch}`, `{repo}`, `{org}` and
+    /// `{date}` substituted for the given context.
+    pub fn render(&self, context: &GitContext, date: &str) -> Vec<String> {
+        self.tasks
+            .iter()
+            .map(|line| {
+                line.replace("{branch}", &context.branch)
+                    .replace("{repo}", &context.repo)
+                    .replace("{org}", &context.org)
+                    .replace("{date}", date)
+            })
+            .collect()
+    }
+
+    /// Parses a template file: one task per line, `#` comments and blank
+    /// lines ignored, Markdown list/checkbox prefixes stripped.
+    pub fn parse(name: &str, content: &str) -> Self {
+        let tasks = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let line = line
+                    .strip_prefix("- [ ] ")
+                    .or_else(|| line.strip_prefix("- [x] "))
+                    .or_else(|| line.strip_prefix("- "))
+                    .or_else(|| line.strip_prefix("* "))
+                    .unwrap_or(line);
+                line.to_string()
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            tasks,
+        }
+    }
+}
+
+/// Combines templates from the config with those in `<repo>/.quill/templates/`.
+/// Repository templates replace configured ones with the same name.
+pub fn load_templates(configured: &[TaskTemplate], repo_root: Option<&Path>) -> Vec<TaskTemplate> {
+    let mut templates = configured.to_vec();
+
+    if let Some(root) = repo_root {
+        for template in load_template_dir(&root.join(".quill").join("templates")) {
+            templates.retain(|t| t.name != template.name);
+            templates.push(template);
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+fn load_template_dir(dir: &Path) -> Vec<TaskTemplate> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.replace(['-', '_'], " ");
+            let content = fs::read_to_string(&path).ok()?;
+            Some(TaskTemplate::parse(&name, &content))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn context() -> GitContext {
+        GitContext {
+            org: "acme".to_string(),
+            repo: "widgets".to_string(),
+            branch: "release/1.2".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_substitutes_placeholders() {
+        let template = TaskTemplate {
+            name: "release".to_string(),
+            tasks: vec!["Tag {repo} from {branch}".to_string(), "Announce on {date} for {org}".to_string()],
+        };
+
+        let tasks = template.render(&context(), "2024-05-01");
+        assert_eq!(tasks[0], "Tag widgets from release/1.2");
+        assert_eq!(tasks[1], "Announce on 2024-05-01 for acme");
+    }
+
+    #[test]
+    fn test_parse_strips_markdown_and_comments() {
+        let template = TaskTemplate::parse("release", "# Release\n\n- [ ] Bump version\n- Update changelog\nRun tests\n");
+        assert_eq!(template.tasks, vec!["Bump version", "Update changelog", "Run tests"]);
+    }
+
+    #[test]
+    fn test_repo_templates_override_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join(".quill").join("templates");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("release-checklist.md"), "- [ ] From repo\n").unwrap();
+
+        let configured = vec![
+            TaskTemplate { name: "release checklist".to_string(), tasks: vec!["From config".to_string()] },
+            TaskTemplate { name: "bugfix".to_string(), tasks: vec!["Add regression test".to_string()] },
+        ];
+
+        let templates = load_templates(&configured, Some(temp_dir.path()));
+        assert_eq!(templates.len(), 2);
+        assert_eq!(templates[0].name, "bugfix");
+        assert_eq!(templates[1].tasks, vec!["From repo"]);
+    }
+}
@@ -0,0 +1,46 @@
+use ratatui::style::Color;
+
+/// 256-color palette entries that read well on dark and light backgrounds
+/// and are far enough apart to tell at a glance.
+const PALETTE: [Color; 12] = [
+    Color::Indexed(39),  // blue
+    Color::Indexed(214), // orange
+    Color::Indexed(41),  // green
+    Color::Indexed(170), // magenta
+    Color::Indexed(44),  // teal
+    Color::Indexed(203), // salmon
+    Color::Indexed(141), // purple
+    Color::Indexed(178), // gold
+    Color::Indexed(75),  // sky
+    Color::Indexed(113), // lime
+    Color::Indexed(211), // pink
+    Color::Indexed(109), // slate
+];
+
+/// FNV-1a, so a repository keeps its color across runs and builds.
+fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3))
+}
+
+/// The accent color of the repository a context key `org:repo:branch`
+/// belongs to; every branch of a repository shares it.
+pub fn for_context(context_key: &str) -> Color {
+    let mut parts = context_key.splitn(3, ':');
+    let repo = format!("{}/{}", parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    PALETTE[(hash(&repo) % PALETTE.len() as u64) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accent_is_per_repository() {
+        assert_eq!(for_context("acme:web:main"), for_context("acme:web:feature/login"));
+        assert_eq!(for_context("acme:web:main"), for_context("acme:web:main"));
+
+        let repos = ["acme:web:main", "acme:api:main", "acme:infra:main", "me:dotfiles:main", "oss:quill:main"];
+        let distinct: std::collections::HashSet<String> = repos.iter().map(|key| format!("{:?}", for_context(key))).collect();
+        assert!(distinct.len() > 1);
+    }
+}
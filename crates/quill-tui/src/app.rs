@@ -0,0 +1,4010 @@
+use crate::{
+    bulk::{self, BulkEdit, BulkEntry},
+    carry,
+    cleanup,
+    crash::TerminalGuard,
+    import,
+    input::EventSource,
+    config::{AppConfig, CarryPolicy, ContextScope, ProjectConfig, StorageType},
+    dates::{DateParser, DueShift},
+    defer,
+    diff::ContextDiff,
+    external_editor,
+    shared::SharedConfig,
+    git::{self, Forge, GitContext},
+    github::{GitHubClient, IssueLink},
+    gitlab::GitLabClient,
+    jira::{self, JiraClient, SyncAction},
+    keys::{PendingKeys, Resolved},
+    logging,
+    overview::Row,
+    palette::PaletteItem,
+    prompt::Rollup,
+    plugins::{self, Plugins},
+    quickadd::QuickAdd,
+    secret,
+    similar,
+    state::ViewState,
+    storage::{self, local::LocalTaskStorage, read_only::ReadOnlyStorage, Anchor, Attachment, ExternalChange, Priority, tracked::{SaveTracker, TrackedStorage}, Task, TaskCounts, TaskStorage, TaskStatus},
+    subtasks,
+    summary::{self, SessionSummary},
+    sync::{self, SyncDirection, SyncLog, SyncLogEntry},
+    templates,
+    ui::{ContextStats, InputMode, SetupStep, SortOrder, StorageFailure, TaskUI},
+    views::{self, View},
+};
+use anyhow::Result;
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    Terminal,
+};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+pub struct App {
+    ui: TaskUI,
+    storage: Box<dyn TaskStorage>,
+    current_context: GitContext,
+    context_updates: mpsc::UnboundedReceiver<GitContext>,
+    config: AppConfig,
+    project_config: Option<ProjectConfig>,
+    /// Team settings fetched at startup (or the cached copy), layered under `config`.
+    shared_config: Option<SharedConfig>,
+    storage_error: Option<String>,
+    view_state: ViewState,
+    sync_log: SyncLog,
+    /// Outcome of the latest write, shown in the footer.
+    save_tracker: SaveTracker,
+    /// Bulk change waiting for its typed confirmation.
+    pending_bulk: Option<BulkAction>,
+    /// Count prefix and operator typed in normal mode.
+    pending_keys: PendingKeys,
+    /// Commands that only run from their chord (or the palette), with the chord.
+    chords: Vec<(Command, String)>,
+    /// Commands behind the palette entries currently shown, by index.
+    palette_commands: Vec<Command>,
+    /// Set by key handlers that need the terminal handed to `$EDITOR`.
+    pending_external_edit: Option<ExternalEdit>,
+    /// Watched contexts with unseen open tasks, and when they were last counted.
+    watch_unseen: Vec<(String, usize)>,
+    watch_checked: Option<Instant>,
+    rollup_checked: Option<Instant>,
+    /// When the other contexts were last checked for snoozes that ran out.
+    snooze_checked: Option<Instant>,
+    /// The context left for the inbox, which `i` returns to.
+    inbox_return: Option<GitContext>,
+    /// Set while another backend is browsed read-only.
+    peek: Option<Peek>,
+    /// Storage rule the open backend was picked by; `None` for the default backend.
+    storage_rule: Option<usize>,
+    /// Sources listed in the import picker, in display order.
+    import_offer: Vec<import::Source>,
+    /// Watches the storage's file for other writers, with the path watched.
+    storage_watcher: Option<(PathBuf, Option<RecommendedWatcher>)>,
+    storage_changes: mpsc::UnboundedReceiver<()>,
+    storage_changes_tx: mpsc::UnboundedSender<()>,
+    plugins: Plugins,
+    /// The current context's tasks as plugins last saw them.
+    plugin_baseline: Option<(String, Vec<Task>)>,
+    /// The linked issue's and Jira's syncs started at launch, applied once
+    /// their fetches are done.
+    startup_issue_sync: Option<StartupFetch<Vec<String>>>,
+    startup_jira_sync: Option<StartupFetch<Vec<jira::Issue>>>,
+}
+
+/// The fetch of a sync started at launch, run in the background so a slow
+/// server doesn't hold up the first frame.
+struct StartupFetch<T> {
+    context_key: String,
+    fetch: tokio::task::JoinHandle<Result<T>>,
+}
+
+impl<T: Send + 'static> StartupFetch<T> {
+    fn spawn(context_key: String, fetch: impl Future<Output = Result<T>> + Send + 'static) -> Self {
+        let fetch = tokio::spawn(async move {
+            tokio::time::timeout(STARTUP_SYNC_TIMEOUT, fetch)
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("no reply within {}s", STARTUP_SYNC_TIMEOUT.as_secs())))
+        });
+        Self { context_key, fetch }
+    }
+
+    /// Takes the fetch out of `slot` once it's done, with its context.
+    async fn take_finished(slot: &mut Option<Self>) -> Option<(String, Result<T>)> {
+        if !slot.as_ref().is_some_and(|startup| startup.fetch.is_finished()) {
+            return None;
+        }
+        let startup = slot.take()?;
+        Some((startup.context_key, startup.fetch.await.unwrap_or_else(|e| Err(e.into()))))
+    }
+}
+
+/// The default storage, put aside while another backend is peeked at.
+struct Peek {
+    backend: StorageType,
+    default_storage: Box<dyn TaskStorage>,
+}
+
+/// How often watched contexts are re-counted for the header badge.
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+/// How often the header's repository and organization counts are recounted,
+/// and how soon after the count here changes.
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(10);
+const ROLLUP_CHANGED_INTERVAL: Duration = Duration::from_secs(1);
+/// How often contexts other than the current one are checked for tasks back from snooze.
+const SNOOZE_INTERVAL: Duration = Duration::from_secs(60);
+/// How long the syncs started at launch may take before they're reported as failed.
+const STARTUP_SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+/// How many of the latest commits are searched for ones mentioning a task.
+const LINKED_COMMIT_DEPTH: usize = 500;
+
+/// Every action reachable from the keymap or the command palette.
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Quit,
+    AddTask,
+    EditTask,
+    DeleteTask,
+    UndoDelete,
+    CycleStatus,
+    SetStatus(TaskStatus),
+    ToggleMustFinish,
+    TogglePin,
+    ToggleMark,
+    ClearMarks,
+    ShiftDue,
+    Snooze,
+    ToggleSnoozed,
+    DeferToBranch,
+    EditNotes,
+    SetAnchor,
+    OpenAnchor,
+    ShowAttachments,
+    ShowNotes,
+    ToggleDetails,
+    BulkEdit,
+    CompleteAll,
+    ExportIssue,
+    SyncJira,
+    OpenSyncLog,
+    OpenTrash,
+    ShowActivity,
+    ShowStats,
+    ShowOverview,
+    Search,
+    CleanUpBranches,
+    ShowHealth,
+    PeekBackend,
+    ApplyTemplate,
+    PickView,
+    ImportTodos,
+    OpenConfig,
+    InspectTask,
+    ToggleFilter(TaskStatus),
+    CycleAssigneeFilter,
+    AssignTask,
+    CycleSort,
+    MoveUp,
+    MoveDown,
+    SelectNext,
+    SelectPrevious,
+    PageUp,
+    PageDown,
+    SelectFirst,
+    SelectLast,
+    OpenPalette,
+    SwitchContext(String),
+    ToggleWatch,
+    JumpToWatched,
+    ToggleInbox,
+    MoveToContext,
+    AddSubtask,
+    CyclePriority,
+    TogglePropagation,
+    ToggleDebugLog,
+    /// A command a plugin added to the palette, by index.
+    Plugin(usize),
+}
+
+impl Command {
+    /// Whether the command changes tasks, which isn't possible while peeking.
+    fn writes(&self) -> bool {
+        matches!(
+            self,
+            Command::AddTask
+                | Command::AddSubtask
+                | Command::EditTask
+                | Command::DeleteTask
+                | Command::UndoDelete
+                | Command::CycleStatus
+                | Command::SetStatus(_)
+                | Command::CompleteAll
+                | Command::ToggleMustFinish
+                | Command::TogglePin
+                | Command::ShiftDue
+                | Command::Snooze
+                | Command::DeferToBranch
+                | Command::MoveToContext
+                | Command::CleanUpBranches
+                | Command::CyclePriority
+                | Command::AssignTask
+                | Command::EditNotes
+                | Command::SetAnchor
+                | Command::ShowAttachments
+                | Command::BulkEdit
+                | Command::ExportIssue
+                | Command::SyncJira
+                | Command::OpenTrash
+                | Command::ApplyTemplate
+                | Command::ImportTodos
+                | Command::Plugin(_)
+                | Command::MoveUp
+                | Command::MoveDown
+        )
+    }
+
+    /// Whether a count prefix (`5j`, `3dd`) repeats the command.
+    fn repeats(&self) -> bool {
+        matches!(
+            self,
+            Command::SelectNext
+                | Command::SelectPrevious
+                | Command::PageUp
+                | Command::PageDown
+                | Command::MoveUp
+                | Command::MoveDown
+                | Command::DeleteTask
+                | Command::ToggleMark
+                | Command::UndoDelete
+        )
+    }
+}
+
+/// Commands listed in the palette as (command, label, key binding).
+const COMMANDS: &[(Command, &str, &str)] = &[
+    (Command::AddTask, "Add task", "a"),
+    (Command::AddSubtask, "Add subtask to selected task", "A"),
+    (Command::EditTask, "Edit task", "e"),
+    (Command::DeleteTask, "Delete task", "d"),
+    (Command::UndoDelete, "Undo delete", "u"),
+    (Command::CycleStatus, "Cycle status", "Space"),
+    (Command::SetStatus(TaskStatus::NotStarted), "Set status: Not Started", "1"),
+    (Command::SetStatus(TaskStatus::InProgress), "Set status: In Progress", "2"),
+    (Command::SetStatus(TaskStatus::Completed), "Set status: Completed", "3"),
+    (Command::CompleteAll, "Complete all open tasks", ""),
+    (Command::ToggleMustFinish, "Toggle must finish before push", "p"),
+    (Command::TogglePin, "Pin / unpin task to the top", "*"),
+    (Command::ToggleMark, "Mark / unmark task", "v"),
+    (Command::ClearMarks, "Clear marks", "Esc"),
+    (Command::ShiftDue, "Set or shift due date", "D"),
+    (Command::Snooze, "Snooze task until a date or time", "z"),
+    (Command::ToggleSnoozed, "Show / hide snoozed tasks", "Z"),
+    (Command::DeferToBranch, "Defer task to another branch", ">"),
+    (Command::MoveToContext, "Move task to another context (triage the inbox)", "m"),
+    (Command::CyclePriority, "Cycle priority", "!"),
+    (Command::AssignTask, "Assign task to a teammate", "@"),
+    (Command::TogglePropagation, "Toggle applying priority/due changes to subtasks", "P"),
+    (Command::EditNotes, "Edit notes in $EDITOR", "n"),
+    (Command::SetAnchor, "Set file anchor", "f"),
+    (Command::OpenAnchor, "Open anchored file in editor", "o"),
+    (Command::ShowAttachments, "Show, open and attach files and URLs", "F"),
+    (Command::ShowNotes, "Read notes and open links", "U"),
+    (Command::ToggleDetails, "Toggle the details pane", "|"),
+    (Command::BulkEdit, "Bulk edit tasks in $EDITOR", "E"),
+    (Command::MoveUp, "Move task up", "Ctrl+↑"),
+    (Command::MoveDown, "Move task down", "Ctrl+↓"),
+    (Command::CycleSort, "Sort: manual / priority / due date", ""),
+    (Command::ToggleFilter(TaskStatus::NotStarted), "Toggle filter: Not Started", "F1"),
+    (Command::ToggleFilter(TaskStatus::InProgress), "Toggle filter: In Progress", "F2"),
+    (Command::ToggleFilter(TaskStatus::Completed), "Toggle filter: Completed", "F3"),
+    (Command::CycleAssigneeFilter, "Show tasks of: everyone / mine / unassigned", "F4"),
+    (Command::SelectFirst, "Go to first task", "Home"),
+    (Command::SelectLast, "Go to last task", "End"),
+    (Command::ExportIssue, "Export to GitHub or GitLab issue", "G"),
+    (Command::SyncJira, "Sync with Jira", "J"),
+    (Command::OpenSyncLog, "Open sync log", "L"),
+    (Command::OpenTrash, "Open trash", "T"),
+    (Command::ShowActivity, "Show who changed what in this context", "R"),
+    (Command::ShowStats, "Show stats", "s"),
+    (Command::ShowOverview, "Show open tasks of every context", "O"),
+    (Command::Search, "Search tasks of every context", "Ctrl+f"),
+    (Command::CleanUpBranches, "Clean up task lists of deleted or merged branches", ""),
+    (Command::ShowHealth, "Show storage health", "H"),
+    (Command::PeekBackend, "Peek at another storage backend (read-only)", "B"),
+    (Command::ApplyTemplate, "Apply template", "t"),
+    (Command::PickView, "Switch to a saved view (filter)", "V"),
+    (Command::ImportTodos, "Import todo.txt, TODO.md or Taskwarrior tasks", ""),
+    (Command::OpenConfig, "Open config", "c"),
+    (Command::ToggleDebugLog, "Show / hide debug log", "F12"),
+    (Command::InspectTask, "Inspect stored task", "I"),
+    (Command::ToggleWatch, "Watch / unwatch context", "w"),
+    (Command::JumpToWatched, "Jump to watched context", "W"),
+    (Command::ToggleInbox, "Go to inbox / back to the repository", "i"),
+    (Command::Quit, "Quit", "q"),
+];
+
+/// The normal-mode keymap.
+/// Actions that can be put behind a chord with `[chords]`, by config name.
+const CHORD_ACTIONS: &[(&str, Command)] = &[
+    ("delete", Command::DeleteTask),
+    ("complete", Command::SetStatus(TaskStatus::Completed)),
+    ("complete_all", Command::CompleteAll),
+    ("toggle_must_finish", Command::ToggleMustFinish),
+];
+
+/// The items ticked in `link`'s issue on its forge.
+async fn fetch_checked_items(config: AppConfig, context: GitContext, link: IssueLink) -> Result<Vec<String>> {
+    match link.forge {
+        Forge::GitLab => GitLabClient::new(&config.gitlab)?.checked_items(&link).await,
+        _ => GitHubClient::new(&config.github)?.checked_items(&context, &link).await,
+    }
+}
+
+/// The sync log integration issues on `forge` are recorded under.
+fn issue_integration(forge: Forge) -> &'static str {
+    match forge {
+        Forge::GitLab => sync::GITLAB,
+        _ => sync::GITHUB,
+    }
+}
+
+fn command_for_key(key: KeyCode, modifiers: KeyModifiers) -> Option<Command> {
+    let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+    Some(match key {
+        KeyCode::Char('q') => Command::Quit,
+        KeyCode::Char('a') => Command::AddTask,
+        KeyCode::Char('A') => Command::AddSubtask,
+        KeyCode::Char('!') => Command::CyclePriority,
+        KeyCode::Char('P') => Command::TogglePropagation,
+        KeyCode::Char('c') => Command::OpenConfig,
+        KeyCode::Char(':') => Command::OpenPalette,
+        KeyCode::Down | KeyCode::Char('j') if ctrl => Command::MoveDown,
+        KeyCode::Down | KeyCode::Char('j') => Command::SelectNext,
+        KeyCode::Up | KeyCode::Char('k') if ctrl => Command::MoveUp,
+        KeyCode::Up | KeyCode::Char('k') => Command::SelectPrevious,
+        KeyCode::PageDown => Command::PageDown,
+        KeyCode::PageUp => Command::PageUp,
+        KeyCode::Home => Command::SelectFirst,
+        KeyCode::End => Command::SelectLast,
+        KeyCode::Char(' ') => Command::CycleStatus,
+        KeyCode::Char('1') => Command::SetStatus(TaskStatus::NotStarted),
+        KeyCode::Char('2') => Command::SetStatus(TaskStatus::InProgress),
+        KeyCode::Char('3') => Command::SetStatus(TaskStatus::Completed),
+        KeyCode::Char('d') => Command::DeleteTask,
+        KeyCode::Char('p') => Command::ToggleMustFinish,
+        KeyCode::Char('*') => Command::TogglePin,
+        KeyCode::F(1) => Command::ToggleFilter(TaskStatus::NotStarted),
+        KeyCode::F(2) => Command::ToggleFilter(TaskStatus::InProgress),
+        KeyCode::F(3) => Command::ToggleFilter(TaskStatus::Completed),
+        KeyCode::F(4) => Command::CycleAssigneeFilter,
+        KeyCode::Char('@') => Command::AssignTask,
+        KeyCode::F(12) => Command::ToggleDebugLog,
+        KeyCode::Char('s') => Command::ShowStats,
+        KeyCode::Char('O') => Command::ShowOverview,
+        KeyCode::Char('f') if ctrl => Command::Search,
+        KeyCode::Char('H') => Command::ShowHealth,
+        KeyCode::Char('B') => Command::PeekBackend,
+        KeyCode::Char('T') => Command::OpenTrash,
+        KeyCode::Char('R') => Command::ShowActivity,
+        KeyCode::Char('t') => Command::ApplyTemplate,
+        KeyCode::Char('V') => Command::PickView,
+        KeyCode::Char('v') => Command::ToggleMark,
+        KeyCode::Esc => Command::ClearMarks,
+        KeyCode::Char('D') => Command::ShiftDue,
+        KeyCode::Char('z') => Command::Snooze,
+        KeyCode::Char('Z') => Command::ToggleSnoozed,
+        KeyCode::Char('>') => Command::DeferToBranch,
+        KeyCode::Char('n') => Command::EditNotes,
+        KeyCode::Char('f') => Command::SetAnchor,
+        KeyCode::Char('o') => Command::OpenAnchor,
+        KeyCode::Char('F') => Command::ShowAttachments,
+        KeyCode::Char('U') => Command::ShowNotes,
+        KeyCode::Char('|') => Command::ToggleDetails,
+        KeyCode::Char('L') => Command::OpenSyncLog,
+        KeyCode::Char('G') => Command::ExportIssue,
+        KeyCode::Char('J') => Command::SyncJira,
+        KeyCode::Char('E') => Command::BulkEdit,
+        KeyCode::Char('I') => Command::InspectTask,
+        KeyCode::Char('e') => Command::EditTask,
+        KeyCode::Char('u') => Command::UndoDelete,
+        KeyCode::Char('w') => Command::ToggleWatch,
+        KeyCode::Char('W') => Command::JumpToWatched,
+        KeyCode::Char('i') => Command::ToggleInbox,
+        KeyCode::Char('m') => Command::MoveToContext,
+        _ => return None,
+    })
+}
+
+/// A bulk change held back until its confirmation phrase is typed.
+enum BulkAction {
+    Delete(Vec<usize>),
+    Complete(Vec<usize>),
+    BulkEdit { original: Vec<Task>, edit: BulkEdit },
+}
+
+impl BulkAction {
+    /// Tasks deleted or completed, compared against `confirm_threshold`.
+    fn size(&self) -> usize {
+        match self {
+            BulkAction::Delete(ids) | BulkAction::Complete(ids) => ids.len(),
+            BulkAction::BulkEdit { original, edit } => edit.removed.len().max(edit.completed(original)),
+        }
+    }
+
+    fn phrase(&self) -> String {
+        match self {
+            BulkAction::Delete(ids) => format!("delete {}", ids.len()),
+            BulkAction::Complete(ids) => format!("complete {}", ids.len()),
+            BulkAction::BulkEdit { original, edit } if edit.removed.len() >= edit.completed(original) => {
+                format!("delete {}", edit.removed.len())
+            }
+            BulkAction::BulkEdit { original, edit } => format!("complete {}", edit.completed(original)),
+        }
+    }
+}
+
+/// Actions that suspend the TUI to run an external editor.
+enum ExternalEdit {
+    /// Edit every task of the current context as a text buffer.
+    BulkEdit,
+    /// Edit the notes of the task with this id.
+    Notes(usize),
+    /// Open the linked file of the task with this id.
+    OpenAnchor(usize),
+    /// Open an attachment of the task with this id, by index.
+    OpenAttachment(usize, usize),
+    /// Write the text (and notes) of the task being added or edited.
+    InputText,
+    /// Open a link from a task's text or notes.
+    OpenLink(String),
+}
+
+impl App {
+    pub async fn new() -> Result<Self> {
+        let config = AppConfig::load()?;
+
+        let mut storage_error = None;
+
+        let project_config = match ProjectConfig::discover() {
+            Ok(project_config) => project_config,
+            Err(e) => {
+                storage_error = Some(format!("Ignoring project config: {}", e));
+                None
+            }
+        };
+        let shared_config = match SharedConfig::fetch(&config).await {
+            Ok(shared_config) => shared_config,
+            Err(e) => {
+                storage_error = Some(format!("Using the cached shared config: {}", e));
+                SharedConfig::cached(&config.shared)
+            }
+        };
+        let effective_config = config.with_shared(shared_config.as_ref()).with_project(project_config.as_ref());
+        let detection = GitContext::detect(&git::current_dir(), &effective_config.forges);
+        if let Some(error) = &detection.degraded {
+            storage_error = Some(format!("git could not open this repository ({}); read the context from .git directly", error));
+        }
+        let detected_context = detection.context;
+        // Ignored branches fall back to the repository-wide list
+        let current_context = effective_config
+            .resolve_context(detected_context.clone())
+            .unwrap_or_else(|| GitContext { branch: "*".to_string(), ..detected_context.clone() });
+        let storage_rule = effective_config.storage_rule(&current_context);
+        let effective_config = effective_config.for_context(&current_context);
+        tracing::info!(context = %current_context.context_key(), version = env!("CARGO_PKG_VERSION"), "starting");
+        
+        let mut success_message = None;
+        let mut storage_failure = None;
+        // Without a config file or QUILL_STORAGE_TYPE, setup picks the storage first
+        let first_run = !AppConfig::exists() && std::env::var_os("QUILL_STORAGE_TYPE").is_none();
+        let opened = if first_run { None } else { Some(storage::open_interactive(&effective_config).await) };
+        let storage: Box<dyn TaskStorage> = match opened {
+            None => Box::new(ReadOnlyStorage::new(Box::new(LocalTaskStorage::in_memory()), "No storage")),
+            Some(Ok(storage)) => {
+                success_message = Some(match effective_config.storage_type {
+                    StorageType::Local => "Successfully connected to local storage".to_string(),
+                    StorageType::MongoDB => "Successfully connected to MongoDB".to_string(),
+                    StorageType::Git => format!("Using tasks committed to branch {}", effective_config.git_config.branch),
+                    StorageType::Remote => format!("Connected to sync server {}", effective_config.remote_config.url),
+                });
+                storage
+            }
+            // Nothing is read or written until the recovery screen is answered
+            Some(Err(e)) => {
+                tracing::error!(backend = effective_config.storage_type.label(), error = %e, "storage failed to open");
+                storage_failure = Some(StorageFailure {
+                    backend: effective_config.storage_type.clone(),
+                    error: e.to_string(),
+                    can_switch: storage_rule.is_none() && effective_config.storage_type != StorageType::Local,
+                });
+                Box::new(ReadOnlyStorage::new(Box::new(LocalTaskStorage::in_memory()), "No storage"))
+            }
+        };
+
+        let context_updates = git::spawn_context_watcher(detected_context, Duration::from_secs(1), effective_config.forges.clone());
+        let mut app = Self::with_storage(config, storage, current_context, context_updates, Plugins::load());
+        app.project_config = project_config;
+        app.shared_config = shared_config;
+        app.storage_error = storage_error;
+        app.view_state = ViewState::load().unwrap_or_default();
+        app.sync_log = SyncLog::load().unwrap_or_default();
+        app.storage_rule = storage_rule;
+
+        app.ui.status_filter = app.view_state.status_filter(&app.current_context.context_key());
+        app.ui.issue_link = app.view_state.issue_links.get(&app.current_context.context_key()).cloned();        
+        app.ui.jira_issue = app.branch_jira_issue();
+        app.ui.propagate_to_subtasks = app.view_state.propagate_to_subtasks;
+        app.ui.assignee_filter = app.view_state.assignee_filter;
+        app.ui.user_email = git::user_email(&git::current_dir());
+        app.apply_display_config(&effective_config);
+        // Syncs, migration and the import offer wait for a working storage
+        let storage_ready = storage_failure.is_none() && !first_run;
+
+        if storage_ready {
+            app.migrate_legacy_org(&effective_config).await;
+        }
+
+        // Pick up items ticked in the context's GitHub or GitLab issue since last time
+        if let Some(link) = app.ui.issue_link.clone().filter(|_| storage_ready) {
+            if !app.sync_log.is_paused(issue_integration(link.forge)) {
+                let fetch = fetch_checked_items(app.effective_config(), app.current_context.clone(), link);
+                app.startup_issue_sync = Some(StartupFetch::spawn(app.current_context.context_key(), fetch));
+            }
+        }
+        // Likewise for Jira issues, when the context maps to a project or issue
+        let jira = &effective_config.jira;
+        let jira_linked = app.ui.jira_issue.is_some() || jira.project_for(&app.current_context.context_key()).is_some();
+        if storage_ready && jira.is_enabled() && jira_linked && !app.sync_log.is_paused(sync::JIRA) {
+            let context_key = app.current_context.context_key();
+            match app.jira_query(&context_key).await {
+                Ok(jql) => {
+                    let config = jira.clone();
+                    let fetch = async move { JiraClient::new(&config)?.search(&jql).await };
+                    app.startup_jira_sync = Some(StartupFetch::spawn(context_key, fetch));
+                }
+                Err(e) => {
+                    app.record_sync(SyncLogEntry::new(sync::JIRA, SyncDirection::Pull, &context_key, &Err(e)));
+                }
+            }
+        }
+
+        // Bring over lists from the legacy `~/.quill/todos.json`
+        if let Some(path) = storage::legacy::legacy_path().filter(|p| storage_ready && p.to_string_lossy() != effective_config.expand_local_path()) {
+            match storage::legacy::migrate(&path, app.storage.as_mut()).await {
+                Ok(Some(migration)) => {
+                    success_message = Some(format!(
+                        "Migrated {} task(s) in {} context(s) from {}; the old file was kept as {}",
+                        migration.tasks,
+                        migration.contexts,
+                        path.display(),
+                        migration.archived_to.display()
+                    ));
+                }
+                Ok(None) => {}
+                Err(e) => app.storage_error = Some(format!("Could not migrate legacy tasks: {}", e)),
+            }
+        }
+
+        if storage_ready {
+            app.offer_first_import().await;
+        }
+
+        for error in &app.plugins.errors {
+            tracing::warn!(%error, "plugin failed to load");
+        }
+        if let Some(error) = app.plugins.errors.first() {
+            app.storage_error.get_or_insert_with(|| format!("Plugin failed to load: {}", error));
+        }
+
+        // Show storage error notification if any
+        if let Some(error_msg) = &app.storage_error {
+            app.ui.show_notification(error_msg.clone(), crate::ui::NotificationLevel::Error);
+        }
+        
+        // Show success notification if storage connected successfully
+        if let Some(success_msg) = success_message {
+            app.ui.show_notification(success_msg, crate::ui::NotificationLevel::Success);
+        }
+        if let Some(failure) = storage_failure {
+            app.ui.open_storage_recovery(failure);
+        }
+        if first_run {
+            app.ui.start_setup(&app.config);
+        }
+        
+        Ok(app)
+    }
+
+    /// Moves the repository's lists from the org earlier versions read its
+    /// remote as (e.g. only the top GitLab group) to the one read now.
+    async fn migrate_legacy_org(&mut self, config: &AppConfig) {
+        let Some(legacy) = git::legacy_org(&git::current_dir(), &config.forges) else {
+            return;
+        };
+        let prefix = format!("{}:{}:", legacy, self.current_context.repo);
+        let result = async {
+            let mut moved = 0;
+            for context_key in self.storage.list_contexts().await? {
+                if let Some(branch) = context_key.strip_prefix(&prefix) {
+                    let to = GitContext { branch: branch.to_string(), ..self.current_context.clone() }.context_key();
+                    moved += self.storage.move_context_tasks(&context_key, &to).await?;
+                }
+            }
+            anyhow::Ok(moved)
+        }
+        .await;
+        match result {
+            Ok(0) => {}
+            Ok(moved) => self.ui.show_notification(
+                format!("Moved {} task(s) from {} to {}", moved, prefix.trim_end_matches(':'), self.current_context.org),
+                crate::ui::NotificationLevel::Success,
+            ),
+            Err(e) => self.storage_error = Some(format!("Could not move tasks from {}: {}", prefix.trim_end_matches(':'), e)),
+        }
+    }
+
+    /// An app over `storage` without the user's saved state, which `new` loads on top.
+    fn with_storage(
+        config: AppConfig,
+        storage: Box<dyn TaskStorage>,
+        current_context: GitContext,
+        context_updates: mpsc::UnboundedReceiver<GitContext>,
+        plugins: Plugins,
+    ) -> Self {
+        let save_tracker = SaveTracker::default();
+        let (storage_changes_tx, storage_changes) = mpsc::unbounded_channel();
+        Self {
+            ui: TaskUI::new(),
+            storage: Box::new(TrackedStorage::new(storage, save_tracker.clone())),
+            current_context,
+            context_updates,
+            config,
+            project_config: None,
+            shared_config: None,
+            storage_error: None,
+            view_state: ViewState::default(),
+            sync_log: SyncLog::default(),
+            save_tracker,
+            pending_bulk: None,
+            pending_keys: PendingKeys::default(),
+            chords: Vec::new(),
+            palette_commands: Vec::new(),
+            pending_external_edit: None,
+            watch_unseen: Vec::new(),
+            watch_checked: None,
+            rollup_checked: None,
+            snooze_checked: None,
+            inbox_return: None,
+            peek: None,
+            storage_rule: None,
+            import_offer: Vec::new(),
+            storage_watcher: None,
+            storage_changes,
+            storage_changes_tx,
+            plugins,
+            plugin_baseline: None,
+            startup_issue_sync: None,
+            startup_jira_sync: None,
+        }
+    }
+
+    /// On a first run with no tasks anywhere, offers to bring over todo files.
+    async fn offer_first_import(&mut self) {
+        if self.view_state.import_offered || !self.storage.list_contexts().await.is_ok_and(|contexts| contexts.is_empty()) {
+            return;
+        }
+        self.view_state.import_offered = true;
+        let _ = self.view_state.save();
+        self.import_offer = import::scan(&self.effective_config().for_context(&self.current_context));
+        if !self.import_offer.is_empty() {
+            self.ui.open_import_picker(self.import_offer.iter().map(|source| source.summary()).collect());
+        }
+    }
+
+    /// Applies the settings the UI reads directly.
+    fn apply_display_config(&mut self, config: &AppConfig) {
+        self.ui.open_task_soft_limit = config.open_task_soft_limit;
+        self.ui.long_titles = config.long_titles;
+        self.ui.theme = config.theme;
+        self.ui.status_line = config.status_line.clone();
+        self.ui.backend = config.storage_type.label().to_string();
+        if self.ui.details_pane != config.details_pane {
+            self.ui.show_details = config.details_pane.enabled;
+            self.ui.details_pane = config.details_pane.clone();
+        }
+        self.apply_chords(config);
+        match DateParser::new(&config.date_locales, &config.date_words) {
+            Ok(parser) => self.ui.date_parser = parser,
+            Err(e) => self.ui.show_notification(format!("Ignoring date_locales: {}", e), crate::ui::NotificationLevel::Error),
+        }
+        self.ui.views = config.views.iter().map(|(name, expression)| View::new(name, expression, &self.ui.date_parser)).collect();
+        if !self.ui.active_view.as_ref().is_none_or(|name| config.views.contains_key(name)) {
+            self.ui.active_view = None;
+        }
+    }
+
+    /// The global config with the repository's `.quill.toml` applied.
+    fn effective_config(&self) -> AppConfig {
+        self.config.with_shared(self.shared_config.as_ref()).with_project(self.project_config.as_ref())
+    }
+
+    /// Adds a task to the current context, applying the configured default
+    /// tags and tag rules. On MongoDB, which a team shares, the task is
+    /// assigned to whoever added it.
+    async fn add_task(&mut self, text: String) -> Result<usize> {
+        let context_key = self.current_context.context_key();
+        let config = self.effective_config();
+        let tags = config.tags_for(&context_key, &text);
+        let assignee = match config.for_context(&self.current_context).storage_type {
+            StorageType::MongoDB if self.peek.is_none() => self.ui.user_email.clone(),
+            _ => None,
+        };
+        let id = self.storage.add_task(&context_key, text).await?;
+
+        if !tags.is_empty() || assignee.is_some() {
+            let tasks = self.storage.get_tasks(&context_key).await?;
+            if let Some(mut task) = tasks.into_iter().find(|t| t.id == id) {
+                task.tags = tags;
+                task.assignee = assignee;
+                self.storage.update_task(&context_key, task).await?;
+            }
+        }
+        Ok(id)
+    }
+
+    /// Adds a task typed with quick-add metadata (`!high #tag @tomorrow`).
+    async fn add_quick_task(&mut self, input: &str, parent: Option<usize>) -> Result<usize> {
+        let parsed = QuickAdd::parse(input, chrono::Local::now().date_naive(), &self.ui.date_parser);
+        if parsed.text.is_empty() {
+            return Err(anyhow::anyhow!("task text is empty"));
+        }
+
+        let context_key = self.current_context.context_key();
+        let id = self.add_task(parsed.text.clone()).await?;
+        if parsed.has_metadata() || parent.is_some() {
+            let tasks = self.storage.get_tasks(&context_key).await?;
+            if let Some(mut task) = tasks.into_iter().find(|t| t.id == id) {
+                for tag in parsed.tags {
+                    if !task.tags.contains(&tag) {
+                        task.tags.push(tag);
+                    }
+                }
+                task.priority = parsed.priority;
+                task.due = parsed.due.or(task.due);
+                task.parent = parent;
+                self.storage.update_task(&context_key, task).await?;
+            }
+        }
+        Ok(id)
+    }
+
+    /// Looks up the commits mentioning the selected task for the details
+    /// pane, once per selected task.
+    fn find_linked_commits(&mut self, tasks: &[Task]) {
+        let Some(task) = self.ui.list_state.selected().and_then(|index| tasks.get(index)) else {
+            return;
+        };
+        let short_uid = task.short_uid();
+        if self.ui.details_commits.as_ref().is_some_and(|(uid, _)| *uid == short_uid) {
+            return;
+        }
+        let mut needles = vec![short_uid.clone()];
+        needles.extend(task.issue.clone());
+        self.ui.details_commits = Some((short_uid, git::commits_mentioning(&git::current_dir(), &needles, LINKED_COMMIT_DEPTH)));
+    }
+
+    /// Tasks of the current context that pass the active status and assignee filters.
+    async fn visible_tasks(&self) -> Result<Vec<Task>> {
+        let tasks = self.storage.get_tasks(&self.current_context.context_key()).await?;
+        Ok(self.visible(tasks))
+    }
+
+    /// The tasks of `tasks` that pass the filters, in display order.
+    fn visible(&self, tasks: Vec<Task>) -> Vec<Task> {
+        let me = self.ui.user_email.as_deref();
+        let now = chrono::Utc::now();
+        let context_key = self.current_context.context_key();
+        let env = views::Env { today: chrono::Local::now().date_naive(), now, me, dates: &self.ui.date_parser, context_key: &context_key };
+        let view = self.ui.active_filter();
+        let mut tasks: Vec<Task> = tasks
+            .into_iter()
+            .filter(|t| self.ui.status_filter.allows(t) && self.ui.assignee_filter.allows(t, me))
+            .filter(|t| self.ui.show_snoozed || !t.is_snoozed(now))
+            .filter(|t| view.is_none_or(|view| view.matches(t, &env)))
+            .collect();
+        self.ui.sort_order.sort(&mut tasks);
+        subtasks::tree_order(tasks)
+    }
+
+    fn toggle_status_filter(&mut self, status: TaskStatus) {
+        let context_key = self.current_context.context_key();
+        self.ui.status_filter.toggle(status);
+        self.ui.list_state.select(None);
+        self.view_state.set_status_filter(&context_key, self.ui.status_filter.clone());
+        if let Err(e) = self.view_state.save() {
+            self.ui.show_notification(format!("Failed to save filter: {}", e), crate::ui::NotificationLevel::Error);
+        }
+    }
+
+    /// Runs the TUI in the real terminal, reading input from `events`.
+    pub async fn run(&mut self, events: &mut dyn EventSource) -> Result<()> {
+        let guard = TerminalGuard::enter()?;
+        let backend = CrosstermBackend::new(io::stdout());
+        let mut terminal = Terminal::new(backend)
+            .map_err(|e| anyhow::anyhow!("Failed to create terminal. Error: {}", e))?;
+
+        let before = if self.config.session_summary {
+            summary::snapshot(self.storage.as_ref()).await.ok()
+        } else {
+            None
+        };
+
+        let result = self.run_app(&mut terminal, events).await;
+        drop(guard);
+
+        // Changes still waiting to be written are saved before exiting
+        let storage = match &mut self.peek {
+            Some(peek) => &mut peek.default_storage,
+            None => &mut self.storage,
+        };
+        if let Err(e) = storage.flush().await {
+            eprintln!("Could not save the latest changes: {}", e);
+        }
+
+        if let Err(err) = result {
+            println!("{err:?}");
+        } else if let Some(before) = before {
+            let after = summary::snapshot(self.storage.as_ref()).await?;
+            let session = SessionSummary::between(&before, &after);
+            if !session.is_empty() {
+                print!("{}", session.render(atty::is(atty::Stream::Stdout)));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn run_app<B: Backend>(&mut self, terminal: &mut Terminal<B>, events: &mut dyn EventSource) -> Result<()> {
+        loop {
+            // Apply context changes detected by the background git watcher
+            while let Ok(new_context) = self.context_updates.try_recv() {
+                // Switching to an ignored branch keeps the current list
+                let Some(new_context) = self.effective_config().resolve_context(new_context) else {
+                    continue;
+                };
+                if new_context != self.current_context {
+                    self.carry_tasks_into(&new_context).await;
+                    self.switch_context(new_context);
+                    self.apply_storage_rule().await;
+                }
+            }
+
+            // A lone 1/2/3 that no motion followed sets the status, and a
+            // chord's first key that nothing followed runs its own action
+            if let Some(key) = self.pending_keys.expire(Instant::now()) {
+                self.run_lone_key(key).await?;
+            }
+
+            // Seen counts belong to the default storage
+            if self.peek.is_none() && self.watch_checked.is_none_or(|checked| checked.elapsed() >= WATCH_INTERVAL) {
+                if let Err(e) = self.refresh_watchlist().await {
+                    // Wait out the interval before retrying rather than repeating the error every frame
+                    self.watch_checked = Some(Instant::now());
+                    self.ui.show_notification(format!("Couldn't check watched contexts: {}", e), crate::ui::NotificationLevel::Error);
+                }
+            }
+
+            self.finish_startup_syncs().await;
+
+            self.watch_storage_file();
+            if self.storage_changes.try_recv().is_ok() {
+                while self.storage_changes.try_recv().is_ok() {}
+                self.check_external_changes().await;
+            }
+
+            for error in self.storage.take_failed_writes() {
+                self.ui.show_notification(format!("Couldn't save a change, so it was undone: {}", error), crate::ui::NotificationLevel::Error);
+            }
+
+            self.emit_plugin_events().await;
+            let mut all_tasks = self.storage.get_tasks(&self.current_context.context_key()).await?;
+            if self.ui.peeking.is_none() {
+                self.wake_snoozed(&mut all_tasks).await;
+            }
+            self.ui.progress = (all_tasks.iter().filter(|t| t.is_completed()).count(), all_tasks.len());
+            self.refresh_rollup(TaskCounts::from_tasks(&all_tasks).open()).await;
+            let tasks = self.visible(all_tasks);
+            if self.plugins.decorates() {
+                let context_key = self.current_context.context_key();
+                self.ui.decorations = tasks.iter().filter_map(|t| Some((t.id, self.plugins.decorate(&context_key, t)?))).collect();
+            }
+            if self.ui.debug_log.is_some() {
+                self.ui.debug_log = Some(logging::recent_lines());
+            }
+            self.ui.pending_keys = self.pending_keys.display();
+            if self.ui.show_details {
+                self.find_linked_commits(&tasks);
+            }
+            self.ui.save_indicator = self.save_tracker.indicator(Instant::now());
+            
+            terminal.draw(|f| {
+                self.ui.render(f, &tasks, &self.current_context.context_key());
+            })?;
+
+            if events.is_done() {
+                break;
+            }
+            if let Some(event) = events.next(Duration::from_millis(100))? {
+                if self.handle_event(event).await? {
+                    break;
+                }
+            }
+
+            if let Some(edit) = self.pending_external_edit.take() {
+                self.run_external_edit(terminal, edit).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles one terminal event; returns whether the app should quit.
+    async fn handle_event(&mut self, event: Event) -> Result<bool> {
+        if let Event::Mouse(mouse) = event {
+            if self.handle_mouse(mouse).await? {
+                return Ok(true);
+            }
+        }
+        if let Event::Key(key) = event {
+            if key.kind == KeyEventKind::Press {
+                match self.ui.input_mode {
+                    InputMode::Normal => {
+                        if self.handle_normal_input(key.code, key.modifiers).await? {
+                            return Ok(true);
+                        }
+                    }
+                    InputMode::Adding | InputMode::Editing => {
+                        self.handle_input_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::ConfigHome => {
+                        self.handle_config_home_mode(key.code).await?;
+                    }
+                    InputMode::StorageConflict => {
+                        self.handle_storage_conflict_mode(key.code).await;
+                    }
+                    InputMode::StorageRecovery => {
+                        if self.handle_storage_recovery_mode(key.code).await? {
+                            return Ok(true);
+                        }
+                    }
+                    InputMode::ConfigStorageSelection => {
+                        self.handle_storage_selection_mode(key.code).await?;
+                    }
+                    InputMode::ConfigLocal => {
+                        self.handle_local_config_mode(key.code).await?;
+                    }
+                    InputMode::ConfigLocalField => {
+                        self.handle_local_field_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::ConfigMongoDB => {
+                        self.handle_mongodb_config_mode(key.code).await?;
+                    }
+                    InputMode::ConfigMongoDBField => {
+                        self.handle_mongodb_field_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::ConfigCredentials => {
+                        self.handle_credentials_mode(key.code);
+                    }
+                    InputMode::ConfigCredentialField => {
+                        self.handle_credential_field_mode(key.code, key.modifiers);
+                    }
+                    InputMode::Setup => {
+                        self.handle_setup_mode(key.code).await?;
+                    }
+                    InputMode::SetupField => {
+                        self.handle_setup_field_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::Inspector => {
+                        self.handle_inspector_mode(key.code);
+                    }
+                    InputMode::Stats => {
+                        self.handle_stats_mode(key.code);
+                    }
+                    InputMode::Search => {
+                        self.handle_search_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::Overview => {
+                        self.handle_overview_mode(key.code).await?;
+                    }
+                    InputMode::Cleanup => {
+                        self.handle_cleanup_mode(key.code).await?;
+                    }
+                    InputMode::Health => {
+                        self.handle_health_mode(key.code);
+                    }
+                    InputMode::BackendPicker => {
+                        self.handle_backend_picker_mode(key.code).await;
+                    }
+                    InputMode::Confirming => {
+                        self.handle_confirm_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::SettingAnchor => {
+                        self.handle_anchor_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::Notes => match key.code {
+                        KeyCode::Char(c @ '1'..='9') => {
+                            let index = c as usize - '1' as usize;
+                            if let Some(link) = self.ui.notes_links.get(index) {
+                                self.pending_external_edit = Some(ExternalEdit::OpenLink(link.clone()));
+                            }
+                        }
+                        KeyCode::Enter if self.ui.notes_links.len() == 1 => {
+                            self.pending_external_edit = Some(ExternalEdit::OpenLink(self.ui.notes_links[0].clone()));
+                        }
+                        KeyCode::Down | KeyCode::Char('j') => self.ui.notes_scroll = self.ui.notes_scroll.saturating_add(1),
+                        KeyCode::Up | KeyCode::Char('k') => self.ui.notes_scroll = self.ui.notes_scroll.saturating_sub(1),
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('U') => self.ui.cancel_input(),
+                        _ => {}
+                    },
+                    InputMode::Attachments => {
+                        self.handle_attachments_mode(key.code).await?;
+                    }
+                    InputMode::AddingAttachment => {
+                        self.handle_add_attachment_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::ShiftingDue => {
+                        self.handle_due_shift_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::Snoozing => {
+                        self.handle_snooze_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::Deferring => {
+                        self.handle_defer_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::Triaging => {
+                        self.handle_triage_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::SyncLog => {
+                        self.handle_sync_log_mode(key.code).await?;
+                    }
+                    InputMode::Trash => {
+                        self.handle_trash_mode(key.code).await?;
+                    }
+                    InputMode::Activity => match key.code {
+                        KeyCode::Down | KeyCode::Char('j') => self.ui.activity_next(),
+                        KeyCode::Up | KeyCode::Char('k') => self.ui.activity_prev(),
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('R') => self.ui.cancel_input(),
+                        _ => {}
+                    },
+                    InputMode::Diff => {
+                        self.handle_diff_mode(key.code).await?;
+                    }
+                    InputMode::Palette => {
+                        if self.handle_palette_mode(key.code, key.modifiers).await? {
+                            return Ok(true);
+                        }
+                    }
+                    InputMode::ViewPicker => {
+                        self.handle_view_picker_mode(key.code).await?;
+                    }
+                    InputMode::AddingView => {
+                        self.handle_add_view_mode(key.code, key.modifiers).await?;
+                    }
+                    InputMode::TemplatePicker => {
+                        self.handle_template_picker_mode(key.code).await?;
+                    }
+                    InputMode::ImportPicker => {
+                        self.handle_import_picker_mode(key.code).await?;
+                    }
+                    InputMode::AssigneePicker => {
+                        self.handle_assignee_picker_mode(key.code).await?;
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    async fn handle_normal_input(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<bool> {
+        let takes_count = |key, modifiers| command_for_key(key, modifiers).is_some_and(|c| c.repeats());
+        match self.pending_keys.feed(key, modifiers, Instant::now(), takes_count) {
+            Resolved::Pending | Resolved::Cancelled => Ok(false),
+            Resolved::Chord { action, count } => match CHORD_ACTIONS.iter().find(|(name, _)| *name == action) {
+                Some((_, command)) => self.run_command(command.clone(), count).await,
+                None => Ok(false),
+            },
+            Resolved::Key { count, key, modifiers } => match command_for_key(key, modifiers) {
+                Some(command) if self.needs_chord(&command) => Ok(false),
+                Some(command) => self.run_command(command, count).await,
+                None => Ok(false),
+            },
+            Resolved::StatusDigit { digit, then: (key, modifiers) } => {
+                self.run_lone_key(digit).await?;
+                Box::pin(self.handle_normal_input(key, modifiers)).await
+            }
+        }
+    }
+
+    async fn run_lone_key(&mut self, key: char) -> Result<bool> {
+        match command_for_key(KeyCode::Char(key), KeyModifiers::NONE) {
+            Some(command) if self.needs_chord(&command) => Ok(false),
+            Some(command) => self.execute_command(command).await,
+            None => Ok(false),
+        }
+    }
+
+    /// Whether `command` is behind a chord, saying which when it is.
+    fn needs_chord(&mut self, command: &Command) -> bool {
+        let Some((_, keys)) = self.chords.iter().find(|(c, _)| c == command) else {
+            return false;
+        };
+        let label = COMMANDS.iter().find(|(c, _, _)| c == command).map_or("", |(_, label, _)| *label);
+        self.ui.show_notification(format!("Press {} to {}", keys, label.to_lowercase()), crate::ui::NotificationLevel::Error);
+        true
+    }
+
+    /// Sets up the configured chords; a chord's first key keeps its own
+    /// action (run when nothing follows) unless that action needs a chord too.
+    fn apply_chords(&mut self, config: &AppConfig) {
+        let mut chords = Vec::new();
+        for (action, keys) in config.chord_bindings() {
+            match CHORD_ACTIONS.iter().find(|(name, _)| *name == action) {
+                Some((_, command)) if keys.chars().count() >= 2 => chords.push((command.clone(), keys)),
+                Some(_) => self.ui.show_notification(
+                    format!("Ignoring chord for {}: it needs at least two keys", action),
+                    crate::ui::NotificationLevel::Error,
+                ),
+                None => {
+                    let known: Vec<&str> = CHORD_ACTIONS.iter().map(|(name, _)| *name).collect();
+                    self.ui.show_notification(
+                        format!("Ignoring chord for unknown action {} (known: {})", action, known.join(", ")),
+                        crate::ui::NotificationLevel::Error,
+                    );
+                }
+            }
+        }
+
+        let standalone = chords
+            .iter()
+            .filter_map(|(_, keys)| keys.chars().next())
+            .filter(|c| {
+                command_for_key(KeyCode::Char(*c), KeyModifiers::NONE).is_some_and(|own| !chords.iter().any(|(bound, _)| *bound == own))
+            })
+            .collect();
+        let sequences = chords
+            .iter()
+            .filter_map(|(command, keys)| {
+                CHORD_ACTIONS.iter().find(|(_, c)| c == command).map(|(name, _)| (keys.clone(), name.to_string()))
+            })
+            .collect();
+        self.pending_keys.set_chords(sequences, standalone);
+        self.chords = chords;
+    }
+
+    /// Runs a command once, or `count` times for commands that repeat.
+    async fn run_command(&mut self, command: Command, count: usize) -> Result<bool> {
+        if !command.repeats() || count <= 1 {
+            return self.execute_command(command).await;
+        }
+        match command {
+            Command::DeleteTask if self.ui.marked.is_empty() => {
+                let tasks = self.visible_tasks().await?;
+                if let Some(selected) = self.ui.list_state.selected() {
+                    let ids = tasks.iter().skip(selected).take(count).map(|t| t.id).collect();
+                    self.request_bulk(BulkAction::Delete(ids)).await?;
+                }
+            }
+            // Counted motions stop at the ends of the list instead of wrapping
+            Command::SelectNext | Command::SelectPrevious => {
+                let tasks = self.visible_tasks().await?;
+                let delta = if command == Command::SelectNext { count as i32 } else { -(count as i32) };
+                self.ui.scroll_selection(&tasks, delta);
+            }
+            command => {
+                for _ in 0..count {
+                    if self.execute_command(command.clone()).await? {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Runs an action from the keymap or the command palette; returns true to quit.
+    async fn execute_command(&mut self, command: Command) -> Result<bool> {
+        if let Some(peek) = self.peek.as_ref().filter(|_| command.writes()) {
+            let message = format!("{} is open read-only; press B to return to your storage", peek.backend.label());
+            self.ui.show_notification(message, crate::ui::NotificationLevel::Error);
+            return Ok(false);
+        }
+        let tasks = self.visible_tasks().await?;
+        let selected = self.ui.list_state.selected();
+        let selected_task = selected.and_then(|i| tasks.get(i)).cloned();
+
+        match command {
+            Command::Quit => return Ok(true),
+            Command::AddTask => {
+                self.ui.start_adding();
+                self.ui.suggestion_pool = self.suggestion_pool().await?;
+            }
+            Command::AddSubtask => match &selected_task {
+                Some(task) => {
+                    self.ui.start_adding_subtask(task);
+                    self.ui.suggestion_pool = self.suggestion_pool().await?;
+                }
+                None => self.ui.show_notification("Select a task to add a subtask to".to_string(), crate::ui::NotificationLevel::Error),
+            },
+            Command::CyclePriority => {
+                let targets = self.target_tasks(&tasks, selected_task.as_ref());
+                for task in &targets {
+                    self.set_priority(task.id, task.priority.next()).await?;
+                }
+            }
+            Command::TogglePropagation => {
+                self.view_state.propagate_to_subtasks = !self.view_state.propagate_to_subtasks;
+                self.ui.propagate_to_subtasks = self.view_state.propagate_to_subtasks;
+                self.view_state.save()?;
+                let message = if self.ui.propagate_to_subtasks {
+                    "Priority and due date changes now apply to subtasks"
+                } else {
+                    "Priority and due date changes no longer apply to subtasks"
+                };
+                self.ui.show_notification(message.to_string(), crate::ui::NotificationLevel::Success);
+            }
+            Command::OpenConfig => self.ui.start_storage_config(&self.config),
+            Command::ToggleDebugLog => {
+                self.ui.debug_log = match self.ui.debug_log {
+                    Some(_) => None,
+                    None => Some(logging::recent_lines()),
+                };
+            }
+            Command::OpenPalette => self.open_palette().await?,
+            Command::SelectNext => self.ui.select_next(&tasks),
+            Command::SelectPrevious => self.ui.select_previous(&tasks),
+            Command::PageDown => {
+                let page = self.ui.page_size() as i32;
+                self.ui.scroll_selection(&tasks, page);
+            }
+            Command::PageUp => {
+                let page = self.ui.page_size() as i32;
+                self.ui.scroll_selection(&tasks, -page);
+            }
+            Command::SelectFirst => self.ui.select_first(&tasks),
+            Command::SelectLast => self.ui.select_last(&tasks),
+            Command::MoveDown | Command::MoveUp if self.ui.sort_order != SortOrder::Manual => {
+                self.ui.show_notification("Switch back to manual order to move tasks".to_string(), crate::ui::NotificationLevel::Error);
+            }
+            Command::MoveDown | Command::MoveUp => {
+                let up = command == Command::MoveUp;
+                let target = selected.and_then(|selected| if up { selected.checked_sub(1) } else { Some(selected + 1) });
+                if let (Some(task), Some(target)) = (&selected_task, target) {
+                    if let Some(neighbour) = tasks.get(target) {
+                        if neighbour.pinned != task.pinned {
+                            self.ui.show_notification(
+                                "Pinned tasks stay above the rest; unpin to move past them".to_string(),
+                                crate::ui::NotificationLevel::Error,
+                            );
+                        } else if self.move_past(task.id, neighbour.id, up).await? {
+                            // Adjust selection to follow the moved task
+                            self.ui.list_state.select(Some(target));
+                        }
+                    }
+                }
+            }
+            Command::CycleStatus => {
+                if let Some(task) = &selected_task {
+                    self.storage.toggle_task(&self.current_context.context_key(), task.id).await?;
+                }
+            }
+            Command::SetStatus(TaskStatus::Completed) if !self.ui.marked.is_empty() => {
+                let ids = self.marked_ids(&tasks);
+                self.request_bulk(BulkAction::Complete(ids)).await?;
+            }
+            Command::CompleteAll => {
+                let ids: Vec<usize> = tasks.iter().filter(|t| !t.is_completed()).map(|t| t.id).collect();
+                if ids.is_empty() {
+                    self.ui.show_notification("No open tasks to complete".to_string(), crate::ui::NotificationLevel::Success);
+                } else {
+                    self.request_bulk(BulkAction::Complete(ids)).await?;
+                }
+            }
+            Command::SetStatus(status) if !self.ui.marked.is_empty() => {
+                for id in self.marked_ids(&tasks) {
+                    self.storage.set_task_status(&self.current_context.context_key(), id, status.clone()).await?;
+                }
+                self.ui.marked.clear();
+            }
+            Command::SetStatus(status) => {
+                if let Some(task) = &selected_task {
+                    self.storage.set_task_status(&self.current_context.context_key(), task.id, status).await?;
+                }
+            }
+            Command::DeleteTask if !self.ui.marked.is_empty() => {
+                let ids = self.marked_ids(&tasks);
+                self.request_bulk(BulkAction::Delete(ids)).await?;
+            }
+            Command::DeleteTask => {
+                if let (Some(selected), Some(task)) = (selected, &selected_task) {
+                    self.storage.remove_task(&self.current_context.context_key(), task.id).await?;
+                    if selected > 0 && selected >= tasks.len() - 1 {
+                        self.ui.list_state.select(Some(selected - 1));
+                    }
+                }
+            }
+            Command::ToggleMustFinish => {
+                if let Some(mut task) = selected_task {
+                    task.must_finish = !task.must_finish;
+                    let message = if task.must_finish {
+                        "Task must be finished before pushing"
+                    } else {
+                        "Task no longer blocks pushing"
+                    };
+                    self.storage.update_task(&self.current_context.context_key(), task).await?;
+                    self.ui.show_notification(message.to_string(), crate::ui::NotificationLevel::Success);
+                }
+            }
+            Command::TogglePin => {
+                if let Some(mut task) = selected_task {
+                    task.pinned = !task.pinned;
+                    let message = if task.pinned { "Pinned to the top" } else { "Unpinned" };
+                    self.storage.update_task(&self.current_context.context_key(), task).await?;
+                    self.ui.show_notification(message.to_string(), crate::ui::NotificationLevel::Success);
+                }
+            }
+            Command::ToggleFilter(status) => self.toggle_status_filter(status),
+            Command::CycleAssigneeFilter => {
+                self.ui.assignee_filter = self.ui.assignee_filter.next();
+                self.ui.list_state.select(None);
+                self.view_state.assignee_filter = self.ui.assignee_filter;
+                self.view_state.save()?;
+                if self.ui.assignee_filter == crate::state::AssigneeFilter::Mine && self.ui.user_email.is_none() {
+                    self.ui.show_notification(
+                        "Set git user.email to tell your tasks apart".to_string(),
+                        crate::ui::NotificationLevel::Error,
+                    );
+                }
+            }
+            Command::AssignTask => {
+                if let Some(task) = &selected_task {
+                    let people = self.assignee_choices().await?;
+                    self.ui.open_assignee_picker(people, task.assignee.as_deref());
+                } else if !self.ui.marked.is_empty() {
+                    let people = self.assignee_choices().await?;
+                    self.ui.open_assignee_picker(people, None);
+                }
+            }
+            Command::CycleSort => {
+                self.ui.sort_order = self.ui.sort_order.next();
+                self.ui.list_state.select(None);
+                self.ui.show_notification(format!("Sorted by {}", self.ui.sort_order.label()), crate::ui::NotificationLevel::Success);
+            }
+            Command::ShowStats => {
+                let stats = self
+                    .storage
+                    .all_task_counts()
+                    .await?
+                    .into_iter()
+                    .map(|(context_key, counts)| ContextStats { context_key, open: counts.open(), total: counts.total() })
+                    .collect();
+                self.ui.open_stats(stats);
+            }
+            Command::ShowOverview => {
+                let all = self.storage.get_all_tasks().await?;
+                self.ui.open_overview(all);
+            }
+            Command::Search => self.ui.start_search(),
+            Command::PickView => self.ui.open_view_picker(),
+            Command::CleanUpBranches => {
+                match cleanup::find_candidates(self.storage.as_ref(), &self.current_context, &git::current_dir()).await {
+                    Ok(candidates) => self.ui.open_cleanup(candidates),
+                    Err(e) => self.ui.show_notification(format!("Can't look for stale branches: {}", e), crate::ui::NotificationLevel::Error),
+                }
+            }
+            Command::OpenTrash => {
+                let trash = self.storage.list_deleted(&self.current_context.context_key()).await?;
+                self.ui.trash_state.select(None);
+                self.ui.open_trash(trash);
+            }
+            Command::ShowActivity => {
+                let activity = self.storage.list_activity(&self.current_context.context_key()).await?;
+                self.ui.open_activity(activity);
+            }
+            Command::ApplyTemplate => {
+                let templates = templates::load_templates(&self.effective_config().templates, git::repo_root().as_deref());
+                self.ui.open_template_picker(templates);
+            }
+            Command::ImportTodos => self.offer_import(),
+            Command::Plugin(index) => self.run_plugin_command(index, selected_task.as_ref()).await?,
+            Command::ToggleMark => {
+                if let Some(task) = &selected_task {
+                    self.ui.toggle_mark(task.id);
+                    self.ui.select_next(&tasks);
+                }
+            }
+            Command::ClearMarks => self.ui.marked.clear(),
+            Command::ShiftDue => {
+                if !self.ui.marked.is_empty() || selected.is_some() {
+                    self.ui.start_due_shift();
+                }
+            }
+            Command::Snooze => {
+                if !self.ui.marked.is_empty() || selected.is_some() {
+                    self.ui.start_snooze();
+                }
+            }
+            Command::ToggleSnoozed => self.ui.show_snoozed = !self.ui.show_snoozed,
+            Command::DeferToBranch => {
+                if !self.ui.marked.is_empty() || selected.is_some() {
+                    self.ui.start_defer();
+                }
+            }
+            Command::MoveToContext => {
+                if !self.ui.marked.is_empty() || selected.is_some() {
+                    // From the inbox, suggest the list it was opened from
+                    let target = match &self.inbox_return {
+                        Some(context) if self.current_context.is_inbox() => context.context_key(),
+                        _ if self.current_context.is_inbox() => String::new(),
+                        _ => GitContext::inbox().context_key(),
+                    };
+                    self.ui.start_triage(target);
+                }
+            }
+            Command::EditNotes => {
+                if let Some(task) = &selected_task {
+                    self.pending_external_edit = Some(ExternalEdit::Notes(task.id));
+                }
+            }
+            Command::SetAnchor => {
+                if let Some(task) = &selected_task {
+                    self.ui.start_anchor(task);
+                }
+            }
+            Command::OpenAnchor => match &selected_task {
+                Some(task) if task.linked_file().is_some() => self.pending_external_edit = Some(ExternalEdit::OpenAnchor(task.id)),
+                Some(_) => self.ui.show_notification("This task has no linked file (press f to anchor one, F to attach one)".to_string(), crate::ui::NotificationLevel::Error),
+                None => {}
+            },
+            Command::ShowAttachments => {
+                if let Some(task) = &selected_task {
+                    self.ui.open_attachments(task);
+                }
+            }
+            Command::ToggleDetails => self.ui.show_details = !self.ui.show_details,
+            Command::ShowNotes => {
+                if let Some(task) = &selected_task {
+                    self.ui.open_notes(task);
+                }
+            }
+            Command::OpenSyncLog => self.open_sync_log(),
+            Command::ExportIssue => {
+                if let Some(link) = &self.ui.issue_link {
+                    self.ui.show_notification(format!("Already exported to {}", link.url), crate::ui::NotificationLevel::Success);
+                } else {
+                    match self.export_issue().await {
+                        Ok(message) => self.ui.show_notification(message, crate::ui::NotificationLevel::Success),
+                        Err(e) => self.ui.show_notification(format!("Issue export failed: {}", e), crate::ui::NotificationLevel::Error),
+                    }
+                }
+            }
+            Command::SyncJira => match self.sync_jira().await {
+                // A sync that changed tasks opens its diff view instead
+                Ok(0) => self.ui.show_notification("Jira is up to date".to_string(), crate::ui::NotificationLevel::Success),
+                Ok(changed) if self.ui.input_mode != InputMode::Diff => {
+                    self.ui.show_notification(format!("Synced Jira: {} change(s)", changed), crate::ui::NotificationLevel::Success)
+                }
+                Ok(_) => {}
+                Err(e) => self.ui.show_notification(format!("Jira sync failed: {}", e), crate::ui::NotificationLevel::Error),
+            },
+            Command::BulkEdit => {
+                self.pending_external_edit = Some(ExternalEdit::BulkEdit);
+            }
+            Command::PeekBackend => {
+                let default = self.effective_config().for_context(&self.current_context).storage_type;
+                self.ui.open_backend_picker(default);
+            }
+            Command::ShowHealth => match self.storage.health().await {
+                Ok(health) => {
+                    self.ui.inspector_scroll = 0;
+                    self.ui.open_health(health);
+                }
+                Err(e) => self.ui.show_notification(format!("Storage health check failed: {}", e), crate::ui::NotificationLevel::Error),
+            },
+            Command::InspectTask => {
+                // Hidden debugging aid: show the raw stored form of the selected task
+                if let Some(task) = &selected_task {
+                    let dump = self.storage.inspect_task(&self.current_context.context_key(), task.id).await?;
+                    self.ui.open_inspector(dump);
+                }
+            }
+            Command::EditTask => {
+                if let Some(task) = &selected_task {
+                    // Don't allow editing completed tasks
+                    if !matches!(task.status, TaskStatus::Completed) {
+                        self.ui.start_editing(task);
+                    }
+                }
+            }
+            Command::UndoDelete => {
+                let context_key = self.current_context.context_key();
+                match self.storage.undo_delete(&context_key).await? {
+                    Some(restored_task) => {
+                        let remaining = self.storage.list_deleted(&context_key).await?.len();
+                        self.ui.show_notification(
+                            format!("Restored task: {} ({} more undo{} available)", restored_task.text, remaining, if remaining == 1 { "" } else { "s" }),
+                            crate::ui::NotificationLevel::Success
+                        );
+                    }
+                    None => {
+                        self.ui.show_notification(
+                            format!("No deleted tasks to undo (keeps the last {})", self.effective_config().trash_retention),
+                            crate::ui::NotificationLevel::Error
+                        );
+                    }
+                }
+            }
+            Command::SwitchContext(context_key) => self.switch_to_key(&context_key),
+            Command::ToggleWatch => {
+                let context_key = self.current_context.context_key();
+                let tasks = self.storage.get_tasks(&context_key).await?;
+                let message = if self.view_state.toggle_watch(&context_key, &tasks) {
+                    "Watching this context for new tasks"
+                } else {
+                    "No longer watching this context"
+                };
+                self.view_state.save()?;
+                self.watch_checked = None;
+                self.ui.show_notification(message.to_string(), crate::ui::NotificationLevel::Success);
+            }
+            Command::ToggleInbox => {
+                if self.current_context.is_inbox() {
+                    let config = self.effective_config();
+                    let back = self.inbox_return.take().or_else(|| {
+                        GitContext::from_current_dir_with(&config.forges).ok().and_then(|context| config.resolve_context(context))
+                    });
+                    if let Some(context) = back.filter(|context| !context.is_inbox()) {
+                        self.switch_context(context);
+                    }
+                } else {
+                    self.inbox_return = Some(self.current_context.clone());
+                    self.switch_context(GitContext::inbox());
+                }
+                self.apply_storage_rule().await;
+            }
+            Command::JumpToWatched => match self.next_watched() {
+                Some(context_key) => self.switch_to_key(&context_key),
+                None => self.ui.show_notification(
+                    "No other watched contexts; press 'w' to watch one".to_string(),
+                    crate::ui::NotificationLevel::Error,
+                ),
+            },
+        }
+        Ok(false)
+    }
+
+    /// People offered by the assign picker: nobody, the user, then everyone
+    /// tasks in the storage are assigned to.
+    async fn assignee_choices(&self) -> Result<Vec<Option<String>>> {
+        let mut people: Vec<String> = self
+            .storage
+            .get_all_tasks()
+            .await?
+            .into_values()
+            .flatten()
+            .filter_map(|t| t.assignee)
+            .filter(|a| self.ui.user_email.as_ref() != Some(a))
+            .collect();
+        people.sort();
+        people.dedup();
+        let mut choices = vec![None];
+        choices.extend(self.ui.user_email.clone().map(Some));
+        choices.extend(people.into_iter().map(Some));
+        Ok(choices)
+    }
+
+    /// The marked tasks, or the selected one when nothing is marked.
+    fn target_tasks(&self, tasks: &[Task], selected: Option<&Task>) -> Vec<Task> {
+        if self.ui.marked.is_empty() {
+            selected.cloned().into_iter().collect()
+        } else {
+            tasks.iter().filter(|t| self.ui.marked.contains(&t.id)).cloned().collect()
+        }
+    }
+
+    /// Ids whose priority or due date change with `id`: the task itself, plus
+    /// its subtasks while propagation is on.
+    fn propagation_targets(&self, id: usize, tasks: &[Task]) -> Vec<usize> {
+        let mut ids = vec![id];
+        if self.ui.propagate_to_subtasks {
+            ids.extend(subtasks::descendants(id, tasks));
+        }
+        ids
+    }
+
+    async fn set_priority(&mut self, id: usize, priority: Priority) -> Result<()> {
+        let context_key = self.current_context.context_key();
+        let tasks = self.storage.get_tasks(&context_key).await?;
+        for target in self.propagation_targets(id, &tasks) {
+            if let Some(mut task) = tasks.iter().find(|t| t.id == target).cloned() {
+                task.priority = priority;
+                self.storage.update_task(&context_key, task).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ids of the marked tasks in list order.
+    fn marked_ids(&self, tasks: &[Task]) -> Vec<usize> {
+        tasks.iter().filter(|t| self.ui.marked.contains(&t.id)).map(|t| t.id).collect()
+    }
+
+    /// Returns the action if it may run right away, or holds it and asks for
+    /// its confirmation phrase when it exceeds `confirm_threshold`.
+    fn confirm_first(&mut self, action: BulkAction) -> Option<BulkAction> {
+        let threshold = self.effective_config().confirm_threshold;
+        if threshold > 0 && action.size() > threshold {
+            self.ui.start_confirm(action.phrase());
+            self.pending_bulk = Some(action);
+            None
+        } else {
+            Some(action)
+        }
+    }
+
+    async fn request_bulk(&mut self, action: BulkAction) -> Result<()> {
+        if let Some(action) = self.confirm_first(action) {
+            let message = self.apply_bulk(action).await?;
+            self.ui.show_notification(message, crate::ui::NotificationLevel::Success);
+        }
+        Ok(())
+    }
+
+    async fn apply_bulk(&mut self, action: BulkAction) -> Result<String> {
+        let context_key = self.current_context.context_key();
+        let message = match action {
+            BulkAction::Delete(ids) => {
+                for id in &ids {
+                    self.storage.remove_task(&context_key, *id).await?;
+                }
+                let remaining = self.visible_tasks().await?.len();
+                if let Some(selected) = self.ui.list_state.selected() {
+                    self.ui.list_state.select(if remaining == 0 { None } else { Some(selected.min(remaining - 1)) });
+                }
+                format!("Deleted {} task(s)", ids.len())
+            }
+            BulkAction::Complete(ids) => {
+                for id in &ids {
+                    self.storage.set_task_status(&context_key, *id, TaskStatus::Completed).await?;
+                }
+                format!("Completed {} task(s)", ids.len())
+            }
+            BulkAction::BulkEdit { original, edit } => {
+                let message = self.apply_bulk_edit(&original, edit).await?;
+                self.show_diff("Bulk edit", original).await?;
+                message
+            }
+        };
+        self.ui.marked.clear();
+        Ok(message)
+    }
+
+    async fn handle_confirm_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                let typed = self.ui.finish_input();
+                let Some(action) = self.pending_bulk.take() else {
+                    return Ok(());
+                };
+                if typed.trim() == action.phrase() {
+                    let message = self.apply_bulk(action).await?;
+                    self.ui.show_notification(message, crate::ui::NotificationLevel::Success);
+                } else {
+                    self.ui.show_notification("Confirmation didn't match; nothing was changed".to_string(), crate::ui::NotificationLevel::Error);
+                }
+            }
+            KeyCode::Esc => {
+                self.pending_bulk = None;
+                self.ui.cancel_input();
+                self.ui.show_notification("Cancelled; nothing was changed".to_string(), crate::ui::NotificationLevel::Success);
+            }
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    /// Shows another context's list and restores its per-context view state.
+    fn switch_context(&mut self, context: GitContext) {
+        tracing::info!(context = %context.context_key(), "switched context");
+        self.current_context = context;
+        self.ui.status_filter = self.view_state.status_filter(&self.current_context.context_key());
+        self.ui.issue_link = self.view_state.issue_links.get(&self.current_context.context_key()).cloned();
+        self.ui.jira_issue = self.branch_jira_issue();
+        self.ui.list_state.select(None);
+        self.ui.marked.clear();
+        self.ui.rollup = None;
+        self.watch_checked = None;
+        self.rollup_checked = None;
+    }
+
+    /// Opens the backend the storage rules pick for the current context when
+    /// it differs from the open one.
+    /// Carries unfinished tasks into the list of a branch just created from
+    /// the current one, as `carry_tasks` says.
+    async fn carry_tasks_into(&mut self, new_context: &GitContext) {
+        let policy = self.effective_config().carry_tasks;
+        if policy == CarryPolicy::Off || !git::branched_from(&git::current_dir(), &new_context.branch, &self.current_context.branch) {
+            return;
+        }
+        let from = self.current_context.clone();
+        let carried = match carry::is_fresh_branch(self.storage.as_ref(), &from, new_context).await {
+            Ok(true) => carry::carry(self.storage.as_mut(), policy, &from, new_context).await,
+            Ok(false) => return,
+            Err(e) => Err(e),
+        };
+        match carried {
+            Ok(0) => {}
+            Ok(n) => self.ui.show_notification(
+                format!("Carried {} unfinished task(s) over from {}", n, from.branch),
+                crate::ui::NotificationLevel::Success,
+            ),
+            Err(e) => self.ui.show_notification(format!("Couldn't carry tasks over: {}", e), crate::ui::NotificationLevel::Error),
+        }
+    }
+
+    async fn apply_storage_rule(&mut self) {
+        // The default storage is swapped back in when the peek ends
+        if self.peek.is_some() {
+            return;
+        }
+        let config = self.effective_config();
+        let rule = config.storage_rule(&self.current_context);
+        if rule == self.storage_rule {
+            return;
+        }
+        let config = config.for_context(&self.current_context);
+        let repo = format!("{}/{}", self.current_context.org, self.current_context.repo);
+        // Dropping a storage with unsaved writes would lose them
+        if let Err(e) = self.storage.flush().await {
+            let message = format!("Could not save changes before switching storage for {}: {}. Keeping the current storage.", repo, e);
+            self.ui.show_notification(message, crate::ui::NotificationLevel::Error);
+            return;
+        }
+        match storage::open_interactive(&config).await {
+            Ok(opened) => {
+                self.storage = Box::new(TrackedStorage::new(opened, self.save_tracker.clone()));
+                self.storage_rule = rule;
+                self.watch_checked = None;
+                let message = format!("Using {} storage for {}", config.storage_type.label(), repo);
+                self.ui.show_notification(message, crate::ui::NotificationLevel::Success);
+            }
+            Err(e) => {
+                let message = format!("Could not open {} storage for {}: {}. Keeping the current storage.", config.storage_type.label(), repo, e);
+                self.ui.show_notification(message, crate::ui::NotificationLevel::Error);
+            }
+        }
+    }
+
+    /// Switches to a context given as `org:repo:branch`.
+    fn switch_to_key(&mut self, context_key: &str) {
+        let mut parts = context_key.splitn(3, ':');
+        if let (Some(org), Some(repo), Some(branch)) = (parts.next(), parts.next(), parts.next()) {
+            self.switch_context(GitContext { org: org.to_string(), repo: repo.to_string(), branch: branch.to_string() });
+        }
+    }
+
+    /// Marks the current context as seen if watched and recounts new tasks in the others.
+    async fn refresh_watchlist(&mut self) -> Result<()> {
+        let current = self.current_context.context_key();
+        let mut watch_unseen = Vec::new();
+        let mut changed = false;
+        let mut watched: Vec<String> = self.view_state.watched.keys().cloned().collect();
+        watched.sort();
+        for context_key in watched {
+            let tasks = self.storage.get_tasks(&context_key).await?;
+            if context_key == current {
+                changed |= self.view_state.mark_seen(&context_key, &tasks);
+            } else {
+                let unseen = self.view_state.unseen(&context_key, &tasks);
+                if unseen > 0 {
+                    watch_unseen.push((context_key, unseen));
+                }
+            }
+        }
+        if changed {
+            self.view_state.save()?;
+        }
+
+        self.ui.watched = self.view_state.is_watched(&current);
+        self.ui.watch_badge = watch_unseen.iter().map(|(_, unseen)| unseen).sum();
+        self.watch_unseen = watch_unseen;
+        self.watch_checked = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Recounts open tasks across the repository and organization for the
+    /// header, without loading every task where the backend can count them.
+    /// `here` is the current context's open count from this frame's tasks,
+    /// so frames between recounts don't touch storage.
+    async fn refresh_rollup(&mut self, here: usize) {
+        let wait = if self.ui.rollup.is_some_and(|rollup| rollup.here != here) { ROLLUP_CHANGED_INTERVAL } else { ROLLUP_INTERVAL };
+        if self.rollup_checked.is_some_and(|checked| checked.elapsed() < wait) {
+            return;
+        }
+        self.rollup_checked = Some(Instant::now());
+        match self.storage.open_task_counts().await {
+            Ok(counts) => self.ui.rollup = Some(Rollup::from_open_counts(&counts, &self.current_context)),
+            Err(e) => tracing::warn!(error = %e, "couldn't count open tasks across contexts"),
+        }
+    }
+
+    /// The first watched context with new tasks, or else the watched context after the current one.
+    fn next_watched(&self) -> Option<String> {
+        if let Some((context_key, _)) = self.watch_unseen.first() {
+            return Some(context_key.clone());
+        }
+        let current = self.current_context.context_key();
+        let mut watched: Vec<&String> = self.view_state.watched.keys().filter(|k| **k != current).collect();
+        watched.sort();
+        watched.iter().find(|k| ***k > current).or(watched.first()).map(|k| k.to_string())
+    }
+
+    /// Opens the palette with every registered command plus one entry per other context.
+    async fn open_palette(&mut self) -> Result<()> {
+        let mut commands: Vec<(Command, String, String)> = COMMANDS
+            .iter()
+            .map(|(command, label, key)| {
+                let key = self.chords.iter().find(|(c, _)| c == command).map_or(key.to_string(), |(_, keys)| keys.clone());
+                (command.clone(), label.to_string(), key)
+            })
+            .collect();
+        let current = self.current_context.context_key();
+        for context_key in self.storage.list_contexts().await? {
+            if context_key != current {
+                commands.push((Command::SwitchContext(context_key.clone()), format!("Switch context: {}", context_key), String::new()));
+            }
+        }
+        for (index, label) in self.plugins.commands().into_iter().enumerate() {
+            commands.push((Command::Plugin(index), format!("{} (plugin)", label), String::new()));
+        }
+
+        let items = commands
+            .iter()
+            .map(|(_, label, key)| PaletteItem { label: label.clone(), key: key.clone() })
+            .collect();
+        self.palette_commands = commands.into_iter().map(|(command, _, _)| command).collect();
+        self.ui.open_palette(items);
+        Ok(())
+    }
+
+    /// Runs a plugin's palette command and adds the tasks it hands back.
+    async fn run_plugin_command(&mut self, index: usize, selected: Option<&Task>) -> Result<()> {
+        let context_key = self.current_context.context_key();
+        match self.plugins.run_command(index, &context_key, selected) {
+            Ok(outcome) => {
+                for text in &outcome.add {
+                    self.add_task(text.clone()).await?;
+                }
+                let added = (!outcome.add.is_empty()).then(|| format!("Added {} task(s)", outcome.add.len()));
+                if let Some(message) = outcome.message.or(added) {
+                    self.ui.show_notification(message, crate::ui::NotificationLevel::Success);
+                }
+            }
+            Err(e) => self.ui.show_notification(format!("Plugin command failed: {}", e), crate::ui::NotificationLevel::Error),
+        }
+        Ok(())
+    }
+
+    /// Calls plugin hooks for whatever changed in the current context since
+    /// the last frame, whether from a key, a sync or another process.
+    /// Runs on every frame, so a failed read is logged and retried on the
+    /// next one rather than ending the app.
+    async fn emit_plugin_events(&mut self) {
+        if !self.plugins.has_hooks() || self.peek.is_some() {
+            return;
+        }
+        let context_key = self.current_context.context_key();
+        let tasks = match self.storage.get_tasks(&context_key).await {
+            Ok(tasks) => tasks,
+            Err(error) => {
+                tracing::warn!(%error, "couldn't read tasks for plugin hooks");
+                return;
+            }
+        };
+        let before = match self.plugin_baseline.take() {
+            Some((key, before)) if key == context_key => before,
+            // Opening a context isn't a change to it
+            _ => tasks.clone(),
+        };
+        let mut errors = Vec::new();
+        for (event, task) in plugins::events(&before, &tasks) {
+            errors.extend(self.plugins.emit(event, &context_key, &task));
+        }
+        self.plugin_baseline = Some((context_key, tasks));
+        for error in &errors {
+            tracing::warn!(%error, "plugin hook failed");
+        }
+        if let Some(error) = errors.into_iter().next() {
+            self.ui.show_notification(error, crate::ui::NotificationLevel::Error);
+        }
+    }
+
+    async fn handle_palette_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<bool> {
+        match key {
+            KeyCode::Esc => self.ui.cancel_input(),
+            KeyCode::Enter => {
+                let command = self.ui.selected_palette_item().and_then(|i| self.palette_commands.get(i).cloned());
+                self.ui.cancel_input();
+                if let Some(command) = command {
+                    return self.execute_command(command).await;
+                }
+            }
+            KeyCode::Down => self.ui.palette_next(),
+            KeyCode::Up => self.ui.palette_prev(),
+            KeyCode::Char('n') if modifiers.contains(KeyModifiers::CONTROL) => self.ui.palette_next(),
+            KeyCode::Char('p') if modifiers.contains(KeyModifiers::CONTROL) => self.ui.palette_prev(),
+            _ => {
+                self.edit_input(key, modifiers);
+                self.ui.refresh_palette();
+            }
+        }
+        Ok(false)
+    }
+
+    /// Creates an issue from the current context's open tasks on the forge
+    /// the repository is hosted on and links it to the context.
+    async fn export_issue(&mut self) -> Result<String> {
+        let config = self.effective_config();
+        // Repositories without a recognised remote keep exporting to GitHub
+        let remote = git::origin_remote(&config.forges);
+        let forge = remote.as_ref().and_then(|r| r.forge).unwrap_or_default();
+        let integration = issue_integration(forge);
+        if !matches!(forge, Forge::GitHub | Forge::GitLab) {
+            return Err(anyhow::anyhow!("issues can be exported to GitHub or GitLab, not {}", forge.label()));
+        }
+        if self.sync_log.is_paused(integration) {
+            return Err(anyhow::anyhow!("the {} integration is paused", integration));
+        }
+        let context_key = self.current_context.context_key();
+        let tasks = self.storage.get_tasks(&context_key).await?;
+        if tasks.iter().all(|t| t.is_completed()) {
+            return Err(anyhow::anyhow!("no open tasks to export"));
+        }
+
+        let result = match (forge, remote) {
+            (Forge::GitLab, Some(remote)) => match GitLabClient::new(&config.gitlab) {
+                Ok(client) => client.export_context(&remote.path(), &self.current_context, &tasks).await,
+                Err(e) => Err(e),
+            },
+            _ => match GitHubClient::new(&config.github) {
+                Ok(client) => client.export_context(&self.current_context, &tasks).await,
+                Err(e) => Err(e),
+            },
+        };
+        let exported = tasks.iter().filter(|t| !t.is_completed()).count();
+        let logged = result.as_ref().map(|_| exported).map_err(|e| anyhow::anyhow!("{}", e));
+        self.record_sync(SyncLogEntry::new(integration, SyncDirection::Push, &context_key, &logged));
+        let link = result?;
+
+        let message = format!("Created issue #{}: {}", link.number, link.url);
+        self.view_state.issue_links.insert(context_key, link.clone());
+        self.ui.issue_link = Some(link);
+        self.view_state.save()?;
+        Ok(message)
+    }
+
+    /// Completes open tasks whose checklist items were ticked in the linked issue.
+    async fn sync_issue(&mut self) -> Result<usize> {
+        let Some(link) = self.ui.issue_link.clone() else {
+            return Err(anyhow::anyhow!("this context has no linked issue (press G to create one)"));
+        };
+        let checked = fetch_checked_items(self.effective_config(), self.current_context.clone(), link.clone()).await;
+        self.finish_issue_sync(&self.current_context.context_key(), &link, checked).await
+    }
+
+    /// Applies the syncs started at launch once their fetches are done,
+    /// reporting failures instead of stopping the app.
+    async fn finish_startup_syncs(&mut self) {
+        if let Some((context_key, checked)) = StartupFetch::take_finished(&mut self.startup_issue_sync).await {
+            if let Some(link) = self.view_state.issue_links.get(&context_key).cloned() {
+                if let Err(e) = self.finish_issue_sync(&context_key, &link, checked).await {
+                    let integration = issue_integration(link.forge);
+                    self.ui.show_notification(format!("{} sync failed: {}", integration, e), crate::ui::NotificationLevel::Error);
+                }
+            }
+        }
+        if let Some((context_key, issues)) = StartupFetch::take_finished(&mut self.startup_jira_sync).await {
+            // New issues become tasks of the current context, so a sync the
+            // user switched away from waits for the next one
+            if context_key != self.current_context.context_key() {
+                tracing::info!(context = %context_key, "context changed during the Jira sync; skipped applying it");
+            } else if let Err(e) = self.finish_jira_sync(&context_key, issues).await {
+                self.ui.show_notification(format!("Jira sync failed: {}", e), crate::ui::NotificationLevel::Error);
+            }
+        }
+    }
+
+    /// Completes the tasks ticked in the issue and logs the sync.
+    async fn finish_issue_sync(&mut self, context_key: &str, link: &IssueLink, checked: Result<Vec<String>>) -> Result<usize> {
+        let integration = issue_integration(link.forge);
+        let before = self.storage.get_tasks(context_key).await?;
+        let result = match checked {
+            Ok(checked) => self.complete_checked(context_key, &checked).await,
+            Err(e) => Err(e),
+        };
+        let logged = result.as_ref().map(|n| *n).map_err(|e| anyhow::anyhow!("{}", e));
+        self.record_sync(SyncLogEntry::new(integration, SyncDirection::Pull, context_key, &logged));
+        // A background sync may finish after the user has moved on
+        if context_key == self.current_context.context_key() {
+            self.show_diff(&format!("{} sync", integration), before).await?;
+        }
+        result
+    }
+
+    async fn complete_checked(&mut self, context_key: &str, checked: &[String]) -> Result<usize> {
+        let mut changed = 0;
+        for task in self.storage.get_tasks(context_key).await? {
+            if !task.is_completed() && checked.contains(&task.text) {
+                self.storage.set_task_status(context_key, task.id, TaskStatus::Completed).await?;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// The Jira issue the current branch is named after, when Jira is set up.
+    fn branch_jira_issue(&self) -> Option<String> {
+        if !self.effective_config().jira.is_enabled() {
+            return None;
+        }
+        jira::issue_key_in_branch(&self.current_context.branch)
+    }
+
+    /// Pulls the context's Jira issues into tasks and pushes status changes back.
+    async fn sync_jira(&mut self) -> Result<usize> {
+        if self.sync_log.is_paused(sync::JIRA) {
+            return Err(anyhow::anyhow!("the Jira integration is paused"));
+        }
+        let context_key = self.current_context.context_key();
+        let issues = match self.jira_query(&context_key).await {
+            Ok(jql) => match JiraClient::new(&self.effective_config().jira) {
+                Ok(client) => client.search(&jql).await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        self.finish_jira_sync(&context_key, issues).await
+    }
+
+    /// The query for the context's issues: those its tasks link to, the
+    /// one the branch is named after and the project's.
+    async fn jira_query(&self, context_key: &str) -> Result<String> {
+        let tasks = self.storage.get_tasks(context_key).await?;
+        let mut keys: Vec<String> = tasks.iter().filter_map(|t| t.issue.clone()).collect();
+        if let Some(key) = jira::issue_key_in_branch(&self.current_context.branch) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        jira::jql(self.effective_config().jira.project_for(context_key), &keys)
+            .ok_or_else(|| anyhow::anyhow!("no jira.projects entry matches this context and the branch names no issue"))
+    }
+
+    /// Applies the fetched issues to the context's tasks and logs the sync.
+    async fn finish_jira_sync(&mut self, context_key: &str, issues: Result<Vec<jira::Issue>>) -> Result<usize> {
+        let before = self.storage.get_tasks(context_key).await?;
+        let result = match issues {
+            Ok(issues) => self.apply_jira_issues(context_key, &issues).await,
+            Err(e) => Err(e),
+        };
+
+        let pulled = result.as_ref().map(|(pulled, _)| *pulled).map_err(|e| anyhow::anyhow!("{}", e));
+        self.record_sync(SyncLogEntry::new(sync::JIRA, SyncDirection::Pull, context_key, &pulled));
+        if let Ok((_, pushed)) = &result {
+            if *pushed > 0 {
+                self.record_sync(SyncLogEntry::new(sync::JIRA, SyncDirection::Push, context_key, &Ok(*pushed)));
+            }
+        }
+        self.show_diff("Jira sync", before).await?;
+        result.map(|(pulled, pushed)| pulled + pushed)
+    }
+
+    /// Returns how many tasks were added or updated and how many issues were transitioned.
+    async fn apply_jira_issues(&mut self, context_key: &str, issues: &[jira::Issue]) -> Result<(usize, usize)> {
+        let config = self.effective_config().jira;
+        let client = JiraClient::new(&config)?;
+        let tasks = self.storage.get_tasks(context_key).await?;
+        let actions = jira::plan(&config, &tasks, issues, &self.view_state.jira_statuses);
+        for issue in issues {
+            self.view_state.jira_statuses.insert(issue.key.clone(), issue.status.clone());
+        }
+
+        let (mut pulled, mut pushed) = (0, 0);
+        for action in actions {
+            match action {
+                SyncAction::Add { issue, status } => {
+                    let id = self.add_task(issue.summary.clone()).await?;
+                    if let Some(mut task) = self.storage.get_tasks(context_key).await?.into_iter().find(|t| t.id == id) {
+                        task.issue = Some(issue.key);
+                        task.set_status(status);
+                        self.storage.update_task(context_key, task).await?;
+                    }
+                    pulled += 1;
+                }
+                SyncAction::Pull { task_id, status } => {
+                    self.storage.set_task_status(context_key, task_id, status).await?;
+                    pulled += 1;
+                }
+                SyncAction::Push { key, status } => {
+                    let name = client.transition(&key, &config, &status).await?;
+                    self.view_state.jira_statuses.insert(key, name);
+                    pushed += 1;
+                }
+            }
+        }
+        self.view_state.save()?;
+        Ok((pulled, pushed))
+    }
+
+    fn record_sync(&mut self, entry: SyncLogEntry) {
+        match &entry.error {
+            Some(error) => tracing::warn!(integration = %entry.integration, context = %entry.context_key, %error, "sync failed"),
+            None => tracing::info!(integration = %entry.integration, context = %entry.context_key, changed = entry.items_changed, "synced"),
+        }
+        self.sync_log.record(entry);
+        if let Err(e) = self.sync_log.save() {
+            self.ui.show_notification(format!("Failed to save sync log: {}", e), crate::ui::NotificationLevel::Error);
+        }
+    }
+
+    fn open_sync_log(&mut self) {
+        let integration = self.ui.selected_integration();
+        self.ui.open_sync_log(self.sync_log.entries_for(integration), self.sync_log.is_paused(integration));
+    }
+
+    async fn handle_sync_log_mode(&mut self, key: KeyCode) -> Result<()> {
+        let integration = self.ui.selected_integration();
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.sync_scroll(1),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.sync_scroll(-1),
+            KeyCode::Tab => {
+                self.ui.sync_integration = (self.ui.sync_integration + 1) % sync::INTEGRATIONS.len();
+                self.open_sync_log();
+            }
+            KeyCode::Char('p') => {
+                self.sync_log.toggle_pause(integration);
+                if let Err(e) = self.sync_log.save() {
+                    self.ui.show_notification(format!("Failed to save sync log: {}", e), crate::ui::NotificationLevel::Error);
+                }
+                self.open_sync_log();
+            }
+            KeyCode::Char('n') => {
+                if self.sync_log.is_paused(integration) {
+                    self.ui.show_notification(format!("{} is paused; press p to resume", integration), crate::ui::NotificationLevel::Error);
+                } else {
+                    let result = if integration == sync::JIRA { self.sync_jira().await } else { self.sync_issue().await };
+                    match result {
+                        Ok(changed) => self.ui.show_notification(format!("Synced {}: {} task(s) changed", integration, changed), crate::ui::NotificationLevel::Success),
+                        Err(e) => self.ui.show_notification(format!("Sync failed: {}", e), crate::ui::NotificationLevel::Error),
+                    }
+                    // A sync that changed tasks leaves its diff view open instead
+                    if self.ui.input_mode != InputMode::Diff {
+                        self.open_sync_log();
+                    }
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('L') => self.ui.cancel_input(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Leaves the TUI, runs the external editor for `edit` and restores the TUI.
+    async fn run_external_edit<B: Backend>(&mut self, terminal: &mut Terminal<B>, edit: ExternalEdit) -> Result<()> {
+        disable_raw_mode()?;
+        execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+
+        let result = match edit {
+            ExternalEdit::BulkEdit => self.bulk_edit().await,
+            ExternalEdit::Notes(id) => self.edit_notes(id).await,
+            ExternalEdit::OpenAnchor(id) => self.open_anchor(id).await,
+            ExternalEdit::OpenAttachment(id, index) => self.open_attachment(id, index).await,
+            ExternalEdit::InputText => self.edit_input_text().await,
+            ExternalEdit::OpenLink(link) => self.open_link(&link),
+        };
+
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        terminal.clear()?;
+
+        match result {
+            Ok(message) => self.ui.show_notification(message, crate::ui::NotificationLevel::Success),
+            Err(e) => self.ui.show_notification(format!("Edit failed: {}", e), crate::ui::NotificationLevel::Error),
+        }
+        Ok(())
+    }
+
+    /// Opens a task's linked file, resolving relative paths against the repository root.
+    async fn open_anchor(&mut self, id: usize) -> Result<String> {
+        let tasks = self.storage.get_tasks(&self.current_context.context_key()).await?;
+        let Some(anchor) = tasks.iter().find(|t| t.id == id).and_then(|t| t.linked_file()).cloned() else {
+            return Err(anyhow::anyhow!("task has no linked file"));
+        };
+        let path = match git::repo_root() {
+            Some(root) => root.join(&anchor.file),
+            None => std::path::PathBuf::from(&anchor.file),
+        };
+        external_editor::open_at(&self.effective_config().editor_command, &path, anchor.line)?;
+        Ok(format!("Opened {}", anchor))
+    }
+
+    /// Opens a task's attachment: files in the editor, URLs in the browser.
+    async fn open_attachment(&mut self, id: usize, index: usize) -> Result<String> {
+        let tasks = self.storage.get_tasks(&self.current_context.context_key()).await?;
+        let Some(attachment) = tasks.into_iter().find(|t| t.id == id).and_then(|t| t.attachments.into_iter().nth(index)) else {
+            return Err(anyhow::anyhow!("attachment no longer exists"));
+        };
+        self.open_target(&attachment)
+    }
+
+    /// Opens a link from a task's text or notes; links without a scheme are
+    /// repository files.
+    fn open_link(&mut self, link: &str) -> Result<String> {
+        let Some(target) = Attachment::parse(link) else {
+            return Err(anyhow::anyhow!("not a link: {}", link));
+        };
+        self.open_target(&target)
+    }
+
+    /// Opens files in the editor and URLs in the browser.
+    fn open_target(&self, target: &Attachment) -> Result<String> {
+        match target {
+            Attachment::File(anchor) => {
+                let path = match git::repo_root() {
+                    Some(root) => root.join(&anchor.file),
+                    None => std::path::PathBuf::from(&anchor.file),
+                };
+                external_editor::open_at(&self.effective_config().editor_command, &path, anchor.line)?;
+            }
+            Attachment::Url { url } => external_editor::open_url(url)?,
+        }
+        Ok(format!("Opened {}", target))
+    }
+
+    /// Edits the add/edit popup's text in the editor: the first paragraph
+    /// goes back into the popup and the rest becomes the task's notes.
+    async fn edit_input_text(&mut self) -> Result<String> {
+        let notes = match (&self.ui.input_notes, self.ui.editing_id) {
+            (Some(notes), _) => notes.clone(),
+            (None, Some(id)) => {
+                let tasks = self.storage.get_tasks(&self.current_context.context_key()).await?;
+                tasks.into_iter().find(|t| t.id == id).map(|t| t.notes).unwrap_or_default()
+            }
+            (None, None) => String::new(),
+        };
+        let mut buffer = self.ui.input.text().to_string();
+        if !notes.is_empty() {
+            buffer = format!("{}\n\n{}", buffer, notes);
+        }
+
+        let (text, notes) = external_editor::split_text_and_notes(&external_editor::edit(&buffer, "md")?);
+        self.ui.input.set(text);
+        self.ui.input_notes = Some(notes);
+        self.ui.similar_task = None;
+        Ok("Press Enter to save, Esc to discard".to_string())
+    }
+
+    async fn edit_notes(&mut self, id: usize) -> Result<String> {
+        let context_key = self.current_context.context_key();
+        let tasks = self.storage.get_tasks(&context_key).await?;
+        let Some(mut task) = tasks.into_iter().find(|t| t.id == id) else {
+            return Err(anyhow::anyhow!("task no longer exists"));
+        };
+
+        let notes = external_editor::edit(&task.notes, "md")?;
+        let notes = notes.trim_end().to_string();
+        if notes == task.notes {
+            return Ok("Notes unchanged".to_string());
+        }
+        task.notes = notes;
+        self.storage.update_task(&context_key, task).await?;
+        Ok("Notes saved".to_string())
+    }
+
+    async fn bulk_edit(&mut self) -> Result<String> {
+        let context_key = self.current_context.context_key();
+        let original = self.storage.get_tasks(&context_key).await?;
+        let buffer = external_editor::edit(&bulk::to_buffer(&context_key, &original), "quill")?;
+
+        match bulk::parse_buffer(&buffer, &original)? {
+            Some(edit) => match self.confirm_first(BulkAction::BulkEdit { original, edit }) {
+                Some(action) => self.apply_bulk(action).await,
+                None => Ok(format!("Type \"{}\" to apply the edit", self.ui.confirm_phrase)),
+            },
+            None => Ok("Empty buffer, nothing changed".to_string()),
+        }
+    }
+
+    async fn apply_bulk_edit(&mut self, original: &[Task], edit: BulkEdit) -> Result<String> {
+        let context_key = self.current_context.context_key();
+
+        for id in &edit.removed {
+            self.storage.remove_task(&context_key, *id).await?;
+        }
+        let updated: Vec<Task> = edit.updated(original).cloned().collect();
+        for task in &updated {
+            self.storage.update_task(&context_key, task.clone()).await?;
+        }
+
+        let mut order = Vec::new();
+        for entry in &edit.entries {
+            match entry {
+                BulkEntry::Existing(task) => order.push(task.id),
+                BulkEntry::New { status, text, tags } => {
+                    let id = self.add_task(text.clone()).await?;
+                    if *status != TaskStatus::NotStarted || !tags.is_empty() {
+                        let tasks = self.storage.get_tasks(&context_key).await?;
+                        if let Some(mut task) = tasks.into_iter().find(|t| t.id == id) {
+                            task.set_status(status.clone());
+                            if !tags.is_empty() {
+                                task.tags = tags.clone();
+                            }
+                            self.storage.update_task(&context_key, task).await?;
+                        }
+                    }
+                    order.push(id);
+                }
+            }
+        }
+
+        if edit.reordered(original) || edit.added() > 0 {
+            self.reorder(&context_key, &order).await?;
+        }
+
+        self.ui.list_state.select(None);
+        Ok(format!(
+            "Bulk edit applied: {} updated, {} added, {} deleted",
+            updated.len(),
+            edit.added(),
+            edit.removed.len()
+        ))
+    }
+
+    /// Moves task `id` just past `neighbour`, the task next to it on screen,
+    /// which filters and pinning may put several places away in storage.
+    async fn move_past(&mut self, id: usize, neighbour: usize, up: bool) -> Result<bool> {
+        let context_key = self.current_context.context_key();
+        let ids: Vec<usize> = self.storage.get_tasks(&context_key).await?.iter().map(|t| t.id).collect();
+        let (Some(from), Some(to)) = (ids.iter().position(|&i| i == id), ids.iter().position(|&i| i == neighbour)) else {
+            return Ok(false);
+        };
+        let steps = if up { from.saturating_sub(to) } else { to.saturating_sub(from) };
+        for _ in 0..steps {
+            let moved = if up {
+                self.storage.move_task_up(&context_key, id).await?
+            } else {
+                self.storage.move_task_down(&context_key, id).await?
+            };
+            if !moved {
+                return Ok(false);
+            }
+        }
+        Ok(steps > 0)
+    }
+
+    /// Puts the tasks with the given ids first, in that order, using the
+    /// adjacent moves the storage trait offers.
+    async fn reorder(&mut self, context_key: &str, order: &[usize]) -> Result<()> {
+        let mut current: Vec<usize> = self.storage.get_tasks(context_key).await?.iter().map(|t| t.id).collect();
+        for (target, id) in order.iter().enumerate() {
+            let Some(mut pos) = current.iter().position(|c| c == id) else {
+                continue;
+            };
+            while pos > target {
+                self.storage.move_task_up(context_key, *id).await?;
+                current.swap(pos, pos - 1);
+                pos -= 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens the diff view comparing `before` with the current context, if anything changed.
+    async fn show_diff(&mut self, operation: &str, before: Vec<Task>) -> Result<()> {
+        let context_key = self.current_context.context_key();
+        let after = self.storage.get_tasks(&context_key).await?;
+        let diff = ContextDiff::new(operation, &context_key, before, after);
+        if !diff.is_empty() {
+            self.ui.open_diff(diff);
+        }
+        Ok(())
+    }
+
+    /// Puts a context back the way it was before the diffed operation.
+    async fn revert_diff(&mut self, diff: &ContextDiff) -> Result<()> {
+        let context_key = &diff.context_key;
+
+        for task in &diff.after {
+            if !diff.before.iter().any(|t| t.id == task.id) {
+                self.storage.remove_task(context_key, task.id).await?;
+                self.storage.purge_deleted(context_key, task.id).await?;
+            }
+        }
+
+        let mut order = Vec::new();
+        for task in &diff.before {
+            match diff.after.iter().find(|t| t.id == task.id) {
+                Some(current) => {
+                    if current != task {
+                        self.storage.update_task(context_key, task.clone()).await?;
+                    }
+                    order.push(task.id);
+                }
+                None => {
+                    // Deleted tasks come back from the trash, or are re-added as a new task
+                    let restored = match self.storage.restore_deleted(context_key, task.id).await? {
+                        Some(restored) => restored,
+                        None => {
+                            let id = self.storage.add_task(context_key, task.text.clone()).await?;
+                            let added = self.storage.get_tasks(context_key).await?.into_iter().find(|t| t.id == id);
+                            added.ok_or_else(|| anyhow::anyhow!("task {} vanished while being re-added", id))?
+                        }
+                    };
+                    self.storage.update_task(context_key, Task { id: restored.id, uid: restored.uid, ..task.clone() }).await?;
+                    order.push(restored.id);
+                }
+            }
+        }
+
+        self.reorder(context_key, &order).await?;
+        self.ui.list_state.select(None);
+        Ok(())
+    }
+
+    async fn handle_diff_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.diff_scroll_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.diff_scroll_by(-1),
+            KeyCode::Char('r') => {
+                if let Some(diff) = self.ui.diff.take() {
+                    match self.revert_diff(&diff).await {
+                        Ok(()) => self.ui.show_notification(format!("Reverted {}", diff.operation), crate::ui::NotificationLevel::Success),
+                        Err(e) => self.ui.show_notification(format!("Revert failed: {}", e), crate::ui::NotificationLevel::Error),
+                    }
+                }
+                self.ui.cancel_input();
+            }
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                self.ui.diff = None;
+                self.ui.cancel_input();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_input_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            // A second Enter adds the task anyway
+            KeyCode::Enter if self.ui.input_mode == InputMode::Adding && self.ui.similar_task.is_none() => {
+                let parsed = QuickAdd::parse(self.ui.input.text(), chrono::Local::now().date_naive(), &self.ui.date_parser);
+                let tasks = self.storage.get_tasks(&self.current_context.context_key()).await?;
+                match similar::find_similar(&parsed.text, &tasks) {
+                    Some(task) => self.ui.similar_task = Some((task.id, task.text.clone())),
+                    None => self.submit_input().await?,
+                }
+            }
+            KeyCode::Enter => self.submit_input().await?,
+            KeyCode::Char('e') if modifiers.contains(KeyModifiers::CONTROL) => {
+                self.pending_external_edit = Some(ExternalEdit::InputText);
+            }
+            KeyCode::Tab if self.ui.similar_task.is_some() => {
+                let Some((id, _)) = self.ui.similar_task.clone() else {
+                    return Ok(());
+                };
+                self.ui.cancel_input();
+                // A snoozed duplicate is still one; list snoozed tasks to reach it
+                let all_tasks = self.storage.get_tasks(&self.current_context.context_key()).await?;
+                if all_tasks.iter().any(|t| t.id == id && t.is_snoozed(chrono::Utc::now())) {
+                    self.ui.show_snoozed = true;
+                }
+                let tasks = self.visible(all_tasks);
+                match tasks.iter().position(|t| t.id == id) {
+                    Some(index) => self.ui.list_state.select(Some(index)),
+                    None => self.ui.show_notification(
+                        "The similar task is hidden by the status filter".to_string(),
+                        crate::ui::NotificationLevel::Error,
+                    ),
+                }
+            }
+            KeyCode::Tab if self.ui.accept_suggestion() => self.ui.similar_task = None,
+            KeyCode::Esc => {
+                self.ui.cancel_input();
+            }
+            _ => {
+                let before = self.ui.input.text().to_string();
+                self.edit_input(key, modifiers);
+                // A changed text is checked for duplicates again
+                if self.ui.input.text() != before {
+                    self.ui.similar_task = None;
+                    self.ui.update_suggestions();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Finished and deleted tasks here plus the tasks of other contexts, for
+    /// suggestions while typing a new task.
+    async fn suggestion_pool(&self) -> Result<Vec<similar::Suggestion>> {
+        let context_key = self.current_context.context_key();
+        let mut pool: Vec<similar::Suggestion> = self
+            .storage
+            .get_tasks(&context_key)
+            .await?
+            .into_iter()
+            .filter(|task| task.is_completed())
+            .map(|task| similar::Suggestion { text: task.text, source: "completed here".to_string() })
+            .collect();
+        for deleted in self.storage.list_deleted(&context_key).await? {
+            pool.push(similar::Suggestion { text: deleted.task.text, source: "deleted here".to_string() });
+        }
+
+        let repo_prefix = format!("{}:{}:", self.current_context.org, self.current_context.repo);
+        for key in self.storage.list_contexts().await? {
+            if key == context_key {
+                continue;
+            }
+            let place = match key.strip_prefix(&repo_prefix) {
+                Some(branch) => format!("on {}", branch),
+                None => format!("in {}", key),
+            };
+            for task in self.storage.get_tasks(&key).await? {
+                let state = if task.is_completed() { "completed" } else { "open" };
+                pool.push(similar::Suggestion { text: task.text, source: format!("{} {}", state, place) });
+            }
+        }
+        Ok(pool)
+    }
+
+    /// Adds or edits the task from the add/edit popup and closes it.
+    async fn submit_input(&mut self) -> Result<()> {
+        // Capture editing_id before finish_input clears it
+        let editing_id = self.ui.editing_id;
+        let parent = self.ui.adding_parent;
+        let notes = self.ui.input_notes.take();
+        let text = self.ui.finish_input();
+        if !text.trim().is_empty() {
+            let context_key = self.current_context.context_key();
+            let id = match editing_id {
+                Some(id) => {
+                    self.storage.edit_task(&context_key, id, text).await?;
+                    id
+                }
+                None => self.add_quick_task(&text, parent).await?,
+            };
+            if let Some(notes) = notes {
+                let tasks = self.storage.get_tasks(&context_key).await?;
+                if let Some(mut task) = tasks.into_iter().find(|t| t.id == id && t.notes != notes) {
+                    task.notes = notes;
+                    self.storage.update_task(&context_key, task).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_due_shift_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                let shift = match DueShift::parse(self.ui.input.text()) {
+                    Ok(shift) => shift,
+                    Err(e) => {
+                        // Keep the prompt open so the input can be corrected
+                        self.ui.show_notification(e.to_string(), crate::ui::NotificationLevel::Error);
+                        return Ok(());
+                    }
+                };
+                self.ui.cancel_input();
+
+                let context_key = self.current_context.context_key();
+                let tasks = self.visible_tasks().await?;
+                let selected = self.ui.list_state.selected().and_then(|i| tasks.get(i));
+                let all_tasks = self.storage.get_tasks(&context_key).await?;
+                let mut target_ids: Vec<usize> = Vec::new();
+                for task in self.target_tasks(&tasks, selected) {
+                    for id in self.propagation_targets(task.id, &all_tasks) {
+                        if !target_ids.contains(&id) {
+                            target_ids.push(id);
+                        }
+                    }
+                }
+
+                let mut changed = 0;
+                for mut task in all_tasks.into_iter().filter(|t| target_ids.contains(&t.id)) {
+                    let due = shift.apply(task.due);
+                    if due != task.due {
+                        task.due = due;
+                        self.storage.update_task(&context_key, task).await?;
+                        changed += 1;
+                    }
+                }
+                self.ui.marked.clear();
+                self.ui.show_notification(format!("Updated due dates of {} task(s)", changed), crate::ui::NotificationLevel::Success);
+            }
+            KeyCode::Esc => self.ui.cancel_input(),
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    /// Snoozes the marked tasks (or the selected one) until the entered
+    /// time, or wakes them when the input is empty.
+    async fn handle_snooze_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                let input = self.ui.input.text().trim().to_string();
+                let until = if input.is_empty() {
+                    None
+                } else {
+                    let now = chrono::Local::now();
+                    let until = self
+                        .ui
+                        .date_parser
+                        .parse_snooze(&input, now.naive_local())
+                        .and_then(|t| t.and_local_timezone(chrono::Local).earliest());
+                    match until {
+                        Some(until) if until > now => Some(until.with_timezone(&chrono::Utc).to_rfc3339()),
+                        Some(_) => {
+                            self.ui.show_notification("That time has already passed".to_string(), crate::ui::NotificationLevel::Error);
+                            return Ok(());
+                        }
+                        None => {
+                            // Keep the prompt open so the input can be corrected
+                            self.ui.show_notification(format!("Can't tell when '{}' is", input), crate::ui::NotificationLevel::Error);
+                            return Ok(());
+                        }
+                    }
+                };
+                self.ui.cancel_input();
+
+                let context_key = self.current_context.context_key();
+                let tasks = self.visible_tasks().await?;
+                let selected = self.ui.list_state.selected().and_then(|i| tasks.get(i));
+                for mut task in self.target_tasks(&tasks, selected) {
+                    task.snoozed_until = until.clone();
+                    self.storage.update_task(&context_key, task).await?;
+                }
+                self.ui.marked.clear();
+                let message = match until {
+                    Some(_) => format!("Snoozed until {}", input),
+                    None => "Woke snoozed task(s)".to_string(),
+                };
+                self.ui.show_notification(message, crate::ui::NotificationLevel::Success);
+            }
+            KeyCode::Esc => self.ui.cancel_input(),
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    /// Clears the snooze of tasks whose time has come, announcing each one.
+    /// `tasks` (the current context's) are checked every frame and the other
+    /// contexts every `SNOOZE_INTERVAL`. Failed writes are logged and retried
+    /// on a later check rather than ending the app.
+    async fn wake_snoozed(&mut self, tasks: &mut [Task]) {
+        let now = chrono::Utc::now();
+        let current = self.current_context.context_key();
+        self.wake_in(&current, tasks, now).await;
+        if self.snooze_checked.is_some_and(|checked| checked.elapsed() < SNOOZE_INTERVAL) {
+            return;
+        }
+        self.snooze_checked = Some(Instant::now());
+        match self.storage.get_all_tasks().await {
+            Ok(all_tasks) => {
+                for (context_key, mut tasks) in all_tasks.into_iter().filter(|(context_key, _)| *context_key != current) {
+                    self.wake_in(&context_key, &mut tasks, now).await;
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "couldn't check other contexts for snoozed tasks"),
+        }
+    }
+
+    async fn wake_in(&mut self, context_key: &str, tasks: &mut [Task], now: chrono::DateTime<chrono::Utc>) {
+        let elsewhere = context_key != self.current_context.context_key();
+        for task in tasks.iter_mut().filter(|t| t.snoozed_until.is_some() && !t.is_snoozed(now)) {
+            task.snoozed_until = None;
+            if let Err(e) = self.storage.update_task(context_key, task.clone()).await {
+                tracing::warn!(context = %context_key, id = task.id, error = %e, "couldn't wake snoozed task");
+                continue;
+            }
+            let message = if elsewhere {
+                format!("⏰ '{}' is back from snooze in {}", task.text, context_key)
+            } else {
+                format!("⏰ '{}' is back from snooze", task.text)
+            };
+            self.ui.show_notification(message, crate::ui::NotificationLevel::Success);
+        }
+    }
+
+    /// Moves the marked tasks (or the selected one) to the context of a
+    /// branch that may not exist yet, where they wait until it's checked out.
+    async fn handle_defer_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                let target = match defer::branch_context(&self.current_context, self.ui.input.text(), &self.effective_config()) {
+                    Ok(target) => target,
+                    Err(e) => {
+                        self.ui.show_notification(format!("Can't defer: {}", e), crate::ui::NotificationLevel::Error);
+                        return Ok(());
+                    }
+                };
+                self.ui.cancel_input();
+
+                let context_key = self.current_context.context_key();
+                let tasks = self.visible_tasks().await?;
+                let selected = self.ui.list_state.selected().and_then(|i| tasks.get(i));
+                let ids: Vec<usize> = self.target_tasks(&tasks, selected).iter().map(|t| t.id).collect();
+                let moved = defer::move_tasks(self.storage.as_mut(), &context_key, &ids, &target).await?;
+                self.ui.marked.clear();
+                self.ui.list_state.select(None);
+                self.ui.show_notification(format!("Deferred {} task(s) to {}", moved, target), crate::ui::NotificationLevel::Success);
+            }
+            KeyCode::Esc => self.ui.cancel_input(),
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    /// Moves the marked tasks (or the selected one) to the context typed,
+    /// which is how inbox tasks are filed under a repository.
+    async fn handle_triage_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                let target = match defer::target_context(&self.current_context, self.ui.input.text()) {
+                    Ok(target) => target,
+                    Err(e) => {
+                        self.ui.show_notification(format!("Can't move: {}", e), crate::ui::NotificationLevel::Error);
+                        return Ok(());
+                    }
+                };
+                self.ui.cancel_input();
+
+                let context_key = self.current_context.context_key();
+                let tasks = self.visible_tasks().await?;
+                let selected = self.ui.list_state.selected().and_then(|i| tasks.get(i));
+                let ids: Vec<usize> = self.target_tasks(&tasks, selected).iter().map(|t| t.id).collect();
+                let moved = defer::move_tasks(self.storage.as_mut(), &context_key, &ids, &target).await?;
+                self.ui.marked.clear();
+                self.ui.list_state.select(None);
+                self.ui.show_notification(format!("Moved {} task(s) to {}", moved, target), crate::ui::NotificationLevel::Success);
+            }
+            KeyCode::Esc => self.ui.cancel_input(),
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    async fn handle_anchor_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                let editing_id = self.ui.editing_id;
+                let anchor = Anchor::parse(&self.ui.finish_input());
+                let context_key = self.current_context.context_key();
+                let tasks = self.storage.get_tasks(&context_key).await?;
+                if let Some(mut task) = tasks.into_iter().find(|t| Some(t.id) == editing_id) {
+                    task.anchor = anchor;
+                    self.storage.update_task(&context_key, task).await?;
+                }
+            }
+            KeyCode::Esc => self.ui.cancel_input(),
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    async fn handle_attachments_mode(&mut self, key: KeyCode) -> Result<()> {
+        let Some(id) = self.ui.attachments_task else {
+            self.ui.cancel_input();
+            return Ok(());
+        };
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.attachments_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.attachments_prev(),
+            KeyCode::Enter | KeyCode::Char('o') => {
+                if let Some(index) = self.ui.attachments_state.selected() {
+                    self.pending_external_edit = Some(ExternalEdit::OpenAttachment(id, index));
+                }
+            }
+            KeyCode::Char('a') => self.ui.start_attachment(),
+            KeyCode::Char('x') => {
+                let Some(index) = self.ui.attachments_state.selected() else {
+                    return Ok(());
+                };
+                let context_key = self.current_context.context_key();
+                let tasks = self.storage.get_tasks(&context_key).await?;
+                if let Some(mut task) = tasks.into_iter().find(|t| t.id == id) {
+                    if index < task.attachments.len() {
+                        task.attachments.remove(index);
+                        self.storage.update_task(&context_key, task.clone()).await?;
+                    }
+                    self.ui.open_attachments(&task);
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('F') => {
+                self.ui.attachments_task = None;
+                self.ui.cancel_input();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_add_attachment_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                let input = self.ui.finish_input();
+                let Some(attachment) = Attachment::parse(&input) else {
+                    self.ui.input_mode = InputMode::Attachments;
+                    return Ok(());
+                };
+                let context_key = self.current_context.context_key();
+                let tasks = self.storage.get_tasks(&context_key).await?;
+                match tasks.into_iter().find(|t| Some(t.id) == self.ui.attachments_task) {
+                    Some(mut task) => {
+                        task.attachments.push(attachment);
+                        self.storage.update_task(&context_key, task.clone()).await?;
+                        self.ui.attachments_state.select(Some(task.attachments.len() - 1));
+                        self.ui.open_attachments(&task);
+                    }
+                    None => self.ui.show_notification("The task no longer exists".to_string(), crate::ui::NotificationLevel::Error),
+                }
+            }
+            KeyCode::Esc => {
+                self.ui.input.clear();
+                self.ui.input_mode = InputMode::Attachments;
+            }
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    /// Handles clicks and scrolling; returns true if a footer button asked to quit.
+    async fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<bool> {
+        match self.ui.input_mode {
+            InputMode::Normal => {}
+            InputMode::Inspector => {
+                match mouse.kind {
+                    MouseEventKind::ScrollDown => self.ui.inspector_scroll_by(3),
+                    MouseEventKind::ScrollUp => self.ui.inspector_scroll_by(-3),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            _ => return Ok(false),
+        }
+
+        let tasks = self.visible_tasks().await?;
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(key) = self.ui.footer_button_at(mouse.column, mouse.row) {
+                    // Buttons run their action directly, without counts or `dd`
+                    self.pending_keys.clear();
+                    return match command_for_key(KeyCode::Char(key), KeyModifiers::NONE) {
+                        Some(command) => self.execute_command(command).await,
+                        None => Ok(false),
+                    };
+                }
+                if let Some((index, on_symbol)) = self.ui.task_at(mouse.column, mouse.row, tasks.len()) {
+                    self.ui.list_state.select(Some(index));
+                    if on_symbol {
+                        return self.execute_command(Command::CycleStatus).await;
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.ui.scroll_selection(&tasks, 1),
+            MouseEventKind::ScrollUp => self.ui.scroll_selection(&tasks, -1),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Line-editing keys shared by every text input popup.
+    fn edit_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        let input = &mut self.ui.input;
+        match key {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => match c {
+                'a' => input.home(),
+                'e' => input.end(),
+                'b' => input.left(),
+                'f' => input.right(),
+                'd' => input.delete(),
+                'k' => input.kill_to_end(),
+                'u' => input.kill_to_start(),
+                'w' => input.kill_word_back(),
+                'y' => input.yank(),
+                _ => {}
+            },
+            KeyCode::Char('y') if modifiers.contains(KeyModifiers::ALT) => input.yank_pop(),
+            KeyCode::Char(c) => input.insert(c),
+            KeyCode::Backspace => input.backspace(),
+            KeyCode::Delete => input.delete(),
+            KeyCode::Left => input.left(),
+            KeyCode::Right => input.right(),
+            KeyCode::Home => input.home(),
+            KeyCode::End => input.end(),
+            _ => {}
+        }
+    }
+
+    async fn handle_config_home_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.ui.config_home_prev();
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.ui.config_home_next();
+            }
+            KeyCode::Enter => {
+                match self.ui.config_field_index {
+                    0 => {}, // Current storage - no action
+                    1 => self.ui.enter_storage_selection(), // Configure Storage
+                    2 => self.ui.enter_credentials(),
+                    3 => {
+                        // Save & Exit
+                        let new_config = self.ui.get_config();
+                        new_config.save()?;
+
+                        match self.reopen_storage(new_config).await {
+                            Ok(()) => {
+                                self.ui.show_notification("Storage configuration updated successfully".to_string(), crate::ui::NotificationLevel::Success);
+                            }
+                            Err(e) => {
+                                let error_msg = format!("Failed to connect to new storage: {}. Keeping current configuration.", e);
+                                self.ui.show_notification(error_msg, crate::ui::NotificationLevel::Error);
+                            }
+                        }
+                        
+                        self.close_config();
+                    }
+                    _ => {}
+                }
+            }
+            KeyCode::Esc => {
+                self.close_config();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Opens the storage `new_config` describes and makes it the config in
+    /// use; on failure the current storage and config are kept.
+    async fn reopen_storage(&mut self, new_config: AppConfig) -> Result<()> {
+        let effective_config = new_config.with_shared(self.shared_config.as_ref()).with_project(self.project_config.as_ref());
+        let storage_rule = effective_config.storage_rule(&self.current_context);
+        let effective_config = effective_config.for_context(&self.current_context);
+        let storage = storage::open_interactive(&effective_config).await?;
+        // Dropping a storage with unsaved writes would lose them
+        self.storage.flush().await?;
+        if let Some(peek) = &mut self.peek {
+            peek.default_storage.flush().await?;
+        }
+        self.storage = Box::new(TrackedStorage::new(storage, self.save_tracker.clone()));
+        self.storage_rule = storage_rule;
+        self.peek = None;
+        self.ui.peeking = None;
+        self.apply_display_config(&effective_config);
+        self.config = new_config;
+        self.storage_error = None;
+        self.ui.storage_failure = None;
+        self.plugin_baseline = None;
+        Ok(())
+    }
+
+    async fn handle_setup_mode(&mut self, key: KeyCode) -> Result<()> {
+        let step = self.ui.setup_step;
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => self.ui.setup_prev(),
+            KeyCode::Down | KeyCode::Char('j') => self.ui.setup_next(),
+            KeyCode::Char('r') if step == SetupStep::Connection => self.ui.reveal_secrets = !self.ui.reveal_secrets,
+            KeyCode::Esc => match step {
+                // Skipping still writes the config, so setup isn't offered again
+                SetupStep::Backend => self.finish_setup(self.config.clone()).await?,
+                SetupStep::Connection => self.ui.enter_setup_step(SetupStep::Backend),
+                SetupStep::Scope => self.ui.enter_setup_step(SetupStep::Connection),
+                SetupStep::Theme => {
+                    self.ui.theme = self.config.theme;
+                    self.ui.enter_setup_step(SetupStep::Scope);
+                }
+            },
+            KeyCode::Enter => match step {
+                SetupStep::Backend => {
+                    let index = self.ui.config_field_index;
+                    let unsupported = match index {
+                        1 if !storage::MONGO_SUPPORTED => Some("MongoDB (rebuild with the `mongo` feature)"),
+                        3 if !storage::REMOTE_SUPPORTED => Some("remote storage (rebuild with the `remote` feature)"),
+                        _ => None,
+                    };
+                    if let Some(missing) = unsupported {
+                        self.ui.show_notification(format!("This build of quill has no support for {}", missing), crate::ui::NotificationLevel::Error);
+                        return Ok(());
+                    }
+                    self.ui.storage_selection_index = index;
+                    self.ui.temp_config.storage_type = [StorageType::Local, StorageType::MongoDB, StorageType::Git, StorageType::Remote][index].clone();
+                    self.ui.connection_test = None;
+                    self.ui.enter_setup_step(SetupStep::Connection);
+                }
+                SetupStep::Connection => {
+                    let fields = self.ui.connection_fields().len();
+                    match self.ui.config_field_index {
+                        field if field < fields => {
+                            self.ui.input.set(self.ui.get_current_field_value());
+                            self.ui.input_mode = InputMode::SetupField;
+                        }
+                        field if field == fields => self.test_connection().await,
+                        _ => self.ui.enter_setup_step(SetupStep::Scope),
+                    }
+                }
+                SetupStep::Scope => {
+                    self.ui.temp_config.context_scope = if self.ui.config_field_index == 1 { ContextScope::Repo } else { ContextScope::Branch };
+                    self.ui.enter_setup_step(SetupStep::Theme);
+                }
+                SetupStep::Theme => self.finish_setup(self.ui.get_config()).await?,
+            },
+            _ => {}
+        }
+        // The theme being picked is shown right away
+        if self.ui.input_mode == InputMode::Setup && self.ui.setup_step == SetupStep::Theme {
+            self.ui.theme = crate::ui::THEMES[self.ui.config_field_index];
+            self.ui.temp_config.theme = self.ui.theme;
+        }
+        Ok(())
+    }
+
+    /// Tests the connection of the config being edited and shows the outcome inline.
+    async fn test_connection(&mut self) {
+        let config = self.ui.temp_config.for_context(&self.current_context);
+        self.ui.connection_test = Some(storage::test_connection(&config).await.map_err(|e| e.to_string()));
+    }
+
+    async fn handle_setup_field_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                let value = self.ui.finish_input();
+                self.ui.set_current_field_value(value);
+                self.ui.connection_test = None;
+                self.ui.input_mode = InputMode::Setup;
+            }
+            KeyCode::Esc => {
+                self.ui.input.clear();
+                self.ui.input_mode = InputMode::Setup;
+            }
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    /// Opens the storage chosen in setup and saves the config. When it
+    /// doesn't open, setup goes back to the connection screen with the error.
+    async fn finish_setup(&mut self, config: AppConfig) -> Result<()> {
+        if let Err(e) = self.reopen_storage(config.clone()).await {
+            self.ui.connection_test = Some(Err(e.to_string()));
+            self.ui.enter_setup_step(SetupStep::Connection);
+            return Ok(());
+        }
+        config.save()?;
+        self.ui.cancel_input();
+        self.ui.show_notification(format!("Saved your settings; using {} storage", config.storage_type.label()), crate::ui::NotificationLevel::Success);
+        self.offer_first_import().await;
+        Ok(())
+    }
+
+    /// Follows the file behind the open storage, when it has one, so edits
+    /// from other programs or quill instances show up without a restart.
+    fn watch_storage_file(&mut self) {
+        let path = self.storage.watch_path();
+        if self.storage_watcher.as_ref().map(|(watched, _)| watched) == path.as_ref() {
+            return;
+        }
+        self.storage_watcher = path.map(|path| {
+            let watcher = self.file_watcher(&path);
+            if let Err(e) = &watcher {
+                tracing::warn!(path = %path.display(), error = %e, "can't watch the storage file");
+            }
+            (path, watcher.ok())
+        });
+    }
+
+    fn file_watcher(&self, path: &std::path::Path) -> notify::Result<RecommendedWatcher> {
+        let name = path.file_name().map(|n| n.to_os_string());
+        let changes = self.storage_changes_tx.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            let ours = event.paths.iter().any(|p| p.file_name().map(|n| n.to_os_string()) == name);
+            if ours && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                let _ = changes.send(());
+            }
+        })?;
+        // Editors often save by replacing the file, so watch its directory
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    }
+
+    async fn check_external_changes(&mut self) {
+        match self.storage.check_external_changes().await {
+            Ok(ExternalChange::None) => {}
+            Ok(ExternalChange::Reloaded) => {
+                self.plugin_baseline = None;
+                self.ui.show_notification("Reloaded tasks changed by another program".to_string(), crate::ui::NotificationLevel::Success);
+            }
+            Ok(ExternalChange::Conflict) => self.ui.input_mode = InputMode::StorageConflict,
+            Err(e) => self.ui.show_notification(format!("Could not reload tasks: {}", e), crate::ui::NotificationLevel::Error),
+        }
+    }
+
+    async fn handle_storage_conflict_mode(&mut self, key: KeyCode) {
+        let keep_mine = match key {
+            KeyCode::Char('m') => true,
+            KeyCode::Char('t') => false,
+            _ => return,
+        };
+        self.ui.cancel_input();
+        self.plugin_baseline = None;
+        if let Err(e) = self.storage.resolve_external_change(keep_mine).await {
+            self.ui.show_notification(format!("Could not resolve the conflict: {}", e), crate::ui::NotificationLevel::Error);
+        }
+    }
+
+    /// Leaves the config screen, back to the recovery screen when the
+    /// storage still isn't open.
+    fn close_config(&mut self) {
+        self.ui.cancel_input();
+        if self.ui.storage_failure.is_some() {
+            self.ui.input_mode = InputMode::StorageRecovery;
+        }
+    }
+
+    /// Answers the screen shown when the configured storage didn't open;
+    /// returns true to quit. Only `s` changes the saved config.
+    async fn handle_storage_recovery_mode(&mut self, key: KeyCode) -> Result<bool> {
+        let Some(failure) = self.ui.storage_failure.clone() else {
+            self.ui.cancel_input();
+            return Ok(false);
+        };
+        let config = self.effective_config().for_context(&self.current_context);
+        let opened = match key {
+            KeyCode::Char('r') => storage::open_interactive(&config).await.map(|storage| (storage, format!("Connected to {} storage", failure.backend.label()))),
+            KeyCode::Char('c') => {
+                self.ui.start_storage_config(&self.config);
+                return Ok(false);
+            }
+            KeyCode::Char('l') => {
+                // A local file that failed to open is replaced by the default one
+                let path = match failure.backend {
+                    StorageType::Local => "~/.quill/storage/todos.json".to_string(),
+                    _ => config.expand_local_path(),
+                };
+                LocalTaskStorage::new(path).map(|storage| {
+                    let storage: Box<dyn TaskStorage> = Box::new(storage.with_trash_retention(config.trash_retention));
+                    (storage, "Using local storage for this session".to_string())
+                })
+            }
+            KeyCode::Char('s') if failure.can_switch => {
+                let mut switched = self.config.clone();
+                switched.storage_type = StorageType::Local;
+                let opened = LocalTaskStorage::new(config.expand_local_path());
+                if opened.is_ok() {
+                    switched.save()?;
+                    self.config = switched;
+                }
+                opened.map(|storage| {
+                    let storage: Box<dyn TaskStorage> = Box::new(storage.with_trash_retention(config.trash_retention));
+                    (storage, "Switched to local storage and saved it to the config".to_string())
+                })
+            }
+            KeyCode::Char('q') => return Ok(true),
+            _ => return Ok(false),
+        };
+
+        match opened {
+            Ok((storage, message)) => {
+                if matches!(key, KeyCode::Char('l') | KeyCode::Char('s')) {
+                    self.ui.backend = StorageType::Local.label().to_string();
+                }
+                // Whatever the failed storage still holds is saved if it can be
+                if let Err(e) = self.storage.flush().await {
+                    tracing::warn!(error = %e, "couldn't flush the storage being replaced");
+                }
+                self.storage = Box::new(TrackedStorage::new(storage, self.save_tracker.clone()));
+                self.plugin_baseline = None;
+                self.ui.storage_failure = None;
+                self.ui.cancel_input();
+                self.ui.show_notification(message, crate::ui::NotificationLevel::Success);
+            }
+            Err(e) => self.ui.storage_failure = Some(StorageFailure { error: e.to_string(), ..failure }),
+        }
+        Ok(false)
+    }
+
+    async fn handle_storage_selection_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.ui.storage_selection_prev();
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.ui.storage_selection_next();
+            }
+            KeyCode::Enter => {
+                // Update the temp config with selected storage type
+                match self.ui.storage_selection_index {
+                    0 => {
+                        self.ui.temp_config.storage_type = StorageType::Local;
+                        self.ui.enter_local_config();
+                    }
+                    1 if !storage::MONGO_SUPPORTED => {
+                        self.ui.show_notification(
+                            "This build of quill has no MongoDB support (rebuild with the `mongo` feature)".to_string(),
+                            crate::ui::NotificationLevel::Error,
+                        );
+                    }
+                    1 => {
+                        self.ui.temp_config.storage_type = StorageType::MongoDB;
+                        self.ui.enter_mongodb_config();
+                    }
+                    // The branch and remote are set in the config file
+                    2 => {
+                        self.ui.temp_config.storage_type = StorageType::Git;
+                        self.ui.back_to_home();
+                    }
+                    3 if !storage::REMOTE_SUPPORTED => {
+                        self.ui.show_notification(
+                            "This build of quill has no remote storage support (rebuild with the `remote` feature)".to_string(),
+                            crate::ui::NotificationLevel::Error,
+                        );
+                    }
+                    // The server URL and token are set in the config file
+                    3 => {
+                        self.ui.temp_config.storage_type = StorageType::Remote;
+                        self.ui.back_to_home();
+                    }
+                    _ => {}
+                }
+            }
+            KeyCode::Esc => {
+                self.ui.back_to_home();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_local_config_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                self.ui.start_field_edit();
+            }
+            KeyCode::Char('s') | KeyCode::Char('S') => {
+                self.ui.back_to_home();
+            }
+            KeyCode::Esc => {
+                self.ui.back_to_home();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_local_field_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                // Rejected paths stay in the input to be corrected
+                let mut candidate = self.ui.temp_config.clone();
+                candidate.local_config.path = self.ui.input.text().trim().to_string();
+                if let Err(e) = storage::local::check_path(std::path::Path::new(&candidate.expand_local_path())) {
+                    self.ui.show_notification(format!("Can't keep tasks there: {}", e), crate::ui::NotificationLevel::Error);
+                    return Ok(());
+                }
+                self.ui.finish_input();
+                self.ui.set_current_field_value(candidate.local_config.path);
+                self.ui.connection_test = Some(Ok(Duration::ZERO));
+                self.ui.input_mode = InputMode::ConfigLocal;
+            }
+            KeyCode::Esc => {
+                self.ui.input_mode = InputMode::ConfigLocal;
+                self.ui.input.clear();
+            }
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    async fn handle_mongodb_config_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.ui.mongodb_config_prev();
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.ui.mongodb_config_next();
+            }
+            KeyCode::Enter => {
+                if self.ui.toggle_mongo_field() {
+                    self.ui.connection_test = None;
+                } else {
+                    self.ui.start_field_edit();
+                }
+            }
+            KeyCode::Char('t') | KeyCode::Char('T') => {
+                self.test_connection().await;
+            }
+            KeyCode::Char('r') | KeyCode::Char('R') => {
+                self.ui.reveal_secrets = !self.ui.reveal_secrets;
+            }
+            KeyCode::Char('s') | KeyCode::Char('S') => {
+                self.ui.back_to_home();
+            }
+            KeyCode::Esc => {
+                self.ui.back_to_home();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_mongodb_field_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                if let Err(e) = self.ui.check_current_field_value(self.ui.input.text()) {
+                    self.ui.show_notification(e, crate::ui::NotificationLevel::Error);
+                    return Ok(());
+                }
+                let value = self.ui.finish_input();
+                self.ui.set_current_field_value(value);
+                self.ui.connection_test = None;
+                self.ui.input_mode = InputMode::ConfigMongoDB;
+            }
+            KeyCode::Esc => {
+                self.ui.input_mode = InputMode::ConfigMongoDB;
+                self.ui.input.clear();
+            }
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    fn handle_credentials_mode(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => self.ui.credentials_prev(),
+            KeyCode::Down | KeyCode::Char('j') => self.ui.credentials_next(),
+            KeyCode::Enter if !secret::KEYCHAIN_SUPPORTED => {
+                self.ui.show_notification(
+                    "This build of quill has no keychain support (rebuild with the `keychain` feature)".to_string(),
+                    crate::ui::NotificationLevel::Error,
+                );
+            }
+            KeyCode::Enter => {
+                self.ui.input.clear();
+                self.ui.input_mode = InputMode::ConfigCredentialField;
+            }
+            KeyCode::Esc => self.ui.back_to_home(),
+            _ => {}
+        }
+    }
+
+    /// Stores the typed secret in the keychain and points the config at it.
+    /// The keychain is written right away; the reference with the config.
+    fn handle_credential_field_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        match key {
+            KeyCode::Enter => {
+                let credential = self.ui.selected_credential();
+                let current = credential.value(&self.ui.temp_config);
+                let typed = self.ui.finish_input();
+                self.ui.input_mode = InputMode::ConfigCredentials;
+                let value = if !typed.is_empty() {
+                    typed
+                } else if secret::Source::of(&current) == secret::Source::Plaintext {
+                    current
+                } else {
+                    return;
+                };
+                let name = credential.keychain_name();
+                let stored = secret::keychain_set(name, &value)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|()| credential.set(&mut self.ui.temp_config, secret::keyring_reference(name)));
+                match stored {
+                    Ok(()) => self.ui.show_notification(
+                        format!("{} stored in the keychain; Save & Exit to use it", credential.label()),
+                        crate::ui::NotificationLevel::Success,
+                    ),
+                    Err(e) => self.ui.show_notification(
+                        format!("Couldn't store the {}: {}", credential.label(), e),
+                        crate::ui::NotificationLevel::Error,
+                    ),
+                }
+            }
+            KeyCode::Esc => {
+                self.ui.input_mode = InputMode::ConfigCredentials;
+                self.ui.input.clear();
+            }
+            _ => self.edit_input(key, modifiers),
+        }
+    }
+
+    fn handle_inspector_mode(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.inspector_scroll_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.inspector_scroll_by(-1),
+            KeyCode::PageDown => self.ui.inspector_scroll_by(20),
+            KeyCode::PageUp => self.ui.inspector_scroll_by(-20),
+            KeyCode::Esc | KeyCode::Char('q') => self.ui.cancel_input(),
+            _ => {}
+        }
+    }
+
+    async fn handle_backend_picker_mode(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.backend_picker_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.backend_picker_prev(),
+            KeyCode::Enter => {
+                let choice = self.ui.selected_backend();
+                self.ui.cancel_input();
+                if let Some(backend) = choice {
+                    self.peek_backend(backend).await;
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('B') => self.ui.cancel_input(),
+            _ => {}
+        }
+    }
+
+    /// Browses `backend` read-only in place of the default storage, or goes
+    /// back to the default storage when `backend` is the configured one. The
+    /// saved config is left alone.
+    async fn peek_backend(&mut self, backend: StorageType) {
+        let mut config = self.effective_config().for_context(&self.current_context);
+        if backend == config.storage_type {
+            if let Some(peek) = self.peek.take() {
+                self.storage = peek.default_storage;
+                self.ui.peeking = None;
+                self.reset_list_view();
+                self.ui.show_notification(format!("Back to {} storage", backend.label()), crate::ui::NotificationLevel::Success);
+                // The context may have moved to another rule's repository meanwhile
+                self.apply_storage_rule().await;
+            }
+            return;
+        }
+
+        config.storage_type = backend.clone();
+        let opened = match storage::open(&config).await {
+            Ok(opened) => opened,
+            Err(e) => {
+                self.ui.show_notification(format!("Could not open {}: {}", backend.label(), e), crate::ui::NotificationLevel::Error);
+                return;
+            }
+        };
+        let read_only = Box::new(ReadOnlyStorage::new(opened, backend.label()));
+        let default_storage = std::mem::replace(&mut self.storage, read_only);
+        // Peeking from one backend to another keeps the original default
+        let default_storage = match self.peek.take() {
+            Some(previous) => previous.default_storage,
+            None => default_storage,
+        };
+        self.ui.peeking = Some(backend.label().to_string());
+        self.peek = Some(Peek { backend, default_storage });
+        self.reset_list_view();
+    }
+
+    fn reset_list_view(&mut self) {
+        self.ui.list_state.select(None);
+        self.ui.marked.clear();
+        self.watch_checked = None;
+    }
+
+    fn handle_health_mode(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.inspector_scroll = self.ui.inspector_scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.inspector_scroll = self.ui.inspector_scroll.saturating_sub(1),
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('H') => self.ui.cancel_input(),
+            _ => {}
+        }
+    }
+
+    fn handle_stats_mode(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.stats_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.stats_prev(),
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('s') => self.ui.cancel_input(),
+            _ => {}
+        }
+    }
+
+    async fn handle_overview_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.overview_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.overview_prev(),
+            KeyCode::Char(' ') => {
+                if let Some(Row::Context { context_key, .. } | Row::Task { context_key, .. }) = self.ui.overview_selected() {
+                    let heading = self.ui.overview.toggle(&context_key);
+                    self.ui.overview_state.select(heading);
+                }
+            }
+            KeyCode::Tab => {
+                self.ui.overview.toggle_all();
+                self.ui.overview_state.select(if self.ui.overview.contexts() == 0 { None } else { Some(0) });
+            }
+            KeyCode::Enter => {
+                let (context_key, id) = match self.ui.overview_selected() {
+                    Some(Row::Context { context_key, .. }) => (context_key, None),
+                    Some(Row::Task { context_key, task }) => (context_key, Some(task.id)),
+                    None => return Ok(()),
+                };
+                self.ui.cancel_input();
+                if context_key != self.current_context.context_key() {
+                    self.switch_to_key(&context_key);
+                    self.apply_storage_rule().await;
+                }
+                if let Some(id) = id {
+                    let tasks = self.visible_tasks().await?;
+                    self.ui.list_state.select(tasks.iter().position(|t| t.id == id));
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('O') => self.ui.cancel_input(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Searches every context as the query is typed, and goes to the chosen task.
+    async fn handle_search_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Down => self.ui.search_next(),
+            KeyCode::Up => self.ui.search_prev(),
+            KeyCode::Enter => {
+                let Some((context_key, task)) = self.ui.search_state.selected().and_then(|i| self.ui.search_results.get(i).cloned()) else {
+                    return Ok(());
+                };
+                self.ui.cancel_input();
+                if context_key != self.current_context.context_key() {
+                    self.switch_to_key(&context_key);
+                    self.apply_storage_rule().await;
+                }
+                if task.is_snoozed(chrono::Utc::now()) {
+                    self.ui.show_snoozed = true;
+                }
+                let tasks = self.visible_tasks().await?;
+                match tasks.iter().position(|t| t.id == task.id) {
+                    Some(index) => self.ui.list_state.select(Some(index)),
+                    None => self.ui.show_notification(
+                        "The task is hidden by the status filter".to_string(),
+                        crate::ui::NotificationLevel::Error,
+                    ),
+                }
+            }
+            KeyCode::Esc => self.ui.cancel_input(),
+            _ => {
+                self.edit_input(key, modifiers);
+                match self.storage.search_tasks(self.ui.input.text()).await {
+                    Ok(found) => self.ui.set_search_results(found),
+                    Err(e) => self.ui.show_notification(format!("Search failed: {}", e), crate::ui::NotificationLevel::Error),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Picks task lists of stale branches, previews an action on them and
+    /// applies it once confirmed.
+    async fn handle_cleanup_mode(&mut self, key: KeyCode) -> Result<()> {
+        if let Some((action, _)) = &self.ui.cleanup_preview {
+            let action = *action;
+            match key {
+                KeyCode::Enter => {
+                    let picked = self.ui.cleanup_picked();
+                    self.ui.cancel_input();
+                    let touched = cleanup::apply(self.storage.as_mut(), &picked, action, &self.current_context).await?;
+                    let message = format!("{}d {} task(s) of {} branch(es)", action.label(), touched, picked.len());
+                    self.ui.show_notification(message, crate::ui::NotificationLevel::Success);
+                }
+                KeyCode::Esc => self.ui.cleanup_preview = None,
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        let action = match key {
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.ui.cleanup_next();
+                return Ok(());
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.ui.cleanup_prev();
+                return Ok(());
+            }
+            KeyCode::Char(' ') => {
+                if let Some((_, picked)) = self.ui.cleanup_state.selected().and_then(|i| self.ui.cleanup.get_mut(i)) {
+                    *picked = !*picked;
+                }
+                return Ok(());
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.ui.cancel_input();
+                return Ok(());
+            }
+            KeyCode::Char('a') => cleanup::Action::Archive,
+            KeyCode::Char('m') => cleanup::Action::Migrate,
+            KeyCode::Char('x') => cleanup::Action::Delete,
+            _ => return Ok(()),
+        };
+        let picked = self.ui.cleanup_picked();
+        if !picked.is_empty() {
+            self.ui.cleanup_preview = Some((action, cleanup::preview(&picked, action, &self.current_context)));
+        }
+        Ok(())
+    }
+
+    async fn handle_trash_mode(&mut self, key: KeyCode) -> Result<()> {
+        let context_key = self.current_context.context_key();
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.trash_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.trash_prev(),
+            KeyCode::Enter | KeyCode::Char('r') => {
+                if let Some(id) = self.ui.selected_trash().map(|d| d.task.id) {
+                    if let Some(task) = self.storage.restore_deleted(&context_key, id).await? {
+                        self.ui.show_notification(format!("Restored task: {}", task.text), crate::ui::NotificationLevel::Success);
+                    }
+                    let trash = self.storage.list_deleted(&context_key).await?;
+                    self.ui.open_trash(trash);
+                }
+            }
+            KeyCode::Char('x') | KeyCode::Delete => {
+                if let Some(id) = self.ui.selected_trash().map(|d| d.task.id) {
+                    self.storage.purge_deleted(&context_key, id).await?;
+                    let trash = self.storage.list_deleted(&context_key).await?;
+                    self.ui.open_trash(trash);
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('T') => self.ui.cancel_input(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Switches the list to a saved view, or adds and deletes views, which
+    /// are kept in `[views]` of the config.
+    async fn handle_view_picker_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.view_picker_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.view_picker_prev(),
+            KeyCode::Enter => {
+                let view = self.ui.picked_view().cloned();
+                if let Some(Err(e)) = view.as_ref().map(|v| &v.filter) {
+                    self.ui.show_notification(format!("Can't use this view: {}", e), crate::ui::NotificationLevel::Error);
+                    return Ok(());
+                }
+                self.ui.cancel_input();
+                self.ui.active_view = view.map(|v| v.name);
+                self.ui.list_state.select(None);
+            }
+            KeyCode::Char('n') => self.ui.start_adding_view(),
+            KeyCode::Char('x') => {
+                if let Some(name) = self.ui.picked_view().map(|v| v.name.clone()) {
+                    self.config.views.remove(&name);
+                    self.config.save()?;
+                    self.apply_display_config(&self.effective_config());
+                    self.ui.view_picker_prev();
+                    self.ui.show_notification(format!("Deleted view '{}'", name), crate::ui::NotificationLevel::Success);
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('V') => self.ui.cancel_input(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Saves `name = expression` as a view and switches to it.
+    async fn handle_add_view_mode(&mut self, key: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        match key {
+            KeyCode::Enter => {
+                let input = self.ui.input.text().to_string();
+                let Some((name, expression)) = input.split_once('=').map(|(n, e)| (n.trim(), e.trim())).filter(|(n, _)| !n.is_empty()) else {
+                    self.ui.show_notification("Write it as name = expression".to_string(), crate::ui::NotificationLevel::Error);
+                    return Ok(());
+                };
+                // Keep the prompt open so the expression can be corrected
+                if let Err(e) = views::Filter::parse(expression, &self.ui.date_parser) {
+                    self.ui.show_notification(format!("Invalid view: {}", e), crate::ui::NotificationLevel::Error);
+                    return Ok(());
+                }
+                self.config.views.insert(name.to_string(), expression.to_string());
+                self.config.save()?;
+                self.apply_display_config(&self.effective_config());
+                self.ui.cancel_input();
+                self.ui.active_view = Some(name.to_string());
+                self.ui.list_state.select(None);
+                self.ui.show_notification(format!("Saved view '{}'", name), crate::ui::NotificationLevel::Success);
+            }
+            KeyCode::Esc => self.ui.open_view_picker(),
+            _ => self.edit_input(key, modifiers),
+        }
+        Ok(())
+    }
+
+    async fn handle_template_picker_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.template_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.template_prev(),
+            KeyCode::Enter => {
+                self.ui.cancel_input();
+                if let Some(template) = self.ui.selected_template().cloned() {
+                    let before = self.storage.get_tasks(&self.current_context.context_key()).await?;
+                    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+                    let texts = template.render(&self.current_context, &date);
+                    let count = texts.len();
+                    for text in texts {
+                        self.add_task(text).await?;
+                    }
+                    self.ui.show_notification(
+                        format!("Added {} tasks from '{}'", count, template.name),
+                        crate::ui::NotificationLevel::Success,
+                    );
+                    self.show_diff(&format!("template '{}'", template.name), before).await?;
+                }
+            }
+            KeyCode::Esc => self.ui.cancel_input(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_assignee_picker_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.assignee_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.assignee_prev(),
+            KeyCode::Enter => {
+                self.ui.cancel_input();
+                let Some(assignee) = self.ui.selected_assignee() else {
+                    return Ok(());
+                };
+                let context_key = self.current_context.context_key();
+                let tasks = self.visible_tasks().await?;
+                let selected = self.ui.list_state.selected().and_then(|i| tasks.get(i));
+                let targets = self.target_tasks(&tasks, selected);
+                for mut task in targets.clone() {
+                    task.assignee = assignee.clone();
+                    self.storage.update_task(&context_key, task).await?;
+                }
+                let message = match &assignee {
+                    Some(email) => format!("Assigned {} task(s) to {}", targets.len(), email),
+                    None => format!("Unassigned {} task(s)", targets.len()),
+                };
+                self.ui.show_notification(message, crate::ui::NotificationLevel::Success);
+            }
+            KeyCode::Esc => self.ui.cancel_input(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Looks for todo files and Taskwarrior tasks and lists them for import.
+    fn offer_import(&mut self) {
+        self.import_offer = import::scan(&self.effective_config());
+        self.ui.open_import_picker(self.import_offer.iter().map(|source| source.summary()).collect());
+    }
+
+    async fn handle_import_picker_mode(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.ui.import_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.ui.import_prev(),
+            KeyCode::Char(' ') => self.ui.toggle_import_source(),
+            KeyCode::Enter => {
+                let ticked: Vec<bool> = self.ui.import_sources.iter().map(|(_, ticked)| *ticked).collect();
+                let sources: Vec<import::Source> = std::mem::take(&mut self.import_offer)
+                    .into_iter()
+                    .zip(ticked)
+                    .filter_map(|(source, ticked)| ticked.then_some(source))
+                    .collect();
+                self.ui.cancel_input();
+                match self.import_tasks(&sources).await {
+                    Ok(count) => self.ui.show_notification(format!("Imported {} task(s)", count), crate::ui::NotificationLevel::Success),
+                    Err(e) => self.ui.show_notification(format!("Import failed: {}", e), crate::ui::NotificationLevel::Error),
+                }
+            }
+            KeyCode::Esc => {
+                self.import_offer.clear();
+                self.ui.cancel_input();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Adds the sources' tasks to their contexts, skipping any whose text is
+    /// already there so importing twice doesn't duplicate them.
+    async fn import_tasks(&mut self, sources: &[import::Source]) -> Result<usize> {
+        let mut imported = 0;
+        for (source, task) in sources.iter().flat_map(|source| source.tasks.iter().map(move |task| (source, task))) {
+            let existing = self.storage.get_tasks(&task.context_key).await?;
+            if existing.iter().any(|t| t.text == task.text) {
+                continue;
+            }
+            let id = self.storage.add_task(&task.context_key, task.text.clone()).await?;
+            if let Some(mut added) = self.storage.get_tasks(&task.context_key).await?.into_iter().find(|t| t.id == id) {
+                added.priority = task.priority;
+                added.tags = task.tags.clone();
+                added.due = task.due;
+                added.anchor = source.file.clone().map(|file| Anchor { file, line: task.line });
+                if task.completed {
+                    added.set_status(TaskStatus::Completed);
+                }
+                self.storage.update_task(&task.context_key, added).await?;
+            }
+            imported += 1;
+        }
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Script;
+    use crossterm::event::KeyEvent;
+    use ratatui::backend::TestBackend;
+
+    /// Drives an `App` over in-memory storage with scripted keys, rendering
+    /// to a 100x30 test terminal.
+    struct Harness {
+        app: App,
+        terminal: Terminal<TestBackend>,
+    }
+
+    impl Harness {
+        async fn new(config: AppConfig, tasks: &[&str]) -> Self {
+            let context = GitContext { org: "acme".to_string(), repo: "web".to_string(), branch: "main".to_string() };
+            let mut storage = LocalTaskStorage::in_memory();
+            for text in tasks {
+                storage.add_task(&context.context_key(), text.to_string()).await.unwrap();
+            }
+            // Nothing sends context changes, as no git watcher runs
+            let (_, context_updates) = mpsc::unbounded_channel();
+            let mut app = App::with_storage(config.clone(), Box::new(storage), context, context_updates, Plugins::none());
+            app.apply_display_config(&config);
+            let mut harness = Self { app, terminal: Terminal::new(TestBackend::new(100, 30)).unwrap() };
+            harness.send([]).await;
+            harness
+        }
+
+        /// Presses each character of `keys` in turn; `\r` is Enter and `\x1b` Esc.
+        async fn keys(&mut self, keys: &str) {
+            let code = |c| match c {
+                '\r' => KeyCode::Enter,
+                '\x1b' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+            self.send(keys.chars().map(|c| Event::Key(KeyEvent::new(code(c), KeyModifiers::NONE)))).await;
+        }
+
+        /// Runs the app through `events`, then until it has drawn the result.
+        async fn send(&mut self, events: impl IntoIterator<Item = Event>) {
+            let mut script = Script(events.into_iter().collect());
+            self.app.run_app(&mut self.terminal, &mut script).await.unwrap();
+        }
+
+        /// The rendered screen, one line per row.
+        fn screen(&self) -> String {
+            let buffer = self.terminal.backend().buffer();
+            buffer
+                .content
+                .chunks(buffer.area.width as usize)
+                .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+
+        async fn task_texts(&self) -> Vec<String> {
+            let tasks = self.app.storage.get_tasks("acme:web:main").await.unwrap();
+            tasks.into_iter().map(|t| t.text).collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_adding_a_task() {
+        let mut harness = Harness::new(AppConfig::default(), &["Write docs"]).await;
+        assert!(harness.screen().contains("Write docs"));
+
+        harness.keys("aShip it").await;
+        assert!(harness.app.ui.input_mode == InputMode::Adding);
+        harness.keys("\r").await;
+        assert!(harness.app.ui.input_mode == InputMode::Normal);
+        assert!(harness.screen().contains("Ship it"));
+        assert_eq!(harness.task_texts().await, vec!["Write docs", "Ship it"]);
+
+        // Esc drops a half-typed task
+        harness.keys("aNever mind\x1b").await;
+        assert!(harness.app.ui.input_mode == InputMode::Normal);
+        assert_eq!(harness.task_texts().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_config_screens_mask_the_mongo_password() {
+        let mut config = AppConfig::default();
+        config.mongo_config.connection_string = "mongodb://app:hunter2@db:27017".to_string();
+        let mut harness = Harness::new(config, &[]).await;
+
+        harness.keys("c").await;
+        assert!(harness.app.ui.input_mode == InputMode::ConfigHome);
+        harness.keys("j\r").await;
+        assert!(harness.app.ui.input_mode == InputMode::ConfigStorageSelection);
+        harness.keys("j\r").await;
+        assert!(harness.app.ui.input_mode == InputMode::ConfigMongoDB);
+        let screen = harness.screen();
+        assert!(screen.contains("mongodb://app:••••••••@db:27017"), "{}", screen);
+        assert!(!screen.contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn test_moves_follow_the_order_on_screen() {
+        let mut harness = Harness::new(AppConfig::default(), &["One", "Pinned", "Two"]).await;
+        let context = "acme:web:main";
+        let mut pinned = harness.app.storage.get_tasks(context).await.unwrap().remove(1);
+        pinned.pinned = true;
+        harness.app.storage.update_task(context, pinned).await.unwrap();
+        let ctrl = |code| Event::Key(KeyEvent::new(code, KeyModifiers::CONTROL));
+
+        // Shown as Pinned, One, Two: Two moves past One, not past Pinned
+        harness.send([Event::Key(KeyEvent::new(KeyCode::End, KeyModifiers::NONE))]).await;
+        assert_eq!(harness.app.ui.list_state.selected(), Some(2));
+        harness.send([ctrl(KeyCode::Up)]).await;
+        assert_eq!(harness.task_texts().await, vec!["Two", "One", "Pinned"]);
+        assert_eq!(harness.app.ui.list_state.selected(), Some(1));
+
+        // Moving it up again would cross the pinned task
+        harness.send([ctrl(KeyCode::Up)]).await;
+        assert_eq!(harness.task_texts().await, vec!["Two", "One", "Pinned"]);
+        assert_eq!(harness.app.ui.list_state.selected(), Some(1));
+        let notification = harness.app.ui.notification.as_ref().map(|n| n.message.as_str());
+        assert!(notification.is_some_and(|message| message.contains("unpin")), "{:?}", notification);
+    }
+
+    #[tokio::test]
+    async fn test_palette_runs_the_picked_command() {
+        let mut harness = Harness::new(AppConfig::default(), &["Write docs"]).await;
+        harness.keys(":show stats").await;
+        assert!(harness.app.ui.input_mode == InputMode::Palette);
+        assert!(harness.screen().contains("Show stats"));
+
+        harness.keys("\r").await;
+        assert!(harness.app.ui.input_mode == InputMode::Stats);
+        assert!(harness.screen().contains("acme:web:main"));
+    }
+
+    #[tokio::test]
+    async fn test_snoozes_run_out_in_other_contexts() {
+        let mut harness = Harness::new(AppConfig::default(), &[]).await;
+        let other = "acme:web:feature";
+        let id = harness.app.storage.add_task(other, "Follow up".to_string()).await.unwrap();
+        let mut task = harness.app.storage.get_tasks(other).await.unwrap().remove(0);
+        task.snoozed_until = Some((chrono::Utc::now() - chrono::Duration::minutes(1)).to_rfc3339());
+        harness.app.storage.update_task(other, task).await.unwrap();
+
+        // Other contexts wait out the interval since the last check
+        harness.send([]).await;
+        assert!(harness.app.storage.get_tasks(other).await.unwrap()[0].snoozed_until.is_some());
+        harness.app.snooze_checked = None;
+        harness.send([]).await;
+        let task = harness.app.storage.get_tasks(other).await.unwrap().remove(0);
+        assert_eq!((task.id, task.snoozed_until), (id, None));
+        let notification = harness.app.ui.notification.as_ref().map(|n| n.message.as_str());
+        assert_eq!(notification, Some("⏰ 'Follow up' is back from snooze in acme:web:feature"));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::storage::{Task, TaskStatus};
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+const HEADER: &str = "\
+# Edit the tasks of {context}, then save and quit to apply.
+#
+# <status> <id> <text> [#tag ...]
+#
+#   status: todo | doing | done
+#   Reorder lines to reorder tasks, delete a line to delete its task.
+#   Add a line without an id (\"todo Write docs #docs\") to add a task.
+#   Lines starting with # are ignored; an empty buffer changes nothing.
+";
+
+/// A line of the edited buffer, in buffer order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkEntry {
+    /// An existing task, with its edited fields applied.
+    Existing(Box<Task>),
+    /// A line without a known id.
+    New { status: TaskStatus, text: String, tags: Vec<String> },
+}
+
+/// The result of comparing an edited buffer against the original tasks.
+#[derive(Debug, Default, PartialEq)]
+pub struct BulkEdit {
+    pub entries: Vec<BulkEntry>,
+    /// Ids of original tasks that no longer appear in the buffer.
+    pub removed: Vec<usize>,
+}
+
+impl BulkEdit {
+    /// Existing tasks whose status, text or tags changed.
+    pub fn updated<'a>(&'a self, original: &'a [Task]) -> impl Iterator<Item = &'a Task> {
+        self.entries.iter().filter_map(move |entry| match entry {
+            BulkEntry::Existing(task) => original
+                .iter()
+                .find(|t| t.id == task.id)
+                .filter(|t| t.status != task.status || t.text != task.text || t.tags != task.tags)
+                .map(|_| task.as_ref()),
+            BulkEntry::New { .. } => None,
+        })
+    }
+
+    /// Existing tasks the edit marks done that weren't before.
+    pub fn completed(&self, original: &[Task]) -> usize {
+        self.updated(original)
+            .filter(|task| task.is_completed() && original.iter().any(|t| t.id == task.id && !t.is_completed()))
+            .count()
+    }
+
+    pub fn added(&self) -> usize {
+        self.entries.iter().filter(|e| matches!(e, BulkEntry::New { .. })).count()
+    }
+
+    /// Whether the existing tasks kept in the buffer appear in a different order.
+    pub fn reordered(&self, original: &[Task]) -> bool {
+        let kept: Vec<usize> = self
+            .entries
+            .iter()
+            .filter_map(|e| match e {
+                BulkEntry::Existing(task) => Some(task.id),
+                BulkEntry::New { .. } => None,
+            })
+            .collect();
+        let before: Vec<usize> = original.iter().map(|t| t.id).filter(|id| kept.contains(id)).collect();
+        kept != before
+    }
+}
+
+/// Renders tasks as an editable buffer, one task per line.
+pub fn to_buffer(context_key: &str, tasks: &[Task]) -> String {
+    let mut buffer = HEADER.replace("{context}", context_key);
+    buffer.push('\n');
+    for task in tasks {
+        buffer.push_str(&format!("{} {} {}", status_keyword(&task.status), task.id, task.text));
+        for tag in &task.tags {
+            buffer.push_str(&format!(" #{}", tag));
+        }
+        buffer.push('\n');
+    }
+    buffer
+}
+
+/// Parses an edited buffer against the tasks it was rendered from.
+///
+/// A buffer with no task lines at all is treated as an aborted edit and
+/// yields `None`, like an emptied rebase todo list.
+pub fn parse_buffer(buffer: &str, original: &[Task]) -> Result<Option<BulkEdit>> {
+    let mut edit = BulkEdit::default();
+    let mut seen = HashSet::new();
+
+    for (index, line) in buffer.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (status, rest) = match line.split_once(char::is_whitespace).and_then(|(word, rest)| Some((parse_status(word)?, rest))) {
+            Some((status, rest)) => (status, rest.trim_start()),
+            _ if parse_status(line).is_some() => return Err(anyhow!("line {}: missing task text", index + 1)),
+            // Bare text is a new task
+            _ => (TaskStatus::NotStarted, line),
+        };
+
+        let existing = rest
+            .split_once(char::is_whitespace)
+            .and_then(|(word, text)| Some((word.parse::<usize>().ok()?, text.trim_start())))
+            .and_then(|(id, text)| original.iter().find(|t| t.id == id).map(|t| (t, text)));
+
+        let (text, tags) = match existing {
+            Some((_, text)) => split_tags(text),
+            None => split_tags(rest),
+        };
+        if text.is_empty() {
+            return Err(anyhow!("line {}: missing task text", index + 1));
+        }
+
+        match existing {
+            Some((task, _)) => {
+                if !seen.insert(task.id) {
+                    return Err(anyhow!("line {}: task {} appears more than once", index + 1, task.id));
+                }
+                let mut task = task.clone();
+                task.set_status(status);
+                task.text = text;
+                task.tags = tags;
+                edit.entries.push(BulkEntry::Existing(Box::new(task)));
+            }
+            None => edit.entries.push(BulkEntry::New { status, text, tags }),
+        }
+    }
+
+    if edit.entries.is_empty() {
+        return Ok(None);
+    }
+
+    edit.removed = original.iter().map(|t| t.id).filter(|id| !seen.contains(id)).collect();
+    Ok(Some(edit))
+}
+
+fn status_keyword(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::NotStarted => "todo",
+        TaskStatus::InProgress => "doing",
+        TaskStatus::Completed => "done",
+    }
+}
+
+fn parse_status(word: &str) -> Option<TaskStatus> {
+    match word {
+        "todo" => Some(TaskStatus::NotStarted),
+        "doing" => Some(TaskStatus::InProgress),
+        "done" => Some(TaskStatus::Completed),
+        _ => None,
+    }
+}
+
+/// Splits trailing `#tag` words off a line of text.
+fn split_tags(text: &str) -> (String, Vec<String>) {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    let mut tags = Vec::new();
+    while let Some(word) = words.last() {
+        match word.strip_prefix('#') {
+            Some(tag) if !tag.is_empty() => {
+                tags.insert(0, tag.to_string());
+                words.pop();
+            }
+            _ => break,
+        }
+    }
+    (words.join(" "), tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tasks() -> Vec<Task> {
+        let mut first = Task::new(1, "Fix login".to_string());
+        first.tags = vec!["auth".to_string()];
+        let mut second = Task::new(2, "Write docs".to_string());
+        second.status = TaskStatus::InProgress;
+        vec![first, second, Task::new(3, "Release".to_string())]
+    }
+
+    #[test]
+    fn test_unchanged_buffer_round_trips() {
+        let original = tasks();
+        let buffer = to_buffer("acme:widgets:main", &original);
+        assert!(buffer.contains("todo 1 Fix login #auth\n"));
+        assert!(buffer.contains("doing 2 Write docs\n"));
+
+        let edit = parse_buffer(&buffer, &original).unwrap().unwrap();
+        assert!(edit.removed.is_empty());
+        assert_eq!(edit.added(), 0);
+        assert_eq!(edit.updated(&original).count(), 0);
+        assert!(!edit.reordered(&original));
+    }
+
+    #[test]
+    fn test_rename_delete_reorder_and_add() {
+        let original = tasks();
+        let buffer = "done 3 Release v1.0 #release\ntodo 1 Fix login\nNew idea\ndoing Pair on review #team\n";
+
+        let edit = parse_buffer(buffer, &original).unwrap().unwrap();
+        assert_eq!(edit.removed, vec![2]);
+        assert_eq!(edit.added(), 2);
+        assert!(edit.reordered(&original));
+
+        let updated: Vec<&Task> = edit.updated(&original).collect();
+        assert_eq!(updated.len(), 2);
+        assert_eq!(updated[0].text, "Release v1.0");
+        assert_eq!(updated[0].status, TaskStatus::Completed);
+        assert_eq!(updated[0].tags, vec!["release"]);
+        assert!(updated[1].tags.is_empty());
+        assert_eq!(edit.completed(&original), 1);
+
+        assert_eq!(
+            edit.entries[3],
+            BulkEntry::New { status: TaskStatus::InProgress, text: "Pair on review".to_string(), tags: vec!["team".to_string()] }
+        );
+    }
+
+    #[test]
+    fn test_unknown_id_is_part_of_new_task_text() {
+        let edit = parse_buffer("todo 42 answers\n", &tasks()).unwrap().unwrap();
+        assert_eq!(
+            edit.entries[0],
+            BulkEntry::New { status: TaskStatus::NotStarted, text: "42 answers".to_string(), tags: vec![] }
+        );
+    }
+
+    #[test]
+    fn test_empty_buffer_aborts_and_duplicates_error() {
+        assert!(parse_buffer("# nothing here\n\n", &tasks()).unwrap().is_none());
+        assert!(parse_buffer("todo 1 a\ntodo 1 b\n", &tasks()).is_err());
+        assert!(parse_buffer("todo 1 #only-tags\n", &tasks()).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::config::CarryPolicy;
+use crate::git::GitContext;
+use crate::storage::{Task, TaskStorage};
+use anyhow::Result;
+
+/// Whether switching from `from` to `to` is going to a branch of the same
+/// repository whose list hasn't been used yet, so tasks may be carried into it.
+pub async fn is_fresh_branch(storage: &dyn TaskStorage, from: &GitContext, to: &GitContext) -> Result<bool> {
+    if from.org != to.org || from.repo != to.repo || from.branch == to.branch {
+        return Ok(false);
+    }
+    let context_key = to.context_key();
+    Ok(storage.get_tasks(&context_key).await?.is_empty()
+        && storage.list_deleted(&context_key).await?.is_empty()
+        && storage.list_tombstones(&context_key).await?.is_empty())
+}
+
+/// Carries the unfinished tasks of `from` into `to` as `policy` says and
+/// returns how many were carried.
+pub async fn carry(storage: &mut dyn TaskStorage, policy: CarryPolicy, from: &GitContext, to: &GitContext) -> Result<usize> {
+    let unfinished: Vec<Task> = storage
+        .get_tasks(&from.context_key())
+        .await?
+        .into_iter()
+        .filter(|t| !t.is_completed())
+        .collect();
+    if unfinished.is_empty() {
+        return Ok(0);
+    }
+    let to_key = to.context_key();
+    match policy {
+        CarryPolicy::Off => return Ok(0),
+        CarryPolicy::Copy => {
+            let mut new_ids = Vec::new();
+            for task in &unfinished {
+                new_ids.push((task.id, storage.add_task(&to_key, task.text.clone()).await?));
+            }
+            let new_id = |old: usize| new_ids.iter().find(|(id, _)| *id == old).map(|(_, new)| *new);
+            let placed = storage.get_tasks(&to_key).await?;
+            for task in &unfinished {
+                let Some(placeholder) = new_id(task.id).and_then(|id| placed.iter().find(|t| t.id == id)) else {
+                    continue;
+                };
+                // A copy is a task of its own: new identity, no tracker issue
+                let copy = Task {
+                    id: placeholder.id,
+                    uid: placeholder.uid,
+                    created_at: placeholder.created_at.clone(),
+                    order: placeholder.order,
+                    parent: task.parent.and_then(new_id),
+                    issue: None,
+                    ..task.clone()
+                };
+                storage.update_task(&to_key, copy).await?;
+            }
+        }
+        CarryPolicy::Reference => {
+            let id = storage.add_task(&to_key, format!("{} unfinished task(s) on {}", unfinished.len(), from.branch)).await?;
+            if let Some(mut task) = storage.get_tasks(&to_key).await?.into_iter().find(|t| t.id == id) {
+                task.notes = unfinished.iter().map(|t| format!("- {}", t.text)).collect::<Vec<_>>().join("\n");
+                storage.update_task(&to_key, task).await?;
+            }
+        }
+    }
+    Ok(unfinished.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{local::LocalTaskStorage, TaskStatus};
+
+    fn branch(name: &str) -> GitContext {
+        GitContext { org: "acme".to_string(), repo: "web".to_string(), branch: name.to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_carry_copies_or_references_unfinished_tasks() {
+        let mut storage = LocalTaskStorage::in_memory();
+        let main = branch("main");
+        let parent = storage.add_task(&main.context_key(), "Migrate auth".to_string()).await.unwrap();
+        storage.add_task(&main.context_key(), "Drop sessions".to_string()).await.unwrap();
+        let done = storage.add_task(&main.context_key(), "Release".to_string()).await.unwrap();
+        storage.set_task_status(&main.context_key(), done, TaskStatus::Completed).await.unwrap();
+        let mut tasks = storage.get_tasks(&main.context_key()).await.unwrap();
+        tasks[1].parent = Some(parent);
+        tasks[1].tags = vec!["auth".to_string()];
+        storage.update_task(&main.context_key(), tasks[1].clone()).await.unwrap();
+
+        let feature = branch("feature/login");
+        assert!(is_fresh_branch(&storage, &main, &feature).await.unwrap());
+        assert_eq!(carry(&mut storage, CarryPolicy::Copy, &main, &feature).await.unwrap(), 2);
+        assert!(!is_fresh_branch(&storage, &main, &feature).await.unwrap());
+        let copied = storage.get_tasks(&feature.context_key()).await.unwrap();
+        assert_eq!(copied.iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["Migrate auth", "Drop sessions"]);
+        assert_eq!(copied[1].parent, Some(copied[0].id));
+        assert_eq!(copied[1].tags, vec!["auth".to_string()]);
+        assert_ne!(copied[0].uid, tasks[0].uid);
+        // The parent branch keeps its tasks
+        assert_eq!(storage.get_tasks(&main.context_key()).await.unwrap().len(), 3);
+
+        let other = branch("feature/signup");
+        assert_eq!(carry(&mut storage, CarryPolicy::Reference, &main, &other).await.unwrap(), 2);
+        let referenced = storage.get_tasks(&other.context_key()).await.unwrap();
+        assert_eq!(referenced.len(), 1);
+        assert_eq!(referenced[0].text, "2 unfinished task(s) on main");
+        assert_eq!(referenced[0].notes, "- Migrate auth\n- Drop sessions");
+    }
+}
@@ -0,0 +1,140 @@
+use crate::git::{self, BranchState, GitContext};
+use crate::storage::TaskStorage;
+use anyhow::Result;
+use std::path::Path;
+
+/// Branch prefix archived task lists are kept under, as `archive/<branch>`.
+pub const ARCHIVE_PREFIX: &str = "archive/";
+
+/// A task list of the current repository whose branch is gone or merged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub context_key: String,
+    pub branch: String,
+    pub state: BranchState,
+    pub open: usize,
+    pub total: usize,
+}
+
+/// What the cleanup wizard does with the task lists picked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Keep the tasks under `archive/<branch>`, out of the way but browsable.
+    Archive,
+    /// Move the tasks into the current context.
+    Migrate,
+    /// Delete the tasks for good.
+    Delete,
+}
+
+impl Action {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Archive => "Archive",
+            Action::Migrate => "Migrate",
+            Action::Delete => "Delete",
+        }
+    }
+}
+
+/// Where archiving keeps the tasks of `context_key`.
+pub fn archive_context(context_key: &str) -> String {
+    let mut parts = context_key.splitn(3, ':');
+    let (org, repo, branch) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    format!("{}:{}:{}{}", org, repo, ARCHIVE_PREFIX, branch)
+}
+
+/// Task lists of `current`'s repository, found in `dir`, whose branch no
+/// longer exists or was merged. Archived lists and the current one are left out.
+pub async fn find_candidates(storage: &dyn TaskStorage, current: &GitContext, dir: &Path) -> Result<Vec<Candidate>> {
+    let prefix = format!("{}:{}:", current.org, current.repo);
+    let lists: Vec<(String, String, usize, usize)> = storage
+        .all_task_counts()
+        .await?
+        .into_iter()
+        .filter_map(|(context_key, counts)| {
+            let branch = context_key.strip_prefix(&prefix)?.to_string();
+            Some((context_key, branch, counts.open(), counts.total()))
+        })
+        .filter(|(_, branch, _, _)| *branch != current.branch && branch != "*" && !branch.starts_with(ARCHIVE_PREFIX))
+        .collect();
+
+    let branches: Vec<String> = lists.iter().map(|(_, branch, _, _)| branch.clone()).collect();
+    let stale = git::stale_branches(dir, &branches)?;
+    Ok(lists
+        .into_iter()
+        .filter_map(|(context_key, branch, open, total)| {
+            let state = stale.iter().find(|(name, _)| *name == branch)?.1;
+            Some(Candidate { context_key, branch, state, open, total })
+        })
+        .collect())
+}
+
+/// What `action` would do to each of `candidates`, without doing it.
+pub fn preview(candidates: &[Candidate], action: Action, current: &GitContext) -> Vec<String> {
+    candidates
+        .iter()
+        .map(|c| match action {
+            Action::Archive => format!("Move {} task(s) from {} to {}", c.total, c.context_key, archive_context(&c.context_key)),
+            Action::Migrate => format!("Move {} task(s) from {} to {}", c.total, c.context_key, current.context_key()),
+            Action::Delete => format!("Delete {} task(s) ({} open) in {} for good", c.total, c.open, c.context_key),
+        })
+        .collect()
+}
+
+/// Applies `action` to `candidates`, returning how many tasks it touched.
+pub async fn apply(storage: &mut dyn TaskStorage, candidates: &[Candidate], action: Action, current: &GitContext) -> Result<usize> {
+    let mut touched = 0;
+    for candidate in candidates {
+        touched += match action {
+            Action::Archive => storage.move_context_tasks(&candidate.context_key, &archive_context(&candidate.context_key)).await?,
+            Action::Migrate => storage.move_context_tasks(&candidate.context_key, &current.context_key()).await?,
+            Action::Delete => storage.delete_context(&candidate.context_key).await?,
+        };
+    }
+    Ok(touched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::local::LocalTaskStorage;
+
+    #[tokio::test]
+    async fn test_apply_archives_migrates_and_deletes() {
+        let mut storage = LocalTaskStorage::in_memory();
+        let current = GitContext { org: "acme".to_string(), repo: "web".to_string(), branch: "main".to_string() };
+        storage.add_task("acme:web:main", "Release".to_string()).await.unwrap();
+        for branch in ["old", "merged", "dead"] {
+            let context_key = format!("acme:web:{}", branch);
+            let parent = storage.add_task(&context_key, format!("{} work", branch)).await.unwrap();
+            storage.add_task(&context_key, "Subtask".to_string()).await.unwrap();
+            let mut tasks = storage.get_tasks(&context_key).await.unwrap();
+            tasks[1].parent = Some(parent);
+            storage.update_task(&context_key, tasks[1].clone()).await.unwrap();
+        }
+        let candidate = |branch: &str| Candidate {
+            context_key: format!("acme:web:{}", branch),
+            branch: branch.to_string(),
+            state: BranchState::Missing,
+            open: 2,
+            total: 2,
+        };
+
+        let archived = [candidate("old")];
+        assert_eq!(preview(&archived, Action::Archive, &current), vec!["Move 2 task(s) from acme:web:old to acme:web:archive/old"]);
+        assert_eq!(apply(&mut storage, &archived, Action::Archive, &current).await.unwrap(), 2);
+        assert!(storage.get_tasks("acme:web:old").await.unwrap().is_empty());
+        assert_eq!(storage.get_tasks("acme:web:archive/old").await.unwrap().len(), 2);
+
+        apply(&mut storage, &[candidate("merged")], Action::Migrate, &current).await.unwrap();
+        let here = storage.get_tasks("acme:web:main").await.unwrap();
+        assert_eq!(here.iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["Release", "merged work", "Subtask"]);
+        assert_eq!(here[2].parent, Some(here[1].id));
+
+        apply(&mut storage, &[candidate("dead")], Action::Delete, &current).await.unwrap();
+        assert!(storage.get_tasks("acme:web:dead").await.unwrap().is_empty());
+        assert!(storage.list_deleted("acme:web:dead").await.unwrap().is_empty());
+        assert_eq!(storage.list_tombstones("acme:web:dead").await.unwrap().len(), 2);
+    }
+}
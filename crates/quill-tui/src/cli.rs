@@ -0,0 +1,813 @@
+use crate::{config::{AppConfig, StorageType}, serve, shared::SharedConfig, dates::{self, DateParser}, events, external_editor, quickadd::QuickAdd, ics, git::{self, GitContext}, journal::Journal, prompt, state::ViewState, storage::{self, git_branch, Priority, Task, TaskStatus}, summary, views::{self, Filter}};
+use serde::Serialize;
+use unicode_width::UnicodeWidthStr;
+use anyhow::{anyhow, Result};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// Marker used to recognise hooks written by `quill hook install`.
+const HOOK_MARKER: &str = "# Installed by quill";
+
+const PRE_PUSH_HOOK: &str = r#"#!/bin/sh
+# Installed by quill: refuses to push branches that still have
+# unfinished must-finish tasks. Remove this file to disable.
+while read local_ref local_sha remote_ref remote_sha; do
+    case "$local_ref" in
+        refs/heads/*)
+            quill check --branch "${local_ref#refs/heads/}" || exit 1
+            ;;
+    esac
+done
+exit 0
+"#;
+
+#[derive(Parser)]
+#[command(name = "quill", version, about = "A Git-context-aware task manager")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Also log debug detail to ~/.quill/logs/quill.log
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+    /// Save the session's key presses and mouse events, with their timing, to FILE
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// Play back a session saved with --record; pressing a key hands control back
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Add a task to the current context, with quick-add syntax (`!high #tag @tomorrow`)
+    Add {
+        /// The task, e.g. "Renew the TLS certificate @fri"
+        #[arg(required = true, num_args = 1..)]
+        text: Vec<String>,
+        /// Capture it in the inbox instead, to triage into a repository later
+        #[arg(long)]
+        inbox: bool,
+    },
+    /// Manage git hooks installed by quill
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+    /// Exit non-zero if the context still has unfinished must-finish tasks
+    Check {
+        /// Check this branch's context instead of the current branch
+        #[arg(long)]
+        branch: Option<String>,
+    },
+    /// Print the tasks of the current context (or every context) for scripts and fzf
+    List {
+        /// Filter expression, as in saved views, e.g. "priority:high and open", or a view's name
+        #[arg(long, short)]
+        query: Option<String>,
+        /// List every context rather than the current one; narrow with `context:"acme:*"` in the query
+        #[arg(long)]
+        all: bool,
+        #[arg(long, value_enum, default_value_t = ListFormat::Table)]
+        format: ListFormat,
+        /// Print JSON, an object per task with its context key
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Print open tasks one per line for fzf, or act on the lines fzf picked
+    ///
+    /// `quill pick | fzf | quill pick done`
+    Pick {
+        /// What to do with the task lines read from stdin; without one, print the open tasks
+        #[arg(value_enum)]
+        action: Option<PickAction>,
+        /// Pick from every context rather than the current one
+        #[arg(long)]
+        all: bool,
+        /// Filter expression, as in `quill list --query`
+        #[arg(long, short)]
+        query: Option<String>,
+    },
+    /// Print a shell completion script, e.g. `quill completions zsh > ~/.zfunc/_quill`
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print a one-line summary of the current context for shell prompts and tmux
+    Prompt {
+        /// Format string, e.g. "{repo} {bar} {percent}%" (defaults to `prompt_format` in config)
+        #[arg(long)]
+        format: Option<String>,
+        /// Number of cells in the {bar} progress bar
+        #[arg(long, default_value_t = 3)]
+        bar_width: usize,
+    },
+    /// List completed tasks grouped by day and repository
+    Journal {
+        /// How far back to look: a period like 3d, 1w, 1m or a date like 2024-07-01
+        #[arg(long, default_value = "1w")]
+        since: String,
+        /// Print Markdown instead of plain text
+        #[arg(long)]
+        markdown: bool,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Export every task, e.g. as an iCalendar file for calendar apps
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Ics)]
+        format: ExportFormat,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Print task events (created, status_changed, edited, deleted) from the activity log as JSON Lines, oldest first
+    ExportEvents {
+        /// Only events from this day on: a period like 3d, 1w, 1m or a date like 2024-07-01
+        #[arg(long)]
+        since: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Share the tasks branch used by git storage through the configured remote
+    Git {
+        #[command(subcommand)]
+        action: GitAction,
+    },
+    /// Serve the configured storage to other machines over the sync protocol
+    ServeSync {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        listen: SocketAddr,
+        /// Token clients may present, shared by everyone (defaults to
+        /// QUILL_SYNC_TOKEN); their changes are recorded as "shared token"
+        #[arg(long)]
+        token: Option<String>,
+        /// A person's own token, so their changes are recorded under NAME
+        /// (repeatable)
+        #[arg(long = "user", value_name = "NAME=TOKEN")]
+        users: Vec<String>,
+        /// Token that only opens the calendar feed, so it can go in a
+        /// subscription URL (defaults to QUILL_FEED_TOKEN)
+        #[arg(long)]
+        feed_token: Option<String>,
+    },
+    /// Own the configured storage for TUI instances on this machine, keeping
+    /// them in sync through push notifications instead of re-reading storage
+    Daemon,
+    /// Manage the team settings shared through `[shared]` in config
+    Shared {
+        #[command(subcommand)]
+        action: SharedAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SharedAction {
+    /// Fetch the shared settings now and refresh the cached copy
+    Fetch,
+    /// Publish a TOML file as the shared settings in the MongoDB database
+    Publish {
+        file: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// iCalendar: a to-do per task and an all-day event per due date
+    Ics,
+    /// Tasks by context key
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, ValueEnum)]
+pub enum ListFormat {
+    /// Aligned columns with a header
+    Table,
+    /// A Markdown table
+    Md,
+    /// Tab-separated values without a header
+    Tsv,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, ValueEnum)]
+pub enum PickAction {
+    /// Mark the picked tasks completed
+    Done,
+    /// Mark the picked tasks in progress
+    Start,
+    /// Open each picked task's text and notes in $EDITOR
+    Edit,
+}
+
+#[derive(Subcommand)]
+pub enum GitAction {
+    /// Fast-forward the tasks branch from the remote
+    Pull,
+    /// Push the tasks branch to the remote
+    Push,
+}
+
+#[derive(Subcommand)]
+pub enum HookAction {
+    /// Install a pre-push hook that runs `quill check` for each pushed branch
+    Install {
+        /// Overwrite an existing pre-push hook not written by quill
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+pub async fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Hook { action: HookAction::Install { force } } => {
+            let path = install_pre_push_hook(force)?;
+            println!("Installed pre-push hook at {}", path.display());
+            Ok(())
+        }
+        Command::Add { text, inbox } => add(&text.join(" "), inbox).await,
+        Command::Check { branch } => check(branch).await,
+        Command::List { query, all, format, json } => list(query.as_deref(), all, format, json).await,
+        Command::Pick { action: None, all, query } => pick_list(query.as_deref(), all).await,
+        Command::Pick { action: Some(action), .. } => pick(action).await,
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "quill", &mut std::io::stdout());
+            Ok(())
+        }
+        Command::Prompt { format, bar_width } => print_prompt(format, bar_width).await,
+        Command::Journal { since, markdown, output } => journal(&since, markdown, output).await,
+        Command::Export { format, output } => export(format, output).await,
+        Command::ExportEvents { since, output } => export_events(since.as_deref(), output).await,
+        Command::Git { action } => sync_tasks_branch(action),
+        Command::ServeSync { listen, token, users, feed_token } => serve_sync(listen, token, users, feed_token).await,
+        Command::Daemon => daemon().await,
+        Command::Shared { action } => shared(action).await,
+    }
+}
+
+async fn shared(action: SharedAction) -> Result<()> {
+    let config = AppConfig::load()?;
+    match action {
+        SharedAction::Fetch => match SharedConfig::fetch(&config).await? {
+            Some(shared) => {
+                println!(
+                    "Fetched {} tag rule(s), {} branch rule(s), {} storage rule(s) and {} template(s)",
+                    shared.tag_rules.len(),
+                    shared.branch_rules.len(),
+                    shared.storage_rules.len(),
+                    shared.templates.len()
+                );
+                Ok(())
+            }
+            None => Err(anyhow!("no shared config source: set shared.url or shared.mongodb in config.toml")),
+        },
+        SharedAction::Publish { file } => {
+            if !config.shared.mongodb {
+                return Err(anyhow!("publishing needs shared.mongodb = true; a shared URL is updated wherever it is hosted"));
+            }
+            SharedConfig::publish(&config, &fs::read_to_string(&file)?).await?;
+            println!("Published {} to MongoDB", file.display());
+            Ok(())
+        }
+    }
+}
+
+async fn serve_sync(listen: SocketAddr, token: Option<String>, users: Vec<String>, feed_token: Option<String>) -> Result<()> {
+    let mut tokens = users.iter().map(|user| serve::SyncToken::parse_user(user)).collect::<Result<Vec<_>>>()?;
+    let shared = token.or_else(|| std::env::var("QUILL_SYNC_TOKEN").ok()).filter(|token| !token.is_empty());
+    tokens.extend(shared.map(serve::SyncToken::shared));
+    if tokens.is_empty() {
+        return Err(anyhow!("a token is required: pass --token or --user NAME=TOKEN, or set QUILL_SYNC_TOKEN"));
+    }
+    let feed_token = feed_token.or_else(|| std::env::var("QUILL_FEED_TOKEN").ok()).filter(|token| !token.is_empty());
+    if feed_token.as_ref().is_some_and(|feed_token| tokens.iter().any(|t| t.token == *feed_token)) {
+        return Err(anyhow!("the feed token goes in URLs, so it must differ from every sync token"));
+    }
+    let config = AppConfig::load_effective()?;
+    if config.storage_type == StorageType::Remote {
+        return Err(anyhow!("serve-sync needs a local, MongoDB or git backend, not remote storage"));
+    }
+    // The server records activity itself, as the holder of each request's token
+    let storage = storage::open_backend(&config).await?;
+    eprintln!("Serving {} storage", config.storage_type.label());
+    serve::serve(listen, storage, tokens, feed_token).await
+}
+
+#[cfg(unix)]
+async fn daemon() -> Result<()> {
+    let config = AppConfig::load()?;
+    if config.storage_type == StorageType::Git {
+        return Err(anyhow!("git storage belongs to one repository, so it can't be shared by a daemon"));
+    }
+    let path = storage::daemon::socket_path().ok_or_else(|| anyhow!("could not find the home directory"))?;
+    let storage = storage::open_backend(&config).await?;
+    crate::daemon::run(&path, storage, storage::daemon::storage_id(&config)).await
+}
+
+#[cfg(not(unix))]
+async fn daemon() -> Result<()> {
+    Err(anyhow!("quill daemon needs unix sockets, which this platform doesn't have"))
+}
+
+fn sync_tasks_branch(action: GitAction) -> Result<()> {
+    let config = AppConfig::load_effective()?.git_config;
+    let root = git::repo_root().ok_or_else(|| anyhow!("not inside a git repository"))?;
+    let output = match action {
+        GitAction::Pull => git_branch::pull(&root, &config)?,
+        GitAction::Push => git_branch::push(&root, &config)?,
+    };
+    if !output.is_empty() {
+        eprintln!("{}", output);
+    }
+    Ok(())
+}
+
+async fn journal(since: &str, markdown: bool, output: Option<PathBuf>) -> Result<()> {
+    let since = dates::parse_since(since, chrono::Local::now().date_naive())?;
+    let config = AppConfig::load_effective()?;
+    let storage = storage::open(&config).await?;
+
+    let journal = Journal::collect(&summary::snapshot(storage.as_ref()).await?, since);
+    let rendered = if journal.is_empty() {
+        format!("Nothing completed since {}\n", since)
+    } else if markdown {
+        journal.render_markdown()
+    } else {
+        journal.render_text()
+    };
+
+    match output {
+        Some(path) => {
+            fs::write(&path, rendered)?;
+            println!("Wrote journal to {}", path.display());
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+async fn export(format: ExportFormat, output: Option<PathBuf>) -> Result<()> {
+    let config = AppConfig::load_effective()?;
+    let storage = storage::open(&config).await?;
+    let snapshot = summary::snapshot(storage.as_ref()).await?;
+
+    let rendered = match format {
+        ExportFormat::Ics => ics::render(&snapshot, chrono::Utc::now()),
+        ExportFormat::Json => serde_json::to_string_pretty(&snapshot.into_iter().collect::<BTreeMap<_, _>>())? + "\n",
+    };
+    match output {
+        Some(path) => {
+            fs::write(&path, rendered)?;
+            eprintln!("Exported to {}", path.display());
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+async fn export_events(since: Option<&str>, output: Option<PathBuf>) -> Result<()> {
+    let since = since.map(|since| dates::parse_since(since, chrono::Local::now().date_naive())).transpose()?;
+    // The start of that day here, as the log's times are compared in UTC
+    let since = since
+        .and_then(|day| day.and_hms_opt(0, 0, 0)?.and_local_timezone(chrono::Local).earliest())
+        .map(|start| start.with_timezone(&chrono::Utc).to_rfc3339());
+    let config = AppConfig::load_effective()?;
+    let storage = storage::open(&config).await?;
+
+    let activity = storage.export_activity(since.as_deref()).await?;
+    let rendered = events::to_jsonl(&events::collect(&activity))?;
+
+    match output {
+        Some(path) => {
+            fs::write(&path, rendered)?;
+            eprintln!("Wrote events to {}", path.display());
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+async fn print_prompt(format: Option<String>, bar_width: usize) -> Result<()> {
+    let config = AppConfig::load_effective()?;
+    let Some(context) = config.resolve_context(GitContext::from_current_dir_with(&config.forges)?) else {
+        return Ok(());
+    };
+
+    let storage = storage::open(&config).await?;
+    let counts = storage.get_task_counts(&context.context_key()).await?;
+
+    let view_state = ViewState::load().unwrap_or_default();
+    let mut unseen = 0;
+    for context_key in view_state.watched.keys().filter(|k| **k != context.context_key()) {
+        unseen += view_state.unseen(context_key, &storage.get_tasks(context_key).await?);
+    }
+
+    // Stay silent for empty contexts so prompts don't show a useless badge
+    if counts.total() > 0 || unseen > 0 {
+        let format = format.unwrap_or(config.prompt_format);
+        let rendered = prompt::render(&format, &context, &counts, bar_width);
+        println!("{}", rendered.replace("{watched}", &watch_badge(unseen)));
+    }
+    Ok(())
+}
+
+/// `{watched}` in prompts: `★3` when watched contexts have new open tasks, empty otherwise.
+fn watch_badge(unseen: usize) -> String {
+    if unseen > 0 {
+        format!("★{}", unseen)
+    } else {
+        String::new()
+    }
+}
+
+async fn add(input: &str, inbox: bool) -> Result<()> {
+    let config = AppConfig::load_effective()?;
+    let context = if inbox {
+        GitContext::inbox()
+    } else {
+        let context = GitContext::from_current_dir_with(&config.forges)?;
+        config
+            .resolve_context(context)
+            .ok_or_else(|| anyhow!("a branch rule ignores this branch; use --inbox to capture the task anyway"))?
+    };
+    let context_key = context.context_key();
+
+    let dates = DateParser::new(&config.date_locales, &config.date_words)?;
+    let parsed = QuickAdd::parse(input, chrono::Local::now().date_naive(), &dates);
+    if parsed.text.is_empty() {
+        return Err(anyhow!("task text is empty"));
+    }
+    let mut storage = storage::open(&config).await?;
+    let id = storage.add_task(&context_key, parsed.text.clone()).await?;
+    let mut tags = config.tags_for(&context_key, &parsed.text);
+    for tag in parsed.tags.iter() {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    // Shared MongoDB lists record who added the task, as the TUI does
+    let assignee = match config.storage_type {
+        StorageType::MongoDB => git::user_email(&git::current_dir()),
+        _ => None,
+    };
+    if !tags.is_empty() || parsed.has_metadata() || assignee.is_some() {
+        if let Some(mut task) = storage.get_tasks(&context_key).await?.into_iter().find(|t| t.id == id) {
+            task.tags = tags;
+            task.assignee = assignee;
+            task.priority = parsed.priority;
+            task.due = parsed.due.or(task.due);
+            storage.update_task(&context_key, task).await?;
+        }
+    }
+    storage.flush().await?;
+    println!("Added \"{}\" to {}", parsed.text, context_key);
+    Ok(())
+}
+
+/// A task printed by `quill list --json`.
+#[derive(Serialize)]
+struct ListedTask<'a> {
+    context: &'a str,
+    #[serde(flatten)]
+    task: &'a Task,
+}
+
+async fn list(query: Option<&str>, all: bool, format: ListFormat, json: bool) -> Result<()> {
+    let config = AppConfig::load_effective()?;
+    let tasks = query_tasks(&config, query, all).await?;
+    let listed: Vec<ListedTask> = tasks
+        .iter()
+        .flat_map(|(context_key, tasks)| tasks.iter().map(move |task| ListedTask { context: context_key, task }))
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&listed)?);
+    } else {
+        print!("{}", render_list(&listed, format));
+    }
+    Ok(())
+}
+
+/// The tasks of the current context, or of every context with `all`, that
+/// match `query`: a filter expression or the name of a saved view.
+async fn query_tasks(config: &AppConfig, query: Option<&str>, all: bool) -> Result<BTreeMap<String, Vec<Task>>> {
+    let dates = DateParser::new(&config.date_locales, &config.date_words)?;
+    // A saved view's name stands for its expression
+    let filter = query
+        .map(|query| Filter::parse(config.views.get(query).map_or(query, String::as_str), &dates))
+        .transpose()
+        .map_err(|e| anyhow!("invalid query: {}", e))?;
+
+    let storage = storage::open(config).await?;
+    let mut tasks = if all {
+        storage.get_all_tasks().await?
+    } else {
+        let context = GitContext::from_current_dir_with(&config.forges)?;
+        let context = config.resolve_context(context).ok_or_else(|| anyhow!("a branch rule ignores this branch; use --all"))?;
+        let context_key = context.context_key();
+        BTreeMap::from([(context_key.clone(), storage.get_tasks(&context_key).await?)])
+    };
+
+    if let Some(filter) = filter {
+        let me = git::user_email(&git::current_dir());
+        let (today, now) = (chrono::Local::now().date_naive(), chrono::Utc::now());
+        for (context_key, tasks) in tasks.iter_mut() {
+            let env = views::Env { today, now, me: me.as_deref(), dates: &dates, context_key };
+            tasks.retain(|t| filter.matches(t, &env));
+        }
+    }
+    Ok(tasks)
+}
+
+/// Prints the open tasks as `context<TAB>id<TAB>text` lines for fzf.
+async fn pick_list(query: Option<&str>, all: bool) -> Result<()> {
+    let config = AppConfig::load_effective()?;
+    for (context_key, tasks) in query_tasks(&config, query, all).await? {
+        for task in tasks.iter().filter(|t| t.status != TaskStatus::Completed) {
+            println!("{}", pick_line(&context_key, task));
+        }
+    }
+    Ok(())
+}
+
+fn pick_line(context_key: &str, task: &Task) -> String {
+    // Tabs and line breaks in the text would split the line or its fields
+    let mut line = format!("{}\t{}\t{}", context_key, task.id, task.text.replace(['\t', '\n', '\r'], " "));
+    for tag in &task.tags {
+        line.push_str(&format!(" #{}", tag));
+    }
+    line
+}
+
+/// The context key and task id at the start of a line printed by `quill pick`.
+fn parse_pick_line(line: &str) -> Option<(&str, usize)> {
+    let mut fields = line.splitn(3, '\t');
+    let context_key = fields.next().filter(|key| !key.is_empty())?;
+    let id = fields.next()?.trim().parse().ok()?;
+    Some((context_key, id))
+}
+
+/// Applies `action` to the tasks of the lines on stdin, as fzf prints them.
+async fn pick(action: PickAction) -> Result<()> {
+    let picked: Vec<(String, usize)> = std::io::stdin()
+        .lines()
+        .collect::<std::io::Result<Vec<_>>>()?
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            parse_pick_line(line)
+                .map(|(context_key, id)| (context_key.to_string(), id))
+                .ok_or_else(|| anyhow!("not a line from `quill pick`: {}", line))
+        })
+        .collect::<Result<_>>()?;
+
+    let config = AppConfig::load_effective()?;
+    let mut storage = storage::open(&config).await?;
+    for (context_key, id) in picked {
+        let Some(mut task) = storage.get_tasks(&context_key).await?.into_iter().find(|t| t.id == id) else {
+            eprintln!("No task {} in {}", id, context_key);
+            continue;
+        };
+        let verb = match action {
+            PickAction::Done => {
+                task.set_status(TaskStatus::Completed);
+                "Completed"
+            }
+            PickAction::Start => {
+                task.set_status(TaskStatus::InProgress);
+                "Started"
+            }
+            PickAction::Edit => {
+                let buffer = if task.notes.is_empty() { task.text.clone() } else { format!("{}\n\n{}", task.text, task.notes) };
+                let (text, notes) = external_editor::split_text_and_notes(&external_editor::edit(&buffer, "md")?);
+                if text.is_empty() {
+                    eprintln!("Empty text, left \"{}\" unchanged", task.text);
+                    continue;
+                }
+                task.text = text;
+                task.notes = notes;
+                "Edited"
+            }
+        };
+        println!("{} \"{}\"", verb, task.text);
+        storage.update_task(&context_key, task).await?;
+    }
+    storage.flush().await?;
+    Ok(())
+}
+
+fn render_list(listed: &[ListedTask], format: ListFormat) -> String {
+    let status = |status: &TaskStatus| match status {
+        TaskStatus::NotStarted => "not_started",
+        TaskStatus::InProgress => "in_progress",
+        TaskStatus::Completed => "completed",
+    };
+    let priority = |priority: Priority| match priority {
+        Priority::None => "",
+        Priority::Low => "low",
+        Priority::Medium => "medium",
+        Priority::High => "high",
+    };
+    let header = ["CONTEXT", "ID", "STATUS", "PRIORITY", "DUE", "TAGS", "TASK"].map(String::from);
+    let rows: Vec<[String; 7]> = listed
+        .iter()
+        .map(|ListedTask { context, task }| {
+            [
+                context.to_string(),
+                task.id.to_string(),
+                status(&task.status).to_string(),
+                priority(task.priority).to_string(),
+                task.due.map(|due| due.to_string()).unwrap_or_default(),
+                task.tags.join(","),
+                task.text.clone(),
+            ]
+        })
+        .collect();
+
+    let mut out = String::new();
+    match format {
+        ListFormat::Tsv => {
+            for row in &rows {
+                // Tabs and newlines in task text would break the columns
+                let cells: Vec<String> = row.iter().map(|cell| cell.replace(['\t', '\n'], " ")).collect();
+                out.push_str(&format!("{}\n", cells.join("\t")));
+            }
+        }
+        ListFormat::Md => {
+            out.push_str(&format!("| {} |\n", header.join(" | ")));
+            out.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+            for row in &rows {
+                let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|").replace('\n', " ")).collect();
+                out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+        }
+        ListFormat::Table => {
+            let mut widths = header.clone().map(|cell| cell.width());
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.width());
+                }
+            }
+            for row in std::iter::once(&header).chain(&rows) {
+                let cells: Vec<String> = row
+                    .iter()
+                    .zip(widths)
+                    .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.width())))
+                    .collect();
+                out.push_str(&format!("{}\n", cells.join("  ").trim_end()));
+            }
+        }
+    }
+    out
+}
+
+async fn check(branch: Option<String>) -> Result<()> {
+    let config = AppConfig::load_effective()?;
+    let mut context = GitContext::from_current_dir_with(&config.forges)?;
+    if let Some(branch) = branch {
+        context.branch = branch;
+    }
+    let Some(context) = config.resolve_context(context) else {
+        // Ignored branches have no task list to check
+        return Ok(());
+    };
+
+    let storage = storage::open(&config).await?;
+    let tasks = storage.get_tasks(&context.context_key()).await?;
+    let blocking = blocking_tasks(&tasks);
+
+    if blocking.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("quill: {} unfinished must-finish task(s) in {}:", blocking.len(), context.context_key());
+    for task in blocking {
+        let symbol = match task.status {
+            TaskStatus::InProgress => "◐",
+            _ => "○",
+        };
+        eprintln!("  {} {}", symbol, task.text);
+    }
+    std::process::exit(1);
+}
+
+fn blocking_tasks(tasks: &[Task]) -> Vec<&Task> {
+    tasks
+        .iter()
+        .filter(|t| t.must_finish && !t.is_completed())
+        .collect()
+}
+
+fn install_pre_push_hook(force: bool) -> Result<PathBuf> {
+    let hooks_dir = git::hooks_dir()?;
+    let path = hooks_dir.join("pre-push");
+
+    if path.exists() && !force {
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        if !existing.contains(HOOK_MARKER) {
+            return Err(anyhow!(
+                "{} already exists and was not installed by quill; re-run with --force to overwrite it",
+                path.display()
+            ));
+        }
+    }
+
+    fs::create_dir_all(&hooks_dir)?;
+    fs::write(&path, PRE_PUSH_HOOK)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_tasks_only_unfinished_must_finish() {
+        let mut flagged = Task::new(1, "Flagged".to_string());
+        flagged.must_finish = true;
+        let mut done = Task::new(2, "Done".to_string());
+        done.must_finish = true;
+        done.status = TaskStatus::Completed;
+        let plain = Task::new(3, "Plain".to_string());
+
+        let tasks = vec![flagged, done, plain];
+        let blocking = blocking_tasks(&tasks);
+
+        assert_eq!(blocking.len(), 1);
+        assert_eq!(blocking[0].id, 1);
+    }
+
+    #[test]
+    fn test_pre_push_hook_is_recognised() {
+        assert!(PRE_PUSH_HOOK.contains(HOOK_MARKER));
+        assert!(PRE_PUSH_HOOK.contains("quill check --branch"));
+    }
+
+    #[test]
+    fn test_cli_parses_check_branch() {
+        let cli = Cli::try_parse_from(["quill", "check", "--branch", "feature/x"]).unwrap();
+        match cli.command {
+            Some(Command::Check { branch }) => assert_eq!(branch.as_deref(), Some("feature/x")),
+            _ => panic!("expected check command"),
+        }
+    }
+
+    #[test]
+    fn test_render_list_formats() {
+        let mut task = Task::new(4, "Fix | login".to_string());
+        task.priority = Priority::High;
+        task.tags = vec!["web".to_string()];
+        let listed = [ListedTask { context: "acme:web:main", task: &task }];
+
+        assert_eq!(render_list(&listed, ListFormat::Tsv), "acme:web:main\t4\tnot_started\thigh\t\tweb\tFix | login\n");
+        assert_eq!(
+            render_list(&listed, ListFormat::Md).lines().last(),
+            Some("| acme:web:main | 4 | not_started | high |  | web | Fix \\| login |")
+        );
+        assert_eq!(
+            render_list(&listed, ListFormat::Table),
+            "CONTEXT        ID  STATUS       PRIORITY  DUE  TAGS  TASK\nacme:web:main  4   not_started  high           web   Fix | login\n"
+        );
+        assert!(Cli::try_parse_from(["quill", "list", "--json", "--format", "md"]).is_err());
+    }
+
+    #[test]
+    fn test_pick_lines_round_trip() {
+        let mut task = Task::new(7, "Fix\tlogin\r\nfor admins".to_string());
+        task.tags = vec!["web".to_string()];
+        let line = pick_line("acme:web:main", &task);
+        assert_eq!(line, "acme:web:main\t7\tFix login  for admins #web");
+        assert_eq!(line.lines().count(), 1);
+        assert_eq!(parse_pick_line(&line), Some(("acme:web:main", 7)));
+        assert_eq!(parse_pick_line("Fix login"), None);
+
+        let cli = Cli::try_parse_from(["quill", "pick", "done"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Pick { action: Some(PickAction::Done), .. })));
+        assert!(Cli::try_parse_from(["quill", "completions", "zsh"]).is_ok());
+    }
+
+    #[test]
+    fn test_cli_parses_journal_defaults() {
+        let cli = Cli::try_parse_from(["quill", "journal", "--markdown"]).unwrap();
+        match cli.command {
+            Some(Command::Journal { since, markdown, output }) => {
+                assert_eq!(since, "1w");
+                assert!(markdown);
+                assert!(output.is_none());
+            }
+            _ => panic!("expected journal command"),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::io;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the terminal is in raw mode on the alternate screen, so a panic
+/// outside the TUI (in `quill list`, say) leaves the terminal alone.
+static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Puts the terminal into TUI mode and restores it when dropped, including
+/// while unwinding from a panic.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> Result<Self> {
+        enable_raw_mode()
+            .map_err(|e| anyhow::anyhow!("Failed to enable raw mode. Make sure you're running in a proper terminal. Error: {}", e))?;
+        TUI_ACTIVE.store(true, Ordering::SeqCst);
+        // Dropping the guard undoes raw mode if the screen can't be set up
+        let guard = Self;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+            .map_err(|e| anyhow::anyhow!("Failed to setup terminal. Error: {}", e))?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Leaves raw mode and the alternate screen if the TUI is up; safe to call twice.
+pub fn restore_terminal() {
+    if TUI_ACTIVE.swap(false, Ordering::SeqCst) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, Show);
+    }
+}
+
+/// `~/.quill/crashes/`
+fn crash_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".quill").join("crashes"))
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+/// The text of a crash report, as written to disk and printed.
+fn report(message: &str, location: &str, backtrace: &str, at: DateTime<Local>) -> String {
+    format!(
+        "quill {} crashed at {}\n{} {}\n\npanicked at {}:\n{}\n\nstack backtrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        at.to_rfc3339(),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        location,
+        message,
+        backtrace
+    )
+}
+
+/// Restores the terminal before a panic is reported, then prints the panic
+/// with a backtrace and saves it under `~/.quill/crashes/`.
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        restore_terminal();
+
+        let location = info.location().map_or_else(|| "unknown location".to_string(), |l| l.to_string());
+        let now = Local::now();
+        let text = report(&panic_message(info), &location, &Backtrace::force_capture().to_string(), now);
+        tracing::error!(%location, message = %panic_message(info), "panicked");
+        eprint!("{}", text);
+
+        let saved = crash_dir().and_then(|dir| {
+            fs::create_dir_all(&dir).ok()?;
+            let path = dir.join(format!("crash-{}.txt", now.format("%Y%m%d-%H%M%S")));
+            fs::write(&path, &text).ok().map(|_| path)
+        });
+        match saved {
+            Some(path) => eprintln!("A crash report was saved to {}; please attach it to a bug report.", path.display()),
+            None => eprintln!("The crash report could not be saved; please copy the text above into a bug report."),
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_has_version_location_and_backtrace() {
+        let at = DateTime::parse_from_rfc3339("2024-07-20T10:00:00+02:00").unwrap().with_timezone(&Local);
+        let text = report("index out of bounds", "src/app.rs:42:5", "   0: quill::app::App::run", at);
+        assert!(text.starts_with(&format!("quill {} crashed at ", env!("CARGO_PKG_VERSION"))));
+        assert!(text.contains("panicked at src/app.rs:42:5:\nindex out of bounds\n"));
+        assert!(text.ends_with("stack backtrace:\n   0: quill::app::App::run\n"));
+    }
+}
@@ -0,0 +1,205 @@
+use crate::storage::{
+    activity::{self, ActivityStorage},
+    daemon::{Call, Message},
+    remote::{self, Response},
+    ExternalChange, TaskStorage,
+};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, Mutex};
+
+/// How often the storage is checked for changes made without the daemon,
+/// such as `quill add` or a text editor.
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+struct Daemon {
+    /// Calls run one at a time, as they would against the backend directly.
+    /// Changes are recorded as the user running the daemon.
+    storage: Mutex<ActivityStorage>,
+    storage_id: String,
+    /// Lines queued for each connected client.
+    clients: StdMutex<Vec<mpsc::UnboundedSender<String>>>,
+    /// The user running the daemon, the only one whose clients it serves.
+    uid: u32,
+}
+
+impl Daemon {
+    fn broadcast(&self, message: &Message) {
+        let Ok(line) = message.encode() else {
+            return;
+        };
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        clients.retain(|client| client.send(line.clone()).is_ok());
+    }
+}
+
+/// Serves `storage` on the unix socket at `path` until interrupted, then
+/// saves pending changes and removes the socket.
+pub async fn run(path: &Path, storage: Box<dyn TaskStorage>, storage_id: String) -> Result<()> {
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(anyhow!("a quill daemon is already listening on {}", path.display()));
+        }
+        // Left behind by a daemon that didn't shut down cleanly
+        fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    // Anyone who can connect reads and writes the tasks as this user
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    let uid = fs::metadata(path)?.uid();
+    eprintln!("quill daemon serving {} on {}", storage_id, path.display());
+
+    let storage = ActivityStorage::new(storage, activity::actor());
+    let daemon = Arc::new(Daemon { storage: Mutex::new(storage), storage_id, clients: StdMutex::default(), uid });
+    tokio::select! {
+        result = serve(listener, daemon.clone()) => result?,
+        _ = tokio::signal::ctrl_c() => {}
+    }
+
+    let flushed = daemon.storage.lock().await.flush().await;
+    let _ = fs::remove_file(path);
+    flushed?;
+    eprintln!("quill daemon stopped");
+    Ok(())
+}
+
+async fn serve(listener: UnixListener, daemon: Arc<Daemon>) -> Result<()> {
+    tokio::spawn(watch_storage(daemon.clone()));
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(serve_client(stream, daemon.clone()));
+    }
+}
+
+/// Tells clients about changes other writers made to the storage.
+async fn watch_storage(daemon: Arc<Daemon>) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let mut storage = daemon.storage.lock().await;
+        match storage.check_external_changes().await {
+            Ok(ExternalChange::None) => {}
+            Ok(ExternalChange::Reloaded) => daemon.broadcast(&Message::Changed { context_key: None }),
+            Ok(ExternalChange::Conflict) => {
+                // Clients saw the daemon's changes succeed, so those win
+                tracing::warn!("storage changed under the daemon while changes were unsaved; keeping the daemon's");
+                if let Err(e) = storage.resolve_external_change(true).await {
+                    tracing::warn!(error = %e, "couldn't write the daemon's changes");
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "couldn't check the storage for outside changes"),
+        }
+    }
+}
+
+async fn serve_client(stream: UnixStream, daemon: Arc<Daemon>) {
+    // Closes the gap between binding the socket and restricting it
+    if stream.peer_cred().map(|cred| cred.uid()).ok() != Some(daemon.uid) {
+        tracing::warn!("refused a quill daemon client run by another user");
+        return;
+    }
+    let (reader, mut writer) = stream.into_split();
+    let (lines_tx, mut lines) = mpsc::unbounded_channel::<String>();
+    tokio::spawn(async move {
+        while let Some(line) = lines.recv().await {
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    if let Ok(hello) = (Message::Hello { storage: daemon.storage_id.clone() }).encode() {
+        let _ = lines_tx.send(hello);
+    }
+    daemon.clients.lock().unwrap_or_else(|e| e.into_inner()).push(lines_tx.clone());
+    tracing::debug!("quill daemon client connected");
+
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    while reader.read_line(&mut line).await.is_ok_and(|read| read > 0) {
+        let reply = match serde_json::from_str::<Call>(&line) {
+            Ok(Call { id, request }) => {
+                let written: Vec<String> = request.written_contexts().into_iter().map(str::to_string).collect();
+                let mut storage = daemon.storage.lock().await;
+                let result = remote::dispatch_recorded(&mut storage, request).await;
+                // Sent while the storage is locked, so every client hears of
+                // changes in the order they were made, before this reply
+                if result.is_ok() {
+                    for context_key in written {
+                        daemon.broadcast(&Message::Changed { context_key: Some(context_key) });
+                    }
+                }
+                match result {
+                    Ok(value) => Message::Reply { id, status: 200, response: Response::Result(value) },
+                    Err(e) => Message::Reply { id, status: remote::status_for(&e), response: Response::Error(e.to_string()) },
+                }
+            }
+            Err(e) => {
+                // The number still reaches the caller when only the request is unreadable
+                let id = serde_json::from_str::<serde_json::Value>(&line).ok().and_then(|call| call.get("id")?.as_u64()).unwrap_or_default();
+                Message::Reply { id, status: 400, response: Response::Error(format!("bad request: {}", e)) }
+            }
+        };
+        let Ok(reply) = reply.encode() else {
+            break;
+        };
+        if lines_tx.send(reply).is_err() {
+            break;
+        }
+        line.clear();
+    }
+    tracing::debug!("quill daemon client disconnected");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::storage::{daemon::DaemonStorage, local::LocalTaskStorage, TaskStatus};
+
+    #[tokio::test]
+    async fn test_clients_see_each_others_changes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("daemon.sock");
+        let config = AppConfig::default();
+        let storage_id = crate::storage::daemon::storage_id(&config);
+        let daemon = Arc::new(Daemon {
+            storage: Mutex::new(ActivityStorage::new(Box::new(LocalTaskStorage::in_memory()), "ann@acme.dev".to_string())),
+            storage_id,
+            clients: StdMutex::default(),
+            uid: fs::metadata(dir.path()).unwrap().uid(),
+        });
+        tokio::spawn(serve(UnixListener::bind(&path).unwrap(), daemon));
+
+        let mut left = DaemonStorage::connect(&path, &config).await.unwrap();
+        let right = DaemonStorage::connect(&path, &config).await.unwrap();
+        let context = "acme:web:main";
+        assert!(right.get_tasks(context).await.unwrap().is_empty());
+
+        let id = left.add_task(context, "Shared".to_string()).await.unwrap();
+        left.set_task_status(context, id, TaskStatus::Completed).await.unwrap();
+        // The cached empty list is dropped once the change notification arrives
+        let mut seen = Vec::new();
+        for _ in 0..50 {
+            seen = right.get_tasks(context).await.unwrap();
+            if !seen.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(seen[0].status, TaskStatus::Completed);
+
+        let mut other = AppConfig::default();
+        other.local_config.path = "/elsewhere/todos.json".to_string();
+        assert!(DaemonStorage::connect(&path, &other).await.is_err());
+    }
+}
@@ -0,0 +1,339 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+use std::collections::{BTreeMap, HashMap};
+
+/// A change to apply to due dates, parsed from input such as `+1d`, `-2w`,
+/// `+1m`, `2024-07-01`, or an empty string to clear.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DueShift {
+    /// Moves existing due dates; tasks without one are left alone.
+    Offset { amount: i64, unit: OffsetUnit },
+    /// Sets every due date to this day.
+    Set(NaiveDate),
+    Clear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OffsetUnit {
+    Days,
+    Weeks,
+    Months,
+}
+
+impl DueShift {
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(Self::Clear);
+        }
+
+        if let Some(sign) = input.chars().next().filter(|c| *c == '+' || *c == '-') {
+            let body = &input[1..];
+            let (number, unit) = body.split_at(body.find(|c: char| !c.is_ascii_digit()).unwrap_or(body.len()));
+            let amount: i64 = number.parse().map_err(|_| anyhow!("expected a number after '{}' in '{}'", sign, input))?;
+            let unit = match unit {
+                "" | "d" => OffsetUnit::Days,
+                "w" => OffsetUnit::Weeks,
+                "m" => OffsetUnit::Months,
+                other => return Err(anyhow!("unknown unit '{}', use d, w or m", other)),
+            };
+            let amount = if sign == '-' { -amount } else { amount };
+            return Ok(Self::Offset { amount, unit });
+        }
+
+        NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .map(Self::Set)
+            .map_err(|_| anyhow!("'{}' is not an offset like +1d/-1w or a date like 2024-07-01", input))
+    }
+
+    pub fn apply(&self, due: Option<NaiveDate>) -> Option<NaiveDate> {
+        match *self {
+            Self::Offset { amount, unit } => due.and_then(|date| shift(date, amount, unit)),
+            Self::Set(date) => Some(date),
+            Self::Clear => None,
+        }
+    }
+}
+
+/// Parses a look-back period such as `3d`, `1w` or `1m`, or a date, into
+/// the first day it covers.
+pub fn parse_since(input: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let input = input.trim();
+    let shift = if input.starts_with(|c: char| c.is_ascii_digit()) && !input.contains('-') {
+        DueShift::parse(&format!("-{}", input))?
+    } else {
+        DueShift::parse(input)?
+    };
+    match shift {
+        DueShift::Clear => Err(anyhow!("expected a period like 1w or a date like 2024-07-01")),
+        shift => shift.apply(Some(today)).ok_or_else(|| anyhow!("'{}' is out of range", input)),
+    }
+}
+
+/// Due-date vocabulary of one language.
+pub struct DateLocale {
+    pub code: &'static str,
+    today: &'static [&'static str],
+    tomorrow: &'static [&'static str],
+    /// Words for the Monday after this week.
+    next_week: &'static [&'static str],
+    /// Weekday names from Monday; prefixes of three letters or more also match.
+    weekdays: [&'static str; 7],
+}
+
+/// Built-in locales, selected with `date_locales` in the config.
+pub const LOCALES: &[DateLocale] = &[
+    DateLocale {
+        code: "en",
+        today: &["today", "tod"],
+        tomorrow: &["tomorrow", "tom"],
+        next_week: &["nextweek", "next-week"],
+        weekdays: ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"],
+    },
+    DateLocale {
+        code: "fr",
+        today: &["aujourd'hui", "auj"],
+        tomorrow: &["demain"],
+        next_week: &["semaine-prochaine"],
+        weekdays: ["lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche"],
+    },
+    DateLocale {
+        code: "de",
+        today: &["heute"],
+        tomorrow: &["morgen"],
+        next_week: &["nächste-woche", "naechste-woche"],
+        weekdays: ["montag", "dienstag", "mittwoch", "donnerstag", "freitag", "samstag", "sonntag"],
+    },
+    DateLocale {
+        code: "es",
+        today: &["hoy"],
+        tomorrow: &["mañana", "manana"],
+        next_week: &["próxima-semana", "proxima-semana"],
+        weekdays: ["lunes", "martes", "miércoles", "jueves", "viernes", "sábado", "domingo"],
+    },
+];
+
+impl DateLocale {
+    pub fn find(code: &str) -> Option<&'static DateLocale> {
+        LOCALES.iter().find(|locale| locale.code.eq_ignore_ascii_case(code))
+    }
+
+    fn parse(&self, word: &str, today: NaiveDate) -> Option<NaiveDate> {
+        if self.today.contains(&word) {
+            return Some(today);
+        }
+        if self.tomorrow.contains(&word) {
+            return today.succ_opt();
+        }
+        if self.next_week.contains(&word) {
+            return next_weekday(today, Weekday::Mon);
+        }
+        // Prefixes are counted in characters so `mié` matches `miércoles`
+        self.weekdays
+            .iter()
+            .position(|name| word.chars().count() >= 3 && name.starts_with(word))
+            .and_then(|i| next_weekday(today, Weekday::try_from(i as u8).ok()?))
+    }
+}
+
+/// Parses due-date words in the configured languages, plus user-defined
+/// words (`date_words` in the config) that stand for another expression.
+pub struct DateParser {
+    locales: Vec<&'static DateLocale>,
+    words: HashMap<String, String>,
+}
+
+impl Default for DateParser {
+    fn default() -> Self {
+        Self { locales: vec![&LOCALES[0]], words: HashMap::new() }
+    }
+}
+
+impl DateParser {
+    /// Locales are tried in order; unknown codes are an error.
+    pub fn new(codes: &[String], words: &BTreeMap<String, String>) -> Result<Self> {
+        let locales = codes
+            .iter()
+            .map(|code| {
+                DateLocale::find(code).ok_or_else(|| {
+                    let known: Vec<&str> = LOCALES.iter().map(|l| l.code).collect();
+                    anyhow!("unknown date locale '{}', expected one of {}", code, known.join(", "))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let words = words.iter().map(|(word, meaning)| (word.to_lowercase(), meaning.clone())).collect();
+        Ok(Self { locales, words })
+    }
+
+    /// Parses a due-date word as typed after `@` in quick-add: today,
+    /// tomorrow or next week in any configured locale, a weekday (`mon`,
+    /// `vendredi`: the next one after today), a user-defined word, an
+    /// offset (`+3d`, `2w`) or a date (`2024-07-01`).
+    pub fn parse_due_word(&self, word: &str, today: NaiveDate) -> Option<NaiveDate> {
+        let word = word.to_lowercase();
+        match self.words.get(&word) {
+            Some(meaning) => self.parse_builtin(&meaning.to_lowercase(), today),
+            None => self.parse_builtin(&word, today),
+        }
+    }
+
+    /// Parses when a snoozed task comes back: hours or minutes from now
+    /// (`2h`, `45min`), or a due-date word with an optional time (`tomorrow`,
+    /// `fri 14:00`, `2024-07-01 08:30`), at 9:00 when no time is given.
+    pub fn parse_snooze(&self, input: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let input = input.trim().trim_start_matches('@').to_lowercase();
+        if let Some(hours) = input.strip_suffix('h').and_then(|n| n.parse().ok()) {
+            return now.checked_add_signed(TimeDelta::try_hours(hours)?);
+        }
+        if let Some(minutes) = input.strip_suffix("min").and_then(|n| n.parse().ok()) {
+            return now.checked_add_signed(TimeDelta::try_minutes(minutes)?);
+        }
+
+        let mut words = input.split_whitespace();
+        let day = self.parse_due_word(words.next()?, now.date())?;
+        let time = match words.next() {
+            Some(time) => NaiveTime::parse_from_str(time, "%H:%M").ok()?,
+            None => NaiveTime::from_hms_opt(SNOOZE_WAKE_HOUR, 0, 0)?,
+        };
+        if words.next().is_some() {
+            return None;
+        }
+        Some(day.and_time(time))
+    }
+
+    fn parse_builtin(&self, word: &str, today: NaiveDate) -> Option<NaiveDate> {
+        if let Some(date) = self.locales.iter().find_map(|locale| locale.parse(word, today)) {
+            return Some(date);
+        }
+
+        let shift = if word.starts_with(|c: char| c.is_ascii_digit()) && !word.contains('-') {
+            DueShift::parse(&format!("+{}", word)).ok()?
+        } else {
+            DueShift::parse(word).ok()?
+        };
+        match shift {
+            DueShift::Clear => None,
+            shift => shift.apply(Some(today)),
+        }
+    }
+}
+
+/// Hour snoozed tasks come back on a day given without a time.
+const SNOOZE_WAKE_HOUR: u32 = 9;
+
+/// The next `weekday` strictly after `today`.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    today.checked_add_days(Days::new(if ahead == 0 { 7 } else { ahead as u64 }))
+}
+
+fn shift(date: NaiveDate, amount: i64, unit: OffsetUnit) -> Option<NaiveDate> {
+    let magnitude = amount.unsigned_abs();
+    match unit {
+        OffsetUnit::Days | OffsetUnit::Weeks => {
+            let days = Days::new(if unit == OffsetUnit::Weeks { magnitude * 7 } else { magnitude });
+            if amount < 0 { date.checked_sub_days(days) } else { date.checked_add_days(days) }
+        }
+        OffsetUnit::Months => {
+            let months = Months::new(u32::try_from(magnitude).ok()?);
+            if amount < 0 { date.checked_sub_months(months) } else { date.checked_add_months(months) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_offsets_and_dates() {
+        assert_eq!(DueShift::parse("+1d").unwrap(), DueShift::Offset { amount: 1, unit: OffsetUnit::Days });
+        assert_eq!(DueShift::parse("-2w").unwrap(), DueShift::Offset { amount: -2, unit: OffsetUnit::Weeks });
+        assert_eq!(DueShift::parse("+3").unwrap(), DueShift::Offset { amount: 3, unit: OffsetUnit::Days });
+        assert_eq!(DueShift::parse("2024-07-01").unwrap(), DueShift::Set(date("2024-07-01")));
+        assert_eq!(DueShift::parse("  ").unwrap(), DueShift::Clear);
+
+        assert!(DueShift::parse("+1y").is_err());
+        assert!(DueShift::parse("+d").is_err());
+        assert!(DueShift::parse("next week").is_err());
+    }
+
+    #[test]
+    fn test_apply_shift() {
+        let due = Some(date("2024-01-31"));
+        assert_eq!(DueShift::parse("+1w").unwrap().apply(due), Some(date("2024-02-07")));
+        assert_eq!(DueShift::parse("-1d").unwrap().apply(due), Some(date("2024-01-30")));
+        // Month offsets clamp to the end of shorter months
+        assert_eq!(DueShift::parse("+1m").unwrap().apply(due), Some(date("2024-02-29")));
+
+        // Offsets leave tasks without a due date alone; absolute dates set one
+        assert_eq!(DueShift::parse("+1d").unwrap().apply(None), None);
+        assert_eq!(DueShift::parse("2024-03-01").unwrap().apply(None), Some(date("2024-03-01")));
+        assert_eq!(DueShift::Clear.apply(due), None);
+    }
+
+    #[test]
+    fn test_parse_due_word() {
+        let parser = DateParser::default();
+        // A Sunday
+        let today = date("2024-03-10");
+        assert_eq!(parser.parse_due_word("today", today), Some(today));
+        assert_eq!(parser.parse_due_word("Tomorrow", today), Some(date("2024-03-11")));
+        assert_eq!(parser.parse_due_word("fri", today), Some(date("2024-03-15")));
+        assert_eq!(parser.parse_due_word("sunday", today), Some(date("2024-03-17")));
+        assert_eq!(parser.parse_due_word("next-week", today), Some(date("2024-03-11")));
+        assert_eq!(parser.parse_due_word("3d", today), Some(date("2024-03-13")));
+        assert_eq!(parser.parse_due_word("+1w", today), Some(date("2024-03-17")));
+        assert_eq!(parser.parse_due_word("2024-07-01", today), Some(date("2024-07-01")));
+        assert_eq!(parser.parse_due_word("john", today), None);
+        assert_eq!(parser.parse_due_word("mo", today), None);
+        assert_eq!(parser.parse_due_word("vendredi", today), None);
+    }
+
+    #[test]
+    fn test_parse_snooze() {
+        let parser = DateParser::default();
+        let now = date("2024-03-10").and_hms_opt(16, 30, 0).unwrap();
+        let at = |day: &str, hour: u32, minute: u32| date(day).and_hms_opt(hour, minute, 0).unwrap();
+        assert_eq!(parser.parse_snooze("2h", now), Some(at("2024-03-10", 18, 30)));
+        assert_eq!(parser.parse_snooze("45min", now), Some(at("2024-03-10", 17, 15)));
+        assert_eq!(parser.parse_snooze("@tomorrow", now), Some(at("2024-03-11", 9, 0)));
+        assert_eq!(parser.parse_snooze("fri 14:00", now), Some(at("2024-03-15", 14, 0)));
+        assert_eq!(parser.parse_snooze("2024-07-01 08:30", now), Some(at("2024-07-01", 8, 30)));
+        assert_eq!(parser.parse_snooze("tomorrow noon", now), None);
+        assert_eq!(parser.parse_snooze("", now), None);
+    }
+
+    #[test]
+    fn test_locales_and_custom_words() {
+        let today = date("2024-03-10");
+        let words = BTreeMap::from([("Übermorgen".to_string(), "+2d".to_string())]);
+        let parser = DateParser::new(&["de".to_string(), "fr".to_string(), "es".to_string()], &words).unwrap();
+
+        assert_eq!(parser.parse_due_word("morgen", today), Some(date("2024-03-11")));
+        assert_eq!(parser.parse_due_word("Freitag", today), Some(date("2024-03-15")));
+        assert_eq!(parser.parse_due_word("nächste-woche", today), Some(date("2024-03-11")));
+        assert_eq!(parser.parse_due_word("vendredi", today), Some(date("2024-03-15")));
+        assert_eq!(parser.parse_due_word("mié", today), Some(date("2024-03-13")));
+        assert_eq!(parser.parse_due_word("übermorgen", today), Some(date("2024-03-12")));
+        // Offsets and dates work in every locale; English words only when enabled
+        assert_eq!(parser.parse_due_word("2w", today), Some(date("2024-03-24")));
+        assert_eq!(parser.parse_due_word("tomorrow", today), None);
+
+        assert!(DateParser::new(&["xx".to_string()], &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let today = date("2024-03-10");
+        assert_eq!(parse_since("1w", today).unwrap(), date("2024-03-03"));
+        assert_eq!(parse_since("2d", today).unwrap(), date("2024-03-08"));
+        assert_eq!(parse_since("2024-01-01", today).unwrap(), date("2024-01-01"));
+        assert!(parse_since("", today).is_err());
+        assert!(parse_since("soon", today).is_err());
+    }
+}
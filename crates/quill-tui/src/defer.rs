@@ -0,0 +1,104 @@
+use crate::config::{AppConfig, ContextScope};
+use crate::git::GitContext;
+use crate::storage::TaskStorage;
+use crate::subtasks;
+use anyhow::{anyhow, Result};
+
+/// The context key tasks deferred to `branch` are stored under: the one
+/// checking the branch out later will open, after branch rules.
+pub fn branch_context(current: &GitContext, branch: &str, config: &AppConfig) -> Result<String> {
+    let branch = branch.trim();
+    if branch.is_empty() || branch.contains(char::is_whitespace) {
+        return Err(anyhow!("\"{}\" is not a branch name", branch));
+    }
+    if config.context_scope == ContextScope::Repo {
+        return Err(anyhow!("context_scope = \"repo\" keeps one list for every branch"));
+    }
+    let target = GitContext { branch: branch.to_string(), ..current.clone() };
+    let context = config
+        .resolve_context(target)
+        .ok_or_else(|| anyhow!("a branch rule ignores {}", branch))?;
+    if context.context_key() == current.context_key() {
+        return Err(anyhow!("{} shares this context's list", branch));
+    }
+    Ok(context.context_key())
+}
+
+/// The context key typed to move tasks to from `current`, such as when
+/// triaging the inbox into a repository's list.
+pub fn target_context(current: &GitContext, input: &str) -> Result<String> {
+    let input = input.trim();
+    let parts: Vec<&str> = input.splitn(3, ':').collect();
+    if parts.len() != 3 || parts.iter().any(|part| part.is_empty() || part.contains(char::is_whitespace)) {
+        return Err(anyhow!("\"{}\" is not a context, expected org:repo:branch", input));
+    }
+    if input == current.context_key() {
+        return Err(anyhow!("the tasks are already in {}", input));
+    }
+    Ok(input.to_string())
+}
+
+/// Moves the tasks `ids`, with their subtasks, from one context to another,
+/// keeping everything but their ids and places in the list. Returns how many tasks moved.
+pub async fn move_tasks(storage: &mut dyn TaskStorage, from: &str, ids: &[usize], to: &str) -> Result<usize> {
+    let tasks = storage.get_tasks(from).await?;
+    let mut moving: Vec<usize> = Vec::new();
+    for &id in ids {
+        for id in std::iter::once(id).chain(subtasks::descendants(id, &tasks)) {
+            if !moving.contains(&id) {
+                moving.push(id);
+            }
+        }
+    }
+
+    Ok(storage.move_tasks(from, &moving, to).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{local::LocalTaskStorage, TaskStatus};
+
+    #[tokio::test]
+    async fn test_move_tasks_with_subtasks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut storage = LocalTaskStorage::new(dir.path().join("todos.json").to_string_lossy().to_string()).unwrap();
+        let from = "acme:web:main";
+        let to = "acme:web:feature/cleanup";
+        storage.add_task(from, "Stays".to_string()).await.unwrap();
+        let parent = storage.add_task(from, "Clean up".to_string()).await.unwrap();
+        let child = storage.add_task(from, "Remove dead code".to_string()).await.unwrap();
+        let mut tasks = storage.get_tasks(from).await.unwrap();
+        tasks[2].parent = Some(parent);
+        storage.update_task(from, tasks[2].clone()).await.unwrap();
+        storage.set_task_status(from, child, TaskStatus::InProgress).await.unwrap();
+
+        assert_eq!(move_tasks(&mut storage, from, &[parent], to).await.unwrap(), 2);
+
+        let left = storage.get_tasks(from).await.unwrap();
+        assert_eq!(left.iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["Stays"]);
+        assert!(storage.list_deleted(from).await.unwrap().is_empty());
+        let moved = storage.get_tasks(to).await.unwrap();
+        assert_eq!(moved.len(), 2);
+        assert_eq!(moved[1].parent, Some(moved[0].id));
+        assert_eq!(moved[1].status, TaskStatus::InProgress);
+    }
+
+    #[test]
+    fn test_branch_context_follows_branch_rules() {
+        let current = GitContext { org: "acme".to_string(), repo: "web".to_string(), branch: "main".to_string() };
+        let config = AppConfig::default();
+        assert_eq!(branch_context(&current, " feature/cleanup ", &config).unwrap(), "acme:web:feature/cleanup");
+        assert!(branch_context(&current, "main", &config).is_err());
+        assert!(branch_context(&current, "two words", &config).is_err());
+    }
+
+    #[test]
+    fn test_target_context_needs_a_full_key() {
+        let inbox = GitContext::inbox();
+        assert_eq!(target_context(&inbox, " acme:web:main ").unwrap(), "acme:web:main");
+        assert!(target_context(&inbox, "acme:web").is_err());
+        assert!(target_context(&inbox, "acme::main").is_err());
+        assert!(target_context(&inbox, &inbox.context_key()).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::storage::{Task, TaskStatus};
+
+/// A context before and after a bulk operation (sync, bulk edit, template),
+/// kept so the operation can be reviewed and reverted as one unit.
+#[derive(Debug, Clone)]
+pub struct ContextDiff {
+    /// What produced the change, e.g. "GitHub sync".
+    pub operation: String,
+    pub context_key: String,
+    pub before: Vec<Task>,
+    pub after: Vec<Task>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Added(Task),
+    Removed(Task),
+    Modified { before: Box<Task>, after: Box<Task> },
+}
+
+impl ContextDiff {
+    pub fn new(operation: &str, context_key: &str, before: Vec<Task>, after: Vec<Task>) -> Self {
+        Self {
+            operation: operation.to_string(),
+            context_key: context_key.to_string(),
+            before,
+            after,
+        }
+    }
+
+    /// Added and modified tasks in their new order, then removed ones.
+    pub fn lines(&self) -> Vec<DiffLine> {
+        let mut lines = Vec::new();
+        for task in &self.after {
+            match self.before.iter().find(|t| t.uid == task.uid) {
+                None => lines.push(DiffLine::Added(task.clone())),
+                // Moves show up through `reordered`, not as edits
+                Some(previous) if *previous != (Task { order: previous.order, ..task.clone() }) => {
+                    lines.push(DiffLine::Modified { before: Box::new(previous.clone()), after: Box::new(task.clone()) })
+                }
+                Some(_) => {}
+            }
+        }
+        for task in &self.before {
+            if !self.after.iter().any(|t| t.uid == task.uid) {
+                lines.push(DiffLine::Removed(task.clone()));
+            }
+        }
+        lines
+    }
+
+    /// Whether tasks present both before and after changed their relative order.
+    pub fn reordered(&self) -> bool {
+        let kept: Vec<_> = self.before.iter().map(|t| t.uid).filter(|uid| self.after.iter().any(|t| t.uid == *uid)).collect();
+        let now: Vec<_> = self.after.iter().map(|t| t.uid).filter(|uid| kept.contains(uid)).collect();
+        kept != now
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines().is_empty() && !self.reordered()
+    }
+}
+
+/// Short descriptions of the fields that differ, e.g. `status: todo → done`.
+pub fn changed_fields(before: &Task, after: &Task) -> Vec<String> {
+    let mut fields = Vec::new();
+    if before.text != after.text {
+        fields.push(format!("text: {} → {}", before.text, after.text));
+    }
+    if before.status != after.status {
+        fields.push(format!("status: {} → {}", status_word(&before.status), status_word(&after.status)));
+    }
+    if before.tags != after.tags {
+        fields.push(format!("tags: [{}] → [{}]", before.tags.join(", "), after.tags.join(", ")));
+    }
+    if before.due != after.due {
+        let show = |due: Option<chrono::NaiveDate>| due.map(|d| d.to_string()).unwrap_or_else(|| "none".to_string());
+        fields.push(format!("due: {} → {}", show(before.due), show(after.due)));
+    }
+    if before.priority != after.priority {
+        fields.push(format!(
+            "priority: {} → {}",
+            crate::quickadd::priority_label(before.priority),
+            crate::quickadd::priority_label(after.priority)
+        ));
+    }
+    if before.must_finish != after.must_finish {
+        fields.push(format!("must finish: {} → {}", before.must_finish, after.must_finish));
+    }
+    if before.notes != after.notes {
+        fields.push("notes".to_string());
+    }
+    fields
+}
+
+fn status_word(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::NotStarted => "todo",
+        TaskStatus::InProgress => "doing",
+        TaskStatus::Completed => "done",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_and_changed_fields() {
+        let keep = Task::new(1, "Keep".to_string());
+        let mut done = Task::new(2, "Finish".to_string());
+        let gone = Task::new(3, "Gone".to_string());
+        let before = vec![keep.clone(), done.clone(), gone.clone()];
+
+        done.set_status(TaskStatus::Completed);
+        done.tags = vec!["ci".to_string()];
+        let new = Task::new(4, "New".to_string());
+        let diff = ContextDiff::new("GitHub sync", "a:b:main", before, vec![new.clone(), keep, done.clone()]);
+
+        let lines = diff.lines();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], DiffLine::Added(new));
+        assert!(matches!(&lines[1], DiffLine::Modified { after, .. } if after.id == 2));
+        assert_eq!(lines[2], DiffLine::Removed(gone));
+
+        let DiffLine::Modified { before, after } = &lines[1] else { unreachable!() };
+        assert_eq!(changed_fields(before, after), vec!["status: todo → done", "tags: [] → [ci]"]);
+    }
+
+    #[test]
+    fn test_reorder_only_is_not_empty() {
+        let tasks = vec![Task::new(1, "A".to_string()), Task::new(2, "B".to_string())];
+        let swapped = vec![tasks[1].clone(), tasks[0].clone()];
+
+        assert!(ContextDiff::new("Bulk edit", "a:b:main", tasks.clone(), tasks.clone()).is_empty());
+        let diff = ContextDiff::new("Bulk edit", "a:b:main", tasks, swapped);
+        assert!(diff.lines().is_empty());
+        assert!(!diff.is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::VecDeque;
+
+const KILL_RING_SIZE: usize = 16;
+
+/// Single-line text editor with a cursor and an Emacs/readline-style kill ring.
+///
+/// The kill ring survives `clear`/`set`, so text killed in one input popup
+/// can be yanked into another.
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    text: String,
+    /// Cursor position in characters, not bytes.
+    cursor: usize,
+    kill_ring: VecDeque<String>,
+    /// Char range and ring index of the most recent yank, while `yank_pop` is allowed.
+    last_yank: Option<(usize, usize, usize)>,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces the text and moves the cursor to the end.
+    pub fn set(&mut self, text: String) {
+        self.cursor = text.chars().count();
+        self.text = text;
+        self.last_yank = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let at = self.byte_index(self.cursor);
+        self.text.insert(at, c);
+        self.cursor += 1;
+        self.last_yank = None;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let at = self.byte_index(self.cursor);
+            self.text.remove(at);
+        }
+        self.last_yank = None;
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.len() {
+            let at = self.byte_index(self.cursor);
+            self.text.remove(at);
+        }
+        self.last_yank = None;
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+        self.last_yank = None;
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.len());
+        self.last_yank = None;
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+        self.last_yank = None;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.len();
+        self.last_yank = None;
+    }
+
+    /// Ctrl+K: kills from the cursor to the end of the line.
+    pub fn kill_to_end(&mut self) {
+        let at = self.byte_index(self.cursor);
+        let killed = self.text.split_off(at);
+        self.push_kill(killed);
+    }
+
+    /// Ctrl+U: kills from the start of the line to the cursor.
+    pub fn kill_to_start(&mut self) {
+        let at = self.byte_index(self.cursor);
+        let killed: String = self.text.drain(..at).collect();
+        self.cursor = 0;
+        self.push_kill(killed);
+    }
+
+    /// Ctrl+W: kills the word before the cursor, including trailing spaces.
+    pub fn kill_word_back(&mut self) {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut start = self.cursor;
+        while start > 0 && chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+
+        let (from, to) = (self.byte_index(start), self.byte_index(self.cursor));
+        let killed: String = self.text.drain(from..to).collect();
+        self.cursor = start;
+        self.push_kill(killed);
+    }
+
+    /// Ctrl+Y: inserts the most recently killed text at the cursor.
+    pub fn yank(&mut self) {
+        if let Some(text) = self.kill_ring.front().cloned() {
+            let start = self.cursor;
+            self.insert_str(&text);
+            self.last_yank = Some((start, text.chars().count(), 0));
+        }
+    }
+
+    /// Alt+Y: directly after a yank, replaces the yanked text with the
+    /// next older kill-ring entry.
+    pub fn yank_pop(&mut self) {
+        let Some((start, len, index)) = self.last_yank else {
+            return;
+        };
+
+        let next = (index + 1) % self.kill_ring.len();
+        let text = self.kill_ring[next].clone();
+
+        let (from, to) = (self.byte_index(start), self.byte_index(start + len));
+        self.text.replace_range(from..to, &text);
+        self.cursor = start + text.chars().count();
+        self.last_yank = Some((start, text.chars().count(), next));
+    }
+
+    fn insert_str(&mut self, s: &str) {
+        let at = self.byte_index(self.cursor);
+        self.text.insert_str(at, s);
+        self.cursor += s.chars().count();
+    }
+
+    fn push_kill(&mut self, killed: String) {
+        self.last_yank = None;
+        if killed.is_empty() {
+            return;
+        }
+        self.kill_ring.push_front(killed);
+        self.kill_ring.truncate(KILL_RING_SIZE);
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.text.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::new();
+        editor.set(text.to_string());
+        editor
+    }
+
+    #[test]
+    fn test_insert_and_backspace_at_cursor() {
+        let mut e = editor("helo");
+        e.left();
+        e.insert('l');
+        assert_eq!(e.text(), "hello");
+        assert_eq!(e.cursor(), 4);
+
+        e.backspace();
+        assert_eq!(e.text(), "helo");
+    }
+
+    #[test]
+    fn test_kill_to_end_and_yank() {
+        let mut e = editor("fix the bug");
+        e.home();
+        for _ in 0..3 {
+            e.right();
+        }
+        e.kill_to_end();
+        assert_eq!(e.text(), "fix");
+
+        e.end();
+        e.insert(' ');
+        e.yank();
+        assert_eq!(e.text(), "fix  the bug");
+    }
+
+    #[test]
+    fn test_kill_to_start() {
+        let mut e = editor("prefix rest");
+        e.home();
+        for _ in 0..7 {
+            e.right();
+        }
+        e.kill_to_start();
+        assert_eq!(e.text(), "rest");
+        assert_eq!(e.cursor(), 0);
+    }
+
+    #[test]
+    fn test_kill_word_back_handles_unicode() {
+        let mut e = editor("écrire le café");
+        e.kill_word_back();
+        assert_eq!(e.text(), "écrire le ");
+        e.kill_word_back();
+        assert_eq!(e.text(), "écrire ");
+    }
+
+    #[test]
+    fn test_kill_ring_survives_clear_and_yank_pop_cycles() {
+        let mut e = editor("first");
+        e.kill_to_start();
+        e.set("second".to_string());
+        e.kill_to_start();
+
+        e.clear();
+        e.yank();
+        assert_eq!(e.text(), "second");
+        e.yank_pop();
+        assert_eq!(e.text(), "first");
+        e.yank_pop();
+        assert_eq!(e.text(), "second");
+    }
+
+    #[test]
+    fn test_yank_pop_requires_preceding_yank() {
+        let mut e = editor("text");
+        e.kill_to_start();
+        e.insert('x');
+        e.yank_pop();
+        assert_eq!(e.text(), "x");
+    }
+}
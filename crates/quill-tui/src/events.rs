@@ -0,0 +1,130 @@
+use crate::storage::activity::{ActivityAction, ActivityEntry};
+use crate::storage::TaskStatus;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Created,
+    StatusChanged,
+    Edited,
+    Deleted,
+}
+
+/// One line of `quill export-events`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskEvent {
+    /// RFC 3339 time the event happened.
+    pub at: String,
+    pub event: EventKind,
+    pub context_key: String,
+    /// Stays the same when the task is reordered or moved to another context.
+    pub task_uid: String,
+    /// The task's text at the time.
+    pub text: String,
+    /// Who made the change, as recorded in the activity log.
+    pub actor: String,
+    /// The status a `status_changed` event moved the task to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<TaskStatus>,
+    /// The field a `status_changed` or `edited` event changed, with its
+    /// value before and after.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+}
+
+/// Events for the activity log's `entries`, in their order: additions,
+/// changes to one field and deletions of single tasks. Entries that don't
+/// create, change or delete a task (reordering, restoring, moving, purging
+/// from the trash) and those naming no task are left out.
+pub fn collect(entries: &[ActivityEntry]) -> Vec<TaskEvent> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let task_uid = entry.task_uid?.to_string();
+            let event = match entry.action {
+                ActivityAction::Added => EventKind::Created,
+                ActivityAction::Changed if entry.field.as_deref() == Some("status") => EventKind::StatusChanged,
+                ActivityAction::Changed => EventKind::Edited,
+                ActivityAction::Deleted => EventKind::Deleted,
+                _ => return None,
+            };
+            let changed = matches!(event, EventKind::StatusChanged | EventKind::Edited);
+            Some(TaskEvent {
+                at: entry.at.clone(),
+                event,
+                context_key: entry.context_key.clone(),
+                task_uid,
+                text: entry.task_text.clone(),
+                actor: entry.actor.clone(),
+                status: match event {
+                    EventKind::StatusChanged => entry.new.as_ref().and_then(|new| serde_json::from_value(serde_json::Value::String(new.clone())).ok()),
+                    _ => None,
+                },
+                field: entry.field.clone().filter(|_| changed),
+                old: entry.old.clone().filter(|_| changed),
+                new: entry.new.clone().filter(|_| changed),
+            })
+        })
+        .collect()
+}
+
+/// Renders events as JSON Lines, one object per line.
+pub fn to_jsonl(events: &[TaskEvent]) -> serde_json::Result<String> {
+    let mut out = String::new();
+    for event in events {
+        out.push_str(&serde_json::to_string(event)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Task;
+
+    #[test]
+    fn test_collect_maps_activity_to_events() {
+        let task = Task::new(1, "Ship it".to_string());
+        let entry = |action, at: &str| {
+            let mut entry = ActivityEntry::new("a:b:main", "ann@acme.dev", action, Some(&task));
+            entry.at = at.to_string();
+            entry
+        };
+        let mut status = entry(ActivityAction::Changed, "2024-03-02T09:00:00+00:00");
+        status.field = Some("status".to_string());
+        status.old = Some("NotStarted".to_string());
+        status.new = Some("Completed".to_string());
+        let mut notes = entry(ActivityAction::Changed, "2024-03-03T09:00:00+00:00");
+        notes.field = Some("notes".to_string());
+        notes.new = Some("See the spec".to_string());
+        let entries = vec![
+            entry(ActivityAction::Added, "2024-03-01T09:00:00+00:00"),
+            status,
+            notes,
+            entry(ActivityAction::Reordered, "2024-03-03T10:00:00+00:00"),
+            entry(ActivityAction::Deleted, "2024-03-04T09:00:00+00:00"),
+            ActivityEntry::new("a:b:main", "ann@acme.dev", ActivityAction::ContextDeleted, None),
+        ];
+
+        let events = collect(&entries);
+        let kinds: Vec<EventKind> = events.iter().map(|e| e.event).collect();
+        assert_eq!(kinds, vec![EventKind::Created, EventKind::StatusChanged, EventKind::Edited, EventKind::Deleted]);
+        assert_eq!(events[1].status, Some(TaskStatus::Completed));
+        assert_eq!(events[2].field.as_deref(), Some("notes"));
+
+        let line = to_jsonl(&events[1..2]).unwrap();
+        assert_eq!(
+            line,
+            format!(
+                "{{\"at\":\"2024-03-02T09:00:00+00:00\",\"event\":\"status_changed\",\"context_key\":\"a:b:main\",\"task_uid\":\"{}\",\"text\":\"Ship it\",\"actor\":\"ann@acme.dev\",\"status\":\"Completed\",\"field\":\"status\",\"old\":\"NotStarted\",\"new\":\"Completed\"}}\n",
+                task.uid
+            )
+        );
+    }
+}
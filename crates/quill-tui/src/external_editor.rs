@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Opens `initial` in `$VISUAL`/`$EDITOR` (falling back to `vi`) and returns
+/// the saved contents. `extension` picks the temp file's suffix so editors
+/// can choose a syntax mode.
+///
+/// The caller is responsible for leaving the TUI before calling this.
+pub fn edit(initial: &str, extension: &str) -> Result<String> {
+    let path = env::temp_dir().join(format!(
+        "quill-{}-{}.{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_millis(),
+        extension
+    ));
+    fs::write(&path, initial)?;
+
+    let editor = editor_command();
+    // Run through the shell so editors configured with arguments ("code --wait") work
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(format!("{} \"$1\"", editor)).arg("sh").arg(&path);
+    // Behind a pipe (`quill pick | fzf | quill pick edit`) the editor still needs the terminal
+    if !atty::is(atty::Stream::Stdin) {
+        if let Ok(tty) = fs::File::open("/dev/tty") {
+            command.stdin(tty);
+        }
+    }
+    let status = command.status();
+
+    let result = match status {
+        Ok(status) if status.success() => fs::read_to_string(&path).map_err(Into::into),
+        Ok(status) => Err(anyhow!("{} exited with {}", editor, status)),
+        Err(e) => Err(anyhow!("Failed to launch {}: {}", editor, e)),
+    };
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// Splits an editor buffer like a commit message: the first paragraph,
+/// joined into one line, is the task text and the rest are its notes.
+pub fn split_text_and_notes(buffer: &str) -> (String, String) {
+    let buffer = buffer.trim();
+    let (text, notes) = match buffer.find("\n\n") {
+        Some(end) => (&buffer[..end], &buffer[end..]),
+        None => (buffer, ""),
+    };
+    (text.split_whitespace().collect::<Vec<_>>().join(" "), notes.trim().to_string())
+}
+
+/// Opens `file` at `line` with `template` (placeholders `{file}` and
+/// `{line}`), or with `$VISUAL`/`$EDITOR +line file` when the template is empty.
+///
+/// The caller is responsible for leaving the TUI before calling this.
+pub fn open_at(template: &str, file: &Path, line: Option<u32>) -> Result<()> {
+    let command = open_command(template, &file.to_string_lossy(), line.unwrap_or(1));
+    match Command::new("sh").arg("-c").arg(&command).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(anyhow!("{} exited with {}", command, status)),
+        Err(e) => Err(anyhow!("Failed to launch {}: {}", command, e)),
+    }
+}
+
+/// Opens `url` with `$BROWSER`, falling back to `open` on macOS and
+/// `xdg-open` elsewhere.
+pub fn open_url(url: &str) -> Result<()> {
+    let command = format!("{} {}", browser_command(env::var("BROWSER").ok()), quote(url));
+    match Command::new("sh").arg("-c").arg(&command).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(anyhow!("{} exited with {}", command, status)),
+        Err(e) => Err(anyhow!("Failed to launch {}: {}", command, e)),
+    }
+}
+
+fn browser_command(browser: Option<String>) -> String {
+    match browser {
+        Some(browser) if !browser.trim().is_empty() => browser,
+        _ if cfg!(target_os = "macos") => "open".to_string(),
+        _ => "xdg-open".to_string(),
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn open_command(template: &str, file: &str, line: u32) -> String {
+    let quoted = quote(file);
+    if template.trim().is_empty() {
+        format!("{} +{} {}", editor_command(), line, quoted)
+    } else {
+        template.replace("{file}", &quoted).replace("{line}", &line.to_string())
+    }
+}
+
+fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_command_template() {
+        assert_eq!(open_command("code -g {file}:{line}", "src/app.rs", 42), "code -g 'src/app.rs':42");
+        assert_eq!(open_command("idea --line {line} {file}", "it's.rs", 1), "idea --line 1 'it'\\''s.rs'");
+    }
+
+    #[test]
+    fn test_split_text_and_notes() {
+        assert_eq!(
+            split_text_and_notes("Rework the login flow\nso sessions survive restarts\n\nSee #42.\n\n- keep tokens\n"),
+            ("Rework the login flow so sessions survive restarts".to_string(), "See #42.\n\n- keep tokens".to_string())
+        );
+        assert_eq!(split_text_and_notes("  Ship it  \n"), ("Ship it".to_string(), String::new()));
+    }
+
+    #[test]
+    fn test_browser_command() {
+        assert_eq!(browser_command(Some("firefox".to_string())), "firefox");
+        let fallback = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+        assert_eq!(browser_command(Some(" ".to_string())), fallback);
+        assert_eq!(browser_command(None), fallback);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::config::GitHubConfig;
+use crate::git::{Forge, GitContext};
+use crate::storage::Task;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long a GitHub request may take before it fails.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// An issue created from a context, remembered so the context can link to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssueLink {
+    pub number: u64,
+    pub url: String,
+    /// Links saved before GitLab support are GitHub issues.
+    #[serde(default)]
+    pub forge: Forge,
+    /// The GitLab project path the issue lives in, e.g. `group/sub/repo`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub project: String,
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct CreatedIssue {
+    number: u64,
+    html_url: String,
+}
+
+#[derive(Deserialize)]
+struct FetchedIssue {
+    #[serde(default)]
+    body: Option<String>,
+}
+
+pub struct GitHubClient {
+    http: reqwest::Client,
+    api_url: String,
+    token: String,
+}
+
+impl GitHubClient {
+    pub fn new(config: &GitHubConfig) -> Result<Self> {
+        let token = config
+            .resolve_token()?
+            .ok_or_else(|| anyhow!("No GitHub token: set github.token in config.toml, QUILL_GITHUB_TOKEN or GITHUB_TOKEN"))?;
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .user_agent(concat!("quill/", env!("CARGO_PKG_VERSION")))
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            api_url: config.api_url.trim_end_matches('/').to_string(),
+            token,
+        })
+    }
+
+    async fn current_user(&self) -> Result<String> {
+        let user: User = self
+            .http
+            .get(format!("{}/user", self.api_url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(user.login)
+    }
+
+    /// Creates an issue in the context's repository with its open tasks as a
+    /// checklist, assigned to the token's owner.
+    pub async fn export_context(&self, context: &GitContext, tasks: &[Task]) -> Result<IssueLink> {
+        let login = self.current_user().await?;
+        let body = serde_json::json!({
+            "title": issue_title(context),
+            "body": issue_body(context, tasks),
+            "assignees": [login],
+        });
+
+        let issue: CreatedIssue = self
+            .http
+            .post(format!("{}/repos/{}/{}/issues", self.api_url, context.org, context.repo))
+            .bearer_auth(&self.token)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(IssueLink { number: issue.number, url: issue.html_url, forge: Forge::GitHub, project: String::new() })
+    }
+
+    /// Returns the texts of the checklist items ticked in a linked issue.
+    pub async fn checked_items(&self, context: &GitContext, link: &IssueLink) -> Result<Vec<String>> {
+        let issue: FetchedIssue = self
+            .http
+            .get(format!("{}/repos/{}/{}/issues/{}", self.api_url, context.org, context.repo, link.number))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(parse_checked_items(issue.body.as_deref().unwrap_or_default()))
+    }
+}
+
+/// Extracts the text of `- [x]` items written by `issue_body`, without tag suffixes.
+pub fn parse_checked_items(body: &str) -> Vec<String> {
+    body.lines()
+        .filter_map(|line| {
+            let line = line.trim_start();
+            line.strip_prefix("- [x] ").or_else(|| line.strip_prefix("- [X] "))
+        })
+        .map(|item| {
+            let mut words: Vec<&str> = item.split_whitespace().collect();
+            while words.last().is_some_and(|w| w.starts_with("`#") && w.ends_with('`')) {
+                words.pop();
+            }
+            words.join(" ")
+        })
+        .collect()
+}
+
+pub fn issue_title(context: &GitContext) -> String {
+    if context.branch == "*" {
+        format!("Open tasks for {}", context.repo)
+    } else {
+        format!("Open tasks for {}", context.branch)
+    }
+}
+
+/// Renders the open tasks as a Markdown task list, which GitHub and GitLab both tick off.
+pub fn issue_body(context: &GitContext, tasks: &[Task]) -> String {
+    let mut body = format!("Handed off from quill context `{}`.\n\n", context.context_key());
+    for task in tasks.iter().filter(|t| !t.is_completed()) {
+        body.push_str(&format!("- [ ] {}", task.text));
+        for tag in &task.tags {
+            body.push_str(&format!(" `#{}`", tag));
+        }
+        body.push('\n');
+        for line in task.notes.lines() {
+            body.push_str(&format!("  > {}\n", line));
+        }
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TaskStatus;
+
+    #[test]
+    fn test_issue_body_lists_open_tasks() {
+        let context = GitContext {
+            org: "acme".to_string(),
+            repo: "widgets".to_string(),
+            branch: "feature/login".to_string(),
+        };
+        let mut open = Task::new(1, "Fix redirect".to_string());
+        open.tags = vec!["auth".to_string()];
+        open.notes = "See the SSO ticket".to_string();
+        let mut done = Task::new(2, "Done already".to_string());
+        done.status = TaskStatus::Completed;
+
+        let body = issue_body(&context, &[open, done]);
+        assert!(body.starts_with("Handed off from quill context `acme:widgets:feature/login`.\n\n"));
+        assert!(body.contains("- [ ] Fix redirect `#auth`\n  > See the SSO ticket\n"));
+        assert!(!body.contains("Done already"));
+        assert_eq!(issue_title(&context), "Open tasks for feature/login");
+    }
+
+    #[test]
+    fn test_parse_checked_items() {
+        let body = "Handed off\n\n- [x] Fix redirect `#auth`\n  > note\n- [ ] Still open\n- [X] Ship it\n";
+        assert_eq!(parse_checked_items(body), vec!["Fix redirect", "Ship it"]);
+    }
+}
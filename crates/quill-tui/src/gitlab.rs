@@ -0,0 +1,115 @@
+use crate::config::GitLabConfig;
+use crate::git::{Forge, GitContext};
+use crate::github::{issue_body, issue_title, parse_checked_items, IssueLink};
+use crate::storage::Task;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+struct User {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct CreatedIssue {
+    iid: u64,
+    web_url: String,
+}
+
+#[derive(Deserialize)]
+struct FetchedIssue {
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// How long a GitLab request may take before it fails.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub struct GitLabClient {
+    http: reqwest::Client,
+    api_url: String,
+    token: String,
+}
+
+impl GitLabClient {
+    pub fn new(config: &GitLabConfig) -> Result<Self> {
+        let token = config
+            .resolve_token()?
+            .ok_or_else(|| anyhow!("No GitLab token: set gitlab.token in config.toml, QUILL_GITLAB_TOKEN or GITLAB_TOKEN"))?;
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .user_agent(concat!("quill/", env!("CARGO_PKG_VERSION")))
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            api_url: config.api_url.trim_end_matches('/').to_string(),
+            token,
+        })
+    }
+
+    async fn current_user(&self) -> Result<u64> {
+        let user: User = self
+            .http
+            .get(format!("{}/user", self.api_url))
+            .header("PRIVATE-TOKEN", &self.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(user.id)
+    }
+
+    /// Creates an issue in `project` (its `group/sub/repo` path) with the
+    /// context's open tasks as a checklist, assigned to the token's owner.
+    pub async fn export_context(&self, project: &str, context: &GitContext, tasks: &[Task]) -> Result<IssueLink> {
+        let user_id = self.current_user().await?;
+        let body = serde_json::json!({
+            "title": issue_title(context),
+            "description": issue_body(context, tasks),
+            "assignee_ids": [user_id],
+        });
+
+        let issue: CreatedIssue = self
+            .http
+            .post(format!("{}/projects/{}/issues", self.api_url, project_id(project)))
+            .header("PRIVATE-TOKEN", &self.token)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(IssueLink { number: issue.iid, url: issue.web_url, forge: Forge::GitLab, project: project.to_string() })
+    }
+
+    /// Returns the texts of the checklist items ticked in a linked issue.
+    pub async fn checked_items(&self, link: &IssueLink) -> Result<Vec<String>> {
+        let issue: FetchedIssue = self
+            .http
+            .get(format!("{}/projects/{}/issues/{}", self.api_url, project_id(&link.project), link.number))
+            .header("PRIVATE-TOKEN", &self.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(parse_checked_items(issue.description.as_deref().unwrap_or_default()))
+    }
+}
+
+/// GitLab takes a project's path in place of its id once the slashes are escaped.
+fn project_id(path: &str) -> String {
+    path.replace('/', "%2F")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_id_escapes_subgroups() {
+        assert_eq!(project_id("acme/platform/api"), "acme%2Fplatform%2Fapi");
+    }
+}
@@ -0,0 +1,157 @@
+use crate::storage::{Priority, Task, TaskStatus};
+use crate::summary::Snapshot;
+use chrono::{DateTime, Days, NaiveDate, Utc};
+
+/// iCalendar lines are folded once they pass this many bytes.
+const MAX_LINE: usize = 75;
+
+/// Escapes a TEXT value: backslashes, separators and newlines, with CRLF
+/// and lone CR line endings (notes from some editors) taken as newlines.
+fn escape(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Appends a content line, folded with a leading space on each continuation.
+fn push_line(out: &mut String, line: &str) {
+    let mut start = 0;
+    let mut limit = MAX_LINE;
+    for (i, c) in line.char_indices() {
+        if i + c.len_utf8() - start > limit {
+            out.push_str(&line[start..i]);
+            out.push_str("\r\n ");
+            start = i;
+            limit = MAX_LINE - 1;
+        }
+    }
+    out.push_str(&line[start..]);
+    out.push_str("\r\n");
+}
+
+fn utc_stamp(at: &str) -> Option<String> {
+    DateTime::parse_from_rfc3339(at).ok().map(|at| at.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+fn date(day: NaiveDate) -> String {
+    day.format("%Y%m%d").to_string()
+}
+
+fn push_todo(out: &mut String, context_key: &str, task: &Task, stamp: &str) {
+    push_line(out, "BEGIN:VTODO");
+    push_line(out, &format!("UID:{}", escape(&format!("quill-{}-{}", context_key, task.id))));
+    push_line(out, &format!("DTSTAMP:{}", stamp));
+    if let Some(created) = utc_stamp(&task.created_at) {
+        push_line(out, &format!("CREATED:{}", created));
+    }
+    push_line(out, &format!("SUMMARY:{}", escape(&task.text)));
+    if !task.notes.is_empty() {
+        push_line(out, &format!("DESCRIPTION:{}", escape(&task.notes)));
+    }
+    // The context comes first so calendar apps can filter by repository and branch
+    let categories: Vec<String> = std::iter::once(context_key).chain(task.tags.iter().map(String::as_str)).map(escape).collect();
+    push_line(out, &format!("CATEGORIES:{}", categories.join(",")));
+    if let Some(due) = task.due {
+        push_line(out, &format!("DUE;VALUE=DATE:{}", date(due)));
+    }
+    let priority = match task.priority {
+        Priority::High => Some(1),
+        Priority::Medium => Some(5),
+        Priority::Low => Some(9),
+        Priority::None => None,
+    };
+    if let Some(priority) = priority {
+        push_line(out, &format!("PRIORITY:{}", priority));
+    }
+    let status = match task.status {
+        TaskStatus::NotStarted => "NEEDS-ACTION",
+        TaskStatus::InProgress => "IN-PROCESS",
+        TaskStatus::Completed => "COMPLETED",
+    };
+    push_line(out, &format!("STATUS:{}", status));
+    if let Some(completed) = task.completed_at.as_deref().filter(|_| task.is_completed()).and_then(utc_stamp) {
+        push_line(out, &format!("COMPLETED:{}", completed));
+    }
+    push_line(out, "END:VTODO");
+}
+
+/// An all-day event on the due date, for calendars that don't show to-dos.
+fn push_due_event(out: &mut String, context_key: &str, task: &Task, due: NaiveDate, stamp: &str) {
+    push_line(out, "BEGIN:VEVENT");
+    push_line(out, &format!("UID:{}", escape(&format!("quill-{}-{}-due", context_key, task.id))));
+    push_line(out, &format!("DTSTAMP:{}", stamp));
+    push_line(out, &format!("DTSTART;VALUE=DATE:{}", date(due)));
+    push_line(out, &format!("DTEND;VALUE=DATE:{}", date(due.checked_add_days(Days::new(1)).unwrap_or(due))));
+    push_line(out, &format!("SUMMARY:{}", escape(&format!("Due: {}", task.text))));
+    push_line(out, "TRANSP:TRANSPARENT");
+    push_line(out, "END:VEVENT");
+}
+
+/// Renders every task as a VTODO, plus a VEVENT on the due date of each open
+/// task that has one.
+pub fn render(snapshot: &Snapshot, now: DateTime<Utc>) -> String {
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, concat!("PRODID:-//quill//quill ", env!("CARGO_PKG_VERSION"), "//EN"));
+    push_line(&mut out, "X-WR-CALNAME:quill");
+
+    let mut context_keys: Vec<&String> = snapshot.keys().collect();
+    context_keys.sort();
+    for context_key in context_keys {
+        for task in &snapshot[context_key] {
+            push_todo(&mut out, context_key, task, &stamp);
+            if let Some(due) = task.due.filter(|_| !task.is_completed()) {
+                push_due_event(&mut out, context_key, task, due, &stamp);
+            }
+        }
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_todos_and_due_events() {
+        let mut due = Task::new(1, "Ship v2, finally".to_string());
+        due.due = NaiveDate::from_ymd_opt(2024, 7, 31);
+        due.tags = vec!["release".to_string()];
+        due.priority = Priority::High;
+        let mut done = Task::new(2, "Old".to_string());
+        done.status = TaskStatus::Completed;
+        done.due = NaiveDate::from_ymd_opt(2024, 7, 1);
+        let snapshot = Snapshot::from([("acme:web:main".to_string(), vec![due, done])]);
+
+        let ics = render(&snapshot, DateTime::parse_from_rfc3339("2024-07-20T10:00:00Z").unwrap().with_timezone(&Utc));
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.contains("SUMMARY:Ship v2\\, finally\r\n"));
+        assert!(ics.contains("CATEGORIES:acme:web:main,release\r\n"));
+        assert!(ics.contains("DUE;VALUE=DATE:20240731\r\nPRIORITY:1\r\nSTATUS:NEEDS-ACTION\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240731\r\nDTEND;VALUE=DATE:20240801\r\n"));
+        assert_eq!(ics.matches("BEGIN:VTODO").count(), 2);
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn test_escape_carriage_returns() {
+        assert_eq!(escape("one\r\ntwo\rthree\nfour"), "one\\ntwo\\nthree\\nfour");
+    }
+
+    #[test]
+    fn test_long_lines_are_folded() {
+        let mut out = String::new();
+        push_line(&mut out, &format!("SUMMARY:{}", "é".repeat(60)));
+        let lines: Vec<&str> = out.split("\r\n").filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.len() <= MAX_LINE));
+        assert!(lines[1].starts_with(' '));
+    }
+}
@@ -0,0 +1,313 @@
+use crate::config::AppConfig;
+use crate::git::{self, GitContext};
+use crate::storage::Priority;
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Todo files looked for in the home directory and the current repository.
+const FILE_NAMES: [&str; 6] = ["todo.txt", "TODO.txt", "todo.md", "TODO.md", "Documents/todo.txt", "Dropbox/todo/todo.txt"];
+
+/// A task read from another tool, with the context it should land in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedTask {
+    pub context_key: String,
+    pub text: String,
+    pub completed: bool,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    pub due: Option<NaiveDate>,
+    /// Line of the source file the task was read from.
+    pub line: Option<u32>,
+}
+
+impl ImportedTask {
+    fn new(context_key: &str, text: &str) -> Self {
+        Self {
+            context_key: context_key.to_string(),
+            text: text.to_string(),
+            completed: false,
+            priority: Priority::None,
+            tags: Vec::new(),
+            due: None,
+            line: None,
+        }
+    }
+}
+
+/// A file or tool that has tasks to offer.
+#[derive(Debug, Clone)]
+pub struct Source {
+    /// What the picker shows, e.g. `~/todo.txt`.
+    pub label: String,
+    /// The file read, relative to the repository root when inside one.
+    /// Imported tasks are anchored to their line of it.
+    pub file: Option<String>,
+    pub tasks: Vec<ImportedTask>,
+}
+
+impl Source {
+    /// `~/todo.txt — 12 tasks → local:todo:default`
+    pub fn summary(&self) -> String {
+        let mut contexts: Vec<&str> = self.tasks.iter().map(|t| t.context_key.as_str()).collect();
+        contexts.dedup();
+        let target = match contexts.as_slice() {
+            [context] => context.to_string(),
+            _ => format!("{} contexts", contexts.iter().collect::<HashSet<_>>().len()),
+        };
+        format!("{} — {} task(s) → {}", self.label, self.tasks.len(), target)
+    }
+}
+
+/// A context for tasks from outside any repository, e.g. `local:todo:default`.
+fn local_context(name: &str) -> String {
+    format!("local:{}:default", name.replace(':', "-"))
+}
+
+/// Reads todo.txt lines: `x` marks done, `(A)`-`(C)` set the priority,
+/// `+project` and `@context` become tags and `due:` the due date. Without
+/// `file_context`, each task goes to the context of its first `+project`.
+pub fn parse_todo_txt(text: &str, file_context: Option<&str>) -> Vec<ImportedTask> {
+    let is_date = |word: &str| NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok();
+    let mut tasks = Vec::new();
+    for (number, line) in text.lines().enumerate().map(|(i, l)| (i as u32 + 1, l.trim())).filter(|(_, l)| !l.is_empty()) {
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        let completed = words.first() == Some(&"x");
+        if completed {
+            words.remove(0);
+        }
+        let priority = match words.first().copied() {
+            Some("(A)") => Priority::High,
+            Some("(B)") => Priority::Medium,
+            Some("(C)") => Priority::Low,
+            _ => Priority::None,
+        };
+        if words.first().is_some_and(|w| w.len() == 3 && w.starts_with('(') && w.ends_with(')')) {
+            words.remove(0);
+        }
+        // Completion and creation dates
+        while words.first().is_some_and(|w| is_date(w)) {
+            words.remove(0);
+        }
+
+        let mut task = ImportedTask::new("", "");
+        let mut project = None;
+        let mut kept = Vec::new();
+        for word in words {
+            if let Some(name) = word.strip_prefix('+').filter(|n| !n.is_empty()) {
+                project.get_or_insert(name);
+                task.tags.push(name.to_string());
+            } else if let Some(name) = word.strip_prefix('@').filter(|n| !n.is_empty()) {
+                task.tags.push(name.to_string());
+            } else if let Some(due) = word.strip_prefix("due:").and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) {
+                task.due = Some(due);
+            } else {
+                kept.push(word);
+            }
+        }
+        if kept.is_empty() {
+            continue;
+        }
+        task.text = kept.join(" ");
+        task.completed = completed;
+        task.priority = priority;
+        task.line = Some(number);
+        task.context_key = match file_context {
+            Some(context) => context.to_string(),
+            None => local_context(project.unwrap_or("todo")),
+        };
+        tasks.push(task);
+    }
+    tasks
+}
+
+/// Reads Markdown checklist items (`- [ ]` and `- [x]`, also with `*`).
+pub fn parse_markdown(text: &str, context_key: &str) -> Vec<ImportedTask> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let item = line.trim_start().strip_prefix("- ").or_else(|| line.trim_start().strip_prefix("* "))?;
+            let (completed, text) = if let Some(text) = item.strip_prefix("[ ] ") {
+                (false, text)
+            } else {
+                (true, item.strip_prefix("[x] ").or_else(|| item.strip_prefix("[X] "))?)
+            };
+            let mut task = ImportedTask::new(context_key, text.trim());
+            task.completed = completed;
+            task.line = Some(i as u32 + 1);
+            Some(task).filter(|t| !t.text.is_empty())
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct WarriorTask {
+    description: String,
+    status: String,
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    priority: Option<String>,
+    /// e.g. `20240731T220000Z`
+    #[serde(default)]
+    due: Option<String>,
+}
+
+/// Reads the JSON printed by `task export`. Deleted and recurring template
+/// tasks are left out; each project becomes a `local` context.
+pub fn parse_taskwarrior(json: &str) -> Result<Vec<ImportedTask>> {
+    let exported: Vec<WarriorTask> = serde_json::from_str(json)?;
+    Ok(exported
+        .into_iter()
+        .filter(|t| matches!(t.status.as_str(), "pending" | "waiting" | "completed"))
+        .map(|t| {
+            let mut task = ImportedTask::new(&local_context(t.project.as_deref().unwrap_or("taskwarrior")), &t.description);
+            task.completed = t.status == "completed";
+            task.tags = t.tags;
+            task.priority = match t.priority.as_deref() {
+                Some("H") => Priority::High,
+                Some("M") => Priority::Medium,
+                Some("L") => Priority::Low,
+                _ => Priority::None,
+            };
+            task.due = t.due.and_then(|due| NaiveDate::parse_from_str(due.get(..8)?, "%Y%m%d").ok());
+            task
+        })
+        .collect())
+}
+
+/// Reads one todo file; tasks in a file inside a repository go to that
+/// repository's context.
+fn read_file(path: &Path, config: &AppConfig) -> Option<Vec<ImportedTask>> {
+    let text = fs::read_to_string(path).ok()?;
+    let in_repo = path
+        .parent()
+        .filter(|dir| git::repo_root_of(dir).is_some())
+        .and_then(|dir| GitContext::from_dir_with(dir, &config.forges).ok())
+        .and_then(|context| config.resolve_context(context))
+        .map(|context| context.context_key());
+
+    let markdown = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+    Some(match (markdown, in_repo) {
+        (true, Some(context)) => parse_markdown(&text, &context),
+        (true, None) => parse_markdown(&text, &local_context("todo")),
+        (false, context) => parse_todo_txt(&text, context.as_deref()),
+    })
+}
+
+fn candidate_files() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        paths.extend(FILE_NAMES.iter().map(|name| home.join(name)));
+    }
+    // todo.sh settings
+    if let Ok(dir) = std::env::var("TODO_DIR") {
+        paths.push(Path::new(&dir).join("todo.txt"));
+    }
+    if let Ok(file) = std::env::var("TODO_FILE") {
+        paths.push(PathBuf::from(file));
+    }
+    if let Some(root) = git::repo_root() {
+        paths.extend(FILE_NAMES[..4].iter().map(|name| root.join(name)));
+    }
+    paths
+}
+
+/// Tasks from `task export`, when Taskwarrior is installed and has data.
+fn taskwarrior() -> Option<Vec<ImportedTask>> {
+    let data = std::env::var_os("TASKDATA").map(PathBuf::from).or_else(|| dirs::home_dir().map(|home| home.join(".task")))?;
+    if !data.exists() {
+        return None;
+    }
+    let output = Command::new("task").args(["rc.verbose=nothing", "rc.hooks=off", "export"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_taskwarrior(&String::from_utf8_lossy(&output.stdout)).ok()
+}
+
+/// Looks in the usual places for todo.txt, TODO.md and Taskwarrior tasks,
+/// returning the sources that have any.
+pub fn scan(config: &AppConfig) -> Vec<Source> {
+    let home = dirs::home_dir();
+    let mut seen = HashSet::new();
+    let mut sources = Vec::new();
+    for path in candidate_files() {
+        // Case-insensitive filesystems find TODO.md and todo.md both
+        let Ok(canonical) = path.canonicalize() else {
+            continue;
+        };
+        if !seen.insert(canonical) {
+            continue;
+        }
+        let Some(tasks) = read_file(&path, config).filter(|tasks| !tasks.is_empty()) else {
+            continue;
+        };
+        let file = match git::repo_root_of(path.parent().unwrap_or(Path::new("."))) {
+            Some(root) => path.strip_prefix(&root).unwrap_or(&path).display().to_string(),
+            None => path.display().to_string(),
+        };
+        let label = match home.as_deref().and_then(|home| path.strip_prefix(home).ok()) {
+            Some(relative) => format!("~/{}", relative.display()),
+            None => path.display().to_string(),
+        };
+        sources.push(Source { label, file: Some(file), tasks });
+    }
+    if let Some(tasks) = taskwarrior().filter(|tasks| !tasks.is_empty()) {
+        sources.push(Source { label: "Taskwarrior".to_string(), file: None, tasks });
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_todo_txt() {
+        let text = "(A) 2024-07-01 Call the bank +finance @phone due:2024-07-31\nx 2024-07-02 2024-07-01 File taxes +finance\nWater plants\n\n";
+        let tasks = parse_todo_txt(text, None);
+
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].text, "Call the bank");
+        assert_eq!(tasks[0].priority, Priority::High);
+        assert_eq!(tasks[0].tags, vec!["finance", "phone"]);
+        assert_eq!(tasks[0].due, NaiveDate::from_ymd_opt(2024, 7, 31));
+        assert_eq!(tasks[0].context_key, "local:finance:default");
+        assert!(tasks[1].completed);
+        assert_eq!(tasks[1].text, "File taxes");
+        assert_eq!(tasks[2].context_key, "local:todo:default");
+        assert_eq!(tasks.iter().map(|t| t.line).collect::<Vec<_>>(), vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(parse_todo_txt("Ship it +web", Some("acme:web:main"))[0].context_key, "acme:web:main");
+    }
+
+    #[test]
+    fn test_parse_markdown_checklist() {
+        let text = "# Todo\n\n- [ ] Write docs\n* [x] Fix CI\n- plain bullet\n- [ ] \n";
+        let tasks = parse_markdown(text, "acme:web:main");
+        assert_eq!(tasks.iter().map(|t| (t.text.as_str(), t.completed)).collect::<Vec<_>>(), vec![("Write docs", false), ("Fix CI", true)]);
+        assert_eq!(tasks[1].line, Some(4));
+    }
+
+    #[test]
+    fn test_parse_taskwarrior_export() {
+        let json = r#"[
+            {"description":"Renew passport","status":"pending","project":"home","tags":["admin"],"priority":"M","due":"20240801T220000Z"},
+            {"description":"Old","status":"deleted"},
+            {"description":"Done thing","status":"completed"}
+        ]"#;
+        let tasks = parse_taskwarrior(json).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].context_key, "local:home:default");
+        assert_eq!(tasks[0].priority, Priority::Medium);
+        assert_eq!(tasks[0].due, NaiveDate::from_ymd_opt(2024, 8, 1));
+        assert!(tasks[1].completed);
+        assert_eq!(tasks[1].context_key, "local:taskwarrior:default");
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event, KeyEventKind};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Where `App::run_app` reads terminal events from.
+pub trait EventSource {
+    /// The next event, waiting at most `timeout`; `None` when none came.
+    fn next(&mut self, timeout: Duration) -> Result<Option<Event>>;
+
+    /// Whether no more events will come, which ends the session.
+    fn is_done(&self) -> bool {
+        false
+    }
+}
+
+/// Events typed into the real terminal.
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next(&mut self, timeout: Duration) -> Result<Option<Event>> {
+        Ok(if event::poll(timeout)? { Some(event::read()?) } else { None })
+    }
+}
+
+/// A session saved by `quill --record`. On disk it is JSON Lines: a
+/// header with the terminal size, then one [`RecordedEvent`] per line.
+#[derive(Debug, Default, PartialEq)]
+pub struct Recording {
+    /// Columns and rows of the terminal it was recorded in.
+    pub size: (u16, u16),
+    pub events: Vec<RecordedEvent>,
+}
+
+/// The first line of a recording.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    size: (u16, u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Milliseconds since the session's first event was asked for.
+    pub at_ms: u64,
+    pub event: Event,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("can't read {}", path.display()))?;
+        let not_a_recording = || format!("{} is not a quill recording", path.display());
+        let mut lines = text.lines();
+        let header: Header = serde_json::from_str(lines.next().unwrap_or_default()).with_context(not_a_recording)?;
+        let mut events = Vec::new();
+        for line in lines {
+            match serde_json::from_str(line) {
+                Ok(event) => events.push(event),
+                // The last line may be cut short by the crash being reproduced
+                Err(e) if e.is_eof() => break,
+                Err(e) => return Err(e).with_context(not_a_recording),
+            }
+        }
+        Ok(Self { size: header.size, events })
+    }
+}
+
+/// Passes on `inner`'s events, appending each one and when it came to the
+/// recording file as it arrives, so a session that crashes is kept.
+pub struct Recorder<S> {
+    inner: S,
+    started: Option<Instant>,
+    file: fs::File,
+}
+
+impl<S: EventSource> Recorder<S> {
+    /// Starts the recording at `path`, replacing what was there.
+    pub fn create(inner: S, size: (u16, u16), path: &Path) -> Result<Self> {
+        let mut file = fs::File::create(path).with_context(|| format!("can't write {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&Header { size })?)?;
+        Ok(Self { inner, started: None, file })
+    }
+}
+
+impl<S: EventSource> EventSource for Recorder<S> {
+    fn next(&mut self, timeout: Duration) -> Result<Option<Event>> {
+        // Timing starts once the app is up, so a slow storage connection isn't recorded
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let event = self.inner.next(timeout)?;
+        if let Some(event) = &event {
+            let at_ms = started.elapsed().as_millis() as u64;
+            let line = serde_json::to_string(&RecordedEvent { at_ms, event: event.clone() })?;
+            // Losing the recording isn't worth ending the session over
+            if let Err(e) = writeln!(self.file, "{}", line) {
+                tracing::warn!(error = %e, "couldn't write to the recording");
+            }
+        }
+        Ok(event)
+    }
+
+    fn is_done(&self) -> bool {
+        self.inner.is_done()
+    }
+}
+
+/// Plays a recording back at its original pace. A key pressed in `live`
+/// stops it early, and when it stops `live` takes over.
+pub struct Replay<S> {
+    events: VecDeque<RecordedEvent>,
+    live: S,
+    started: Option<Instant>,
+}
+
+impl<S: EventSource> Replay<S> {
+    pub fn new(recording: Recording, live: S) -> Self {
+        Self { events: recording.events.into(), live, started: None }
+    }
+
+    /// Whether recorded events are still to come.
+    pub fn is_playing(&self) -> bool {
+        !self.events.is_empty()
+    }
+}
+
+impl<S: EventSource> EventSource for Replay<S> {
+    fn next(&mut self, timeout: Duration) -> Result<Option<Event>> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let Some(due) = self.events.front().map(|next| started + Duration::from_millis(next.at_ms)) else {
+            return self.live.next(timeout);
+        };
+        let wait = due.saturating_duration_since(Instant::now()).min(timeout);
+        match self.live.next(wait)? {
+            Some(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                self.events.clear();
+                return Ok(None);
+            }
+            // Resizes and the like still reach the app
+            Some(event) if !matches!(event, Event::Key(_)) => return Ok(Some(event)),
+            _ => {}
+        }
+        if Instant::now() < due {
+            return Ok(None);
+        }
+        Ok(self.events.pop_front().map(|recorded| recorded.event))
+    }
+
+    fn is_done(&self) -> bool {
+        !self.is_playing() && self.live.is_done()
+    }
+}
+
+/// Events handed out in order; the session ends when they run out.
+#[cfg(test)]
+pub struct Script(pub VecDeque<Event>);
+
+#[cfg(test)]
+impl EventSource for Script {
+    fn next(&mut self, _timeout: Duration) -> Result<Option<Event>> {
+        Ok(self.0.pop_front())
+    }
+
+    fn is_done(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use tempfile::TempDir;
+
+    fn key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let mut recorder = Recorder::create(Script(VecDeque::from([key('a'), key('x')])), (100, 30), &path).unwrap();
+        recorder.next(Duration::ZERO).unwrap();
+        // Written as it comes, before the session ends
+        assert_eq!(Recording::load(&path).unwrap().events.len(), 1);
+        while !recorder.is_done() {
+            recorder.next(Duration::ZERO).unwrap();
+        }
+        // A line cut short by a crash is dropped
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"at_ms\":9").unwrap();
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.size, (100, 30));
+        assert_eq!(recording.events.len(), 2);
+
+        let mut replay = Replay::new(recording, Script(VecDeque::new()));
+        let mut replayed = Vec::new();
+        while replay.is_playing() {
+            replayed.extend(replay.next(Duration::from_millis(10)).unwrap());
+        }
+        assert_eq!(replayed, vec![key('a'), key('x')]);
+        assert!(replay.is_done());
+
+        // A key pressed during the replay stops it
+        let recording = Recording { size: (100, 30), events: vec![RecordedEvent { at_ms: 60_000, event: key('a') }] };
+        let mut replay = Replay::new(recording, Script(VecDeque::from([key('q')])));
+        assert_eq!(replay.next(Duration::from_millis(10)).unwrap(), None);
+        assert!(!replay.is_playing());
+    }
+}
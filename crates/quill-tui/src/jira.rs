@@ -0,0 +1,304 @@
+use crate::config::{JiraConfig, JiraProject};
+use crate::storage::{Task, TaskStatus};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How long a Jira request may take before it fails.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Issues asked for per search request.
+const PAGE_SIZE: usize = 100;
+
+/// A Jira issue as far as sync needs it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub key: String,
+    pub summary: String,
+    /// Jira status name, e.g. `In Progress`.
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResult {
+    issues: Vec<FetchedIssue>,
+    /// Jira Cloud: where the next page starts, absent on the last page.
+    #[serde(default)]
+    next_page_token: Option<String>,
+    /// Jira Server: how many issues match in all.
+    #[serde(default)]
+    total: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct FetchedIssue {
+    key: String,
+    fields: Fields,
+}
+
+#[derive(Deserialize)]
+struct Fields {
+    summary: String,
+    status: Named,
+}
+
+#[derive(Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Transitions {
+    transitions: Vec<Transition>,
+}
+
+#[derive(Deserialize)]
+struct Transition {
+    id: String,
+    to: Named,
+}
+
+enum Auth {
+    /// Jira Cloud: account email and API token.
+    Basic { email: String, token: String },
+    /// Jira Server personal access token.
+    Bearer(String),
+}
+
+pub struct JiraClient {
+    http: reqwest::Client,
+    url: String,
+    auth: Auth,
+}
+
+impl JiraClient {
+    pub fn new(config: &JiraConfig) -> Result<Self> {
+        if !config.is_enabled() {
+            return Err(anyhow!("Jira is not set up: set jira.url in config.toml"));
+        }
+        let token = config
+            .resolve_token()?
+            .ok_or_else(|| anyhow!("No Jira token: set jira.token in config.toml or QUILL_JIRA_TOKEN"))?;
+        let auth = if config.email.is_empty() {
+            Auth::Bearer(token)
+        } else {
+            Auth::Basic { email: config.email.clone(), token }
+        };
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .user_agent(concat!("quill/", env!("CARGO_PKG_VERSION")))
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            url: config.url.trim_end_matches('/').to_string(),
+            auth,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.request_v(2, method, path)
+    }
+
+    fn request_v(&self, version: u8, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}/rest/api/{}/{}", self.url, version, path));
+        match &self.auth {
+            Auth::Basic { email, token } => request.basic_auth(email, Some(token)),
+            Auth::Bearer(token) => request.bearer_auth(token),
+        }
+    }
+
+    /// Every issue `jql` matches, fetched a page at a time.
+    pub async fn search(&self, jql: &str) -> Result<Vec<Issue>> {
+        let mut issues = Vec::new();
+        let mut next_page_token = None;
+        loop {
+            let request = match &self.auth {
+                // Jira Cloud pages by token; its offset-paged search is retired
+                Auth::Basic { .. } => {
+                    let request = self.request_v(3, reqwest::Method::GET, "search/jql");
+                    match &next_page_token {
+                        Some(token) => request.query(&[("nextPageToken", token)]),
+                        None => request,
+                    }
+                }
+                // Jira Server only has the offset-paged one
+                Auth::Bearer(_) => self.request(reqwest::Method::GET, "search").query(&[("startAt", issues.len())]),
+            };
+            let page: SearchResult = request
+                .query(&[("jql", jql), ("fields", "summary,status")])
+                .query(&[("maxResults", PAGE_SIZE)])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let fetched = page.issues.len();
+            issues.extend(
+                page.issues
+                    .into_iter()
+                    .map(|issue| Issue { key: issue.key, summary: issue.fields.summary, status: issue.fields.status.name }),
+            );
+            next_page_token = page.next_page_token;
+            let last_page = match self.auth {
+                Auth::Basic { .. } => next_page_token.is_none(),
+                Auth::Bearer(_) => fetched == 0 || page.total.is_none_or(|total| issues.len() >= total),
+            };
+            if last_page {
+                return Ok(issues);
+            }
+        }
+    }
+
+    /// Moves an issue to a Jira status that maps to `status` and returns
+    /// that status's name.
+    pub async fn transition(&self, key: &str, config: &JiraConfig, status: &TaskStatus) -> Result<String> {
+        let available: Transitions = self
+            .request(reqwest::Method::GET, &format!("issue/{}/transitions", key))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let transition = available
+            .transitions
+            .into_iter()
+            .find(|t| config.status_for(&t.to.name).as_ref() == Some(status))
+            .ok_or_else(|| anyhow!("no transition of {} leads to a status mapped to {:?}", key, status))?;
+
+        self.request(reqwest::Method::POST, &format!("issue/{}/transitions", key))
+            .json(&serde_json::json!({ "transition": { "id": transition.id } }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(transition.to.name)
+    }
+}
+
+/// The issue key a branch is named after, e.g. `PROJ-123` in `feature/PROJ-123-fix`.
+pub fn issue_key_in_branch(branch: &str) -> Option<String> {
+    let chars: Vec<char> = branch.chars().collect();
+    for start in 0..chars.len() {
+        if !chars[start].is_ascii_uppercase() || (start > 0 && chars[start - 1].is_ascii_alphanumeric()) {
+            continue;
+        }
+        let mut i = start;
+        while i < chars.len() && (chars[i].is_ascii_uppercase() || chars[i].is_ascii_digit()) {
+            i += 1;
+        }
+        if i - start < 2 || chars.get(i) != Some(&'-') {
+            continue;
+        }
+        let digits = i + 1;
+        let mut end = digits;
+        while end < chars.len() && chars[end].is_ascii_digit() {
+            end += 1;
+        }
+        if end > digits && chars.get(end).is_none_or(|c| !c.is_ascii_alphanumeric()) {
+            return Some(chars[start..end].iter().collect());
+        }
+    }
+    None
+}
+
+/// The query for a context: my issues in its project plus the issues named
+/// by `keys`. `None` when there is nothing to ask for.
+pub fn jql(project: Option<&JiraProject>, keys: &[String]) -> Option<String> {
+    let mut clauses = Vec::new();
+    if let Some(project) = project {
+        let mut mine = format!("assignee = currentUser() AND project = \"{}\"", project.project);
+        if let Some(epic) = &project.epic {
+            mine.push_str(&format!(" AND parent = \"{}\"", epic));
+        }
+        clauses.push(format!("({})", mine));
+    }
+    if !keys.is_empty() {
+        clauses.push(format!("key in ({})", keys.join(", ")));
+    }
+    (!clauses.is_empty()).then(|| format!("{} ORDER BY created", clauses.join(" OR ")))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncAction {
+    /// A task for an open issue no task is linked to yet.
+    Add { issue: Issue, status: TaskStatus },
+    /// The issue's status changed in Jira since the last sync.
+    Pull { task_id: usize, status: TaskStatus },
+    /// The task's status changed in quill since the last sync.
+    Push { key: String, status: TaskStatus },
+}
+
+/// Works out what a sync does. `seen` holds each issue's Jira status at the
+/// last sync; an issue whose status moved since then wins over the task.
+pub fn plan(config: &JiraConfig, tasks: &[Task], issues: &[Issue], seen: &HashMap<String, String>) -> Vec<SyncAction> {
+    let mut actions = Vec::new();
+    for issue in issues {
+        let mapped = config.status_for(&issue.status);
+        let Some(task) = tasks.iter().find(|t| t.issue.as_deref() == Some(issue.key.as_str())) else {
+            if mapped != Some(TaskStatus::Completed) {
+                actions.push(SyncAction::Add { issue: issue.clone(), status: mapped.unwrap_or_default() });
+            }
+            continue;
+        };
+        let Some(mapped) = mapped else {
+            continue;
+        };
+        if mapped == task.status {
+            continue;
+        }
+        if seen.get(&issue.key) != Some(&issue.status) {
+            actions.push(SyncAction::Pull { task_id: task.id, status: mapped });
+        } else {
+            actions.push(SyncAction::Push { key: issue.key.clone(), status: task.status.clone() });
+        }
+    }
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(key: &str, status: &str) -> Issue {
+        Issue { key: key.to_string(), summary: format!("Work on {}", key), status: status.to_string() }
+    }
+
+    #[test]
+    fn test_issue_key_in_branch() {
+        assert_eq!(issue_key_in_branch("PROJ-123-fix").as_deref(), Some("PROJ-123"));
+        assert_eq!(issue_key_in_branch("feature/WEB-7_login").as_deref(), Some("WEB-7"));
+        assert_eq!(issue_key_in_branch("main"), None);
+        assert_eq!(issue_key_in_branch("fix-123"), None);
+        assert_eq!(issue_key_in_branch("xPROJ-12"), None);
+    }
+
+    #[test]
+    fn test_jql() {
+        let project = JiraProject { context: "acme:*".to_string(), project: "WEB".to_string(), epic: Some("WEB-1".to_string()) };
+        assert_eq!(
+            jql(Some(&project), &["OPS-4".to_string()]).unwrap(),
+            "(assignee = currentUser() AND project = \"WEB\" AND parent = \"WEB-1\") OR key in (OPS-4) ORDER BY created"
+        );
+        assert_eq!(jql(None, &[]), None);
+    }
+
+    #[test]
+    fn test_plan_pulls_new_jira_changes_and_pushes_local_ones() {
+        let config = JiraConfig::default();
+        let mut pulled = Task::new(1, "Pulled".to_string());
+        pulled.issue = Some("WEB-1".to_string());
+        let mut pushed = Task::new(2, "Pushed".to_string());
+        pushed.issue = Some("WEB-2".to_string());
+        pushed.status = TaskStatus::Completed;
+        let seen = HashMap::from([("WEB-1".to_string(), "To Do".to_string()), ("WEB-2".to_string(), "To Do".to_string())]);
+        let issues = vec![issue("WEB-1", "In Progress"), issue("WEB-2", "To Do"), issue("WEB-3", "To Do"), issue("WEB-4", "Done")];
+
+        assert_eq!(
+            plan(&config, &[pulled, pushed], &issues, &seen),
+            vec![
+                SyncAction::Pull { task_id: 1, status: TaskStatus::InProgress },
+                SyncAction::Push { key: "WEB-2".to_string(), status: TaskStatus::Completed },
+                SyncAction::Add { issue: issue("WEB-3", "To Do"), status: TaskStatus::NotStarted },
+            ]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use crate::summary::Snapshot;
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::BTreeMap;
+
+/// A task finished on a given day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub branch: String,
+    pub text: String,
+}
+
+/// Completed tasks grouped by local day (newest first when rendered) and repository.
+#[derive(Debug, Default)]
+pub struct Journal {
+    pub days: BTreeMap<NaiveDate, BTreeMap<String, Vec<JournalEntry>>>,
+}
+
+impl Journal {
+    /// Collects every completed task whose `completed_at` falls on or after `since`.
+    pub fn collect(snapshot: &Snapshot, since: NaiveDate) -> Self {
+        let mut journal = Self::default();
+
+        for (context_key, tasks) in snapshot {
+            let mut parts = context_key.splitn(3, ':');
+            let (org, repo, branch) = (
+                parts.next().unwrap_or_default(),
+                parts.next().unwrap_or_default(),
+                parts.next().unwrap_or_default(),
+            );
+
+            for task in tasks.iter().filter(|t| t.is_completed()) {
+                let Some(day) = task
+                    .completed_at
+                    .as_deref()
+                    .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+                    .map(|at| at.with_timezone(&Local).date_naive())
+                else {
+                    continue;
+                };
+                if day < since {
+                    continue;
+                }
+
+                journal
+                    .days
+                    .entry(day)
+                    .or_default()
+                    .entry(format!("{}/{}", org, repo))
+                    .or_default()
+                    .push(JournalEntry { branch: branch.to_string(), text: task.text.clone() });
+            }
+        }
+        journal
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.days.is_empty()
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        for (day, repos) in self.days.iter().rev() {
+            out.push_str(&format!("{}\n", day.format("%Y-%m-%d (%a)")));
+            for (repo, entries) in repos {
+                out.push_str(&format!("  {}\n", repo));
+                for entry in entries {
+                    out.push_str(&format!("    ✓ {}{}\n", entry.text, branch_suffix(&entry.branch, |b| format!("  [{}]", b))));
+                }
+            }
+        }
+        out
+    }
+
+    pub fn render_markdown(&self) -> String {
+        let mut sections = Vec::new();
+        for (day, repos) in self.days.iter().rev() {
+            let mut section = format!("## {}\n", day.format("%Y-%m-%d (%A)"));
+            for (repo, entries) in repos {
+                section.push_str(&format!("\n### {}\n\n", repo));
+                for entry in entries {
+                    section.push_str(&format!("- {}{}\n", entry.text, branch_suffix(&entry.branch, |b| format!(" (`{}`)", b))));
+                }
+            }
+            sections.push(section);
+        }
+        format!("# Journal\n\n{}", sections.join("\n"))
+    }
+}
+
+/// Formats the branch, or nothing for repository-wide contexts.
+fn branch_suffix(branch: &str, format: impl Fn(&str) -> String) -> String {
+    if branch.is_empty() || branch == "*" {
+        String::new()
+    } else {
+        format(branch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Task, TaskStatus};
+
+    fn completed(id: usize, text: &str, at: &str) -> Task {
+        let mut task = Task::new(id, text.to_string());
+        task.status = TaskStatus::Completed;
+        task.completed_at = Some(at.to_string());
+        task
+    }
+
+    fn local_noon(day: &str) -> String {
+        let date = NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap();
+        date.and_hms_opt(12, 0, 0).unwrap().and_local_timezone(Local).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn test_collect_groups_by_day_and_repo() {
+        let snapshot = Snapshot::from([
+            (
+                "acme:widgets:feature/login".to_string(),
+                vec![
+                    completed(1, "Fix login", &local_noon("2024-03-08")),
+                    completed(2, "Too old", &local_noon("2024-02-01")),
+                    Task::new(3, "Still open".to_string()),
+                ],
+            ),
+            ("acme:infra:*".to_string(), vec![completed(4, "Rotate keys", &local_noon("2024-03-08"))]),
+        ]);
+
+        let since = NaiveDate::parse_from_str("2024-03-01", "%Y-%m-%d").unwrap();
+        let journal = Journal::collect(&snapshot, since);
+        assert_eq!(journal.days.len(), 1);
+
+        let text = journal.render_text();
+        assert!(text.starts_with("2024-03-08 (Fri)\n  acme/infra\n    ✓ Rotate keys\n  acme/widgets\n"));
+        assert!(text.contains("    ✓ Fix login  [feature/login]\n"));
+
+        let markdown = journal.render_markdown();
+        assert!(markdown.contains("## 2024-03-08 (Friday)\n"));
+        assert!(markdown.contains("### acme/widgets\n\n- Fix login (`feature/login`)\n"));
+    }
+
+    #[test]
+    fn test_tasks_without_completed_at_are_skipped() {
+        let mut task = Task::new(1, "Legacy".to_string());
+        task.status = TaskStatus::Completed;
+        let snapshot = Snapshot::from([("a:b:main".to_string(), vec![task])]);
+
+        assert!(Journal::collect(&snapshot, NaiveDate::MIN).is_empty());
+    }
+}
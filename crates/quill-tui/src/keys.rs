@@ -0,0 +1,232 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::time::{Duration, Instant};
+
+/// How long a lone `1`/`2`/`3` waits for a motion before it sets the status instead.
+pub const STATUS_DIGIT_TIMEOUT: Duration = Duration::from_millis(600);
+
+/// How long a chord's first key waits for the rest before it runs its own action.
+pub const CHORD_TIMEOUT: Duration = Duration::from_millis(600);
+
+/// What a normal-mode key means once combined with the keys typed before it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolved {
+    /// Part of a count or operator; wait for more keys.
+    Pending,
+    /// A pending count or operator was abandoned.
+    Cancelled,
+    /// Run the key's action `count` times.
+    Key { count: usize, key: KeyCode, modifiers: KeyModifiers },
+    /// A configured chord such as `dd`, with an optional count (`3dd`).
+    Chord { action: String, count: usize },
+    /// A lone `1`/`2`/`3` that didn't turn into a count: set the status,
+    /// then handle the key that followed it.
+    StatusDigit { digit: char, then: (KeyCode, KeyModifiers) },
+}
+
+/// Vim-style count prefixes (`5j`) and chords (`3dd`) typed so far.
+#[derive(Debug, Default)]
+pub struct PendingKeys {
+    digits: String,
+    chord: String,
+    since: Option<Instant>,
+    /// Key sequences and the actions they run.
+    chords: Vec<(String, String)>,
+    /// Chord first keys that run their own action when nothing follows.
+    standalone: Vec<char>,
+}
+
+impl PendingKeys {
+    pub fn set_chords(&mut self, chords: Vec<(String, String)>, standalone: Vec<char>) {
+        self.clear();
+        self.chords = chords;
+        self.standalone = standalone;
+    }
+
+    /// Feeds one key. `takes_count` tells whether a key's action can be repeated,
+    /// which decides whether a lone `1`/`2`/`3` before it was a count.
+    pub fn feed(
+        &mut self,
+        key: KeyCode,
+        modifiers: KeyModifiers,
+        now: Instant,
+        takes_count: impl Fn(KeyCode, KeyModifiers) -> bool,
+    ) -> Resolved {
+        let plain = !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+
+        if key == KeyCode::Esc && !self.is_empty() {
+            self.clear();
+            return Resolved::Cancelled;
+        }
+
+        if let KeyCode::Char(c) = key {
+            if plain && c.is_ascii_digit() && self.chord.is_empty() && (c != '0' || !self.digits.is_empty()) {
+                self.digits.push(c);
+                self.since = Some(now);
+                return Resolved::Pending;
+            }
+
+            let typed = format!("{}{}", self.chord, c);
+            if plain {
+                if let Some((_, action)) = self.chords.iter().find(|(keys, _)| *keys == typed) {
+                    let resolved = Resolved::Chord { action: action.clone(), count: self.count() };
+                    self.clear();
+                    return resolved;
+                }
+                if self.chords.iter().any(|(keys, _)| keys.starts_with(&typed)) {
+                    self.chord = typed;
+                    self.since = Some(now);
+                    return Resolved::Pending;
+                }
+            }
+        }
+
+        if !self.chord.is_empty() {
+            self.clear();
+            return Resolved::Cancelled;
+        }
+
+        let status_digit = self.status_digit();
+        let count = self.count();
+        self.clear();
+        match status_digit {
+            Some(digit) if !takes_count(key, modifiers) => Resolved::StatusDigit { digit, then: (key, modifiers) },
+            _ => Resolved::Key { count, key, modifiers },
+        }
+    }
+
+    /// Returns a key that has waited long enough to stand on its own: a lone
+    /// status digit, or a chord's first key that has an action of its own.
+    pub fn expire(&mut self, now: Instant) -> Option<char> {
+        let (key, timeout) = match self.status_digit() {
+            Some(digit) => (digit, STATUS_DIGIT_TIMEOUT),
+            None => (self.standalone_chord_key()?, CHORD_TIMEOUT),
+        };
+        if self.since.is_some_and(|since| now.saturating_duration_since(since) >= timeout) {
+            self.clear();
+            return Some(key);
+        }
+        None
+    }
+
+    /// The keys typed so far, e.g. `3d`, for display.
+    pub fn display(&self) -> String {
+        format!("{}{}", self.digits, self.chord)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.digits.is_empty() && self.chord.is_empty()
+    }
+
+    fn standalone_chord_key(&self) -> Option<char> {
+        let mut chars = self.chord.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if self.digits.is_empty() && self.standalone.contains(&c) => Some(c),
+            _ => None,
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.digits.parse().unwrap_or(1).max(1)
+    }
+
+    fn status_digit(&self) -> Option<char> {
+        let mut chars = self.digits.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c @ '1'..='3'), None) if self.chord.is_empty() => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.digits.clear();
+        self.chord.clear();
+        self.since = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_delete_chord() -> PendingKeys {
+        let mut pending = PendingKeys::default();
+        pending.set_chords(vec![("dd".to_string(), "delete".to_string())], Vec::new());
+        pending
+    }
+
+    fn feed_all(pending: &mut PendingKeys, keys: &str) -> Resolved {
+        let now = Instant::now();
+        let mut last = Resolved::Pending;
+        for c in keys.chars() {
+            last = pending.feed(KeyCode::Char(c), KeyModifiers::NONE, now, |key, _| matches!(key, KeyCode::Char('j' | 'k')));
+        }
+        last
+    }
+
+    #[test]
+    fn test_counts_and_operator() {
+        let mut pending = with_delete_chord();
+        assert_eq!(
+            feed_all(&mut pending, "12j"),
+            Resolved::Key { count: 12, key: KeyCode::Char('j'), modifiers: KeyModifiers::NONE }
+        );
+        assert!(pending.is_empty());
+
+        assert_eq!(feed_all(&mut pending, "3d"), Resolved::Pending);
+        assert_eq!(pending.display(), "3d");
+        assert_eq!(feed_all(&mut pending, "d"), Resolved::Chord { action: "delete".to_string(), count: 3 });
+        assert_eq!(feed_all(&mut pending, "dd"), Resolved::Chord { action: "delete".to_string(), count: 1 });
+
+        // An operator followed by anything else is dropped
+        assert_eq!(feed_all(&mut pending, "dx"), Resolved::Cancelled);
+        // A leading zero is not a count
+        assert_eq!(
+            feed_all(&mut pending, "0"),
+            Resolved::Key { count: 1, key: KeyCode::Char('0'), modifiers: KeyModifiers::NONE }
+        );
+    }
+
+    #[test]
+    fn test_lone_status_digit() {
+        let mut pending = with_delete_chord();
+        assert_eq!(
+            feed_all(&mut pending, "2x"),
+            Resolved::StatusDigit { digit: '2', then: (KeyCode::Char('x'), KeyModifiers::NONE) }
+        );
+        assert_eq!(
+            feed_all(&mut pending, "2j"),
+            Resolved::Key { count: 2, key: KeyCode::Char('j'), modifiers: KeyModifiers::NONE }
+        );
+
+        let start = Instant::now();
+        pending.feed(KeyCode::Char('3'), KeyModifiers::NONE, start, |_, _| false);
+        assert_eq!(pending.expire(start), None);
+        assert_eq!(pending.expire(start + STATUS_DIGIT_TIMEOUT), Some('3'));
+        assert!(pending.is_empty());
+
+        // Larger counts never expire into a status change
+        feed_all(&mut pending, "5");
+        assert_eq!(pending.expire(start + STATUS_DIGIT_TIMEOUT * 10), None);
+    }
+
+    #[test]
+    fn test_configured_chords() {
+        let mut pending = PendingKeys::default();
+        pending.set_chords(
+            vec![("cc".to_string(), "complete_all".to_string()), ("dd".to_string(), "delete".to_string())],
+            vec!['c'],
+        );
+        assert_eq!(feed_all(&mut pending, "cc"), Resolved::Chord { action: "complete_all".to_string(), count: 1 });
+
+        // A lone `c` still runs its own action once the chord times out
+        let start = Instant::now();
+        assert_eq!(pending.feed(KeyCode::Char('c'), KeyModifiers::NONE, start, |_, _| false), Resolved::Pending);
+        assert_eq!(pending.expire(start), None);
+        assert_eq!(pending.expire(start + CHORD_TIMEOUT), Some('c'));
+
+        // A lone `d` has no action of its own and waits
+        feed_all(&mut pending, "d");
+        assert_eq!(pending.expire(start + CHORD_TIMEOUT * 10), None);
+        assert_eq!(feed_all(&mut pending, "x"), Resolved::Cancelled);
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::{self, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// The log file is rotated once it would grow past this many bytes.
+const MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated files kept next to the log, `quill.log.1` being the newest.
+const KEEP: usize = 3;
+/// Lines the debug overlay can show.
+const RECENT_LINES: usize = 200;
+
+static RECENT: OnceLock<RecentLines> = OnceLock::new();
+
+/// `~/.quill/logs/quill.log`
+pub fn log_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".quill").join("logs").join("quill.log"))
+}
+
+/// An append-only log file that moves itself aside when it gets too big.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size, max_bytes })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..KEEP).rev() {
+            let older = rotated(&self.path, n);
+            if older.exists() {
+                fs::rename(&older, rotated(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated(&self.path, 1))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// `quill.log.<n>`
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// The latest log lines, kept in memory for the debug overlay.
+#[derive(Clone, Default)]
+struct RecentLines(Arc<Mutex<VecDeque<String>>>);
+
+impl Write for RecentLines {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Ok(mut lines) = self.0.lock() {
+            for line in String::from_utf8_lossy(buf).lines().filter(|l| !l.is_empty()) {
+                if lines.len() == RECENT_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(line.to_string());
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for RecentLines {
+    type Writer = RecentLines;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Sends quill's log events to `~/.quill/logs/quill.log` and to the debug
+/// overlay. Without `verbose` only info and above are kept; other crates
+/// only log warnings.
+pub fn init(verbose: bool) {
+    let level = if verbose { LevelFilter::DEBUG } else { LevelFilter::INFO };
+    let targets = Targets::new()
+        .with_target("quill", level)
+        .with_target("quill_core", level)
+        .with_default(LevelFilter::WARN);
+
+    let recent = RECENT.get_or_init(RecentLines::default).clone();
+    // Logging is best effort: a read-only home directory shouldn't stop quill
+    let file_layer = log_path()
+        .and_then(|path| RotatingFile::open(path, MAX_BYTES).ok())
+        .map(|file| fmt::layer().with_ansi(false).with_writer(Mutex::new(file)));
+    let _ = tracing_subscriber::registry()
+        .with(file_layer)
+        .with(fmt::layer().with_ansi(false).with_target(false).without_time().with_writer(recent))
+        .with(targets)
+        .try_init();
+}
+
+/// The most recent log lines, oldest first.
+pub fn recent_lines() -> Vec<String> {
+    RECENT
+        .get()
+        .and_then(|recent| recent.0.lock().ok().map(|lines| lines.iter().cloned().collect()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_file_rotates_and_keeps_a_few() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("quill.log");
+        let mut file = RotatingFile::open(path.clone(), 10).unwrap();
+        for n in 0..6 {
+            file.write_all(format!("line {}\n", n).as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line 5\n");
+        assert_eq!(fs::read_to_string(rotated(&path, 1)).unwrap(), "line 4\n");
+        assert_eq!(fs::read_to_string(rotated(&path, KEEP)).unwrap(), "line 2\n");
+        assert!(!rotated(&path, KEEP + 1).exists());
+    }
+}
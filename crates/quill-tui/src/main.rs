@@ -0,0 +1,85 @@
+mod accent;
+mod app;
+mod bulk;
+mod carry;
+mod cleanup;
+mod cli;
+mod crash;
+#[cfg(unix)]
+mod daemon;
+mod dates;
+mod defer;
+mod diff;
+mod editor;
+mod events;
+mod external_editor;
+mod github;
+mod import;
+mod input;
+mod ics;
+mod gitlab;
+mod jira;
+mod journal;
+mod keys;
+mod logging;
+mod markdown;
+mod overview;
+mod palette;
+mod plugins;
+mod quickadd;
+mod serve;
+mod similar;
+mod state;
+mod statusline;
+mod subtasks;
+mod summary;
+mod sync;
+mod theme;
+mod ui;
+mod views;
+
+use anyhow::Result;
+use quill_core::{config, git, prompt, secret, shared, storage, templates};
+use app::App;
+use clap::Parser;
+use cli::Cli;
+use input::{Recorder, Replay, TerminalEvents};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    logging::init(cli.verbose);
+    crash::install_panic_hook();
+    if let Some(command) = cli.command {
+        return cli::run(command).await;
+    }
+
+    if !atty::is(atty::Stream::Stdout) {
+        eprintln!("Error: This application requires a proper terminal to run.");
+        eprintln!("Please run this application from a terminal emulator like:");
+        eprintln!("- Terminal.app on macOS");
+        eprintln!("- Command Prompt, PowerShell, or Windows Terminal on Windows");
+        eprintln!("- Any terminal emulator on Linux");
+        eprintln!("\nYou cannot run this TUI application through IDEs or non-terminal environments.");
+        std::process::exit(1);
+    }
+
+    // A bad recording is reported before the terminal is taken over
+    let replay = cli.replay.as_deref().map(input::Recording::load).transpose()?;
+    let size = crossterm::terminal::size().unwrap_or_default();
+    if let Some(recording) = replay.as_ref().filter(|recording| recording.size != size) {
+        tracing::warn!(recorded = ?recording.size, current = ?size, "replaying in a terminal of another size");
+    }
+
+    let recorder = match (&replay, &cli.record) {
+        (None, Some(path)) => Some(Recorder::create(TerminalEvents, size, path)?),
+        _ => None,
+    };
+
+    let mut app = App::new().await?;
+    match (replay, recorder) {
+        (Some(recording), _) => app.run(&mut Replay::new(recording, TerminalEvents)).await,
+        (None, Some(mut recorder)) => app.run(&mut recorder).await,
+        (None, None) => app.run(&mut TerminalEvents).await,
+    }
+}
@@ -0,0 +1,155 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// A run of task text with one inline Markdown style.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    Plain(String),
+    Bold(String),
+    Italic(String),
+    Code(String),
+    /// `[text](url)`, or a bare `http(s)://` URL whose text is the URL.
+    Link { text: String, url: String },
+}
+
+/// Splits `text` into Markdown pieces: `**bold**`, `*italic*`/`_italic_`,
+/// `` `code` `` and links. Unclosed markers are kept as plain text.
+pub fn parse(text: &str) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let at_word_start = plain.is_empty() || plain.ends_with(|c: char| !c.is_alphanumeric());
+        let found = match c {
+            '`' => closed(rest, "`").map(|(inner, len)| (Piece::Code(inner.to_string()), len)),
+            '*' if rest.starts_with("**") => closed(rest, "**").map(|(inner, len)| (Piece::Bold(inner.to_string()), len)),
+            '*' => closed(rest, "*").map(|(inner, len)| (Piece::Italic(inner.to_string()), len)),
+            '_' if at_word_start => closed(rest, "_").map(|(inner, len)| (Piece::Italic(inner.to_string()), len)),
+            '[' => link(rest),
+            'h' if at_word_start && is_web_url(rest) => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                // Sentence punctuation after a URL isn't part of it
+                let url = rest[..end].trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+                Some((Piece::Link { text: url.to_string(), url: url.to_string() }, url.len()))
+            }
+            _ => None,
+        };
+        match found {
+            Some((piece, len)) => {
+                if !plain.is_empty() {
+                    pieces.push(Piece::Plain(std::mem::take(&mut plain)));
+                }
+                pieces.push(piece);
+                rest = &rest[len..];
+            }
+            None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if !plain.is_empty() {
+        pieces.push(Piece::Plain(plain));
+    }
+    pieces
+}
+
+/// The non-empty text between `marker` at the start of `text` and the next
+/// `marker`, and the length of the whole span.
+fn closed<'a>(text: &'a str, marker: &str) -> Option<(&'a str, usize)> {
+    let body = &text[marker.len()..];
+    let end = body.find(marker)?;
+    let inner = &body[..end];
+    if inner.is_empty() || inner.starts_with(' ') || inner.ends_with(' ') {
+        return None;
+    }
+    Some((inner, marker.len() * 2 + end))
+}
+
+fn is_web_url(text: &str) -> bool {
+    text.starts_with("https://") || text.starts_with("http://")
+}
+
+/// `[text](url)`, for `http(s)://` URLs only: notes may come from teammates,
+/// and opening a `file://` URL or a path could launch a local program.
+fn link(text: &str) -> Option<(Piece, usize)> {
+    let label_end = text.find("](")?;
+    let url_end = label_end + 2 + text[label_end + 2..].find(')')?;
+    let (label, url) = (&text[1..label_end], &text[label_end + 2..url_end]);
+    if label.is_empty() || !is_web_url(url) || url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((Piece::Link { text: label.to_string(), url: url.to_string() }, url_end + 1))
+}
+
+/// URLs of the links in `text`, in the order they are numbered.
+pub fn links(text: &str) -> Vec<String> {
+    parse(text)
+        .into_iter()
+        .filter_map(|piece| match piece {
+            Piece::Link { url, .. } => Some(url),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Renders `text` as spans over `base`, numbering links from `first_link`
+/// (shown as `text[1]`). Returns the spans and the next link number.
+pub fn spans(text: &str, base: Style, first_link: usize) -> (Vec<Span<'static>>, usize) {
+    let mut number = first_link;
+    let mut spans = Vec::new();
+    for piece in parse(text) {
+        match piece {
+            Piece::Plain(text) => spans.push(Span::styled(text, base)),
+            Piece::Bold(text) => spans.push(Span::styled(text, base.add_modifier(Modifier::BOLD))),
+            Piece::Italic(text) => spans.push(Span::styled(text, base.add_modifier(Modifier::ITALIC))),
+            Piece::Code(text) => spans.push(Span::styled(text, base.fg(Color::LightYellow))),
+            Piece::Link { text, .. } => {
+                spans.push(Span::styled(text, base.fg(Color::Blue).add_modifier(Modifier::UNDERLINED)));
+                spans.push(Span::styled(format!("[{}]", number), Style::default().fg(Color::DarkGray)));
+                number += 1;
+            }
+        }
+    }
+    (spans, number)
+}
+
+/// Renders multi-line notes, numbering links on from `first_link`.
+pub fn lines(text: &str, base: Style, first_link: usize) -> Vec<Line<'static>> {
+    let mut number = first_link;
+    text.lines()
+        .map(|line| {
+            let (spans, next) = spans(line, base, number);
+            number = next;
+            Line::from(spans)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inline_markdown() {
+        let plain = |s: &str| Piece::Plain(s.to_string());
+        assert_eq!(
+            parse("Fix **login** in `auth.rs`, see [the spec](https://acme.dev/spec) and _soon_"),
+            vec![
+                plain("Fix "),
+                Piece::Bold("login".to_string()),
+                plain(" in "),
+                Piece::Code("auth.rs".to_string()),
+                plain(", see "),
+                Piece::Link { text: "the spec".to_string(), url: "https://acme.dev/spec".to_string() },
+                plain(" and "),
+                Piece::Italic("soon".to_string()),
+            ]
+        );
+        // Unclosed markers and snake_case stay as they are
+        assert_eq!(parse("2 * 3 = *6 and snake_case_name"), vec![plain("2 * 3 = *6 and snake_case_name")]);
+        assert_eq!(links("Read https://acme.dev/a. Then [b](https://acme.dev/b)"), vec!["https://acme.dev/a", "https://acme.dev/b"]);
+        // Only web links are opened
+        assert!(links("[docs](file:///tmp/x.desktop) [x](/Applications/Some.app) [x](--help)").is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+use crate::storage::Task;
+use crate::subtasks;
+use std::collections::{BTreeMap, HashSet};
+
+/// One line of the overview: a context heading or one of its open tasks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Row {
+    Context { context_key: String, open: usize, done: usize, collapsed: bool },
+    Task { context_key: String, task: Box<Task> },
+}
+
+/// Open tasks across every context, grouped by context, for the view
+/// opened with `O`.
+#[derive(Debug, Default)]
+pub struct Overview {
+    /// Open tasks by context key, in list order; contexts without any are left out.
+    groups: BTreeMap<String, Vec<Task>>,
+    /// Completed tasks by context key, for the progress bars.
+    done: BTreeMap<String, usize>,
+    /// Contexts shown as just their heading; kept while quill runs.
+    collapsed: HashSet<String>,
+}
+
+impl Overview {
+    /// Replaces the tasks shown with the open ones in `all`, keeping which
+    /// contexts are collapsed.
+    pub fn load(&mut self, all: BTreeMap<String, Vec<Task>>) {
+        self.done = all.iter().map(|(context_key, tasks)| (context_key.clone(), tasks.iter().filter(|t| t.is_completed()).count())).collect();
+        self.groups = all
+            .into_iter()
+            .map(|(context_key, tasks)| (context_key, subtasks::tree_order(tasks.into_iter().filter(|t| !t.is_completed()).collect())))
+            .filter(|(_, tasks)| !tasks.is_empty())
+            .collect();
+    }
+
+    pub fn open_tasks(&self) -> usize {
+        self.groups.values().map(Vec::len).sum()
+    }
+
+    pub fn contexts(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (context_key, tasks) in &self.groups {
+            let collapsed = self.collapsed.contains(context_key);
+            let done = self.done.get(context_key).copied().unwrap_or(0);
+            rows.push(Row::Context { context_key: context_key.clone(), open: tasks.len(), done, collapsed });
+            if !collapsed {
+                rows.extend(tasks.iter().map(|task| Row::Task { context_key: context_key.clone(), task: Box::new(task.clone()) }));
+            }
+        }
+        rows
+    }
+
+    /// Collapses or expands a context, returning the index of its heading
+    /// so the selection can stay on it.
+    pub fn toggle(&mut self, context_key: &str) -> Option<usize> {
+        if !self.collapsed.remove(context_key) {
+            self.collapsed.insert(context_key.to_string());
+        }
+        self.rows()
+            .iter()
+            .position(|row| matches!(row, Row::Context { context_key: key, .. } if key == context_key))
+    }
+
+    /// Collapses every context, or expands them all when all are collapsed.
+    pub fn toggle_all(&mut self) {
+        if self.groups.keys().all(|key| self.collapsed.contains(key)) {
+            self.collapsed.clear();
+        } else {
+            self.collapsed.extend(self.groups.keys().cloned());
+        }
+    }
+}
+
+/// A text progress bar `width` cells wide, e.g. `██████░░░░` for 6 of 10.
+pub fn progress_bar(done: usize, total: usize, width: usize) -> String {
+    let filled = (done * width).checked_div(total).unwrap_or(0);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+/// Percentage of `total` that is `done`, rounded down.
+pub fn percent(done: usize, total: usize) -> usize {
+    (done * 100).checked_div(total).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TaskStatus;
+
+    fn task(id: usize, text: &str, status: TaskStatus) -> Task {
+        let mut task = Task::new(id, text.to_string());
+        task.status = status;
+        task
+    }
+
+    #[test]
+    fn test_overview_groups_open_tasks_and_collapses() {
+        let mut all = BTreeMap::new();
+        all.insert(
+            "acme:web:main".to_string(),
+            vec![task(1, "Ship", TaskStatus::InProgress), task(2, "Done", TaskStatus::Completed)],
+        );
+        all.insert("acme:api:main".to_string(), vec![task(3, "Migrate", TaskStatus::NotStarted)]);
+        all.insert("acme:old:main".to_string(), vec![task(4, "Archived", TaskStatus::Completed)]);
+
+        let mut overview = Overview::default();
+        overview.load(all.clone());
+        assert_eq!((overview.contexts(), overview.open_tasks()), (2, 2));
+        let rows = overview.rows();
+        assert_eq!(rows.len(), 4);
+        assert!(matches!(&rows[0], Row::Context { context_key, open: 1, done: 0, collapsed: false } if context_key == "acme:api:main"));
+        assert!(matches!(&rows[2], Row::Context { open: 1, done: 1, .. }));
+        assert!(matches!(&rows[3], Row::Task { task, .. } if task.text == "Ship"));
+
+        assert_eq!(overview.toggle("acme:api:main"), Some(0));
+        assert_eq!(overview.rows().len(), 3);
+        // Collapsed contexts stay collapsed when the tasks are reloaded
+        overview.load(all);
+        assert!(matches!(&overview.rows()[0], Row::Context { collapsed: true, .. }));
+
+        overview.toggle_all();
+        assert_eq!(overview.rows().len(), 2);
+        overview.toggle_all();
+        assert_eq!(overview.rows().len(), 4);
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        assert_eq!(progress_bar(7, 12, 10), "█████░░░░░");
+        assert_eq!(percent(7, 12), 58);
+        assert_eq!(progress_bar(0, 0, 4), "░░░░");
+        assert_eq!(percent(0, 0), 0);
+    }
+}
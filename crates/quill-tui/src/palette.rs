@@ -0,0 +1,70 @@
+/// An entry in the command palette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteItem {
+    pub label: String,
+    /// Key binding shown next to the label; empty for palette-only commands.
+    pub key: String,
+}
+
+/// Scores `candidate` against `query` as a case-insensitive subsequence,
+/// favouring consecutive matches and matches at word starts. None if some
+/// query character doesn't appear in order.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + candidate[position..].iter().position(|c| *c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - position) as i64;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// Indices of the items matching `query`, best first; ties keep registry order.
+pub fn rank(query: &str, items: &[PaletteItem]) -> Vec<usize> {
+    let mut scored: Vec<(usize, i64)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| fuzzy_score(query, &item.label).map(|score| (i, score)))
+        .collect();
+    scored.sort_by_key(|(i, score)| (-score, *i));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(labels: &[&str]) -> Vec<PaletteItem> {
+        labels.iter().map(|l| PaletteItem { label: l.to_string(), key: String::new() }).collect()
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("", "Anything").is_some());
+        assert!(fuzzy_score("stc", "Set status: Completed").is_some());
+        assert_eq!(fuzzy_score("xyz", "Set status: Completed"), None);
+        // Order matters
+        assert_eq!(fuzzy_score("ts", "st"), None);
+        assert!(fuzzy_score("trash", "Open trash") > fuzzy_score("trash", "Toggle must finish before push"));
+    }
+
+    #[test]
+    fn test_rank_orders_by_score_then_registry() {
+        let items = items(&["Add task", "Open trash", "Show stats", "Toggle filter: Not Started"]);
+        assert_eq!(rank("", &items), vec![0, 1, 2, 3]);
+        assert_eq!(rank("st", &items), vec![2, 3]);
+        assert_eq!(rank("OPEN", &items), vec![1]);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::diff::{ContextDiff, DiffLine};
+use crate::storage::{Task, TaskStatus};
+use std::path::PathBuf;
+
+/// Task lifecycle events plugins can subscribe to with `quill.on`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Added,
+    Edited,
+    StatusChanged,
+    Completed,
+    Deleted,
+}
+
+/// The events behind a context going from `before` to `after`. A task whose
+/// status and other fields both changed raises both kinds of event.
+pub fn events(before: &[Task], after: &[Task]) -> Vec<(Event, Task)> {
+    let diff = ContextDiff::new("", "", before.to_vec(), after.to_vec());
+    let mut events = Vec::new();
+    for line in diff.lines() {
+        match line {
+            DiffLine::Added(task) => events.push((Event::Added, task)),
+            DiffLine::Removed(task) => events.push((Event::Deleted, task)),
+            DiffLine::Modified { before, after } => {
+                if before.status != after.status {
+                    events.push((Event::StatusChanged, (*after).clone()));
+                    if after.status == TaskStatus::Completed {
+                        events.push((Event::Completed, (*after).clone()));
+                    }
+                }
+                let mut unchanged_status = (*before).clone();
+                unchanged_status.status = after.status.clone();
+                unchanged_status.completed_at = after.completed_at.clone();
+                if unchanged_status != *after {
+                    events.push((Event::Edited, *after));
+                }
+            }
+        }
+    }
+    events
+}
+
+/// What a plugin command asks for once it returns.
+#[derive(Debug, Default, PartialEq)]
+pub struct CommandOutcome {
+    pub message: Option<String>,
+    /// Tasks to add to the current context.
+    pub add: Vec<String>,
+}
+
+fn plugins_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".quill").join("plugins"))
+}
+
+#[cfg(feature = "plugins")]
+pub use lua::Plugins;
+
+#[cfg(feature = "plugins")]
+mod lua {
+    use super::{plugins_dir, CommandOutcome, Event};
+    use crate::storage::{Priority, Task, TaskStatus};
+    use anyhow::Result;
+    use mlua::{Function, Lua, Table, Value};
+    use std::fs;
+    use std::path::Path;
+
+    /// Registration functions every plugin sees as the global `quill`.
+    const PRELUDE: &str = r#"
+quill = { _hooks = {}, _commands = {}, _decorators = {} }
+function quill.on(event, fn)
+    quill._hooks[event] = quill._hooks[event] or {}
+    table.insert(quill._hooks[event], fn)
+end
+function quill.command(label, fn)
+    table.insert(quill._commands, { label = label, fn = fn })
+end
+function quill.decorate(fn)
+    table.insert(quill._decorators, fn)
+end
+"#;
+
+    /// The name a hook is registered under with `quill.on`.
+    fn hook_name(event: Event) -> &'static str {
+        match event {
+            Event::Added => "task_added",
+            Event::Edited => "task_edited",
+            Event::StatusChanged => "task_status_changed",
+            Event::Completed => "task_completed",
+            Event::Deleted => "task_deleted",
+        }
+    }
+
+    /// Lua plugins loaded from `~/.quill/plugins/*.lua`, sharing one state.
+    pub struct Plugins {
+        lua: Lua,
+        /// File names of the plugins that loaded.
+        pub loaded: Vec<String>,
+        /// Plugins that failed to load, with why.
+        pub errors: Vec<String>,
+    }
+
+    impl Plugins {
+        pub fn load() -> Self {
+            match plugins_dir() {
+                Some(dir) => Self::load_from(&dir),
+                None => Self::load_from(Path::new("")),
+            }
+        }
+
+        /// No plugins at all, for an app that mustn't pick up the user's.
+        #[cfg(test)]
+        pub fn none() -> Self {
+            Self { lua: Lua::new(), loaded: Vec::new(), errors: Vec::new() }
+        }
+
+        /// Runs every `.lua` file in `dir` in name order; a plugin that fails
+        /// is reported and skipped without affecting the others.
+        pub fn load_from(dir: &Path) -> Self {
+            let lua = Lua::new();
+            let mut plugins = Self { lua, loaded: Vec::new(), errors: Vec::new() };
+            if let Err(e) = plugins.lua.load(PRELUDE).set_name("prelude").exec() {
+                plugins.errors.push(format!("prelude: {}", e));
+                return plugins;
+            }
+
+            let mut paths: Vec<_> = fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "lua"))
+                .collect();
+            paths.sort();
+            for path in paths {
+                let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                let result = fs::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|code| Ok(plugins.lua.load(&code).set_name(name.as_str()).exec()?));
+                match result {
+                    Ok(()) => plugins.loaded.push(name),
+                    Err(e) => plugins.errors.push(format!("{}: {}", name, e)),
+                }
+            }
+            plugins
+        }
+
+        fn registry(&self, name: &str) -> Option<Table<'_>> {
+            self.lua.globals().get::<_, Table>("quill").ok()?.get(name).ok()
+        }
+
+        fn task_table(&self, context_key: &str, task: &Task) -> mlua::Result<Table<'_>> {
+            let table = self.lua.create_table()?;
+            table.set("context", context_key)?;
+            table.set("id", task.id)?;
+            table.set("text", task.text.as_str())?;
+            table.set(
+                "status",
+                match task.status {
+                    TaskStatus::NotStarted => "todo",
+                    TaskStatus::InProgress => "doing",
+                    TaskStatus::Completed => "done",
+                },
+            )?;
+            table.set(
+                "priority",
+                match task.priority {
+                    Priority::None => "none",
+                    Priority::Low => "low",
+                    Priority::Medium => "medium",
+                    Priority::High => "high",
+                },
+            )?;
+            table.set("tags", self.lua.create_sequence_from(task.tags.iter().map(String::as_str))?)?;
+            table.set("notes", task.notes.as_str())?;
+            table.set("must_finish", task.must_finish)?;
+            if let Some(due) = task.due {
+                table.set("due", due.to_string())?;
+            }
+            Ok(table)
+        }
+
+        /// Whether any plugin subscribed to lifecycle events.
+        pub fn has_hooks(&self) -> bool {
+            self.registry("_hooks").is_some_and(|hooks| hooks.pairs::<Value, Value>().next().is_some())
+        }
+
+        /// Calls the hooks for `event`; returns the errors they raised.
+        pub fn emit(&self, event: Event, context_key: &str, task: &Task) -> Vec<String> {
+            let Some(hooks) = self.registry("_hooks").and_then(|hooks| hooks.get::<_, Option<Table>>(hook_name(event)).ok().flatten()) else {
+                return Vec::new();
+            };
+            let mut errors = Vec::new();
+            for hook in hooks.sequence_values::<Function>().flatten() {
+                if let Err(e) = self.task_table(context_key, task).and_then(|task| hook.call::<_, ()>(task)) {
+                    errors.push(format!("{} hook: {}", hook_name(event), e));
+                }
+            }
+            errors
+        }
+
+        /// Labels of the commands plugins added to the palette, by index.
+        pub fn commands(&self) -> Vec<String> {
+            let Some(commands) = self.registry("_commands") else {
+                return Vec::new();
+            };
+            commands
+                .sequence_values::<Table>()
+                .flatten()
+                .map(|command| command.get::<_, String>("label").unwrap_or_default())
+                .collect()
+        }
+
+        /// Runs a plugin command with the current context and selected task.
+        /// It may return a message, or a table `{ message = ..., add = { ... } }`.
+        pub fn run_command(&self, index: usize, context_key: &str, selected: Option<&Task>) -> Result<CommandOutcome> {
+            let command: Table = self
+                .registry("_commands")
+                .ok_or_else(|| anyhow::anyhow!("no plugin commands"))?
+                .get(index + 1)?;
+            let function: Function = command.get("fn")?;
+            let context = self.lua.create_table()?;
+            context.set("context", context_key)?;
+            if let Some(task) = selected {
+                context.set("task", self.task_table(context_key, task)?)?;
+            }
+
+            Ok(match function.call::<_, Value>(context)? {
+                Value::String(message) => CommandOutcome { message: Some(message.to_str()?.to_string()), add: Vec::new() },
+                Value::Table(result) => CommandOutcome {
+                    message: result.get("message")?,
+                    add: result.get::<_, Option<Vec<String>>>("add")?.unwrap_or_default(),
+                },
+                _ => CommandOutcome::default(),
+            })
+        }
+
+        /// Whether any plugin decorates list items.
+        pub fn decorates(&self) -> bool {
+            self.registry("_decorators").is_some_and(|decorators| decorators.raw_len() > 0)
+        }
+
+        /// The text plugins append to a task's row, if any.
+        pub fn decorate(&self, context_key: &str, task: &Task) -> Option<String> {
+            let decorators = self.registry("_decorators")?;
+            let table = self.task_table(context_key, task).ok()?;
+            let parts: Vec<String> = decorators
+                .sequence_values::<Function>()
+                .flatten()
+                .filter_map(|decorate| decorate.call::<_, Option<String>>(table.clone()).ok().flatten())
+                .filter(|part| !part.is_empty())
+                .collect();
+            (!parts.is_empty()).then(|| parts.join(" "))
+        }
+    }
+}
+
+/// Without the `plugins` feature nothing is loaded and every call is a no-op.
+#[cfg(not(feature = "plugins"))]
+pub struct Plugins {
+    pub errors: Vec<String>,
+}
+
+#[cfg(not(feature = "plugins"))]
+impl Plugins {
+    pub fn load() -> Self {
+        let errors = match plugins_dir().filter(|dir| dir.exists()) {
+            Some(dir) => vec![format!("{} is ignored: this build has no plugin support", dir.display())],
+            None => Vec::new(),
+        };
+        Self { errors }
+    }
+
+    #[cfg(test)]
+    pub fn none() -> Self {
+        Self { errors: Vec::new() }
+    }
+
+    pub fn has_hooks(&self) -> bool {
+        false
+    }
+
+    pub fn emit(&self, _event: Event, _context_key: &str, _task: &Task) -> Vec<String> {
+        Vec::new()
+    }
+
+    pub fn commands(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    pub fn run_command(&self, _index: usize, _context_key: &str, _selected: Option<&Task>) -> anyhow::Result<CommandOutcome> {
+        Err(anyhow::anyhow!("this build has no plugin support"))
+    }
+
+    pub fn decorates(&self) -> bool {
+        false
+    }
+
+    pub fn decorate(&self, _context_key: &str, _task: &Task) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_from_before_and_after() {
+        let kept = Task::new(1, "Kept".to_string());
+        let mut done = kept.clone();
+        done.set_status(TaskStatus::Completed);
+        let mut renamed = Task::new(2, "Old name".to_string());
+        let gone = Task::new(3, "Gone".to_string());
+        let before = vec![kept, renamed.clone(), gone];
+        renamed.text = "New name".to_string();
+        let added = Task::new(4, "New".to_string());
+
+        let raised: Vec<(Event, usize)> = events(&before, &[done, renamed, added]).into_iter().map(|(e, t)| (e, t.id)).collect();
+        assert_eq!(
+            raised,
+            vec![(Event::StatusChanged, 1), (Event::Completed, 1), (Event::Edited, 2), (Event::Added, 4), (Event::Deleted, 3)]
+        );
+    }
+
+    #[cfg(feature = "plugins")]
+    #[test]
+    fn test_lua_plugins_register_hooks_commands_and_decorations() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("a.lua"),
+            r#"
+            added = {}
+            quill.on("task_added", function(task) table.insert(added, task.text) end)
+            quill.command("Add follow-up", function(ctx)
+                return { message = "added for " .. ctx.task.text, add = { "Follow up on " .. ctx.task.text } }
+            end)
+            quill.decorate(function(task)
+                for _, tag in ipairs(task.tags) do
+                    if tag == "urgent" then return "🔥" end
+                end
+            end)
+            "#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("b.lua"), "this is not lua").unwrap();
+
+        let plugins = Plugins::load_from(dir.path());
+        assert_eq!(plugins.loaded, vec!["a.lua"]);
+        assert_eq!(plugins.errors.len(), 1);
+        assert!(plugins.has_hooks());
+
+        let mut task = Task::new(1, "Ship".to_string());
+        assert!(plugins.emit(Event::Added, "a:b:main", &task).is_empty());
+        assert_eq!(plugins.decorate("a:b:main", &task), None);
+        task.tags = vec!["urgent".to_string()];
+        assert_eq!(plugins.decorate("a:b:main", &task).as_deref(), Some("🔥"));
+
+        assert_eq!(plugins.commands(), vec!["Add follow-up"]);
+        let outcome = plugins.run_command(0, "a:b:main", Some(&task)).unwrap();
+        assert_eq!(outcome, CommandOutcome { message: Some("added for Ship".to_string()), add: vec!["Follow up on Ship".to_string()] });
+    }
+}
@@ -0,0 +1,102 @@
+use crate::dates::DateParser;
+use crate::storage::Priority;
+use chrono::NaiveDate;
+
+/// A task typed with inline metadata, e.g. `Fix login bug !high #auth @tomorrow`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuickAdd {
+    pub text: String,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    pub due: Option<NaiveDate>,
+}
+
+impl QuickAdd {
+    /// Splits `!priority`, `#tag` and `@date` words out of the input. Words
+    /// that look like metadata but don't parse (`@alice`) stay in the text.
+    pub fn parse(input: &str, today: NaiveDate, dates: &DateParser) -> Self {
+        let mut parsed = Self::default();
+        let mut words = Vec::new();
+
+        for word in input.split_whitespace() {
+            if let Some(tag) = word.strip_prefix('#').filter(|t| !t.is_empty()) {
+                if !parsed.tags.iter().any(|t| t == tag) {
+                    parsed.tags.push(tag.to_string());
+                }
+            } else if let Some(priority) = word.strip_prefix('!').and_then(parse_priority) {
+                parsed.priority = priority;
+            } else if let Some(due) = word.strip_prefix('@').and_then(|w| dates.parse_due_word(w, today)) {
+                parsed.due = Some(due);
+            } else {
+                words.push(word);
+            }
+        }
+
+        parsed.text = words.join(" ");
+        parsed
+    }
+
+    pub fn has_metadata(&self) -> bool {
+        self.priority != Priority::None || !self.tags.is_empty() || self.due.is_some()
+    }
+
+    /// One-line description of the parsed fields for the add popup.
+    pub fn preview(&self) -> String {
+        let mut parts = Vec::new();
+        if self.priority != Priority::None {
+            parts.push(format!("priority: {}", priority_label(self.priority)));
+        }
+        if !self.tags.is_empty() {
+            parts.push(format!("tags: {}", self.tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" ")));
+        }
+        if let Some(due) = self.due {
+            parts.push(format!("due: {}", due.format("%a %Y-%m-%d")));
+        }
+        parts.join("  ·  ")
+    }
+}
+
+pub fn priority_label(priority: Priority) -> &'static str {
+    match priority {
+        Priority::None => "none",
+        Priority::Low => "low",
+        Priority::Medium => "medium",
+        Priority::High => "high",
+    }
+}
+
+fn parse_priority(word: &str) -> Option<Priority> {
+    match word.to_lowercase().as_str() {
+        "high" | "h" | "1" => Some(Priority::High),
+        "medium" | "med" | "m" | "2" => Some(Priority::Medium),
+        "low" | "l" | "3" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::parse_from_str("2024-03-10", "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_inline_metadata() {
+        let parsed = QuickAdd::parse("Fix login bug !high #auth @tomorrow #auth", today(), &DateParser::default());
+        assert_eq!(parsed.text, "Fix login bug");
+        assert_eq!(parsed.priority, Priority::High);
+        assert_eq!(parsed.tags, vec!["auth"]);
+        assert_eq!(parsed.due, NaiveDate::from_ymd_opt(2024, 3, 11));
+        assert_eq!(parsed.preview(), "priority: high  ·  tags: #auth  ·  due: Mon 2024-03-11");
+    }
+
+    #[test]
+    fn test_unparseable_words_stay_in_text() {
+        let parsed = QuickAdd::parse("Ping @alice about !important # stuff", today(), &DateParser::default());
+        assert_eq!(parsed.text, "Ping @alice about !important # stuff");
+        assert!(!parsed.has_metadata());
+        assert_eq!(parsed.preview(), "");
+    }
+}
@@ -0,0 +1,249 @@
+use crate::storage::{
+    activity::ActivityStorage,
+    remote::{self, Request, Response, RPC_PATH},
+    TaskStorage,
+};
+use anyhow::{anyhow, Result};
+use crate::{ics, summary};
+use axum::{
+    extract::{Query, State},
+    http::{header::{AUTHORIZATION, CONTENT_TYPE}, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+/// A token clients may present, and who changes made with it are recorded as.
+#[derive(Debug, Clone)]
+pub struct SyncToken {
+    pub token: String,
+    pub actor: String,
+}
+
+/// Who changes made with the `--token` everyone shares are recorded as.
+pub const SHARED_ACTOR: &str = "shared token";
+
+impl SyncToken {
+    /// The token everyone shares.
+    pub fn shared(token: String) -> Self {
+        Self { token, actor: SHARED_ACTOR.to_string() }
+    }
+
+    /// Parses `NAME=TOKEN` from `--user`.
+    pub fn parse_user(arg: &str) -> Result<Self> {
+        match arg.split_once('=') {
+            Some((actor, token)) if !actor.is_empty() && !token.is_empty() => {
+                Ok(Self { token: token.to_string(), actor: actor.to_string() })
+            }
+            _ => Err(anyhow!("--user takes NAME=TOKEN, got {:?}", arg)),
+        }
+    }
+}
+
+struct Server {
+    /// Calls run one at a time, as they would against the backend directly.
+    storage: Mutex<ActivityStorage>,
+    tokens: Vec<SyncToken>,
+    /// Only opens the calendar feed, so it can sit in a subscription URL.
+    feed_token: Option<String>,
+}
+
+impl Server {
+    fn new(storage: Box<dyn TaskStorage>, tokens: Vec<SyncToken>, feed_token: Option<String>) -> Self {
+        Self { storage: Mutex::new(ActivityStorage::new(storage, SHARED_ACTOR.to_string())), tokens, feed_token }
+    }
+
+    /// The holder of `presented`, if it's one of the server's tokens.
+    fn holder(&self, presented: &str) -> Option<&SyncToken> {
+        self.tokens.iter().find(|token| same_token(presented, &token.token))
+    }
+}
+
+/// Read-only iCalendar feed of every task.
+pub const CALENDAR_PATH: &str = "/calendar.ics";
+
+/// The sync protocol's routes, serving `storage` to clients holding one of
+/// `tokens`, plus the calendar feed, which `feed_token` also opens. Changes
+/// are recorded as the actor of the token they came with.
+pub fn router(storage: Box<dyn TaskStorage>, tokens: Vec<SyncToken>, feed_token: Option<String>) -> Router {
+    let server = Arc::new(Server::new(storage, tokens, feed_token));
+    Router::new()
+        .route(RPC_PATH, post(rpc))
+        .route(CALENDAR_PATH, get(calendar))
+        .with_state(server)
+}
+
+/// Serves `storage` on `addr` until the process is stopped.
+pub async fn serve(addr: SocketAddr, storage: Box<dyn TaskStorage>, tokens: Vec<SyncToken>, feed_token: Option<String>) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    eprintln!("quill sync server listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, router(storage, tokens, feed_token)).await?;
+    Ok(())
+}
+
+fn bearer_token(headers: &HeaderMap) -> &str {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default()
+}
+
+async fn rpc(State(server): State<Arc<Server>>, headers: HeaderMap, Json(request): Json<Request>) -> (StatusCode, Json<Response>) {
+    let Some(holder) = server.holder(bearer_token(&headers)) else {
+        return (StatusCode::UNAUTHORIZED, Json(Response::Error("invalid token".to_string())));
+    };
+
+    let mut storage = server.storage.lock().await;
+    storage.set_actor(holder.actor.clone());
+    match remote::dispatch_recorded(&mut storage, request).await {
+        Ok(value) => (StatusCode::OK, Json(Response::Result(value))),
+        Err(e) => {
+            let status = StatusCode::from_u16(remote::status_for(&e)).unwrap_or(StatusCode::BAD_REQUEST);
+            (status, Json(Response::Error(e.to_string())))
+        }
+    }
+}
+
+/// Calendar apps subscribe by URL, so the feed token may come as `?token=`.
+/// Sync tokens grant writes and are only taken from the header, where they
+/// stay out of URLs that end up in logs and calendar apps.
+async fn calendar(State(server): State<Arc<Server>>, headers: HeaderMap, Query(query): Query<HashMap<String, String>>) -> impl IntoResponse {
+    let allowed = match query.get("token") {
+        Some(presented) => server.feed_token.as_deref().is_some_and(|feed_token| same_token(presented, feed_token)),
+        None => server.holder(bearer_token(&headers)).is_some(),
+    };
+    if !allowed {
+        return (StatusCode::UNAUTHORIZED, [(CONTENT_TYPE, "text/plain")], "invalid token".to_string());
+    }
+
+    let storage = server.storage.lock().await;
+    match summary::snapshot(&*storage).await {
+        Ok(snapshot) => (StatusCode::OK, [(CONTENT_TYPE, "text/calendar; charset=utf-8")], ics::render(&snapshot, chrono::Utc::now())),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, [(CONTENT_TYPE, "text/plain")], e.to_string()),
+    }
+}
+
+/// Compares without returning early, so response times don't reveal the token.
+fn same_token(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(all(test, feature = "remote"))]
+mod tests {
+    use super::*;
+    use crate::config::RemoteConfig;
+    use quill_core::QuillError;
+    use crate::storage::{local::LocalTaskStorage, remote::RemoteTaskStorage, TaskStatus};
+
+    async fn spawn_server(token: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = router(Box::new(LocalTaskStorage::in_memory()), vec![SyncToken::shared(token.to_string())], Some("f33d".to_string()));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_remote_storage_round_trip() {
+        let url = spawn_server("s3cret").await;
+        let config = RemoteConfig { url, token: "s3cret".to_string() };
+
+        let mut laptop = RemoteTaskStorage::connect(&config).await.unwrap();
+        let id = laptop.add_task("acme:web:main", "Sync me".to_string()).await.unwrap();
+        laptop.set_task_status("acme:web:main", id, TaskStatus::Completed).await.unwrap();
+
+        let desktop = RemoteTaskStorage::connect(&config).await.unwrap();
+        let tasks = desktop.get_tasks("acme:web:main").await.unwrap();
+        assert_eq!(tasks[0].text, "Sync me");
+        assert_eq!(tasks[0].status, TaskStatus::Completed);
+        assert!(desktop.health().await.unwrap().backend.starts_with("Remote server"));
+    }
+
+    /// A server from before the count calls, which turns them down as
+    /// unknown ops the way `Json` does.
+    async fn spawn_old_server(token: &str) -> String {
+        async fn old_rpc(State(server): State<Arc<Server>>, headers: HeaderMap, body: String) -> axum::response::Response {
+            if body.contains("task_counts") {
+                return (StatusCode::UNPROCESSABLE_ENTITY, "unknown variant").into_response();
+            }
+            rpc(State(server), headers, Json(serde_json::from_str(&body).unwrap())).await.into_response()
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = Arc::new(Server::new(Box::new(LocalTaskStorage::in_memory()), vec![SyncToken::shared(token.to_string())], None));
+        let app = Router::new().route(RPC_PATH, post(old_rpc)).with_state(server);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_counts_fall_back_on_older_servers() {
+        let url = spawn_old_server("s3cret").await;
+        let mut storage = RemoteTaskStorage::connect(&RemoteConfig { url, token: "s3cret".to_string() }).await.unwrap();
+        storage.add_task("acme:web:main", "Count me".to_string()).await.unwrap();
+
+        assert_eq!(storage.get_task_counts("acme:web:main").await.unwrap().open(), 1);
+        assert_eq!(storage.all_task_counts().await.unwrap()["acme:web:main"].total(), 1);
+        assert_eq!(storage.open_task_counts().await.unwrap()["acme:web:main"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_activity_is_recorded_as_the_token_holder() {
+        use crate::storage::activity::{ActivityAction, ActivityEntry};
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let tokens = vec![SyncToken::parse_user("ann@acme.dev=ann-token").unwrap(), SyncToken::shared("s3cret".to_string())];
+        let app = router(Box::new(LocalTaskStorage::in_memory()), tokens, None);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let context = "acme:web:main";
+
+        let mut ann = RemoteTaskStorage::connect(&RemoteConfig { url: url.clone(), token: "ann-token".to_string() }).await.unwrap();
+        ann.add_task(context, "Ship".to_string()).await.unwrap();
+        // Whoever a client claims to be, the server records the token's holder
+        let forged = ActivityEntry::new(context, "bob@acme.dev", ActivityAction::Deleted, None);
+        ann.record_activity(vec![forged]).await.unwrap();
+        let mut shared = RemoteTaskStorage::connect(&RemoteConfig { url, token: "s3cret".to_string() }).await.unwrap();
+        shared.add_task(context, "Review".to_string()).await.unwrap();
+
+        let actors: Vec<String> = shared.list_activity(context).await.unwrap().into_iter().map(|entry| entry.actor).collect();
+        assert_eq!(actors, vec![SHARED_ACTOR, "ann@acme.dev"]);
+        assert!(SyncToken::parse_user("no-token").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wrong_token_is_rejected() {
+        let url = spawn_server("s3cret").await;
+        let config = RemoteConfig { url, token: "guess".to_string() };
+        let error = RemoteTaskStorage::connect(&config).await.err().unwrap();
+        assert_eq!(error.to_string(), "sync server: invalid token");
+        assert!(matches!(error, QuillError::PermissionDenied(_)));
+    }
+
+    #[tokio::test]
+    async fn test_calendar_feed_takes_only_the_feed_token_in_query() {
+        let url = spawn_server("s3cret").await;
+        let feed = reqwest::get(format!("{}{}?token=f33d", url, CALENDAR_PATH)).await.unwrap();
+        assert_eq!(feed.status(), 200);
+        assert!(feed.text().await.unwrap().starts_with("BEGIN:VCALENDAR"));
+
+        // The sync token grants writes, so it isn't taken from a URL
+        for token in ["s3cret", "guess"] {
+            let refused = reqwest::get(format!("{}{}?token={}", url, CALENDAR_PATH, token)).await.unwrap();
+            assert_eq!(refused.status(), 401);
+        }
+        let client = reqwest::Client::new();
+        let feed = client.get(format!("{}{}", url, CALENDAR_PATH)).bearer_auth("s3cret").send().await.unwrap();
+        assert_eq!(feed.status(), 200);
+
+        // And the feed token opens nothing else
+        let config = RemoteConfig { url, token: "f33d".to_string() };
+        assert!(RemoteTaskStorage::connect(&config).await.is_err());
+    }
+}
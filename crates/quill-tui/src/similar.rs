@@ -0,0 +1,141 @@
+use crate::storage::Task;
+use std::collections::HashSet;
+
+/// Similarity from which a new task counts as a likely duplicate.
+const THRESHOLD: f64 = 0.8;
+
+/// Lowercase words of letters and digits, so punctuation and spacing don't matter.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// How alike two task texts are, from 0.0 to 1.0: the better of their
+/// character-level closeness (typos) and shared words (reordering).
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a_words, b_words) = (words(a), words(b));
+    if a_words.is_empty() || b_words.is_empty() {
+        return 0.0;
+    }
+
+    let a_chars: Vec<char> = a_words.join(" ").chars().collect();
+    let b_chars: Vec<char> = b_words.join(" ").chars().collect();
+    let longest = a_chars.len().max(b_chars.len());
+    let closeness = 1.0 - edit_distance(&a_chars, &b_chars) as f64 / longest as f64;
+
+    let a_set: HashSet<&String> = a_words.iter().collect();
+    let b_set: HashSet<&String> = b_words.iter().collect();
+    let overlap = a_set.intersection(&b_set).count() as f64 / a_set.union(&b_set).count() as f64;
+
+    closeness.max(overlap)
+}
+
+/// The open task in `tasks` most like `text`, if it is close enough to be a
+/// likely duplicate.
+pub fn find_similar<'a>(text: &str, tasks: &'a [Task]) -> Option<&'a Task> {
+    tasks
+        .iter()
+        .filter(|task| !task.is_completed())
+        .map(|task| (task, similarity(text, &task.text)))
+        .filter(|(_, score)| *score >= THRESHOLD)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(task, _)| task)
+}
+
+/// A past task offered while typing a new one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub text: String,
+    /// Where the task is, e.g. "completed on main".
+    pub source: String,
+}
+
+/// How many suggestions the add popup shows.
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Whether every word typed so far starts a word of `candidate`, so a
+/// suggestion shows up before the whole text is typed.
+fn starts_words(typed: &[String], candidate: &[String]) -> bool {
+    typed.iter().all(|word| candidate.iter().any(|c| c.starts_with(word.as_str())))
+}
+
+/// The tasks in `pool` that fit what has been typed so far, best first.
+pub fn suggest(input: &str, pool: &[Suggestion]) -> Vec<Suggestion> {
+    let typed = words(input);
+    if typed.is_empty() {
+        return Vec::new();
+    }
+    let mut scored: Vec<(&Suggestion, f64)> = pool
+        .iter()
+        .filter(|s| words(&s.text) != typed)
+        .map(|s| (s, similarity(input, &s.text)))
+        .filter(|(s, score)| *score >= THRESHOLD || starts_words(&typed, &words(&s.text)))
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut seen = HashSet::new();
+    scored
+        .into_iter()
+        .filter(|(s, _)| seen.insert(words(&s.text)))
+        .map(|(s, _)| s.clone())
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TaskStatus;
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Fix login bug", "fix  login bug!"), 1.0);
+        assert!(similarity("Fix the login bug", "Fix teh login bug") >= THRESHOLD);
+        assert!(similarity("Update docs for API", "API docs update for") >= THRESHOLD);
+        assert!(similarity("Fix login bug", "Write release notes") < THRESHOLD);
+        assert_eq!(similarity("", "anything"), 0.0);
+    }
+
+    #[test]
+    fn test_find_similar_ignores_completed_tasks() {
+        let mut done = Task::new(1, "Fix the login bugs".to_string());
+        done.status = TaskStatus::Completed;
+        let open = Task::new(2, "Fix the login bug!".to_string());
+        let unrelated = Task::new(3, "Write release notes".to_string());
+        let tasks = vec![done, open, unrelated];
+
+        assert_eq!(find_similar("fix the login bugs", &tasks).map(|t| t.id), Some(2));
+        assert!(find_similar("Plan the offsite", &tasks).is_none());
+    }
+
+    #[test]
+    fn test_suggest_matches_partial_words_and_typos() {
+        let pool = vec![
+            Suggestion { text: "Update the changelog".to_string(), source: "completed on main".to_string() },
+            Suggestion { text: "update the CHANGELOG".to_string(), source: "deleted".to_string() },
+            Suggestion { text: "Rotate API keys".to_string(), source: "open on release".to_string() },
+        ];
+
+        let found = suggest("upd chan", &pool);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].source, "completed on main");
+        assert_eq!(suggest("Rotate APi kyes", &pool)[0].text, "Rotate API keys");
+        assert!(suggest("Rotate API keys", &pool).is_empty());
+        assert!(suggest("", &pool).is_empty());
+    }
+}
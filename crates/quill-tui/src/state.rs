@@ -0,0 +1,266 @@
+use crate::github::IssueLink;
+use crate::storage::{Task, TaskStatus};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Which statuses are hidden from the task list.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusFilter {
+    #[serde(default)]
+    pub hidden: Vec<TaskStatus>,
+}
+
+impl StatusFilter {
+    pub fn toggle(&mut self, status: TaskStatus) {
+        if let Some(pos) = self.hidden.iter().position(|s| *s == status) {
+            self.hidden.remove(pos);
+        } else {
+            self.hidden.push(status);
+        }
+    }
+
+    pub fn allows(&self, task: &Task) -> bool {
+        !self.hidden.contains(&task.status)
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.hidden.is_empty()
+    }
+
+    /// Short description for the header, e.g. `showing ○ ◐`.
+    pub fn label(&self) -> String {
+        let shown: Vec<&str> = [
+            (TaskStatus::NotStarted, "○"),
+            (TaskStatus::InProgress, "◐"),
+            (TaskStatus::Completed, "✓"),
+        ]
+        .iter()
+        .filter(|(status, _)| !self.hidden.contains(status))
+        .map(|(_, symbol)| *symbol)
+        .collect();
+
+        if shown.is_empty() {
+            "showing nothing".to_string()
+        } else {
+            format!("showing {}", shown.join(" "))
+        }
+    }
+}
+
+/// Whose tasks are listed, for storage a team shares.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssigneeFilter {
+    #[default]
+    Everyone,
+    Mine,
+    Unassigned,
+}
+
+impl AssigneeFilter {
+    pub fn next(self) -> Self {
+        match self {
+            AssigneeFilter::Everyone => AssigneeFilter::Mine,
+            AssigneeFilter::Mine => AssigneeFilter::Unassigned,
+            AssigneeFilter::Unassigned => AssigneeFilter::Everyone,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AssigneeFilter::Everyone => "everyone",
+            AssigneeFilter::Mine => "mine",
+            AssigneeFilter::Unassigned => "unassigned",
+        }
+    }
+
+    /// Whether `task` is listed for the user with git email `me`.
+    pub fn allows(self, task: &Task, me: Option<&str>) -> bool {
+        match self {
+            AssigneeFilter::Everyone => true,
+            AssigneeFilter::Mine => me.is_some_and(|me| task.assignee.as_deref().is_some_and(|a| a.eq_ignore_ascii_case(me))),
+            AssigneeFilter::Unassigned => task.assignee.is_none(),
+        }
+    }
+}
+
+/// UI state remembered between sessions, stored in `~/.quill/state.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ViewState {
+    #[serde(default)]
+    pub status_filters: HashMap<String, StatusFilter>,
+    /// Issues created from a context with `G`, by context key.
+    #[serde(default)]
+    pub issue_links: HashMap<String, IssueLink>,
+    /// Watched contexts, with the ids of the open tasks already seen there.
+    #[serde(default)]
+    pub watched: HashMap<String, Vec<usize>>,
+    /// Priority and due date changes on a parent also apply to its subtasks.
+    #[serde(default)]
+    pub propagate_to_subtasks: bool,
+    /// Jira status of each linked issue at the last sync, by issue key.
+    #[serde(default)]
+    pub jira_statuses: HashMap<String, String>,
+    /// Set once the first-run offer to import todo files has been shown.
+    #[serde(default)]
+    pub import_offered: bool,
+    /// Applies to every context, as it's about the user rather than the list.
+    #[serde(default)]
+    pub assignee_filter: AssigneeFilter,
+}
+
+impl ViewState {
+    pub fn load() -> Result<Self> {
+        let path = Self::get_state_path()?;
+
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::get_state_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&path, content)?;
+        Ok(())
+    }
+
+    pub fn status_filter(&self, context_key: &str) -> StatusFilter {
+        self.status_filters.get(context_key).cloned().unwrap_or_default()
+    }
+
+    pub fn set_status_filter(&mut self, context_key: &str, filter: StatusFilter) {
+        if filter.is_active() {
+            self.status_filters.insert(context_key.to_string(), filter);
+        } else {
+            self.status_filters.remove(context_key);
+        }
+    }
+
+    pub fn is_watched(&self, context_key: &str) -> bool {
+        self.watched.contains_key(context_key)
+    }
+
+    /// Watches or unwatches a context; its current open tasks count as seen.
+    /// Returns whether the context is now watched.
+    pub fn toggle_watch(&mut self, context_key: &str, tasks: &[Task]) -> bool {
+        if self.watched.remove(context_key).is_some() {
+            false
+        } else {
+            self.watched.insert(context_key.to_string(), open_ids(tasks));
+            true
+        }
+    }
+
+    /// Records the open tasks of a watched context as seen; returns whether anything changed.
+    pub fn mark_seen(&mut self, context_key: &str, tasks: &[Task]) -> bool {
+        match self.watched.get_mut(context_key) {
+            Some(seen) if *seen != open_ids(tasks) => {
+                *seen = open_ids(tasks);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Open tasks of a watched context added since it was last seen.
+    pub fn unseen(&self, context_key: &str, tasks: &[Task]) -> usize {
+        self.watched.get(context_key).map_or(0, |seen| {
+            tasks.iter().filter(|t| !t.is_completed() && !seen.contains(&t.id)).count()
+        })
+    }
+
+    fn get_state_path() -> Result<PathBuf> {
+        let mut path = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        path.push(".quill");
+        path.push("state.json");
+        Ok(path)
+    }
+}
+
+fn open_ids(tasks: &[Task]) -> Vec<usize> {
+    tasks.iter().filter(|t| !t.is_completed()).map(|t| t.id).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_filter_toggle() {
+        let mut filter = StatusFilter::default();
+        let mut task = Task::new(1, "Task".to_string());
+        task.status = TaskStatus::Completed;
+
+        assert!(filter.allows(&task));
+        filter.toggle(TaskStatus::Completed);
+        assert!(!filter.allows(&task));
+        assert_eq!(filter.label(), "showing ○ ◐");
+
+        filter.toggle(TaskStatus::Completed);
+        assert!(!filter.is_active());
+    }
+
+    #[test]
+    fn test_assignee_filter() {
+        let mut mine = Task::new(1, "Mine".to_string());
+        mine.assignee = Some("Ann@acme.dev".to_string());
+        let unassigned = Task::new(2, "Anyone".to_string());
+        let me = Some("ann@acme.dev");
+
+        let filter = AssigneeFilter::default().next();
+        assert_eq!(filter, AssigneeFilter::Mine);
+        assert!(filter.allows(&mine, me) && !filter.allows(&unassigned, me));
+        // Without a git email nothing is anyone's own
+        assert!(!filter.allows(&mine, None));
+
+        let filter = filter.next();
+        assert!(!filter.allows(&mine, me) && filter.allows(&unassigned, me));
+        assert_eq!(filter.next(), AssigneeFilter::Everyone);
+    }
+
+    #[test]
+    fn test_inactive_filters_are_not_stored() {
+        let mut state = ViewState::default();
+        let mut filter = StatusFilter::default();
+        filter.toggle(TaskStatus::InProgress);
+        state.set_status_filter("a:b:c", filter);
+        assert_eq!(state.status_filter("a:b:c").hidden, vec![TaskStatus::InProgress]);
+
+        state.set_status_filter("a:b:c", StatusFilter::default());
+        assert!(state.status_filters.is_empty());
+    }
+
+    #[test]
+    fn test_watched_context_counts_new_open_tasks() {
+        let mut state = ViewState::default();
+        let mut tasks = vec![Task::new(1, "Seen".to_string())];
+        assert!(state.toggle_watch("a:b:c", &tasks));
+        assert_eq!(state.unseen("a:b:c", &tasks), 0);
+
+        let mut done = Task::new(3, "Done".to_string());
+        done.status = TaskStatus::Completed;
+        tasks.push(Task::new(2, "New".to_string()));
+        tasks.push(done);
+        assert_eq!(state.unseen("a:b:c", &tasks), 1);
+
+        assert!(state.mark_seen("a:b:c", &tasks));
+        assert!(!state.mark_seen("a:b:c", &tasks));
+        assert_eq!(state.unseen("a:b:c", &tasks), 0);
+
+        assert!(!state.toggle_watch("a:b:c", &tasks));
+        assert!(!state.is_watched("a:b:c"));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::config::StatusSegment;
+use crate::storage::tracked::SaveIndicator;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+
+/// Whether the storage the list comes from can be used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connection {
+    Connected,
+    /// Browsing another backend with the peek switcher.
+    ReadOnly,
+    /// The configured storage failed to open.
+    Down,
+}
+
+/// What the status line shows, gathered on each render.
+pub struct StatusInfo<'a> {
+    pub context: &'a str,
+    /// Listed tasks not started, in progress and completed.
+    pub counts: [usize; 3],
+    pub filters: Vec<String>,
+    pub sort: Option<&'static str>,
+    pub backend: &'a str,
+    pub connection: Connection,
+    pub save: Option<&'a SaveIndicator>,
+}
+
+const HELP: &str = "1/2/3: set status, p: require before push, Ctrl+↑/↓: move, F1/F2/F3: filter statuses";
+
+/// The spans of one segment, or none when it has nothing to say.
+fn segment(info: &StatusInfo, segment: StatusSegment) -> Option<Vec<Span<'static>>> {
+    let gray = Style::default().fg(Color::Gray);
+    Some(match segment {
+        StatusSegment::Context => {
+            let mut parts = info.context.splitn(3, ':');
+            let (org, repo, branch) = (parts.next()?, parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            vec![
+                Span::styled(format!("⌂ {}", org), Style::default().fg(Color::Cyan)),
+                Span::styled(format!(" ▣ {}", repo), Style::default().fg(Color::Cyan)),
+                Span::styled(format!(" ⎇ {}", branch), Style::default().fg(Color::Magenta)),
+            ]
+        }
+        StatusSegment::Counts => {
+            let [open, started, done] = info.counts;
+            vec![
+                Span::styled(format!("○{}", open), Style::default().fg(Color::Yellow)),
+                Span::styled(format!(" ◐{}", started), Style::default().fg(Color::Blue)),
+                Span::styled(format!(" ✓{}", done), Style::default().fg(Color::Green)),
+            ]
+        }
+        StatusSegment::Filter if !info.filters.is_empty() => vec![Span::styled(format!("⧩ {}", info.filters.join(", ")), gray)],
+        StatusSegment::Sort => vec![Span::styled(format!("⇅ {}", info.sort?), gray)],
+        StatusSegment::Backend => {
+            let (state, color) = match info.connection {
+                Connection::Connected => ("●", Color::Green),
+                Connection::ReadOnly => ("read-only", Color::Yellow),
+                Connection::Down => ("unavailable", Color::Red),
+            };
+            vec![Span::styled(format!("{} ", info.backend), gray), Span::styled(state, Style::default().fg(color))]
+        }
+        StatusSegment::Save => {
+            let save = info.save?;
+            let color = match save {
+                SaveIndicator::Saving => Color::DarkGray,
+                SaveIndicator::Saved => Color::Green,
+                SaveIndicator::Failed(_) => Color::Red,
+            };
+            vec![Span::styled(save.label(), Style::default().fg(color))]
+        }
+        StatusSegment::Help => vec![Span::styled(HELP, Style::default().fg(Color::DarkGray))],
+        StatusSegment::Filter => return None,
+    })
+}
+
+/// `segments` joined with separators, padded by a space at each end.
+pub fn line(info: &StatusInfo, segments: &[StatusSegment]) -> Line<'static> {
+    let mut spans = vec![Span::raw(" ")];
+    for (i, parts) in segments.iter().filter_map(|s| segment(info, *s)).enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
+        }
+        spans.extend(parts);
+    }
+    if spans.len() == 1 {
+        return Line::default();
+    }
+    spans.push(Span::raw(" "));
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_skips_empty_segments() {
+        let mut info = StatusInfo {
+            context: "acme:web:feature/login",
+            counts: [3, 1, 5],
+            filters: Vec::new(),
+            sort: None,
+            backend: "MongoDB",
+            connection: Connection::Connected,
+            save: None,
+        };
+        let segments = [StatusSegment::Context, StatusSegment::Counts, StatusSegment::Filter, StatusSegment::Sort];
+        assert_eq!(line(&info, &segments).to_string(), " ⌂ acme ▣ web ⎇ feature/login │ ○3 ◐1 ✓5 ");
+
+        info.filters = vec!["showing ○ ◐".to_string()];
+        info.sort = Some("due");
+        info.connection = Connection::Down;
+        assert_eq!(line(&info, &segments[2..]).to_string(), " ⧩ showing ○ ◐ │ ⇅ due ");
+        assert_eq!(line(&info, &[StatusSegment::Backend, StatusSegment::Save]).to_string(), " MongoDB unavailable ");
+        assert_eq!(line(&info, &[StatusSegment::Save]).to_string(), "");
+    }
+}
@@ -0,0 +1,154 @@
+use crate::storage::{Priority, Task};
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+
+/// Orders tasks so every subtask follows its parent, keeping the existing
+/// order among siblings. Tasks whose parent isn't in the list are top-level.
+pub fn tree_order(tasks: Vec<Task>) -> Vec<Task> {
+    let ids: HashSet<usize> = tasks.iter().map(|t| t.id).collect();
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (i, task) in tasks.iter().enumerate() {
+        match task.parent.filter(|p| ids.contains(p) && *p != task.id) {
+            Some(parent) => children.entry(parent).or_default().push(i),
+            None => roots.push(i),
+        }
+    }
+
+    let mut order = Vec::with_capacity(tasks.len());
+    let mut seen = HashSet::new();
+    let mut stack: Vec<usize> = roots.into_iter().rev().collect();
+    while let Some(i) = stack.pop() {
+        if !seen.insert(i) {
+            continue;
+        }
+        order.push(i);
+        if let Some(kids) = children.get(&tasks[i].id) {
+            stack.extend(kids.iter().rev());
+        }
+    }
+    // Parent cycles have no root; keep their tasks at the end
+    order.extend((0..tasks.len()).filter(|i| !seen.contains(i)));
+
+    let mut slots: Vec<Option<Task>> = tasks.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|i| slots[i].take()).collect()
+}
+
+/// Nesting level of each task within `tasks`, by id.
+pub fn depths(tasks: &[Task]) -> HashMap<usize, usize> {
+    let parents: HashMap<usize, Option<usize>> = tasks.iter().map(|t| (t.id, t.parent)).collect();
+    tasks
+        .iter()
+        .map(|task| {
+            let mut depth = 0;
+            let mut current = task.parent;
+            while let Some(parent) = current.filter(|p| parents.contains_key(p) && depth < tasks.len()) {
+                depth += 1;
+                current = parents[&parent];
+            }
+            (task.id, depth)
+        })
+        .collect()
+}
+
+/// Ids of every subtask below `id`, at any depth.
+pub fn descendants(id: usize, tasks: &[Task]) -> Vec<usize> {
+    let mut found = Vec::new();
+    let mut frontier = vec![id];
+    while let Some(current) = frontier.pop() {
+        for task in tasks.iter().filter(|t| t.parent == Some(current)) {
+            if task.id != id && !found.contains(&task.id) {
+                found.push(task.id);
+                frontier.push(task.id);
+            }
+        }
+    }
+    found
+}
+
+/// Values a parent derives from its open subtasks.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rollup {
+    pub priority: Priority,
+    pub due: Option<NaiveDate>,
+}
+
+/// The highest priority and earliest due date among each parent's open
+/// subtasks (at any depth), for parents that have open subtasks.
+pub fn rollups(tasks: &[Task]) -> HashMap<usize, Rollup> {
+    let by_id: HashMap<usize, &Task> = tasks.iter().map(|t| (t.id, t)).collect();
+    let mut rollups = HashMap::new();
+    for parent in tasks {
+        let open: Vec<&Task> = descendants(parent.id, tasks)
+            .into_iter()
+            .filter_map(|id| by_id.get(&id).copied())
+            .filter(|t| !t.is_completed())
+            .collect();
+        if open.is_empty() {
+            continue;
+        }
+        rollups.insert(
+            parent.id,
+            Rollup {
+                priority: open.iter().map(|t| t.priority).max().unwrap_or_default(),
+                due: open.iter().filter_map(|t| t.due).min(),
+            },
+        );
+    }
+    rollups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TaskStatus;
+
+    fn task(id: usize, parent: Option<usize>) -> Task {
+        let mut task = Task::new(id, format!("Task {}", id));
+        task.parent = parent;
+        task
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_tree_order_and_depths() {
+        let tasks = vec![task(3, Some(1)), task(1, None), task(2, None), task(4, Some(3)), task(5, Some(99))];
+        let ordered = tree_order(tasks);
+        let ids: Vec<usize> = ordered.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 3, 4, 2, 5]);
+
+        let depths = depths(&ordered);
+        assert_eq!((depths[&1], depths[&3], depths[&4], depths[&5]), (0, 1, 2, 0));
+        assert_eq!(descendants(1, &ordered), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_cycles_do_not_lose_tasks() {
+        let ids: Vec<usize> = tree_order(vec![task(1, Some(2)), task(2, Some(1)), task(3, None)])
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn test_rollup_uses_open_subtasks_only() {
+        let mut urgent = task(2, Some(1));
+        urgent.priority = Priority::High;
+        urgent.due = Some(date("2024-03-01"));
+        urgent.status = TaskStatus::Completed;
+        let mut nested = task(4, Some(3));
+        nested.priority = Priority::Medium;
+        nested.due = Some(date("2024-03-05"));
+        let mut later = task(3, Some(1));
+        later.due = Some(date("2024-04-01"));
+
+        let rollups = rollups(&[task(1, None), urgent, later, nested]);
+        assert_eq!(rollups[&1], Rollup { priority: Priority::Medium, due: Some(date("2024-03-05")) });
+        assert_eq!(rollups[&3], Rollup { priority: Priority::Medium, due: Some(date("2024-03-05")) });
+        assert!(!rollups.contains_key(&2));
+    }
+}
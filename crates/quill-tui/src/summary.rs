@@ -0,0 +1,147 @@
+use crate::storage::{Task, TaskStorage};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+
+/// Every context's tasks at one point in time.
+pub type Snapshot = HashMap<String, Vec<Task>>;
+
+pub async fn snapshot(storage: &dyn TaskStorage) -> Result<Snapshot> {
+    Ok(storage.get_all_tasks().await?.into_iter().collect())
+}
+
+/// Changes to one context between two snapshots.
+#[derive(Debug, Default, PartialEq)]
+pub struct ContextChanges {
+    pub added: usize,
+    pub completed: usize,
+    pub deleted: usize,
+    /// Existing tasks changed in any other way (text, status, tags...).
+    pub edited: usize,
+}
+
+impl ContextChanges {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What changed during a TUI session, per context.
+#[derive(Debug, Default)]
+pub struct SessionSummary {
+    pub contexts: BTreeMap<String, ContextChanges>,
+}
+
+impl SessionSummary {
+    pub fn between(before: &Snapshot, after: &Snapshot) -> Self {
+        let mut contexts = BTreeMap::new();
+        let empty = Vec::new();
+
+        for context_key in before.keys().chain(after.keys()) {
+            if contexts.contains_key(context_key) {
+                continue;
+            }
+            let old = before.get(context_key).unwrap_or(&empty);
+            let new = after.get(context_key).unwrap_or(&empty);
+
+            let mut changes = ContextChanges::default();
+            for task in new {
+                match old.iter().find(|t| t.uid == task.uid) {
+                    None => {
+                        changes.added += 1;
+                        if task.is_completed() {
+                            changes.completed += 1;
+                        }
+                    }
+                    Some(previous) if task.is_completed() && !previous.is_completed() => changes.completed += 1,
+                    Some(previous) if previous != task => changes.edited += 1,
+                    Some(_) => {}
+                }
+            }
+            changes.deleted = old.iter().filter(|t| !new.iter().any(|n| n.uid == t.uid)).count();
+
+            if !changes.is_empty() {
+                contexts.insert(context_key.clone(), changes);
+            }
+        }
+
+        Self { contexts }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty()
+    }
+
+    /// Renders the summary, with ANSI colors when `color` is set.
+    pub fn render(&self, color: bool) -> String {
+        let paint = |code: &str, text: String| {
+            if color {
+                format!("\x1b[{}m{}\x1b[0m", code, text)
+            } else {
+                text
+            }
+        };
+
+        let total = |f: fn(&ContextChanges) -> usize| self.contexts.values().map(f).sum::<usize>();
+        let mut out = format!(
+            "quill: {} added, {} completed, {} deleted in {} context{}\n",
+            total(|c| c.added),
+            total(|c| c.completed),
+            total(|c| c.deleted),
+            self.contexts.len(),
+            if self.contexts.len() == 1 { "" } else { "s" }
+        );
+
+        for (context_key, changes) in &self.contexts {
+            let mut parts = Vec::new();
+            if changes.added > 0 {
+                parts.push(paint("32", format!("+{}", changes.added)));
+            }
+            if changes.completed > 0 {
+                parts.push(paint("36", format!("✓{}", changes.completed)));
+            }
+            if changes.deleted > 0 {
+                parts.push(paint("31", format!("-{}", changes.deleted)));
+            }
+            if changes.edited > 0 {
+                parts.push(paint("33", format!("~{}", changes.edited)));
+            }
+            out.push_str(&format!("  {}  {}\n", context_key, parts.join(" ")));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TaskStatus;
+
+    #[test]
+    fn test_summary_counts_changes_per_context() {
+        let kept = Task::new(1, "Kept".to_string());
+        let finished = Task::new(2, "Finish me".to_string());
+        let doomed = Task::new(3, "Delete me".to_string());
+        let before = Snapshot::from([("a:b:main".to_string(), vec![kept.clone(), finished.clone(), doomed])]);
+
+        let mut done = finished;
+        done.status = TaskStatus::Completed;
+        let after = Snapshot::from([
+            ("a:b:main".to_string(), vec![kept, done]),
+            ("a:b:dev".to_string(), vec![Task::new(4, "New".to_string())]),
+        ]);
+
+        let summary = SessionSummary::between(&before, &after);
+        assert_eq!(summary.contexts["a:b:main"], ContextChanges { added: 0, completed: 1, deleted: 1, edited: 0 });
+        assert_eq!(summary.contexts["a:b:dev"], ContextChanges { added: 1, completed: 0, deleted: 0, edited: 0 });
+
+        let text = summary.render(false);
+        assert!(text.starts_with("quill: 1 added, 1 completed, 1 deleted in 2 contexts\n"));
+        assert!(text.contains("  a:b:main  ✓1 -1\n"));
+    }
+
+    #[test]
+    fn test_unchanged_snapshot_is_empty() {
+        let snapshot = Snapshot::from([("a:b:main".to_string(), vec![Task::new(1, "Same".to_string())])]);
+        assert!(SessionSummary::between(&snapshot, &snapshot).is_empty());
+    }
+}
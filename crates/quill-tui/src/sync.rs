@@ -0,0 +1,147 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+
+/// Entries kept in `~/.quill/sync_log.json`.
+const MAX_ENTRIES: usize = 200;
+
+/// Integrations that can appear in the sync log, in display order.
+pub const INTEGRATIONS: [&str; 3] = [GITHUB, GITLAB, JIRA];
+pub const GITHUB: &str = "GitHub";
+pub const GITLAB: &str = "GitLab";
+pub const JIRA: &str = "Jira";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SyncDirection {
+    /// Local tasks sent to the integration.
+    Push,
+    /// Remote changes applied to local tasks.
+    Pull,
+}
+
+impl SyncDirection {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SyncDirection::Push => "push",
+            SyncDirection::Pull => "pull",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncLogEntry {
+    /// RFC 3339 time of the operation.
+    pub at: String,
+    pub integration: String,
+    pub direction: SyncDirection,
+    pub context_key: String,
+    pub items_changed: usize,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl SyncLogEntry {
+    pub fn new(integration: &str, direction: SyncDirection, context_key: &str, result: &Result<usize>) -> Self {
+        Self {
+            at: chrono::Utc::now().to_rfc3339(),
+            integration: integration.to_string(),
+            direction,
+            context_key: context_key.to_string(),
+            items_changed: *result.as_ref().unwrap_or(&0),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        }
+    }
+}
+
+/// Recent sync operations and which integrations are paused.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncLog {
+    #[serde(default)]
+    pub entries: VecDeque<SyncLogEntry>,
+    #[serde(default)]
+    pub paused: Vec<String>,
+}
+
+impl SyncLog {
+    pub fn load() -> Result<Self> {
+        let path = Self::get_log_path()?;
+
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::get_log_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Adds an entry, newest first, dropping the oldest beyond the cap.
+    pub fn record(&mut self, entry: SyncLogEntry) {
+        self.entries.push_front(entry);
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    pub fn entries_for(&self, integration: &str) -> Vec<SyncLogEntry> {
+        self.entries.iter().filter(|e| e.integration == integration).cloned().collect()
+    }
+
+    pub fn is_paused(&self, integration: &str) -> bool {
+        self.paused.iter().any(|p| p == integration)
+    }
+
+    pub fn toggle_pause(&mut self, integration: &str) {
+        if self.is_paused(integration) {
+            self.paused.retain(|p| p != integration);
+        } else {
+            self.paused.push(integration.to_string());
+        }
+    }
+
+    fn get_log_path() -> Result<PathBuf> {
+        let mut path = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        path.push(".quill");
+        path.push("sync_log.json");
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_keeps_newest_first_and_caps() {
+        let mut log = SyncLog::default();
+        for i in 0..(MAX_ENTRIES + 5) {
+            log.record(SyncLogEntry::new(GITHUB, SyncDirection::Pull, "a:b:c", &Ok(i)));
+        }
+        assert_eq!(log.entries.len(), MAX_ENTRIES);
+        assert_eq!(log.entries[0].items_changed, MAX_ENTRIES + 4);
+    }
+
+    #[test]
+    fn test_entry_records_errors_and_pause_toggles() {
+        let entry = SyncLogEntry::new(GITHUB, SyncDirection::Push, "a:b:c", &Err(anyhow::anyhow!("401 Unauthorized")));
+        assert_eq!(entry.items_changed, 0);
+        assert_eq!(entry.error.as_deref(), Some("401 Unauthorized"));
+
+        let mut log = SyncLog::default();
+        log.toggle_pause(GITHUB);
+        assert!(log.is_paused(GITHUB));
+        log.toggle_pause(GITHUB);
+        assert!(!log.is_paused(GITHUB));
+    }
+}
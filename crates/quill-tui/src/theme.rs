@@ -0,0 +1,78 @@
+use crate::config::Theme;
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+
+/// Recolors a rendered frame for `theme`. Screens are drawn for dark
+/// backgrounds, so the dark theme leaves them as they are.
+pub fn apply(theme: Theme, buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        match theme {
+            Theme::Dark => return,
+            Theme::Light => {
+                cell.fg = light_fg(cell.fg);
+                cell.bg = light_bg(cell.bg);
+            }
+            Theme::Mono => {
+                // Highlighted rows and buttons still stand out
+                if cell.bg != Color::Reset {
+                    cell.modifier.insert(Modifier::REVERSED);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+        }
+    }
+}
+
+/// Text colors that stay readable on a light background. The 256-color
+/// accents already are.
+fn light_fg(color: Color) -> Color {
+    match color {
+        Color::White | Color::Gray => Color::Black,
+        Color::Yellow | Color::LightYellow => Color::Indexed(136),
+        Color::Cyan | Color::LightCyan => Color::Indexed(31),
+        Color::Green | Color::LightGreen => Color::Indexed(28),
+        Color::Magenta | Color::LightMagenta => Color::Indexed(127),
+        Color::LightBlue => Color::Blue,
+        Color::LightRed => Color::Red,
+        other => other,
+    }
+}
+
+/// Highlight backgrounds light enough for the darkened text on them.
+fn light_bg(color: Color) -> Color {
+    match color {
+        Color::DarkGray => Color::Indexed(252),
+        Color::Blue => Color::Indexed(153),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::style::Style;
+
+    #[test]
+    fn test_themes_recolor_cells() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 2, 1));
+        buffer.set_string(0, 0, "a", Style::default().fg(Color::White).bg(Color::DarkGray));
+        buffer.set_string(1, 0, "b", Style::default().fg(Color::Indexed(39)));
+
+        let mut dark = buffer.clone();
+        apply(Theme::Dark, &mut dark);
+        assert_eq!(dark, buffer);
+
+        let mut light = buffer.clone();
+        apply(Theme::Light, &mut light);
+        assert_eq!((light[(0, 0)].fg, light[(0, 0)].bg), (Color::Black, Color::Indexed(252)));
+        assert_eq!(light[(1, 0)].fg, Color::Indexed(39));
+
+        let mut mono = buffer.clone();
+        apply(Theme::Mono, &mut mono);
+        assert_eq!((mono[(0, 0)].fg, mono[(0, 0)].bg), (Color::Reset, Color::Reset));
+        assert!(mono[(0, 0)].modifier.contains(Modifier::REVERSED));
+        assert!(!mono[(1, 0)].modifier.contains(Modifier::REVERSED));
+    }
+}
@@ -0,0 +1,3329 @@
+use crate::accent;
+use crate::cleanup::{self, Candidate};
+use crate::dates::DateParser;
+use crate::diff::{self, ContextDiff, DiffLine};
+use crate::markdown;
+use crate::palette::{self, PaletteItem};
+use crate::prompt::Rollup;
+use crate::overview::{self, Overview, Row};
+use crate::quickadd::QuickAdd;
+use crate::similar::Suggestion;
+use crate::storage::activity::{ActivityAction, ActivityEntry};
+use crate::storage::tracked::SaveIndicator;
+use crate::storage::{self, Attachment, BackendHealth, DeletedTask, Priority, Task, TaskStatus};
+use crate::secret;
+use crate::config::{AppConfig, ContextScope, DetailsPaneConfig, LongTitles, StatusLineConfig, StorageType, Theme};
+use crate::git::LinkedCommit;
+use crate::editor::LineEditor;
+use crate::github::IssueLink;
+use crate::sync::{SyncLogEntry, INTEGRATIONS};
+use crate::state::{AssigneeFilter, StatusFilter};
+use crate::statusline::{self, Connection, StatusInfo};
+use crate::subtasks;
+use crate::theme;
+use crate::templates::TaskTemplate;
+use crate::views::{Filter, View};
+use unicode_width::UnicodeWidthChar;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, Gauge, HighlightSpacing, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
+    Frame,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+pub struct TaskUI {
+    pub list_state: ListState,
+    pub input_mode: InputMode,
+    pub input: LineEditor,
+    pub editing_id: Option<usize>,
+    pub config_field_index: usize,
+    pub temp_config: AppConfig,
+    pub config_screen: ConfigScreen,
+    pub storage_selection_index: usize,
+    pub notification: Option<Notification>,
+    pub inspector_text: String,
+    pub inspector_scroll: u16,
+    pub open_task_soft_limit: usize,
+    /// Due-date words understood by quick-add, from `date_locales`.
+    pub date_parser: DateParser,
+    pub stats: Vec<ContextStats>,
+    pub stats_state: ListState,
+    /// Task lists of stale branches offered by the cleanup wizard, and
+    /// whether each is picked.
+    pub cleanup: Vec<(Candidate, bool)>,
+    pub cleanup_state: ListState,
+    /// The dry run of the action chosen in the cleanup wizard, shown until
+    /// it is confirmed.
+    pub cleanup_preview: Option<(cleanup::Action, Vec<String>)>,
+    /// Open tasks here, in the repository and in the organization.
+    pub rollup: Option<Rollup>,
+    /// Open tasks of every context, shown by the overview (`O`).
+    pub overview: Overview,
+    pub overview_state: ListState,
+    /// Tasks matching the search (`Ctrl+f`) with their context key, grouped by context.
+    pub search_results: Vec<(String, Task)>,
+    pub search_state: ListState,
+    pub health: Option<BackendHealth>,
+    /// Backends offered by the peek switcher, the default one first.
+    pub backend_choices: Vec<StorageType>,
+    pub backend_state: ListState,
+    /// Label of the backend being browsed read-only instead of the default.
+    pub peeking: Option<String>,
+    pub trash: Vec<DeletedTask>,
+    pub trash_state: ListState,
+    /// The current context's recorded changes, newest first.
+    pub activity: Vec<ActivityEntry>,
+    pub activity_state: ListState,
+    /// Task text and notes shown by the notes view, and their links in order.
+    pub notes_view: Option<(String, String)>,
+    pub notes_links: Vec<String>,
+    pub notes_scroll: u16,
+    /// Attachments of the task the attachments view was opened for.
+    pub attachments: Vec<Attachment>,
+    pub attachments_state: ListState,
+    pub attachments_task: Option<usize>,
+    pub templates: Vec<TaskTemplate>,
+    pub template_state: ListState,
+    /// Saved filters from `[views]`, offered by the view picker (`V`).
+    pub views: Vec<View>,
+    /// Name of the view filtering the list.
+    pub active_view: Option<String>,
+    /// Selection in the view picker, where row 0 is "All tasks".
+    pub view_picker_state: ListState,
+    /// Todo sources offered for import, and whether each is ticked.
+    pub import_sources: Vec<(String, bool)>,
+    pub import_state: ListState,
+    /// People offered by the assign picker; `None` is "unassigned".
+    pub assignees: Vec<Option<String>>,
+    pub assignee_state: ListState,
+    pub status_filter: StatusFilter,
+    pub assignee_filter: AssigneeFilter,
+    /// The user's git `user.email`, which "mine" means.
+    pub user_email: Option<String>,
+    /// Layout from the last render, used for mouse hit-testing.
+    pub list_area: Rect,
+    /// Lines each task row took in the last render, for mapping clicks.
+    pub row_heights: Vec<u16>,
+    pub long_titles: LongTitles,
+    pub status_line: StatusLineConfig,
+    /// Completed and total tasks of the current context, whatever the filters.
+    pub progress: (usize, usize),
+    /// Label of the storage backend in use, for the status line.
+    pub backend: String,
+    pub details_pane: DetailsPaneConfig,
+    pub show_details: bool,
+    /// Whether snoozed tasks are listed rather than hidden.
+    pub show_snoozed: bool,
+    /// Commits mentioning the task with this short uid, for the details pane.
+    pub details_commits: Option<(String, Vec<LinkedCommit>)>,
+    pub footer_buttons: Vec<(Rect, char)>,
+    /// Ids of tasks marked with `v` for bulk actions.
+    pub marked: HashSet<usize>,
+    /// GitHub issue the current context was exported to.
+    pub issue_link: Option<IssueLink>,
+    /// Jira issue the current branch is named after.
+    pub jira_issue: Option<String>,
+    /// Index into `sync::INTEGRATIONS` shown in the sync log.
+    pub sync_integration: usize,
+    pub sync_entries: Vec<SyncLogEntry>,
+    pub sync_paused: bool,
+    pub sync_state: ListState,
+    /// Outcome of the latest write, shown at the right of the footer.
+    pub save_indicator: Option<SaveIndicator>,
+    pub sort_order: SortOrder,
+    pub palette: Vec<PaletteItem>,
+    /// Indices into `palette` matching the typed query, best first.
+    pub palette_matches: Vec<usize>,
+    pub palette_state: ListState,
+    /// Result of the last sync or import, open for review and revert.
+    pub diff: Option<ContextDiff>,
+    pub diff_scroll: u16,
+    /// Count prefix / operator typed so far in normal mode, e.g. `3d`.
+    pub pending_keys: String,
+    /// Phrase to type to confirm a held-back bulk change, e.g. `delete 14`.
+    pub confirm_phrase: String,
+    /// Whether the current context is watched, and new tasks in the other watched ones.
+    pub watched: bool,
+    pub watch_badge: usize,
+    /// Parent of the task being added with `A`.
+    pub adding_parent: Option<usize>,
+    /// Notes written with `Ctrl+e`, saved with the task being added or edited.
+    pub input_notes: Option<String>,
+    /// Open task (id, text) resembling the one being added.
+    pub similar_task: Option<(usize, String)>,
+    /// Past tasks to suggest from, gathered when the add popup opens.
+    pub suggestion_pool: Vec<Suggestion>,
+    pub suggestions: Vec<Suggestion>,
+    /// Suggestion the next Tab fills in.
+    pub suggestion_index: usize,
+    pub propagate_to_subtasks: bool,
+    /// Text plugins append to rows, by task id.
+    pub decorations: HashMap<usize, String>,
+    /// Set while the configured storage is unavailable and no choice was made.
+    pub storage_failure: Option<StorageFailure>,
+    /// Recent log lines while the debug overlay (F12) is open.
+    pub debug_log: Option<Vec<String>>,
+    pub theme: Theme,
+    /// Screen of the first-run setup shown while `input_mode` is `Setup`.
+    pub setup_step: SetupStep,
+    /// Outcome of the last connection test: how long it took, or the error.
+    pub connection_test: Option<Result<Duration, String>>,
+    /// Whether config screens show passwords instead of masking them.
+    pub reveal_secrets: bool,
+}
+
+/// Clickable footer buttons as (key, label); clicking one acts like pressing the key.
+const FOOTER_BUTTONS: [(char, &str); 9] = [
+    ('a', "Add"),
+    ('e', "Edit"),
+    ('d', "Delete"),
+    ('u', "Undo"),
+    (' ', "Status"),
+    ('s', "Stats"),
+    ('t', "Templates"),
+    ('c', "Config"),
+    ('q', "Quit"),
+];
+
+/// Width of the "➤ " highlight column that precedes every list item.
+const HIGHLIGHT_WIDTH: u16 = 2;
+
+pub struct ContextStats {
+    pub context_key: String,
+    pub open: usize,
+    pub total: usize,
+}
+
+#[derive(Clone)]
+pub struct Notification {
+    pub message: String,
+    pub level: NotificationLevel,
+    pub created_at: Instant,
+    pub duration: Duration,
+}
+
+#[derive(Clone)]
+pub enum NotificationLevel {
+    Success,
+    Error,
+}
+
+#[derive(PartialEq)]
+pub enum InputMode {
+    Normal,
+    Adding,
+    Editing,
+    ConfigHome,
+    ConfigStorageSelection,
+    ConfigLocal,
+    ConfigLocalField,
+    ConfigMongoDB,
+    ConfigMongoDBField,
+    ConfigCredentials,
+    ConfigCredentialField,
+    Inspector,
+    Stats,
+    Overview,
+    Search,
+    Cleanup,
+    Health,
+    BackendPicker,
+    Trash,
+    Activity,
+    Notes,
+    Attachments,
+    AddingAttachment,
+    ShiftingDue,
+    Snoozing,
+    SettingAnchor,
+    Deferring,
+    Triaging,
+    Confirming,
+    SyncLog,
+    Diff,
+    Palette,
+    TemplatePicker,
+    ViewPicker,
+    AddingView,
+    ImportPicker,
+    AssigneePicker,
+    StorageRecovery,
+    StorageConflict,
+    Setup,
+    SetupField,
+}
+
+/// Screens of the first-run setup, in order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetupStep {
+    Backend,
+    Connection,
+    Scope,
+    Theme,
+}
+
+/// Themes offered by the setup, in the order listed.
+pub const THEMES: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::Mono];
+
+/// Fields of the MongoDB screen. After the first three come the options
+/// given to the driver on top of the connection string.
+pub const MONGO_FIELDS: [&str; 12] = [
+    "Connection URL",
+    "Database",
+    "Collection",
+    "TLS",
+    "CA file",
+    "Client certificate",
+    "Allow invalid certificates",
+    "Auth mechanism",
+    "Auth source",
+    "Server selection timeout (s)",
+    "Max pool size",
+    "Min pool size",
+];
+
+/// Secrets the credentials screen can keep in the OS keychain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Credential {
+    MongoPassword,
+    RemoteToken,
+    GitHubToken,
+    GitLabToken,
+    JiraToken,
+}
+
+impl Credential {
+    /// In the order listed.
+    pub const ALL: [Credential; 5] = [
+        Credential::MongoPassword,
+        Credential::RemoteToken,
+        Credential::GitHubToken,
+        Credential::GitLabToken,
+        Credential::JiraToken,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Credential::MongoPassword => "MongoDB password",
+            Credential::RemoteToken => "Sync server token",
+            Credential::GitHubToken => "GitHub token",
+            Credential::GitLabToken => "GitLab token",
+            Credential::JiraToken => "Jira token",
+        }
+    }
+
+    /// Name of its keychain entry.
+    pub fn keychain_name(self) -> &'static str {
+        match self {
+            Credential::MongoPassword => "mongodb",
+            Credential::RemoteToken => "remote",
+            Credential::GitHubToken => "github",
+            Credential::GitLabToken => "gitlab",
+            Credential::JiraToken => "jira",
+        }
+    }
+
+    /// What `config` has for it: the secret itself or a reference to it.
+    pub fn value(self, config: &AppConfig) -> String {
+        match self {
+            Credential::MongoPassword => secret::uri_password(&config.mongo_config.connection_string).unwrap_or_default().to_string(),
+            Credential::RemoteToken => config.remote_config.token.clone(),
+            Credential::GitHubToken => config.github.token.clone(),
+            Credential::GitLabToken => config.gitlab.token.clone(),
+            Credential::JiraToken => config.jira.token.clone(),
+        }
+    }
+
+    /// Makes `value` what `config` has for it. The MongoDB password needs a
+    /// user in the connection URL to belong to.
+    pub fn set(self, config: &mut AppConfig, value: String) -> anyhow::Result<()> {
+        match self {
+            Credential::MongoPassword => {
+                let mongo = &mut config.mongo_config;
+                mongo.connection_string = secret::with_uri_password(&mongo.connection_string, &value)
+                    .ok_or_else(|| anyhow::anyhow!("Add a user to the MongoDB connection URL first, e.g. mongodb://app@db:27017"))?;
+            }
+            Credential::RemoteToken => config.remote_config.token = value,
+            Credential::GitHubToken => config.github.token = value,
+            Credential::GitLabToken => config.gitlab.token = value,
+            Credential::JiraToken => config.jira.token = value,
+        }
+        Ok(())
+    }
+}
+
+/// Why the configured storage couldn't be opened, shown until the user
+/// decides what to do about it.
+#[derive(Debug, Clone)]
+pub struct StorageFailure {
+    pub backend: StorageType,
+    pub error: String,
+    /// Whether switching the saved config to local storage is on offer;
+    /// not when the backend came from a storage rule or already was local.
+    pub can_switch: bool,
+}
+
+/// Display order of the task list; only Manual matches the stored order.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SortOrder {
+    #[default]
+    Manual,
+    Priority,
+    Due,
+}
+
+impl SortOrder {
+    pub fn next(self) -> Self {
+        match self {
+            SortOrder::Manual => SortOrder::Priority,
+            SortOrder::Priority => SortOrder::Due,
+            SortOrder::Due => SortOrder::Manual,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Manual => "manual order",
+            SortOrder::Priority => "priority",
+            SortOrder::Due => "due date",
+        }
+    }
+
+    /// Stable sort, so ties keep the manual order. Pinned tasks come first
+    /// and tasks without a due date go last.
+    pub fn sort(self, tasks: &mut [Task]) {
+        match self {
+            SortOrder::Manual => {}
+            SortOrder::Priority => tasks.sort_by_key(|t| std::cmp::Reverse(t.priority)),
+            SortOrder::Due => tasks.sort_by_key(|t| (t.due.is_none(), t.due)),
+        }
+        tasks.sort_by_key(|t| !t.pinned);
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub enum ConfigScreen {
+    Home,
+    StorageSelection,
+    LocalConfig,
+    MongoDBConfig,
+    GitConfig,
+    RemoteConfig,
+    Credentials,
+}
+
+impl Default for TaskUI {
+    fn default() -> Self {
+        Self {
+            list_state: ListState::default(),
+            input_mode: InputMode::Normal,
+            input: LineEditor::new(),
+            editing_id: None,
+            config_field_index: 0,
+            temp_config: AppConfig::default(),
+            config_screen: ConfigScreen::Home,
+            storage_selection_index: 0,
+            notification: None,
+            inspector_text: String::new(),
+            inspector_scroll: 0,
+            open_task_soft_limit: AppConfig::default().open_task_soft_limit,
+            date_parser: DateParser::default(),
+            stats: Vec::new(),
+            stats_state: ListState::default(),
+            cleanup: Vec::new(),
+            cleanup_state: ListState::default(),
+            cleanup_preview: None,
+            rollup: None,
+            overview: Overview::default(),
+            overview_state: ListState::default(),
+            search_results: Vec::new(),
+            search_state: ListState::default(),
+            health: None,
+            backend_choices: Vec::new(),
+            backend_state: ListState::default(),
+            peeking: None,
+            trash: Vec::new(),
+            trash_state: ListState::default(),
+            activity: Vec::new(),
+            activity_state: ListState::default(),
+            notes_view: None,
+            notes_links: Vec::new(),
+            notes_scroll: 0,
+            attachments: Vec::new(),
+            attachments_state: ListState::default(),
+            attachments_task: None,
+            templates: Vec::new(),
+            template_state: ListState::default(),
+            views: Vec::new(),
+            active_view: None,
+            view_picker_state: ListState::default(),
+            import_sources: Vec::new(),
+            import_state: ListState::default(),
+            assignees: Vec::new(),
+            assignee_state: ListState::default(),
+            status_filter: StatusFilter::default(),
+            assignee_filter: AssigneeFilter::default(),
+            user_email: None,
+            list_area: Rect::default(),
+            row_heights: Vec::new(),
+            long_titles: LongTitles::default(),
+            status_line: StatusLineConfig::default(),
+            progress: (0, 0),
+            backend: String::new(),
+            details_pane: DetailsPaneConfig::default(),
+            show_details: false,
+            show_snoozed: false,
+            details_commits: None,
+            footer_buttons: Vec::new(),
+            marked: HashSet::new(),
+            issue_link: None,
+            jira_issue: None,
+            sync_integration: 0,
+            sync_entries: Vec::new(),
+            sync_paused: false,
+            sync_state: ListState::default(),
+            save_indicator: None,
+            sort_order: SortOrder::default(),
+            palette: Vec::new(),
+            palette_matches: Vec::new(),
+            palette_state: ListState::default(),
+            diff: None,
+            diff_scroll: 0,
+            pending_keys: String::new(),
+            confirm_phrase: String::new(),
+            watched: false,
+            watch_badge: 0,
+            adding_parent: None,
+            input_notes: None,
+            similar_task: None,
+            suggestion_pool: Vec::new(),
+            suggestions: Vec::new(),
+            suggestion_index: 0,
+            propagate_to_subtasks: false,
+            decorations: HashMap::new(),
+            storage_failure: None,
+            debug_log: None,
+            theme: Theme::default(),
+            setup_step: SetupStep::Backend,
+            connection_test: None,
+            reveal_secrets: false,
+        }
+    }
+}
+
+impl TaskUI {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn select_next(&mut self, tasks: &[Task]) {
+        if tasks.is_empty() {
+            return;
+        }
+        
+        let selected = self.list_state.selected().unwrap_or(0);
+        let next = if selected >= tasks.len() - 1 {
+            0
+        } else {
+            selected + 1
+        };
+        self.list_state.select(Some(next));
+    }
+
+    pub fn select_previous(&mut self, tasks: &[Task]) {
+        if tasks.is_empty() {
+            return;
+        }
+        
+        let selected = self.list_state.selected().unwrap_or(0);
+        let previous = if selected == 0 {
+            tasks.len() - 1
+        } else {
+            selected - 1
+        };
+        self.list_state.select(Some(previous));
+    }
+
+    /// Moves the selection by `delta` without wrapping around.
+    pub fn scroll_selection(&mut self, tasks: &[Task], delta: i32) {
+        if tasks.is_empty() {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0) as i32;
+        let target = (selected + delta).clamp(0, tasks.len() as i32 - 1);
+        self.list_state.select(Some(target as usize));
+    }
+
+    /// Number of task rows visible in the list viewport.
+    pub fn page_size(&self) -> usize {
+        self.list_area.height.saturating_sub(2).max(1) as usize
+    }
+
+    pub fn select_first(&mut self, tasks: &[Task]) {
+        if !tasks.is_empty() {
+            self.list_state.select(Some(0));
+        }
+    }
+
+    pub fn select_last(&mut self, tasks: &[Task]) {
+        if !tasks.is_empty() {
+            self.list_state.select(Some(tasks.len() - 1));
+        }
+    }
+
+    /// Returns the index of the task rendered at a screen position and
+    /// whether the position is on its status symbol.
+    pub fn task_at(&self, column: u16, row: u16, task_count: usize) -> Option<(usize, bool)> {
+        let area = self.list_area;
+        let inner_top = area.y + 1;
+        let inner_left = area.x + 1;
+        if row < inner_top || row >= area.y + area.height.saturating_sub(1)
+            || column < inner_left || column >= area.x + area.width.saturating_sub(1)
+        {
+            return None;
+        }
+
+        // Wrapped rows take several lines
+        let mut index = self.list_state.offset();
+        let mut top = inner_top;
+        loop {
+            if index >= task_count {
+                return None;
+            }
+            let height = self.row_heights.get(index).copied().unwrap_or(1).max(1);
+            if row < top + height {
+                break;
+            }
+            top += height;
+            index += 1;
+        }
+
+        let symbol_column = inner_left + HIGHLIGHT_WIDTH;
+        let on_symbol = row == top && column >= symbol_column && column <= symbol_column + 1;
+        Some((index, on_symbol))
+    }
+
+    pub fn footer_button_at(&self, column: u16, row: u16) -> Option<char> {
+        self.footer_buttons
+            .iter()
+            .find(|(rect, _)| {
+                column >= rect.x && column < rect.x + rect.width && row >= rect.y && row < rect.y + rect.height
+            })
+            .map(|(_, key)| *key)
+    }
+
+    pub fn start_adding(&mut self) {
+        self.input_mode = InputMode::Adding;
+        self.input.clear();
+    }
+
+    pub fn start_adding_subtask(&mut self, parent: &Task) {
+        self.start_adding();
+        self.adding_parent = Some(parent.id);
+    }
+
+    pub fn start_editing(&mut self, task: &Task) {
+        self.input_mode = InputMode::Editing;
+        self.input.set(task.text.clone());
+        self.editing_id = Some(task.id);
+    }
+
+    pub fn toggle_mark(&mut self, id: usize) {
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+    }
+
+    /// Starts the due-date prompt for the marked tasks (or the selected one).
+    pub fn start_anchor(&mut self, task: &Task) {
+        self.input_mode = InputMode::SettingAnchor;
+        self.input.set(task.anchor.as_ref().map(|a| a.to_string()).unwrap_or_default());
+        self.editing_id = Some(task.id);
+    }
+
+    pub fn start_confirm(&mut self, phrase: String) {
+        self.input_mode = InputMode::Confirming;
+        self.input.clear();
+        self.confirm_phrase = phrase;
+    }
+
+    pub fn start_due_shift(&mut self) {
+        self.input_mode = InputMode::ShiftingDue;
+        self.input.clear();
+    }
+
+    /// Starts the prompt for snoozing the marked tasks (or the selected one).
+    pub fn start_snooze(&mut self) {
+        self.input_mode = InputMode::Snoozing;
+        self.input.clear();
+    }
+
+    /// Starts the branch prompt for deferring the marked tasks (or the selected one).
+    pub fn start_defer(&mut self) {
+        self.input_mode = InputMode::Deferring;
+        self.input.clear();
+    }
+
+    /// Starts the context prompt for moving the marked tasks (or the
+    /// selected one) elsewhere, suggesting `target`.
+    pub fn start_triage(&mut self, target: String) {
+        self.input_mode = InputMode::Triaging;
+        self.input.set(target);
+    }
+
+    pub fn cancel_input(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.input.clear();
+        self.editing_id = None;
+        self.adding_parent = None;
+        self.input_notes = None;
+        self.similar_task = None;
+        self.suggestion_pool.clear();
+        self.suggestions.clear();
+        self.suggestion_index = 0;
+    }
+
+    pub fn finish_input(&mut self) -> String {
+        let text = self.input.text().to_string();
+        self.cancel_input();
+        text
+    }
+
+    /// Refreshes the suggestions for the text typed so far.
+    pub fn update_suggestions(&mut self) {
+        self.suggestions = crate::similar::suggest(self.input.text(), &self.suggestion_pool);
+        self.suggestion_index = 0;
+    }
+
+    /// Replaces the input with the next suggestion; repeated calls cycle.
+    pub fn accept_suggestion(&mut self) -> bool {
+        let Some(suggestion) = self.suggestions.get(self.suggestion_index) else {
+            return false;
+        };
+        self.input.set(suggestion.text.clone());
+        self.suggestion_index = (self.suggestion_index + 1) % self.suggestions.len();
+        true
+    }
+
+    fn render_suggestions(&self, f: &mut Frame, popup_area: Rect) {
+        let area = Rect {
+            x: popup_area.x,
+            y: popup_area.y + popup_area.height,
+            width: popup_area.width,
+            height: self.suggestions.len() as u16 + 2,
+        }
+        .intersection(f.area());
+        let lines: Vec<Line> = self
+            .suggestions
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let style = if i == self.suggestion_index { Style::default().fg(Color::Cyan) } else { Style::default() };
+                Line::from(vec![
+                    Span::styled(s.text.clone(), style),
+                    Span::styled(format!("  {}", s.source), Style::default().fg(Color::DarkGray)),
+                ])
+            })
+            .collect();
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Suggestions (Tab to use)")),
+            area,
+        );
+    }
+
+    pub fn start_storage_config(&mut self, current_config: &AppConfig) {
+        self.input_mode = InputMode::ConfigHome;
+        self.temp_config = current_config.clone();
+        self.config_screen = ConfigScreen::Home;
+        self.reveal_secrets = false;
+        self.config_field_index = 0;
+        self.storage_selection_index = match current_config.storage_type {
+            StorageType::Local => 0,
+            StorageType::MongoDB => 1,
+            StorageType::Git => 2,
+            StorageType::Remote => 3,
+        };
+    }
+
+    // Navigation methods for different config screens
+    pub fn config_home_next(&mut self) {
+        self.config_field_index = (self.config_field_index + 1) % 4; // Current, Configure, Credentials, Save
+    }
+
+    pub fn config_home_prev(&mut self) {
+        self.config_field_index = if self.config_field_index == 0 { 3 } else { self.config_field_index - 1 };
+    }
+
+    pub fn storage_selection_next(&mut self) {
+        self.storage_selection_index = (self.storage_selection_index + 1) % 4; // Local, MongoDB, Git, Remote
+    }
+
+    pub fn storage_selection_prev(&mut self) {
+        self.storage_selection_index = (self.storage_selection_index + 3) % 4;
+    }
+
+
+    pub fn mongodb_config_next(&mut self) {
+        self.config_field_index = (self.config_field_index + 1) % MONGO_FIELDS.len();
+    }
+
+    pub fn mongodb_config_prev(&mut self) {
+        self.config_field_index = (self.config_field_index + MONGO_FIELDS.len() - 1) % MONGO_FIELDS.len();
+    }
+
+    fn mongo_field_value(&self, index: usize) -> String {
+        let mongo = &self.temp_config.mongo_config;
+        let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+        let number = |n: Option<u64>| n.map(|n| n.to_string()).unwrap_or_default();
+        match index {
+            0 => mongo.connection_string.clone(),
+            1 => mongo.database.clone(),
+            2 => mongo.collection.clone(),
+            3 => on_off(mongo.tls),
+            4 => mongo.tls_ca_file.clone(),
+            5 => mongo.tls_cert_key_file.clone(),
+            6 => on_off(mongo.tls_allow_invalid_certificates),
+            7 => mongo.auth_mechanism.clone(),
+            8 => mongo.auth_source.clone(),
+            9 => number(mongo.server_selection_timeout_secs),
+            10 => number(mongo.max_pool_size.map(u64::from)),
+            11 => number(mongo.min_pool_size.map(u64::from)),
+            _ => String::new(),
+        }
+    }
+
+    /// Rejects text a number field can't take; empty means the default.
+    pub fn check_current_field_value(&self, value: &str) -> Result<(), String> {
+        let numeric = self.config_screen == ConfigScreen::MongoDBConfig && self.config_field_index >= 9;
+        if numeric && !value.trim().is_empty() && value.trim().parse::<u32>().is_err() {
+            return Err(format!(
+                "{} must be a whole number; leave it empty for the default",
+                MONGO_FIELDS[self.config_field_index]
+            ));
+        }
+        Ok(())
+    }
+
+    /// Flips the selected MongoDB setting if it is an on/off one.
+    pub fn toggle_mongo_field(&mut self) -> bool {
+        let mongo = &mut self.temp_config.mongo_config;
+        match self.config_field_index {
+            3 => mongo.tls = !mongo.tls,
+            6 => mongo.tls_allow_invalid_certificates = !mongo.tls_allow_invalid_certificates,
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn get_current_field_value(&self) -> String {
+        match self.config_screen {
+            ConfigScreen::LocalConfig => {
+                self.temp_config.local_config.path.clone()
+            }
+            ConfigScreen::MongoDBConfig => self.mongo_field_value(self.config_field_index),
+            ConfigScreen::GitConfig => {
+                match self.config_field_index {
+                    0 => self.temp_config.git_config.branch.clone(),
+                    1 => self.temp_config.git_config.remote.clone(),
+                    _ => String::new(),
+                }
+            }
+            ConfigScreen::RemoteConfig => {
+                match self.config_field_index {
+                    0 => self.temp_config.remote_config.url.clone(),
+                    1 => self.temp_config.remote_config.token.clone(),
+                    _ => String::new(),
+                }
+            }
+            _ => String::new(),
+        }
+    }
+
+    pub fn set_current_field_value(&mut self, value: String) {
+        match self.config_screen {
+            ConfigScreen::LocalConfig => {
+                self.temp_config.local_config.path = value;
+            }
+            ConfigScreen::MongoDBConfig => {
+                let mongo = &mut self.temp_config.mongo_config;
+                match self.config_field_index {
+                    0 => mongo.connection_string = value,
+                    1 => mongo.database = value,
+                    2 => mongo.collection = value,
+                    4 => mongo.tls_ca_file = value.trim().to_string(),
+                    5 => mongo.tls_cert_key_file = value.trim().to_string(),
+                    7 => mongo.auth_mechanism = value.trim().to_string(),
+                    8 => mongo.auth_source = value.trim().to_string(),
+                    9 => mongo.server_selection_timeout_secs = value.trim().parse().ok(),
+                    10 => mongo.max_pool_size = value.trim().parse().ok(),
+                    11 => mongo.min_pool_size = value.trim().parse().ok(),
+                    _ => {}
+                }
+            }
+            ConfigScreen::GitConfig => {
+                match self.config_field_index {
+                    0 => self.temp_config.git_config.branch = value,
+                    1 => self.temp_config.git_config.remote = value,
+                    _ => {}
+                }
+            }
+            ConfigScreen::RemoteConfig => {
+                match self.config_field_index {
+                    0 => self.temp_config.remote_config.url = value,
+                    1 => self.temp_config.remote_config.token = value,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn enter_storage_selection(&mut self) {
+        self.config_screen = ConfigScreen::StorageSelection;
+        self.input_mode = InputMode::ConfigStorageSelection;
+    }
+
+    pub fn enter_local_config(&mut self) {
+        self.config_screen = ConfigScreen::LocalConfig;
+        self.input_mode = InputMode::ConfigLocal;
+        self.config_field_index = 0;
+        self.connection_test = None;
+    }
+
+    pub fn enter_mongodb_config(&mut self) {
+        self.config_screen = ConfigScreen::MongoDBConfig;
+        self.input_mode = InputMode::ConfigMongoDB;
+        self.config_field_index = 0;
+        self.connection_test = None;
+        self.reveal_secrets = false;
+    }
+
+    /// The connection string as shown, its password masked until revealed.
+    fn shown_connection_string(&self) -> String {
+        let uri = &self.temp_config.mongo_config.connection_string;
+        if self.reveal_secrets {
+            uri.clone()
+        } else {
+            secret::mask_uri(uri)
+        }
+    }
+
+    /// The outcome of the last connection test, as shown under the settings.
+    fn connection_test_line(&self) -> Option<Line<'static>> {
+        let line = match self.connection_test.as_ref()? {
+            Ok(_) if self.temp_config.storage_type == StorageType::Local => {
+                Span::styled("✓ Tasks can be written there", Style::default().fg(Color::Green))
+            }
+            Ok(took) => Span::styled(format!("✓ Connected in {} ms", took.as_millis()), Style::default().fg(Color::Green)),
+            Err(error) => Span::styled(format!("✗ {}", error), Style::default().fg(Color::Red)),
+        };
+        Some(Line::from(line))
+    }
+
+    pub fn start_field_edit(&mut self) {
+        match self.config_screen {
+            ConfigScreen::LocalConfig => {
+                self.input_mode = InputMode::ConfigLocalField;
+                self.input.set(self.get_current_field_value());
+            }
+            ConfigScreen::MongoDBConfig => {
+                self.input_mode = InputMode::ConfigMongoDBField;
+                self.input.set(self.get_current_field_value());
+            }
+            _ => {}
+        }
+    }
+
+    pub fn enter_credentials(&mut self) {
+        self.config_screen = ConfigScreen::Credentials;
+        self.input_mode = InputMode::ConfigCredentials;
+        self.config_field_index = 0;
+    }
+
+    pub fn credentials_next(&mut self) {
+        self.config_field_index = (self.config_field_index + 1) % Credential::ALL.len();
+    }
+
+    pub fn credentials_prev(&mut self) {
+        self.config_field_index = (self.config_field_index + Credential::ALL.len() - 1) % Credential::ALL.len();
+    }
+
+    pub fn selected_credential(&self) -> Credential {
+        Credential::ALL[self.config_field_index.min(Credential::ALL.len() - 1)]
+    }
+
+    /// Where each credential's secret comes from, as listed.
+    pub fn credential_rows(&self) -> Vec<String> {
+        Credential::ALL
+            .iter()
+            .map(|credential| {
+                let source = match secret::Source::of(&credential.value(&self.temp_config)) {
+                    secret::Source::Unset => "not set".to_string(),
+                    secret::Source::Plaintext => "plaintext in config.toml".to_string(),
+                    secret::Source::Environment(name) => format!("from ${}", name),
+                    secret::Source::Keychain(name) => format!("in the keychain as \"{}\"", name),
+                };
+                format!("{}: {}", credential.label(), source)
+            })
+            .collect()
+    }
+
+    pub fn back_to_home(&mut self) {
+        self.config_screen = ConfigScreen::Home;
+        self.input_mode = InputMode::ConfigHome;
+        self.config_field_index = 0;
+    }
+
+    pub fn get_config(&self) -> AppConfig {
+        self.temp_config.clone()
+    }
+
+    /// Opens the first-run setup on a copy of `config`.
+    pub fn start_setup(&mut self, config: &AppConfig) {
+        self.start_storage_config(config);
+        self.connection_test = None;
+        self.enter_setup_step(SetupStep::Backend);
+    }
+
+    /// Shows `step` with the row matching the config selected.
+    pub fn enter_setup_step(&mut self, step: SetupStep) {
+        self.setup_step = step;
+        self.input_mode = InputMode::Setup;
+        self.config_field_index = match step {
+            SetupStep::Backend => self.storage_selection_index,
+            SetupStep::Connection => 0,
+            SetupStep::Scope => usize::from(self.temp_config.context_scope == ContextScope::Repo),
+            SetupStep::Theme => THEMES.iter().position(|theme| *theme == self.temp_config.theme).unwrap_or(0),
+        };
+        if step == SetupStep::Connection {
+            self.config_screen = match self.temp_config.storage_type {
+                StorageType::Local => ConfigScreen::LocalConfig,
+                StorageType::MongoDB => ConfigScreen::MongoDBConfig,
+                StorageType::Git => ConfigScreen::GitConfig,
+                StorageType::Remote => ConfigScreen::RemoteConfig,
+            };
+        }
+    }
+
+    /// The settings of the chosen backend, as (label, value).
+    pub fn connection_fields(&self) -> Vec<(&'static str, String)> {
+        let config = &self.temp_config;
+        match config.storage_type {
+            StorageType::Local => vec![("Path", config.local_config.path.clone())],
+            StorageType::MongoDB => vec![
+                ("Connection URL", self.shown_connection_string()),
+                ("Database", config.mongo_config.database.clone()),
+                ("Collection", config.mongo_config.collection.clone()),
+            ],
+            StorageType::Git => vec![("Branch", config.git_config.branch.clone()), ("Remote", config.git_config.remote.clone())],
+            StorageType::Remote => vec![
+                ("Server URL", config.remote_config.url.clone()),
+                ("Token", match config.remote_config.token.as_str() {
+                    "" => "(QUILL_REMOTE_TOKEN)".to_string(),
+                    token if self.reveal_secrets => token.to_string(),
+                    _ => secret::MASK.to_string(),
+                }),
+            ],
+        }
+    }
+
+    /// Rows of the current setup screen.
+    pub fn setup_rows(&self) -> Vec<String> {
+        match self.setup_step {
+            SetupStep::Backend => self.storage_labels().to_vec(),
+            SetupStep::Connection => self
+                .connection_fields()
+                .into_iter()
+                .map(|(label, value)| format!("{}: {}", label, value))
+                .chain(["Test connection".to_string(), "Next".to_string()])
+                .collect(),
+            SetupStep::Scope => vec![
+                "One list per branch (org:repo:branch)".to_string(),
+                "One list per repository, shared by its branches (org:repo:*)".to_string(),
+            ],
+            SetupStep::Theme => vec![
+                "Dark: light text, for dark terminal backgrounds".to_string(),
+                "Light: dark text, for light terminal backgrounds".to_string(),
+                "Mono: no colors".to_string(),
+            ],
+        }
+    }
+
+    pub fn setup_next(&mut self) {
+        self.config_field_index = (self.config_field_index + 1) % self.setup_rows().len();
+    }
+
+    pub fn setup_prev(&mut self) {
+        let rows = self.setup_rows().len();
+        self.config_field_index = (self.config_field_index + rows - 1) % rows;
+    }
+
+    pub fn open_inspector(&mut self, text: String) {
+        self.input_mode = InputMode::Inspector;
+        self.inspector_text = text;
+        self.inspector_scroll = 0;
+    }
+
+    pub fn open_backend_picker(&mut self, default: StorageType) {
+        let mut choices = vec![default.clone()];
+        choices.extend(StorageType::ALL.into_iter().filter(|backend| *backend != default));
+        self.input_mode = InputMode::BackendPicker;
+        self.backend_choices = choices;
+        self.backend_state.select(Some(0));
+    }
+
+    pub fn backend_picker_next(&mut self) {
+        if let Some(selected) = self.backend_state.selected() {
+            self.backend_state.select(Some((selected + 1).min(self.backend_choices.len() - 1)));
+        }
+    }
+
+    pub fn backend_picker_prev(&mut self) {
+        if let Some(selected) = self.backend_state.selected() {
+            self.backend_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn selected_backend(&self) -> Option<StorageType> {
+        self.backend_state.selected().and_then(|i| self.backend_choices.get(i)).cloned()
+    }
+
+    pub fn open_storage_recovery(&mut self, failure: StorageFailure) {
+        self.input_mode = InputMode::StorageRecovery;
+        self.storage_failure = Some(failure);
+    }
+
+    pub fn open_health(&mut self, health: BackendHealth) {
+        self.input_mode = InputMode::Health;
+        self.health = Some(health);
+    }
+
+    pub fn inspector_scroll_by(&mut self, delta: i32) {
+        let max = self.inspector_text.lines().count().saturating_sub(1) as i32;
+        self.inspector_scroll = (self.inspector_scroll as i32 + delta).clamp(0, max) as u16;
+    }
+
+    /// Opens the stats view with contexts ordered from most to fewest open tasks.
+    pub fn open_stats(&mut self, mut stats: Vec<ContextStats>) {
+        stats.sort_by(|a, b| b.open.cmp(&a.open).then_with(|| a.context_key.cmp(&b.context_key)));
+        self.input_mode = InputMode::Stats;
+        self.stats_state.select(if stats.is_empty() { None } else { Some(0) });
+        self.stats = stats;
+    }
+
+    pub fn open_overview(&mut self, all: std::collections::BTreeMap<String, Vec<Task>>) {
+        self.overview.load(all);
+        self.input_mode = InputMode::Overview;
+        self.overview_state.select(if self.overview.contexts() == 0 { None } else { Some(0) });
+    }
+
+    /// The overview row under the selection.
+    pub fn overview_selected(&self) -> Option<Row> {
+        self.overview_state.selected().and_then(|i| self.overview.rows().get(i).cloned())
+    }
+
+    pub fn overview_next(&mut self) {
+        if let Some(selected) = self.overview_state.selected() {
+            let last = self.overview.rows().len().saturating_sub(1);
+            self.overview_state.select(Some((selected + 1).min(last)));
+        }
+    }
+
+    pub fn overview_prev(&mut self) {
+        if let Some(selected) = self.overview_state.selected() {
+            self.overview_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn start_search(&mut self) {
+        self.input_mode = InputMode::Search;
+        self.input.clear();
+        self.set_search_results(BTreeMap::new());
+    }
+
+    pub fn set_search_results(&mut self, found: BTreeMap<String, Vec<Task>>) {
+        self.search_results = found
+            .into_iter()
+            .flat_map(|(context_key, tasks)| tasks.into_iter().map(move |task| (context_key.clone(), task)))
+            .collect();
+        self.search_state.select(if self.search_results.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn search_next(&mut self) {
+        if let Some(selected) = self.search_state.selected() {
+            self.search_state.select(Some((selected + 1).min(self.search_results.len() - 1)));
+        }
+    }
+
+    pub fn search_prev(&mut self) {
+        if let Some(selected) = self.search_state.selected() {
+            self.search_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn open_cleanup(&mut self, candidates: Vec<Candidate>) {
+        self.input_mode = InputMode::Cleanup;
+        self.cleanup_state.select(if candidates.is_empty() { None } else { Some(0) });
+        self.cleanup = candidates.into_iter().map(|candidate| (candidate, true)).collect();
+        self.cleanup_preview = None;
+    }
+
+    /// The task lists picked in the cleanup wizard.
+    pub fn cleanup_picked(&self) -> Vec<Candidate> {
+        self.cleanup.iter().filter(|(_, picked)| *picked).map(|(candidate, _)| candidate.clone()).collect()
+    }
+
+    pub fn cleanup_next(&mut self) {
+        if let Some(selected) = self.cleanup_state.selected() {
+            self.cleanup_state.select(Some((selected + 1).min(self.cleanup.len() - 1)));
+        }
+    }
+
+    pub fn cleanup_prev(&mut self) {
+        if let Some(selected) = self.cleanup_state.selected() {
+            self.cleanup_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn open_diff(&mut self, diff: ContextDiff) {
+        self.input_mode = InputMode::Diff;
+        self.diff = Some(diff);
+        self.diff_scroll = 0;
+    }
+
+    pub fn diff_scroll_by(&mut self, delta: i32) {
+        let max = self.diff.as_ref().map_or(0, |d| d.lines().len().saturating_sub(1)) as i32;
+        self.diff_scroll = (self.diff_scroll as i32 + delta).clamp(0, max) as u16;
+    }
+
+    pub fn open_palette(&mut self, items: Vec<PaletteItem>) {
+        self.input_mode = InputMode::Palette;
+        self.input.clear();
+        self.palette = items;
+        self.refresh_palette();
+    }
+
+    /// Re-ranks the palette against the typed query and selects the best match.
+    pub fn refresh_palette(&mut self) {
+        self.palette_matches = palette::rank(self.input.text(), &self.palette);
+        self.palette_state.select(if self.palette_matches.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn palette_next(&mut self) {
+        if let Some(selected) = self.palette_state.selected() {
+            self.palette_state.select(Some((selected + 1).min(self.palette_matches.len() - 1)));
+        }
+    }
+
+    pub fn palette_prev(&mut self) {
+        if let Some(selected) = self.palette_state.selected() {
+            self.palette_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    /// Index into `palette` of the highlighted entry.
+    pub fn selected_palette_item(&self) -> Option<usize> {
+        self.palette_state.selected().and_then(|i| self.palette_matches.get(i).copied())
+    }
+
+    /// Opens (or refreshes) the trash view, keeping the selection in range.
+    pub fn open_trash(&mut self, trash: Vec<DeletedTask>) {
+        self.input_mode = InputMode::Trash;
+        let selected = self.trash_state.selected().unwrap_or(0);
+        self.trash_state.select(if trash.is_empty() { None } else { Some(selected.min(trash.len() - 1)) });
+        self.trash = trash;
+    }
+
+    pub fn open_activity(&mut self, activity: Vec<ActivityEntry>) {
+        self.input_mode = InputMode::Activity;
+        self.activity_state.select(if activity.is_empty() { None } else { Some(0) });
+        self.activity = activity;
+    }
+
+    pub fn activity_next(&mut self) {
+        if let Some(selected) = self.activity_state.selected() {
+            self.activity_state.select(Some((selected + 1).min(self.activity.len() - 1)));
+        }
+    }
+
+    pub fn activity_prev(&mut self) {
+        if let Some(selected) = self.activity_state.selected() {
+            self.activity_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn open_notes(&mut self, task: &Task) {
+        self.input_mode = InputMode::Notes;
+        self.notes_links = markdown::links(&task.text);
+        self.notes_links.extend(markdown::links(&task.notes));
+        self.notes_view = Some((task.text.clone(), task.notes.clone()));
+        self.notes_scroll = 0;
+    }
+
+    /// Shows the attachments of `task`, keeping the selection when reopened.
+    pub fn open_attachments(&mut self, task: &Task) {
+        self.input_mode = InputMode::Attachments;
+        let selected = self.attachments_state.selected().filter(|_| self.attachments_task == Some(task.id)).unwrap_or(0);
+        self.attachments_state
+            .select(if task.attachments.is_empty() { None } else { Some(selected.min(task.attachments.len() - 1)) });
+        self.attachments = task.attachments.clone();
+        self.attachments_task = Some(task.id);
+    }
+
+    pub fn start_attachment(&mut self) {
+        self.input_mode = InputMode::AddingAttachment;
+        self.input.clear();
+    }
+
+    pub fn attachments_next(&mut self) {
+        if let Some(selected) = self.attachments_state.selected() {
+            self.attachments_state.select(Some((selected + 1).min(self.attachments.len() - 1)));
+        }
+    }
+
+    pub fn attachments_prev(&mut self) {
+        if let Some(selected) = self.attachments_state.selected() {
+            self.attachments_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn trash_next(&mut self) {
+        if let Some(selected) = self.trash_state.selected() {
+            self.trash_state.select(Some((selected + 1).min(self.trash.len() - 1)));
+        }
+    }
+
+    pub fn trash_prev(&mut self) {
+        if let Some(selected) = self.trash_state.selected() {
+            self.trash_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn selected_trash(&self) -> Option<&DeletedTask> {
+        self.trash_state.selected().and_then(|i| self.trash.get(i))
+    }
+
+    /// Opens (or refreshes) the sync log for the selected integration.
+    pub fn open_sync_log(&mut self, entries: Vec<SyncLogEntry>, paused: bool) {
+        self.input_mode = InputMode::SyncLog;
+        self.sync_state.select(if entries.is_empty() { None } else { Some(0) });
+        self.sync_entries = entries;
+        self.sync_paused = paused;
+    }
+
+    pub fn sync_scroll(&mut self, delta: i32) {
+        if let Some(selected) = self.sync_state.selected() {
+            let next = (selected as i32 + delta).clamp(0, self.sync_entries.len() as i32 - 1);
+            self.sync_state.select(Some(next as usize));
+        }
+    }
+
+    pub fn selected_integration(&self) -> &'static str {
+        INTEGRATIONS[self.sync_integration % INTEGRATIONS.len()]
+    }
+
+    pub fn stats_next(&mut self) {
+        if let Some(selected) = self.stats_state.selected() {
+            self.stats_state.select(Some((selected + 1).min(self.stats.len() - 1)));
+        }
+    }
+
+    pub fn stats_prev(&mut self) {
+        if let Some(selected) = self.stats_state.selected() {
+            self.stats_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    pub fn open_view_picker(&mut self) {
+        self.input_mode = InputMode::ViewPicker;
+        let active = self.active_view.as_ref().and_then(|name| self.views.iter().position(|v| &v.name == name));
+        self.view_picker_state.select(Some(active.map_or(0, |i| i + 1)));
+    }
+
+    pub fn view_picker_next(&mut self) {
+        if let Some(selected) = self.view_picker_state.selected() {
+            self.view_picker_state.select(Some((selected + 1).min(self.views.len())));
+        }
+    }
+
+    pub fn view_picker_prev(&mut self) {
+        if let Some(selected) = self.view_picker_state.selected() {
+            self.view_picker_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    /// The view under the picker's selection; `None` on "All tasks".
+    pub fn picked_view(&self) -> Option<&View> {
+        self.view_picker_state.selected().and_then(|i| i.checked_sub(1)).and_then(|i| self.views.get(i))
+    }
+
+    pub fn start_adding_view(&mut self) {
+        self.input_mode = InputMode::AddingView;
+        self.input.clear();
+    }
+
+    /// The filter of the active view, unless its expression doesn't parse.
+    pub fn active_filter(&self) -> Option<&Filter> {
+        let name = self.active_view.as_ref()?;
+        self.views.iter().find(|v| &v.name == name)?.filter.as_ref().ok()
+    }
+
+    pub fn open_template_picker(&mut self, templates: Vec<TaskTemplate>) {
+        self.input_mode = InputMode::TemplatePicker;
+        self.template_state.select(if templates.is_empty() { None } else { Some(0) });
+        self.templates = templates;
+    }
+
+    pub fn template_next(&mut self) {
+        if let Some(selected) = self.template_state.selected() {
+            self.template_state.select(Some((selected + 1) % self.templates.len()));
+        }
+    }
+
+    pub fn template_prev(&mut self) {
+        if let Some(selected) = self.template_state.selected() {
+            let len = self.templates.len();
+            self.template_state.select(Some((selected + len - 1) % len));
+        }
+    }
+
+    pub fn open_import_picker(&mut self, summaries: Vec<String>) {
+        self.input_mode = InputMode::ImportPicker;
+        self.import_state.select(if summaries.is_empty() { None } else { Some(0) });
+        self.import_sources = summaries.into_iter().map(|summary| (summary, true)).collect();
+    }
+
+    pub fn import_next(&mut self) {
+        if let Some(selected) = self.import_state.selected() {
+            self.import_state.select(Some((selected + 1) % self.import_sources.len()));
+        }
+    }
+
+    pub fn import_prev(&mut self) {
+        if let Some(selected) = self.import_state.selected() {
+            let len = self.import_sources.len();
+            self.import_state.select(Some((selected + len - 1) % len));
+        }
+    }
+
+    pub fn toggle_import_source(&mut self) {
+        if let Some(source) = self.import_state.selected().and_then(|i| self.import_sources.get_mut(i)) {
+            source.1 = !source.1;
+        }
+    }
+
+    /// Opens the assign picker on `people`, selecting `current`.
+    pub fn open_assignee_picker(&mut self, people: Vec<Option<String>>, current: Option<&str>) {
+        self.input_mode = InputMode::AssigneePicker;
+        let selected = people.iter().position(|p| p.as_deref() == current).unwrap_or(0);
+        self.assignee_state.select(Some(selected));
+        self.assignees = people;
+    }
+
+    pub fn assignee_next(&mut self) {
+        if let Some(selected) = self.assignee_state.selected() {
+            self.assignee_state.select(Some((selected + 1) % self.assignees.len()));
+        }
+    }
+
+    pub fn assignee_prev(&mut self) {
+        if let Some(selected) = self.assignee_state.selected() {
+            let len = self.assignees.len();
+            self.assignee_state.select(Some((selected + len - 1) % len));
+        }
+    }
+
+    pub fn selected_assignee(&self) -> Option<Option<String>> {
+        self.assignee_state.selected().and_then(|i| self.assignees.get(i)).cloned()
+    }
+
+    pub fn selected_template(&self) -> Option<&TaskTemplate> {
+        self.template_state.selected().and_then(|i| self.templates.get(i))
+    }
+
+    pub fn show_notification(&mut self, message: String, level: NotificationLevel) {
+        self.notification = Some(Notification {
+            message,
+            level,
+            created_at: Instant::now(),
+            duration: Duration::from_secs(5),
+        });
+    }
+
+    pub fn clear_expired_notification(&mut self) {
+        if let Some(ref notification) = self.notification {
+            if notification.created_at.elapsed() >= notification.duration {
+                self.notification = None;
+            }
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, tasks: &[Task], context: &str) {
+        // Clear expired notifications
+        self.clear_expired_notification();
+        
+        let open_count = tasks.iter().filter(|t| !t.is_completed()).count();
+        let over_limit = open_count > self.open_task_soft_limit;
+
+        let mut constraints = vec![Constraint::Length(3)];
+        if over_limit {
+            constraints.push(Constraint::Length(1));
+        }
+        constraints.push(Constraint::Min(0));
+        constraints.push(Constraint::Length(3));
+
+        let mut chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(f.area())
+            .to_vec();
+
+        if over_limit {
+            let banner = Paragraph::new(format!(
+                "{} open tasks here (soft limit {}). Consider completing, archiving, or splitting this list - press 's' for stats.",
+                open_count, self.open_task_soft_limit
+            ))
+            .style(Style::default().fg(Color::Black).bg(Color::Yellow))
+            .alignment(Alignment::Center);
+            f.render_widget(banner, chunks.remove(1));
+        }
+
+        // Header
+        let mut header_text = if self.status_filter.is_active() {
+            format!("Quill Task - {} [{}]", context, self.status_filter.label())
+        } else {
+            format!("Quill Task - {}", context)
+        };
+        if let Some(link) = &self.issue_link {
+            header_text.push_str(&format!(" ↗ #{}", link.number));
+        }
+        if let Some(key) = &self.jira_issue {
+            header_text.push_str(&format!(" ↗ {}", key));
+        }
+        if self.watched {
+            header_text.insert_str(0, "★ ");
+        }
+        if self.propagate_to_subtasks {
+            header_text.push_str(" ⇣");
+        }
+        if self.assignee_filter != AssigneeFilter::Everyone {
+            header_text.push_str(&format!(" [{}]", self.assignee_filter.label()));
+        }
+        if let Some(rollup) = &self.rollup {
+            header_text.push_str(&format!(
+                "  {} open here · {} open in repo · {} open across org",
+                rollup.here, rollup.repo, rollup.org
+            ));
+        }
+        if self.watch_badge > 0 {
+            header_text.push_str(&format!("  ★ {} new (W)", self.watch_badge));
+        }
+        if let Some(backend) = &self.peeking {
+            header_text.push_str(&format!("  👁 {} read-only (B to return)", backend));
+        }
+        // Each repository gets its own color, so terminals are easy to tell apart
+        let header_color = if self.peeking.is_some() { Color::Yellow } else { accent::for_context(context) };
+        let header_block = Block::default().borders(Borders::ALL).style(Style::default().fg(header_color));
+        let mut header_area = header_block.inner(chunks[0]);
+        f.render_widget(header_block, chunks[0]);
+        let (done, total) = self.progress;
+        if total > 0 && header_area.width >= 80 {
+            let split = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(26)])
+                .split(header_area);
+            header_area = split[0];
+            let gauge = Gauge::default()
+                .gauge_style(Style::default().fg(Color::Green).bg(Color::DarkGray))
+                .ratio(done as f64 / total as f64)
+                .label(Span::styled(
+                    format!("{}/{} done, {}%", done, total, overview::percent(done, total)),
+                    Style::default().fg(Color::White),
+                ));
+            f.render_widget(gauge, split[1]);
+        }
+        let header = Paragraph::new(header_text).alignment(Alignment::Center);
+        f.render_widget(header, header_area);
+
+        // Task List
+        let today = chrono::Local::now().date_naive();
+        let depths = subtasks::depths(tasks);
+        let rollups = subtasks::rollups(tasks);
+        // The details pane takes the right of the list area on wide enough terminals
+        let (list_area, details_area) = if self.show_details && f.area().width >= self.details_pane.min_columns {
+            let width = self.details_pane.width.clamp(10, 90);
+            let split = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(100 - width), Constraint::Percentage(width)])
+                .split(chunks[1]);
+            (split[0], Some(split[1]))
+        } else {
+            (chunks[1], None)
+        };
+        let row_width = list_area.width.saturating_sub(2 + HIGHLIGHT_WIDTH) as usize;
+        let selected = self.list_state.selected();
+        let mut full_title = None;
+        let items: Vec<ListItem> = tasks
+            .iter()
+            .enumerate()
+            .map(|(index, task)| {
+                let (symbol, style) = match task.status {
+                    TaskStatus::NotStarted => ("○", Style::default().fg(Color::Yellow)),
+                    TaskStatus::InProgress => ("◐", Style::default().fg(Color::Blue)),
+                    TaskStatus::Completed => ("✓", Style::default().fg(Color::Green).add_modifier(Modifier::CROSSED_OUT)),
+                };
+
+                let mut spans = Vec::new();
+                if self.marked.contains(&task.id) {
+                    spans.push(Span::styled("■ ", Style::default().fg(Color::Cyan)));
+                }
+                spans.push(Span::styled(format!("{} ", symbol), style));
+                // Indented after the symbol so it stays clickable at a fixed column
+                let depth = depths.get(&task.id).copied().unwrap_or(0);
+                if depth > 0 {
+                    spans.push(Span::styled(format!("{}└ ", "  ".repeat(depth - 1)), Style::default().fg(Color::DarkGray)));
+                }
+                if task.pinned {
+                    spans.push(Span::styled("★ ", Style::default().fg(Color::LightYellow)));
+                }
+                if task.must_finish {
+                    spans.push(Span::styled("⚑ ", Style::default().fg(Color::Red)));
+                }
+                // Parents show their subtasks' priority and due date, dimmed, when those are more pressing
+                let rollup = rollups.get(&task.id).filter(|_| !task.is_completed());
+                let derived = |own: Style| if rollup.is_some_and(|r| r.priority > task.priority) { Style::default().fg(Color::DarkGray) } else { own };
+                match rollup.map_or(task.priority, |r| r.priority.max(task.priority)) {
+                    Priority::High => spans.push(Span::styled("!!! ", derived(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)))),
+                    Priority::Medium => spans.push(Span::styled("!! ", derived(Style::default().fg(Color::Yellow)))),
+                    Priority::Low => spans.push(Span::styled("! ", derived(Style::default().fg(Color::Blue)))),
+                    Priority::None => {}
+                }
+                spans.extend(markdown::spans(&task.text, style, 1).0);
+                if let Some(key) = &task.issue {
+                    spans.push(Span::styled(format!(" [{}]", key), Style::default().fg(Color::Blue)));
+                }
+                for tag in &task.tags {
+                    spans.push(Span::styled(format!(" #{}", tag), Style::default().fg(Color::Magenta)));
+                }
+                if let Some(assignee) = &task.assignee {
+                    let name = assignee.split('@').next().unwrap_or(assignee);
+                    spans.push(Span::styled(format!(" @{}", name), Style::default().fg(Color::Green)));
+                }
+                if let Some(decoration) = self.decorations.get(&task.id) {
+                    spans.push(Span::styled(format!(" {}", decoration), Style::default().fg(Color::Cyan)));
+                }
+                if let Some(due) = task.due {
+                    let due_style = if task.is_overdue(today) {
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(Color::Gray)
+                    };
+                    spans.push(Span::styled(format!(" due {}", due.format("%Y-%m-%d")), due_style));
+                }
+                if let Some(due) = rollup.and_then(|r| r.due).filter(|d| task.due.is_none_or(|own| d < &own)) {
+                    spans.push(Span::styled(format!(" subtask due {}", due.format("%Y-%m-%d")), Style::default().fg(Color::DarkGray)));
+                }
+                if let Some(until) = task.snoozed_until.as_deref().and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok()) {
+                    let until = until.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+                    spans.push(Span::styled(format!(" 💤 until {}", until), Style::default().fg(Color::DarkGray)));
+                }
+                if let Some(anchor) = &task.anchor {
+                    spans.push(Span::styled(format!(" → {}", anchor), Style::default().fg(Color::DarkGray)));
+                }
+                if !task.notes.trim().is_empty() {
+                    spans.push(Span::styled(" ✎", Style::default().fg(Color::Gray)));
+                }
+                if !task.attachments.is_empty() {
+                    spans.push(Span::styled(format!(" ⎘{}", task.attachments.len()), Style::default().fg(Color::Gray)));
+                }
+
+                let (lines, truncated) = fit_row(spans, row_width, self.long_titles);
+                if truncated && selected == Some(index) {
+                    full_title = Some(task.text.clone());
+                }
+                ListItem::new(lines)
+            })
+            .collect();
+        self.row_heights = items.iter().map(|item| item.height() as u16).collect();
+
+        let title = match self.list_state.selected() {
+            Some(selected) if !tasks.is_empty() => format!("Tasks [{}/{}]", selected + 1, tasks.len()),
+            _ => format!("Tasks [{}]", tasks.len()),
+        };
+        let title = match self.sort_order {
+            SortOrder::Manual => title,
+            order => format!("{} by {}", title, order.label()),
+        };
+        let title = if self.pending_keys.is_empty() { title } else { format!("{}  {}", title, self.pending_keys) };
+        let mut block = Block::default().borders(Borders::ALL).title(title);
+        if let Some(full_title) = full_title {
+            block = block.title_bottom(Line::styled(format!(" {} ", full_title), Style::default().fg(Color::Gray)));
+        }
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray))
+            .highlight_symbol("➤ ")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        self.list_area = list_area;
+        f.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        // Scrollbar on the list's right border, only when the list overflows
+        if tasks.len() > self.page_size() {
+            let mut scrollbar_state = ScrollbarState::new(tasks.len().saturating_sub(self.page_size()))
+                .viewport_content_length(self.page_size())
+                .position(self.list_state.offset());
+            let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(Some("▲"))
+                .end_symbol(Some("▼"));
+            f.render_stateful_widget(
+                scrollbar,
+                list_area.inner(ratatui::layout::Margin { vertical: 1, horizontal: 0 }),
+                &mut scrollbar_state,
+            );
+        }
+        if let Some(area) = details_area {
+            let selected = self.list_state.selected().and_then(|index| tasks.get(index));
+            self.render_details(f, area, selected);
+        }
+
+        self.render_footer(f, chunks[2], tasks, context);
+
+        // Floating input box
+        match self.input_mode {
+            InputMode::Adding | InputMode::Editing | InputMode::ConfigLocalField | InputMode::ConfigMongoDBField | InputMode::ConfigCredentialField | InputMode::SetupField | InputMode::ShiftingDue | InputMode::Snoozing | InputMode::AddingView | InputMode::SettingAnchor | InputMode::AddingAttachment | InputMode::Deferring | InputMode::Triaging | InputMode::Confirming => {
+                let popup_area = self.centered_rect(60, 20, f.area());
+                f.render_widget(Clear, popup_area);
+                
+                let dynamic_title;
+                let title = match self.input_mode {
+                    InputMode::Adding if self.adding_parent.is_some() => "Add Subtask",
+                    InputMode::Adding if self.input_notes.is_some() => "Add New Task (+ notes)",
+                    InputMode::Adding => "Add New Task",
+                    InputMode::Editing if self.input_notes.is_some() => "Edit Task (+ notes)",
+                    InputMode::Editing => "Edit Task",
+                    InputMode::ConfigLocalField => "Edit Local Path",
+                    InputMode::ConfigMongoDBField => "Edit MongoDB Field",
+                    InputMode::ConfigCredentialField => {
+                        let credential = self.selected_credential();
+                        dynamic_title = if secret::Source::of(&credential.value(&self.temp_config)) == secret::Source::Plaintext {
+                            format!("{} to store in the keychain (empty moves the current one)", credential.label())
+                        } else {
+                            format!("{} to store in the keychain", credential.label())
+                        };
+                        &dynamic_title
+                    }
+                    InputMode::SetupField => {
+                        dynamic_title = format!("Edit {}", self.connection_fields().get(self.config_field_index).map_or("", |(label, _)| label));
+                        &dynamic_title
+                    }
+                    InputMode::SettingAnchor => "File anchor: path[:line] relative to the repository root, empty clears",
+                    InputMode::AddingView => "New view: name = expression, e.g. urgent = priority:high and open",
+                    InputMode::AddingAttachment => "Attach: path[:line] relative to the repository root, or a URL",
+                    InputMode::ShiftingDue => {
+                        dynamic_title = format!(
+                            "Due date for {} task(s): +1d, -1w, +1m, 2024-07-01, empty clears",
+                            self.marked.len().max(1)
+                        );
+                        &dynamic_title
+                    }
+                    InputMode::Snoozing => {
+                        dynamic_title = format!(
+                            "Snooze {} task(s) until: 2h, 45min, tomorrow, fri 14:00, 2024-07-01 08:30, empty wakes",
+                            self.marked.len().max(1)
+                        );
+                        &dynamic_title
+                    }
+                    InputMode::Deferring => {
+                        dynamic_title = format!("Defer {} task(s) to branch, e.g. feature/cleanup", self.marked.len().max(1));
+                        &dynamic_title
+                    }
+                    InputMode::Triaging => {
+                        dynamic_title = format!("Move {} task(s) to context org:repo:branch", self.marked.len().max(1));
+                        &dynamic_title
+                    }
+                    InputMode::Confirming => {
+                        dynamic_title = format!("Type \"{}\" to confirm, Esc cancels", self.confirm_phrase);
+                        &dynamic_title
+                    }
+                    _ => "",
+                };
+                
+                let color = if self.input_mode == InputMode::Confirming { Color::Red } else { Color::Cyan };
+                let input_block = Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(color));
+                
+                // Secrets typed for the keychain are never shown
+                let text = match self.input_mode {
+                    InputMode::ConfigCredentialField => "•".repeat(self.input.text().chars().count()),
+                    _ => self.input.text().to_string(),
+                };
+                let input_paragraph = Paragraph::new(text)
+                    .block(input_block)
+                    .wrap(Wrap { trim: false });
+                
+                f.render_widget(input_paragraph, popup_area);
+
+                // Live preview of quick-add metadata below the input line
+                if self.input_mode == InputMode::Adding && popup_area.height > 3 {
+                    let parsed = QuickAdd::parse(self.input.text(), chrono::Local::now().date_naive(), &self.date_parser);
+                    let (preview, preview_color) = if let Some((_, text)) = &self.similar_task {
+                        (format!("⚠ Similar task exists: \"{}\" - Enter: add anyway, Tab: go to it", text), Color::Yellow)
+                    } else if parsed.has_metadata() {
+                        (parsed.preview(), Color::Gray)
+                    } else {
+                        ("Tip: !high #tag @tomorrow, Ctrl+e: write in $EDITOR".to_string(), Color::Gray)
+                    };
+                    let preview_area = Rect {
+                        x: popup_area.x + 1,
+                        y: popup_area.y + popup_area.height - 2,
+                        width: popup_area.width.saturating_sub(2),
+                        height: 1,
+                    };
+                    f.render_widget(Paragraph::new(preview).style(Style::default().fg(preview_color)), preview_area);
+                }
+                if self.input_mode == InputMode::Adding && !self.suggestions.is_empty() {
+                    self.render_suggestions(f, popup_area);
+                }
+                
+                // Show cursor
+                f.set_cursor_position((
+                    popup_area.x + self.input.cursor() as u16 + 1,
+                    popup_area.y + 1,
+                ));
+            }
+            InputMode::ConfigHome => {
+                self.render_config_home(f);
+            }
+            InputMode::ConfigStorageSelection => {
+                self.render_storage_selection(f);
+            }
+            InputMode::ConfigLocal => {
+                self.render_local_config(f);
+            }
+            InputMode::ConfigMongoDB => {
+                self.render_mongodb_config(f);
+            }
+            InputMode::ConfigCredentials => {
+                self.render_credentials(f);
+            }
+            InputMode::Setup => {
+                self.render_setup(f);
+            }
+            InputMode::Inspector => {
+                self.render_inspector(f);
+            }
+            InputMode::SyncLog => {
+                self.render_sync_log(f);
+            }
+            InputMode::Trash => {
+                self.render_trash(f);
+            }
+            InputMode::Activity => {
+                self.render_activity(f);
+            }
+            InputMode::Notes => {
+                self.render_notes(f);
+            }
+            InputMode::Attachments => {
+                self.render_attachments(f);
+            }
+            InputMode::Palette => {
+                self.render_palette(f);
+            }
+            InputMode::Diff => {
+                self.render_diff(f);
+            }
+            InputMode::Stats => {
+                self.render_stats(f);
+            }
+            InputMode::Search => {
+                self.render_search(f);
+            }
+            InputMode::Overview => {
+                self.render_overview(f);
+            }
+            InputMode::Cleanup => {
+                self.render_cleanup(f);
+            }
+            InputMode::Health => {
+                self.render_health(f);
+            }
+            InputMode::StorageRecovery => {
+                self.render_storage_recovery(f);
+            }
+            InputMode::StorageConflict => {
+                self.render_storage_conflict(f);
+            }
+            InputMode::BackendPicker => {
+                self.render_backend_picker(f);
+            }
+            InputMode::ViewPicker => {
+                self.render_view_picker(f);
+            }
+            InputMode::TemplatePicker => {
+                self.render_template_picker(f);
+            }
+            InputMode::ImportPicker => {
+                self.render_import_picker(f);
+            }
+            InputMode::AssigneePicker => {
+                self.render_assignee_picker(f);
+            }
+            _ => {}
+        }
+
+        if let Some(lines) = &self.debug_log {
+            self.render_debug_log(f, lines);
+        }
+
+        // Render notification if present
+        if let Some(ref notification) = self.notification {
+            self.render_notification(f, notification);
+        }
+        theme::apply(self.theme, f.buffer_mut());
+    }
+
+    fn render_footer(&mut self, f: &mut Frame, area: Rect, tasks: &[Task], context: &str) {
+        let count = |status: TaskStatus| tasks.iter().filter(|t| t.status == status).count();
+        let mut filters = Vec::new();
+        if self.status_filter.is_active() {
+            filters.push(self.status_filter.label());
+        }
+        if self.assignee_filter != AssigneeFilter::Everyone {
+            filters.push(self.assignee_filter.label().to_string());
+        }
+        if let Some(view) = &self.active_view {
+            filters.push(format!("view {}", view));
+        }
+        let info = StatusInfo {
+            context,
+            counts: [count(TaskStatus::NotStarted), count(TaskStatus::InProgress), count(TaskStatus::Completed)],
+            filters,
+            sort: Some(self.sort_order).filter(|order| *order != SortOrder::Manual).map(|order| order.label()),
+            backend: self.peeking.as_deref().unwrap_or(&self.backend),
+            connection: if self.peeking.is_some() {
+                Connection::ReadOnly
+            } else if self.storage_failure.is_some() {
+                Connection::Down
+            } else {
+                Connection::Connected
+            },
+            save: self.save_indicator.as_ref(),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(statusline::line(&info, &self.status_line.left))
+            .title(statusline::line(&info, &self.status_line.right).right_aligned());
+        let inner = block.inner(area);
+
+        let mut spans = Vec::new();
+        self.footer_buttons.clear();
+        let mut x = inner.x;
+        for (key, label) in FOOTER_BUTTONS {
+            let key_label = if key == ' ' { "Space".to_string() } else { key.to_string() };
+            let width = (key_label.chars().count() + label.chars().count() + 3) as u16;
+            if x + width > inner.x + inner.width {
+                break;
+            }
+
+            spans.push(Span::styled(format!(" {}", key_label), Style::default().fg(Color::Black).bg(Color::Cyan)));
+            spans.push(Span::styled(format!(" {} ", label), Style::default().fg(Color::Cyan)));
+            self.footer_buttons.push((Rect { x, y: inner.y, width, height: 1 }, key));
+            x += width + 1;
+            spans.push(Span::raw(" "));
+        }
+
+        f.render_widget(Paragraph::new(Line::from(spans)).block(block), area);
+    }
+
+    fn centered_rect(&self, percent_x: u16, percent_y: u16, r: ratatui::layout::Rect) -> ratatui::layout::Rect {
+        let popup_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ])
+            .split(r);
+
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ])
+            .split(popup_layout[1])[1]
+    }
+
+    fn render_config_home(&self, f: &mut Frame) {
+        let popup_area = self.centered_rect(70, 50, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let home_block = Block::default()
+            .title("Storage Configuration")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let current_storage = self.temp_config.storage_type.label();
+
+        let options = [
+            format!("Current Storage: {}", current_storage),
+            "Configure Storage".to_string(),
+            "Credentials".to_string(),
+            "Save & Exit".to_string(),
+        ];
+
+        let items: Vec<ListItem> = options
+            .iter()
+            .enumerate()
+            .map(|(i, option)| {
+                let style = if i == self.config_field_index {
+                    Style::default().bg(Color::DarkGray).fg(Color::White)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(option.as_str()).style(style)
+            })
+            .collect();
+
+        let home_list = List::new(items)
+            .block(home_block)
+            .highlight_style(Style::default().bg(Color::Blue));
+
+        f.render_widget(home_list, popup_area);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter: Select, Esc: Cancel");
+    }
+
+    fn render_storage_selection(&self, f: &mut Frame) {
+        let popup_area = self.centered_rect(60, 40, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let selection_block = Block::default()
+            .title("Select Storage Type")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let storage_types = self.storage_labels();
+
+        let items: Vec<ListItem> = storage_types
+            .iter()
+            .enumerate()
+            .map(|(i, storage_type)| {
+                let style = if i == self.storage_selection_index {
+                    Style::default().bg(Color::DarkGray).fg(Color::White)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(storage_type.as_str()).style(style)
+            })
+            .collect();
+
+        let selection_list = List::new(items)
+            .block(selection_block)
+            .highlight_style(Style::default().bg(Color::Blue));
+
+        f.render_widget(selection_list, popup_area);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter: Select, Esc: Back");
+    }
+
+    /// Backends in the order of `storage_selection_index`.
+    fn storage_labels(&self) -> [String; 4] {
+        let mongo_label = if storage::MONGO_SUPPORTED { "MongoDB" } else { "MongoDB (not included in this build)" };
+        let git_label = format!("Git branch ({} in the current repository)", self.temp_config.git_config.branch);
+        let remote_label = if storage::REMOTE_SUPPORTED {
+            format!("Remote server ({})", self.temp_config.remote_config.url)
+        } else {
+            "Remote server (not included in this build)".to_string()
+        };
+        ["Local".to_string(), mongo_label.to_string(), git_label, remote_label]
+    }
+
+    fn render_setup(&self, f: &mut Frame) {
+        let popup_area = self.centered_rect(70, 60, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let (number, name, intro, instructions) = match self.setup_step {
+            SetupStep::Backend => (
+                1,
+                "Storage",
+                "Where should quill keep your tasks? This can be changed later with `c`.",
+                "↑/↓: Navigate, Enter: Select, Esc: Skip and use local storage",
+            ),
+            SetupStep::Connection => (
+                2,
+                "Connection",
+                "Check the settings, then test the connection before going on.",
+                "↑/↓: Navigate, Enter: Edit / Select, R: Reveal password, Esc: Back",
+            ),
+            SetupStep::Scope => (
+                3,
+                "Task lists",
+                "How should tasks be split within a repository?",
+                "↑/↓: Navigate, Enter: Select, Esc: Back",
+            ),
+            SetupStep::Theme => (
+                4,
+                "Theme",
+                "Pick the colors that read best in your terminal.",
+                "↑/↓: Navigate, Enter: Finish, Esc: Back",
+            ),
+        };
+        let block = Block::default()
+            .title(format!("Welcome to quill: step {} of 4, {}", number, name))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let mut lines = vec![Line::from(Span::styled(intro, Style::default().fg(Color::White))), Line::from("")];
+        for (i, row) in self.setup_rows().into_iter().enumerate() {
+            let style = if i == self.config_field_index {
+                Style::default().bg(Color::DarkGray).fg(Color::White)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(Span::styled(row, style)));
+        }
+        if let Some(result) = self.connection_test_line().filter(|_| self.setup_step == SetupStep::Connection) {
+            lines.push(Line::from(""));
+            lines.push(result);
+        }
+
+        f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), popup_area);
+        self.render_instructions(f, popup_area, instructions);
+    }
+
+    fn render_local_config(&self, f: &mut Frame) {
+        let popup_area = self.centered_rect(70, 40, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let local_block = Block::default()
+            .title("Local Storage Configuration")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let fields = [
+            format!("Path: {}", self.temp_config.local_config.path),
+        ];
+
+        let items: Vec<ListItem> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let style = if i == self.config_field_index {
+                    Style::default().bg(Color::DarkGray).fg(Color::White)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(field.as_str()).style(style)
+            })
+            .collect();
+
+        let local_list = List::new(items)
+            .block(local_block)
+            .highlight_style(Style::default().bg(Color::Blue));
+
+        f.render_widget(local_list, popup_area);
+        self.render_connection_test(f, popup_area);
+
+        self.render_instructions(f, popup_area, "Enter: Edit, S: Save & Back, Esc: Back");
+    }
+
+    fn render_mongodb_config(&self, f: &mut Frame) {
+        let popup_area = self.centered_rect(80, 70, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let mongo_block = Block::default()
+            .title("MongoDB Configuration")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let fields: Vec<String> = MONGO_FIELDS
+            .iter()
+            .enumerate()
+            .map(|(i, label)| match i {
+                0 => format!("{}: {}", label, self.shown_connection_string()),
+                _ => match self.mongo_field_value(i) {
+                    value if value.is_empty() => format!("{}: (default)", label),
+                    value => format!("{}: {}", label, value),
+                },
+            })
+            .collect();
+
+        let items: Vec<ListItem> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let style = if i == self.config_field_index {
+                    Style::default().bg(Color::DarkGray).fg(Color::White)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(field.as_str()).style(style)
+            })
+            .collect();
+
+        let mongo_list = List::new(items)
+            .block(mongo_block)
+            .highlight_style(Style::default().bg(Color::Blue));
+
+        f.render_widget(mongo_list, popup_area);
+        self.render_connection_test(f, popup_area);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter: Edit / Toggle, T: Test connection, R: Reveal password, S: Save & Back, Esc: Back");
+    }
+
+    fn render_credentials(&self, f: &mut Frame) {
+        let popup_area = self.centered_rect(80, 50, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("Credentials")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let mut lines: Vec<Line> = self
+            .credential_rows()
+            .into_iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let style = if i == self.config_field_index {
+                    Style::default().bg(Color::DarkGray).fg(Color::White)
+                } else {
+                    Style::default()
+                };
+                Line::from(Span::styled(row, style))
+            })
+            .collect();
+        lines.push(Line::from(""));
+        let note = if secret::KEYCHAIN_SUPPORTED {
+            "Stored secrets go to the OS keychain; config.toml only gets a ${keyring:…} reference."
+        } else {
+            "This build of quill has no keychain support (rebuild with the `keychain` feature)."
+        };
+        lines.push(Line::from(Span::styled(note, Style::default().fg(Color::Gray))));
+
+        f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), popup_area);
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter: Store in keychain, Esc: Back");
+    }
+
+    /// The last connection test's outcome, just above the instructions.
+    fn render_connection_test(&self, f: &mut Frame, popup_area: Rect) {
+        if let Some(line) = self.connection_test_line() {
+            let area = Rect {
+                x: popup_area.x + 2,
+                y: popup_area.y + popup_area.height.saturating_sub(6),
+                width: popup_area.width.saturating_sub(4),
+                height: 2,
+            };
+            f.render_widget(Paragraph::new(line).wrap(Wrap { trim: true }), area);
+        }
+    }
+
+    fn render_inspector(&self, f: &mut Frame) {
+        let popup_area = self.centered_rect(90, 80, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("Inspector (raw storage) - ↑/↓ scroll, PgUp/PgDn, Esc close")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Magenta));
+
+        let paragraph = Paragraph::new(self.inspector_text.as_str())
+            .block(block)
+            .style(Style::default().fg(Color::White))
+            .scroll((self.inspector_scroll, 0));
+
+        f.render_widget(paragraph, popup_area);
+    }
+
+    fn render_backend_picker(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(50, 40, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("Peek at Storage (read-only)")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let items: Vec<ListItem> = self.backend_choices
+            .iter()
+            .enumerate()
+            .map(|(i, backend)| {
+                if i == 0 {
+                    ListItem::new(format!("{} (default)", backend.label()))
+                } else if (*backend == StorageType::MongoDB && !storage::MONGO_SUPPORTED)
+                    || (*backend == StorageType::Remote && !storage::REMOTE_SUPPORTED)
+                {
+                    ListItem::new(format!("{} (not included in this build)", backend.label())).style(Style::default().fg(Color::DarkGray))
+                } else {
+                    ListItem::new(backend.label())
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray).fg(Color::White));
+
+        f.render_stateful_widget(list, popup_area, &mut self.backend_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter: Open, Esc: Cancel");
+    }
+
+    fn render_health(&self, f: &mut Frame) {
+        let Some(health) = &self.health else {
+            return;
+        };
+        let popup_area = self.centered_rect(80, 70, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title(format!("Storage Health - {}", health.backend))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let label_width = health.details.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        let mut lines: Vec<Line> = health
+            .details
+            .iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    Span::styled(format!("{:<width$}  ", label, width = label_width), Style::default().fg(Color::Gray)),
+                    Span::styled(value.clone(), Style::default().fg(Color::White)),
+                ])
+            })
+            .collect();
+        if health.warnings.is_empty() {
+            lines.push(Line::from(Span::styled("✓ No problems found", Style::default().fg(Color::Green))));
+        }
+        for warning in &health.warnings {
+            lines.push(Line::from(Span::styled(format!("⚠ {}", warning), Style::default().fg(Color::Yellow))));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!("{} contexts", health.contexts.len()),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )));
+        for (context_key, count) in &health.contexts {
+            lines.push(Line::from(Span::styled(format!("{:>5} tasks  {}", count, context_key), Style::default().fg(Color::White))));
+        }
+
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .scroll((self.inspector_scroll, 0));
+        f.render_widget(paragraph, popup_area);
+
+        self.render_instructions(f, popup_area, "↑/↓: Scroll, Esc: Close");
+    }
+
+    fn render_storage_recovery(&self, f: &mut Frame) {
+        let Some(failure) = &self.storage_failure else {
+            return;
+        };
+        let popup_area = self.centered_rect(80, 70, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title(format!("{} storage unavailable", failure.backend.label()))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Red));
+
+        let mut lines: Vec<Line> = failure
+            .error
+            .lines()
+            .map(|line| Line::from(Span::styled(line.to_string(), Style::default().fg(Color::White))))
+            .collect();
+        lines.push(Line::from(""));
+        let mut choices = vec![
+            ("r", "Retry".to_string()),
+            ("c", "Edit the connection settings".to_string()),
+            ("l", "Use local storage for this session (the config is left as it is)".to_string()),
+        ];
+        if failure.can_switch {
+            choices.push(("s", format!("Switch to local storage and save that instead of {}", failure.backend.label())));
+        }
+        choices.push(("q", "Quit".to_string()));
+        for (key, label) in choices {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {}  ", key), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::styled(label, Style::default().fg(Color::White)),
+            ]));
+        }
+
+        let paragraph = Paragraph::new(lines).block(block).wrap(Wrap { trim: false });
+        f.render_widget(paragraph, popup_area);
+    }
+
+    fn render_storage_conflict(&self, f: &mut Frame) {
+        let popup_area = self.centered_rect(60, 30, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("Storage file changed on disk")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Yellow));
+        let lines = vec![
+            Line::from(Span::styled(
+                "Another program changed the task file while changes made here haven't been saved.",
+                Style::default().fg(Color::White),
+            )),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("  m  ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::styled("Keep mine: write this list over the file", Style::default().fg(Color::White)),
+            ]),
+            Line::from(vec![
+                Span::styled("  t  ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::styled("Take theirs: load the file and drop the unsaved changes", Style::default().fg(Color::White)),
+            ]),
+        ];
+        f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), popup_area);
+    }
+
+    fn render_stats(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(80, 70, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let over_limit = self.stats.iter().filter(|c| c.open > self.open_task_soft_limit).count();
+        let block = Block::default()
+            .title(format!(
+                "Context Stats - {} contexts, {} over the soft limit of {}",
+                self.stats.len(),
+                over_limit,
+                self.open_task_soft_limit
+            ))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let items: Vec<ListItem> = self.stats
+            .iter()
+            .map(|stat| {
+                let style = if stat.open > self.open_task_soft_limit {
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                let marker = if stat.open > self.open_task_soft_limit { "⚠" } else { " " };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{} {:>4} open / {:>4} total  ", marker, stat.open, stat.total), style),
+                    Span::styled(stat.context_key.clone(), Style::default().fg(accent::for_context(&stat.context_key))),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+
+        f.render_stateful_widget(list, popup_area, &mut self.stats_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Esc: Close");
+    }
+
+    fn render_overview(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(80, 80, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title(format!(
+                "All Open Tasks - {} across {} contexts",
+                self.overview.open_tasks(),
+                self.overview.contexts()
+            ))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let items: Vec<ListItem> = self
+            .overview
+            .rows()
+            .into_iter()
+            .map(|row| match row {
+                Row::Context { context_key, open, done, collapsed } => {
+                    let mut parts = context_key.splitn(3, ':');
+                    let (org, repo, branch) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+                    ListItem::new(Line::from(vec![
+                        Span::styled(if collapsed { "▸ " } else { "▾ " }, Style::default().fg(Color::Gray)),
+                        Span::styled(
+                            format!("{}/{}", org, repo),
+                            Style::default().fg(accent::for_context(&context_key)).add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(format!(" ({})", branch), Style::default().fg(Color::Gray)),
+                        Span::styled(format!("  {} open", open), Style::default().fg(Color::White)),
+                        Span::styled(format!("  {}", overview::progress_bar(done, open + done, 10)), Style::default().fg(Color::Green)),
+                        Span::styled(format!(" {}/{} done, {}%", done, open + done, overview::percent(done, open + done)), Style::default().fg(Color::Gray)),
+                    ]))
+                }
+                Row::Task { task, .. } => {
+                    let (symbol, style) = match task.status {
+                        TaskStatus::InProgress => ("◐", Style::default().fg(Color::Blue)),
+                        _ => ("○", Style::default().fg(Color::Yellow)),
+                    };
+                    let indent = if task.parent.is_some() { "      " } else { "    " };
+                    ListItem::new(Line::from(vec![
+                        Span::raw(indent),
+                        Span::styled(format!("{} ", symbol), style),
+                        Span::styled(task.text, Style::default().fg(Color::White)),
+                    ]))
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+
+        f.render_stateful_widget(list, popup_area, &mut self.overview_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Space: Collapse, Tab: Collapse all, Enter: Go to context, Esc: Close");
+    }
+
+    fn render_search(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(80, 80, f.area());
+        f.render_widget(Clear, popup_area);
+        let [input_area, results_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(popup_area);
+
+        let input_block = Block::default()
+            .title("Search all contexts: words of task text, notes or tags")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+        f.render_widget(Paragraph::new(self.input.text()).block(input_block), input_area);
+        f.set_cursor_position((input_area.x + self.input.cursor() as u16 + 1, input_area.y + 1));
+
+        // Context headings go between the results, so the selected result's row shifts by them
+        let mut items = Vec::new();
+        let mut selected_row = None;
+        for (index, (context_key, task)) in self.search_results.iter().enumerate() {
+            if index == 0 || self.search_results[index - 1].0 != *context_key {
+                let mut parts = context_key.splitn(3, ':');
+                let (org, repo, branch) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+                items.push(ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{}/{}", org, repo),
+                        Style::default().fg(accent::for_context(context_key)).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(format!(" ({})", branch), Style::default().fg(Color::Gray)),
+                ])));
+            }
+            if self.search_state.selected() == Some(index) {
+                selected_row = Some(items.len());
+            }
+            let (symbol, style) = match task.status {
+                TaskStatus::NotStarted => ("○", Style::default().fg(Color::Yellow)),
+                TaskStatus::InProgress => ("◐", Style::default().fg(Color::Blue)),
+                TaskStatus::Completed => ("✓", Style::default().fg(Color::Green)),
+            };
+            let mut spans = vec![Span::raw("    "), Span::styled(format!("{} ", symbol), style), Span::styled(task.text.clone(), Style::default().fg(Color::White))];
+            if !task.tags.is_empty() {
+                spans.push(Span::styled(format!(" #{}", task.tags.join(" #")), Style::default().fg(Color::Cyan)));
+            }
+            items.push(ListItem::new(Line::from(spans)));
+        }
+
+        let title = match self.search_results.len() {
+            0 if self.input.text().trim().is_empty() => "Results".to_string(),
+            0 => "No matching tasks".to_string(),
+            n => format!("{} matching task(s)", n),
+        };
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL).style(Style::default().fg(Color::Cyan)))
+            .highlight_style(Style::default().bg(Color::DarkGray));
+        let mut state = ListState::default().with_selected(selected_row);
+        f.render_stateful_widget(list, results_area, &mut state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter: Go to task, Esc: Close");
+    }
+
+    fn render_cleanup(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(80, 70, f.area());
+        f.render_widget(Clear, popup_area);
+
+        if let Some((action, lines)) = &self.cleanup_preview {
+            let block = Block::default()
+                .title(format!("Clean Up Branches - {} (dry run)", action.label()))
+                .borders(Borders::ALL)
+                .style(Style::default().fg(if *action == cleanup::Action::Delete { Color::Red } else { Color::Cyan }));
+            let lines: Vec<Line> = lines.iter().map(|line| Line::from(line.as_str())).collect();
+            f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), popup_area);
+            self.render_instructions(f, popup_area, "Enter: Apply, Esc: Back");
+            return;
+        }
+
+        let block = Block::default()
+            .title(format!("Clean Up Branches - {} task lists of deleted or merged branches", self.cleanup.len()))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        if self.cleanup.is_empty() {
+            let empty = Paragraph::new("No task lists of deleted or merged branches in this repository.").block(block);
+            f.render_widget(empty, popup_area);
+            self.render_instructions(f, popup_area, "Esc: Close");
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .cleanup
+            .iter()
+            .map(|(candidate, picked)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(if *picked { "[x] " } else { "[ ] " }, Style::default().fg(Color::Cyan)),
+                    Span::styled(format!("{:<32}", candidate.branch), Style::default().fg(Color::White)),
+                    Span::styled(format!("{:<16}", candidate.state.label()), Style::default().fg(Color::Yellow)),
+                    Span::styled(format!("{} open / {} total", candidate.open, candidate.total), Style::default().fg(Color::Gray)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+
+        f.render_stateful_widget(list, popup_area, &mut self.cleanup_state);
+
+        self.render_instructions(f, popup_area, "Space: Pick, a: Archive, m: Migrate here, x: Delete, Esc: Close");
+    }
+
+    fn render_diff(&self, f: &mut Frame) {
+        let Some(diff) = &self.diff else {
+            return;
+        };
+        let popup_area = self.centered_rect(85, 70, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let mut lines: Vec<Line> = diff
+            .lines()
+            .into_iter()
+            .map(|line| match line {
+                DiffLine::Added(task) => Line::from(Span::styled(format!("+ {}", task.text), Style::default().fg(Color::Green))),
+                DiffLine::Removed(task) => Line::from(Span::styled(format!("- {}", task.text), Style::default().fg(Color::Red))),
+                DiffLine::Modified { before, after } => Line::from(vec![
+                    Span::styled(format!("~ {}", after.text), Style::default().fg(Color::Yellow)),
+                    Span::styled(format!("  ({})", diff::changed_fields(&before, &after).join(", ")), Style::default().fg(Color::Gray)),
+                ]),
+            })
+            .collect();
+        if diff.reordered() {
+            lines.push(Line::from(Span::styled("↕ tasks reordered", Style::default().fg(Color::Yellow))));
+        }
+
+        let block = Block::default()
+            .title(format!("Changes from {} - {}", diff.operation, diff.context_key))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+        let paragraph = Paragraph::new(lines).block(block).scroll((self.diff_scroll, 0));
+        f.render_widget(paragraph, popup_area);
+
+        self.render_instructions(f, popup_area, "↑/↓: Scroll, r: Revert all, Enter/Esc: Keep");
+    }
+
+    fn render_palette(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(60, 60, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(popup_area);
+
+        let input = Paragraph::new(format!(":{}", self.input.text())).block(
+            Block::default()
+                .title("Command Palette")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Cyan)),
+        );
+        f.render_widget(input, chunks[0]);
+        f.set_cursor_position((chunks[0].x + self.input.cursor() as u16 + 2, chunks[0].y + 1));
+
+        let label_width = chunks[1].width.saturating_sub(14) as usize;
+        let items: Vec<ListItem> = self.palette_matches
+            .iter()
+            .map(|&i| {
+                let item = &self.palette[i];
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<width$}", item.label, width = label_width), Style::default().fg(Color::White)),
+                    Span::styled(format!("{:>8}", item.key), Style::default().fg(Color::Gray)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).style(Style::default().fg(Color::Cyan)))
+            .highlight_style(Style::default().bg(Color::DarkGray));
+        f.render_stateful_widget(list, chunks[1], &mut self.palette_state);
+
+        self.render_instructions(f, chunks[1], "Type to filter, ↑/↓: Navigate, Enter: Run, Esc: Close");
+    }
+
+    fn render_trash(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(80, 70, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title(format!("Trash - {} deleted tasks", self.trash.len()))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        if self.trash.is_empty() {
+            let empty = Paragraph::new("The trash is empty.").block(block);
+            f.render_widget(empty, popup_area);
+            self.render_instructions(f, popup_area, "Esc: Close");
+            return;
+        }
+
+        let items: Vec<ListItem> = self.trash
+            .iter()
+            .map(|deleted| {
+                let when = chrono::DateTime::parse_from_rfc3339(&deleted.deleted_at)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|_| "unknown".to_string());
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<16}  ", when), Style::default().fg(Color::Gray)),
+                    Span::styled(deleted.task.text.clone(), Style::default().fg(Color::White)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+
+        f.render_stateful_widget(list, popup_area, &mut self.trash_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter/r: Restore, x: Purge, Esc: Close");
+    }
+
+    fn render_activity(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(85, 70, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title(format!("Activity - {} change(s)", self.activity.len()))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        if self.activity.is_empty() {
+            let empty = Paragraph::new("No changes recorded here. MongoDB and local storage record who changes which task.")
+                .block(block)
+                .wrap(Wrap { trim: true });
+            f.render_widget(empty, popup_area);
+            self.render_instructions(f, popup_area, "Esc: Close");
+            return;
+        }
+
+        let items: Vec<ListItem> = self.activity
+            .iter()
+            .map(|entry| {
+                let when = chrono::DateTime::parse_from_rfc3339(&entry.at)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|_| "unknown".to_string());
+                let style = match entry.action {
+                    ActivityAction::Deleted | ActivityAction::Purged | ActivityAction::ContextDeleted => Style::default().fg(Color::Red),
+                    ActivityAction::Added | ActivityAction::Restored => Style::default().fg(Color::Green),
+                    _ => Style::default().fg(Color::White),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<16}  ", when), Style::default().fg(Color::Gray)),
+                    Span::styled(format!("{}  ", entry.actor), Style::default().fg(Color::Magenta)),
+                    Span::styled(entry.describe(), style),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+
+        f.render_stateful_widget(list, popup_area, &mut self.activity_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Esc: Close");
+    }
+
+    fn render_details(&self, f: &mut Frame, area: Rect, task: Option<&Task>) {
+        let block = Block::default().title("Details").borders(Borders::ALL);
+        let Some(task) = task else {
+            f.render_widget(Paragraph::new("No task selected").style(Style::default().fg(Color::DarkGray)).block(block), area);
+            return;
+        };
+        let label = Style::default().fg(Color::DarkGray);
+        let value = Style::default().fg(Color::White);
+        let when = |at: &str| {
+            chrono::DateTime::parse_from_rfc3339(at)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|_| at.to_string())
+        };
+
+        let (title, next_link) = markdown::spans(&task.text, value.add_modifier(Modifier::BOLD), 1);
+        let mut lines = vec![Line::from(title), Line::from("")];
+        let mut field = |name: &str, spans: Vec<Span<'static>>| {
+            let mut line = vec![Span::styled(format!("{:<10}", name), label)];
+            line.extend(spans);
+            lines.push(Line::from(line));
+        };
+        let (status, status_color) = match task.status {
+            TaskStatus::NotStarted => ("○ not started", Color::Yellow),
+            TaskStatus::InProgress => ("◐ in progress", Color::Blue),
+            TaskStatus::Completed => ("✓ completed", Color::Green),
+        };
+        field("Status", vec![Span::styled(status, Style::default().fg(status_color))]);
+        match task.priority {
+            Priority::High => field("Priority", vec![Span::styled("high", Style::default().fg(Color::Red))]),
+            Priority::Medium => field("Priority", vec![Span::styled("medium", Style::default().fg(Color::Yellow))]),
+            Priority::Low => field("Priority", vec![Span::styled("low", Style::default().fg(Color::Blue))]),
+            Priority::None => {}
+        }
+        if !task.tags.is_empty() {
+            let tags = task.tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<_>>().join(" ");
+            field("Tags", vec![Span::styled(tags, Style::default().fg(Color::Magenta))]);
+        }
+        if let Some(due) = task.due {
+            let overdue = task.is_overdue(chrono::Local::now().date_naive());
+            field("Due", vec![Span::styled(due.format("%Y-%m-%d").to_string(), Style::default().fg(if overdue { Color::Red } else { Color::White }))]);
+        }
+        if let Some(assignee) = &task.assignee {
+            field("Assignee", vec![Span::styled(assignee.clone(), Style::default().fg(Color::Green))]);
+        }
+        if let Some(issue) = &task.issue {
+            field("Issue", vec![Span::styled(issue.clone(), Style::default().fg(Color::Blue))]);
+        }
+        if let Some(anchor) = &task.anchor {
+            field("File", vec![Span::styled(anchor.to_string(), value)]);
+        }
+        for attachment in &task.attachments {
+            field("Attached", vec![Span::styled(attachment.to_string(), value)]);
+        }
+        field("Created", vec![Span::styled(when(&task.created_at), value)]);
+        if let Some(completed_at) = &task.completed_at {
+            field("Completed", vec![Span::styled(when(completed_at), value)]);
+        }
+
+        lines.push(Line::from(""));
+        lines.push(Line::styled("Notes", label));
+        if task.notes.trim().is_empty() {
+            lines.push(Line::styled("none (n to write some)", label));
+        } else {
+            lines.extend(markdown::lines(&task.notes, Style::default().fg(Color::Gray), next_link));
+        }
+
+        lines.push(Line::from(""));
+        lines.push(Line::styled("Commits", label));
+        match self.details_commits.as_ref().filter(|(uid, _)| *uid == task.short_uid()) {
+            Some((_, commits)) if !commits.is_empty() => {
+                for commit in commits {
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("{} ", commit.id), Style::default().fg(Color::Yellow)),
+                        Span::styled(commit.summary.clone(), value),
+                    ]));
+                }
+            }
+            _ => lines.push(Line::styled(format!("none yet; mention {} in a commit message to link one", task.short_uid()), label)),
+        }
+
+        f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
+    }
+
+    fn render_notes(&self, f: &mut Frame) {
+        let popup_area = self.centered_rect(70, 60, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let Some((text, notes)) = &self.notes_view else {
+            return;
+        };
+        let (title, next_link) = markdown::spans(text, Style::default().fg(Color::White).add_modifier(Modifier::BOLD), 1);
+        let mut lines = vec![Line::from(title), Line::from("")];
+        if notes.trim().is_empty() {
+            lines.push(Line::styled("No notes yet (press n to write some).", Style::default().fg(Color::DarkGray)));
+        } else {
+            lines.extend(markdown::lines(notes, Style::default().fg(Color::Gray), next_link));
+        }
+
+        let block = Block::default()
+            .title(format!("Notes - {} link(s)", self.notes_links.len()))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .scroll((self.notes_scroll, 0));
+        f.render_widget(paragraph, popup_area);
+
+        let instructions = match self.notes_links.len() {
+            0 => "↑/↓: Scroll, Esc: Close",
+            1 => "↑/↓: Scroll, Enter/1: Open link, Esc: Close",
+            _ => "↑/↓: Scroll, 1-9: Open link, Esc: Close",
+        };
+        self.render_instructions(f, popup_area, instructions);
+    }
+
+    fn render_attachments(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(70, 50, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title(format!("Attachments - {}", self.attachments.len()))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        if self.attachments.is_empty() {
+            let empty = Paragraph::new("Nothing attached yet. Press a to attach a file (path[:line]) or a URL.")
+                .block(block)
+                .wrap(Wrap { trim: true });
+            f.render_widget(empty, popup_area);
+            self.render_instructions(f, popup_area, "a: Attach, Esc: Close");
+            return;
+        }
+
+        let items: Vec<ListItem> = self.attachments
+            .iter()
+            .map(|attachment| {
+                let (kind, color) = match attachment {
+                    Attachment::File(_) => ("file", Color::Yellow),
+                    Attachment::Url { .. } => ("url ", Color::Blue),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}  ", kind), Style::default().fg(color)),
+                    Span::styled(attachment.to_string(), Style::default().fg(Color::White)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+
+        f.render_stateful_widget(list, popup_area, &mut self.attachments_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter/o: Open, a: Attach, x: Remove, Esc: Close");
+    }
+
+    fn render_sync_log(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(85, 70, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let tabs: Vec<String> = INTEGRATIONS
+            .iter()
+            .map(|name| if *name == self.selected_integration() { format!("[{}]", name) } else { name.to_string() })
+            .collect();
+        let block = Block::default()
+            .title(format!(
+                "Sync Log - {}{}",
+                tabs.join(" "),
+                if self.sync_paused { " (paused)" } else { "" }
+            ))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(if self.sync_paused { Color::Yellow } else { Color::Cyan }));
+
+        let instructions = "↑/↓: Navigate, n: Sync now, p: Pause/resume, Tab: Next integration, Esc: Close";
+        if self.sync_entries.is_empty() {
+            let empty = Paragraph::new("No sync operations recorded yet.").block(block);
+            f.render_widget(empty, popup_area);
+            self.render_instructions(f, popup_area, instructions);
+            return;
+        }
+
+        let items: Vec<ListItem> = self.sync_entries
+            .iter()
+            .map(|entry| {
+                let when = chrono::DateTime::parse_from_rfc3339(&entry.at)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|_| entry.at.clone());
+                let (outcome, style) = match &entry.error {
+                    Some(error) => (format!("error: {}", error), Style::default().fg(Color::Red)),
+                    None => (format!("{} changed", entry.items_changed), Style::default().fg(Color::White)),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}  ", when), Style::default().fg(Color::Gray)),
+                    Span::styled(format!("{:<4}  ", entry.direction.label()), Style::default().fg(Color::Magenta)),
+                    Span::styled(format!("{}  ", entry.context_key), Style::default().fg(Color::Cyan)),
+                    Span::styled(outcome, style),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+
+        f.render_stateful_widget(list, popup_area, &mut self.sync_state);
+
+        self.render_instructions(f, popup_area, instructions);
+    }
+
+    fn render_view_picker(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(60, 50, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("Views")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let mark = |active: bool| if active { "● " } else { "  " };
+        let mut items = vec![ListItem::new(Line::from(vec![
+            Span::styled(mark(self.active_view.is_none()), Style::default().fg(Color::Green)),
+            Span::styled("All tasks", Style::default().fg(Color::White)),
+        ]))];
+        for view in &self.views {
+            let detail = match &view.filter {
+                Ok(_) => Span::styled(format!("  {}", view.expression), Style::default().fg(Color::Gray)),
+                Err(e) => Span::styled(format!("  {}: {}", view.expression, e), Style::default().fg(Color::Red)),
+            };
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(mark(self.active_view.as_ref() == Some(&view.name)), Style::default().fg(Color::Green)),
+                Span::styled(view.name.clone(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+                detail,
+            ])));
+        }
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray).fg(Color::White));
+
+        f.render_stateful_widget(list, popup_area, &mut self.view_picker_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter: Apply, n: New view, x: Delete, Esc: Close");
+    }
+
+    fn render_template_picker(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(60, 50, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("Apply Template")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        if self.templates.is_empty() {
+            let empty = Paragraph::new("No templates found. Add them to [[templates]] in ~/.quill/config.toml or as files in .quill/templates/ in this repository.")
+                .block(block)
+                .wrap(Wrap { trim: true });
+            f.render_widget(empty, popup_area);
+            self.render_instructions(f, popup_area, "Esc: Close");
+            return;
+        }
+
+        let items: Vec<ListItem> = self.templates
+            .iter()
+            .map(|template| ListItem::new(format!("{} ({} tasks)", template.name, template.tasks.len())))
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray).fg(Color::White));
+
+        f.render_stateful_widget(list, popup_area, &mut self.template_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter: Apply, Esc: Cancel");
+    }
+
+    fn render_assignee_picker(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(50, 40, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("Assign To")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        let items: Vec<ListItem> = self.assignees
+            .iter()
+            .map(|person| match person {
+                None => ListItem::new("Unassigned"),
+                Some(email) if Some(email) == self.user_email.as_ref() => ListItem::new(format!("{} (you)", email)),
+                Some(email) => ListItem::new(email.clone()),
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray).fg(Color::White));
+
+        f.render_stateful_widget(list, popup_area, &mut self.assignee_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Enter: Assign, Esc: Cancel");
+    }
+
+    fn render_import_picker(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(70, 50, f.area());
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("Import Existing Tasks")
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan));
+
+        if self.import_sources.is_empty() {
+            let empty = Paragraph::new("No todo.txt, TODO.md or Taskwarrior tasks found in the usual places.")
+                .block(block)
+                .wrap(Wrap { trim: true });
+            f.render_widget(empty, popup_area);
+            self.render_instructions(f, popup_area, "Esc: Close");
+            return;
+        }
+
+        let items: Vec<ListItem> = self.import_sources
+            .iter()
+            .map(|(summary, ticked)| ListItem::new(format!("[{}] {}", if *ticked { "x" } else { " " }, summary)))
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray).fg(Color::White));
+
+        f.render_stateful_widget(list, popup_area, &mut self.import_state);
+
+        self.render_instructions(f, popup_area, "↑/↓: Navigate, Space: Toggle, Enter: Import ticked, Esc: Skip");
+    }
+
+    fn render_instructions(&self, f: &mut Frame, popup_area: ratatui::layout::Rect, text: &str) {
+        let instructions_area = ratatui::layout::Rect {
+            x: popup_area.x,
+            y: popup_area.y + popup_area.height - 3,
+            width: popup_area.width,
+            height: 3,
+        };
+
+        let instructions = Paragraph::new(text)
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(Alignment::Center);
+
+        f.render_widget(instructions, instructions_area);
+    }
+
+    /// The newest log lines over the bottom of the screen; keys still go to the list.
+    fn render_debug_log(&self, f: &mut Frame, lines: &[String]) {
+        let area = f.area();
+        let height = (area.height / 3).max(5).min(area.height);
+        let overlay_area = Rect { x: area.x, y: area.y + area.height - height, width: area.width, height };
+        f.render_widget(Clear, overlay_area);
+
+        let shown = lines.iter().skip(lines.len().saturating_sub(height.saturating_sub(2) as usize));
+        let text: Vec<Line> = shown
+            .map(|line| {
+                let color = if line.contains("ERROR") {
+                    Color::Red
+                } else if line.contains("WARN") {
+                    Color::Yellow
+                } else {
+                    Color::Gray
+                };
+                Line::from(Span::styled(line.clone(), Style::default().fg(color)))
+            })
+            .collect();
+        let title = match crate::logging::log_path() {
+            Some(path) => format!("Debug log - {} (F12 closes)", path.display()),
+            None => "Debug log (F12 closes)".to_string(),
+        };
+        let block = Block::default().title(title).borders(Borders::ALL).style(Style::default().fg(Color::DarkGray));
+        f.render_widget(Paragraph::new(text).block(block), overlay_area);
+    }
+
+    fn render_notification(&self, f: &mut Frame, notification: &Notification) {
+        let area = f.area();
+        let notification_width = 40;
+        let notification_height = 3;
+        
+        let notification_area = ratatui::layout::Rect {
+            x: area.width.saturating_sub(notification_width + 2),
+            y: 1,
+            width: notification_width,
+            height: notification_height,
+        };
+
+        f.render_widget(Clear, notification_area);
+
+        let (style, border_style) = match notification.level {
+            NotificationLevel::Success => (
+                Style::default().fg(Color::White).bg(Color::Green),
+                Style::default().fg(Color::Green)
+            ),
+            NotificationLevel::Error => (
+                Style::default().fg(Color::White).bg(Color::Red),
+                Style::default().fg(Color::Red)
+            ),
+        };
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .style(border_style);
+
+        let paragraph = Paragraph::new(notification.message.as_str())
+            .block(block)
+            .style(style)
+            .wrap(Wrap { trim: true });
+
+        f.render_widget(paragraph, notification_area);
+    }
+}
+/// Fits a task row into `width` columns, cutting it off with `…` or
+/// wrapping it onto indented lines. Returns the lines and whether the row
+/// was cut off.
+fn fit_row(spans: Vec<Span<'_>>, width: usize, long_titles: LongTitles) -> (Vec<Line<'_>>, bool) {
+    const INDENT: usize = 2;
+    if width <= INDENT || spans.iter().map(Span::width).sum::<usize>() <= width {
+        return (vec![Line::from(spans)], false);
+    }
+
+    match long_titles {
+        LongTitles::Truncate => {
+            let mut line = Vec::new();
+            let mut used = 0;
+            for span in spans {
+                if used + span.width() < width {
+                    used += span.width();
+                    line.push(span);
+                    continue;
+                }
+                let mut cut = String::new();
+                for c in span.content.chars() {
+                    let c_width = c.width().unwrap_or(0);
+                    if used + c_width >= width {
+                        break;
+                    }
+                    used += c_width;
+                    cut.push(c);
+                }
+                line.push(Span::styled(cut, span.style));
+                break;
+            }
+            line.push(Span::styled("…", Style::default().fg(Color::DarkGray)));
+            (vec![Line::from(line)], true)
+        }
+        LongTitles::Wrap => {
+            let mut lines = Vec::new();
+            let mut line = Vec::new();
+            let (mut used, mut start) = (0, 0);
+            for span in spans {
+                let mut chunk = String::new();
+                for c in span.content.chars() {
+                    let c_width = c.width().unwrap_or(0);
+                    if used + c_width > width && used > start {
+                        if !chunk.is_empty() {
+                            line.push(Span::styled(std::mem::take(&mut chunk), span.style));
+                        }
+                        lines.push(Line::from(std::mem::take(&mut line)));
+                        line.push(Span::raw(" ".repeat(INDENT)));
+                        (used, start) = (INDENT, INDENT);
+                        if c == ' ' {
+                            continue;
+                        }
+                    }
+                    chunk.push(c);
+                    used += c_width;
+                }
+                if !chunk.is_empty() {
+                    line.push(Span::styled(chunk, span.style));
+                }
+            }
+            lines.push(Line::from(line));
+            (lines, false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_row_truncates_or_wraps() {
+        let row = || vec![Span::raw("○ "), Span::raw("Rename the session cookie"), Span::raw(" #auth")];
+        let text = |lines: &[Line]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        let (lines, truncated) = fit_row(row(), 40, LongTitles::Truncate);
+        assert_eq!((text(&lines), truncated), (vec!["○ Rename the session cookie #auth".to_string()], false));
+
+        let (lines, truncated) = fit_row(row(), 16, LongTitles::Truncate);
+        assert_eq!((text(&lines), truncated), (vec!["○ Rename the se…".to_string()], true));
+
+        let (lines, truncated) = fit_row(row(), 16, LongTitles::Wrap);
+        assert!(!truncated);
+        assert_eq!(text(&lines), vec!["○ Rename the ses", "  sion cookie #a", "  uth"]);
+    }
+
+    #[test]
+    fn test_task_at_maps_rows_and_status_column() {
+        let mut ui = TaskUI::new();
+        ui.list_area = Rect { x: 0, y: 3, width: 40, height: 10 };
+
+        // Border row and rows past the last task miss
+        assert_eq!(ui.task_at(5, 3, 3), None);
+        assert_eq!(ui.task_at(5, 7, 3), None);
+
+        assert_eq!(ui.task_at(10, 4, 3), Some((0, false)));
+        assert_eq!(ui.task_at(3, 6, 3), Some((2, true)));
+
+        // The second task wrapped onto three lines
+        ui.row_heights = vec![1, 3, 1];
+        assert_eq!(ui.task_at(3, 6, 3), Some((1, false)));
+        assert_eq!(ui.task_at(3, 8, 3), Some((2, true)));
+    }
+
+    #[test]
+    fn test_sort_order_is_stable() {
+        let mut tasks: Vec<Task> = (1..=4).map(|id| Task::new(id, format!("Task {}", id))).collect();
+        tasks[1].priority = Priority::Low;
+        tasks[2].priority = Priority::High;
+        tasks[3].priority = Priority::Low;
+        tasks[0].due = chrono::NaiveDate::from_ymd_opt(2024, 5, 1);
+        tasks[3].due = chrono::NaiveDate::from_ymd_opt(2024, 4, 1);
+
+        let mut by_priority = tasks.clone();
+        SortOrder::Priority.sort(&mut by_priority);
+        assert_eq!(by_priority.iter().map(|t| t.id).collect::<Vec<_>>(), vec![3, 2, 4, 1]);
+
+        SortOrder::Due.sort(&mut tasks);
+        assert_eq!(tasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![4, 1, 2, 3]);
+
+        tasks[3].pinned = true;
+        SortOrder::Manual.sort(&mut tasks);
+        assert_eq!(tasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn test_footer_button_at() {
+        let mut ui = TaskUI::new();
+        ui.footer_buttons = vec![(Rect { x: 1, y: 20, width: 7, height: 1 }, 'a')];
+
+        assert_eq!(ui.footer_button_at(4, 20), Some('a'));
+        assert_eq!(ui.footer_button_at(8, 20), None);
+        assert_eq!(ui.footer_button_at(4, 21), None);
+    }
+
+    #[test]
+    fn test_scroll_selection_does_not_wrap() {
+        let mut ui = TaskUI::new();
+        let tasks = vec![Task::new(1, "a".to_string()), Task::new(2, "b".to_string())];
+
+        ui.scroll_selection(&tasks, -1);
+        assert_eq!(ui.list_state.selected(), Some(0));
+        ui.scroll_selection(&tasks, 5);
+        assert_eq!(ui.list_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn test_page_size_and_jumps() {
+        let mut ui = TaskUI::new();
+        ui.list_area = Rect { x: 0, y: 3, width: 40, height: 12 };
+        assert_eq!(ui.page_size(), 10);
+
+        let tasks: Vec<Task> = (1..=25).map(|i| Task::new(i, format!("Task {}", i))).collect();
+        ui.select_last(&tasks);
+        assert_eq!(ui.list_state.selected(), Some(24));
+        ui.scroll_selection(&tasks, -(ui.page_size() as i32));
+        assert_eq!(ui.list_state.selected(), Some(14));
+        ui.select_first(&tasks);
+        assert_eq!(ui.list_state.selected(), Some(0));
+    }
+}
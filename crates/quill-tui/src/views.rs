@@ -0,0 +1,371 @@
+use crate::dates::DateParser;
+use crate::storage::{Priority, Task, TaskStatus};
+use quill_core::pattern::glob_match;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// How a field is compared with a value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn holds<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Status(TaskStatus),
+    Priority(Op, Priority),
+    Tag(String),
+    /// Days since the task was created.
+    Age(Op, i64),
+    /// A due-date word, read on the day the view is used; `None` for `due:none`.
+    Due(Op, Option<String>),
+    /// `me`, `none` or an email.
+    Assignee(String),
+    Text(String),
+    /// A glob on the task's context key, e.g. `acme:*:main`.
+    Context(String),
+    Overdue,
+    Pinned,
+    MustFinish,
+    Snoozed,
+}
+
+/// A parsed view expression, e.g. `priority:high and status!=completed`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Is(Condition),
+}
+
+/// What a filter is checked against besides the task.
+pub struct Env<'a> {
+    pub today: NaiveDate,
+    pub now: DateTime<Utc>,
+    /// The user's email, for `assignee:me`.
+    pub me: Option<&'a str>,
+    pub dates: &'a DateParser,
+    /// Key of the context the task is in.
+    pub context_key: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+/// Characters of unquoted words besides letters and digits; values with
+/// others, like `:` in context keys, are written in double quotes.
+const WORD_CHARS: &str = "_-+.@#*?/";
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        chars.next();
+        let followed_by_eq = "!<>".contains(c) && chars.next_if_eq(&'=').is_some();
+        tokens.push(match (c, followed_by_eq) {
+            (c, false) if c.is_whitespace() => continue,
+            ('(', false) => Token::Open,
+            (')', false) => Token::Close,
+            (':' | '=', false) => Token::Op(Op::Eq),
+            ('!', true) => Token::Op(Op::Ne),
+            ('<', eq) => Token::Op(if eq { Op::Le } else { Op::Lt }),
+            ('>', eq) => Token::Op(if eq { Op::Ge } else { Op::Gt }),
+            ('"', false) => {
+                let word: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                Token::Word(word)
+            }
+            (c, false) if c.is_alphanumeric() || WORD_CHARS.contains(c) => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || WORD_CHARS.contains(*c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            (c, _) => bail!("unexpected '{}'", c),
+        });
+    }
+    Ok(tokens)
+}
+
+/// Reads tokens into a filter: `or` binds loosest, then `and` (also implied
+/// between terms), then `not`.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    dates: &'a DateParser,
+}
+
+impl Parser<'_> {
+    fn peek_word(&self) -> Option<String> {
+        match self.tokens.get(self.position) {
+            Some(Token::Word(word)) => Some(word.to_lowercase()),
+            _ => None,
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter> {
+        let mut filter = self.and()?;
+        while self.peek_word().as_deref() == Some("or") {
+            self.position += 1;
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter> {
+        let mut filter = self.not()?;
+        loop {
+            match (self.tokens.get(self.position), self.peek_word().as_deref()) {
+                (None | Some(Token::Close), _) | (_, Some("or")) => return Ok(filter),
+                (_, Some("and")) => self.position += 1,
+                _ => {}
+            }
+            filter = Filter::And(Box::new(filter), Box::new(self.not()?));
+        }
+    }
+
+    fn not(&mut self) -> Result<Filter> {
+        if self.peek_word().as_deref() == Some("not") {
+            self.position += 1;
+            return Ok(Filter::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Filter> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Open) => {
+                let filter = self.or()?;
+                match self.tokens.get(self.position) {
+                    Some(Token::Close) => {
+                        self.position += 1;
+                        Ok(filter)
+                    }
+                    _ => bail!("missing ')'"),
+                }
+            }
+            Some(Token::Word(field)) => match self.tokens.get(self.position) {
+                Some(Token::Op(op)) => {
+                    let op = *op;
+                    let value = match self.tokens.get(self.position + 1) {
+                        Some(Token::Word(value)) => value.clone(),
+                        _ => bail!("'{}' needs a value", field),
+                    };
+                    self.position += 2;
+                    self.condition(&field.to_lowercase(), op, &value)
+                }
+                _ => flag(&field.to_lowercase()),
+            },
+            Some(Token::Close) => bail!("unexpected ')'"),
+            Some(Token::Op(_)) => bail!("a comparison needs a field before it"),
+            None => bail!("the expression ends too early"),
+        }
+    }
+
+    fn condition(&self, field: &str, op: Op, value: &str) -> Result<Filter> {
+        let lower = value.to_lowercase();
+        let equality = |condition: Condition| match op {
+            Op::Eq => Ok(Filter::Is(condition)),
+            Op::Ne => Ok(Filter::Not(Box::new(Filter::Is(condition)))),
+            _ => Err(anyhow!("'{}' can only be compared with ':' or '!='", field)),
+        };
+        match field {
+            "status" => equality(Condition::Status(status(&lower)?)),
+            "tag" => equality(Condition::Tag(lower.trim_start_matches('#').to_string())),
+            "assignee" => equality(Condition::Assignee(lower)),
+            "text" => equality(Condition::Text(lower)),
+            "context" => equality(Condition::Context(value.to_string())),
+            "priority" => Ok(Filter::Is(Condition::Priority(op, priority(&lower)?))),
+            "age" => {
+                let days = match lower.strip_suffix('w') {
+                    Some(weeks) => weeks.parse::<i64>().map(|w| w * 7),
+                    None => lower.trim_end_matches('d').parse(),
+                };
+                Ok(Filter::Is(Condition::Age(op, days.map_err(|_| anyhow!("age needs days or weeks, e.g. 7d or 2w"))?)))
+            }
+            "due" if lower == "none" => equality(Condition::Due(Op::Eq, None)),
+            "due" => {
+                if self.dates.parse_due_word(&lower, chrono::Local::now().date_naive()).is_none() {
+                    bail!("can't tell when '{}' is", value);
+                }
+                Ok(Filter::Is(Condition::Due(op, Some(lower))))
+            }
+            _ => bail!("unknown field '{}'", field),
+        }
+    }
+}
+
+fn status(value: &str) -> Result<TaskStatus> {
+    Ok(match value {
+        "not_started" | "todo" => TaskStatus::NotStarted,
+        "in_progress" | "doing" => TaskStatus::InProgress,
+        "completed" | "done" => TaskStatus::Completed,
+        _ => bail!("unknown status '{}', expected not_started, in_progress or completed", value),
+    })
+}
+
+fn priority(value: &str) -> Result<Priority> {
+    Ok(match value {
+        "none" => Priority::None,
+        "low" => Priority::Low,
+        "medium" => Priority::Medium,
+        "high" => Priority::High,
+        _ => bail!("unknown priority '{}', expected none, low, medium or high", value),
+    })
+}
+
+/// A term without a comparison, e.g. `overdue` or `in_progress`.
+fn flag(word: &str) -> Result<Filter> {
+    Ok(Filter::Is(match word {
+        "open" => return Ok(Filter::Not(Box::new(Filter::Is(Condition::Status(TaskStatus::Completed))))),
+        "overdue" => Condition::Overdue,
+        "pinned" => Condition::Pinned,
+        "must_finish" => Condition::MustFinish,
+        "snoozed" => Condition::Snoozed,
+        _ => Condition::Status(status(word).map_err(|_| anyhow!("unknown term '{}'", word))?),
+    }))
+}
+
+impl Filter {
+    pub fn parse(input: &str, dates: &DateParser) -> Result<Self> {
+        let mut parser = Parser { tokens: tokenize(input)?, position: 0, dates };
+        let filter = parser.or()?;
+        if parser.position < parser.tokens.len() {
+            bail!("unexpected ')'");
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, task: &Task, env: &Env) -> bool {
+        match self {
+            Filter::And(a, b) => a.matches(task, env) && b.matches(task, env),
+            Filter::Or(a, b) => a.matches(task, env) || b.matches(task, env),
+            Filter::Not(filter) => !filter.matches(task, env),
+            Filter::Is(condition) => condition.matches(task, env),
+        }
+    }
+}
+
+impl Condition {
+    fn matches(&self, task: &Task, env: &Env) -> bool {
+        match self {
+            Condition::Status(status) => task.status == *status,
+            Condition::Priority(op, priority) => op.holds(task.priority, *priority),
+            Condition::Tag(tag) => task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            Condition::Age(op, days) => DateTime::parse_from_rfc3339(&task.created_at)
+                .is_ok_and(|created| op.holds((env.now - created.with_timezone(&Utc)).num_days(), *days)),
+            Condition::Due(_, None) => task.due.is_none(),
+            Condition::Due(op, Some(word)) => {
+                let day = env.dates.parse_due_word(word, env.today);
+                task.due.zip(day).is_some_and(|(due, day)| op.holds(due, day))
+            }
+            Condition::Assignee(who) => match (who.as_str(), &task.assignee) {
+                ("none", assignee) => assignee.is_none(),
+                ("me", assignee) => assignee.is_some() && assignee.as_deref() == env.me,
+                (email, assignee) => assignee.as_deref().is_some_and(|a| a.eq_ignore_ascii_case(email)),
+            },
+            Condition::Text(text) => task.text.to_lowercase().contains(text.as_str()),
+            Condition::Context(pattern) => glob_match(pattern, env.context_key),
+            Condition::Overdue => task.is_overdue(env.today),
+            Condition::Pinned => task.pinned,
+            Condition::MustFinish => task.must_finish,
+            Condition::Snoozed => task.is_snoozed(env.now),
+        }
+    }
+}
+
+/// A named filter from `[views]` in the config.
+#[derive(Debug, Clone)]
+pub struct View {
+    pub name: String,
+    pub expression: String,
+    /// Why the expression doesn't parse, shown in the view picker.
+    pub filter: Result<Filter, String>,
+}
+
+impl View {
+    pub fn new(name: &str, expression: &str, dates: &DateParser) -> Self {
+        Self {
+            name: name.to_string(),
+            expression: expression.to_string(),
+            filter: Filter::parse(expression, dates).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_expressions() {
+        let dates = DateParser::default();
+        let today = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let now = today.and_hms_opt(12, 0, 0).unwrap().and_utc();
+        let env = Env { today, now, me: Some("ana@acme.dev"), dates: &dates, context_key: "acme:web:main" };
+
+        let mut urgent = Task::new(1, "Fix login".to_string());
+        urgent.priority = Priority::High;
+        urgent.due = Some(today);
+        urgent.assignee = Some("ana@acme.dev".to_string());
+        let mut stale = Task::new(2, "Refactor storage".to_string());
+        stale.status = TaskStatus::InProgress;
+        stale.created_at = (now - chrono::Duration::days(10)).to_rfc3339();
+        stale.tags = vec!["backend".to_string()];
+        let mut done = Task::new(3, "Write docs".to_string());
+        done.status = TaskStatus::Completed;
+        done.priority = Priority::High;
+        done.created_at = now.to_rfc3339();
+        let tasks = [urgent, stale, done];
+
+        let ids = |expression: &str| {
+            let filter = Filter::parse(expression, &dates).unwrap();
+            tasks.iter().filter(|t| filter.matches(t, &env)).map(|t| t.id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids("priority:high AND status!=completed"), vec![1]);
+        assert_eq!(ids("in_progress and age>7d"), vec![2]);
+        assert_eq!(ids("priority>=medium or tag:#backend"), vec![1, 2, 3]);
+        assert_eq!(ids("not (open and due:none)"), vec![1, 3]);
+        assert_eq!(ids("due<=tomorrow assignee:me"), vec![1]);
+        assert_eq!(ids("text:docs or age<1w done"), vec![3]);
+        assert_eq!(ids(r#"context:"acme:*:main" and text:"fix login""#), vec![1]);
+        assert_eq!(ids(r#"context!="acme:*""#), Vec::<usize>::new());
+
+        for (expression, error) in [
+            ("priority:urgent", "unknown priority 'urgent'"),
+            ("(open", "missing ')'"),
+            ("tag>x", "'tag' can only be compared"),
+            ("colour:red", "unknown field 'colour'"),
+            ("due<someday", "can't tell when 'someday' is"),
+        ] {
+            let message = Filter::parse(expression, &dates).unwrap_err().to_string();
+            assert!(message.starts_with(error), "{}: {}", expression, message);
+        }
+    }
+}